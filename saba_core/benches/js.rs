@@ -0,0 +1,103 @@
+//! 構文木の評価とバイトコードの実行の速さを比べる
+//! `cargo bench`で実行する
+
+#![feature(test)]
+
+extern crate test;
+
+use saba_core::renderer::dom::node::Node;
+use saba_core::renderer::dom::node::NodeKind;
+use saba_core::renderer::js::ast::JsParser;
+use saba_core::renderer::js::runtime::Engine;
+use saba_core::renderer::js::runtime::JsRuntime;
+use saba_core::renderer::js::token::JsLexer;
+use std::cell::RefCell;
+use std::rc::Rc;
+use test::Bencher;
+
+const FIB: &str = "function fib(n) { return n < 2 ? n : fib(n - 1) + fib(n - 2); } fib(18)";
+
+const LOOPS: &str = r#"
+var sum = 0;
+for (let i = 0; i < 3000; i++) {
+  for (var j = 0; j < 10; j++) {
+    if ((i + j) % 3 === 0) continue;
+    sum += i * j;
+  }
+}
+sum
+"#;
+
+const CLOSURES: &str = r#"
+function counter() {
+  let n = 0;
+  return { add: (x) => { n += x; return n; }, get: () => n };
+}
+var c = counter();
+var values = [];
+for (var i = 0; i < 2000; i++) {
+  values.push(c.add(i % 7));
+}
+values.map((v) => v * 2).filter((v) => v % 3 === 0).length + c.get()
+"#;
+
+/// 配列の要素を追加する速さは、要素の数によらない
+const PUSH: &str = r#"
+var a = [];
+for (var i = 0; i < 16000; i++) {
+  a.push(i);
+}
+a.length
+"#;
+
+fn bench_script(b: &mut Bencher, source: &str, engine: Engine) {
+    let program = JsParser::new(JsLexer::new(source.to_string()))
+        .parse_ast()
+        .expect("failed to parse");
+    b.iter(|| {
+        let dom = Rc::new(RefCell::new(Node::new(NodeKind::Document)));
+        let mut runtime = JsRuntime::new(dom);
+        runtime.set_engine(engine);
+        runtime.evaluate(&program).expect("script failed")
+    });
+}
+
+#[bench]
+fn fib_tree_walker(b: &mut Bencher) {
+    bench_script(b, FIB, Engine::TreeWalker);
+}
+
+#[bench]
+fn fib_bytecode(b: &mut Bencher) {
+    bench_script(b, FIB, Engine::Bytecode);
+}
+
+#[bench]
+fn loops_tree_walker(b: &mut Bencher) {
+    bench_script(b, LOOPS, Engine::TreeWalker);
+}
+
+#[bench]
+fn loops_bytecode(b: &mut Bencher) {
+    bench_script(b, LOOPS, Engine::Bytecode);
+}
+
+#[bench]
+fn closures_tree_walker(b: &mut Bencher) {
+    bench_script(b, CLOSURES, Engine::TreeWalker);
+}
+
+#[bench]
+fn closures_bytecode(b: &mut Bencher) {
+    bench_script(b, CLOSURES, Engine::Bytecode);
+}
+
+#[bench]
+fn push_tree_walker(b: &mut Bencher) {
+    bench_script(b, PUSH, Engine::TreeWalker);
+}
+
+#[bench]
+fn push_bytecode(b: &mut Bencher) {
+    bench_script(b, PUSH, Engine::Bytecode);
+}
@@ -1,3 +1,4 @@
+use crate::renderer::layout::viewport::Viewport;
 use crate::renderer::page::Page;
 use alloc::rc::Rc;
 use alloc::vec::Vec;
@@ -10,8 +11,9 @@ pub struct Browser {
 }
 
 impl Browser {
-    pub fn new() -> Rc<RefCell<Self>> {
-        let mut page = Page::new();
+    /// ページは`viewport`の大きさで表示する
+    pub fn new(viewport: Viewport) -> Rc<RefCell<Self>> {
+        let mut page = Page::new(viewport);
 
         let browser = Rc::new(RefCell::new(Self {
             active_page_index: 0,
@@ -27,4 +29,11 @@ impl Browser {
     pub fn current_page(&self) -> Rc<RefCell<Page>> {
         self.pages[self.active_page_index].clone()
     }
+
+    /// ウィンドウの大きさが変わったときに、すべてのページをレイアウトし直す
+    pub fn set_viewport(&self, viewport: Viewport) {
+        for page in &self.pages {
+            page.borrow_mut().set_viewport(viewport);
+        }
+    }
 }
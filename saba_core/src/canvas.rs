@@ -0,0 +1,632 @@
+//! ディスプレイアイテムをピクセルの配列に描画するソフトウェアのバックエンド
+//! 色は0xRRGGBBの形式で保持し、半透明の色は重ねて描画する
+
+use crate::display_item::DisplayItem;
+use crate::error::Error;
+use crate::font::metrics::FontMetrics;
+use crate::font::raster::rasterize_glyph;
+use crate::font::ttf::Font;
+use crate::font::unicode::grapheme_clusters;
+use crate::image::Image;
+use crate::renderer::layout::computed_style::Color;
+use crate::renderer::layout::computed_style::ComputedStyle;
+use crate::renderer::layout::computed_style::FontSize;
+use crate::renderer::layout::computed_style::FontStyle;
+use crate::renderer::layout::computed_style::FontWeight;
+use crate::renderer::layout::computed_style::TextDecoration;
+use crate::renderer::layout::viewport::Viewport;
+use alloc::rc::Rc;
+use alloc::string::String;
+use alloc::string::ToString;
+use alloc::vec;
+use alloc::vec::Vec;
+
+/// `alpha`(0から255)の割合で`src`を`dst`に重ねる
+fn blend(dst: u32, src: u32, alpha: u8) -> u32 {
+    let a = alpha as u32;
+    let mix = |shift: u32| {
+        let d = (dst >> shift) & 0xff;
+        let s = (src >> shift) & 0xff;
+        ((s * a + d * (255 - a) + 127) / 255) << shift
+    };
+    mix(16) | mix(8) | mix(0)
+}
+
+/// 斜体のフォントがない場合に、文字を傾ける割合(約12度)
+const OBLIQUE_SKEW: f32 = 0.2;
+
+/// グリフを並べて描画するときの設定
+struct GlyphRun<'a> {
+    font: &'a Font,
+    color: u32,
+    /// 色の不透明度
+    alpha: u8,
+    font_size: i64,
+    /// 1ピクセル右にずらして重ねることで太字に見せる
+    synthetic_bold: bool,
+    /// ベースラインからの高さに応じて右にずらすことで斜体に見せる
+    synthetic_oblique: bool,
+}
+
+#[derive(Debug, Clone)]
+pub struct Canvas {
+    width: i64,
+    height: i64,
+    pixels: Vec<u32>,
+    /// ファミリー名と、そのフォント
+    fonts: Vec<(String, Rc<Font>)>,
+    /// 描画できる範囲の左上と右下。入れ子のクリップは重なる部分を積む
+    clips: Vec<(i64, i64, i64, i64)>,
+    /// ディスプレイアイテムの座標(CSSピクセル)をピクセルに変換する
+    viewport: Viewport,
+}
+
+impl Canvas {
+    /// 白で塗りつぶしたキャンバスを作る
+    pub fn new(width: i64, height: i64) -> Self {
+        let width = core::cmp::max(width, 0);
+        let height = core::cmp::max(height, 0);
+        Self {
+            width,
+            height,
+            pixels: vec![0xffffff; (width * height) as usize],
+            fonts: Vec::new(),
+            clips: Vec::new(),
+            viewport: Viewport::new(width, height),
+        }
+    }
+
+    /// ビューポートをデバイスピクセル比で拡大した大きさのキャンバスを作る
+    /// `paint`で描画するディスプレイアイテムも、同じ比で拡大する
+    pub fn for_viewport(viewport: Viewport) -> Self {
+        let mut canvas = Self::new(viewport.device_width(), viewport.device_height());
+        canvas.viewport = viewport;
+        canvas
+    }
+
+    /// テキストの描画に使うフォントを追加する
+    /// 最初に追加したフォントは、`font-family`に一致するものがない場合にも使う
+    pub fn add_font(&mut self, font: Rc<Font>) {
+        self.fonts.push((font.family(), font));
+    }
+
+    /// `families`の先頭から順に探し、見つからない場合は最初に追加したフォントを返す
+    fn select_font(&self, families: &[String]) -> Result<Rc<Font>, Error> {
+        families
+            .iter()
+            .find_map(|family| {
+                self.fonts
+                    .iter()
+                    .find(|(name, _)| name.eq_ignore_ascii_case(family))
+            })
+            .or_else(|| self.fonts.first())
+            .map(|(_, font)| font.clone())
+            .ok_or(Error::Other("font is not loaded".to_string()))
+    }
+
+    pub fn width(&self) -> i64 {
+        self.width
+    }
+
+    pub fn height(&self) -> i64 {
+        self.height
+    }
+
+    pub fn pixels(&self) -> &[u32] {
+        &self.pixels
+    }
+
+    /// (x, y)のピクセルの色
+    /// キャンバスの外側の場合はNoneを返す
+    pub fn pixel(&self, x: i64, y: i64) -> Option<u32> {
+        if x < 0 || y < 0 || x >= self.width || y >= self.height {
+            return None;
+        }
+        Some(self.pixels[(y * self.width + x) as usize])
+    }
+
+    /// 描画できる範囲の左上と右下
+    fn clip(&self) -> (i64, i64, i64, i64) {
+        self.clips
+            .last()
+            .copied()
+            .unwrap_or((0, 0, self.width, self.height))
+    }
+
+    /// 以降の描画を、今の描画範囲と矩形が重なる部分に限る
+    pub fn push_clip(&mut self, x: i64, y: i64, width: i64, height: i64) {
+        let (x0, y0, x1, y1) = self.clip();
+        let x0 = core::cmp::max(x0, x);
+        let y0 = core::cmp::max(y0, y);
+        let x1 = core::cmp::max(core::cmp::min(x1, x + width), x0);
+        let y1 = core::cmp::max(core::cmp::min(y1, y + height), y0);
+        self.clips.push((x0, y0, x1, y1));
+    }
+
+    /// 最後に`push_clip`した矩形による制限を解除する
+    pub fn pop_clip(&mut self) {
+        self.clips.pop();
+    }
+
+    /// 不透明度`alpha`で色を重ねる
+    /// 描画範囲の外側は無視する
+    pub fn blend_pixel(&mut self, x: i64, y: i64, color: u32, alpha: u8) {
+        let (x0, y0, x1, y1) = self.clip();
+        if x < x0 || y < y0 || x >= x1 || y >= y1 || alpha == 0 {
+            return;
+        }
+        let index = (y * self.width + x) as usize;
+        self.pixels[index] = blend(self.pixels[index], color, alpha);
+    }
+
+    pub fn fill_rect(&mut self, color: u32, x: i64, y: i64, width: i64, height: i64) {
+        let (clip_x0, clip_y0, clip_x1, clip_y1) = self.clip();
+        let x0 = core::cmp::max(x, clip_x0);
+        let y0 = core::cmp::max(y, clip_y0);
+        let x1 = core::cmp::min(x + width, clip_x1);
+        let y1 = core::cmp::min(y + height, clip_y1);
+        for py in y0..y1 {
+            for px in x0..x1 {
+                self.pixels[(py * self.width + px) as usize] = color;
+            }
+        }
+    }
+
+    /// 文字の上端(ベースラインからascentだけ上)を`y`として、`font_size`ピクセルのテキストを描画する
+    pub fn draw_string(
+        &mut self,
+        color: u32,
+        x: i64,
+        y: i64,
+        text: &str,
+        font_size: i64,
+        underline: bool,
+    ) -> Result<(), Error> {
+        let font = self.select_font(&[])?;
+        let run = GlyphRun {
+            font: &font,
+            color,
+            alpha: 255,
+            font_size,
+            synthetic_bold: false,
+            synthetic_oblique: false,
+        };
+        self.draw_glyphs(&run, x, y, text, underline)
+    }
+
+    /// `style`のフォントと色でテキストを描画する
+    /// 太字や斜体のフォントがない場合は、通常のフォントを加工して描画する
+    pub fn draw_text(
+        &mut self,
+        x: i64,
+        y: i64,
+        text: &str,
+        style: &ComputedStyle,
+    ) -> Result<(), Error> {
+        let font = self.select_font(&style.font_family())?;
+        let run = GlyphRun {
+            font: &font,
+            color: style.color().code_u32(),
+            alpha: style.color().alpha(),
+            font_size: style.font_size().to_px(),
+            synthetic_bold: style.font_weight() == FontWeight::Bold,
+            synthetic_oblique: style.font_style() != FontStyle::Normal,
+        };
+        self.draw_glyphs(
+            &run,
+            x,
+            y,
+            text,
+            style.text_decoration() == TextDecoration::Underline,
+        )
+    }
+
+    fn draw_glyphs(
+        &mut self,
+        run: &GlyphRun,
+        x: i64,
+        y: i64,
+        text: &str,
+        underline: bool,
+    ) -> Result<(), Error> {
+        let font = run.font;
+        let baseline = y + font.ascent(run.font_size);
+        let scale = font.scale(run.font_size);
+        // 丸め誤差がたまらないように、ペンの位置はフォント単位で進める
+        let mut pen: i64 = 0;
+        let mut previous: Option<u16> = None;
+
+        for cluster in grapheme_clusters(text) {
+            let mut chars = cluster.chars();
+            let base = match chars.next() {
+                Some(c) => c,
+                None => continue,
+            };
+            let glyph = font.glyph_index(base);
+            if let Some(p) = previous {
+                pen += font.kerning_units(p, glyph) as i64;
+            }
+            let pen_x = x + (pen as f32 * scale) as i64;
+
+            // 結合文字は基底文字と同じ位置に重ねる
+            for g in core::iter::once(glyph).chain(chars.map(|c| font.glyph_index(c))) {
+                let bitmap = rasterize_glyph(font, g, run.font_size)?;
+                for by in 0..bitmap.height() {
+                    let py = bitmap.top() + by as i64;
+                    let skew = if run.synthetic_oblique {
+                        (-py as f32 * OBLIQUE_SKEW) as i64
+                    } else {
+                        0
+                    };
+                    for bx in 0..bitmap.width() {
+                        let px = pen_x + bitmap.left() + bx as i64 + skew;
+                        let alpha = (bitmap.alpha(bx, by) as u32 * run.alpha as u32 / 255) as u8;
+                        self.blend_pixel(px, baseline + py, run.color, alpha);
+                        if run.synthetic_bold {
+                            self.blend_pixel(px + 1, baseline + py, run.color, alpha);
+                        }
+                    }
+                }
+            }
+
+            pen += font.advance_width_units(glyph) as i64;
+            previous = Some(glyph);
+        }
+
+        if underline {
+            let width = (pen as f32 * scale) as i64;
+            for px in x..x + width {
+                self.blend_pixel(px, baseline + 1, run.color, run.alpha);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// 不透明度を持つ`color`で矩形を塗る
+    pub fn blend_rect(&mut self, color: Color, x: i64, y: i64, width: i64, height: i64) {
+        if color.alpha() == 255 {
+            self.fill_rect(color.code_u32(), x, y, width, height);
+            return;
+        }
+        for py in y..y + height {
+            for px in x..x + width {
+                self.blend_pixel(px, py, color.code_u32(), color.alpha());
+            }
+        }
+    }
+
+    /// 画像を(x, y)から`width`x`height`の大きさに拡大縮小して描画する
+    /// 拡大縮小は最も近いピクセルの色を使い、半透明のピクセルは重ねて描画する
+    pub fn draw_image(&mut self, image: &Image, x: i64, y: i64, width: i64, height: i64) {
+        if width <= 0 || height <= 0 {
+            return;
+        }
+        // 描画範囲の内側だけを計算する
+        let (clip_x0, clip_y0, clip_x1, clip_y1) = self.clip();
+        let x0 = core::cmp::max(x, clip_x0);
+        let y0 = core::cmp::max(y, clip_y0);
+        let x1 = core::cmp::min(x + width, clip_x1);
+        let y1 = core::cmp::min(y + height, clip_y1);
+        for py in y0..y1 {
+            let sy = (py - y) * image.height() / height;
+            for px in x0..x1 {
+                let sx = (px - x) * image.width() / width;
+                let color = image.pixel(sx, sy);
+                self.blend_pixel(px, py, color.code_u32(), color.alpha());
+            }
+        }
+    }
+
+    /// ディスプレイアイテムを順番に描画する
+    /// 座標と大きさはデバイスピクセル比を掛けてから描画する
+    pub fn paint(&mut self, display_items: &[DisplayItem]) -> Result<(), Error> {
+        let viewport = self.viewport;
+        let d = |v: i64| viewport.to_device_pixels(v);
+        // 隣り合う矩形の間に隙間ができないように、左上と右下をそれぞれ変換する
+        let rect = |x: i64, y: i64, width: i64, height: i64| {
+            (d(x), d(y), d(x + width) - d(x), d(y + height) - d(y))
+        };
+
+        for item in display_items {
+            match item {
+                DisplayItem::Rect {
+                    style,
+                    layout_point,
+                    layout_size,
+                } => {
+                    let (x, y, width, height) = rect(
+                        layout_point.x(),
+                        layout_point.y(),
+                        layout_size.width(),
+                        layout_size.height(),
+                    );
+                    self.blend_rect(style.background_color(), x, y, width, height)
+                }
+                DisplayItem::Text {
+                    text,
+                    style,
+                    layout_point,
+                } => {
+                    let mut style = style.clone();
+                    style.set_font_size(FontSize::Px(d(style.font_size().to_px())));
+                    self.draw_text(d(layout_point.x()), d(layout_point.y()), text, &style)?
+                }
+                DisplayItem::Bullet {
+                    shape,
+                    style,
+                    layout_point,
+                    layout_size,
+                } => {
+                    for (x, y, width) in shape.spans(d(layout_size.width())) {
+                        self.blend_rect(
+                            style.color(),
+                            d(layout_point.x()) + x,
+                            d(layout_point.y()) + y,
+                            width,
+                            1,
+                        );
+                    }
+                }
+                DisplayItem::Image {
+                    image,
+                    layout_point,
+                    layout_size,
+                } => {
+                    let (x, y, width, height) = rect(
+                        layout_point.x(),
+                        layout_point.y(),
+                        layout_size.width(),
+                        layout_size.height(),
+                    );
+                    self.draw_image(image, x, y, width, height)
+                }
+                DisplayItem::PushClip {
+                    layout_point,
+                    layout_size,
+                } => {
+                    let (x, y, width, height) = rect(
+                        layout_point.x(),
+                        layout_point.y(),
+                        layout_size.width(),
+                        layout_size.height(),
+                    );
+                    self.push_clip(x, y, width, height)
+                }
+                DisplayItem::PopClip => self.pop_clip(),
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::font::ttf::tests::build_test_font;
+    use crate::renderer::layout::layout_object::LayoutPoint;
+    use crate::renderer::layout::layout_object::LayoutSize;
+    use crate::renderer::layout::list_marker::BulletShape;
+
+    #[test]
+    fn test_blend() {
+        assert_eq!(0xff0000, blend(0x0000ff, 0xff0000, 255));
+        assert_eq!(0x0000ff, blend(0x0000ff, 0xff0000, 0));
+        assert_eq!(0x80007f, blend(0x0000ff, 0xff0000, 128));
+    }
+
+    #[test]
+    fn test_fill_rect_is_clipped() {
+        let mut canvas = Canvas::new(4, 4);
+        canvas.fill_rect(0x000000, 2, 2, 10, 10);
+        assert_eq!(Some(0xffffff), canvas.pixel(1, 1));
+        assert_eq!(Some(0x000000), canvas.pixel(3, 3));
+        assert_eq!(None, canvas.pixel(4, 4));
+    }
+
+    #[test]
+    fn test_draw_string_without_font() {
+        let mut canvas = Canvas::new(10, 10);
+        assert!(canvas.draw_string(0, 0, 0, "A", 16, false).is_err());
+    }
+
+    #[test]
+    fn test_draw_string() {
+        let mut canvas = Canvas::new(20, 12);
+        canvas.add_font(Rc::new(
+            Font::parse(build_test_font()).expect("failed to parse font"),
+        ));
+        // 10pxではascentが8pxなので、高さ7pxの"A"は上から1pxの位置から描かれる
+        canvas
+            .draw_string(0x000000, 0, 0, "AA", 10, false)
+            .expect("failed to draw");
+
+        assert_eq!(Some(0xffffff), canvas.pixel(0, 0));
+        assert_eq!(Some(0x000000), canvas.pixel(0, 1));
+        assert_eq!(Some(0x000000), canvas.pixel(5, 7));
+        assert_eq!(Some(0xffffff), canvas.pixel(6, 7));
+        // 2文字目は送り幅の7px右から描かれる
+        assert_eq!(Some(0x000000), canvas.pixel(7, 1));
+        assert_eq!(Some(0xffffff), canvas.pixel(13, 1));
+    }
+
+    fn text_style(font_weight: FontWeight, font_style: FontStyle) -> ComputedStyle {
+        let mut style = ComputedStyle::new();
+        style.set_color(Color::from_name("black").expect("black should be valid"));
+        style.set_font_size(FontSize::Px(10));
+        style.set_text_decoration(TextDecoration::None);
+        style.set_font_family(vec!["Test Sans".to_string()]);
+        style.set_font_weight(font_weight);
+        style.set_font_style(font_style);
+        style
+    }
+
+    #[test]
+    fn test_synthetic_bold() {
+        let mut canvas = Canvas::new(20, 12);
+        canvas.add_font(Rc::new(
+            Font::parse(build_test_font()).expect("failed to parse font"),
+        ));
+        canvas
+            .draw_text(0, 0, "A", &text_style(FontWeight::Bold, FontStyle::Normal))
+            .expect("failed to draw");
+
+        // 太字は1px太くなる
+        assert_eq!(Some(0x000000), canvas.pixel(6, 4));
+        assert_eq!(Some(0xffffff), canvas.pixel(7, 4));
+    }
+
+    #[test]
+    fn test_synthetic_oblique() {
+        let mut canvas = Canvas::new(20, 12);
+        canvas.add_font(Rc::new(
+            Font::parse(build_test_font()).expect("failed to parse font"),
+        ));
+        canvas
+            .draw_text(
+                0,
+                0,
+                "A",
+                &text_style(FontWeight::Normal, FontStyle::Italic),
+            )
+            .expect("failed to draw");
+
+        // ベースラインから離れるほど右にずれる
+        assert_eq!(Some(0xffffff), canvas.pixel(0, 1));
+        assert_eq!(Some(0x000000), canvas.pixel(1, 1));
+        assert_eq!(Some(0x000000), canvas.pixel(0, 7));
+    }
+
+    #[test]
+    fn test_blend_translucent_rect() {
+        let mut canvas = Canvas::new(4, 4);
+        canvas.fill_rect(0x0000ff, 0, 0, 4, 4);
+        canvas.blend_rect(Color::new(255, 0, 0, 128), 1, 1, 2, 2);
+        assert_eq!(Some(0x0000ff), canvas.pixel(0, 0));
+        assert_eq!(Some(0x80007f), canvas.pixel(1, 1));
+    }
+
+    #[test]
+    fn test_paint_display_items() {
+        let mut style = ComputedStyle::new();
+        style.set_background_color(Color::from_name("red").expect("red should be valid"));
+        let items = vec![DisplayItem::Rect {
+            style,
+            layout_point: LayoutPoint::new(1, 1),
+            layout_size: LayoutSize::new(2, 2),
+        }];
+
+        let mut canvas = Canvas::new(4, 4);
+        canvas.paint(&items).expect("failed to paint");
+        assert_eq!(Some(0xffffff), canvas.pixel(0, 0));
+        assert_eq!(Some(0xff0000), canvas.pixel(1, 1));
+        assert_eq!(Some(0xff0000), canvas.pixel(2, 2));
+    }
+
+    #[test]
+    fn test_paint_clip() {
+        let mut style = ComputedStyle::new();
+        style.set_background_color(Color::from_name("red").expect("red should be valid"));
+        let rect = DisplayItem::Rect {
+            style,
+            layout_point: LayoutPoint::new(0, 0),
+            layout_size: LayoutSize::new(8, 8),
+        };
+        let items = vec![
+            DisplayItem::PushClip {
+                layout_point: LayoutPoint::new(1, 1),
+                layout_size: LayoutSize::new(4, 4),
+            },
+            DisplayItem::PushClip {
+                layout_point: LayoutPoint::new(3, 3),
+                layout_size: LayoutSize::new(4, 4),
+            },
+            rect.clone(),
+            DisplayItem::PopClip,
+            DisplayItem::PopClip,
+        ];
+
+        let mut canvas = Canvas::new(8, 8);
+        canvas.paint(&items).expect("failed to paint");
+        // 2つの矩形が重なる(3, 3)から(5, 5)の範囲だけが塗られる
+        assert_eq!(Some(0xffffff), canvas.pixel(2, 2));
+        assert_eq!(Some(0xff0000), canvas.pixel(3, 3));
+        assert_eq!(Some(0xff0000), canvas.pixel(4, 4));
+        assert_eq!(Some(0xffffff), canvas.pixel(5, 5));
+
+        // クリップを解除した後は全体に描画できる
+        canvas.paint(&[rect]).expect("failed to paint");
+        assert_eq!(Some(0xff0000), canvas.pixel(7, 7));
+    }
+
+    #[test]
+    fn test_paint_bullet() {
+        let mut style = ComputedStyle::new();
+        style.set_color(Color::black());
+        let items = vec![DisplayItem::Bullet {
+            shape: BulletShape::Disc,
+            style,
+            layout_point: LayoutPoint::new(1, 1),
+            layout_size: LayoutSize::new(5, 5),
+        }];
+
+        let mut canvas = Canvas::new(8, 8);
+        canvas.paint(&items).expect("failed to paint");
+        assert_eq!(Some(0x000000), canvas.pixel(3, 3));
+        // 円の外側にあたる角は塗らない
+        assert_eq!(Some(0xffffff), canvas.pixel(1, 1));
+        assert_eq!(Some(0x000000), canvas.pixel(2, 1));
+    }
+
+    #[test]
+    fn test_paint_image() {
+        // 左は赤、右は半透明の青の2x1の画像を、4x2に拡大する
+        let mut image = Image::new(2, 1).expect("failed to create image");
+        image.set_pixel(0, 0, Color::new(255, 0, 0, 255));
+        image.set_pixel(1, 0, Color::new(0, 0, 255, 128));
+        let items = vec![
+            DisplayItem::PushClip {
+                layout_point: LayoutPoint::new(0, 0),
+                layout_size: LayoutSize::new(4, 2),
+            },
+            DisplayItem::Image {
+                image: Rc::new(image),
+                layout_point: LayoutPoint::new(1, 1),
+                layout_size: LayoutSize::new(4, 2),
+            },
+            DisplayItem::PopClip,
+        ];
+
+        let mut canvas = Canvas::new(8, 8);
+        canvas.paint(&items).expect("failed to paint");
+        assert_eq!(Some(0xffffff), canvas.pixel(0, 0));
+        assert_eq!(Some(0xff0000), canvas.pixel(1, 1));
+        assert_eq!(Some(0xff0000), canvas.pixel(2, 1));
+        assert_eq!(Some(0x7f7fff), canvas.pixel(3, 1));
+        // クリップの外側は描画しない
+        assert_eq!(Some(0xffffff), canvas.pixel(3, 2));
+        assert_eq!(Some(0xffffff), canvas.pixel(4, 1));
+    }
+
+    #[test]
+    fn test_paint_with_device_pixel_ratio() {
+        let mut style = ComputedStyle::new();
+        style.set_background_color(Color::from_name("red").expect("red should be valid"));
+        let items = vec![DisplayItem::Rect {
+            style,
+            layout_point: LayoutPoint::new(1, 1),
+            layout_size: LayoutSize::new(2, 2),
+        }];
+
+        let mut canvas = Canvas::for_viewport(Viewport::new(4, 3).with_device_pixel_ratio(2.0));
+        assert_eq!(8, canvas.width());
+        assert_eq!(6, canvas.height());
+        canvas.paint(&items).expect("failed to paint");
+        // (1, 1)から2x2の矩形は、(2, 2)から4x4のピクセルになる
+        assert_eq!(Some(0xffffff), canvas.pixel(1, 1));
+        assert_eq!(Some(0xff0000), canvas.pixel(2, 2));
+        assert_eq!(Some(0xff0000), canvas.pixel(5, 5));
+        assert_eq!(Some(0xffffff), canvas.pixel(6, 5));
+    }
+}
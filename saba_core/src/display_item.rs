@@ -1,6 +1,9 @@
+use crate::image::Image;
 use crate::renderer::layout::computed_style::ComputedStyle;
 use crate::renderer::layout::layout_object::LayoutPoint;
 use crate::renderer::layout::layout_object::LayoutSize;
+use crate::renderer::layout::list_marker::BulletShape;
+use alloc::rc::Rc;
 use alloc::string::String;
 
 #[derive(Debug, Clone, PartialEq)]
@@ -15,4 +18,40 @@ pub enum DisplayItem {
         style: ComputedStyle,
         layout_point: LayoutPoint,
     },
+    /// リストの記号。`style`の文字色で塗る
+    Bullet {
+        shape: BulletShape,
+        style: ComputedStyle,
+        layout_point: LayoutPoint,
+        layout_size: LayoutSize,
+    },
+    /// 画像。`layout_size`の大きさに拡大縮小して描画する
+    Image {
+        image: Rc<Image>,
+        layout_point: LayoutPoint,
+        layout_size: LayoutSize,
+    },
+    /// 以降のアイテムを、`PopClip`までこの矩形の内側だけに描画する
+    /// 入れ子になった場合は、すべての矩形が重なる部分に描画する
+    PushClip {
+        layout_point: LayoutPoint,
+        layout_size: LayoutSize,
+    },
+    PopClip,
+}
+
+impl DisplayItem {
+    /// アイテムの位置をずらす
+    pub fn translate(&mut self, dx: i64, dy: i64) {
+        match self {
+            DisplayItem::Rect { layout_point, .. }
+            | DisplayItem::Text { layout_point, .. }
+            | DisplayItem::Bullet { layout_point, .. }
+            | DisplayItem::Image { layout_point, .. }
+            | DisplayItem::PushClip { layout_point, .. } => {
+                *layout_point = LayoutPoint::new(layout_point.x() + dx, layout_point.y() + dy);
+            }
+            DisplayItem::PopClip => {}
+        }
+    }
 }
@@ -0,0 +1,72 @@
+use crate::font::metrics::BitmapFontMetrics;
+use crate::font::metrics::FontMetrics;
+use alloc::rc::Rc;
+use alloc::string::String;
+use alloc::vec::Vec;
+
+/// ファミリー名で選べるフォントの一覧
+/// どのファミリーも見つからない場合は既定のフォントを使う
+#[derive(Debug, Clone)]
+pub struct FontCollection {
+    default: Rc<dyn FontMetrics>,
+    fonts: Vec<(String, Rc<dyn FontMetrics>)>,
+}
+
+impl FontCollection {
+    pub fn new(default: Rc<dyn FontMetrics>) -> Self {
+        Self {
+            default,
+            fonts: Vec::new(),
+        }
+    }
+
+    /// `family`という名前でフォントを登録する
+    pub fn add(&mut self, family: &str, font: Rc<dyn FontMetrics>) {
+        self.fonts.push((String::from(family), font));
+    }
+
+    /// `font-family`のリストの先頭から順に、登録されているフォントを探す
+    /// ファミリー名は大文字と小文字を区別しない
+    pub fn select(&self, families: &[String]) -> &dyn FontMetrics {
+        for family in families {
+            if let Some((_, font)) = self
+                .fonts
+                .iter()
+                .find(|(name, _)| name.eq_ignore_ascii_case(family))
+            {
+                return font.as_ref();
+            }
+        }
+        self.default.as_ref()
+    }
+}
+
+impl Default for FontCollection {
+    fn default() -> Self {
+        Self::new(Rc::new(BitmapFontMetrics::new()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::font::ttf::tests::build_test_font;
+    use crate::font::ttf::Font;
+    use alloc::string::ToString;
+    use alloc::vec;
+
+    #[test]
+    fn test_select_fallback() {
+        let mut fonts = FontCollection::default();
+        fonts.add(
+            "Test Sans",
+            Rc::new(Font::parse(build_test_font()).expect("failed to parse font")),
+        );
+
+        // テスト用のフォントの"A"は10pxで7px、ビットマップフォントでは5px
+        let families = vec!["Unknown".to_string(), "test sans".to_string()];
+        assert_eq!(7, fonts.select(&families).text_width("A", 10));
+        let families = vec!["Unknown".to_string(), "serif".to_string()];
+        assert_eq!(5, fonts.select(&families).text_width("A", 10));
+    }
+}
@@ -0,0 +1,104 @@
+use crate::constants::CHAR_HEIGHT;
+use crate::constants::CHAR_HEIGHT_WITH_PADDING;
+use crate::constants::CHAR_WIDTH;
+use crate::font::unicode::grapheme_clusters;
+use crate::font::unicode::is_wide;
+use core::fmt::Debug;
+
+/// レイアウトが文字の大きさを知るためのインターフェース
+/// `font_size`はピクセル単位のフォントサイズ(emの大きさ)
+pub trait FontMetrics: Debug {
+    /// 1つの書記素クラスタを描画したあとに、次の文字まで進む幅
+    fn advance_width(&self, cluster: &str, font_size: i64) -> i64;
+
+    /// ベースラインから文字の上端までの高さ
+    fn ascent(&self, font_size: i64) -> i64;
+
+    /// ベースラインから文字の下端までの深さ
+    fn descent(&self, font_size: i64) -> i64;
+
+    /// 行と行の間に追加する余白
+    fn line_gap(&self, _font_size: i64) -> i64 {
+        0
+    }
+
+    /// `line-height: normal`のときの行の高さ
+    fn line_height(&self, font_size: i64) -> i64 {
+        self.ascent(font_size) + self.descent(font_size) + self.line_gap(font_size)
+    }
+
+    /// テキスト全体の幅
+    fn text_width(&self, text: &str, font_size: i64) -> i64 {
+        grapheme_clusters(text)
+            .iter()
+            .map(|cluster| self.advance_width(cluster, font_size))
+            .sum()
+    }
+}
+
+/// noliの等幅ビットマップフォント(8x16ピクセル)の大きさ
+/// 全角文字は2文字分の幅で描画される
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct BitmapFontMetrics;
+
+impl BitmapFontMetrics {
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+impl FontMetrics for BitmapFontMetrics {
+    fn advance_width(&self, cluster: &str, font_size: i64) -> i64 {
+        let c = match cluster.chars().next() {
+            Some(c) => c,
+            None => return 0,
+        };
+        if c.is_control() && c != '\t' {
+            return 0;
+        }
+
+        let width = CHAR_WIDTH * font_size / CHAR_HEIGHT;
+        if is_wide(c) {
+            width * 2
+        } else {
+            width
+        }
+    }
+
+    fn ascent(&self, font_size: i64) -> i64 {
+        font_size
+    }
+
+    fn descent(&self, font_size: i64) -> i64 {
+        (CHAR_HEIGHT_WITH_PADDING - CHAR_HEIGHT) * font_size / CHAR_HEIGHT
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ascii_width() {
+        let metrics = BitmapFontMetrics::new();
+        assert_eq!(40, metrics.text_width("hello", 16));
+        assert_eq!(80, metrics.text_width("hello", 32));
+    }
+
+    #[test]
+    fn test_japanese_width() {
+        // UTF-8では1文字3バイトだが、全角2文字分の幅として数える
+        let metrics = BitmapFontMetrics::new();
+        assert_eq!(48, metrics.text_width("日本語", 16));
+        assert_eq!(16, metrics.text_width("か\u{3099}", 16));
+    }
+
+    #[test]
+    fn test_line_height() {
+        let metrics = BitmapFontMetrics::new();
+        assert_eq!(16, metrics.ascent(16));
+        assert_eq!(4, metrics.descent(16));
+        assert_eq!(20, metrics.line_height(16));
+        assert_eq!(60, metrics.line_height(48));
+    }
+}
@@ -0,0 +1,5 @@
+pub mod collection;
+pub mod metrics;
+pub mod raster;
+pub mod ttf;
+pub mod unicode;
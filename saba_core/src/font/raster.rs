@@ -0,0 +1,359 @@
+//! グリフの輪郭をアンチエイリアスをかけたビットマップに変換する
+//! 各ピクセルの被覆率を、輪郭の線分が通過する面積の符号付きの和として求める
+
+use crate::error::Error;
+use crate::font::ttf::Font;
+use crate::font::ttf::PathSegment;
+use crate::font::ttf::Point;
+use alloc::format;
+use alloc::vec;
+use alloc::vec::Vec;
+
+/// ラスタライズするビットマップのピクセル数の上限
+/// 壊れたフォントや極端な文字の大きさで、巨大な領域を確保しないようにする
+const MAX_BITMAP_PIXELS: usize = 1 << 21;
+
+fn floor(x: f32) -> f32 {
+    let i = x as i64 as f32;
+    if i > x {
+        i - 1.0
+    } else {
+        i
+    }
+}
+
+fn ceil(x: f32) -> f32 {
+    -floor(-x)
+}
+
+fn abs(x: f32) -> f32 {
+    if x < 0.0 {
+        -x
+    } else {
+        x
+    }
+}
+
+/// ニュートン法による平方根
+fn sqrt(x: f32) -> f32 {
+    if x <= 0.0 {
+        return 0.0;
+    }
+    let mut r = if x > 1.0 { x } else { 1.0 };
+    for _ in 0..16 {
+        r = 0.5 * (r + x / r);
+    }
+    r
+}
+
+/// 1ピクセルあたりのアルファ値(0から255)を持つビットマップ
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GlyphBitmap {
+    width: usize,
+    height: usize,
+    /// ペンの位置からビットマップの左端までの距離
+    left: i64,
+    /// ベースラインからビットマップの上端までの距離(上向きが負)
+    top: i64,
+    coverage: Vec<u8>,
+}
+
+impl GlyphBitmap {
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn left(&self) -> i64 {
+        self.left
+    }
+
+    pub fn top(&self) -> i64 {
+        self.top
+    }
+
+    /// (x, y)のピクセルのアルファ値
+    pub fn alpha(&self, x: usize, y: usize) -> u8 {
+        if x >= self.width || y >= self.height {
+            return 0;
+        }
+        self.coverage[y * self.width + x]
+    }
+}
+
+/// 線分の被覆面積を蓄積するラスタライザ
+#[derive(Debug, Clone)]
+pub struct Rasterizer {
+    width: usize,
+    height: usize,
+    accumulation: Vec<f32>,
+}
+
+impl Rasterizer {
+    pub fn new(width: usize, height: usize) -> Result<Self, Error> {
+        match width.checked_mul(height) {
+            Some(pixels) if pixels <= MAX_BITMAP_PIXELS => Ok(Self {
+                width,
+                height,
+                // 右端のピクセルの次の要素にも書き込むので余分に確保する
+                accumulation: vec![0.0; pixels + 4],
+            }),
+            _ => Err(Error::UnexpectedInput(format!(
+                "glyph bitmap is too large: {}x{}",
+                width, height
+            ))),
+        }
+    }
+
+    /// ビットマップの座標(下向きがy軸の正)で線分を描く
+    pub fn draw_line(&mut self, p0: Point, p1: Point) {
+        if p0.y == p1.y {
+            return;
+        }
+        let (direction, p0, p1) = if p0.y < p1.y {
+            (1.0, p0, p1)
+        } else {
+            (-1.0, p1, p0)
+        };
+        let clamp_x = |x: f32| x.max(0.0).min(self.width as f32);
+
+        let dxdy = (p1.x - p0.x) / (p1.y - p0.y);
+        let mut x = p0.x;
+        if p0.y < 0.0 {
+            x -= p0.y * dxdy;
+        }
+
+        let y_start = if p0.y < 0.0 { 0 } else { p0.y as usize };
+        let y_end = core::cmp::min(self.height, ceil(p1.y).max(0.0) as usize);
+        for y in y_start..y_end {
+            let row = y * self.width;
+            let dy = (y as f32 + 1.0).min(p1.y) - (y as f32).max(p0.y);
+            let x_next = x + dxdy * dy;
+            let d = dy * direction;
+
+            let (x0, x1) = if x < x_next {
+                (clamp_x(x), clamp_x(x_next))
+            } else {
+                (clamp_x(x_next), clamp_x(x))
+            };
+            let x0_floor = floor(x0);
+            let x0_index = x0_floor as usize;
+            let x1_ceil = ceil(x1);
+            let x1_index = x1_ceil as usize;
+
+            if x1_index <= x0_index + 1 {
+                // 線分が1ピクセルの中に収まる場合
+                let x_mid = 0.5 * (x0 + x1) - x0_floor;
+                self.accumulation[row + x0_index] += d - d * x_mid;
+                self.accumulation[row + x0_index + 1] += d * x_mid;
+            } else {
+                // 複数のピクセルにまたがる場合は、台形の面積を各ピクセルに分ける
+                let s = 1.0 / (x1 - x0);
+                let x0_fraction = x0 - x0_floor;
+                let a0 = 0.5 * s * (1.0 - x0_fraction) * (1.0 - x0_fraction);
+                let x1_fraction = x1 - x1_ceil + 1.0;
+                let am = 0.5 * s * x1_fraction * x1_fraction;
+
+                self.accumulation[row + x0_index] += d * a0;
+                if x1_index == x0_index + 2 {
+                    self.accumulation[row + x0_index + 1] += d * (1.0 - a0 - am);
+                } else {
+                    let a1 = s * (1.5 - x0_fraction);
+                    self.accumulation[row + x0_index + 1] += d * (a1 - a0);
+                    for xi in x0_index + 2..x1_index - 1 {
+                        self.accumulation[row + xi] += d * s;
+                    }
+                    let a2 = a1 + (x1_index - x0_index - 3) as f32 * s;
+                    self.accumulation[row + x1_index - 1] += d * (1.0 - a2 - am);
+                }
+                self.accumulation[row + x1_index] += d * am;
+            }
+
+            x = x_next;
+        }
+    }
+
+    /// 2次ベジェ曲線を線分に分割して描く
+    pub fn draw_quad(&mut self, p0: Point, p1: Point, p2: Point) {
+        // 曲がり具合に応じて分割数を決める
+        let dx = p0.x - 2.0 * p1.x + p2.x;
+        let dy = p0.y - 2.0 * p1.y + p2.y;
+        let deviation = sqrt(dx * dx + dy * dy);
+        let n = 1 + sqrt(deviation * 3.0) as usize;
+
+        let mut previous = p0;
+        for i in 1..=n {
+            let t = i as f32 / n as f32;
+            let u = 1.0 - t;
+            let p = Point::new(
+                u * u * p0.x + 2.0 * u * t * p1.x + t * t * p2.x,
+                u * u * p0.y + 2.0 * u * t * p1.y + t * t * p2.y,
+            );
+            self.draw_line(previous, p);
+            previous = p;
+        }
+    }
+
+    /// 蓄積した面積を左から順に足し合わせて被覆率にする
+    pub fn coverage(&self) -> Vec<u8> {
+        let mut sum = 0.0;
+        self.accumulation[..self.width * self.height]
+            .iter()
+            .map(|a| {
+                sum += a;
+                let alpha = abs(sum).min(1.0);
+                (alpha * 255.0 + 0.5) as u8
+            })
+            .collect()
+    }
+}
+
+/// グリフを`font_size`ピクセルの大きさでラスタライズする
+pub fn rasterize_glyph(font: &Font, glyph: u16, font_size: i64) -> Result<GlyphBitmap, Error> {
+    let outline = font.outline(glyph)?;
+    if outline.segments.is_empty() {
+        return Ok(GlyphBitmap {
+            width: 0,
+            height: 0,
+            left: 0,
+            top: 0,
+            coverage: Vec::new(),
+        });
+    }
+
+    let scale = font.scale(font_size);
+    // 整数に変換すると飽和して大きさを確かめられないので、先に浮動小数点数のまま調べる
+    let bounds = [
+        outline.x_min * scale,
+        outline.y_min * scale,
+        outline.x_max * scale,
+        outline.y_max * scale,
+    ];
+    let area = (bounds[2] - bounds[0] + 3.0) * (bounds[3] - bounds[1] + 3.0);
+    let limit = MAX_BITMAP_PIXELS as f32;
+    if !(bounds.iter().all(|b| abs(*b) <= limit) && area <= limit) {
+        return Err(Error::UnexpectedInput(format!(
+            "glyph {} is too large at {}px",
+            glyph, font_size
+        )));
+    }
+
+    let left = floor(bounds[0]);
+    let top = floor(-bounds[3]);
+    let width = (ceil(bounds[2]) - left) as usize + 1;
+    let height = (ceil(-bounds[1]) - top) as usize + 1;
+
+    // フォントの座標は上向きがy軸の正なので反転する
+    let to_bitmap = |p: Point| Point::new(p.x * scale - left, -p.y * scale - top);
+    let mut rasterizer = Rasterizer::new(width, height)?;
+    for segment in &outline.segments {
+        match segment {
+            PathSegment::Line(p0, p1) => rasterizer.draw_line(to_bitmap(*p0), to_bitmap(*p1)),
+            PathSegment::Quad(p0, p1, p2) => {
+                rasterizer.draw_quad(to_bitmap(*p0), to_bitmap(*p1), to_bitmap(*p2))
+            }
+        }
+    }
+
+    Ok(GlyphBitmap {
+        width,
+        height,
+        left: left as i64,
+        top: top as i64,
+        coverage: rasterizer.coverage(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::font::ttf::tests::build_test_font;
+
+    #[test]
+    fn test_fill_square() {
+        let mut rasterizer = Rasterizer::new(4, 4).unwrap();
+        let square = [
+            Point::new(1.0, 1.0),
+            Point::new(3.0, 1.0),
+            Point::new(3.0, 3.0),
+            Point::new(1.0, 3.0),
+        ];
+        for i in 0..4 {
+            rasterizer.draw_line(square[i], square[(i + 1) % 4]);
+        }
+
+        assert_eq!(
+            vec![
+                0, 0, 0, 0, //
+                0, 255, 255, 0, //
+                0, 255, 255, 0, //
+                0, 0, 0, 0, //
+            ],
+            rasterizer.coverage()
+        );
+    }
+
+    #[test]
+    fn test_antialiased_edge() {
+        // 右端がピクセルの中央を通る矩形
+        let mut rasterizer = Rasterizer::new(3, 1).unwrap();
+        let rect = [
+            Point::new(0.0, 0.0),
+            Point::new(1.5, 0.0),
+            Point::new(1.5, 1.0),
+            Point::new(0.0, 1.0),
+        ];
+        for i in 0..4 {
+            rasterizer.draw_line(rect[i], rect[(i + 1) % 4]);
+        }
+
+        assert_eq!(vec![255, 128, 0], rasterizer.coverage());
+    }
+
+    #[test]
+    fn test_rasterize_glyph() {
+        let font = Font::parse(build_test_font()).expect("failed to parse font");
+        // 600x700の正方形を10px(拡大率0.01)で描くと6x7ピクセルになる
+        let bitmap = rasterize_glyph(&font, 1, 10).expect("failed to rasterize");
+        assert_eq!(0, bitmap.left());
+        assert_eq!(-7, bitmap.top());
+        assert_eq!(7, bitmap.width());
+        assert_eq!(8, bitmap.height());
+        assert_eq!(255, bitmap.alpha(0, 0));
+        assert_eq!(255, bitmap.alpha(5, 6));
+        assert_eq!(0, bitmap.alpha(6, 6));
+        assert_eq!(0, bitmap.alpha(0, 7));
+    }
+
+    #[test]
+    fn test_rasterize_curve() {
+        let font = Font::parse(build_test_font()).expect("failed to parse font");
+        let bitmap = rasterize_glyph(&font, 2, 100).expect("failed to rasterize");
+        // 曲線の内側は塗られ、外側は塗られない
+        assert_eq!(255, bitmap.alpha(30, 10));
+        assert_eq!(0, bitmap.alpha(5, 60));
+    }
+
+    #[test]
+    fn test_too_large_bitmap() {
+        assert!(Rasterizer::new(usize::MAX, 2).is_err());
+        assert!(Rasterizer::new(4096, 4096).is_err());
+
+        // 確保する前にエラーになる
+        let font = Font::parse(build_test_font()).expect("failed to parse font");
+        assert!(rasterize_glyph(&font, 1, 100_000).is_err());
+        assert!(rasterize_glyph(&font, 1, i64::MAX).is_err());
+        assert!(rasterize_glyph(&font, 1, 1000).is_ok());
+    }
+
+    #[test]
+    fn test_empty_glyph() {
+        let font = Font::parse(build_test_font()).expect("failed to parse font");
+        let bitmap = rasterize_glyph(&font, 0, 16).expect("failed to rasterize");
+        assert_eq!(0, bitmap.width());
+        assert_eq!(0, bitmap.alpha(0, 0));
+    }
+}
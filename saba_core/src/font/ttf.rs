@@ -0,0 +1,1197 @@
+//! TrueType/OpenTypeフォントの読み込み
+//! https://learn.microsoft.com/en-us/typography/opentype/spec/otff
+//!
+//! グリフの形はglyfテーブル(TrueTypeアウトライン)からのみ読み込む
+//! CFFアウトラインを持つOpenTypeフォントは、文字の幅などの情報だけを使える
+
+use crate::error::Error;
+use crate::font::metrics::FontMetrics;
+use crate::font::unicode::grapheme_clusters;
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt;
+
+/// 複合グリフの入れ子の深さの上限
+const MAX_COMPONENT_DEPTH: u32 = 8;
+
+/// headテーブルのunitsPerEmとして仕様で認められている範囲
+const UNITS_PER_EM_RANGE: core::ops::RangeInclusive<u16> = 16..=16384;
+
+fn read_u8(data: &[u8], offset: usize) -> Result<u8, Error> {
+    data.get(offset)
+        .copied()
+        .ok_or_else(|| Error::UnexpectedInput(format!("font data is too short: {}", offset)))
+}
+
+fn read_u16(data: &[u8], offset: usize) -> Result<u16, Error> {
+    Ok(((read_u8(data, offset)? as u16) << 8) | read_u8(data, offset + 1)? as u16)
+}
+
+fn read_i16(data: &[u8], offset: usize) -> Result<i16, Error> {
+    Ok(read_u16(data, offset)? as i16)
+}
+
+fn read_u32(data: &[u8], offset: usize) -> Result<u32, Error> {
+    Ok(((read_u16(data, offset)? as u32) << 16) | read_u16(data, offset + 2)? as u32)
+}
+
+/// 2.14形式の固定小数点数
+fn read_f2dot14(data: &[u8], offset: usize) -> Result<f32, Error> {
+    Ok(read_i16(data, offset)? as f32 / 16384.0)
+}
+
+/// フォント単位の座標
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Point {
+    pub x: f32,
+    pub y: f32,
+}
+
+impl Point {
+    pub fn new(x: f32, y: f32) -> Self {
+        Self { x, y }
+    }
+
+    fn midpoint(&self, other: Point) -> Point {
+        Point::new((self.x + other.x) / 2.0, (self.y + other.y) / 2.0)
+    }
+}
+
+/// グリフの輪郭を構成する線分と2次ベジェ曲線
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PathSegment {
+    Line(Point, Point),
+    /// 始点、制御点、終点
+    Quad(Point, Point, Point),
+}
+
+/// グリフの輪郭とその外接矩形
+#[derive(Debug, Clone, PartialEq)]
+pub struct Outline {
+    pub segments: Vec<PathSegment>,
+    pub x_min: f32,
+    pub y_min: f32,
+    pub x_max: f32,
+    pub y_max: f32,
+}
+
+impl Outline {
+    fn empty() -> Self {
+        Self {
+            segments: Vec::new(),
+            x_min: 0.0,
+            y_min: 0.0,
+            x_max: 0.0,
+            y_max: 0.0,
+        }
+    }
+
+    fn update_bounds(&mut self) {
+        let mut points = self.segments.iter().flat_map(|s| match s {
+            PathSegment::Line(p0, p1) => [*p0, *p1, *p1],
+            PathSegment::Quad(p0, p1, p2) => [*p0, *p1, *p2],
+        });
+        let first = match points.next() {
+            Some(p) => p,
+            None => return,
+        };
+        let (mut x_min, mut y_min, mut x_max, mut y_max) = (first.x, first.y, first.x, first.y);
+        for p in points {
+            x_min = x_min.min(p.x);
+            y_min = y_min.min(p.y);
+            x_max = x_max.max(p.x);
+            y_max = y_max.max(p.y);
+        }
+        self.x_min = x_min;
+        self.y_min = y_min;
+        self.x_max = x_max;
+        self.y_max = y_max;
+    }
+}
+
+/// 2x2の変換行列と平行移動
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Transform {
+    xx: f32,
+    xy: f32,
+    yx: f32,
+    yy: f32,
+    dx: f32,
+    dy: f32,
+}
+
+impl Transform {
+    fn identity() -> Self {
+        Self {
+            xx: 1.0,
+            xy: 0.0,
+            yx: 0.0,
+            yy: 1.0,
+            dx: 0.0,
+            dy: 0.0,
+        }
+    }
+
+    fn apply(&self, p: Point) -> Point {
+        Point::new(
+            self.xx * p.x + self.yx * p.y + self.dx,
+            self.xy * p.x + self.yy * p.y + self.dy,
+        )
+    }
+
+    /// `inner`を適用したあとに`self`を適用する変換
+    fn then(&self, inner: &Transform) -> Transform {
+        Transform {
+            xx: self.xx * inner.xx + self.yx * inner.xy,
+            xy: self.xy * inner.xx + self.yy * inner.xy,
+            yx: self.xx * inner.yx + self.yx * inner.yy,
+            yy: self.xy * inner.yx + self.yy * inner.yy,
+            dx: self.xx * inner.dx + self.yx * inner.dy + self.dx,
+            dy: self.xy * inner.dx + self.yy * inner.dy + self.dy,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Table {
+    offset: usize,
+    length: usize,
+}
+
+/// 読み込んだフォント
+#[derive(Clone)]
+pub struct Font {
+    data: Vec<u8>,
+    family: String,
+    units_per_em: u16,
+    /// すべてのグリフを囲む矩形(xMin, yMin, xMax, yMax)
+    bounding_box: [i16; 4],
+    index_to_loc_format: i16,
+    num_glyphs: u16,
+    ascender: i16,
+    descender: i16,
+    line_gap: i16,
+    number_of_h_metrics: u16,
+    /// 使用するcmapのサブテーブルの位置
+    cmap: Option<usize>,
+    hmtx: Table,
+    loca: Option<Table>,
+    glyf: Option<Table>,
+    /// 水平方向のカーニングを持つkernのサブテーブル(フォーマット0)の位置
+    kern: Option<usize>,
+}
+
+impl fmt::Debug for Font {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Font")
+            .field("family", &self.family)
+            .field("units_per_em", &self.units_per_em)
+            .field("num_glyphs", &self.num_glyphs)
+            .finish()
+    }
+}
+
+impl PartialEq for Font {
+    fn eq(&self, other: &Self) -> bool {
+        self.data == other.data
+    }
+}
+
+impl Font {
+    pub fn parse(data: Vec<u8>) -> Result<Self, Error> {
+        let version = read_u32(&data, 0)?;
+        // 0x00010000とtrueはTrueTypeアウトライン、OTTOはCFFアウトライン
+        if version != 0x0001_0000 && version != 0x7472_7565 && version != 0x4f54_544f {
+            return Err(Error::UnexpectedInput(format!(
+                "unsupported font format: {:#x}",
+                version
+            )));
+        }
+
+        let num_tables = read_u16(&data, 4)? as usize;
+        let mut tables = Vec::new();
+        for i in 0..num_tables {
+            let record = 12 + 16 * i;
+            let tag = [
+                read_u8(&data, record)?,
+                read_u8(&data, record + 1)?,
+                read_u8(&data, record + 2)?,
+                read_u8(&data, record + 3)?,
+            ];
+            let table = Table {
+                offset: read_u32(&data, record + 8)? as usize,
+                length: read_u32(&data, record + 12)? as usize,
+            };
+            if table.offset + table.length > data.len() {
+                return Err(Error::UnexpectedInput(format!(
+                    "font table is out of range: {}",
+                    String::from_utf8_lossy(&tag)
+                )));
+            }
+            tables.push((tag, table));
+        }
+        let find = |tag: &[u8; 4]| {
+            tables
+                .iter()
+                .find(|(t, _)| t == tag)
+                .map(|(_, table)| *table)
+        };
+        let require = |tag: &[u8; 4]| {
+            find(tag).ok_or_else(|| {
+                Error::UnexpectedInput(format!(
+                    "font table is missing: {}",
+                    String::from_utf8_lossy(tag)
+                ))
+            })
+        };
+
+        let head = require(b"head")?;
+        let hhea = require(b"hhea")?;
+        let maxp = require(b"maxp")?;
+        let hmtx = require(b"hmtx")?;
+
+        let cmap = match find(b"cmap") {
+            Some(table) => select_cmap_subtable(&data, table.offset)?,
+            None => None,
+        };
+        let kern = match find(b"kern") {
+            Some(table) => select_kern_subtable(&data, table.offset)?,
+            None => None,
+        };
+        let family = match find(b"name") {
+            Some(table) => read_family_name(&data, table.offset).unwrap_or_default(),
+            None => String::new(),
+        };
+
+        let units_per_em = read_u16(&data, head.offset + 18)?;
+        if !UNITS_PER_EM_RANGE.contains(&units_per_em) {
+            return Err(Error::UnexpectedInput(format!(
+                "unitsPerEm is out of range: {}",
+                units_per_em
+            )));
+        }
+        let bounding_box = [
+            read_i16(&data, head.offset + 36)?,
+            read_i16(&data, head.offset + 38)?,
+            read_i16(&data, head.offset + 40)?,
+            read_i16(&data, head.offset + 42)?,
+        ];
+        if bounding_box[0] > bounding_box[2] || bounding_box[1] > bounding_box[3] {
+            return Err(Error::UnexpectedInput(format!(
+                "invalid font bounding box: {:?}",
+                bounding_box
+            )));
+        }
+
+        Ok(Self {
+            family,
+            units_per_em,
+            bounding_box,
+            index_to_loc_format: read_i16(&data, head.offset + 50)?,
+            num_glyphs: read_u16(&data, maxp.offset + 4)?,
+            ascender: read_i16(&data, hhea.offset + 4)?,
+            descender: read_i16(&data, hhea.offset + 6)?,
+            line_gap: read_i16(&data, hhea.offset + 8)?,
+            number_of_h_metrics: read_u16(&data, hhea.offset + 34)?,
+            cmap,
+            hmtx,
+            loca: find(b"loca"),
+            glyf: find(b"glyf"),
+            kern,
+            data,
+        })
+    }
+
+    /// nameテーブルに書かれたフォントファミリー名
+    pub fn family(&self) -> String {
+        self.family.clone()
+    }
+
+    pub fn units_per_em(&self) -> u16 {
+        self.units_per_em
+    }
+
+    pub fn num_glyphs(&self) -> u16 {
+        self.num_glyphs
+    }
+
+    /// 文字に対応するグリフの番号
+    /// フォントに含まれない文字は0(.notdef)になる
+    pub fn glyph_index(&self, c: char) -> u16 {
+        let subtable = match self.cmap {
+            Some(s) => s,
+            None => return 0,
+        };
+        lookup_cmap(&self.data, subtable, c as u32).unwrap_or(0)
+    }
+
+    /// フォント単位の送り幅
+    pub fn advance_width_units(&self, glyph: u16) -> u16 {
+        if self.number_of_h_metrics == 0 {
+            return 0;
+        }
+        let index = core::cmp::min(glyph, self.number_of_h_metrics - 1) as usize;
+        read_u16(&self.data, self.hmtx.offset + 4 * index).unwrap_or(0)
+    }
+
+    /// フォント単位のカーニング
+    pub fn kerning_units(&self, left: u16, right: u16) -> i16 {
+        let subtable = match self.kern {
+            Some(s) => s,
+            None => return 0,
+        };
+        lookup_kern(&self.data, subtable, left, right).unwrap_or(0)
+    }
+
+    /// ピクセル単位にするときの拡大率
+    pub fn scale(&self, font_size: i64) -> f32 {
+        font_size as f32 / self.units_per_em as f32
+    }
+
+    /// グリフの輪郭
+    /// 空白のように輪郭を持たないグリフや、glyfテーブルがない場合は空になる
+    /// headテーブルの外接矩形からはみ出す輪郭は、壊れたデータとしてエラーにする
+    pub fn outline(&self, glyph: u16) -> Result<Outline, Error> {
+        let mut outline = Outline::empty();
+        self.append_outline(glyph, &Transform::identity(), 0, &mut outline)?;
+        outline.update_bounds();
+
+        let [x_min, y_min, x_max, y_max] = self.bounding_box.map(|n| n as f32);
+        let inside = x_min <= outline.x_min
+            && y_min <= outline.y_min
+            && outline.x_max <= x_max
+            && outline.y_max <= y_max;
+        if !outline.segments.is_empty() && !inside {
+            return Err(Error::UnexpectedInput(format!(
+                "glyph {} is outside the font bounding box",
+                glyph
+            )));
+        }
+        Ok(outline)
+    }
+
+    fn glyph_range(&self, glyph: u16) -> Result<Option<(usize, usize)>, Error> {
+        let (loca, glyf) = match (self.loca, self.glyf) {
+            (Some(loca), Some(glyf)) => (loca, glyf),
+            _ => return Ok(None),
+        };
+        if glyph >= self.num_glyphs {
+            return Ok(None);
+        }
+
+        let index = glyph as usize;
+        let (start, end) = if self.index_to_loc_format == 0 {
+            (
+                read_u16(&self.data, loca.offset + 2 * index)? as usize * 2,
+                read_u16(&self.data, loca.offset + 2 * index + 2)? as usize * 2,
+            )
+        } else {
+            (
+                read_u32(&self.data, loca.offset + 4 * index)? as usize,
+                read_u32(&self.data, loca.offset + 4 * index + 4)? as usize,
+            )
+        };
+        if start >= end || end > glyf.length {
+            return Ok(None);
+        }
+
+        Ok(Some((glyf.offset + start, glyf.offset + end)))
+    }
+
+    fn append_outline(
+        &self,
+        glyph: u16,
+        transform: &Transform,
+        depth: u32,
+        outline: &mut Outline,
+    ) -> Result<(), Error> {
+        if depth > MAX_COMPONENT_DEPTH {
+            return Err(Error::UnexpectedInput(
+                "composite glyph is nested too deeply".into(),
+            ));
+        }
+        let (start, _end) = match self.glyph_range(glyph)? {
+            Some(range) => range,
+            None => return Ok(()),
+        };
+
+        let number_of_contours = read_i16(&self.data, start)?;
+        if number_of_contours >= 0 {
+            self.append_simple_glyph(start, number_of_contours as usize, transform, outline)
+        } else {
+            self.append_composite_glyph(start, transform, depth, outline)
+        }
+    }
+
+    fn append_simple_glyph(
+        &self,
+        start: usize,
+        number_of_contours: usize,
+        transform: &Transform,
+        outline: &mut Outline,
+    ) -> Result<(), Error> {
+        const ON_CURVE_POINT: u8 = 0x01;
+        const X_SHORT_VECTOR: u8 = 0x02;
+        const Y_SHORT_VECTOR: u8 = 0x04;
+        const REPEAT_FLAG: u8 = 0x08;
+        const X_IS_SAME_OR_POSITIVE: u8 = 0x10;
+        const Y_IS_SAME_OR_POSITIVE: u8 = 0x20;
+
+        let data = &self.data;
+        let mut end_points = Vec::with_capacity(number_of_contours);
+        for i in 0..number_of_contours {
+            end_points.push(read_u16(data, start + 10 + 2 * i)? as usize);
+        }
+        let num_points = match end_points.last() {
+            Some(last) => last + 1,
+            None => return Ok(()),
+        };
+
+        let instruction_length_offset = start + 10 + 2 * number_of_contours;
+        let instruction_length = read_u16(data, instruction_length_offset)? as usize;
+        let mut offset = instruction_length_offset + 2 + instruction_length;
+
+        let mut flags = Vec::with_capacity(num_points);
+        while flags.len() < num_points {
+            let flag = read_u8(data, offset)?;
+            offset += 1;
+            flags.push(flag);
+            if flag & REPEAT_FLAG != 0 {
+                let count = read_u8(data, offset)?;
+                offset += 1;
+                for _ in 0..count {
+                    flags.push(flag);
+                }
+            }
+        }
+        flags.truncate(num_points);
+
+        let mut read_coordinates = |short: u8, same_or_positive: u8| -> Result<Vec<f32>, Error> {
+            let mut values = Vec::with_capacity(num_points);
+            let mut value: i32 = 0;
+            for flag in &flags {
+                if flag & short != 0 {
+                    let delta = read_u8(data, offset)? as i32;
+                    offset += 1;
+                    value += if flag & same_or_positive != 0 {
+                        delta
+                    } else {
+                        -delta
+                    };
+                } else if flag & same_or_positive == 0 {
+                    value += read_i16(data, offset)? as i32;
+                    offset += 2;
+                }
+                values.push(value as f32);
+            }
+            Ok(values)
+        };
+        let xs = read_coordinates(X_SHORT_VECTOR, X_IS_SAME_OR_POSITIVE)?;
+        let ys = read_coordinates(Y_SHORT_VECTOR, Y_IS_SAME_OR_POSITIVE)?;
+
+        let mut contour_start = 0;
+        for end in end_points {
+            if end < contour_start || end >= num_points {
+                return Err(Error::UnexpectedInput("invalid contour end point".into()));
+            }
+            let points = (contour_start..=end)
+                .map(|i| {
+                    (
+                        transform.apply(Point::new(xs[i], ys[i])),
+                        flags[i] & ON_CURVE_POINT != 0,
+                    )
+                })
+                .collect::<Vec<_>>();
+            append_contour(&points, &mut outline.segments);
+            contour_start = end + 1;
+        }
+
+        Ok(())
+    }
+
+    fn append_composite_glyph(
+        &self,
+        start: usize,
+        transform: &Transform,
+        depth: u32,
+        outline: &mut Outline,
+    ) -> Result<(), Error> {
+        const ARG_1_AND_2_ARE_WORDS: u16 = 0x0001;
+        const ARGS_ARE_XY_VALUES: u16 = 0x0002;
+        const WE_HAVE_A_SCALE: u16 = 0x0008;
+        const MORE_COMPONENTS: u16 = 0x0020;
+        const WE_HAVE_AN_X_AND_Y_SCALE: u16 = 0x0040;
+        const WE_HAVE_A_TWO_BY_TWO: u16 = 0x0080;
+
+        let data = &self.data;
+        let mut offset = start + 10;
+        loop {
+            let flags = read_u16(data, offset)?;
+            let glyph = read_u16(data, offset + 2)?;
+            offset += 4;
+
+            let (arg1, arg2) = if flags & ARG_1_AND_2_ARE_WORDS != 0 {
+                let args = (
+                    read_i16(data, offset)? as f32,
+                    read_i16(data, offset + 2)? as f32,
+                );
+                offset += 4;
+                args
+            } else {
+                let args = (
+                    read_u8(data, offset)? as i8 as f32,
+                    read_u8(data, offset + 1)? as i8 as f32,
+                );
+                offset += 2;
+                args
+            };
+
+            let mut component = Transform::identity();
+            if flags & WE_HAVE_A_SCALE != 0 {
+                let scale = read_f2dot14(data, offset)?;
+                component.xx = scale;
+                component.yy = scale;
+                offset += 2;
+            } else if flags & WE_HAVE_AN_X_AND_Y_SCALE != 0 {
+                component.xx = read_f2dot14(data, offset)?;
+                component.yy = read_f2dot14(data, offset + 2)?;
+                offset += 4;
+            } else if flags & WE_HAVE_A_TWO_BY_TWO != 0 {
+                component.xx = read_f2dot14(data, offset)?;
+                component.xy = read_f2dot14(data, offset + 2)?;
+                component.yx = read_f2dot14(data, offset + 4)?;
+                component.yy = read_f2dot14(data, offset + 6)?;
+                offset += 8;
+            }
+            // 点の番号で位置を合わせる指定には対応せず、移動しない
+            if flags & ARGS_ARE_XY_VALUES != 0 {
+                component.dx = arg1;
+                component.dy = arg2;
+            }
+
+            self.append_outline(glyph, &transform.then(&component), depth + 1, outline)?;
+
+            if flags & MORE_COMPONENTS == 0 {
+                return Ok(());
+            }
+        }
+    }
+}
+
+/// TrueTypeの輪郭を線分と2次ベジェ曲線に変換する
+/// 連続する2つの曲線外の点の間には、その中点に曲線上の点があるものとして扱う
+fn append_contour(points: &[(Point, bool)], segments: &mut Vec<PathSegment>) {
+    if points.is_empty() {
+        return;
+    }
+
+    // 曲線上の点から始める
+    // 曲線上の点がない場合は、最初の2点の中点から始める
+    let first_on = points.iter().position(|(_, on_curve)| *on_curve);
+    let start = match first_on {
+        Some(i) => points[i].0,
+        None => points[0].0.midpoint(points[1 % points.len()].0),
+    };
+    let i = first_on.unwrap_or(0);
+    let rotated = points[i + 1..].iter().chain(points[..=i].iter());
+
+    let mut current = start;
+    let mut control: Option<Point> = None;
+    for (p, on_curve) in rotated {
+        match (on_curve, control) {
+            (true, None) => {
+                segments.push(PathSegment::Line(current, *p));
+                current = *p;
+            }
+            (true, Some(c)) => {
+                segments.push(PathSegment::Quad(current, c, *p));
+                current = *p;
+                control = None;
+            }
+            (false, None) => control = Some(*p),
+            (false, Some(c)) => {
+                let mid = c.midpoint(*p);
+                segments.push(PathSegment::Quad(current, c, mid));
+                current = mid;
+                control = Some(*p);
+            }
+        }
+    }
+
+    // 輪郭を閉じる
+    match control {
+        Some(c) => segments.push(PathSegment::Quad(current, c, start)),
+        None if current != start => segments.push(PathSegment::Line(current, start)),
+        None => {}
+    }
+}
+
+/// Unicodeの文字を引けるcmapのサブテーブルを選ぶ
+fn select_cmap_subtable(data: &[u8], cmap: usize) -> Result<Option<usize>, Error> {
+    let num_tables = read_u16(data, cmap + 2)? as usize;
+    let mut best: Option<(u32, usize)> = None;
+    for i in 0..num_tables {
+        let record = cmap + 4 + 8 * i;
+        let platform = read_u16(data, record)?;
+        let encoding = read_u16(data, record + 2)?;
+        let subtable = cmap + read_u32(data, record + 4)? as usize;
+        let format = read_u16(data, subtable)?;
+
+        // 補助面の文字まで引けるものを優先する
+        let priority = match (platform, encoding, format) {
+            (3, 10, 12) | (0, 4, 12) | (0, 6, 12) => 4,
+            (3, 1, 4) | (0, 3, 4) => 3,
+            (0, _, 4) | (0, _, 6) | (3, 1, 6) => 2,
+            (1, 0, 0) | (1, 0, 6) => 1,
+            _ => 0,
+        };
+        if priority > 0 && best.map_or(true, |(p, _)| priority > p) {
+            best = Some((priority, subtable));
+        }
+    }
+
+    Ok(best.map(|(_, subtable)| subtable))
+}
+
+fn lookup_cmap(data: &[u8], subtable: usize, c: u32) -> Result<u16, Error> {
+    match read_u16(data, subtable)? {
+        0 => {
+            if c < 256 {
+                Ok(read_u8(data, subtable + 6 + c as usize)? as u16)
+            } else {
+                Ok(0)
+            }
+        }
+        4 => {
+            if c > 0xffff {
+                return Ok(0);
+            }
+            let c = c as u16;
+            let seg_count = read_u16(data, subtable + 6)? as usize / 2;
+            let end_codes = subtable + 14;
+            let start_codes = end_codes + 2 * seg_count + 2;
+            let id_deltas = start_codes + 2 * seg_count;
+            let id_range_offsets = id_deltas + 2 * seg_count;
+
+            // endCodeは昇順に並んでいる
+            let (mut low, mut high) = (0, seg_count);
+            while low < high {
+                let mid = (low + high) / 2;
+                if read_u16(data, end_codes + 2 * mid)? < c {
+                    low = mid + 1;
+                } else {
+                    high = mid;
+                }
+            }
+            if low >= seg_count {
+                return Ok(0);
+            }
+
+            let i = low;
+            let start = read_u16(data, start_codes + 2 * i)?;
+            if c < start {
+                return Ok(0);
+            }
+            let delta = read_u16(data, id_deltas + 2 * i)?;
+            let range_offset = read_u16(data, id_range_offsets + 2 * i)? as usize;
+            if range_offset == 0 {
+                return Ok(c.wrapping_add(delta));
+            }
+
+            let glyph_offset = id_range_offsets + 2 * i + range_offset + 2 * (c - start) as usize;
+            let glyph = read_u16(data, glyph_offset)?;
+            if glyph == 0 {
+                Ok(0)
+            } else {
+                Ok(glyph.wrapping_add(delta))
+            }
+        }
+        6 => {
+            let first = read_u16(data, subtable + 6)? as u32;
+            let count = read_u16(data, subtable + 8)? as u32;
+            if c < first || c >= first + count {
+                return Ok(0);
+            }
+            read_u16(data, subtable + 10 + 2 * (c - first) as usize)
+        }
+        12 => {
+            let num_groups = read_u32(data, subtable + 12)? as usize;
+            let (mut low, mut high) = (0, num_groups);
+            while low < high {
+                let mid = (low + high) / 2;
+                let group = subtable + 16 + 12 * mid;
+                let start = read_u32(data, group)?;
+                let end = read_u32(data, group + 4)?;
+                if c < start {
+                    high = mid;
+                } else if c > end {
+                    low = mid + 1;
+                } else {
+                    let start_glyph = read_u32(data, group + 8)?;
+                    return Ok((start_glyph + c - start) as u16);
+                }
+            }
+            Ok(0)
+        }
+        _ => Ok(0),
+    }
+}
+
+/// 水平方向のカーニングを持つフォーマット0のサブテーブルを探す
+fn select_kern_subtable(data: &[u8], kern: usize) -> Result<Option<usize>, Error> {
+    // Apple形式(バージョン1.0)のkernテーブルには対応しない
+    if read_u16(data, kern)? != 0 {
+        return Ok(None);
+    }
+
+    let num_tables = read_u16(data, kern + 2)? as usize;
+    let mut offset = kern + 4;
+    for _ in 0..num_tables {
+        let length = read_u16(data, offset + 2)? as usize;
+        let coverage = read_u16(data, offset + 4)?;
+        let horizontal = coverage & 0x01 != 0;
+        let minimum = coverage & 0x02 != 0;
+        let cross_stream = coverage & 0x04 != 0;
+        let format = coverage >> 8;
+        if horizontal && !minimum && !cross_stream && format == 0 {
+            return Ok(Some(offset));
+        }
+        offset += length;
+    }
+
+    Ok(None)
+}
+
+fn lookup_kern(data: &[u8], subtable: usize, left: u16, right: u16) -> Result<i16, Error> {
+    let num_pairs = read_u16(data, subtable + 6)? as usize;
+    let pairs = subtable + 14;
+    let key = ((left as u32) << 16) | right as u32;
+
+    // ペアは左右のグリフ番号の順に並んでいる
+    let (mut low, mut high) = (0, num_pairs);
+    while low < high {
+        let mid = (low + high) / 2;
+        let pair = pairs + 6 * mid;
+        let k = read_u32(data, pair)?;
+        match k.cmp(&key) {
+            core::cmp::Ordering::Less => low = mid + 1,
+            core::cmp::Ordering::Greater => high = mid,
+            core::cmp::Ordering::Equal => return read_i16(data, pair + 4),
+        }
+    }
+
+    Ok(0)
+}
+
+/// nameテーブルからフォントファミリー名(nameID 1)を読む
+fn read_family_name(data: &[u8], name: usize) -> Result<String, Error> {
+    let count = read_u16(data, name + 2)? as usize;
+    let storage = name + read_u16(data, name + 4)? as usize;
+    for i in 0..count {
+        let record = name + 6 + 12 * i;
+        let platform = read_u16(data, record)?;
+        let name_id = read_u16(data, record + 6)?;
+        if name_id != 1 {
+            continue;
+        }
+        let length = read_u16(data, record + 8)? as usize;
+        let offset = storage + read_u16(data, record + 10)? as usize;
+        let bytes = data
+            .get(offset..offset + length)
+            .ok_or_else(|| Error::UnexpectedInput("font name is out of range".into()))?;
+
+        return Ok(match platform {
+            // UTF-16BE
+            0 | 3 => {
+                let units = bytes
+                    .chunks(2)
+                    .filter(|c| c.len() == 2)
+                    .map(|c| ((c[0] as u16) << 8) | c[1] as u16)
+                    .collect::<Vec<_>>();
+                String::from_utf16_lossy(&units)
+            }
+            _ => String::from_utf8_lossy(bytes).into_owned(),
+        });
+    }
+
+    Ok(String::new())
+}
+
+/// 浮動小数点数を四捨五入して整数にする
+fn round(x: f32) -> i64 {
+    if x >= 0.0 {
+        (x + 0.5) as i64
+    } else {
+        (x - 0.5) as i64
+    }
+}
+
+impl FontMetrics for Font {
+    fn advance_width(&self, cluster: &str, font_size: i64) -> i64 {
+        // 結合文字は基底文字に重ねて描画されるので、送り幅は基底文字だけで決まる
+        let c = match cluster.chars().next() {
+            Some(c) => c,
+            None => return 0,
+        };
+        round(self.advance_width_units(self.glyph_index(c)) as f32 * self.scale(font_size))
+    }
+
+    fn ascent(&self, font_size: i64) -> i64 {
+        round(self.ascender as f32 * self.scale(font_size))
+    }
+
+    fn descent(&self, font_size: i64) -> i64 {
+        round(-(self.descender as f32) * self.scale(font_size))
+    }
+
+    fn line_gap(&self, font_size: i64) -> i64 {
+        round(self.line_gap as f32 * self.scale(font_size))
+    }
+
+    fn text_width(&self, text: &str, font_size: i64) -> i64 {
+        // 隣り合う文字の間のカーニングを含めて、まとめて丸める
+        let mut units: i64 = 0;
+        let mut previous: Option<u16> = None;
+        for cluster in grapheme_clusters(text) {
+            let c = match cluster.chars().next() {
+                Some(c) => c,
+                None => continue,
+            };
+            let glyph = self.glyph_index(c);
+            if let Some(p) = previous {
+                units += self.kerning_units(p, glyph) as i64;
+            }
+            units += self.advance_width_units(glyph) as i64;
+            previous = Some(glyph);
+        }
+        round(units as f32 * self.scale(font_size))
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use alloc::vec;
+
+    fn push_u16(v: &mut Vec<u8>, n: u16) {
+        v.extend_from_slice(&n.to_be_bytes());
+    }
+
+    fn push_i16(v: &mut Vec<u8>, n: i16) {
+        v.extend_from_slice(&n.to_be_bytes());
+    }
+
+    fn push_u32(v: &mut Vec<u8>, n: u32) {
+        v.extend_from_slice(&n.to_be_bytes());
+    }
+
+    /// テスト用の小さなフォントを作る
+    /// unitsPerEmは1000で、グリフ0(.notdef)は空、グリフ1("A")は600x700の正方形、
+    /// グリフ2("V")は三角形、グリフ3("B")はグリフ1を右に100ずらした複合グリフ
+    pub(crate) fn build_test_font() -> Vec<u8> {
+        let mut head = Vec::new();
+        push_u32(&mut head, 0x0001_0000); // version
+        push_u32(&mut head, 0x0001_0000); // fontRevision
+        push_u32(&mut head, 0); // checksumAdjustment
+        push_u32(&mut head, 0x5f0f_3cf5); // magicNumber
+        push_u16(&mut head, 0); // flags
+        push_u16(&mut head, 1000); // unitsPerEm
+        head.extend_from_slice(&[0; 16]); // created, modified
+        for n in [0i16, 0, 700, 700] {
+            push_i16(&mut head, n); // xMin, yMin, xMax, yMax
+        }
+        push_u16(&mut head, 0); // macStyle
+        push_u16(&mut head, 8); // lowestRecPPEM
+        push_i16(&mut head, 2); // fontDirectionHint
+        push_i16(&mut head, 0); // indexToLocFormat
+        push_i16(&mut head, 0); // glyphDataFormat
+
+        let mut hhea = Vec::new();
+        push_u32(&mut hhea, 0x0001_0000);
+        push_i16(&mut hhea, 800); // ascender
+        push_i16(&mut hhea, -200); // descender
+        push_i16(&mut hhea, 100); // lineGap
+        hhea.extend_from_slice(&[0; 24]);
+        push_u16(&mut hhea, 4); // numberOfHMetrics
+
+        let mut maxp = Vec::new();
+        push_u32(&mut maxp, 0x0000_5000);
+        push_u16(&mut maxp, 4); // numGlyphs
+
+        let mut hmtx = Vec::new();
+        for advance in [500u16, 700, 600, 800] {
+            push_u16(&mut hmtx, advance);
+            push_i16(&mut hmtx, 0);
+        }
+
+        // フォーマット4: 'A'(0x41)から'B'(0x42)、'V'(0x56)、終端の0xffff
+        let mut cmap = Vec::new();
+        push_u16(&mut cmap, 0); // version
+        push_u16(&mut cmap, 1); // numTables
+        push_u16(&mut cmap, 3); // platformID
+        push_u16(&mut cmap, 1); // encodingID
+        push_u32(&mut cmap, 12); // offset
+        let seg_count = 3u16;
+        push_u16(&mut cmap, 4); // format
+        push_u16(&mut cmap, 16 + 8 * seg_count); // length
+        push_u16(&mut cmap, 0); // language
+        push_u16(&mut cmap, seg_count * 2);
+        push_u16(&mut cmap, 4); // searchRange
+        push_u16(&mut cmap, 1); // entrySelector
+        push_u16(&mut cmap, 2); // rangeShift
+        for end in [0x42u16, 0x56, 0xffff] {
+            push_u16(&mut cmap, end);
+        }
+        push_u16(&mut cmap, 0); // reservedPad
+        for start in [0x41u16, 0x56, 0xffff] {
+            push_u16(&mut cmap, start);
+        }
+        // 'A' -> 1, 'B' -> 3 はidRangeOffsetで、'V' -> 2 はidDeltaで引く
+        for delta in [0u16, 2u16.wrapping_sub(0x56), 1] {
+            push_u16(&mut cmap, delta);
+        }
+        push_u16(&mut cmap, 6); // idRangeOffset[0]: glyphIdArrayを指す
+        push_u16(&mut cmap, 0);
+        push_u16(&mut cmap, 0);
+        push_u16(&mut cmap, 1); // glyphIdArray['A']
+        push_u16(&mut cmap, 3); // glyphIdArray['B']
+
+        let mut glyf = Vec::new();
+        let mut loca = vec![0u16];
+        // グリフ0: 空
+        loca.push(0);
+        // グリフ1: 正方形
+        push_i16(&mut glyf, 1);
+        for n in [0i16, 0, 600, 700] {
+            push_i16(&mut glyf, n);
+        }
+        push_u16(&mut glyf, 3); // endPtsOfContours
+        push_u16(&mut glyf, 0); // instructionLength
+        glyf.extend_from_slice(&[0x01; 4]); // flags: 曲線上の点
+        for dx in [0i16, 600, 0, -600] {
+            push_i16(&mut glyf, dx);
+        }
+        for dy in [0i16, 0, 700, 0] {
+            push_i16(&mut glyf, dy);
+        }
+        loca.push(glyf.len() as u16 / 2);
+        // グリフ2: 三角形(頂点は曲線外の点)
+        push_i16(&mut glyf, 1);
+        for n in [0i16, 0, 600, 700] {
+            push_i16(&mut glyf, n);
+        }
+        push_u16(&mut glyf, 2);
+        push_u16(&mut glyf, 0);
+        glyf.extend_from_slice(&[0x01, 0x00, 0x01, 0x00]); // flags + パディング
+        glyf.pop();
+        for dx in [0i16, 300, 300] {
+            push_i16(&mut glyf, dx);
+        }
+        for dy in [700i16, -700, 700] {
+            push_i16(&mut glyf, dy);
+        }
+        if glyf.len() % 2 == 1 {
+            glyf.push(0);
+        }
+        loca.push(glyf.len() as u16 / 2);
+        // グリフ3: グリフ1を(100, 0)に移動した複合グリフ
+        push_i16(&mut glyf, -1);
+        for n in [100i16, 0, 700, 700] {
+            push_i16(&mut glyf, n);
+        }
+        push_u16(&mut glyf, 0x0003); // ARG_1_AND_2_ARE_WORDS | ARGS_ARE_XY_VALUES
+        push_u16(&mut glyf, 1);
+        push_i16(&mut glyf, 100);
+        push_i16(&mut glyf, 0);
+        loca.push(glyf.len() as u16 / 2);
+        let loca = loca
+            .iter()
+            .flat_map(|n| n.to_be_bytes())
+            .collect::<Vec<_>>();
+
+        // 'A'と'V'の間のカーニング
+        let mut kern = Vec::new();
+        push_u16(&mut kern, 0); // version
+        push_u16(&mut kern, 1); // nTables
+        push_u16(&mut kern, 0); // subtable version
+        push_u16(&mut kern, 14 + 6); // length
+        push_u16(&mut kern, 0x0001); // coverage: 水平、フォーマット0
+        push_u16(&mut kern, 1); // nPairs
+        push_u16(&mut kern, 6);
+        push_u16(&mut kern, 0);
+        push_u16(&mut kern, 0);
+        push_u16(&mut kern, 1); // left
+        push_u16(&mut kern, 2); // right
+        push_i16(&mut kern, -100); // value
+
+        let mut name = Vec::new();
+        let family = "Test Sans"
+            .encode_utf16()
+            .flat_map(|u| u.to_be_bytes())
+            .collect::<Vec<_>>();
+        push_u16(&mut name, 0); // format
+        push_u16(&mut name, 1); // count
+        push_u16(&mut name, 6 + 12); // stringOffset
+        push_u16(&mut name, 3); // platformID
+        push_u16(&mut name, 1); // encodingID
+        push_u16(&mut name, 0x409); // languageID
+        push_u16(&mut name, 1); // nameID
+        push_u16(&mut name, family.len() as u16);
+        push_u16(&mut name, 0);
+        name.extend_from_slice(&family);
+
+        let tables: [(&[u8; 4], Vec<u8>); 9] = [
+            (b"cmap", cmap),
+            (b"glyf", glyf),
+            (b"head", head),
+            (b"hhea", hhea),
+            (b"hmtx", hmtx),
+            (b"kern", kern),
+            (b"loca", loca),
+            (b"maxp", maxp),
+            (b"name", name),
+        ];
+
+        let mut font = Vec::new();
+        push_u32(&mut font, 0x0001_0000);
+        push_u16(&mut font, tables.len() as u16);
+        font.extend_from_slice(&[0; 6]);
+        let mut offset = 12 + 16 * tables.len();
+        let mut body = Vec::new();
+        for (tag, data) in &tables {
+            font.extend_from_slice(tag.as_slice());
+            push_u32(&mut font, 0);
+            push_u32(&mut font, offset as u32);
+            push_u32(&mut font, data.len() as u32);
+            body.extend_from_slice(data);
+            while body.len() % 4 != 0 {
+                body.push(0);
+            }
+            offset = 12 + 16 * tables.len() + body.len();
+        }
+        font.extend_from_slice(&body);
+
+        font
+    }
+
+    #[test]
+    fn test_parse_header() {
+        let font = Font::parse(build_test_font()).expect("failed to parse font");
+        assert_eq!(1000, font.units_per_em());
+        assert_eq!(4, font.num_glyphs());
+        assert_eq!("Test Sans", font.family());
+    }
+
+    #[test]
+    fn test_invalid_font() {
+        assert!(Font::parse(vec![0, 1, 2]).is_err());
+        assert!(Font::parse(vec![0; 64]).is_err());
+    }
+
+    /// テスト用のフォントのheadテーブルの`offset`にある値を書き換える
+    fn patch_head(offset: usize, value: i16) -> Vec<u8> {
+        let mut font = build_test_font();
+        let record = (0..read_u16(&font, 4).unwrap() as usize)
+            .map(|i| 12 + 16 * i)
+            .find(|record| &font[*record..*record + 4] == b"head")
+            .unwrap();
+        let head = read_u32(&font, record + 8).unwrap() as usize;
+        font[head + offset..head + offset + 2].copy_from_slice(&value.to_be_bytes());
+        font
+    }
+
+    #[test]
+    fn test_invalid_head() {
+        // unitsPerEm
+        assert!(Font::parse(patch_head(18, 0)).is_err());
+        assert!(Font::parse(patch_head(18, 15)).is_err());
+        assert!(Font::parse(patch_head(18, 16385)).is_err());
+        assert!(Font::parse(patch_head(18, 16)).is_ok());
+        // xMinがxMaxより大きい
+        assert!(Font::parse(patch_head(36, 701)).is_err());
+        // yMaxがyMinより小さい
+        assert!(Font::parse(patch_head(42, -1)).is_err());
+    }
+
+    #[test]
+    fn test_outline_outside_bounding_box() {
+        // グリフ1の右端は600
+        let font = Font::parse(patch_head(40, 599)).expect("failed to parse font");
+        assert!(font.outline(1).is_err());
+        assert!(font.outline(0).is_ok());
+    }
+
+    #[test]
+    fn test_glyph_index() {
+        let font = Font::parse(build_test_font()).expect("failed to parse font");
+        assert_eq!(1, font.glyph_index('A'));
+        assert_eq!(3, font.glyph_index('B'));
+        assert_eq!(2, font.glyph_index('V'));
+        assert_eq!(0, font.glyph_index('C'));
+        assert_eq!(0, font.glyph_index('あ'));
+    }
+
+    #[test]
+    fn test_metrics() {
+        let font = Font::parse(build_test_font()).expect("failed to parse font");
+        assert_eq!(14, font.advance_width("A", 20));
+        assert_eq!(16, font.ascent(20));
+        assert_eq!(4, font.descent(20));
+        assert_eq!(22, font.line_height(20));
+        // "AV"は700 + 600 - 100 = 1200
+        assert_eq!(-100, font.kerning_units(1, 2));
+        assert_eq!(24, font.text_width("AV", 20));
+        assert_eq!(26, font.text_width("VA", 20));
+    }
+
+    #[test]
+    fn test_simple_outline() {
+        let font = Font::parse(build_test_font()).expect("failed to parse font");
+        let outline = font.outline(1).expect("failed to read outline");
+        assert_eq!(
+            vec![
+                PathSegment::Line(Point::new(0.0, 0.0), Point::new(600.0, 0.0)),
+                PathSegment::Line(Point::new(600.0, 0.0), Point::new(600.0, 700.0)),
+                PathSegment::Line(Point::new(600.0, 700.0), Point::new(0.0, 700.0)),
+                PathSegment::Line(Point::new(0.0, 700.0), Point::new(0.0, 0.0)),
+            ],
+            outline.segments
+        );
+        assert_eq!(600.0, outline.x_max);
+        assert_eq!(700.0, outline.y_max);
+    }
+
+    #[test]
+    fn test_off_curve_points() {
+        let font = Font::parse(build_test_font()).expect("failed to parse font");
+        let outline = font.outline(2).expect("failed to read outline");
+        assert_eq!(
+            vec![
+                PathSegment::Quad(
+                    Point::new(0.0, 700.0),
+                    Point::new(300.0, 0.0),
+                    Point::new(600.0, 700.0)
+                ),
+                PathSegment::Line(Point::new(600.0, 700.0), Point::new(0.0, 700.0)),
+            ],
+            outline.segments
+        );
+    }
+
+    #[test]
+    fn test_composite_outline() {
+        let font = Font::parse(build_test_font()).expect("failed to parse font");
+        let outline = font.outline(3).expect("failed to read outline");
+        assert_eq!(4, outline.segments.len());
+        assert_eq!(100.0, outline.x_min);
+        assert_eq!(700.0, outline.x_max);
+    }
+
+    #[test]
+    fn test_empty_outline() {
+        let font = Font::parse(build_test_font()).expect("failed to parse font");
+        let outline = font.outline(0).expect("failed to read outline");
+        assert!(outline.segments.is_empty());
+    }
+}
@@ -0,0 +1,146 @@
+//! 書記素クラスタの分割と文字幅の判定
+//! Unicodeの規則のうち、ウェブページでよく使われるものだけを扱う
+//! https://www.unicode.org/reports/tr29/
+//! https://www.unicode.org/reports/tr11/
+
+use alloc::vec::Vec;
+
+const ZERO_WIDTH_JOINER: char = '\u{200d}';
+
+/// 直前の文字と結合して1つの書記素クラスタになる文字
+fn is_extend(c: char) -> bool {
+    matches!(c as u32,
+        0x0300..=0x036f // 結合分音記号
+        | 0x0483..=0x0489
+        | 0x0591..=0x05bd
+        | 0x0610..=0x061a
+        | 0x064b..=0x065f
+        | 0x0e31 | 0x0e34..=0x0e3a | 0x0e47..=0x0e4e // タイ文字の母音と声調記号
+        | 0x1ab0..=0x1aff
+        | 0x1dc0..=0x1dff
+        | 0x200c // ZERO WIDTH NON-JOINER
+        | 0x20d0..=0x20ff
+        | 0x3099..=0x309a // 結合用の濁点と半濁点
+        | 0xfe00..=0xfe0f // 異体字セレクタ
+        | 0xfe20..=0xfe2f
+        | 0x1f3fb..=0x1f3ff // 絵文字の肌の色
+        | 0xe0020..=0xe007f // タグ文字
+        | 0xe0100..=0xe01ef // 異体字セレクタ補助
+    )
+}
+
+fn is_regional_indicator(c: char) -> bool {
+    matches!(c as u32, 0x1f1e6..=0x1f1ff)
+}
+
+/// 書記素クラスタ(ユーザーが1文字として認識する単位)に分割する
+pub fn grapheme_clusters(text: &str) -> Vec<&str> {
+    let mut clusters = Vec::new();
+    let mut start = 0;
+    let mut prev: Option<char> = None;
+    // 現在のクラスタに含まれる国旗の記号の数
+    let mut regional_indicators = 0;
+
+    for (i, c) in text.char_indices() {
+        let join = match prev {
+            None => false,
+            Some(p) => {
+                (p == '\r' && c == '\n')
+                    || is_extend(c)
+                    || c == ZERO_WIDTH_JOINER
+                    || (p == ZERO_WIDTH_JOINER && !c.is_whitespace())
+                    || (is_regional_indicator(p)
+                        && is_regional_indicator(c)
+                        && regional_indicators % 2 == 1)
+            }
+        };
+
+        if !join && prev.is_some() {
+            clusters.push(&text[start..i]);
+            start = i;
+            regional_indicators = 0;
+        }
+        if is_regional_indicator(c) {
+            regional_indicators += 1;
+        }
+        prev = Some(c);
+    }
+
+    if start < text.len() {
+        clusters.push(&text[start..]);
+    }
+
+    clusters
+}
+
+/// 東アジアの文字幅が全角(Wide、Fullwidth)の文字
+pub fn is_wide(c: char) -> bool {
+    matches!(c as u32,
+        0x1100..=0x115f // ハングルの字母
+        | 0x2e80..=0x303e // CJKの部首、記号と句読点
+        | 0x3041..=0x33ff // ひらがな、カタカナ、CJKの互換文字
+        | 0x3400..=0x4dbf // CJK統合漢字拡張A
+        | 0x4e00..=0x9fff // CJK統合漢字
+        | 0xa000..=0xa4cf // イ文字
+        | 0xac00..=0xd7a3 // ハングル
+        | 0xf900..=0xfaff // CJK互換漢字
+        | 0xfe30..=0xfe4f // CJK互換形
+        | 0xff00..=0xff60 // 全角英数字
+        | 0xffe0..=0xffe6 // 全角記号
+        | 0x1f1e6..=0x1f1ff // 国旗の記号
+        | 0x1f300..=0x1f64f // 絵文字
+        | 0x1f900..=0x1f9ff // 絵文字補助
+        | 0x20000..=0x3fffd // CJK統合漢字拡張B以降
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::vec;
+
+    #[test]
+    fn test_ascii() {
+        assert_eq!(vec!["a", "b", " ", "c"], grapheme_clusters("ab c"));
+    }
+
+    #[test]
+    fn test_japanese() {
+        assert_eq!(vec!["日", "本", "語"], grapheme_clusters("日本語"));
+    }
+
+    #[test]
+    fn test_combining_marks() {
+        // "か" + 結合用の濁点
+        assert_eq!(vec!["か\u{3099}", "a"], grapheme_clusters("か\u{3099}a"));
+        // "e" + 結合アキュート・アクセント
+        assert_eq!(vec!["e\u{301}"], grapheme_clusters("e\u{301}"));
+    }
+
+    #[test]
+    fn test_crlf() {
+        assert_eq!(vec!["a", "\r\n", "b"], grapheme_clusters("a\r\nb"));
+    }
+
+    #[test]
+    fn test_emoji_sequences() {
+        // 家族の絵文字はZWJでつながった1つのクラスタになる
+        let family = "\u{1f468}\u{200d}\u{1f469}\u{200d}\u{1f467}";
+        assert_eq!(vec![family], grapheme_clusters(family));
+        // 国旗は2つの記号で1つのクラスタになる
+        let flags = "\u{1f1ef}\u{1f1f5}\u{1f1fa}\u{1f1f8}";
+        assert_eq!(
+            vec!["\u{1f1ef}\u{1f1f5}", "\u{1f1fa}\u{1f1f8}"],
+            grapheme_clusters(flags)
+        );
+    }
+
+    #[test]
+    fn test_is_wide() {
+        assert!(is_wide('あ'));
+        assert!(is_wide('漢'));
+        assert!(is_wide('Ａ'));
+        assert!(!is_wide('a'));
+        assert!(!is_wide('ｱ'));
+    }
+}
@@ -23,6 +23,7 @@ pub struct HttpResponse {
     reason: String,
     headers: Vec<Header>,
     body: String,
+    body_bytes: Vec<u8>,
 }
 
 impl HttpResponse {
@@ -61,9 +62,19 @@ impl HttpResponse {
             reason: statuses[2].to_string(),
             headers,
             body: body.to_string(),
+            body_bytes: body.as_bytes().to_vec(),
         })
     }
 
+    /// 本文をバイト列のまま残す。画像のようにUTF-8でない本文を受け取るときに使う
+    pub fn from_bytes(raw_response: &[u8]) -> Result<Self, Error> {
+        let (head, body) = split_head(raw_response);
+        let mut response = Self::new(String::from_utf8_lossy(head).into_owned() + "\n\n")?;
+        response.body = String::from_utf8_lossy(body).replace("\r\n", "\n");
+        response.body_bytes = body.to_vec();
+        Ok(response)
+    }
+
     pub fn version(&self) -> String {
         self.version.clone()
     }
@@ -84,6 +95,10 @@ impl HttpResponse {
         self.body.clone()
     }
 
+    pub fn body_bytes(&self) -> Vec<u8> {
+        self.body_bytes.clone()
+    }
+
     pub fn header_value(&self, name: &str) -> Result<String, String> {
         for h in &self.headers {
             if h.name == name {
@@ -95,9 +110,23 @@ impl HttpResponse {
     }
 }
 
+/// 最初の空行で、ステータス行とヘッダーの部分と本文に分ける
+fn split_head(raw_response: &[u8]) -> (&[u8], &[u8]) {
+    for i in 0..raw_response.len() {
+        if raw_response[i..].starts_with(b"\r\n\r\n") {
+            return (&raw_response[..i], &raw_response[i + 4..]);
+        }
+        if raw_response[i..].starts_with(b"\n\n") {
+            return (&raw_response[..i], &raw_response[i + 2..]);
+        }
+    }
+    (raw_response, &[])
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use alloc::vec;
     #[test]
     fn test_status_line_only() {
         let raw = "HTTP/1.1 200 OK\n\n".to_string();
@@ -141,6 +170,22 @@ mod tests {
         assert_eq!(res.body(), "body message");
     }
 
+    #[test]
+    fn test_binary_body() {
+        let mut raw = b"HTTP/1.1 200 OK\r\nContent-Type: image/png\r\n\r\n".to_vec();
+        raw.extend_from_slice(&[0x89, b'P', b'N', b'G', 0x0d, 0x0a, 0xff, 0x00]);
+        let res = HttpResponse::from_bytes(&raw).expect("failed to parse http response");
+        assert_eq!(res.status_code, 200);
+        assert_eq!(
+            res.header_value("Content-Type"),
+            Ok("image/png".to_string())
+        );
+        assert_eq!(
+            res.body_bytes(),
+            vec![0x89, b'P', b'N', b'G', 0x0d, 0x0a, 0xff, 0x00]
+        );
+    }
+
     #[test]
     fn test_invalid() {
         let raw = "HTTP/1.1 200 OK".to_string();
@@ -0,0 +1,317 @@
+//! GIF画像のデコード
+//! https://www.w3.org/Graphics/GIF/spec-gif89a.txt
+//!
+//! アニメーションは最初のフレームだけを読む
+
+use crate::error::Error;
+use crate::image::Image;
+use crate::renderer::layout::computed_style::Color;
+use alloc::string::ToString;
+use alloc::vec::Vec;
+use core::cmp::Ordering;
+
+/// LZWの符号の最大の長さ
+const MAX_CODE_SIZE: u32 = 12;
+
+/// インターレースの各パスで読む行の開始位置と間隔
+const INTERLACE_PASSES: [(usize, usize); 4] = [(0, 8), (4, 8), (2, 4), (1, 2)];
+
+fn too_short() -> Error {
+    Error::UnexpectedInput("gif data is too short".to_string())
+}
+
+struct Reader<'a> {
+    data: &'a [u8],
+    position: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn read_u8(&mut self) -> Result<u8, Error> {
+        let byte = *self.data.get(self.position).ok_or_else(too_short)?;
+        self.position += 1;
+        Ok(byte)
+    }
+
+    fn read_u16(&mut self) -> Result<u16, Error> {
+        Ok(self.read_u8()? as u16 | (self.read_u8()? as u16) << 8)
+    }
+
+    fn read_bytes(&mut self, length: usize) -> Result<&'a [u8], Error> {
+        let bytes = self
+            .data
+            .get(self.position..self.position + length)
+            .ok_or_else(too_short)?;
+        self.position += length;
+        Ok(bytes)
+    }
+
+    /// 長さ0のブロックで終わるサブブロックの列をつなげる
+    fn read_sub_blocks(&mut self) -> Result<Vec<u8>, Error> {
+        let mut data = Vec::new();
+        loop {
+            let length = self.read_u8()? as usize;
+            if length == 0 {
+                return Ok(data);
+            }
+            data.extend_from_slice(self.read_bytes(length)?);
+        }
+    }
+
+    /// `packed`の下位3ビットで大きさが決まるカラーテーブル
+    fn read_color_table(&mut self, packed: u8) -> Result<Vec<Color>, Error> {
+        let count = 2usize << (packed & 0x07);
+        Ok(self
+            .read_bytes(count * 3)?
+            .chunks_exact(3)
+            .map(|c| Color::new(c[0], c[1], c[2], 255))
+            .collect())
+    }
+}
+
+/// GIF画像の最初のフレームをデコードする
+/// フレームの外側と透明色のピクセルは透明になる
+pub fn decode(data: &[u8]) -> Result<Image, Error> {
+    if !data.starts_with(b"GIF87a") && !data.starts_with(b"GIF89a") {
+        return Err(Error::UnexpectedInput("invalid gif signature".to_string()));
+    }
+    let mut reader = Reader { data, position: 6 };
+
+    // 論理画面記述子
+    let screen_width = reader.read_u16()?;
+    let screen_height = reader.read_u16()?;
+    let packed = reader.read_u8()?;
+    let _background = reader.read_u8()?;
+    let _aspect_ratio = reader.read_u8()?;
+    let global_colors = if packed & 0x80 != 0 {
+        reader.read_color_table(packed)?
+    } else {
+        Vec::new()
+    };
+
+    let mut transparent_index = None;
+    loop {
+        match reader.read_u8()? {
+            // 拡張ブロック
+            0x21 => {
+                let label = reader.read_u8()?;
+                let body = reader.read_sub_blocks()?;
+                // 画像制御拡張で、透明にする色の番号を指定する
+                if label == 0xf9 && body.len() >= 4 && body[0] & 0x01 != 0 {
+                    transparent_index = Some(body[3]);
+                }
+            }
+            // イメージ記述子
+            0x2c => {
+                let left = reader.read_u16()? as i64;
+                let top = reader.read_u16()? as i64;
+                let width = reader.read_u16()? as usize;
+                let height = reader.read_u16()? as usize;
+                let packed = reader.read_u8()?;
+                let colors = if packed & 0x80 != 0 {
+                    reader.read_color_table(packed)?
+                } else {
+                    global_colors
+                };
+                let interlaced = packed & 0x40 != 0;
+
+                let min_code_size = reader.read_u8()? as u32;
+                let indices = lzw_decode(&reader.read_sub_blocks()?, min_code_size)?;
+
+                let mut image = Image::new(screen_width as u32, screen_height as u32)?;
+                let rows = interlaced_rows(height, interlaced);
+                for (i, index) in indices.iter().take(width * height).enumerate() {
+                    if Some(*index) == transparent_index {
+                        continue;
+                    }
+                    let color = colors
+                        .get(*index as usize)
+                        .copied()
+                        .unwrap_or(Color::transparent());
+                    let x = left + (i % width) as i64;
+                    let y = top + rows[i / width] as i64;
+                    image.set_pixel(x, y, color);
+                }
+                return Ok(image);
+            }
+            0x3b => return Err(Error::UnexpectedInput("gif has no image".to_string())),
+            _ => return Err(Error::UnexpectedInput("invalid gif block".to_string())),
+        }
+    }
+}
+
+/// データの`i`番目の行が表示される行
+fn interlaced_rows(height: usize, interlaced: bool) -> Vec<usize> {
+    if !interlaced {
+        return (0..height).collect();
+    }
+    INTERLACE_PASSES
+        .iter()
+        .flat_map(|(start, step)| (*start..height).step_by(*step))
+        .collect()
+}
+
+/// 下位のビットから順に読んだLZWの符号を展開する
+fn lzw_decode(data: &[u8], min_code_size: u32) -> Result<Vec<u8>, Error> {
+    if !(1..MAX_CODE_SIZE).contains(&min_code_size) {
+        return Err(Error::UnexpectedInput(
+            "invalid gif lzw code size".to_string(),
+        ));
+    }
+    let clear = 1u16 << min_code_size;
+    let end = clear + 1;
+
+    // 辞書の各項目は、1つ前の項目に1バイトを加えたもの
+    let mut prefix = [0u16; 1 << MAX_CODE_SIZE];
+    let mut suffix = [0u8; 1 << MAX_CODE_SIZE];
+    let mut first = [0u8; 1 << MAX_CODE_SIZE];
+    let mut lengths = [0u16; 1 << MAX_CODE_SIZE];
+    for code in 0..clear {
+        suffix[code as usize] = code as u8;
+        first[code as usize] = code as u8;
+        lengths[code as usize] = 1;
+    }
+
+    let mut output = Vec::new();
+    let mut code_size = min_code_size + 1;
+    let mut next = end + 1;
+    let mut previous: Option<u16> = None;
+    let mut bits: u32 = 0;
+    let mut bit_count = 0;
+    let mut bytes = data.iter();
+
+    loop {
+        while bit_count < code_size {
+            match bytes.next() {
+                Some(byte) => {
+                    bits |= (*byte as u32) << bit_count;
+                    bit_count += 8;
+                }
+                // 終了の符号がなくても、読めたところまでを使う
+                None => return Ok(output),
+            }
+        }
+        let code = (bits & ((1 << code_size) - 1)) as u16;
+        bits >>= code_size;
+        bit_count -= code_size;
+
+        if code == clear {
+            code_size = min_code_size + 1;
+            next = end + 1;
+            previous = None;
+            continue;
+        }
+        if code == end {
+            return Ok(output);
+        }
+
+        let previous_code = match previous {
+            Some(p) => p,
+            None => {
+                if code >= clear {
+                    return Err(Error::UnexpectedInput("invalid gif lzw code".to_string()));
+                }
+                output.push(code as u8);
+                previous = Some(code);
+                continue;
+            }
+        };
+
+        // 辞書にまだない符号は、直前の項目にその先頭のバイトを加えたもの
+        let entry = match code.cmp(&next) {
+            Ordering::Less => code,
+            Ordering::Equal => previous_code,
+            Ordering::Greater => {
+                return Err(Error::UnexpectedInput("invalid gif lzw code".to_string()))
+            }
+        };
+        let start = output.len();
+        output.resize(start + lengths[entry as usize] as usize, 0);
+        let mut c = entry;
+        for i in (start..output.len()).rev() {
+            output[i] = suffix[c as usize];
+            c = prefix[c as usize];
+        }
+        if code == next {
+            output.push(first[previous_code as usize]);
+        }
+
+        if (next as usize) < (1 << MAX_CODE_SIZE) {
+            let n = next as usize;
+            prefix[n] = previous_code;
+            suffix[n] = first[entry as usize];
+            first[n] = first[previous_code as usize];
+            lengths[n] = lengths[previous_code as usize] + 1;
+            next += 1;
+            if next == 1 << code_size && code_size < MAX_CODE_SIZE {
+                code_size += 1;
+            }
+        }
+        previous = Some(code);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_transparent_index() {
+        // 3x2で、最後のピクセルは透明色
+        let data = [
+            0x47, 0x49, 0x46, 0x38, 0x39, 0x61, 0x03, 0x00, 0x02, 0x00, 0x81, 0x00, 0x00, 0x00,
+            0x00, 0x00, 0xff, 0xff, 0xff, 0xff, 0x00, 0x00, 0x00, 0x00, 0xff, 0x21, 0xf9, 0x04,
+            0x01, 0x00, 0x00, 0x03, 0x00, 0x2c, 0x00, 0x00, 0x00, 0x00, 0x03, 0x00, 0x02, 0x00,
+            0x00, 0x02, 0x03, 0x44, 0x0c, 0x53, 0x00, 0x3b,
+        ];
+        let image = decode(&data).expect("failed to decode gif");
+        assert_eq!(3, image.width());
+        assert_eq!(2, image.height());
+        let black = Color::new(0, 0, 0, 255);
+        let white = Color::new(255, 255, 255, 255);
+        assert_eq!(black, image.pixel(0, 0));
+        assert_eq!(white, image.pixel(1, 0));
+        assert_eq!(black, image.pixel(2, 0));
+        assert_eq!(white, image.pixel(0, 1));
+        assert_eq!(black, image.pixel(1, 1));
+        assert_eq!(Color::transparent(), image.pixel(2, 1));
+    }
+
+    #[test]
+    fn test_interlaced() {
+        // データは0, 4, 2, 1, 3行目の順に並ぶ
+        let data = [
+            0x47, 0x49, 0x46, 0x38, 0x39, 0x61, 0x01, 0x00, 0x05, 0x00, 0x81, 0x00, 0x00, 0x00,
+            0x00, 0x00, 0xff, 0xff, 0xff, 0xff, 0x00, 0x00, 0x00, 0x00, 0xff, 0x2c, 0x00, 0x00,
+            0x00, 0x00, 0x01, 0x00, 0x05, 0x00, 0x40, 0x02, 0x03, 0x44, 0x34, 0x50, 0x00, 0x3b,
+        ];
+        let image = decode(&data).expect("failed to decode gif");
+        assert_eq!(Color::new(0, 0, 0, 255), image.pixel(0, 0));
+        assert_eq!(Color::new(0, 0, 255, 255), image.pixel(0, 1));
+        assert_eq!(Color::new(255, 0, 0, 255), image.pixel(0, 2));
+        assert_eq!(Color::new(0, 0, 0, 255), image.pixel(0, 3));
+        assert_eq!(Color::new(255, 255, 255, 255), image.pixel(0, 4));
+    }
+
+    #[test]
+    fn test_frame_smaller_than_screen() {
+        // 4x4の画面の(1, 1)に2x2のフレームを置く
+        let data = [
+            0x47, 0x49, 0x46, 0x38, 0x39, 0x61, 0x04, 0x00, 0x04, 0x00, 0x81, 0x00, 0x00, 0x00,
+            0x00, 0x00, 0xff, 0xff, 0xff, 0xff, 0x00, 0x00, 0x00, 0x00, 0xff, 0x2c, 0x01, 0x00,
+            0x01, 0x00, 0x02, 0x00, 0x02, 0x00, 0x00, 0x02, 0x02, 0x94, 0x55, 0x00, 0x3b,
+        ];
+        let image = decode(&data).expect("failed to decode gif");
+        assert_eq!(4, image.width());
+        assert_eq!(4, image.height());
+        assert_eq!(Color::transparent(), image.pixel(0, 0));
+        assert_eq!(Color::new(255, 0, 0, 255), image.pixel(1, 1));
+        assert_eq!(Color::new(255, 0, 0, 255), image.pixel(2, 2));
+        assert_eq!(Color::transparent(), image.pixel(3, 3));
+    }
+
+    #[test]
+    fn test_invalid_gif() {
+        assert!(decode(b"GIF90a").is_err());
+        assert!(decode(b"GIF89a\x01\x00").is_err());
+    }
+}
@@ -0,0 +1,382 @@
+//! zlib形式とDEFLATE形式のデータの展開
+//! https://www.rfc-editor.org/rfc/rfc1950
+//! https://www.rfc-editor.org/rfc/rfc1951
+
+use crate::error::Error;
+use alloc::format;
+use alloc::string::ToString;
+use alloc::vec;
+use alloc::vec::Vec;
+
+/// ハフマン符号の最大の長さ
+const MAX_BITS: usize = 15;
+
+/// 長さの符号(257から285)が表す長さの基準値と追加ビット数
+const LENGTH_BASE: [u16; 29] = [
+    3, 4, 5, 6, 7, 8, 9, 10, 11, 13, 15, 17, 19, 23, 27, 31, 35, 43, 51, 59, 67, 83, 99, 115, 131,
+    163, 195, 227, 258,
+];
+const LENGTH_EXTRA: [u8; 29] = [
+    0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2, 3, 3, 3, 3, 4, 4, 4, 4, 5, 5, 5, 5, 0,
+];
+
+/// 距離の符号(0から29)が表す距離の基準値と追加ビット数
+const DISTANCE_BASE: [u16; 30] = [
+    1, 2, 3, 4, 5, 7, 9, 13, 17, 25, 33, 49, 65, 97, 129, 193, 257, 385, 513, 769, 1025, 1537,
+    2049, 3073, 4097, 6145, 8193, 12289, 16385, 24577,
+];
+const DISTANCE_EXTRA: [u8; 30] = [
+    0, 0, 0, 0, 1, 1, 2, 2, 3, 3, 4, 4, 5, 5, 6, 6, 7, 7, 8, 8, 9, 9, 10, 10, 11, 11, 12, 12, 13,
+    13,
+];
+
+/// 符号の長さを表す符号の長さが並ぶ順序
+const CODE_LENGTH_ORDER: [usize; 19] = [
+    16, 17, 18, 0, 8, 7, 9, 6, 10, 5, 11, 4, 12, 3, 13, 2, 14, 1, 15,
+];
+
+/// 下位のビットから順に読む
+struct BitReader<'a> {
+    data: &'a [u8],
+    position: usize,
+    bit: u32,
+}
+
+impl<'a> BitReader<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        Self {
+            data,
+            position: 0,
+            bit: 0,
+        }
+    }
+
+    fn read_bit(&mut self) -> Result<u32, Error> {
+        let byte = self
+            .data
+            .get(self.position)
+            .ok_or_else(|| Error::UnexpectedInput("deflate data is too short".to_string()))?;
+        let value = (*byte as u32 >> self.bit) & 1;
+        self.bit += 1;
+        if self.bit == 8 {
+            self.bit = 0;
+            self.position += 1;
+        }
+        Ok(value)
+    }
+
+    fn read_bits(&mut self, count: u32) -> Result<u32, Error> {
+        let mut value = 0;
+        for i in 0..count {
+            value |= self.read_bit()? << i;
+        }
+        Ok(value)
+    }
+
+    /// 次のバイトの境界まで読み飛ばす
+    fn align_to_byte(&mut self) {
+        if self.bit != 0 {
+            self.bit = 0;
+            self.position += 1;
+        }
+    }
+
+    fn read_bytes(&mut self, length: usize) -> Result<&'a [u8], Error> {
+        let bytes = self
+            .data
+            .get(self.position..self.position + length)
+            .ok_or_else(|| Error::UnexpectedInput("deflate data is too short".to_string()))?;
+        self.position += length;
+        Ok(bytes)
+    }
+}
+
+/// 符号の長さから作る正規ハフマン符号
+struct Huffman {
+    /// 長さごとの符号の数
+    counts: [u16; MAX_BITS + 1],
+    /// 符号の短い順、同じ長さでは値の小さい順に並べた値
+    symbols: Vec<u16>,
+}
+
+impl Huffman {
+    fn new(lengths: &[u8]) -> Self {
+        let mut counts = [0u16; MAX_BITS + 1];
+        for &l in lengths {
+            counts[l as usize] += 1;
+        }
+        counts[0] = 0;
+
+        // 長さごとの先頭の位置
+        let mut offsets = [0u16; MAX_BITS + 2];
+        for len in 1..=MAX_BITS {
+            offsets[len + 1] = offsets[len] + counts[len];
+        }
+        let mut symbols = vec![0; offsets[MAX_BITS + 1] as usize];
+        for (symbol, &l) in lengths.iter().enumerate() {
+            if l != 0 {
+                symbols[offsets[l as usize] as usize] = symbol as u16;
+                offsets[l as usize] += 1;
+            }
+        }
+
+        Self { counts, symbols }
+    }
+
+    /// 1ビットずつ読み、長さごとの符号の範囲に入るかを調べる
+    fn decode(&self, reader: &mut BitReader) -> Result<u16, Error> {
+        let mut code: i32 = 0;
+        let mut first: i32 = 0;
+        let mut index: i32 = 0;
+        for len in 1..=MAX_BITS {
+            code |= reader.read_bit()? as i32;
+            let count = self.counts[len] as i32;
+            if code - first < count {
+                return Ok(self.symbols[(index + code - first) as usize]);
+            }
+            index += count;
+            first = (first + count) << 1;
+            code <<= 1;
+        }
+        Err(Error::UnexpectedInput(
+            "invalid huffman code in deflate data".to_string(),
+        ))
+    }
+}
+
+/// zlib形式のデータを展開する
+pub fn zlib_decompress(data: &[u8]) -> Result<Vec<u8>, Error> {
+    if data.len() < 6 {
+        return Err(Error::UnexpectedInput("zlib data is too short".to_string()));
+    }
+    let cmf = data[0];
+    let flg = data[1];
+    if cmf & 0x0f != 8 || ((cmf as u16) << 8 | flg as u16) % 31 != 0 {
+        return Err(Error::UnexpectedInput("invalid zlib header".to_string()));
+    }
+    if flg & 0x20 != 0 {
+        return Err(Error::UnexpectedInput(
+            "zlib preset dictionary is not supported".to_string(),
+        ));
+    }
+
+    let output = inflate(&data[2..])?;
+
+    let expected = data[data.len() - 4..]
+        .iter()
+        .fold(0u32, |acc, b| (acc << 8) | *b as u32);
+    let actual = adler32(&output);
+    if expected != actual {
+        return Err(Error::UnexpectedInput(format!(
+            "zlib checksum mismatch: expected {:#x}, but got {:#x}",
+            expected, actual
+        )));
+    }
+    Ok(output)
+}
+
+fn adler32(data: &[u8]) -> u32 {
+    let mut a: u32 = 1;
+    let mut b: u32 = 0;
+    for &byte in data {
+        a = (a + byte as u32) % 65521;
+        b = (b + a) % 65521;
+    }
+    (b << 16) | a
+}
+
+/// DEFLATE形式のデータを展開する
+pub fn inflate(data: &[u8]) -> Result<Vec<u8>, Error> {
+    let mut reader = BitReader::new(data);
+    let mut output = Vec::new();
+
+    loop {
+        let last = reader.read_bit()? == 1;
+        match reader.read_bits(2)? {
+            0 => inflate_stored(&mut reader, &mut output)?,
+            1 => {
+                let (literal, distance) = fixed_huffman();
+                inflate_block(&mut reader, &mut output, &literal, &distance)?;
+            }
+            2 => {
+                let (literal, distance) = dynamic_huffman(&mut reader)?;
+                inflate_block(&mut reader, &mut output, &literal, &distance)?;
+            }
+            _ => {
+                return Err(Error::UnexpectedInput(
+                    "invalid deflate block type".to_string(),
+                ))
+            }
+        }
+        if last {
+            return Ok(output);
+        }
+    }
+}
+
+/// 圧縮されていないブロック
+fn inflate_stored(reader: &mut BitReader, output: &mut Vec<u8>) -> Result<(), Error> {
+    reader.align_to_byte();
+    let header = reader.read_bytes(4)?;
+    let length = u16::from_le_bytes([header[0], header[1]]);
+    let complement = u16::from_le_bytes([header[2], header[3]]);
+    if length != !complement {
+        return Err(Error::UnexpectedInput(
+            "invalid stored block length".to_string(),
+        ));
+    }
+    output.extend_from_slice(reader.read_bytes(length as usize)?);
+    Ok(())
+}
+
+/// 固定ハフマン符号
+fn fixed_huffman() -> (Huffman, Huffman) {
+    let mut lengths = [0u8; 288];
+    for (i, l) in lengths.iter_mut().enumerate() {
+        *l = match i {
+            0..=143 => 8,
+            144..=255 => 9,
+            256..=279 => 7,
+            _ => 8,
+        };
+    }
+    (Huffman::new(&lengths), Huffman::new(&[5; 30]))
+}
+
+/// ブロックの先頭に書かれた動的ハフマン符号
+fn dynamic_huffman(reader: &mut BitReader) -> Result<(Huffman, Huffman), Error> {
+    let literal_count = reader.read_bits(5)? as usize + 257;
+    let distance_count = reader.read_bits(5)? as usize + 1;
+    let code_length_count = reader.read_bits(4)? as usize + 4;
+
+    let mut code_lengths = [0u8; 19];
+    for &i in CODE_LENGTH_ORDER.iter().take(code_length_count) {
+        code_lengths[i] = reader.read_bits(3)? as u8;
+    }
+    let code_length_huffman = Huffman::new(&code_lengths);
+
+    // 文字と長さの符号の長さと、距離の符号の長さは続けて書かれる
+    let mut lengths = Vec::with_capacity(literal_count + distance_count);
+    while lengths.len() < literal_count + distance_count {
+        let symbol = code_length_huffman.decode(reader)?;
+        let (value, repeat) = match symbol {
+            0..=15 => (symbol as u8, 1),
+            16 => {
+                let previous = *lengths.last().ok_or_else(|| {
+                    Error::UnexpectedInput("no code length to repeat".to_string())
+                })?;
+                (previous, 3 + reader.read_bits(2)?)
+            }
+            17 => (0, 3 + reader.read_bits(3)?),
+            _ => (0, 11 + reader.read_bits(7)?),
+        };
+        for _ in 0..repeat {
+            lengths.push(value);
+        }
+    }
+    if lengths.len() > literal_count + distance_count {
+        return Err(Error::UnexpectedInput(
+            "too many code lengths in deflate data".to_string(),
+        ));
+    }
+
+    Ok((
+        Huffman::new(&lengths[..literal_count]),
+        Huffman::new(&lengths[literal_count..]),
+    ))
+}
+
+/// ハフマン符号で圧縮されたブロック
+fn inflate_block(
+    reader: &mut BitReader,
+    output: &mut Vec<u8>,
+    literal: &Huffman,
+    distance: &Huffman,
+) -> Result<(), Error> {
+    loop {
+        let symbol = literal.decode(reader)? as usize;
+        match symbol {
+            0..=255 => output.push(symbol as u8),
+            256 => return Ok(()),
+            257..=285 => {
+                let index = symbol - 257;
+                let length = LENGTH_BASE[index] as usize
+                    + reader.read_bits(LENGTH_EXTRA[index] as u32)? as usize;
+                let index = distance.decode(reader)? as usize;
+                if index >= DISTANCE_BASE.len() {
+                    return Err(Error::UnexpectedInput(
+                        "invalid distance code in deflate data".to_string(),
+                    ));
+                }
+                let dist = DISTANCE_BASE[index] as usize
+                    + reader.read_bits(DISTANCE_EXTRA[index] as u32)? as usize;
+                if dist > output.len() {
+                    return Err(Error::UnexpectedInput(
+                        "distance is too far back in deflate data".to_string(),
+                    ));
+                }
+                // 長さが距離より長い場合は、コピーしたばかりのバイトを繰り返す
+                let start = output.len() - dist;
+                for i in 0..length {
+                    output.push(output[start + i]);
+                }
+            }
+            _ => {
+                return Err(Error::UnexpectedInput(
+                    "invalid literal/length code in deflate data".to_string(),
+                ))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stored_block() {
+        let data = [
+            0x78, 0x01, 0x01, 0x04, 0x00, 0xfb, 0xff, 0x73, 0x61, 0x62, 0x61, 0x04, 0x18, 0x01,
+            0x98,
+        ];
+        assert_eq!(Ok(b"saba".to_vec()), zlib_decompress(&data));
+    }
+
+    #[test]
+    fn test_fixed_huffman() {
+        // 繰り返しは距離3の参照になる
+        let data = [
+            0x78, 0xda, 0x4b, 0x4c, 0x4a, 0x4e, 0x84, 0x21, 0x00, 0x1d, 0xe0, 0x04, 0x99,
+        ];
+        assert_eq!(Ok(b"abcabcabcabc".to_vec()), zlib_decompress(&data));
+    }
+
+    #[test]
+    fn test_dynamic_huffman() {
+        let data = [
+            0x78, 0xda, 0xb5, 0xcb, 0xdb, 0x11, 0x80, 0x20, 0x10, 0x43, 0xd1, 0x56, 0x52, 0x81,
+            0x3d, 0x81, 0x02, 0xae, 0x02, 0xab, 0x3c, 0x85, 0xea, 0xdd, 0xb1, 0x07, 0x3f, 0x33,
+            0xf7, 0xa4, 0xec, 0x06, 0x77, 0xa5, 0xf5, 0x84, 0x4e, 0xdc, 0x23, 0x2c, 0x3f, 0x38,
+            0x6a, 0xb8, 0x32, 0xb8, 0x99, 0x84, 0x22, 0xd9, 0xab, 0x39, 0xb0, 0xb1, 0x5b, 0xbe,
+            0xf5, 0x0f, 0xbe, 0x94, 0xb8, 0x30, 0xa0, 0x05, 0x75, 0x2a, 0x3b, 0x2c, 0x35, 0x23,
+            0x69, 0x9a, 0x08, 0x4f, 0x77, 0xe5, 0x24, 0x5f, 0x97, 0x97, 0x17, 0xe8, 0xbb, 0x3f,
+            0x8d,
+        ];
+        let mut expected = b"the quick brown fox jumps over the lazy dog. ".repeat(3);
+        expected.extend_from_slice(b"pack my box with five dozen liquor jugs.");
+        assert_eq!(Ok(expected), zlib_decompress(&data));
+    }
+
+    #[test]
+    fn test_invalid_data() {
+        // チェックサムが合わない
+        let data = [
+            0x78, 0x01, 0x01, 0x04, 0x00, 0xfb, 0xff, 0x73, 0x61, 0x62, 0x61, 0x04, 0x18, 0x01,
+            0x99,
+        ];
+        assert!(zlib_decompress(&data).is_err());
+        // 途中で終わる
+        assert!(zlib_decompress(&[0x78, 0xda, 0x4b, 0x4c, 0x4a, 0x4e]).is_err());
+    }
+}
@@ -0,0 +1,739 @@
+//! ベースラインJPEG画像のデコード
+//! https://www.w3.org/Graphics/JPEG/itu-t81.pdf
+//!
+//! ハフマン符号を使うシーケンシャルDCT方式(SOF0とSOF1)だけをサポートする
+//! プログレッシブ方式と算術符号は読めない
+
+use crate::error::Error;
+use crate::image::Image;
+use crate::renderer::layout::computed_style::Color;
+use alloc::format;
+use alloc::string::ToString;
+use alloc::vec;
+use alloc::vec::Vec;
+
+/// ジグザグの順序で`k`番目の係数の、8x8ブロックの中での位置
+const ZIGZAG: [usize; 64] = [
+    0, 1, 8, 16, 9, 2, 3, 10, 17, 24, 32, 25, 18, 11, 4, 5, 12, 19, 26, 33, 40, 48, 41, 34, 27, 20,
+    13, 6, 7, 14, 21, 28, 35, 42, 49, 56, 57, 50, 43, 36, 29, 22, 15, 23, 30, 37, 44, 51, 58, 59,
+    52, 45, 38, 31, 39, 46, 53, 60, 61, 54, 47, 55, 62, 63,
+];
+
+/// cos(kπ/16) (kは0から8)
+const COS_16: [f32; 9] = [
+    1.0,
+    0.980_785_3,
+    0.923_879_5,
+    0.831_469_6,
+    core::f32::consts::FRAC_1_SQRT_2,
+    0.555_570_2,
+    0.382_683_43,
+    0.195_090_32,
+    0.0,
+];
+
+/// 8ビット精度のDC成分の差分と、AC成分の値のビット数の上限
+const MAX_DC_SIZE: u32 = 11;
+const MAX_AC_SIZE: u32 = 10;
+
+fn too_short() -> Error {
+    Error::UnexpectedInput("jpeg data is too short".to_string())
+}
+
+fn read_u16(data: &[u8], offset: usize) -> Result<u16, Error> {
+    let bytes = data.get(offset..offset + 2).ok_or_else(too_short)?;
+    Ok(u16::from_be_bytes([bytes[0], bytes[1]]))
+}
+
+/// DHTセグメントで定義されるハフマン符号
+#[derive(Debug, Clone, Default)]
+struct HuffmanTable {
+    /// 長さ(1から16ビット)ごとの符号の数
+    counts: [u8; 16],
+    /// 符号の短い順に並べた値
+    symbols: Vec<u8>,
+}
+
+impl HuffmanTable {
+    /// 上位のビットから1ビットずつ読み、長さごとの符号の範囲に入るかを調べる
+    fn decode(&self, reader: &mut BitReader) -> Result<u8, Error> {
+        let mut code: i32 = 0;
+        let mut first: i32 = 0;
+        let mut index: i32 = 0;
+        for count in self.counts {
+            code |= reader.read_bit() as i32;
+            let count = count as i32;
+            if code - first < count {
+                return self
+                    .symbols
+                    .get((index + code - first) as usize)
+                    .copied()
+                    .ok_or_else(invalid_code);
+            }
+            index += count;
+            first = (first + count) << 1;
+            code <<= 1;
+        }
+        Err(invalid_code())
+    }
+}
+
+fn invalid_code() -> Error {
+    Error::UnexpectedInput("invalid huffman code in jpeg data".to_string())
+}
+
+/// エントロピー符号化されたデータを上位のビットから読む
+/// 0xFFの後の0x00は取り除き、マーカーに達した後は0を返す
+struct BitReader<'a> {
+    data: &'a [u8],
+    position: usize,
+    byte: u8,
+    bits_left: u32,
+}
+
+impl<'a> BitReader<'a> {
+    fn new(data: &'a [u8], position: usize) -> Self {
+        Self {
+            data,
+            position,
+            byte: 0,
+            bits_left: 0,
+        }
+    }
+
+    fn read_bit(&mut self) -> u32 {
+        if self.bits_left == 0 {
+            self.byte = match self.data.get(self.position) {
+                Some(0xff) => match self.data.get(self.position + 1) {
+                    Some(0x00) => {
+                        self.position += 2;
+                        0xff
+                    }
+                    _ => 0,
+                },
+                Some(b) => {
+                    self.position += 1;
+                    *b
+                }
+                None => 0,
+            };
+            self.bits_left = 8;
+        }
+        self.bits_left -= 1;
+        (self.byte as u32 >> self.bits_left) & 1
+    }
+
+    fn receive(&mut self, count: u32) -> u32 {
+        let mut value = 0;
+        for _ in 0..count {
+            value = (value << 1) | self.read_bit();
+        }
+        value
+    }
+
+    /// `count`ビットの値を符号付きの値に戻す
+    /// 壊れたデータでは`count`が`max`を超えることがあり、その場合はエラーにする
+    fn receive_extend(&mut self, count: u32, max: u32) -> Result<i32, Error> {
+        if count > max {
+            return Err(Error::UnexpectedInput(format!(
+                "invalid jpeg coefficient size: {}",
+                count
+            )));
+        }
+        if count == 0 {
+            return Ok(0);
+        }
+        let value = self.receive(count) as i32;
+        if value < 1 << (count - 1) {
+            Ok(value - (1 << count) + 1)
+        } else {
+            Ok(value)
+        }
+    }
+
+    /// リスタートマーカーを読み飛ばし、次のバイトから読み直す
+    fn restart(&mut self) {
+        self.bits_left = 0;
+        if self.data.get(self.position) == Some(&0xff)
+            && matches!(self.data.get(self.position + 1), Some(0xd0..=0xd7))
+        {
+            self.position += 2;
+        }
+    }
+
+    /// スキャンの後にある、次のマーカーの位置
+    fn next_marker(&self) -> usize {
+        let mut position = self.position;
+        while position + 1 < self.data.len() {
+            if self.data[position] == 0xff
+                && !matches!(self.data[position + 1], 0x00 | 0xd0..=0xd7 | 0xff)
+            {
+                return position;
+            }
+            position += 1;
+        }
+        self.data.len()
+    }
+}
+
+/// 色の成分
+#[derive(Debug, Clone)]
+struct Component {
+    id: u8,
+    h: usize,
+    v: usize,
+    quant_table: usize,
+    dc_table: usize,
+    ac_table: usize,
+    /// 直前のブロックのDC成分
+    prediction: i32,
+    /// MCUの数に合わせた、ブロック単位の大きさのサンプルの配列
+    blocks_per_line: usize,
+    blocks_per_column: usize,
+    samples: Vec<u8>,
+}
+
+impl Component {
+    fn line_width(&self) -> usize {
+        self.blocks_per_line * 8
+    }
+}
+
+/// SOFセグメントで定義されるフレーム
+struct Frame {
+    width: usize,
+    height: usize,
+    components: Vec<Component>,
+    max_h: usize,
+    max_v: usize,
+    mcus_per_line: usize,
+    mcus_per_column: usize,
+}
+
+/// ベースラインJPEG画像をデコードする
+pub fn decode(data: &[u8]) -> Result<Image, Error> {
+    if !data.starts_with(&[0xff, 0xd8]) {
+        return Err(Error::UnexpectedInput("invalid jpeg signature".to_string()));
+    }
+
+    let mut quant_tables = [[0u16; 64]; 4];
+    let mut dc_tables: [HuffmanTable; 4] = Default::default();
+    let mut ac_tables: [HuffmanTable; 4] = Default::default();
+    let mut restart_interval = 0;
+    let mut frame: Option<Frame> = None;
+    // Adobeのマーカーで、成分がYCbCrでなくRGBであることが示される場合がある
+    let mut adobe_transform: Option<u8> = None;
+    let mut position = 2;
+
+    loop {
+        if data.get(position) != Some(&0xff) {
+            return Err(Error::UnexpectedInput(format!(
+                "jpeg marker is expected at {}",
+                position
+            )));
+        }
+        let marker = *data.get(position + 1).ok_or_else(too_short)?;
+        position += 2;
+        match marker {
+            // 詰め物
+            0xff => {
+                position -= 1;
+                continue;
+            }
+            // EOI
+            0xd9 => break,
+            _ => {}
+        }
+
+        let length = read_u16(data, position)? as usize;
+        let segment = data
+            .get(position + 2..position + length)
+            .ok_or_else(too_short)?;
+        position += length;
+
+        match marker {
+            // SOF0(ベースライン)とSOF1(拡張シーケンシャル)
+            0xc0 | 0xc1 => frame = Some(read_frame(segment)?),
+            0xc2 | 0xc3 | 0xc5..=0xc7 | 0xc9..=0xcb | 0xcd..=0xcf => {
+                return Err(Error::UnexpectedInput(format!(
+                    "unsupported jpeg process: SOF{}",
+                    marker - 0xc0
+                )))
+            }
+            0xc4 => read_huffman_tables(segment, &mut dc_tables, &mut ac_tables)?,
+            0xdb => read_quant_tables(segment, &mut quant_tables)?,
+            0xdd => restart_interval = read_u16(segment, 0)? as usize,
+            0xee => {
+                if segment.starts_with(b"Adobe") && segment.len() >= 12 {
+                    adobe_transform = Some(segment[11]);
+                }
+            }
+            0xda => {
+                let frame = frame
+                    .as_mut()
+                    .ok_or_else(|| Error::UnexpectedInput("jpeg SOF is missing".to_string()))?;
+                let tables = Tables {
+                    quant: &quant_tables,
+                    dc: &dc_tables,
+                    ac: &ac_tables,
+                    idct: idct_table(),
+                };
+                position = decode_scan(data, position, segment, frame, &tables, restart_interval)?;
+            }
+            // APPnやコメントなどは読み飛ばす
+            _ => {}
+        }
+    }
+
+    let frame = frame.ok_or_else(|| Error::UnexpectedInput("jpeg SOF is missing".to_string()))?;
+    let rgb = match adobe_transform {
+        Some(0) => true,
+        Some(_) => false,
+        None => {
+            let ids: Vec<u8> = frame.components.iter().map(|c| c.id).collect();
+            ids == b"RGB"
+        }
+    };
+    to_image(&frame, rgb)
+}
+
+fn read_frame(segment: &[u8]) -> Result<Frame, Error> {
+    let precision = *segment.first().ok_or_else(too_short)?;
+    if precision != 8 {
+        return Err(Error::UnexpectedInput(format!(
+            "unsupported jpeg sample precision: {}",
+            precision
+        )));
+    }
+    let height = read_u16(segment, 1)? as usize;
+    let width = read_u16(segment, 3)? as usize;
+    let count = *segment.get(5).ok_or_else(too_short)? as usize;
+    if width == 0 || height == 0 {
+        return Err(Error::UnexpectedInput("unsupported jpeg size".to_string()));
+    }
+    if count != 1 && count != 3 {
+        return Err(Error::UnexpectedInput(format!(
+            "unsupported number of jpeg components: {}",
+            count
+        )));
+    }
+
+    let mut components = Vec::new();
+    for i in 0..count {
+        let c = segment.get(6 + i * 3..9 + i * 3).ok_or_else(too_short)?;
+        let (h, v) = ((c[1] >> 4) as usize, (c[1] & 0x0f) as usize);
+        if !(1..=4).contains(&h) || !(1..=4).contains(&v) || c[2] > 3 {
+            return Err(Error::UnexpectedInput("invalid jpeg component".to_string()));
+        }
+        components.push(Component {
+            id: c[0],
+            h,
+            v,
+            quant_table: c[2] as usize,
+            dc_table: 0,
+            ac_table: 0,
+            prediction: 0,
+            blocks_per_line: 0,
+            blocks_per_column: 0,
+            samples: Vec::new(),
+        });
+    }
+
+    let max_h = components.iter().map(|c| c.h).max().unwrap_or(1);
+    let max_v = components.iter().map(|c| c.v).max().unwrap_or(1);
+    let mcus_per_line = width.div_ceil(8 * max_h);
+    let mcus_per_column = height.div_ceil(8 * max_v);
+    for c in &mut components {
+        c.blocks_per_line = mcus_per_line * c.h;
+        c.blocks_per_column = mcus_per_column * c.v;
+        c.samples = vec![0; c.blocks_per_line * c.blocks_per_column * 64];
+    }
+
+    Ok(Frame {
+        width,
+        height,
+        components,
+        max_h,
+        max_v,
+        mcus_per_line,
+        mcus_per_column,
+    })
+}
+
+fn read_huffman_tables(
+    segment: &[u8],
+    dc_tables: &mut [HuffmanTable; 4],
+    ac_tables: &mut [HuffmanTable; 4],
+) -> Result<(), Error> {
+    let mut offset = 0;
+    while offset < segment.len() {
+        let class_and_id = segment[offset];
+        let counts = segment.get(offset + 1..offset + 17).ok_or_else(too_short)?;
+        let total: usize = counts.iter().map(|c| *c as usize).sum();
+        let symbols = segment
+            .get(offset + 17..offset + 17 + total)
+            .ok_or_else(too_short)?;
+        let mut table = HuffmanTable::default();
+        table.counts.copy_from_slice(counts);
+        table.symbols = symbols.to_vec();
+
+        let id = (class_and_id & 0x0f) as usize;
+        if id > 3 {
+            return Err(Error::UnexpectedInput(
+                "invalid jpeg huffman table".to_string(),
+            ));
+        }
+        if class_and_id >> 4 == 0 {
+            dc_tables[id] = table;
+        } else {
+            ac_tables[id] = table;
+        }
+        offset += 17 + total;
+    }
+    Ok(())
+}
+
+/// 量子化テーブルはジグザグの順序のまま持つ
+fn read_quant_tables(segment: &[u8], quant_tables: &mut [[u16; 64]; 4]) -> Result<(), Error> {
+    let mut offset = 0;
+    while offset < segment.len() {
+        let precision_and_id = segment[offset];
+        let id = (precision_and_id & 0x0f) as usize;
+        if id > 3 {
+            return Err(Error::UnexpectedInput(
+                "invalid jpeg quantization table".to_string(),
+            ));
+        }
+        let wide = precision_and_id >> 4 != 0;
+        offset += 1;
+        for k in 0..64 {
+            quant_tables[id][k] = if wide {
+                read_u16(segment, offset + k * 2)?
+            } else {
+                *segment.get(offset + k).ok_or_else(too_short)? as u16
+            };
+        }
+        offset += if wide { 128 } else { 64 };
+    }
+    Ok(())
+}
+
+/// スキャンを読むときに使うテーブル
+struct Tables<'a> {
+    quant: &'a [[u16; 64]; 4],
+    dc: &'a [HuffmanTable; 4],
+    ac: &'a [HuffmanTable; 4],
+    idct: [[f32; 8]; 8],
+}
+
+impl Tables<'_> {
+    /// 8x8ブロックの係数を読み、逆DCTしたサンプルを成分に書き込む
+    fn decode_block(
+        &self,
+        reader: &mut BitReader,
+        c: &mut Component,
+        block_x: usize,
+        block_y: usize,
+    ) -> Result<(), Error> {
+        let mut coefficients = [0f32; 64];
+        let quant = &self.quant[c.quant_table];
+
+        let size = self.dc[c.dc_table].decode(reader)?;
+        let diff = reader.receive_extend(size as u32, MAX_DC_SIZE)?;
+        c.prediction = c
+            .prediction
+            .checked_add(diff)
+            .ok_or_else(|| Error::UnexpectedInput("jpeg dc coefficient overflows".to_string()))?;
+        coefficients[0] = c.prediction as f32 * quant[0] as f32;
+
+        let mut k = 1;
+        while k < 64 {
+            let rs = self.ac[c.ac_table].decode(reader)?;
+            let (run, size) = ((rs >> 4) as usize, (rs & 0x0f) as u32);
+            if size == 0 {
+                // ZRLは0が16個続くことを表し、それ以外はブロックの終わりを表す
+                if run == 15 {
+                    k += 16;
+                    continue;
+                }
+                break;
+            }
+            k += run;
+            if k >= 64 {
+                break;
+            }
+            coefficients[ZIGZAG[k]] =
+                (reader.receive_extend(size, MAX_AC_SIZE)? * quant[k] as i32) as f32;
+            k += 1;
+        }
+
+        let pixels = inverse_dct(&coefficients, &self.idct);
+        let line = c.line_width();
+        for (y, row) in pixels.chunks_exact(8).enumerate() {
+            let start = (block_y * 8 + y) * line + block_x * 8;
+            c.samples[start..start + 8].copy_from_slice(row);
+        }
+        Ok(())
+    }
+}
+
+/// SOSセグメントに続くスキャンを読み、次のマーカーの位置を返す
+fn decode_scan(
+    data: &[u8],
+    position: usize,
+    segment: &[u8],
+    frame: &mut Frame,
+    tables: &Tables,
+    restart_interval: usize,
+) -> Result<usize, Error> {
+    let count = *segment.first().ok_or_else(too_short)? as usize;
+    let mut scan = Vec::new();
+    for i in 0..count {
+        let s = segment.get(1 + i * 2..3 + i * 2).ok_or_else(too_short)?;
+        let index = frame
+            .components
+            .iter()
+            .position(|c| c.id == s[0])
+            .ok_or_else(|| Error::UnexpectedInput("unknown jpeg component".to_string()))?;
+        let c = &mut frame.components[index];
+        c.dc_table = (s[1] >> 4) as usize & 3;
+        c.ac_table = (s[1] & 0x0f) as usize & 3;
+        c.prediction = 0;
+        scan.push(index);
+    }
+
+    let mut reader = BitReader::new(data, position);
+    if scan.len() == 1 {
+        // 1つの成分だけのスキャンは、画像を覆うブロックを順に並べる
+        let c = &mut frame.components[scan[0]];
+        let width = (frame.width * c.h).div_ceil(frame.max_h).div_ceil(8);
+        let height = (frame.height * c.v).div_ceil(frame.max_v).div_ceil(8);
+        for i in 0..width * height {
+            if restart_interval > 0 && i > 0 && i % restart_interval == 0 {
+                reader.restart();
+                c.prediction = 0;
+            }
+            tables.decode_block(&mut reader, c, i % width, i / width)?;
+        }
+    } else {
+        // MCUごとに、各成分のh x v個のブロックが並ぶ
+        let mcus = frame.mcus_per_line * frame.mcus_per_column;
+        for m in 0..mcus {
+            if restart_interval > 0 && m > 0 && m % restart_interval == 0 {
+                reader.restart();
+                for &index in &scan {
+                    frame.components[index].prediction = 0;
+                }
+            }
+            let (mcu_x, mcu_y) = (m % frame.mcus_per_line, m / frame.mcus_per_line);
+            for &index in &scan {
+                let c = &mut frame.components[index];
+                for v in 0..c.v {
+                    for h in 0..c.h {
+                        tables.decode_block(&mut reader, c, mcu_x * c.h + h, mcu_y * c.v + v)?;
+                    }
+                }
+            }
+        }
+    }
+
+    Ok(reader.next_marker())
+}
+
+/// cos(mπ/16)
+fn cos_16(m: usize) -> f32 {
+    let m = m % 32;
+    let m = if m > 16 { 32 - m } else { m };
+    if m > 8 {
+        -COS_16[16 - m]
+    } else {
+        COS_16[m]
+    }
+}
+
+/// 逆DCTの係数。`[x][u]`はC(u)/2 * cos((2x+1)uπ/16)
+fn idct_table() -> [[f32; 8]; 8] {
+    let mut table = [[0f32; 8]; 8];
+    for (x, row) in table.iter_mut().enumerate() {
+        for (u, value) in row.iter_mut().enumerate() {
+            let c = if u == 0 { COS_16[4] } else { 1.0 };
+            *value = c / 2.0 * cos_16((2 * x + 1) * u);
+        }
+    }
+    table
+}
+
+/// 8x8ブロックの係数を、行と列に分けて逆DCTする
+fn inverse_dct(coefficients: &[f32; 64], table: &[[f32; 8]; 8]) -> [u8; 64] {
+    let mut rows = [0f32; 64];
+    for v in 0..8 {
+        for x in 0..8 {
+            rows[v * 8 + x] = (0..8).map(|u| table[x][u] * coefficients[v * 8 + u]).sum();
+        }
+    }
+
+    let mut pixels = [0u8; 64];
+    for y in 0..8 {
+        for x in 0..8 {
+            let value: f32 = (0..8).map(|v| table[y][v] * rows[v * 8 + x]).sum();
+            pixels[y * 8 + x] = clamp_sample(value + 128.0);
+        }
+    }
+    pixels
+}
+
+fn clamp_sample(value: f32) -> u8 {
+    // 四捨五入する
+    let value = (value + 0.5) as i32;
+    value.clamp(0, 255) as u8
+}
+
+/// 成分のサンプルを拡大して重ね、RGBの画像にする
+fn to_image(frame: &Frame, rgb: bool) -> Result<Image, Error> {
+    let mut image = Image::new(frame.width as u32, frame.height as u32)?;
+    let sample = |c: &Component, x: usize, y: usize| {
+        let sx = x * c.h / frame.max_h;
+        let sy = y * c.v / frame.max_v;
+        c.samples[sy * c.line_width() + sx] as f32
+    };
+
+    for y in 0..frame.height {
+        for x in 0..frame.width {
+            let color = match frame.components.as_slice() {
+                [gray] => {
+                    let v = sample(gray, x, y) as u8;
+                    Color::new(v, v, v, 255)
+                }
+                [a, b, c] if rgb => Color::new(
+                    sample(a, x, y) as u8,
+                    sample(b, x, y) as u8,
+                    sample(c, x, y) as u8,
+                    255,
+                ),
+                // JFIFのYCbCrからRGBへの変換
+                [luma, cb, cr] => {
+                    let l = sample(luma, x, y);
+                    let cb = sample(cb, x, y) - 128.0;
+                    let cr = sample(cr, x, y) - 128.0;
+                    Color::new(
+                        clamp_sample(l + 1.402 * cr),
+                        clamp_sample(l - 0.344_136 * cb - 0.714_136 * cr),
+                        clamp_sample(l + 1.772 * cb),
+                        255,
+                    )
+                }
+                _ => {
+                    return Err(Error::UnexpectedInput(
+                        "unsupported number of jpeg components".to_string(),
+                    ))
+                }
+            };
+            image.set_pixel(x as i64, y as i64, color);
+        }
+    }
+    Ok(image)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 各成分の差が`tolerance`以内か
+    fn assert_close(expected: (u8, u8, u8), actual: Color, tolerance: i16) {
+        let diff = |a: u8, b: u8| (a as i16 - b as i16).abs();
+        assert!(
+            diff(expected.0, actual.r()) <= tolerance
+                && diff(expected.1, actual.g()) <= tolerance
+                && diff(expected.2, actual.b()) <= tolerance
+                && actual.alpha() == 255,
+            "expected {:?}, but got {:?}",
+            expected,
+            actual
+        );
+    }
+
+    /// 16x8のグレー画像。左右のブロックの間にリスタートマーカーがある
+    fn grayscale_with_restart() -> [u8; 159] {
+        [
+            0xff, 0xd8, 0xff, 0xdb, 0x00, 0x43, 0x00, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01,
+            0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01,
+            0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01,
+            0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01,
+            0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01,
+            0x01, 0xff, 0xc0, 0x00, 0x0b, 0x08, 0x00, 0x08, 0x00, 0x10, 0x01, 0x01, 0x11, 0x00,
+            0xff, 0xc4, 0x00, 0x31, 0x00, 0x00, 0x00, 0x00, 0x0c, 0x00, 0x00, 0x00, 0x00, 0x00,
+            0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x01, 0x02, 0x03, 0x04, 0x05, 0x06,
+            0x07, 0x08, 0x09, 0x0a, 0x0b, 0x10, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+            0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0xff, 0xdd, 0x00, 0x04, 0x00,
+            0x01, 0xff, 0xda, 0x00, 0x08, 0x01, 0x01, 0x00, 0x00, 0x3f, 0x00, 0xa6, 0x3d, 0xff,
+            0xd0, 0xa9, 0x01, 0xff, 0xd9,
+        ]
+    }
+
+    #[test]
+    fn test_grayscale_with_restart() {
+        let data = grayscale_with_restart();
+        let image = decode(&data).expect("failed to decode jpeg");
+        assert_eq!(16, image.width());
+        assert_eq!(8, image.height());
+        assert_close((50, 50, 50), image.pixel(0, 0), 1);
+        assert_close((50, 50, 50), image.pixel(7, 7), 1);
+        assert_close((200, 200, 200), image.pixel(8, 0), 1);
+        assert_close((200, 200, 200), image.pixel(15, 7), 1);
+    }
+
+    #[test]
+    fn test_corrupted_coefficient_size() {
+        let data = grayscale_with_restart();
+        let dht = data.windows(2).position(|w| w == [0xff, 0xc4]).unwrap();
+        // DCのテーブルの12個の値と、ACのテーブルの1個の値
+        let dc_symbols = dht + 4 + 1 + 16;
+        let ac_symbol = dc_symbols + 12 + 1 + 16;
+
+        // DC成分の差分のビット数が32になっていても、範囲外のシフトで落ちない
+        let mut corrupted = data;
+        corrupted[dc_symbols..dc_symbols + 12].fill(32);
+        assert!(decode(&corrupted).is_err());
+        corrupted[dc_symbols..dc_symbols + 12].fill(MAX_DC_SIZE as u8 + 1);
+        assert!(decode(&corrupted).is_err());
+
+        let mut corrupted = data;
+        corrupted[ac_symbol] = MAX_AC_SIZE as u8 + 1;
+        assert!(decode(&corrupted).is_err());
+    }
+
+    #[test]
+    fn test_chroma_subsampling() {
+        // 4:2:0の16x16。輝度は8x8のブロックごとに違い、色差は1つだけ
+        let data = [
+            0xff, 0xd8, 0xff, 0xdb, 0x00, 0x43, 0x00, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01,
+            0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01,
+            0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01,
+            0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01,
+            0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01,
+            0x01, 0xff, 0xc0, 0x00, 0x11, 0x08, 0x00, 0x10, 0x00, 0x10, 0x03, 0x01, 0x22, 0x00,
+            0x02, 0x11, 0x00, 0x03, 0x11, 0x00, 0xff, 0xc4, 0x00, 0x31, 0x00, 0x00, 0x00, 0x00,
+            0x0c, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+            0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07, 0x08, 0x09, 0x0a, 0x0b, 0x10, 0x01, 0x00,
+            0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+            0x00, 0xff, 0xda, 0x00, 0x0c, 0x03, 0x01, 0x00, 0x02, 0x00, 0x03, 0x00, 0x00, 0x3f,
+            0x00, 0xa7, 0x7d, 0x3e, 0x04, 0xf8, 0x13, 0xe0, 0x4b, 0x3d, 0x52, 0x03, 0xff, 0xd9,
+        ];
+        let image = decode(&data).expect("failed to decode jpeg");
+        assert_eq!(16, image.width());
+        assert_eq!(16, image.height());
+        assert_close((161, 22, 0), image.pixel(0, 0), 2);
+        assert_close((221, 82, 53), image.pixel(15, 0), 2);
+        assert_close((255, 142, 113), image.pixel(0, 15), 2);
+        assert_close((255, 202, 173), image.pixel(15, 15), 2);
+    }
+
+    #[test]
+    fn test_progressive_is_unsupported() {
+        let data = [
+            0xff, 0xd8, 0xff, 0xc2, 0x00, 0x0b, 0x08, 0x00, 0x08, 0x00, 0x08, 0x01, 0x01, 0x11,
+            0x00,
+        ];
+        assert!(decode(&data).is_err());
+    }
+}
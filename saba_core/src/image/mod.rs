@@ -0,0 +1,106 @@
+//! 画像の読み込み
+//! ファイルの先頭のシグネチャで形式を判別し、RGBAのピクセルの配列にデコードする
+
+pub mod gif;
+pub mod inflate;
+pub mod jpeg;
+pub mod png;
+
+use crate::error::Error;
+use crate::renderer::layout::computed_style::Color;
+use alloc::collections::BTreeMap;
+use alloc::format;
+use alloc::rc::Rc;
+use alloc::string::String;
+use alloc::string::ToString;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::fmt;
+
+/// デコードする画像の幅と高さの上限
+const MAX_DIMENSION: u32 = 16384;
+
+/// デコードする画像のピクセル数の上限
+const MAX_PIXELS: u64 = 1 << 24;
+
+/// `<img>`要素の`src`属性の値と、読み込んだ画像
+pub type ImageMap = BTreeMap<String, Rc<Image>>;
+
+/// デコードした画像。ピクセルは左上から1行ずつ並ぶ
+#[derive(Clone, PartialEq, Eq)]
+pub struct Image {
+    width: i64,
+    height: i64,
+    pixels: Vec<Color>,
+}
+
+impl fmt::Debug for Image {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // ピクセルは多すぎるので表示しない
+        f.debug_struct("Image")
+            .field("width", &self.width)
+            .field("height", &self.height)
+            .finish()
+    }
+}
+
+impl Image {
+    /// 透明なピクセルで埋めた画像を作る
+    pub fn new(width: u32, height: u32) -> Result<Self, Error> {
+        if width == 0
+            || height == 0
+            || width > MAX_DIMENSION
+            || height > MAX_DIMENSION
+            || width as u64 * height as u64 > MAX_PIXELS
+        {
+            return Err(Error::UnexpectedInput(format!(
+                "unsupported image size: {}x{}",
+                width, height
+            )));
+        }
+        Ok(Self {
+            width: width as i64,
+            height: height as i64,
+            pixels: vec![Color::transparent(); width as usize * height as usize],
+        })
+    }
+
+    pub fn width(&self) -> i64 {
+        self.width
+    }
+
+    pub fn height(&self) -> i64 {
+        self.height
+    }
+
+    /// (x, y)のピクセルの色。画像の外側は透明になる
+    pub fn pixel(&self, x: i64, y: i64) -> Color {
+        if x < 0 || y < 0 || x >= self.width || y >= self.height {
+            return Color::transparent();
+        }
+        self.pixels[(y * self.width + x) as usize]
+    }
+
+    /// 画像の外側は無視する
+    pub fn set_pixel(&mut self, x: i64, y: i64, color: Color) {
+        if x < 0 || y < 0 || x >= self.width || y >= self.height {
+            return;
+        }
+        self.pixels[(y * self.width + x) as usize] = color;
+    }
+}
+
+/// PNG、GIF、JPEGの画像をデコードする
+pub fn decode(data: &[u8]) -> Result<Image, Error> {
+    if data.starts_with(&png::SIGNATURE) {
+        png::decode(data)
+    } else if data.starts_with(b"GIF87a") || data.starts_with(b"GIF89a") {
+        gif::decode(data)
+    } else if data.starts_with(&[0xff, 0xd8]) {
+        jpeg::decode(data)
+    } else {
+        Err(Error::UnexpectedInput(
+            "unsupported image format".to_string(),
+        ))
+    }
+}
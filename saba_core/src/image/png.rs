@@ -0,0 +1,431 @@
+//! PNG画像のデコード
+//! https://www.w3.org/TR/png/
+//!
+//! チャンクのCRCは確かめない
+
+use crate::error::Error;
+use crate::image::inflate::zlib_decompress;
+use crate::image::Image;
+use crate::renderer::layout::computed_style::Color;
+use alloc::format;
+use alloc::string::ToString;
+use alloc::vec;
+use alloc::vec::Vec;
+
+pub const SIGNATURE: [u8; 8] = [0x89, b'P', b'N', b'G', 0x0d, 0x0a, 0x1a, 0x0a];
+
+/// インターレースの各パスで読むピクセルの開始位置と間隔
+/// https://www.w3.org/TR/png/#8Interlace
+const ADAM7_PASSES: [(u32, u32, u32, u32); 7] = [
+    (0, 0, 8, 8),
+    (4, 0, 8, 8),
+    (0, 4, 4, 8),
+    (2, 0, 4, 4),
+    (0, 2, 2, 4),
+    (1, 0, 2, 2),
+    (0, 1, 1, 2),
+];
+
+fn read_u32(data: &[u8], offset: usize) -> Result<u32, Error> {
+    let bytes = data
+        .get(offset..offset + 4)
+        .ok_or_else(|| Error::UnexpectedInput(format!("png data is too short: {}", offset)))?;
+    Ok(u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+}
+
+/// ピクセルの表し方
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ColorType {
+    Grayscale,
+    Truecolor,
+    Indexed,
+    GrayscaleAlpha,
+    TruecolorAlpha,
+}
+
+impl ColorType {
+    fn from_u8(value: u8) -> Result<Self, Error> {
+        match value {
+            0 => Ok(ColorType::Grayscale),
+            2 => Ok(ColorType::Truecolor),
+            3 => Ok(ColorType::Indexed),
+            4 => Ok(ColorType::GrayscaleAlpha),
+            6 => Ok(ColorType::TruecolorAlpha),
+            _ => Err(Error::UnexpectedInput(format!(
+                "invalid png color type: {}",
+                value
+            ))),
+        }
+    }
+
+    fn channels(&self) -> u32 {
+        match self {
+            ColorType::Grayscale | ColorType::Indexed => 1,
+            ColorType::GrayscaleAlpha => 2,
+            ColorType::Truecolor => 3,
+            ColorType::TruecolorAlpha => 4,
+        }
+    }
+}
+
+/// IHDRチャンクと、色に関わるチャンクの内容
+struct Header {
+    width: u32,
+    height: u32,
+    bit_depth: u8,
+    color_type: ColorType,
+    interlaced: bool,
+    palette: Vec<Color>,
+    /// tRNSチャンクで指定された、透明にするグレーかRGBの値
+    transparent: Option<[u16; 3]>,
+}
+
+impl Header {
+    fn bits_per_pixel(&self) -> u32 {
+        self.color_type.channels() * self.bit_depth as u32
+    }
+
+    /// フィルタで左隣として参照するバイト数
+    fn filter_distance(&self) -> usize {
+        core::cmp::max(self.bits_per_pixel() / 8, 1) as usize
+    }
+
+    /// 幅`width`の行のバイト数。先頭のフィルタの種類は含めない
+    fn stride(&self, width: u32) -> usize {
+        (width as usize * self.bits_per_pixel() as usize).div_ceil(8)
+    }
+
+    /// 行の`index`番目の成分の値
+    fn sample(&self, row: &[u8], index: usize) -> u16 {
+        match self.bit_depth {
+            16 => (row[index * 2] as u16) << 8 | row[index * 2 + 1] as u16,
+            8 => row[index] as u16,
+            depth => {
+                let depth = depth as usize;
+                let bit = index * depth;
+                let shift = 8 - depth - bit % 8;
+                (row[bit / 8] as u16 >> shift) & ((1 << depth) - 1)
+            }
+        }
+    }
+
+    /// 成分の値を0から255に変換する
+    fn scale(&self, value: u16) -> u8 {
+        match self.bit_depth {
+            16 => (value >> 8) as u8,
+            8 => value as u8,
+            depth => (value as u32 * 255 / ((1 << depth) - 1)) as u8,
+        }
+    }
+
+    /// 行の`x`番目のピクセルの色
+    fn color(&self, row: &[u8], x: usize) -> Color {
+        let channels = self.color_type.channels() as usize;
+        let s = |i: usize| self.sample(row, x * channels + i);
+        match self.color_type {
+            ColorType::Grayscale => {
+                let v = s(0);
+                let alpha = match self.transparent {
+                    Some([t, _, _]) if t == v => 0,
+                    _ => 255,
+                };
+                let v = self.scale(v);
+                Color::new(v, v, v, alpha)
+            }
+            ColorType::Truecolor => {
+                let (r, g, b) = (s(0), s(1), s(2));
+                let alpha = match self.transparent {
+                    Some(t) if t == [r, g, b] => 0,
+                    _ => 255,
+                };
+                Color::new(self.scale(r), self.scale(g), self.scale(b), alpha)
+            }
+            ColorType::Indexed => self
+                .palette
+                .get(s(0) as usize)
+                .copied()
+                .unwrap_or(Color::transparent()),
+            ColorType::GrayscaleAlpha => {
+                let v = self.scale(s(0));
+                Color::new(v, v, v, self.scale(s(1)))
+            }
+            ColorType::TruecolorAlpha => Color::new(
+                self.scale(s(0)),
+                self.scale(s(1)),
+                self.scale(s(2)),
+                self.scale(s(3)),
+            ),
+        }
+    }
+}
+
+/// PNG画像をデコードする
+pub fn decode(data: &[u8]) -> Result<Image, Error> {
+    if !data.starts_with(&SIGNATURE) {
+        return Err(Error::UnexpectedInput("invalid png signature".to_string()));
+    }
+
+    let mut header: Option<Header> = None;
+    let mut compressed = Vec::new();
+    let mut offset = SIGNATURE.len();
+    loop {
+        let length = read_u32(data, offset)? as usize;
+        let kind = data
+            .get(offset + 4..offset + 8)
+            .ok_or_else(|| Error::UnexpectedInput("png data is too short".to_string()))?;
+        let body = data
+            .get(offset + 8..offset + 8 + length)
+            .ok_or_else(|| Error::UnexpectedInput("png chunk is too short".to_string()))?;
+        match kind {
+            b"IHDR" => header = Some(read_header(body)?),
+            b"PLTE" => {
+                let h = header.as_mut().ok_or_else(missing_header)?;
+                h.palette = body
+                    .chunks_exact(3)
+                    .map(|c| Color::new(c[0], c[1], c[2], 255))
+                    .collect();
+            }
+            b"tRNS" => {
+                let h = header.as_mut().ok_or_else(missing_header)?;
+                match h.color_type {
+                    // パレットの順に不透明度が並ぶ
+                    ColorType::Indexed => {
+                        for (color, alpha) in h.palette.iter_mut().zip(body) {
+                            *color = Color::new(color.r(), color.g(), color.b(), *alpha);
+                        }
+                    }
+                    ColorType::Grayscale => {
+                        let v = read_u16(body, 0)?;
+                        h.transparent = Some([v, 0, 0]);
+                    }
+                    ColorType::Truecolor => {
+                        h.transparent =
+                            Some([read_u16(body, 0)?, read_u16(body, 2)?, read_u16(body, 4)?]);
+                    }
+                    _ => {}
+                }
+            }
+            b"IDAT" => compressed.extend_from_slice(body),
+            b"IEND" => break,
+            _ => {}
+        }
+        // 長さ、種類、データ、CRC
+        offset += 12 + length;
+    }
+
+    let header = header.ok_or_else(missing_header)?;
+    let raw = zlib_decompress(&compressed)?;
+    let mut image = Image::new(header.width, header.height)?;
+
+    if header.interlaced {
+        let mut offset = 0;
+        for (x0, y0, dx, dy) in ADAM7_PASSES {
+            let width = (header.width + dx - 1 - x0) / dx;
+            let height = (header.height + dy - 1 - y0) / dy;
+            // 画像が小さい場合、ピクセルのないパスは行も持たない
+            if width == 0 || height == 0 {
+                continue;
+            }
+            let rows = unfilter(&header, raw.get(offset..).unwrap_or(&[]), width, height)?;
+            offset += height as usize * (header.stride(width) + 1);
+            for (y, row) in rows.iter().enumerate() {
+                for x in 0..width as usize {
+                    image.set_pixel(
+                        (x0 + x as u32 * dx) as i64,
+                        (y0 + y as u32 * dy) as i64,
+                        header.color(row, x),
+                    );
+                }
+            }
+        }
+    } else {
+        let rows = unfilter(&header, &raw, header.width, header.height)?;
+        for (y, row) in rows.iter().enumerate() {
+            for x in 0..header.width as usize {
+                image.set_pixel(x as i64, y as i64, header.color(row, x));
+            }
+        }
+    }
+
+    Ok(image)
+}
+
+fn missing_header() -> Error {
+    Error::UnexpectedInput("png IHDR chunk is missing".to_string())
+}
+
+fn read_u16(data: &[u8], offset: usize) -> Result<u16, Error> {
+    let bytes = data
+        .get(offset..offset + 2)
+        .ok_or_else(|| Error::UnexpectedInput(format!("png data is too short: {}", offset)))?;
+    Ok(u16::from_be_bytes([bytes[0], bytes[1]]))
+}
+
+fn read_header(body: &[u8]) -> Result<Header, Error> {
+    let width = read_u32(body, 0)?;
+    let height = read_u32(body, 4)?;
+    let bit_depth = *body
+        .get(8)
+        .ok_or_else(|| Error::UnexpectedInput("png IHDR chunk is too short".to_string()))?;
+    let color_type = ColorType::from_u8(
+        *body
+            .get(9)
+            .ok_or_else(|| Error::UnexpectedInput("png IHDR chunk is too short".to_string()))?,
+    )?;
+    let valid_depth = match color_type {
+        ColorType::Grayscale => matches!(bit_depth, 1 | 2 | 4 | 8 | 16),
+        ColorType::Indexed => matches!(bit_depth, 1 | 2 | 4 | 8),
+        _ => matches!(bit_depth, 8 | 16),
+    };
+    if !valid_depth {
+        return Err(Error::UnexpectedInput(format!(
+            "invalid png bit depth {} for {:?}",
+            bit_depth, color_type
+        )));
+    }
+    Ok(Header {
+        width,
+        height,
+        bit_depth,
+        color_type,
+        interlaced: body.get(12) == Some(&1),
+        palette: Vec::new(),
+        transparent: None,
+    })
+}
+
+/// 各行の先頭に書かれたフィルタを戻し、行ごとのバイト列にする
+/// https://www.w3.org/TR/png/#9Filters
+fn unfilter(header: &Header, raw: &[u8], width: u32, height: u32) -> Result<Vec<Vec<u8>>, Error> {
+    let stride = header.stride(width);
+    let distance = header.filter_distance();
+    let mut rows: Vec<Vec<u8>> = Vec::with_capacity(height as usize);
+    let mut previous = vec![0u8; stride];
+
+    for y in 0..height as usize {
+        let start = y * (stride + 1);
+        let line = raw
+            .get(start..start + stride + 1)
+            .ok_or_else(|| Error::UnexpectedInput("png image data is too short".to_string()))?;
+        let filter = line[0];
+        let mut row = line[1..].to_vec();
+        for i in 0..stride {
+            let left = if i >= distance { row[i - distance] } else { 0 };
+            let up = previous[i];
+            let up_left = if i >= distance {
+                previous[i - distance]
+            } else {
+                0
+            };
+            let predictor = match filter {
+                0 => 0,
+                1 => left,
+                2 => up,
+                3 => ((left as u16 + up as u16) / 2) as u8,
+                4 => paeth(left, up, up_left),
+                _ => {
+                    return Err(Error::UnexpectedInput(format!(
+                        "invalid png filter type: {}",
+                        filter
+                    )))
+                }
+            };
+            row[i] = row[i].wrapping_add(predictor);
+        }
+        previous = row.clone();
+        rows.push(row);
+    }
+
+    Ok(rows)
+}
+
+/// 左、上、左上のうち、left + up - up_leftに最も近い値
+fn paeth(left: u8, up: u8, up_left: u8) -> u8 {
+    let p = left as i16 + up as i16 - up_left as i16;
+    let pa = (p - left as i16).abs();
+    let pb = (p - up as i16).abs();
+    let pc = (p - up_left as i16).abs();
+    if pa <= pb && pa <= pc {
+        left
+    } else if pb <= pc {
+        up
+    } else {
+        up_left
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rgba_with_filters() {
+        // 1行目はSub、2行目はPaethのフィルタ
+        let data = [
+            0x89, 0x50, 0x4e, 0x47, 0x0d, 0x0a, 0x1a, 0x0a, 0x00, 0x00, 0x00, 0x0d, 0x49, 0x48,
+            0x44, 0x52, 0x00, 0x00, 0x00, 0x02, 0x00, 0x00, 0x00, 0x02, 0x08, 0x06, 0x00, 0x00,
+            0x00, 0x72, 0xb6, 0x0d, 0x24, 0x00, 0x00, 0x00, 0x1a, 0x49, 0x44, 0x41, 0x54, 0x78,
+            0xda, 0x63, 0xfc, 0xcf, 0xc0, 0xf0, 0x9f, 0xf1, 0x3f, 0x43, 0x23, 0x0b, 0x23, 0xc3,
+            0x7f, 0x06, 0x2e, 0x51, 0xf9, 0x06, 0x00, 0x37, 0x5b, 0x05, 0x43, 0x72, 0x1f, 0x52,
+            0xf6, 0x00, 0x00, 0x00, 0x00, 0x49, 0x45, 0x4e, 0x44, 0xae, 0x42, 0x60, 0x82,
+        ];
+        let image = decode(&data).expect("failed to decode png");
+        assert_eq!(2, image.width());
+        assert_eq!(2, image.height());
+        assert_eq!(Color::new(255, 0, 0, 255), image.pixel(0, 0));
+        assert_eq!(Color::new(0, 255, 0, 128), image.pixel(1, 0));
+        assert_eq!(Color::new(0, 0, 255, 255), image.pixel(0, 1));
+        assert_eq!(Color::new(10, 20, 30, 0), image.pixel(1, 1));
+    }
+
+    #[test]
+    fn test_indexed_with_transparency() {
+        // 2ビットのパレット。3番目の色だけ透明
+        let data = [
+            0x89, 0x50, 0x4e, 0x47, 0x0d, 0x0a, 0x1a, 0x0a, 0x00, 0x00, 0x00, 0x0d, 0x49, 0x48,
+            0x44, 0x52, 0x00, 0x00, 0x00, 0x04, 0x00, 0x00, 0x00, 0x01, 0x02, 0x03, 0x00, 0x00,
+            0x00, 0x84, 0x52, 0xe7, 0x5e, 0x00, 0x00, 0x00, 0x0c, 0x50, 0x4c, 0x54, 0x45, 0x00,
+            0x00, 0x00, 0xff, 0xff, 0xff, 0xff, 0x00, 0x00, 0x00, 0x00, 0xff, 0x01, 0x1d, 0x33,
+            0x4a, 0x00, 0x00, 0x00, 0x03, 0x74, 0x52, 0x4e, 0x53, 0xff, 0xff, 0x00, 0xd7, 0xca,
+            0x0d, 0x41, 0x00, 0x00, 0x00, 0x0a, 0x49, 0x44, 0x41, 0x54, 0x78, 0xda, 0x63, 0x90,
+            0x06, 0x00, 0x00, 0x1d, 0x00, 0x1c, 0x23, 0x7c, 0x8f, 0xac, 0x00, 0x00, 0x00, 0x00,
+            0x49, 0x45, 0x4e, 0x44, 0xae, 0x42, 0x60, 0x82,
+        ];
+        let image = decode(&data).expect("failed to decode png");
+        assert_eq!(4, image.width());
+        assert_eq!(1, image.height());
+        assert_eq!(Color::new(0, 0, 0, 255), image.pixel(0, 0));
+        assert_eq!(Color::new(255, 255, 255, 255), image.pixel(1, 0));
+        assert_eq!(Color::new(255, 0, 0, 0), image.pixel(2, 0));
+        assert_eq!(Color::new(0, 0, 255, 255), image.pixel(3, 0));
+    }
+
+    #[test]
+    fn test_interlaced_grayscale() {
+        // 3x3のAdam7。ピクセルのないパスがある
+        let data = [
+            0x89, 0x50, 0x4e, 0x47, 0x0d, 0x0a, 0x1a, 0x0a, 0x00, 0x00, 0x00, 0x0d, 0x49, 0x48,
+            0x44, 0x52, 0x00, 0x00, 0x00, 0x03, 0x00, 0x00, 0x00, 0x03, 0x08, 0x00, 0x00, 0x00,
+            0x01, 0x04, 0x44, 0xda, 0xf5, 0x00, 0x00, 0x00, 0x17, 0x49, 0x44, 0x41, 0x54, 0x78,
+            0xda, 0x63, 0x60, 0x60, 0x10, 0x61, 0xb0, 0x09, 0x60, 0xe0, 0x62, 0x70, 0x63, 0x90,
+            0xd3, 0x30, 0x02, 0x00, 0x08, 0xa7, 0x01, 0x69, 0x85, 0x60, 0xee, 0x25, 0x00, 0x00,
+            0x00, 0x00, 0x49, 0x45, 0x4e, 0x44, 0xae, 0x42, 0x60, 0x82,
+        ];
+        let image = decode(&data).expect("failed to decode png");
+        for y in 0..3 {
+            for x in 0..3 {
+                let v = ((y * 3 + x) * 10) as u8;
+                assert_eq!(Color::new(v, v, v, 255), image.pixel(x, y));
+            }
+        }
+    }
+
+    #[test]
+    fn test_invalid_png() {
+        assert!(decode(b"GIF89a").is_err());
+        // IHDRの途中で終わる
+        let mut data = SIGNATURE.to_vec();
+        data.extend_from_slice(&[0x00, 0x00, 0x00, 0x0d, b'I', b'H', b'D', b'R', 0x00]);
+        assert!(decode(&data).is_err());
+    }
+}
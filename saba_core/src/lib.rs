@@ -3,10 +3,13 @@
 extern crate alloc;
 
 pub mod browser;
+pub mod canvas;
 pub mod constants;
 pub mod display_item;
 pub mod error;
+pub mod font;
 pub mod http;
+pub mod image;
 pub mod renderer;
 pub mod url;
 pub mod utils;
@@ -2,6 +2,7 @@ use crate::renderer::css::token::CssToken;
 use crate::renderer::css::token::CssTokenizer;
 use alloc::string::String;
 use alloc::string::ToString;
+use alloc::vec;
 use alloc::vec::Vec;
 use core::iter::Peekable;
 
@@ -16,9 +17,36 @@ impl CssParser {
     }
 
     fn consume_component_value(&mut self) -> ComponentValue {
-        self.t
+        let token = self
+            .t
             .next()
-            .expect("should have a token in consume_component_value")
+            .expect("should have a token in consume_component_value");
+
+        // 識別子の直後に括弧が続く場合は関数として読み込む
+        match token {
+            CssToken::Ident(name) if self.t.peek() == Some(&CssToken::OpenParenthesis) => {
+                assert_eq!(self.t.next(), Some(CssToken::OpenParenthesis));
+                CssToken::Function(name, self.consume_function_arguments())
+            }
+            _ => token,
+        }
+    }
+
+    /// 閉じ括弧までのトークンを関数の引数として読み込む
+    fn consume_function_arguments(&mut self) -> Vec<ComponentValue> {
+        let mut arguments = Vec::new();
+
+        loop {
+            match self.t.peek() {
+                Some(CssToken::CloseParenthesis) => {
+                    assert_eq!(self.t.next(), Some(CssToken::CloseParenthesis));
+                    return arguments;
+                }
+                // 閉じ括弧がないまま宣言が終わった場合
+                Some(CssToken::SemiColon) | Some(CssToken::CloseCurly) | None => return arguments,
+                Some(_) => arguments.push(self.consume_component_value()),
+            }
+        }
     }
 
     fn consume_ident(&mut self) -> String {
@@ -54,6 +82,17 @@ impl CssParser {
 
         declaration.set_value(self.consume_component_value());
 
+        // `margin: 0 auto;` のように値が複数ある場合は残りも読み込む
+        loop {
+            match self.t.peek() {
+                Some(CssToken::SemiColon) | Some(CssToken::CloseCurly) | None => break,
+                Some(_) => {
+                    let value = self.consume_component_value();
+                    declaration.add_value(value);
+                }
+            }
+        }
+
         Some(declaration)
     }
 
@@ -227,6 +266,7 @@ pub enum Selector {
 pub struct Declaration {
     pub property: String,
     pub value: ComponentValue,
+    pub values: Vec<ComponentValue>,
 }
 
 impl Declaration {
@@ -234,6 +274,7 @@ impl Declaration {
         Self {
             property: String::new(),
             value: ComponentValue::Ident(String::new()),
+            values: Vec::new(),
         }
     }
 
@@ -242,7 +283,12 @@ impl Declaration {
     }
 
     pub fn set_value(&mut self, value: ComponentValue) {
-        self.value = value;
+        self.value = value.clone();
+        self.values = vec![value];
+    }
+
+    pub fn add_value(&mut self, value: ComponentValue) {
+        self.values.push(value);
     }
 }
 
@@ -252,7 +298,6 @@ pub type ComponentValue = CssToken;
 mod tests {
     use super::*;
     use alloc::string::ToString;
-    use alloc::vec;
 
     #[test]
     fn test_empty() {
@@ -364,4 +409,48 @@ mod tests {
             i += 1;
         }
     }
+
+    #[test]
+    fn test_multiple_values() {
+        let style = "div { margin: 0 auto; }".to_string();
+        let t = CssTokenizer::new(style);
+        let cssom = CssParser::new(t).parse_stylesheet();
+
+        assert_eq!(cssom.rules.len(), 1);
+        let declaration = &cssom.rules[0].declarations[0];
+        assert_eq!(declaration.value, ComponentValue::Number(0.0));
+        assert_eq!(
+            declaration.values,
+            vec![
+                ComponentValue::Number(0.0),
+                ComponentValue::Ident("auto".to_string())
+            ]
+        );
+    }
+
+    #[test]
+    fn test_function_value() {
+        let style = "p { border: 1px solid rgb(0 .5 2 / 50%); }".to_string();
+        let t = CssTokenizer::new(style);
+        let cssom = CssParser::new(t).parse_stylesheet();
+
+        let declaration = &cssom.rules[0].declarations[0];
+        assert_eq!(
+            declaration.values,
+            vec![
+                ComponentValue::Dimension(1.0, "px".to_string()),
+                ComponentValue::Ident("solid".to_string()),
+                ComponentValue::Function(
+                    "rgb".to_string(),
+                    vec![
+                        ComponentValue::Number(0.0),
+                        ComponentValue::Number(0.5),
+                        ComponentValue::Number(2.0),
+                        ComponentValue::Delim('/'),
+                        ComponentValue::Percentage(50.0),
+                    ]
+                ),
+            ]
+        );
+    }
 }
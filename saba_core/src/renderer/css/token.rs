@@ -6,6 +6,8 @@ pub enum CssToken {
     HashToken(String),
     Delim(char),
     Number(f64),
+    Dimension(f64, String),
+    Percentage(f64),
     Colon,
     SemiColon,
     OpenParenthesis,
@@ -15,6 +17,9 @@ pub enum CssToken {
     Ident(String),
     StringToken(String),
     AtKeyword(String),
+    /// `rgb(0, 0, 0)`のような関数の呼び出し
+    /// トークナイザは作らず、パーサが関数名と括弧の中のトークンをまとめて作る
+    Function(String, Vec<CssToken>),
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -82,6 +87,38 @@ impl CssTokenizer {
         num
     }
 
+    fn starts_number(&self, pos: usize) -> bool {
+        match self.input.get(pos) {
+            Some('0'..='9') => true,
+            Some('.') => matches!(self.input.get(pos + 1), Some('0'..='9')),
+            _ => false,
+        }
+    }
+
+    /// 数値の後ろに続く単位や%を読み、Number/Dimension/Percentageのいずれかを返す
+    fn consume_numeric_value(&mut self, sign: f64) -> CssToken {
+        let num = sign * self.consume_numeric_token();
+
+        match self.input.get(self.pos) {
+            Some('%') => {
+                self.pos += 1;
+                CssToken::Percentage(num)
+            }
+            Some(c) if c.is_ascii_alphabetic() => {
+                let mut unit = String::new();
+                while let Some(c) = self.input.get(self.pos) {
+                    if !c.is_ascii_alphabetic() {
+                        break;
+                    }
+                    unit.push(c.to_ascii_lowercase());
+                    self.pos += 1;
+                }
+                CssToken::Dimension(num, unit)
+            }
+            _ => CssToken::Number(num),
+        }
+    }
+
     fn consume_ident_token(&mut self) -> String {
         let mut s = String::new();
         s.push(self.input[self.pos]);
@@ -115,13 +152,19 @@ impl Iterator for CssTokenizer {
                 '(' => CssToken::OpenParenthesis,
                 ')' => CssToken::CloseParenthesis,
                 ',' => CssToken::Delim(','),
+                '.' if self.starts_number(self.pos) => {
+                    // .5 のように整数部分のない数値
+                    let t = self.consume_numeric_value(1.0);
+                    self.pos -= 1;
+                    t
+                }
                 '.' => CssToken::Delim('.'),
                 ':' => CssToken::Colon,
                 ';' => CssToken::SemiColon,
                 '{' => CssToken::OpenCurly,
                 '}' => CssToken::CloseCurly,
                 '0'..='9' => {
-                    let t = CssToken::Number(self.consume_numeric_token());
+                    let t = self.consume_numeric_value(1.0);
                     self.pos -= 1;
                     t
                 }
@@ -130,6 +173,13 @@ impl Iterator for CssTokenizer {
                     self.pos -= 1;
                     CssToken::HashToken(value)
                 }
+                '-' if self.starts_number(self.pos + 1) => {
+                    // -10px のような負の数値
+                    self.pos += 1;
+                    let t = self.consume_numeric_value(-1.0);
+                    self.pos -= 1;
+                    t
+                }
                 '-' => {
                     // -は識別子の1つとして扱う
                     let t = CssToken::Ident(self.consume_ident_token());
@@ -155,17 +205,16 @@ impl Iterator for CssTokenizer {
                     self.pos -= 1;
                     t
                 }
-                ' ' | '\n' => {
-                    self.pos += 1;
-                    continue;
-                }
                 '"' | '\'' => {
                     let value = self.consume_string_token();
                     CssToken::StringToken(value)
                 }
-                _ => {
-                    unimplemented!("char {} is not implemented", c)
+                ' ' | '\n' | '\t' | '\r' => {
+                    self.pos += 1;
+                    continue;
                 }
+                // 未対応の文字は区切り文字として扱う
+                _ => CssToken::Delim(c),
             };
 
             self.pos += 1;
@@ -273,4 +322,25 @@ mod tests {
         }
         assert!(t.next().is_none());
     }
+
+    #[test]
+    fn test_dimensions() {
+        let style = "p { margin: 10px -2.5em 50%; }".to_string();
+        let mut t = CssTokenizer::new(style);
+        let expected = [
+            CssToken::Ident("p".to_string()),
+            CssToken::OpenCurly,
+            CssToken::Ident("margin".to_string()),
+            CssToken::Colon,
+            CssToken::Dimension(10.0, "px".to_string()),
+            CssToken::Dimension(-2.5, "em".to_string()),
+            CssToken::Percentage(50.0),
+            CssToken::SemiColon,
+            CssToken::CloseCurly,
+        ];
+        for e in expected {
+            assert_eq!(Some(e.clone()), t.next());
+        }
+        assert!(t.next().is_none());
+    }
 }
@@ -59,6 +59,30 @@ pub fn get_target_element_node(
     }
 }
 
+/// `<img>`要素の`src`属性の値を、文書の順に重複なく集める
+pub fn get_image_sources(root: Rc<RefCell<Node>>) -> Vec<String> {
+    let mut sources = Vec::new();
+    collect_image_sources(Some(root), &mut sources);
+    sources
+}
+
+fn collect_image_sources(node: Option<Rc<RefCell<Node>>>, sources: &mut Vec<String>) {
+    let mut current = node;
+    while let Some(n) = current {
+        if let NodeKind::Element(e) = n.borrow().kind() {
+            if e.kind() == ElementKind::Img {
+                if let Some(src) = e.get_attribute("src") {
+                    if !sources.contains(&src) {
+                        sources.push(src);
+                    }
+                }
+            }
+        }
+        collect_image_sources(n.borrow().first_child(), sources);
+        current = n.borrow().next_sibling();
+    }
+}
+
 pub fn get_style_content(root: Rc<RefCell<Node>>) -> String {
     let style_node = match get_target_element_node(Some(root), ElementKind::Style) {
         Some(node) => node,
@@ -76,18 +100,133 @@ pub fn get_style_content(root: Rc<RefCell<Node>>) -> String {
     content
 }
 
-pub fn get_js_content(root: Rc<RefCell<Node>>) -> String {
-    let js_node = match get_target_element_node(Some(root), ElementKind::Script) {
-        Some(node) => node,
-        None => return "".to_string(),
+/// `<script>`要素をいつ実行するか
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Script {
+    /// 要素の中に書かれたスクリプト。パーサを止めてすぐに実行する
+    Inline(String),
+    /// `src`のスクリプト。パーサを止めて取得し、実行する
+    Blocking(String),
+    /// `defer`のスクリプト。文書の解析が終わってから、文書の順に実行する
+    Deferred(String),
+    /// `async`のスクリプト。パーサを止めずに取得し、取得できた順に実行する
+    Async(String),
+}
+
+/// JavaScriptを表すMIMEタイプ
+/// https://mimesniff.spec.whatwg.org/#javascript-mime-type
+const JAVASCRIPT_MIME_TYPES: [&str; 16] = [
+    "application/ecmascript",
+    "application/javascript",
+    "application/x-ecmascript",
+    "application/x-javascript",
+    "text/ecmascript",
+    "text/javascript",
+    "text/javascript1.0",
+    "text/javascript1.1",
+    "text/javascript1.2",
+    "text/javascript1.3",
+    "text/javascript1.4",
+    "text/javascript1.5",
+    "text/jscript",
+    "text/livescript",
+    "text/x-ecmascript",
+    "text/x-javascript",
+];
+
+/// `<script>`要素の実行の仕方を決める。JavaScriptでない要素や、空の`src`を持つ要素はNone
+/// https://html.spec.whatwg.org/multipage/scripting.html#prepare-the-script-element
+pub fn get_script(node: Rc<RefCell<Node>>) -> Option<Script> {
+    let element = match node.borrow().kind() {
+        NodeKind::Element(e) if e.kind() == ElementKind::Script => e,
+        _ => return None,
     };
-    let text_node = match js_node.borrow().first_child() {
-        Some(node) => node,
-        None => return "".to_string(),
+    // モジュールには対応していないので、type="module"も実行しない
+    let script_type = match (
+        element.get_attribute("type"),
+        element.get_attribute("language"),
+    ) {
+        (Some(t), _) => t,
+        (None, Some(language)) if !language.is_empty() => "text/".to_string() + &language,
+        (None, _) => String::new(),
     };
-    let content = match &text_node.borrow().kind() {
-        NodeKind::Text(ref s) => s.clone(),
-        _ => return "".to_string(),
+    let script_type = script_type.trim().to_ascii_lowercase();
+    if !script_type.is_empty() && !JAVASCRIPT_MIME_TYPES.contains(&script_type.as_str()) {
+        return None;
+    }
+
+    let src = match element.get_attribute("src") {
+        Some(src) => src,
+        None => return Some(Script::Inline(get_text_content(node))),
     };
+    if src.is_empty() {
+        return None;
+    }
+    // asyncとdeferの両方があればasyncを優先する
+    if element.get_attribute("async").is_some() {
+        Some(Script::Async(src))
+    } else if element.get_attribute("defer").is_some() {
+        Some(Script::Deferred(src))
+    } else {
+        Some(Script::Blocking(src))
+    }
+}
+
+/// 子孫のテキストノードの文字列をつなげたもの
+pub fn get_text_content(node: Rc<RefCell<Node>>) -> String {
+    if let NodeKind::Text(s) = node.borrow().kind() {
+        return s;
+    }
+    let mut content = String::new();
+    let mut child = node.borrow().first_child();
+    while let Some(c) = child {
+        content.push_str(&get_text_content(c.clone()));
+        child = c.borrow().next_sibling();
+    }
     content
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::renderer::html::parser::HtmlParser;
+    use crate::renderer::html::token::HtmlTokenizer;
+    use alloc::vec;
+
+    #[test]
+    fn test_get_script() {
+        let html = "<html><head></head><body>\
+            <script>a()</script>\
+            <script type=\" Text/JavaScript \">b()</script>\
+            <script language=\"javascript\">c()</script>\
+            <script type=\"text/plain\">d()</script>\
+            <script type=\"module\">e()</script>\
+            <script src=\"f.js\"></script>\
+            <script src=\"g.js\" defer></script>\
+            <script src=\"h.js\" async defer></script>\
+            <script src=\"\"></script>\
+            <script defer>i()</script>\
+            </body></html>"
+            .to_string();
+        let mut scripts = Vec::new();
+        HtmlParser::new(HtmlTokenizer::new(html))
+            .construct_tree_with_scripts(|node| scripts.push(get_script(node)));
+
+        assert_eq!(
+            vec![
+                Some(Script::Inline("a()".to_string())),
+                Some(Script::Inline("b()".to_string())),
+                Some(Script::Inline("c()".to_string())),
+                None,
+                None,
+                Some(Script::Blocking("f.js".to_string())),
+                Some(Script::Deferred("g.js".to_string())),
+                Some(Script::Async("h.js".to_string())),
+                None,
+                // deferとasyncは`src`のあるスクリプトにしか効かない
+                Some(Script::Inline("i()".to_string())),
+            ],
+            scripts
+        );
+    }
+}
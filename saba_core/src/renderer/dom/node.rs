@@ -180,7 +180,15 @@ impl Element {
 
     pub fn is_block_element(&self) -> bool {
         match self.kind {
-            ElementKind::Body | ElementKind::H1 | ElementKind::H2 | ElementKind::P => true,
+            ElementKind::Body
+            | ElementKind::H1
+            | ElementKind::H2
+            | ElementKind::P
+            | ElementKind::Div
+            | ElementKind::Table
+            | ElementKind::Ul
+            | ElementKind::Ol
+            | ElementKind::Li => true,
             _ => false,
         }
     }
@@ -196,7 +204,25 @@ pub enum ElementKind {
     H1,
     H2,
     P,
+    Div,
     A,
+    Span,
+    B,
+    Strong,
+    I,
+    Em,
+    Table,
+    Caption,
+    Thead,
+    Tbody,
+    Tfoot,
+    Tr,
+    Td,
+    Th,
+    Ul,
+    Ol,
+    Li,
+    Img,
 }
 
 impl FromStr for ElementKind {
@@ -212,7 +238,25 @@ impl FromStr for ElementKind {
             "p" => Ok(ElementKind::P),
             "h1" => Ok(ElementKind::H1),
             "h2" => Ok(ElementKind::H2),
+            "div" => Ok(ElementKind::Div),
             "a" => Ok(ElementKind::A),
+            "span" => Ok(ElementKind::Span),
+            "b" => Ok(ElementKind::B),
+            "strong" => Ok(ElementKind::Strong),
+            "i" => Ok(ElementKind::I),
+            "em" => Ok(ElementKind::Em),
+            "table" => Ok(ElementKind::Table),
+            "caption" => Ok(ElementKind::Caption),
+            "thead" => Ok(ElementKind::Thead),
+            "tbody" => Ok(ElementKind::Tbody),
+            "tfoot" => Ok(ElementKind::Tfoot),
+            "tr" => Ok(ElementKind::Tr),
+            "td" => Ok(ElementKind::Td),
+            "th" => Ok(ElementKind::Th),
+            "ul" => Ok(ElementKind::Ul),
+            "ol" => Ok(ElementKind::Ol),
+            "li" => Ok(ElementKind::Li),
+            "img" => Ok(ElementKind::Img),
             _ => Err(format!("Unimplemented element name: {:?}", s)),
         }
     }
@@ -229,7 +273,25 @@ impl Display for ElementKind {
             ElementKind::H1 => "h1",
             ElementKind::H2 => "h2",
             ElementKind::P => "p",
+            ElementKind::Div => "div",
             ElementKind::A => "a",
+            ElementKind::Span => "span",
+            ElementKind::B => "b",
+            ElementKind::Strong => "strong",
+            ElementKind::I => "i",
+            ElementKind::Em => "em",
+            ElementKind::Table => "table",
+            ElementKind::Caption => "caption",
+            ElementKind::Thead => "thead",
+            ElementKind::Tbody => "tbody",
+            ElementKind::Tfoot => "tfoot",
+            ElementKind::Tr => "tr",
+            ElementKind::Td => "td",
+            ElementKind::Th => "th",
+            ElementKind::Ul => "ul",
+            ElementKind::Ol => "ol",
+            ElementKind::Li => "li",
+            ElementKind::Img => "img",
         };
         write!(f, "{}", s)
     }
@@ -20,6 +20,11 @@ pub enum InsertionMode {
     InHead,
     AfterHead,
     InBody,
+    InTable,
+    InCaption,
+    InTableBody,
+    InRow,
+    InCell,
     Text,
     AfterBody,
     AfterAfterBody,
@@ -100,38 +105,29 @@ impl HtmlParser {
             None => return,
         };
 
-        if let NodeKind::Text(ref mut s) = current.borrow_mut().kind {
-            s.push(c);
-            return;
-        }
-
-        if c == '\n' || c == ' ' {
-            return;
+        // 直前の子がテキストノードの場合、そのノードに文字を追加する
+        let last_child = current.borrow().last_child().upgrade();
+        if let Some(last) = &last_child {
+            if let NodeKind::Text(ref mut s) = last.borrow_mut().kind {
+                s.push(c);
+                return;
+            }
         }
 
         let node = Rc::new(RefCell::new(self.create_char(c)));
 
-        if current.borrow().first_child().is_some() {
-            current
-                .borrow()
-                .first_child()
-                .unwrap()
-                .borrow_mut()
-                .set_next_sibling(Some(node.clone()));
-            node.borrow_mut().set_previous_sibling(Rc::downgrade(
-                &current
-                    .borrow()
-                    .first_child()
-                    .expect("failed to get first child"),
-            ));
-        } else {
-            current.borrow_mut().set_first_child(Some(node.clone()));
+        match last_child {
+            Some(last) => {
+                last.borrow_mut().set_next_sibling(Some(node.clone()));
+                node.borrow_mut().set_previous_sibling(Rc::downgrade(&last));
+            }
+            None => {
+                current.borrow_mut().set_first_child(Some(node.clone()));
+            }
         }
 
         current.borrow_mut().set_last_child(Rc::downgrade(&node));
         node.borrow_mut().set_parent(Rc::downgrade(&current));
-
-        self.stack_of_open_elements.push(node);
     }
 
     fn create_element(&self, tag: &str, attributes: Vec<Attribute>) -> Node {
@@ -162,16 +158,12 @@ impl HtmlParser {
                 }
             }
 
+            let last_sibling = last_sibling.expect("last sibling should exist");
             last_sibling
-                .unwrap()
                 .borrow_mut()
                 .set_next_sibling(Some(node.clone()));
-            node.borrow_mut().set_previous_sibling(Rc::downgrade(
-                &current
-                    .borrow()
-                    .first_child()
-                    .expect("failed to get first child"),
-            ));
+            node.borrow_mut()
+                .set_previous_sibling(Rc::downgrade(&last_sibling));
         } else {
             current.borrow_mut().set_first_child(Some(node.clone()));
         }
@@ -182,7 +174,150 @@ impl HtmlParser {
         self.stack_of_open_elements.push(node);
     }
 
+    /// テーブルの中に置けない文字は、テーブルの直前に移す(foster parenting)
+    /// 空白はテーブルの構造には影響しないので無視する
+    /// https://html.spec.whatwg.org/multipage/parsing.html#foster-parent
+    fn insert_char_in_table(&mut self, c: char) {
+        if c.is_whitespace() {
+            return;
+        }
+
+        let table = match self
+            .stack_of_open_elements
+            .iter()
+            .rev()
+            .find(|n| n.borrow().element_kind() == Some(ElementKind::Table))
+        {
+            Some(t) => t.clone(),
+            None => return,
+        };
+        let parent = match table.borrow().parent().upgrade() {
+            Some(p) => p,
+            None => return,
+        };
+
+        let previous = table.borrow().previous_sibling().upgrade();
+        if let Some(previous) = &previous {
+            if let NodeKind::Text(ref mut s) = previous.borrow_mut().kind {
+                s.push(c);
+                return;
+            }
+        }
+
+        let node = Rc::new(RefCell::new(self.create_char(c)));
+        match previous {
+            Some(previous) => {
+                previous.borrow_mut().set_next_sibling(Some(node.clone()));
+                node.borrow_mut()
+                    .set_previous_sibling(Rc::downgrade(&previous));
+            }
+            None => {
+                parent.borrow_mut().set_first_child(Some(node.clone()));
+            }
+        }
+        node.borrow_mut().set_next_sibling(Some(table.clone()));
+        node.borrow_mut().set_parent(Rc::downgrade(&parent));
+        table
+            .borrow_mut()
+            .set_previous_sibling(Rc::downgrade(&node));
+    }
+
+    /// `element_kinds`のいずれか、またはhtml要素が現在のノードになるまで要素を取り除く
+    fn clear_stack_back_to(&mut self, element_kinds: &[ElementKind]) {
+        while let Some(current) = self.stack_of_open_elements.last() {
+            let kind = current.borrow().element_kind();
+            match kind {
+                Some(ElementKind::Html) => return,
+                Some(kind) if element_kinds.contains(&kind) => return,
+                _ => {
+                    self.stack_of_open_elements.pop();
+                }
+            }
+        }
+    }
+
+    fn close_cell(&mut self) {
+        while let Some(current) = self.stack_of_open_elements.pop() {
+            if matches!(
+                current.borrow().element_kind(),
+                Some(ElementKind::Td) | Some(ElementKind::Th)
+            ) {
+                break;
+            }
+        }
+        self.mode = InsertionMode::InRow;
+    }
+
+    fn close_row(&mut self) {
+        self.clear_stack_back_to(&[ElementKind::Tr]);
+        self.pop_current_node(ElementKind::Tr);
+        self.mode = InsertionMode::InTableBody;
+    }
+
+    fn close_table_section(&mut self) {
+        let sections = [ElementKind::Tbody, ElementKind::Thead, ElementKind::Tfoot];
+        self.clear_stack_back_to(&sections);
+        if let Some(current) = self.stack_of_open_elements.last() {
+            let kind = current.borrow().element_kind();
+            if kind.is_some_and(|k| sections.contains(&k)) {
+                self.stack_of_open_elements.pop();
+            }
+        }
+        self.mode = InsertionMode::InTable;
+    }
+
+    /// 新しいli要素を開く前に、同じリストで開いているli要素を閉じる
+    /// https://html.spec.whatwg.org/multipage/parsing.html#parsing-main-inbody
+    fn close_list_item(&mut self) {
+        for node in self.stack_of_open_elements.iter().rev() {
+            match node.borrow().element_kind() {
+                Some(ElementKind::Li) => break,
+                // 入れ子のリストの外側にあるli要素は閉じない
+                Some(ElementKind::Ul) | Some(ElementKind::Ol) | Some(ElementKind::Table) => return,
+                _ => continue,
+            }
+        }
+        if self.contain_in_stack(ElementKind::Li) {
+            self.pop_until(ElementKind::Li);
+        }
+    }
+
+    /// スタックに残っている要素から挿入モードを決め直す
+    /// https://html.spec.whatwg.org/multipage/parsing.html#reset-the-insertion-mode-appropriately
+    fn reset_insertion_mode(&mut self) {
+        for node in self.stack_of_open_elements.iter().rev() {
+            let mode = match node.borrow().element_kind() {
+                Some(ElementKind::Td) | Some(ElementKind::Th) => InsertionMode::InCell,
+                Some(ElementKind::Tr) => InsertionMode::InRow,
+                Some(ElementKind::Tbody) | Some(ElementKind::Thead) | Some(ElementKind::Tfoot) => {
+                    InsertionMode::InTableBody
+                }
+                Some(ElementKind::Caption) => InsertionMode::InCaption,
+                Some(ElementKind::Table) => InsertionMode::InTable,
+                Some(ElementKind::Body) => InsertionMode::InBody,
+                _ => continue,
+            };
+            self.mode = mode;
+            return;
+        }
+        self.mode = InsertionMode::InBody;
+    }
+
+    /// 構築中の文書のウィンドウ。解析を始める前からスクリプトの実行環境を作れる
+    pub fn window(&self) -> Rc<RefCell<Window>> {
+        self.window.clone()
+    }
+
     pub fn construct_tree(&mut self) -> Rc<RefCell<Window>> {
+        self.construct_tree_with_scripts(|_| {})
+    }
+
+    /// `</script>`で`<script>`要素を閉じるたびに、解析を止めて`execute_script`を呼ぶ
+    /// スクリプトからは、それまでに構築した文書が見える
+    pub fn construct_tree_with_scripts<F>(&mut self, mut execute_script: F) -> Rc<RefCell<Window>>
+    where
+        F: FnMut(Rc<RefCell<Node>>),
+    {
         let mut token = self.t.next();
 
         while token.is_some() {
@@ -334,7 +469,64 @@ impl HtmlParser {
                     self.mode = InsertionMode::InBody;
                     continue;
                 }
-                InsertionMode::InBody => {
+                InsertionMode::InBody | InsertionMode::InCaption | InsertionMode::InCell => {
+                    // セルとキャプションの中身はInBodyと同じ規則で処理する
+                    // テーブルの構造を表すタグが来たら、セルやキャプションを閉じて処理し直す
+                    if self.mode == InsertionMode::InCell {
+                        match token {
+                            Some(HtmlToken::StartTag { ref tag, .. })
+                                if matches!(
+                                    tag.as_str(),
+                                    "caption" | "tbody" | "td" | "tfoot" | "th" | "thead" | "tr"
+                                ) =>
+                            {
+                                self.close_cell();
+                                continue;
+                            }
+                            Some(HtmlToken::EndTag { ref tag }) if tag == "td" || tag == "th" => {
+                                self.close_cell();
+                                token = self.t.next();
+                                continue;
+                            }
+                            Some(HtmlToken::EndTag { ref tag })
+                                if matches!(
+                                    tag.as_str(),
+                                    "table" | "tbody" | "tfoot" | "thead" | "tr"
+                                ) =>
+                            {
+                                self.close_cell();
+                                continue;
+                            }
+                            _ => {}
+                        }
+                    }
+                    if self.mode == InsertionMode::InCaption {
+                        match token {
+                            Some(HtmlToken::EndTag { ref tag }) if tag == "caption" => {
+                                self.pop_until(ElementKind::Caption);
+                                self.mode = InsertionMode::InTable;
+                                token = self.t.next();
+                                continue;
+                            }
+                            Some(HtmlToken::StartTag { ref tag, .. })
+                                if matches!(
+                                    tag.as_str(),
+                                    "caption" | "tbody" | "td" | "tfoot" | "th" | "thead" | "tr"
+                                ) =>
+                            {
+                                self.pop_until(ElementKind::Caption);
+                                self.mode = InsertionMode::InTable;
+                                continue;
+                            }
+                            Some(HtmlToken::EndTag { ref tag }) if tag == "table" => {
+                                self.pop_until(ElementKind::Caption);
+                                self.mode = InsertionMode::InTable;
+                                continue;
+                            }
+                            _ => {}
+                        }
+                    }
+
                     match token {
                         Some(HtmlToken::StartTag {
                             ref tag,
@@ -351,11 +543,52 @@ impl HtmlParser {
                                 token = self.t.next();
                                 continue;
                             }
+                            "div" => {
+                                self.insert_element(tag, attributes.to_vec());
+                                token = self.t.next();
+                                continue;
+                            }
+                            "ul" | "ol" => {
+                                self.insert_element(tag, attributes.to_vec());
+                                token = self.t.next();
+                                continue;
+                            }
+                            "li" => {
+                                self.close_list_item();
+                                self.insert_element(tag, attributes.to_vec());
+                                token = self.t.next();
+                                continue;
+                            }
                             "a" => {
                                 self.insert_element(tag, attributes.to_vec());
                                 token = self.t.next();
                                 continue;
                             }
+                            "span" | "b" | "strong" | "i" | "em" => {
+                                self.insert_element(tag, attributes.to_vec());
+                                token = self.t.next();
+                                continue;
+                            }
+                            "table" => {
+                                self.insert_element(tag, attributes.to_vec());
+                                self.mode = InsertionMode::InTable;
+                                token = self.t.next();
+                                continue;
+                            }
+                            "script" => {
+                                self.insert_element(tag, attributes.to_vec());
+                                self.original_insertion_mode = self.mode;
+                                self.mode = InsertionMode::Text;
+                                token = self.t.next();
+                                continue;
+                            }
+                            "img" => {
+                                // 空要素なので、子を持たずにすぐ閉じる
+                                self.insert_element(tag, attributes.to_vec());
+                                self.pop_current_node(ElementKind::Img);
+                                token = self.t.next();
+                                continue;
+                            }
                             _ => {
                                 token = self.t.next();
                             }
@@ -395,6 +628,15 @@ impl HtmlParser {
                                     self.pop_until(element_kind);
                                     continue;
                                 }
+                                "div" | "ul" | "ol" | "li" => {
+                                    let element_kind = ElementKind::from_str(tag)
+                                        .expect("failed to convert string to ElementKind");
+                                    token = self.t.next();
+                                    if self.contain_in_stack(element_kind) {
+                                        self.pop_until(element_kind);
+                                    }
+                                    continue;
+                                }
                                 "a" => {
                                     let element_kind = ElementKind::from_str(tag)
                                         .expect("failed to convert string to ElementKind");
@@ -402,6 +644,15 @@ impl HtmlParser {
                                     self.pop_until(element_kind);
                                     continue;
                                 }
+                                "span" | "b" | "strong" | "i" | "em" => {
+                                    let element_kind = ElementKind::from_str(tag)
+                                        .expect("failed to convert string to ElementKind");
+                                    token = self.t.next();
+                                    if self.contain_in_stack(element_kind) {
+                                        self.pop_until(element_kind);
+                                    }
+                                    continue;
+                                }
                                 _ => {
                                     token = self.t.next();
                                 }
@@ -418,6 +669,166 @@ impl HtmlParser {
                     }
                     continue;
                 }
+                InsertionMode::InTable => {
+                    match token {
+                        Some(HtmlToken::Char(c)) => {
+                            self.insert_char_in_table(c);
+                            token = self.t.next();
+                            continue;
+                        }
+                        Some(HtmlToken::StartTag {
+                            ref tag,
+                            self_closing: _,
+                            ref attributes,
+                        }) => match tag.as_str() {
+                            "caption" => {
+                                self.clear_stack_back_to(&[ElementKind::Table]);
+                                self.insert_element(tag, attributes.to_vec());
+                                self.mode = InsertionMode::InCaption;
+                                token = self.t.next();
+                                continue;
+                            }
+                            "tbody" | "thead" | "tfoot" => {
+                                self.clear_stack_back_to(&[ElementKind::Table]);
+                                self.insert_element(tag, attributes.to_vec());
+                                self.mode = InsertionMode::InTableBody;
+                                token = self.t.next();
+                                continue;
+                            }
+                            "tr" | "td" | "th" => {
+                                // tbody省略のハンドリング
+                                self.clear_stack_back_to(&[ElementKind::Table]);
+                                self.insert_element("tbody", Vec::new());
+                                self.mode = InsertionMode::InTableBody;
+                                continue;
+                            }
+                            "table" => {
+                                // 開いているテーブルを閉じてから、新しいテーブルとして処理し直す
+                                self.pop_until(ElementKind::Table);
+                                self.reset_insertion_mode();
+                                continue;
+                            }
+                            _ => {}
+                        },
+                        Some(HtmlToken::EndTag { ref tag }) => {
+                            if tag == "table" {
+                                self.pop_until(ElementKind::Table);
+                                self.reset_insertion_mode();
+                                token = self.t.next();
+                                continue;
+                            }
+                        }
+                        Some(HtmlToken::Eof) | None => {
+                            return self.window.clone();
+                        }
+                    }
+                    // テーブルの構造以外のタグは無視する
+                    token = self.t.next();
+                    continue;
+                }
+                InsertionMode::InTableBody => {
+                    match token {
+                        Some(HtmlToken::Char(c)) => {
+                            self.insert_char_in_table(c);
+                            token = self.t.next();
+                            continue;
+                        }
+                        Some(HtmlToken::StartTag {
+                            ref tag,
+                            self_closing: _,
+                            ref attributes,
+                        }) => match tag.as_str() {
+                            "tr" => {
+                                self.clear_stack_back_to(&[
+                                    ElementKind::Tbody,
+                                    ElementKind::Thead,
+                                    ElementKind::Tfoot,
+                                ]);
+                                self.insert_element(tag, attributes.to_vec());
+                                self.mode = InsertionMode::InRow;
+                                token = self.t.next();
+                                continue;
+                            }
+                            "td" | "th" => {
+                                // tr省略のハンドリング
+                                self.clear_stack_back_to(&[
+                                    ElementKind::Tbody,
+                                    ElementKind::Thead,
+                                    ElementKind::Tfoot,
+                                ]);
+                                self.insert_element("tr", Vec::new());
+                                self.mode = InsertionMode::InRow;
+                                continue;
+                            }
+                            "caption" | "tbody" | "thead" | "tfoot" | "table" => {
+                                self.close_table_section();
+                                continue;
+                            }
+                            _ => {}
+                        },
+                        Some(HtmlToken::EndTag { ref tag }) => match tag.as_str() {
+                            "tbody" | "thead" | "tfoot" => {
+                                self.close_table_section();
+                                token = self.t.next();
+                                continue;
+                            }
+                            "table" => {
+                                self.close_table_section();
+                                continue;
+                            }
+                            _ => {}
+                        },
+                        Some(HtmlToken::Eof) | None => {
+                            return self.window.clone();
+                        }
+                    }
+                    token = self.t.next();
+                    continue;
+                }
+                InsertionMode::InRow => {
+                    match token {
+                        Some(HtmlToken::Char(c)) => {
+                            self.insert_char_in_table(c);
+                            token = self.t.next();
+                            continue;
+                        }
+                        Some(HtmlToken::StartTag {
+                            ref tag,
+                            self_closing: _,
+                            ref attributes,
+                        }) => match tag.as_str() {
+                            "td" | "th" => {
+                                self.clear_stack_back_to(&[ElementKind::Tr]);
+                                self.insert_element(tag, attributes.to_vec());
+                                self.mode = InsertionMode::InCell;
+                                token = self.t.next();
+                                continue;
+                            }
+                            "tr" | "caption" | "tbody" | "thead" | "tfoot" | "table" => {
+                                self.close_row();
+                                continue;
+                            }
+                            _ => {}
+                        },
+                        Some(HtmlToken::EndTag { ref tag }) => match tag.as_str() {
+                            "tr" => {
+                                self.close_row();
+                                token = self.t.next();
+                                continue;
+                            }
+                            "tbody" | "thead" | "tfoot" | "table" => {
+                                self.close_row();
+                                continue;
+                            }
+                            _ => {}
+                        },
+                        Some(HtmlToken::Eof) | None => {
+                            return self.window.clone();
+                        }
+                    }
+                    token = self.t.next();
+                    continue;
+                }
                 InsertionMode::Text => {
                     match token {
                         Some(HtmlToken::Eof) => {
@@ -431,8 +842,12 @@ impl HtmlParser {
                                 continue;
                             }
                             if tag == "script" {
+                                let script = self.stack_of_open_elements.last().cloned();
                                 self.pop_until(ElementKind::Script);
                                 self.mode = self.original_insertion_mode;
+                                if let Some(script) = script {
+                                    execute_script(script);
+                                }
                                 token = self.t.next();
                                 continue;
                             }
@@ -489,6 +904,7 @@ impl HtmlParser {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::renderer::dom::api::get_text_content;
     use alloc::string::ToString;
     use alloc::vec;
 
@@ -600,6 +1016,34 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_leading_white_space_is_kept() {
+        // 空白をまとめるかどうかはwhite-spaceで決まるので、要素の先頭の空白も残す
+        let html = "<html><head></head><body><p> a</p><span> </span></body></html>".to_string();
+        let t = HtmlTokenizer::new(html);
+        let window = HtmlParser::new(t).construct_tree();
+        let document = window.borrow().document();
+
+        let body = document
+            .borrow()
+            .first_child()
+            .expect("failed to get first child of document")
+            .borrow()
+            .last_child()
+            .upgrade()
+            .expect("failed to get body");
+        let p = body.borrow().first_child().expect("failed to get p");
+        let span = p.borrow().next_sibling().expect("failed to get span");
+        for (element, text) in [(p, " a"), (span, " ")] {
+            assert_eq!(
+                Some(Rc::new(RefCell::new(Node::new(NodeKind::Text(
+                    text.to_string()
+                ))))),
+                element.borrow().first_child()
+            );
+        }
+    }
+
     #[test]
     fn test_multiple_nodes() {
         let html =
@@ -668,4 +1112,190 @@ mod tests {
             text
         )
     }
+
+    fn body_of(html: &str) -> Rc<RefCell<Node>> {
+        let t = HtmlTokenizer::new(html.to_string());
+        let window = HtmlParser::new(t).construct_tree();
+        let document = window.borrow().document();
+        let html = document
+            .borrow()
+            .first_child()
+            .expect("failed to get first child of document");
+        let head = html
+            .borrow()
+            .first_child()
+            .expect("failed to get first child of html");
+        let body = head
+            .borrow()
+            .next_sibling()
+            .expect("failed to get next sibling of head");
+        body
+    }
+
+    fn child_kinds(node: &Rc<RefCell<Node>>) -> Vec<Option<ElementKind>> {
+        let mut kinds = Vec::new();
+        let mut child = node.borrow().first_child();
+        while let Some(c) = child {
+            kinds.push(c.borrow().element_kind());
+            child = c.borrow().next_sibling();
+        }
+        kinds
+    }
+
+    #[test]
+    fn test_table() {
+        let body = body_of(
+            "<html><head></head><body><table>\n  <tr><td>a</td><th>b</th></tr>\n</table></body></html>",
+        );
+        let table = body.borrow().first_child().expect("table should exist");
+        assert_eq!(Some(ElementKind::Table), table.borrow().element_kind());
+
+        // tbodyが補われ、表の中の空白は無視される
+        assert_eq!(vec![Some(ElementKind::Tbody)], child_kinds(&table));
+        let tbody = table.borrow().first_child().unwrap();
+        assert_eq!(vec![Some(ElementKind::Tr)], child_kinds(&tbody));
+        let tr = tbody.borrow().first_child().unwrap();
+        assert_eq!(
+            vec![Some(ElementKind::Td), Some(ElementKind::Th)],
+            child_kinds(&tr)
+        );
+        let td = tr.borrow().first_child().unwrap();
+        let text = td.borrow().first_child().unwrap();
+        let kind = text.borrow().kind();
+        match kind {
+            NodeKind::Text(text) => assert_eq!("a", text),
+            kind => panic!("expected a text node, but got {:?}", kind),
+        }
+    }
+
+    #[test]
+    fn test_table_implied_end_tags() {
+        let body = body_of(
+            "<html><head></head><body><table><caption>title</caption><thead><tr><th>h<tbody><tr><td>a<td>b<tr><td>c</table><p>after</p></body></html>",
+        );
+        assert_eq!(
+            vec![Some(ElementKind::Table), Some(ElementKind::P)],
+            child_kinds(&body)
+        );
+
+        let table = body.borrow().first_child().unwrap();
+        assert_eq!(
+            vec![
+                Some(ElementKind::Caption),
+                Some(ElementKind::Thead),
+                Some(ElementKind::Tbody)
+            ],
+            child_kinds(&table)
+        );
+        let tbody = table.borrow().last_child().upgrade().unwrap();
+        assert_eq!(
+            vec![Some(ElementKind::Tr), Some(ElementKind::Tr)],
+            child_kinds(&tbody)
+        );
+        let first_row = tbody.borrow().first_child().unwrap();
+        assert_eq!(
+            vec![Some(ElementKind::Td), Some(ElementKind::Td)],
+            child_kinds(&first_row)
+        );
+    }
+
+    #[test]
+    fn test_table_foster_parenting() {
+        let body = body_of(
+            "<html><head></head><body><table>oops<tr><td>cell</td></tr></table></body></html>",
+        );
+        assert_eq!(vec![None, Some(ElementKind::Table)], child_kinds(&body));
+        let text = body.borrow().first_child().unwrap();
+        let kind = text.borrow().kind();
+        match kind {
+            NodeKind::Text(text) => assert_eq!("oops", text),
+            kind => panic!("expected a text node, but got {:?}", kind),
+        }
+    }
+
+    #[test]
+    fn test_list_implied_end_tags() {
+        let body = body_of(
+            "<html><head></head><body><ul><li>a<li>b<ol><li>c<li>d</ol></ul><p>after</p></body></html>",
+        );
+        assert_eq!(
+            vec![Some(ElementKind::Ul), Some(ElementKind::P)],
+            child_kinds(&body)
+        );
+
+        let ul = body.borrow().first_child().unwrap();
+        assert_eq!(
+            vec![Some(ElementKind::Li), Some(ElementKind::Li)],
+            child_kinds(&ul)
+        );
+        // 入れ子のリストのli要素は、外側のli要素を閉じない
+        let second = ul.borrow().last_child().upgrade().unwrap();
+        assert_eq!(vec![None, Some(ElementKind::Ol)], child_kinds(&second));
+        let ol = second.borrow().last_child().upgrade().unwrap();
+        assert_eq!(
+            vec![Some(ElementKind::Li), Some(ElementKind::Li)],
+            child_kinds(&ol)
+        );
+    }
+
+    #[test]
+    fn test_img_is_void_element() {
+        let body = body_of(
+            "<html><head></head><body><p>a<img src=\"x.png\" width=10>b<img src=y.gif />c</p></body></html>",
+        );
+        let p = body.borrow().first_child().unwrap();
+        assert_eq!(
+            vec![
+                None,
+                Some(ElementKind::Img),
+                None,
+                Some(ElementKind::Img),
+                None
+            ],
+            child_kinds(&p)
+        );
+
+        // 後ろのテキストはimg要素の子にならない
+        let img = p
+            .borrow()
+            .first_child()
+            .unwrap()
+            .borrow()
+            .next_sibling()
+            .unwrap();
+        assert!(img.borrow().first_child().is_none());
+        let element = img.borrow().get_element().unwrap();
+        assert_eq!(Some("x.png".to_string()), element.get_attribute("src"));
+        assert_eq!(Some("10".to_string()), element.get_attribute("width"));
+        let img = p.borrow().last_child().upgrade().unwrap();
+        let img = img.borrow().previous_sibling().upgrade().unwrap();
+        let element = img.borrow().get_element().unwrap();
+        assert_eq!(Some("y.gif".to_string()), element.get_attribute("src"));
+    }
+
+    #[test]
+    fn test_execute_scripts_while_parsing() {
+        let html = "<html><head><script>a</script></head><body><p>x</p><script>b</script><p>y</p></body></html>".to_string();
+        let mut parser = HtmlParser::new(HtmlTokenizer::new(html));
+        let document = parser.window().borrow().document();
+        let mut executed = Vec::new();
+        parser.construct_tree_with_scripts(|script| {
+            // スクリプトより後ろの要素はまだ文書にない
+            let text = get_text_content(script);
+            let html = document.borrow().first_child().unwrap();
+            let last = html.borrow().last_child().upgrade().unwrap();
+            executed.push((text, child_kinds(&last)));
+        });
+
+        assert_eq!(
+            vec![
+                ("a".to_string(), vec![Some(ElementKind::Script)]),
+                (
+                    "b".to_string(),
+                    vec![Some(ElementKind::P), Some(ElementKind::Script)]
+                ),
+            ],
+            executed
+        );
+    }
 }
@@ -76,6 +76,13 @@ impl HtmlTokenizer {
         self.latest_token = None;
         assert!(self.latest_token.is_none());
 
+        // scriptの中身は、終了タグが来るまでタグとして解釈しない
+        if let Some(HtmlToken::StartTag { ref tag, .. }) = t {
+            if tag == "script" {
+                self.state = State::ScriptData;
+            }
+        }
+
         t
     }
 
@@ -439,7 +446,8 @@ impl Iterator for HtmlTokenizer {
                     return Some(HtmlToken::Char('<'));
                 }
                 State::ScriptDataEndTagName => {
-                    if c == '>' {
+                    // `</script>`以外の終了タグは文字として扱う
+                    if c == '>' && self.buf.eq_ignore_ascii_case("script") {
                         self.state = State::Data;
                         return self.take_latest_token();
                     }
@@ -589,4 +597,22 @@ mod tests {
             assert_eq!(Some(e), tokenizer.next());
         }
     }
+
+    #[test]
+    fn test_script_data() {
+        let html = "<script>a<b</p> </script><p>".to_string();
+        let tokenizer = HtmlTokenizer::new(html);
+        let mut text = String::new();
+        let mut tags = Vec::new();
+        for token in tokenizer {
+            match token {
+                HtmlToken::Char(c) => text.push(c),
+                HtmlToken::StartTag { tag, .. } => tags.push(tag),
+                HtmlToken::EndTag { tag } => tags.push("/".to_string() + &tag),
+                HtmlToken::Eof => {}
+            }
+        }
+        assert_eq!("a<b</p> ", text);
+        assert_eq!(vec!["script", "/script", "p"], tags);
+    }
 }
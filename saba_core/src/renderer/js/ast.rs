@@ -1,30 +1,71 @@
+use crate::error::Error;
 use crate::renderer::js::token::JsLexer;
+use crate::renderer::js::token::Lexeme;
+use crate::renderer::js::token::Token;
+use alloc::format;
 use alloc::rc::Rc;
 use alloc::string::String;
+use alloc::string::ToString;
+use alloc::vec;
 use alloc::vec::Vec;
-use core::iter::Peekable;
 
-use super::token::Token;
+/// オブジェクトリテラルのプロパティの種類
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PropertyKind {
+    Init,
+    Get,
+    Set,
+}
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum Node {
     ExpressionStatement(Option<Rc<Node>>),
-    AdditiveExpression {
-        operator: char,
+    /// 算術、比較、ビット演算などの二項演算
+    BinaryExpression {
+        operator: &'static str,
+        left: Option<Rc<Node>>,
+        right: Option<Rc<Node>>,
+    },
+    /// 短絡評価する`&&`、`||`、`??`
+    LogicalExpression {
+        operator: &'static str,
         left: Option<Rc<Node>>,
         right: Option<Rc<Node>>,
     },
+    UnaryExpression {
+        operator: &'static str,
+        argument: Option<Rc<Node>>,
+    },
+    /// `++`と`--`
+    UpdateExpression {
+        operator: &'static str,
+        prefix: bool,
+        argument: Option<Rc<Node>>,
+    },
     AssignmentExpression {
-        operator: char,
+        operator: &'static str,
         left: Option<Rc<Node>>,
         right: Option<Rc<Node>>,
     },
+    ConditionalExpression {
+        test: Option<Rc<Node>>,
+        consequent: Option<Rc<Node>>,
+        alternate: Option<Rc<Node>>,
+    },
+    SequenceExpression {
+        expressions: Vec<Option<Rc<Node>>>,
+    },
+    /// `computed`は`a[b]`の形、`optional`は`a?.b`の形
     MemberExpression {
         object: Option<Rc<Node>>,
         property: Option<Rc<Node>>,
+        computed: bool,
+        optional: bool,
     },
-    NumberLiteral(u64),
+    NumberLiteral(f64),
+    /// `kind`は`var`、`let`、`const`のどれか
     VariableDecration {
+        kind: &'static str,
         declarations: Vec<Option<Rc<Node>>>,
     },
     VariableDeclarator {
@@ -33,21 +74,133 @@ pub enum Node {
     },
     Identifier(String),
     StringLiteral(String),
+    BooleanLiteral(bool),
+    NullLiteral,
+    RegExpLiteral {
+        pattern: String,
+        flags: String,
+    },
+    /// `quasis`は`expressions`より1つ多い
+    TemplateLiteral {
+        quasis: Vec<String>,
+        expressions: Vec<Option<Rc<Node>>>,
+    },
+    ThisExpression,
+    ArrayExpression {
+        /// 省略された要素はNone
+        elements: Vec<Option<Rc<Node>>>,
+    },
+    ObjectExpression {
+        properties: Vec<Option<Rc<Node>>>,
+    },
+    Property {
+        key: Option<Rc<Node>>,
+        value: Option<Rc<Node>>,
+        kind: PropertyKind,
+        computed: bool,
+    },
+    SpreadElement(Option<Rc<Node>>),
+    /// 引数の既定値`a = 1`
+    AssignmentPattern {
+        left: Option<Rc<Node>>,
+        right: Option<Rc<Node>>,
+    },
+    /// 残余引数`...args`
+    RestElement(Option<Rc<Node>>),
     BlockStatement {
         body: Vec<Option<Rc<Node>>>,
     },
+    EmptyStatement,
     ReturnStatement {
         argument: Option<Rc<Node>>,
     },
+    IfStatement {
+        test: Option<Rc<Node>>,
+        consequent: Option<Rc<Node>>,
+        alternate: Option<Rc<Node>>,
+    },
+    ForStatement {
+        init: Option<Rc<Node>>,
+        test: Option<Rc<Node>>,
+        update: Option<Rc<Node>>,
+        body: Option<Rc<Node>>,
+    },
+    ForInStatement {
+        left: Option<Rc<Node>>,
+        right: Option<Rc<Node>>,
+        body: Option<Rc<Node>>,
+    },
+    ForOfStatement {
+        left: Option<Rc<Node>>,
+        right: Option<Rc<Node>>,
+        body: Option<Rc<Node>>,
+    },
+    WhileStatement {
+        test: Option<Rc<Node>>,
+        body: Option<Rc<Node>>,
+    },
+    DoWhileStatement {
+        body: Option<Rc<Node>>,
+        test: Option<Rc<Node>>,
+    },
+    SwitchStatement {
+        discriminant: Option<Rc<Node>>,
+        cases: Vec<Option<Rc<Node>>>,
+    },
+    /// `test`がNoneなら`default`
+    SwitchCase {
+        test: Option<Rc<Node>>,
+        consequent: Vec<Option<Rc<Node>>>,
+    },
+    LabeledStatement {
+        label: String,
+        body: Option<Rc<Node>>,
+    },
+    BreakStatement {
+        label: Option<String>,
+    },
+    ContinueStatement {
+        label: Option<String>,
+    },
+    ThrowStatement {
+        argument: Option<Rc<Node>>,
+    },
+    TryStatement {
+        block: Option<Rc<Node>>,
+        handler: Option<Rc<Node>>,
+        finalizer: Option<Rc<Node>>,
+    },
+    /// `catch {}`のように引数を省略した場合、`param`はNone
+    CatchClause {
+        param: Option<Rc<Node>>,
+        body: Option<Rc<Node>>,
+    },
     FunctionDeclaration {
         id: Option<Rc<Node>>,
         params: Vec<Option<Rc<Node>>>,
         body: Option<Rc<Node>>,
     },
+    FunctionExpression {
+        id: Option<Rc<Node>>,
+        params: Vec<Option<Rc<Node>>>,
+        body: Option<Rc<Node>>,
+    },
+    /// 式だけの本体は、その式を返すBlockStatementにする
+    ArrowFunctionExpression {
+        params: Vec<Option<Rc<Node>>>,
+        body: Option<Rc<Node>>,
+    },
     CallExpression {
         callee: Option<Rc<Node>>,
         arguments: Vec<Option<Rc<Node>>>,
+        optional: bool,
     },
+    NewExpression {
+        callee: Option<Rc<Node>>,
+        arguments: Vec<Option<Rc<Node>>>,
+    },
+    /// `?.`を含むメンバーアクセスと関数呼び出しの連なり
+    ChainExpression(Option<Rc<Node>>),
 }
 
 impl Node {
@@ -55,20 +208,51 @@ impl Node {
         Some(Rc::new(Node::ExpressionStatement(expression)))
     }
 
-    pub fn new_additive_expression(
-        operator: char,
+    pub fn new_binary_expression(
+        operator: &'static str,
         left: Option<Rc<Self>>,
         right: Option<Rc<Self>>,
     ) -> Option<Rc<Self>> {
-        Some(Rc::new(Node::AdditiveExpression {
+        Some(Rc::new(Node::BinaryExpression {
             operator,
             left,
             right,
         }))
     }
 
+    pub fn new_logical_expression(
+        operator: &'static str,
+        left: Option<Rc<Self>>,
+        right: Option<Rc<Self>>,
+    ) -> Option<Rc<Self>> {
+        Some(Rc::new(Node::LogicalExpression {
+            operator,
+            left,
+            right,
+        }))
+    }
+
+    pub fn new_unary_expression(
+        operator: &'static str,
+        argument: Option<Rc<Self>>,
+    ) -> Option<Rc<Self>> {
+        Some(Rc::new(Node::UnaryExpression { operator, argument }))
+    }
+
+    pub fn new_update_expression(
+        operator: &'static str,
+        prefix: bool,
+        argument: Option<Rc<Self>>,
+    ) -> Option<Rc<Self>> {
+        Some(Rc::new(Node::UpdateExpression {
+            operator,
+            prefix,
+            argument,
+        }))
+    }
+
     pub fn new_assignment_expression(
-        operator: char,
+        operator: &'static str,
         left: Option<Rc<Self>>,
         right: Option<Rc<Self>>,
     ) -> Option<Rc<Self>> {
@@ -79,14 +263,37 @@ impl Node {
         }))
     }
 
+    pub fn new_conditional_expression(
+        test: Option<Rc<Self>>,
+        consequent: Option<Rc<Self>>,
+        alternate: Option<Rc<Self>>,
+    ) -> Option<Rc<Self>> {
+        Some(Rc::new(Node::ConditionalExpression {
+            test,
+            consequent,
+            alternate,
+        }))
+    }
+
+    pub fn new_sequence_expression(expressions: Vec<Option<Rc<Self>>>) -> Option<Rc<Self>> {
+        Some(Rc::new(Node::SequenceExpression { expressions }))
+    }
+
     pub fn new_member_expression(
         object: Option<Rc<Self>>,
         property: Option<Rc<Self>>,
+        computed: bool,
+        optional: bool,
     ) -> Option<Rc<Self>> {
-        Some(Rc::new(Node::MemberExpression { object, property }))
+        Some(Rc::new(Node::MemberExpression {
+            object,
+            property,
+            computed,
+            optional,
+        }))
     }
 
-    pub fn new_numeric_literal(value: u64) -> Option<Rc<Self>> {
+    pub fn new_numeric_literal(value: f64) -> Option<Rc<Self>> {
         Some(Rc::new(Node::NumberLiteral(value)))
     }
 
@@ -97,8 +304,11 @@ impl Node {
         Some(Rc::new(Node::VariableDeclarator { id, init }))
     }
 
-    pub fn new_variable_declaration(declarations: Vec<Option<Rc<Self>>>) -> Option<Rc<Self>> {
-        Some(Rc::new(Node::VariableDecration { declarations }))
+    pub fn new_variable_declaration(
+        kind: &'static str,
+        declarations: Vec<Option<Rc<Self>>>,
+    ) -> Option<Rc<Self>> {
+        Some(Rc::new(Node::VariableDecration { kind, declarations }))
     }
 
     pub fn new_identifier(name: String) -> Option<Rc<Self>> {
@@ -109,14 +319,183 @@ impl Node {
         Some(Rc::new(Node::StringLiteral(value)))
     }
 
+    pub fn new_boolean_literal(value: bool) -> Option<Rc<Self>> {
+        Some(Rc::new(Node::BooleanLiteral(value)))
+    }
+
+    pub fn new_null_literal() -> Option<Rc<Self>> {
+        Some(Rc::new(Node::NullLiteral))
+    }
+
+    pub fn new_regexp_literal(pattern: String, flags: String) -> Option<Rc<Self>> {
+        Some(Rc::new(Node::RegExpLiteral { pattern, flags }))
+    }
+
+    pub fn new_template_literal(
+        quasis: Vec<String>,
+        expressions: Vec<Option<Rc<Self>>>,
+    ) -> Option<Rc<Self>> {
+        Some(Rc::new(Node::TemplateLiteral {
+            quasis,
+            expressions,
+        }))
+    }
+
+    pub fn new_this_expression() -> Option<Rc<Self>> {
+        Some(Rc::new(Node::ThisExpression))
+    }
+
+    pub fn new_array_expression(elements: Vec<Option<Rc<Self>>>) -> Option<Rc<Self>> {
+        Some(Rc::new(Node::ArrayExpression { elements }))
+    }
+
+    pub fn new_object_expression(properties: Vec<Option<Rc<Self>>>) -> Option<Rc<Self>> {
+        Some(Rc::new(Node::ObjectExpression { properties }))
+    }
+
+    pub fn new_property(
+        key: Option<Rc<Self>>,
+        value: Option<Rc<Self>>,
+        kind: PropertyKind,
+        computed: bool,
+    ) -> Option<Rc<Self>> {
+        Some(Rc::new(Node::Property {
+            key,
+            value,
+            kind,
+            computed,
+        }))
+    }
+
+    pub fn new_spread_element(argument: Option<Rc<Self>>) -> Option<Rc<Self>> {
+        Some(Rc::new(Node::SpreadElement(argument)))
+    }
+
+    pub fn new_assignment_pattern(
+        left: Option<Rc<Self>>,
+        right: Option<Rc<Self>>,
+    ) -> Option<Rc<Self>> {
+        Some(Rc::new(Node::AssignmentPattern { left, right }))
+    }
+
+    pub fn new_rest_element(argument: Option<Rc<Self>>) -> Option<Rc<Self>> {
+        Some(Rc::new(Node::RestElement(argument)))
+    }
+
     pub fn new_block_statement(body: Vec<Option<Rc<Self>>>) -> Option<Rc<Self>> {
         Some(Rc::new(Node::BlockStatement { body }))
     }
 
+    pub fn new_empty_statement() -> Option<Rc<Self>> {
+        Some(Rc::new(Node::EmptyStatement))
+    }
+
     pub fn new_return_statement(argument: Option<Rc<Self>>) -> Option<Rc<Self>> {
         Some(Rc::new(Node::ReturnStatement { argument }))
     }
 
+    pub fn new_if_statement(
+        test: Option<Rc<Self>>,
+        consequent: Option<Rc<Self>>,
+        alternate: Option<Rc<Self>>,
+    ) -> Option<Rc<Self>> {
+        Some(Rc::new(Node::IfStatement {
+            test,
+            consequent,
+            alternate,
+        }))
+    }
+
+    pub fn new_for_statement(
+        init: Option<Rc<Self>>,
+        test: Option<Rc<Self>>,
+        update: Option<Rc<Self>>,
+        body: Option<Rc<Self>>,
+    ) -> Option<Rc<Self>> {
+        Some(Rc::new(Node::ForStatement {
+            init,
+            test,
+            update,
+            body,
+        }))
+    }
+
+    pub fn new_for_in_statement(
+        left: Option<Rc<Self>>,
+        right: Option<Rc<Self>>,
+        body: Option<Rc<Self>>,
+    ) -> Option<Rc<Self>> {
+        Some(Rc::new(Node::ForInStatement { left, right, body }))
+    }
+
+    pub fn new_for_of_statement(
+        left: Option<Rc<Self>>,
+        right: Option<Rc<Self>>,
+        body: Option<Rc<Self>>,
+    ) -> Option<Rc<Self>> {
+        Some(Rc::new(Node::ForOfStatement { left, right, body }))
+    }
+
+    pub fn new_while_statement(test: Option<Rc<Self>>, body: Option<Rc<Self>>) -> Option<Rc<Self>> {
+        Some(Rc::new(Node::WhileStatement { test, body }))
+    }
+
+    pub fn new_do_while_statement(
+        body: Option<Rc<Self>>,
+        test: Option<Rc<Self>>,
+    ) -> Option<Rc<Self>> {
+        Some(Rc::new(Node::DoWhileStatement { body, test }))
+    }
+
+    pub fn new_switch_statement(
+        discriminant: Option<Rc<Self>>,
+        cases: Vec<Option<Rc<Self>>>,
+    ) -> Option<Rc<Self>> {
+        Some(Rc::new(Node::SwitchStatement {
+            discriminant,
+            cases,
+        }))
+    }
+
+    pub fn new_switch_case(
+        test: Option<Rc<Self>>,
+        consequent: Vec<Option<Rc<Self>>>,
+    ) -> Option<Rc<Self>> {
+        Some(Rc::new(Node::SwitchCase { test, consequent }))
+    }
+
+    pub fn new_labeled_statement(label: String, body: Option<Rc<Self>>) -> Option<Rc<Self>> {
+        Some(Rc::new(Node::LabeledStatement { label, body }))
+    }
+
+    pub fn new_break_statement(label: Option<String>) -> Option<Rc<Self>> {
+        Some(Rc::new(Node::BreakStatement { label }))
+    }
+
+    pub fn new_continue_statement(label: Option<String>) -> Option<Rc<Self>> {
+        Some(Rc::new(Node::ContinueStatement { label }))
+    }
+
+    pub fn new_throw_statement(argument: Option<Rc<Self>>) -> Option<Rc<Self>> {
+        Some(Rc::new(Node::ThrowStatement { argument }))
+    }
+
+    pub fn new_try_statement(
+        block: Option<Rc<Self>>,
+        handler: Option<Rc<Self>>,
+        finalizer: Option<Rc<Self>>,
+    ) -> Option<Rc<Self>> {
+        Some(Rc::new(Node::TryStatement {
+            block,
+            handler,
+            finalizer,
+        }))
+    }
+
+    pub fn new_catch_clause(param: Option<Rc<Self>>, body: Option<Rc<Self>>) -> Option<Rc<Self>> {
+        Some(Rc::new(Node::CatchClause { param, body }))
+    }
+
     pub fn new_function_declaration(
         id: Option<Rc<Self>>,
         params: Vec<Option<Rc<Self>>>,
@@ -125,15 +504,54 @@ impl Node {
         Some(Rc::new(Node::FunctionDeclaration { id, params, body }))
     }
 
+    pub fn new_function_expression(
+        id: Option<Rc<Self>>,
+        params: Vec<Option<Rc<Self>>>,
+        body: Option<Rc<Self>>,
+    ) -> Option<Rc<Self>> {
+        Some(Rc::new(Node::FunctionExpression { id, params, body }))
+    }
+
+    pub fn new_arrow_function_expression(
+        params: Vec<Option<Rc<Self>>>,
+        body: Option<Rc<Self>>,
+    ) -> Option<Rc<Self>> {
+        Some(Rc::new(Node::ArrowFunctionExpression { params, body }))
+    }
+
     pub fn new_call_expression(
         callee: Option<Rc<Self>>,
         arguments: Vec<Option<Rc<Self>>>,
+        optional: bool,
     ) -> Option<Rc<Self>> {
-        Some(Rc::new(Node::CallExpression { callee, arguments }))
+        Some(Rc::new(Node::CallExpression {
+            callee,
+            arguments,
+            optional,
+        }))
+    }
+
+    pub fn new_new_expression(
+        callee: Option<Rc<Self>>,
+        arguments: Vec<Option<Rc<Self>>>,
+    ) -> Option<Rc<Self>> {
+        Some(Rc::new(Node::NewExpression { callee, arguments }))
+    }
+
+    pub fn new_chain_expression(expression: Option<Rc<Self>>) -> Option<Rc<Self>> {
+        Some(Rc::new(Node::ChainExpression(expression)))
+    }
+
+    /// 代入や`++`の対象にできるかどうか
+    fn is_simple_assignment_target(node: &Option<Rc<Self>>) -> bool {
+        matches!(
+            node.as_deref(),
+            Some(Node::Identifier(_)) | Some(Node::MemberExpression { .. })
+        )
     }
 }
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct Program {
     body: Vec<Rc<Node>>,
 }
@@ -143,336 +561,1449 @@ impl Program {
         Self { body: Vec::new() }
     }
 
-    pub fn set_body(&mut self, body: Vec<Rc<Node>>) {
-        self.body = body
+    pub fn set_body(&mut self, body: Vec<Rc<Node>>) {
+        self.body = body
+    }
+
+    pub fn body(&self) -> &Vec<Rc<Node>> {
+        &self.body
+    }
+}
+
+/// 二項演算子と結合の強さ
+fn binary_operator(token: &Token) -> Option<(&'static str, u8)> {
+    let operator = match token {
+        Token::Punctuator(p) => *p,
+        Token::Keyword(k @ ("in" | "instanceof")) => *k,
+        _ => return None,
+    };
+
+    let precedence = match operator {
+        "??" => 1,
+        "||" => 2,
+        "&&" => 3,
+        "|" => 4,
+        "^" => 5,
+        "&" => 6,
+        "==" | "!=" | "===" | "!==" => 7,
+        "<" | ">" | "<=" | ">=" | "instanceof" | "in" => 8,
+        "<<" | ">>" | ">>>" => 9,
+        "+" | "-" => 10,
+        "*" | "/" | "%" => 11,
+        "**" => 12,
+        _ => return None,
+    };
+    Some((operator, precedence))
+}
+
+fn is_assignment_operator(operator: &str) -> bool {
+    matches!(
+        operator,
+        "=" | "+="
+            | "-="
+            | "*="
+            | "/="
+            | "%="
+            | "**="
+            | "<<="
+            | ">>="
+            | ">>>="
+            | "&="
+            | "|="
+            | "^="
+    )
+}
+
+/// 式や文を入れ子にできる深さ
+/// 構文解析も、できた木の評価やコンパイルも再帰するため、深すぎる入力はスタックを使い切る前にエラーにする
+const MAX_NESTING_DEPTH: usize = 256;
+
+/// `return`、`break`、`continue`を書けるかどうかを決める、文を囲む構造
+#[derive(Debug, Clone, Default)]
+struct StatementContext {
+    in_function: bool,
+    /// ラベルと、それが繰り返しの文についているかどうか
+    labels: Vec<(String, bool)>,
+    iterations: usize,
+    switches: usize,
+}
+
+pub struct JsParser {
+    tokens: Vec<Lexeme>,
+    pos: usize,
+    /// 字句解析のエラー。そこまでのトークンを読み終えたら返す
+    lex_error: Option<Error>,
+    /// for文の初期化式では`in`を演算子として読まない
+    no_in: bool,
+    context: StatementContext,
+    /// 読んでいる式や文の入れ子の深さ
+    depth: usize,
+}
+
+impl JsParser {
+    pub fn new(t: JsLexer) -> Self {
+        let mut tokens = Vec::new();
+        let mut lex_error = None;
+        for lexeme in t {
+            match lexeme {
+                Ok(lexeme) => tokens.push(lexeme),
+                Err(e) => lex_error = Some(e),
+            }
+        }
+
+        Self {
+            tokens,
+            pos: 0,
+            lex_error,
+            no_in: false,
+            context: StatementContext::default(),
+            depth: 0,
+        }
+    }
+
+    /// 括弧の中などで、`in`を演算子として読めるようにして`f`を呼ぶ
+    fn allow_in<T>(&mut self, f: impl FnOnce(&mut Self) -> Result<T, Error>) -> Result<T, Error> {
+        let no_in = core::mem::replace(&mut self.no_in, false);
+        let result = f(self);
+        self.no_in = no_in;
+        result
+    }
+
+    /// 入れ子を`levels`段深くして`f`を呼ぶ
+    fn nested<T>(
+        &mut self,
+        levels: usize,
+        f: impl FnOnce(&mut Self) -> Result<T, Error>,
+    ) -> Result<T, Error> {
+        if self.depth + levels > MAX_NESTING_DEPTH {
+            return Err(self.error_at(self.pos, "too much nesting"));
+        }
+        self.depth += levels;
+        let result = f(self);
+        self.depth -= levels;
+        result
+    }
+
+    fn peek(&self) -> Option<&Token> {
+        self.peek_at(0)
+    }
+
+    fn peek_at(&self, offset: usize) -> Option<&Token> {
+        self.tokens.get(self.pos + offset).map(|t| t.token())
+    }
+
+    fn advance(&mut self) -> Option<Token> {
+        let token = self.peek().cloned();
+        if token.is_some() {
+            self.pos += 1;
+        }
+        token
+    }
+
+    fn is_punctuator(&self, punctuator: &str) -> bool {
+        matches!(self.peek(), Some(Token::Punctuator(p)) if *p == punctuator)
+    }
+
+    fn is_keyword(&self, keyword: &str) -> bool {
+        matches!(self.peek(), Some(Token::Keyword(k)) if *k == keyword)
+    }
+
+    /// 次のトークンが`punctuator`なら読み進める
+    fn eat(&mut self, punctuator: &str) -> bool {
+        if self.is_punctuator(punctuator) {
+            self.pos += 1;
+            return true;
+        }
+        false
+    }
+
+    fn expect(&mut self, punctuator: &str) -> Result<(), Error> {
+        if self.eat(punctuator) {
+            return Ok(());
+        }
+        Err(self.unexpected())
+    }
+
+    /// 次のトークンの前に改行があるかどうか
+    fn newline_before(&self) -> bool {
+        self.tokens
+            .get(self.pos)
+            .map_or(false, |t| t.newline_before())
+    }
+
+    fn unexpected(&self) -> Error {
+        match self.tokens.get(self.pos) {
+            Some(lexeme) => Error::UnexpectedInput(format!(
+                "{}:{}: unexpected token {:?}",
+                lexeme.position().line(),
+                lexeme.position().column(),
+                lexeme.token()
+            )),
+            None => match &self.lex_error {
+                Some(e) => e.clone(),
+                None => Error::UnexpectedInput("unexpected end of input".to_string()),
+            },
+        }
+    }
+
+    fn error_at(&self, index: usize, message: &str) -> Error {
+        match self.tokens.get(index) {
+            Some(lexeme) => Error::UnexpectedInput(format!(
+                "{}:{}: {}",
+                lexeme.position().line(),
+                lexeme.position().column(),
+                message
+            )),
+            None => Error::UnexpectedInput(message.to_string()),
+        }
+    }
+
+    /// 文の終わりの`;`を読む。省略されていれば自動で挿入されたものとする
+    fn consume_semicolon(&mut self) -> Result<(), Error> {
+        if self.eat(";") || self.is_punctuator("}") || self.newline_before() {
+            return Ok(());
+        }
+        if self.peek().is_none() && self.lex_error.is_none() {
+            return Ok(());
+        }
+        Err(self.unexpected())
+    }
+
+    fn identifier(&mut self) -> Result<Option<Rc<Node>>, Error> {
+        match self.peek() {
+            Some(Token::Identifier(name)) => {
+                let name = name.clone();
+                self.pos += 1;
+                Ok(Node::new_identifier(name))
+            }
+            _ => Err(self.unexpected()),
+        }
+    }
+
+    /// `.`のあとのプロパティ名。予約語も使える
+    fn identifier_name(&mut self) -> Result<Option<Rc<Node>>, Error> {
+        let name = match self.peek() {
+            Some(Token::Identifier(name)) => name.clone(),
+            Some(Token::Keyword(keyword)) => keyword.to_string(),
+            _ => return Err(self.unexpected()),
+        };
+        self.pos += 1;
+        Ok(Node::new_identifier(name))
+    }
+
+    fn template_literal(&mut self) -> Result<Option<Rc<Node>>, Error> {
+        let mut quasis = Vec::new();
+        let mut expressions = Vec::new();
+
+        match self.advance() {
+            Some(Token::NoSubstitutionTemplate(s)) => {
+                quasis.push(s);
+                return Ok(Node::new_template_literal(quasis, expressions));
+            }
+            Some(Token::TemplateHead(s)) => quasis.push(s),
+            _ => return Err(self.unexpected()),
+        }
+
+        loop {
+            expressions.push(self.allow_in(Self::expression)?);
+            match self.peek() {
+                Some(Token::TemplateMiddle(s)) => quasis.push(s.clone()),
+                Some(Token::TemplateTail(s)) => {
+                    quasis.push(s.clone());
+                    self.pos += 1;
+                    return Ok(Node::new_template_literal(quasis, expressions));
+                }
+                _ => return Err(self.unexpected()),
+            }
+            self.pos += 1;
+        }
+    }
+
+    fn array_literal(&mut self) -> Result<Option<Rc<Node>>, Error> {
+        self.expect("[")?;
+        let mut elements = Vec::new();
+
+        loop {
+            if self.eat("]") {
+                return Ok(Node::new_array_expression(elements));
+            }
+            if self.eat(",") {
+                elements.push(None);
+                continue;
+            }
+
+            if self.eat("...") {
+                elements.push(Node::new_spread_element(self.assignment_expression()?));
+            } else {
+                elements.push(self.assignment_expression()?);
+            }
+            if !self.is_punctuator("]") {
+                self.expect(",")?;
+            }
+        }
+    }
+
+    /// プロパティ名と、`[式]`の形かどうか
+    fn property_name(&mut self) -> Result<(Option<Rc<Node>>, bool), Error> {
+        let key = match self.peek() {
+            Some(Token::StringLiteral(s)) => Node::new_string_literal(s.clone()),
+            Some(Token::Number(n)) => Node::new_numeric_literal(*n),
+            Some(Token::Punctuator("[")) => {
+                self.pos += 1;
+                let key = self.assignment_expression()?;
+                self.expect("]")?;
+                return Ok((key, true));
+            }
+            _ => return Ok((self.identifier_name()?, false)),
+        };
+        self.pos += 1;
+        Ok((key, false))
+    }
+
+    fn property_definition(&mut self) -> Result<Option<Rc<Node>>, Error> {
+        if self.eat("...") {
+            return Ok(Node::new_spread_element(self.assignment_expression()?));
+        }
+
+        // `get`と`set`のあとにプロパティ名が続けばアクセサ
+        let mut kind = PropertyKind::Init;
+        if let Some(Token::Identifier(name)) = self.peek() {
+            let accessor = match name.as_str() {
+                "get" => Some(PropertyKind::Get),
+                "set" => Some(PropertyKind::Set),
+                _ => None,
+            };
+            let followed_by_name = !matches!(
+                self.peek_at(1),
+                Some(Token::Punctuator(","))
+                    | Some(Token::Punctuator(":"))
+                    | Some(Token::Punctuator("("))
+                    | Some(Token::Punctuator("}"))
+                    | None
+            );
+            if let (Some(accessor), true) = (accessor, followed_by_name) {
+                kind = accessor;
+                self.pos += 1;
+            }
+        }
+
+        let shorthand = matches!(self.peek(), Some(Token::Identifier(_)));
+        let (key, computed) = self.property_name()?;
+
+        let value = if kind != PropertyKind::Init || self.is_punctuator("(") {
+            let params = self.parameter_list()?;
+            Node::new_function_expression(None, params, self.function_body()?)
+        } else if self.eat(":") {
+            self.assignment_expression()?
+        } else if shorthand && !computed {
+            key.clone()
+        } else {
+            return Err(self.unexpected());
+        };
+
+        Ok(Node::new_property(key, value, kind, computed))
+    }
+
+    fn object_literal(&mut self) -> Result<Option<Rc<Node>>, Error> {
+        self.expect("{")?;
+        let mut properties = Vec::new();
+
+        loop {
+            if self.eat("}") {
+                return Ok(Node::new_object_expression(properties));
+            }
+            properties.push(self.property_definition()?);
+            if !self.is_punctuator("}") {
+                self.expect(",")?;
+            }
+        }
+    }
+
+    fn primary_expression(&mut self) -> Result<Option<Rc<Node>>, Error> {
+        let t = match self.peek() {
+            Some(token) => token.clone(),
+            None => return Err(self.unexpected()),
+        };
+
+        let node = match t {
+            Token::Identifier(value) => Node::new_identifier(value),
+            Token::StringLiteral(value) => Node::new_string_literal(value),
+            Token::Number(n) => Node::new_numeric_literal(n),
+            Token::RegularExpression { pattern, flags } => Node::new_regexp_literal(pattern, flags),
+            Token::Keyword("this") => Node::new_this_expression(),
+            Token::Keyword("null") => Node::new_null_literal(),
+            Token::Keyword("true") => Node::new_boolean_literal(true),
+            Token::Keyword("false") => Node::new_boolean_literal(false),
+            Token::Keyword("function") => return self.function_expression(),
+            Token::NoSubstitutionTemplate(_) | Token::TemplateHead(_) => {
+                return self.template_literal()
+            }
+            Token::Punctuator("[") => return self.allow_in(Self::array_literal),
+            Token::Punctuator("{") => return self.allow_in(Self::object_literal),
+            Token::Punctuator("(") => {
+                self.pos += 1;
+                let expr = self.allow_in(Self::expression)?;
+                self.expect(")")?;
+                return Ok(expr);
+            }
+            _ => return Err(self.unexpected()),
+        };
+
+        self.pos += 1;
+        Ok(node)
+    }
+
+    fn arguments(&mut self) -> Result<Vec<Option<Rc<Node>>>, Error> {
+        self.allow_in(Self::argument_list)
+    }
+
+    fn argument_list(&mut self) -> Result<Vec<Option<Rc<Node>>>, Error> {
+        self.expect("(")?;
+        let mut arguments = Vec::new();
+
+        loop {
+            if self.eat(")") {
+                return Ok(arguments);
+            }
+            if self.eat("...") {
+                arguments.push(Node::new_spread_element(self.assignment_expression()?));
+            } else {
+                arguments.push(self.assignment_expression()?);
+            }
+            if !self.is_punctuator(")") {
+                self.expect(",")?;
+            }
+        }
+    }
+
+    /// `new`の対象。関数呼び出しを含まないメンバーアクセスまでを読む
+    fn new_expression(&mut self) -> Result<Option<Rc<Node>>, Error> {
+        // `new`を消費する
+        assert!(self.advance().is_some());
+
+        let mut callee = if self.is_keyword("new") {
+            self.nested(1, Self::new_expression)?
+        } else {
+            self.primary_expression()?
+        };
+        // メンバーアクセスが続くと、その数だけ木が深くなる
+        let mut levels = 0;
+
+        loop {
+            levels += 1;
+            if self.eat(".") {
+                let property = self.nested(levels, Self::identifier_name)?;
+                callee = Node::new_member_expression(callee, property, false, false);
+            } else if self.eat("[") {
+                let property = self.nested(levels, |p| p.allow_in(Self::expression))?;
+                self.expect("]")?;
+                callee = Node::new_member_expression(callee, property, true, false);
+            } else {
+                break;
+            }
+        }
+
+        let arguments = if self.is_punctuator("(") {
+            self.nested(levels, Self::arguments)?
+        } else {
+            Vec::new()
+        };
+        Ok(Node::new_new_expression(callee, arguments))
+    }
+
+    fn left_hand_side_expression(&mut self) -> Result<Option<Rc<Node>>, Error> {
+        let mut expr = if self.is_keyword("new") {
+            self.new_expression()?
+        } else {
+            self.primary_expression()?
+        };
+        let mut chain = false;
+        // メンバーアクセスや呼び出しが続くと、その数だけ木が深くなる
+        let mut levels = 0;
+
+        loop {
+            levels += 1;
+            if self.eat(".") {
+                let property = self.nested(levels, Self::identifier_name)?;
+                expr = Node::new_member_expression(expr, property, false, false);
+            } else if self.eat("?.") {
+                chain = true;
+                expr = if self.is_punctuator("(") {
+                    Node::new_call_expression(expr, self.nested(levels, Self::arguments)?, true)
+                } else if self.eat("[") {
+                    let property = self.nested(levels, |p| p.allow_in(Self::expression))?;
+                    self.expect("]")?;
+                    Node::new_member_expression(expr, property, true, true)
+                } else {
+                    let property = self.nested(levels, Self::identifier_name)?;
+                    Node::new_member_expression(expr, property, false, true)
+                };
+            } else if self.eat("[") {
+                let property = self.nested(levels, |p| p.allow_in(Self::expression))?;
+                self.expect("]")?;
+                expr = Node::new_member_expression(expr, property, true, false);
+            } else if self.is_punctuator("(") {
+                expr =
+                    Node::new_call_expression(expr, self.nested(levels, Self::arguments)?, false);
+            } else if let Some(Token::NoSubstitutionTemplate(_) | Token::TemplateHead(_)) =
+                self.peek()
+            {
+                // タグ付きテンプレートには対応していない
+                return Err(self.unexpected());
+            } else {
+                break;
+            }
+        }
+
+        if chain {
+            return Ok(Node::new_chain_expression(expr));
+        }
+        Ok(expr)
+    }
+
+    fn postfix_expression(&mut self) -> Result<Option<Rc<Node>>, Error> {
+        let expr = self.left_hand_side_expression()?;
+
+        // `a\n++b`の`++`はbにかかる
+        if (self.is_punctuator("++") || self.is_punctuator("--")) && !self.newline_before() {
+            if !Node::is_simple_assignment_target(&expr) {
+                return Err(self.unexpected());
+            }
+            let operator = if self.is_punctuator("++") { "++" } else { "--" };
+            self.pos += 1;
+            return Ok(Node::new_update_expression(operator, false, expr));
+        }
+
+        Ok(expr)
+    }
+
+    fn unary_expression(&mut self) -> Result<Option<Rc<Node>>, Error> {
+        let operator = match self.peek() {
+            Some(Token::Punctuator(p @ ("+" | "-" | "!" | "~" | "++" | "--"))) => *p,
+            Some(Token::Keyword(k @ ("delete" | "void" | "typeof"))) => *k,
+            _ => return self.postfix_expression(),
+        };
+        self.pos += 1;
+
+        let argument = self.nested(1, Self::unary_expression)?;
+        if operator == "++" || operator == "--" {
+            if !Node::is_simple_assignment_target(&argument) {
+                return Err(self.unexpected());
+            }
+            return Ok(Node::new_update_expression(operator, true, argument));
+        }
+
+        Ok(Node::new_unary_expression(operator, argument))
+    }
+
+    /// 優先順位が`min_precedence`以上の二項演算子を読む
+    fn binary_expression(&mut self, min_precedence: u8) -> Result<Option<Rc<Node>>, Error> {
+        let starts_with_unary = matches!(
+            self.peek(),
+            Some(Token::Punctuator("+" | "-" | "!" | "~"))
+                | Some(Token::Keyword("delete" | "void" | "typeof"))
+        );
+        let mut left = self.unary_expression()?;
+        // 左結合の演算子が続くと、その数だけ木が深くなる
+        let mut levels = 0;
+
+        loop {
+            let (operator, precedence) = match self.peek().and_then(binary_operator) {
+                Some(("in", _)) if self.no_in => return Ok(left),
+                Some((operator, precedence)) if precedence >= min_precedence => {
+                    (operator, precedence)
+                }
+                _ => return Ok(left),
+            };
+
+            // `-2 ** 2`のように単項演算子の式を`**`の左辺にはできない
+            if operator == "**" && starts_with_unary {
+                return Err(self.unexpected());
+            }
+            self.pos += 1;
+            levels += 1;
+
+            // `**`だけは右結合
+            let right = if operator == "**" {
+                self.nested(levels, |p| p.binary_expression(precedence))?
+            } else {
+                self.nested(levels, |p| p.binary_expression(precedence + 1))?
+            };
+
+            left = match operator {
+                "&&" | "||" | "??" => Node::new_logical_expression(operator, left, right),
+                _ => Node::new_binary_expression(operator, left, right),
+            };
+        }
+    }
+
+    fn conditional_expression(&mut self) -> Result<Option<Rc<Node>>, Error> {
+        let test = self.binary_expression(1)?;
+        if !self.eat("?") {
+            return Ok(test);
+        }
+
+        let consequent = self.allow_in(Self::assignment_expression)?;
+        self.expect(":")?;
+        let alternate = self.assignment_expression()?;
+        Ok(Node::new_conditional_expression(
+            test, consequent, alternate,
+        ))
+    }
+
+    /// 次の`(`から対応する`)`までがアロー関数の引数かどうか
+    fn is_arrow_parameters(&self) -> bool {
+        let mut depth = 0;
+        for (i, lexeme) in self.tokens[self.pos..].iter().enumerate() {
+            match lexeme.token() {
+                Token::Punctuator("(") => depth += 1,
+                Token::Punctuator(")") => {
+                    depth -= 1;
+                    if depth == 0 {
+                        return matches!(
+                            self.tokens.get(self.pos + i + 1),
+                            Some(next) if *next.token() == Token::Punctuator("=>")
+                                && !next.newline_before()
+                        );
+                    }
+                }
+                _ => {}
+            }
+        }
+        false
+    }
+
+    fn arrow_function(&mut self) -> Result<Option<Rc<Node>>, Error> {
+        let params = if self.is_punctuator("(") {
+            self.parameter_list()?
+        } else {
+            vec![self.identifier()?]
+        };
+        self.expect("=>")?;
+
+        let body = if self.is_punctuator("{") {
+            self.function_body()?
+        } else {
+            Node::new_block_statement(vec![Node::new_return_statement(
+                self.assignment_expression()?,
+            )])
+        };
+        Ok(Node::new_arrow_function_expression(params, body))
+    }
+
+    fn assignment_expression(&mut self) -> Result<Option<Rc<Node>>, Error> {
+        self.nested(1, Self::parse_assignment_expression)
     }
 
-    pub fn body(&self) -> &Vec<Rc<Node>> {
-        &self.body
-    }
-}
+    fn parse_assignment_expression(&mut self) -> Result<Option<Rc<Node>>, Error> {
+        let single_parameter_arrow = matches!(self.peek(), Some(Token::Identifier(_)))
+            && matches!(self.peek_at(1), Some(Token::Punctuator("=>")));
+        if single_parameter_arrow || (self.is_punctuator("(") && self.is_arrow_parameters()) {
+            return self.arrow_function();
+        }
 
-pub struct JsParser {
-    t: Peekable<JsLexer>,
-}
+        let expr = self.conditional_expression()?;
 
-impl JsParser {
-    pub fn new(t: JsLexer) -> Self {
-        Self { t: t.peekable() }
+        let operator = match self.peek() {
+            Some(Token::Punctuator(p)) if is_assignment_operator(p) => *p,
+            _ => return Ok(expr),
+        };
+        if !Node::is_simple_assignment_target(&expr) {
+            return Err(self.unexpected());
+        }
+        self.pos += 1;
+
+        Ok(Node::new_assignment_expression(
+            operator,
+            expr,
+            self.assignment_expression()?,
+        ))
     }
 
-    fn primary_expression(&mut self) -> Option<Rc<Node>> {
-        let t = match self.t.next() {
-            Some(token) => token,
-            None => return None,
-        };
+    /// カンマで区切られた式
+    fn expression(&mut self) -> Result<Option<Rc<Node>>, Error> {
+        let expr = self.assignment_expression()?;
+        if !self.is_punctuator(",") {
+            return Ok(expr);
+        }
 
-        match t {
-            Token::Identifier(value) => Node::new_identifier(value),
-            Token::StringLiteral(value) => Node::new_string_literal(value),
-            Token::Number(n) => Node::new_numeric_literal(n),
-            _ => None,
+        let mut expressions = Vec::new();
+        expressions.push(expr);
+        while self.eat(",") {
+            expressions.push(self.assignment_expression()?);
         }
+        Ok(Node::new_sequence_expression(expressions))
     }
 
-    fn member_expression(&mut self) -> Option<Rc<Node>> {
-        let expr = self.primary_expression();
+    /// `let x`の形で始まるかどうか。`let`は予約語ではないため、変数名にもなる
+    fn is_let_declaration(&self) -> bool {
+        matches!(self.peek(), Some(Token::Identifier(name)) if name == "let")
+            && matches!(self.peek_at(1), Some(Token::Identifier(_)))
+    }
 
-        let t = match self.t.peek() {
-            Some(token) => token,
-            None => return expr,
+    /// `var`、`let`、`const`を消費して宣言を読む
+    fn variable_declaration(&mut self) -> Result<Option<Rc<Node>>, Error> {
+        let kind = match self.advance() {
+            Some(Token::Keyword("var")) => "var",
+            Some(Token::Keyword("const")) => "const",
+            _ => "let",
         };
+        let mut declarations = Vec::new();
 
-        match t {
-            Token::Punctuator(c) => {
-                if c == &'.' {
-                    // '.'を消費する
-                    assert!(self.t.next().is_some());
-                    return Node::new_member_expression(expr, self.identifier());
-                }
+        loop {
+            let start = self.pos;
+            let id = self.identifier()?;
+            let init = if self.eat("=") {
+                self.assignment_expression()?
+            } else {
+                None
+            };
+            // for-in文とfor-of文の左辺では初期値を書かない
+            let in_for_head = self.no_in
+                && (self.is_keyword("in")
+                    || matches!(self.peek(), Some(Token::Identifier(name)) if name == "of"));
+            if kind == "const" && init.is_none() && !in_for_head {
+                return Err(self.error_at(start, "missing initializer in const declaration"));
+            }
+            declarations.push(Node::new_variable_declarator(id, init));
 
-                expr
+            if !self.eat(",") {
+                return Ok(Node::new_variable_declaration(kind, declarations));
             }
-            _ => expr,
         }
     }
 
-    fn arguments(&mut self) -> Vec<Option<Rc<Node>>> {
-        let mut arguments = Vec::new();
+    fn block_statement(&mut self) -> Result<Option<Rc<Node>>, Error> {
+        self.expect("{")?;
+        let mut body = Vec::new();
 
         loop {
-            match self.t.peek() {
-                Some(t) => match t {
-                    Token::Punctuator(c) => {
-                        if c == &')' {
-                            assert!(self.t.next().is_some());
-                            return arguments;
-                        }
-                        if c == &',' {
-                            assert!(self.t.next().is_some());
-                        }
-                    }
-                    _ => {
-                        arguments.push(self.assignment_expression());
-                    }
-                },
-                None => return arguments,
+            if self.eat("}") {
+                return Ok(Node::new_block_statement(body));
+            }
+            body.push(self.source_element()?);
+        }
+    }
+
+    /// 改行をはさまずに続く、`break`と`continue`の対象のラベル
+    fn statement_label(&mut self) -> Option<String> {
+        if self.newline_before() {
+            return None;
+        }
+        match self.peek() {
+            Some(Token::Identifier(name)) => {
+                let name = name.clone();
+                self.pos += 1;
+                Some(name)
             }
+            _ => None,
         }
     }
 
-    fn left_hand_side_expression(&mut self) -> Option<Rc<Node>> {
-        let expr = self.member_expression();
+    fn parenthesized_expression(&mut self) -> Result<Option<Rc<Node>>, Error> {
+        self.expect("(")?;
+        let expr = self.allow_in(Self::expression)?;
+        self.expect(")")?;
+        Ok(expr)
+    }
 
-        let t = match self.t.peek() {
-            Some(token) => token,
-            None => return expr,
+    fn if_statement(&mut self) -> Result<Option<Rc<Node>>, Error> {
+        // `if`を消費する
+        assert!(self.advance().is_some());
+
+        let test = self.parenthesized_expression()?;
+        let consequent = self.statement()?;
+        let alternate = if self.is_keyword("else") {
+            self.pos += 1;
+            self.statement()?
+        } else {
+            None
         };
+        Ok(Node::new_if_statement(test, consequent, alternate))
+    }
 
-        match t {
-            Token::Punctuator(c) => {
-                if c == &'(' {
-                    // '('を消費する
-                    assert!(self.t.next().is_some());
-                    return Node::new_call_expression(expr, self.arguments());
-                }
+    /// 繰り返しの本体。中では`break`と`continue`を書ける
+    fn iteration_body(&mut self) -> Result<Option<Rc<Node>>, Error> {
+        self.context.iterations += 1;
+        let body = self.statement();
+        self.context.iterations -= 1;
+        body
+    }
 
-                expr
-            }
-            _ => expr,
-        }
+    fn while_statement(&mut self) -> Result<Option<Rc<Node>>, Error> {
+        // `while`を消費する
+        assert!(self.advance().is_some());
+
+        let test = self.parenthesized_expression()?;
+        Ok(Node::new_while_statement(test, self.iteration_body()?))
     }
 
-    fn additive_expression(&mut self) -> Option<Rc<Node>> {
-        let left = self.left_hand_side_expression();
+    fn do_while_statement(&mut self) -> Result<Option<Rc<Node>>, Error> {
+        // `do`を消費する
+        assert!(self.advance().is_some());
 
-        let t = match self.t.peek() {
-            Some(token) => token.clone(),
-            None => return left,
-        };
+        let body = self.iteration_body()?;
+        if !self.is_keyword("while") {
+            return Err(self.unexpected());
+        }
+        self.pos += 1;
+        let test = self.parenthesized_expression()?;
+        // do-while文のあとには、改行がなくてもセミコロンが挿入される
+        self.eat(";");
+        Ok(Node::new_do_while_statement(body, test))
+    }
 
-        match t {
-            Token::Punctuator(c) => match c {
-                '+' | '-' => {
-                    assert!(self.t.next().is_some());
-                    Node::new_additive_expression(c, left, self.assignment_expression())
-                }
-                _ => left,
-            },
-            _ => left,
+    /// for-in文とfor-of文の左辺にできるかどうか
+    fn is_for_in_of_target(node: &Option<Rc<Node>>) -> bool {
+        match node.as_deref() {
+            Some(Node::VariableDecration { declarations, .. }) => {
+                declarations.len() == 1
+                    && matches!(
+                        declarations[0].as_deref(),
+                        Some(Node::VariableDeclarator { init: None, .. })
+                    )
+            }
+            _ => Node::is_simple_assignment_target(node),
         }
     }
 
-    fn assignment_expression(&mut self) -> Option<Rc<Node>> {
-        let expr = self.additive_expression();
+    fn for_statement(&mut self) -> Result<Option<Rc<Node>>, Error> {
+        // `for`を消費する
+        assert!(self.advance().is_some());
+        self.expect("(")?;
+
+        let init = if self.is_punctuator(";") {
+            None
+        } else {
+            self.no_in = true;
+            let init = if self.is_keyword("var")
+                || self.is_keyword("const")
+                || self.is_let_declaration()
+            {
+                self.variable_declaration()
+            } else {
+                self.expression()
+            };
+            self.no_in = false;
+            let init = init?;
+
+            let is_in = self.is_keyword("in");
+            let is_of = matches!(self.peek(), Some(Token::Identifier(name)) if name == "of");
+            if is_in || is_of {
+                if !Self::is_for_in_of_target(&init) {
+                    return Err(self.unexpected());
+                }
+                self.pos += 1;
+                let right = if is_in {
+                    self.expression()?
+                } else {
+                    self.assignment_expression()?
+                };
+                self.expect(")")?;
+                let body = self.iteration_body()?;
+                return Ok(if is_in {
+                    Node::new_for_in_statement(init, right, body)
+                } else {
+                    Node::new_for_of_statement(init, right, body)
+                });
+            }
+            init
+        };
 
-        let t = match self.t.peek() {
-            Some(token) => token,
-            None => return expr,
+        self.expect(";")?;
+        let test = if self.is_punctuator(";") {
+            None
+        } else {
+            self.expression()?
         };
+        self.expect(";")?;
+        let update = if self.is_punctuator(")") {
+            None
+        } else {
+            self.expression()?
+        };
+        self.expect(")")?;
+
+        Ok(Node::new_for_statement(
+            init,
+            test,
+            update,
+            self.iteration_body()?,
+        ))
+    }
+
+    fn switch_cases(&mut self) -> Result<Vec<Option<Rc<Node>>>, Error> {
+        self.expect("{")?;
+        let mut cases = Vec::new();
+        let mut has_default = false;
+
+        loop {
+            if self.eat("}") {
+                return Ok(cases);
+            }
 
-        match t {
-            Token::Punctuator('=') => {
-                assert!(self.t.next().is_some());
-                Node::new_assignment_expression('=', expr, self.assignment_expression())
+            let test = if self.is_keyword("case") {
+                self.pos += 1;
+                self.allow_in(Self::expression)?
+            } else if self.is_keyword("default") && !has_default {
+                self.pos += 1;
+                has_default = true;
+                None
+            } else {
+                return Err(self.unexpected());
+            };
+            self.expect(":")?;
+
+            let mut consequent = Vec::new();
+            while !(self.is_keyword("case")
+                || self.is_keyword("default")
+                || self.is_punctuator("}"))
+            {
+                consequent.push(self.source_element()?);
             }
-            _ => expr,
+            cases.push(Node::new_switch_case(test, consequent));
         }
     }
 
-    fn initialiser(&mut self) -> Option<Rc<Node>> {
-        let t = match self.t.next() {
-            Some(token) => token,
-            None => return None,
-        };
+    fn switch_statement(&mut self) -> Result<Option<Rc<Node>>, Error> {
+        // `switch`を消費する
+        assert!(self.advance().is_some());
 
-        match t {
-            Token::Punctuator(c) => match c {
-                '=' => self.assignment_expression(),
-                _ => None,
-            },
-            _ => None,
-        }
+        let discriminant = self.parenthesized_expression()?;
+        self.context.switches += 1;
+        let cases = self.switch_cases();
+        self.context.switches -= 1;
+        Ok(Node::new_switch_statement(discriminant, cases?))
     }
 
-    fn identifier(&mut self) -> Option<Rc<Node>> {
-        let t = match self.t.next() {
-            Some(token) => token,
-            None => return None,
+    fn labeled_statement(&mut self) -> Result<Option<Rc<Node>>, Error> {
+        let start = self.pos;
+        let label = match self.advance() {
+            Some(Token::Identifier(label)) => label,
+            _ => return Err(self.unexpected()),
         };
+        self.expect(":")?;
+        if self.context.labels.iter().any(|(l, _)| *l == label) {
+            return Err(self.error_at(start, "duplicate label"));
+        }
 
-        match t {
-            Token::Identifier(name) => Node::new_identifier(name),
-            _ => None,
+        // 連続したラベルの先の文が繰り返しなら、`continue`の対象にできる
+        let mut i = 0;
+        while matches!(self.peek_at(i), Some(Token::Identifier(_)))
+            && matches!(self.peek_at(i + 1), Some(Token::Punctuator(":")))
+        {
+            i += 2;
         }
+        let is_loop = matches!(
+            self.peek_at(i),
+            Some(Token::Keyword("for" | "while" | "do"))
+        );
+
+        self.context.labels.push((label.clone(), is_loop));
+        let body = self.statement();
+        self.context.labels.pop();
+        Ok(Node::new_labeled_statement(label, body?))
     }
 
-    fn variable_declaration(&mut self) -> Option<Rc<Node>> {
-        let ident = self.identifier();
+    fn try_statement(&mut self) -> Result<Option<Rc<Node>>, Error> {
+        // `try`を消費する
+        assert!(self.advance().is_some());
+
+        let block = self.block_statement()?;
+        let handler = if self.is_keyword("catch") {
+            self.pos += 1;
+            let param = if self.eat("(") {
+                let param = self.identifier()?;
+                self.expect(")")?;
+                param
+            } else {
+                None
+            };
+            Node::new_catch_clause(param, self.block_statement()?)
+        } else {
+            None
+        };
+        let finalizer = if self.is_keyword("finally") {
+            self.pos += 1;
+            self.block_statement()?
+        } else {
+            None
+        };
 
-        let declarator = Node::new_variable_declarator(ident, self.initialiser());
-        let mut declarations = Vec::new();
-        declarations.push(declarator);
+        if handler.is_none() && finalizer.is_none() {
+            return Err(self.unexpected());
+        }
+        Ok(Node::new_try_statement(block, handler, finalizer))
+    }
 
-        Node::new_variable_declaration(declarations)
+    fn statement(&mut self) -> Result<Option<Rc<Node>>, Error> {
+        self.nested(1, Self::parse_statement)
     }
 
-    fn statement(&mut self) -> Option<Rc<Node>> {
-        let t = match self.t.peek() {
+    fn parse_statement(&mut self) -> Result<Option<Rc<Node>>, Error> {
+        let start = self.pos;
+        let t = match self.peek() {
             Some(t) => t,
-            None => return None,
+            None => return Err(self.unexpected()),
         };
 
         let node = match t {
-            Token::Keyword(keyword) => {
-                if keyword == "var" {
-                    assert!(self.t.next().is_some());
-
-                    self.variable_declaration()
-                } else if keyword == "return" {
-                    assert!(self.t.next().is_some());
-
-                    Node::new_return_statement(self.assignment_expression())
+            Token::Punctuator("{") => return self.block_statement(),
+            Token::Punctuator(";") => {
+                self.pos += 1;
+                return Ok(Node::new_empty_statement());
+            }
+            Token::Keyword("if") => return self.if_statement(),
+            Token::Keyword("for") => return self.for_statement(),
+            Token::Keyword("while") => return self.while_statement(),
+            Token::Keyword("do") => return self.do_while_statement(),
+            Token::Keyword("switch") => return self.switch_statement(),
+            Token::Keyword("try") => return self.try_statement(),
+            Token::Identifier(_) if matches!(self.peek_at(1), Some(Token::Punctuator(":"))) => {
+                return self.labeled_statement()
+            }
+            Token::Keyword("var") | Token::Keyword("const") => self.variable_declaration()?,
+            Token::Identifier(_) if self.is_let_declaration() => self.variable_declaration()?,
+            Token::Keyword("return") => {
+                if !self.context.in_function {
+                    return Err(self.error_at(start, "return statement outside of function"));
+                }
+                self.pos += 1;
+
+                // `return`の直後の改行ではセミコロンが挿入される
+                let has_argument = !(self.is_punctuator(";")
+                    || self.is_punctuator("}")
+                    || self.peek().is_none()
+                    || self.newline_before());
+                let argument = if has_argument {
+                    self.expression()?
                 } else {
                     None
+                };
+                Node::new_return_statement(argument)
+            }
+            Token::Keyword("break") => {
+                self.pos += 1;
+                let label = self.statement_label();
+                let valid = match &label {
+                    Some(label) => self.context.labels.iter().any(|(l, _)| l == label),
+                    None => self.context.iterations > 0 || self.context.switches > 0,
+                };
+                if !valid {
+                    return Err(self.error_at(start, "illegal break statement"));
                 }
+                Node::new_break_statement(label)
             }
-            _ => Node::new_expression_statement(self.assignment_expression()),
-        };
-
-        if let Some(Token::Punctuator(c)) = self.t.peek() {
-            if c == &';' {
-                assert!(self.t.next().is_some());
+            Token::Keyword("continue") => {
+                self.pos += 1;
+                let label = self.statement_label();
+                let valid = match &label {
+                    Some(label) => self
+                        .context
+                        .labels
+                        .iter()
+                        .any(|(l, is_loop)| l == label && *is_loop),
+                    None => self.context.iterations > 0,
+                };
+                if !valid {
+                    return Err(self.error_at(start, "illegal continue statement"));
+                }
+                Node::new_continue_statement(label)
             }
-        }
+            Token::Keyword("throw") => {
+                self.pos += 1;
+                if self.newline_before() {
+                    return Err(self.error_at(start, "illegal newline after throw"));
+                }
+                Node::new_throw_statement(self.expression()?)
+            }
+            _ => Node::new_expression_statement(self.expression()?),
+        };
 
-        node
+        self.consume_semicolon()?;
+        Ok(node)
     }
 
-    fn function_body(&mut self) -> Option<Rc<Node>> {
-        // '{' を消費する
-        match self.t.next() {
-            Some(t) => match t {
-                Token::Punctuator(c) => assert!(c == '{'),
-                _ => unimplemented!(
-                    "function body should have open curly bracket but got {:?}",
-                    t
-                ),
-            },
-            None => unimplemented!("function body should have open curly bracket but got None"),
+    fn function_body(&mut self) -> Result<Option<Rc<Node>>, Error> {
+        // 関数の外側のラベルや繰り返しは、関数の中からは対象にできない
+        let context = StatementContext {
+            in_function: true,
+            ..Default::default()
         };
-
-        let mut body = Vec::new();
-
-        loop {
-            match self.t.peek() {
-                Some(t) => match t {
-                    Token::Punctuator(c) => {
-                        if c == &'}' {
-                            assert!(self.t.next().is_some());
-                            return Node::new_block_statement(body);
-                        }
-                    }
-                    _ => {}
-                },
-                None => {}
-            }
-
-            body.push(self.source_element());
-        }
+        let outer = core::mem::replace(&mut self.context, context);
+        let body = self.allow_in(Self::block_statement);
+        self.context = outer;
+        body
     }
 
-    fn parameter_list(&mut self) -> Vec<Option<Rc<Node>>> {
+    fn parameter_list(&mut self) -> Result<Vec<Option<Rc<Node>>>, Error> {
+        self.expect("(")?;
         let mut params = Vec::new();
 
-        match self.t.next() {
-            Some(t) => match t {
-                Token::Punctuator(c) => assert!(c == '('),
-                _ => unimplemented!("function should have `(` but got {:?}", t),
-            },
-            None => unimplemented!("function should have `(` but got None"),
-        }
-
         loop {
-            match self.t.peek() {
-                Some(t) => match t {
-                    Token::Punctuator(c) => {
-                        if c == &')' {
-                            assert!(self.t.next().is_some());
-                            return params;
-                        }
-                        if c == &',' {
-                            assert!(self.t.next().is_some());
-                        }
-                    }
-
-                    _ => {
-                        params.push(self.identifier());
-                    }
-                },
-                None => return params,
+            if self.eat(")") {
+                return Ok(params);
+            }
+            // 残余引数は最後にだけ書ける
+            if self.eat("...") {
+                params.push(Node::new_rest_element(self.identifier()?));
+                self.expect(")")?;
+                return Ok(params);
+            }
+            let param = self.identifier()?;
+            if self.eat("=") {
+                let default = self.allow_in(Self::assignment_expression)?;
+                params.push(Node::new_assignment_pattern(param, default));
+            } else {
+                params.push(param);
+            }
+            if !self.is_punctuator(")") {
+                self.expect(",")?;
             }
         }
     }
 
-    fn function_declaration(&mut self) -> Option<Rc<Node>> {
-        let id = self.identifier();
-        let params = self.parameter_list();
+    fn function_declaration(&mut self) -> Result<Option<Rc<Node>>, Error> {
+        let id = self.identifier()?;
+        let params = self.parameter_list()?;
 
-        Node::new_function_declaration(id, params, self.function_body())
+        Ok(Node::new_function_declaration(
+            id,
+            params,
+            self.function_body()?,
+        ))
     }
 
-    pub fn source_element(&mut self) -> Option<Rc<Node>> {
-        let t = match self.t.peek() {
-            Some(t) => t,
-            None => return None,
+    fn function_expression(&mut self) -> Result<Option<Rc<Node>>, Error> {
+        // `function`を消費する
+        assert!(self.advance().is_some());
+
+        let id = match self.peek() {
+            Some(Token::Identifier(_)) => self.identifier()?,
+            _ => None,
         };
+        let params = self.parameter_list()?;
 
-        match t {
-            Token::Keyword(keyword) => {
-                if keyword == "function" {
-                    assert!(self.t.next().is_some());
-                    self.function_declaration()
-                } else {
-                    self.statement()
-                }
+        Ok(Node::new_function_expression(
+            id,
+            params,
+            self.function_body()?,
+        ))
+    }
+
+    pub fn source_element(&mut self) -> Result<Option<Rc<Node>>, Error> {
+        match self.peek() {
+            Some(Token::Keyword("function")) => {
+                self.pos += 1;
+                self.nested(1, Self::function_declaration)
             }
             _ => self.statement(),
         }
     }
 
-    pub fn parse_ast(&mut self) -> Program {
+    pub fn parse_ast(&mut self) -> Result<Program, Error> {
         let mut program = Program::new();
 
         let mut body = Vec::new();
 
-        loop {
-            let node = self.source_element();
+        while self.peek().is_some() {
+            if let Some(node) = self.source_element()? {
+                body.push(node);
+            }
+        }
+
+        if let Some(e) = &self.lex_error {
+            return Err(e.clone());
+        }
+        program.set_body(body);
+        Ok(program)
+    }
+}
+
+/// 引数の名前
+pub fn parameter_name(param: &Option<Rc<Node>>) -> Option<&str> {
+    match param.as_deref() {
+        Some(Node::Identifier(name)) => Some(name),
+        Some(Node::AssignmentPattern { left, .. }) | Some(Node::RestElement(left)) => {
+            parameter_name(left)
+        }
+        _ => None,
+    }
+}
+
+/// 通常の関数が`arguments`のオブジェクトを作る必要があるかどうか
+/// 同じ名前の引数や、本体の`let`、`const`、関数の宣言があれば、そちらを参照する
+pub fn uses_arguments(params: &[Option<Rc<Node>>], body: &Option<Rc<Node>>) -> bool {
+    let is_arguments = |id: &Option<Rc<Node>>| matches!(id.as_deref(), Some(Node::Identifier(name)) if name == "arguments");
+    if params
+        .iter()
+        .any(|param| parameter_name(param) == Some("arguments"))
+    {
+        return false;
+    }
+    if let Some(Node::BlockStatement { body }) = body.as_deref() {
+        let shadowed = body.iter().any(|statement| match statement.as_deref() {
+            Some(Node::VariableDecration { kind, declarations }) if *kind != "var" => declarations
+                .iter()
+                .any(|declaration| match declaration.as_deref() {
+                    Some(Node::VariableDeclarator { id, .. }) => is_arguments(id),
+                    _ => false,
+                }),
+            Some(Node::FunctionDeclaration { id, .. }) => is_arguments(id),
+            _ => false,
+        });
+        if shadowed {
+            return false;
+        }
+    }
+    params.iter().any(references_arguments) || references_arguments(body)
+}
+
+/// 構文木が`arguments`を参照するかどうか。アロー関数の中は含み、通常の関数の中は含まない
+fn references_arguments(node: &Option<Rc<Node>>) -> bool {
+    let any = |nodes: &[Option<Rc<Node>>]| nodes.iter().any(references_arguments);
+    let node = match node.as_deref() {
+        Some(node) => node,
+        None => return false,
+    };
+    match node {
+        Node::Identifier(name) => name == "arguments",
+        Node::FunctionDeclaration { .. } | Node::FunctionExpression { .. } => false,
+        Node::ArrowFunctionExpression { params, body } => any(params) || references_arguments(body),
+        // `a.arguments`のプロパティ名は変数ではない
+        Node::MemberExpression {
+            object,
+            property,
+            computed,
+            ..
+        } => references_arguments(object) || (*computed && references_arguments(property)),
+        Node::Property {
+            key,
+            value,
+            computed,
+            ..
+        } => (*computed && references_arguments(key)) || references_arguments(value),
+        Node::ExpressionStatement(argument)
+        | Node::SpreadElement(argument)
+        | Node::RestElement(argument)
+        | Node::ChainExpression(argument)
+        | Node::UnaryExpression { argument, .. }
+        | Node::UpdateExpression { argument, .. }
+        | Node::ReturnStatement { argument }
+        | Node::ThrowStatement { argument } => references_arguments(argument),
+        Node::BinaryExpression { left, right, .. }
+        | Node::LogicalExpression { left, right, .. }
+        | Node::AssignmentExpression { left, right, .. }
+        | Node::AssignmentPattern { left, right } => {
+            references_arguments(left) || references_arguments(right)
+        }
+        Node::ConditionalExpression {
+            test,
+            consequent,
+            alternate,
+        }
+        | Node::IfStatement {
+            test,
+            consequent,
+            alternate,
+        } => {
+            references_arguments(test)
+                || references_arguments(consequent)
+                || references_arguments(alternate)
+        }
+        Node::SequenceExpression { expressions } | Node::TemplateLiteral { expressions, .. } => {
+            any(expressions)
+        }
+        Node::VariableDecration { declarations, .. } => any(declarations),
+        Node::VariableDeclarator { id, init } => {
+            references_arguments(id) || references_arguments(init)
+        }
+        Node::ArrayExpression { elements } => any(elements),
+        Node::ObjectExpression { properties } => any(properties),
+        Node::BlockStatement { body } => any(body),
+        Node::ForStatement {
+            init,
+            test,
+            update,
+            body,
+        } => {
+            references_arguments(init)
+                || references_arguments(test)
+                || references_arguments(update)
+                || references_arguments(body)
+        }
+        Node::ForInStatement { left, right, body } | Node::ForOfStatement { left, right, body } => {
+            references_arguments(left) || references_arguments(right) || references_arguments(body)
+        }
+        Node::WhileStatement { test, body } | Node::DoWhileStatement { body, test } => {
+            references_arguments(test) || references_arguments(body)
+        }
+        Node::SwitchStatement {
+            discriminant,
+            cases,
+        } => references_arguments(discriminant) || any(cases),
+        Node::SwitchCase { test, consequent } => references_arguments(test) || any(consequent),
+        Node::LabeledStatement { body, .. } => references_arguments(body),
+        Node::TryStatement {
+            block,
+            handler,
+            finalizer,
+        } => {
+            references_arguments(block)
+                || references_arguments(handler)
+                || references_arguments(finalizer)
+        }
+        Node::CatchClause { param, body } => {
+            references_arguments(param) || references_arguments(body)
+        }
+        Node::CallExpression {
+            callee, arguments, ..
+        }
+        | Node::NewExpression { callee, arguments } => {
+            references_arguments(callee) || any(arguments)
+        }
+        Node::NumberLiteral(_)
+        | Node::StringLiteral(_)
+        | Node::BooleanLiteral(_)
+        | Node::NullLiteral
+        | Node::RegExpLiteral { .. }
+        | Node::ThisExpression
+        | Node::EmptyStatement
+        | Node::BreakStatement { .. }
+        | Node::ContinueStatement { .. } => false,
+    }
+}
+
+/// ブロックのスコープに作る`let`、`const`、関数の宣言かどうか
+pub fn is_lexical_declaration(statement: &Option<Rc<Node>>) -> bool {
+    match statement.as_deref() {
+        Some(Node::VariableDecration { kind, .. }) => *kind != "var",
+        Some(Node::FunctionDeclaration { .. }) => true,
+        _ => false,
+    }
+}
 
-            match node {
-                Some(n) => body.push(n),
-                None => {
-                    program.set_body(body);
-                    return program;
+/// 文の中で`var`で宣言された変数の名前を集める。関数の中は含まない
+pub fn var_declared_names(statement: &Option<Rc<Node>>, names: &mut Vec<String>) {
+    let statement = match statement.as_deref() {
+        Some(statement) => statement,
+        None => return,
+    };
+    match statement {
+        Node::VariableDecration {
+            kind: "var",
+            declarations,
+        } => {
+            for declaration in declarations {
+                if let Some(Node::VariableDeclarator { id, .. }) = declaration.as_deref() {
+                    if let Some(Node::Identifier(name)) = id.as_deref() {
+                        if !names.contains(name) {
+                            names.push(name.clone());
+                        }
+                    }
+                }
+            }
+        }
+        Node::BlockStatement { body } => {
+            for statement in body {
+                var_declared_names(statement, names);
+            }
+        }
+        Node::IfStatement {
+            consequent,
+            alternate,
+            ..
+        } => {
+            var_declared_names(consequent, names);
+            var_declared_names(alternate, names);
+        }
+        Node::ForStatement { init, body, .. } => {
+            var_declared_names(init, names);
+            var_declared_names(body, names);
+        }
+        Node::ForInStatement { left, body, .. } | Node::ForOfStatement { left, body, .. } => {
+            var_declared_names(left, names);
+            var_declared_names(body, names);
+        }
+        Node::WhileStatement { body, .. }
+        | Node::DoWhileStatement { body, .. }
+        | Node::LabeledStatement { body, .. } => var_declared_names(body, names),
+        Node::SwitchStatement { cases, .. } => {
+            for case in cases {
+                if let Some(Node::SwitchCase { consequent, .. }) = case.as_deref() {
+                    for statement in consequent {
+                        var_declared_names(statement, names);
+                    }
                 }
             }
         }
+        Node::TryStatement {
+            block,
+            handler,
+            finalizer,
+        } => {
+            var_declared_names(block, names);
+            if let Some(Node::CatchClause { body, .. }) = handler.as_deref() {
+                var_declared_names(body, names);
+            }
+            var_declared_names(finalizer, names);
+        }
+        _ => {}
     }
 }
 
 #[cfg(test)]
 mod tests {
     use alloc::string::ToString;
+    use alloc::vec;
 
     use super::*;
 
+    fn parse(input: &str) -> Result<Program, Error> {
+        JsParser::new(JsLexer::new(input.to_string())).parse_ast()
+    }
+
+    /// 1つの式文の式
+    fn expression(input: &str) -> Option<Rc<Node>> {
+        let program = parse(input).expect("failed to parse");
+        assert_eq!(1, program.body().len());
+        match program.body()[0].as_ref() {
+            Node::ExpressionStatement(expr) => expr.clone(),
+            n => panic!("{:?} is not an expression statement", n),
+        }
+    }
+
+    fn id(name: &str) -> Option<Rc<Node>> {
+        Node::new_identifier(name.to_string())
+    }
+
+    fn num(value: f64) -> Option<Rc<Node>> {
+        Node::new_numeric_literal(value)
+    }
+
+    fn binary(
+        operator: &'static str,
+        left: Option<Rc<Node>>,
+        right: Option<Rc<Node>>,
+    ) -> Option<Rc<Node>> {
+        Node::new_binary_expression(operator, left, right)
+    }
+
+    fn member(object: Option<Rc<Node>>, name: &str) -> Option<Rc<Node>> {
+        Node::new_member_expression(object, id(name), false, false)
+    }
+
+    fn block_returning(argument: Option<Rc<Node>>) -> Option<Rc<Node>> {
+        Node::new_block_statement(vec![Node::new_return_statement(argument)])
+    }
+
     #[test]
     fn test_empty() {
         let input = "".to_string();
         let lexer = JsLexer::new(input);
         let mut parser = JsParser::new(lexer);
         let expected = Program::new();
-        assert_eq!(expected, parser.parse_ast());
+        assert_eq!(Ok(expected), parser.parse_ast());
     }
 
     #[test]
@@ -483,10 +2014,10 @@ mod tests {
         let mut expected = Program::new();
         let mut body = Vec::new();
         body.push(Rc::new(Node::ExpressionStatement(Some(Rc::new(
-            Node::NumberLiteral(42),
+            Node::NumberLiteral(42.0),
         )))));
         expected.set_body(body);
-        assert_eq!(expected, parser.parse_ast());
+        assert_eq!(Ok(expected), parser.parse_ast());
     }
 
     #[test]
@@ -497,14 +2028,14 @@ mod tests {
         let mut expected = Program::new();
         let mut body = Vec::new();
         body.push(Rc::new(Node::ExpressionStatement(Some(Rc::new(
-            Node::AdditiveExpression {
-                operator: '+',
-                left: Some(Rc::new(Node::NumberLiteral(1))),
-                right: Some(Rc::new(Node::NumberLiteral(2))),
+            Node::BinaryExpression {
+                operator: "+",
+                left: Some(Rc::new(Node::NumberLiteral(1.0))),
+                right: Some(Rc::new(Node::NumberLiteral(2.0))),
             },
         )))));
         expected.set_body(body);
-        assert_eq!(expected, parser.parse_ast());
+        assert_eq!(Ok(expected), parser.parse_ast());
     }
 
     #[test]
@@ -515,6 +2046,7 @@ mod tests {
         let mut expected = Program::new();
         let mut body = Vec::new();
         body.push(Rc::new(Node::VariableDecration {
+            kind: "var",
             declarations: [Some(Rc::new(Node::VariableDeclarator {
                 id: Some(Rc::new(Node::Identifier("foo".to_string()))),
                 init: Some(Rc::new(Node::StringLiteral("bar".to_string()))),
@@ -522,7 +2054,7 @@ mod tests {
             .to_vec(),
         }));
         expected.set_body(body);
-        assert_eq!(expected, parser.parse_ast());
+        assert_eq!(Ok(expected), parser.parse_ast());
     }
 
     #[test]
@@ -534,25 +2066,27 @@ mod tests {
         let mut body = Vec::new();
 
         body.push(Rc::new(Node::VariableDecration {
+            kind: "var",
             declarations: [Some(Rc::new(Node::VariableDeclarator {
                 id: Some(Rc::new(Node::Identifier("foo".to_string()))),
-                init: Some(Rc::new(Node::NumberLiteral(42))),
+                init: Some(Rc::new(Node::NumberLiteral(42.0))),
             }))]
             .to_vec(),
         }));
         body.push(Rc::new(Node::VariableDecration {
+            kind: "var",
             declarations: [Some(Rc::new(Node::VariableDeclarator {
                 id: Some(Rc::new(Node::Identifier("result".to_string()))),
-                init: Some(Rc::new(Node::AdditiveExpression {
-                    operator: '+',
+                init: Some(Rc::new(Node::BinaryExpression {
+                    operator: "+",
                     left: Some(Rc::new(Node::Identifier("foo".to_string()))),
-                    right: Some(Rc::new(Node::NumberLiteral(1))),
+                    right: Some(Rc::new(Node::NumberLiteral(1.0))),
                 })),
             }))]
             .to_vec(),
         }));
         expected.set_body(body);
-        assert_eq!(expected, parser.parse_ast());
+        assert_eq!(Ok(expected), parser.parse_ast());
     }
 
     #[test]
@@ -568,13 +2102,13 @@ mod tests {
             params: [].to_vec(),
             body: Some(Rc::new(Node::BlockStatement {
                 body: [Some(Rc::new(Node::ReturnStatement {
-                    argument: Some(Rc::new(Node::NumberLiteral(42))),
+                    argument: Some(Rc::new(Node::NumberLiteral(42.0))),
                 }))]
                 .to_vec(),
             })),
         }));
         expected.set_body(body);
-        assert_eq!(expected, parser.parse_ast());
+        assert_eq!(Ok(expected), parser.parse_ast());
     }
 
     #[test]
@@ -594,8 +2128,8 @@ mod tests {
             .to_vec(),
             body: Some(Rc::new(Node::BlockStatement {
                 body: [Some(Rc::new(Node::ReturnStatement {
-                    argument: Some(Rc::new(Node::AdditiveExpression {
-                        operator: '+',
+                    argument: Some(Rc::new(Node::BinaryExpression {
+                        operator: "+",
                         left: Some(Rc::new(Node::Identifier("a".to_string()))),
                         right: Some(Rc::new(Node::Identifier("b".to_string()))),
                     })),
@@ -604,7 +2138,7 @@ mod tests {
             })),
         }));
         expected.set_body(body);
-        assert_eq!(expected, parser.parse_ast());
+        assert_eq!(Ok(expected), parser.parse_ast());
     }
 
     #[test]
@@ -620,26 +2154,555 @@ mod tests {
             params: [].to_vec(),
             body: Some(Rc::new(Node::BlockStatement {
                 body: [Some(Rc::new(Node::ReturnStatement {
-                    argument: Some(Rc::new(Node::NumberLiteral(42))),
+                    argument: Some(Rc::new(Node::NumberLiteral(42.0))),
                 }))]
                 .to_vec(),
             })),
         }));
         body.push(Rc::new(Node::VariableDecration {
+            kind: "var",
             declarations: [Some(Rc::new(Node::VariableDeclarator {
                 id: Some(Rc::new(Node::Identifier("result".to_string()))),
-                init: Some(Rc::new(Node::AdditiveExpression {
-                    operator: '+',
+                init: Some(Rc::new(Node::BinaryExpression {
+                    operator: "+",
                     left: Some(Rc::new(Node::CallExpression {
                         callee: Some(Rc::new(Node::Identifier("foo".to_string()))),
                         arguments: [].to_vec(),
+                        optional: false,
                     })),
-                    right: Some(Rc::new(Node::NumberLiteral(1))),
+                    right: Some(Rc::new(Node::NumberLiteral(1.0))),
                 })),
             }))]
             .to_vec(),
         }));
         expected.set_body(body);
-        assert_eq!(expected, parser.parse_ast());
+        assert_eq!(Ok(expected), parser.parse_ast());
+    }
+
+    #[test]
+    fn test_precedence() {
+        // 1 + ((2 * (3 ** (2 ** 2))) % 4)
+        let power = binary("**", num(3.0), binary("**", num(2.0), num(2.0)));
+        let expected = binary(
+            "+",
+            num(1.0),
+            binary("%", binary("*", num(2.0), power), num(4.0)),
+        );
+        assert_eq!(expected, expression("1 + 2 * 3 ** 2 ** 2 % 4"));
+
+        // ((a - b) - c) << d
+        let expected = binary(
+            "<<",
+            binary("-", binary("-", id("a"), id("b")), id("c")),
+            id("d"),
+        );
+        assert_eq!(expected, expression("a - b - c << d"));
+
+        let expected = binary(
+            "===",
+            binary("<", id("a"), id("b")),
+            binary("instanceof", id("c"), binary("in", id("d"), id("e"))),
+        );
+        assert_eq!(expected, expression("a < b === (c instanceof (d in e))"));
+        assert_eq!(
+            binary("*", binary("+", num(1.0), num(2.0)), num(3.0)),
+            expression("(1 + 2) * 3")
+        );
+    }
+
+    #[test]
+    fn test_logical_and_conditional() {
+        let expected = Node::new_conditional_expression(
+            Node::new_logical_expression(
+                "||",
+                id("a"),
+                Node::new_logical_expression("&&", id("b"), id("c")),
+            ),
+            Node::new_logical_expression("??", id("d"), id("e")),
+            Node::new_conditional_expression(id("f"), id("g"), id("h")),
+        );
+        assert_eq!(expected, expression("a || b && c ? d ?? e : f ? g : h"));
+    }
+
+    #[test]
+    fn test_assignment() {
+        let expected = Node::new_assignment_expression(
+            "=",
+            id("a"),
+            Node::new_assignment_expression("+=", member(id("b"), "c"), num(1.0)),
+        );
+        assert_eq!(expected, expression("a = b.c += 1"));
+        assert_eq!(
+            Node::new_assignment_expression(">>>=", id("x"), num(2.0)),
+            expression("x >>>= 2")
+        );
+        assert!(parse("1 = 2").is_err());
+        assert!(parse("a + b = c").is_err());
+        assert!(parse("a?.b = c").is_err());
+    }
+
+    #[test]
+    fn test_unary_and_update() {
+        let expected =
+            Node::new_unary_expression("-", Node::new_update_expression("++", false, id("x")));
+        assert_eq!(expected, expression("-x++"));
+        assert_eq!(
+            Node::new_unary_expression("typeof", member(id("a"), "b")),
+            expression("typeof a.b")
+        );
+        assert_eq!(
+            Node::new_unary_expression("!", Node::new_update_expression("--", true, id("c"))),
+            expression("!--c")
+        );
+        assert_eq!(
+            binary("**", Node::new_unary_expression("-", num(2.0)), num(2.0)),
+            expression("(-2) ** 2")
+        );
+        assert!(parse("-2 ** 2").is_err());
+        assert!(parse("++1").is_err());
+    }
+
+    #[test]
+    fn test_sequence() {
+        assert_eq!(
+            Node::new_sequence_expression(vec![id("a"), id("b"), num(1.0)]),
+            expression("a, b, 1")
+        );
+    }
+
+    #[test]
+    fn test_member_call_and_new() {
+        let computed = Node::new_member_expression(
+            Node::new_new_expression(member(id("Foo"), "Bar"), vec![num(1.0)]),
+            num(0.0),
+            true,
+            false,
+        );
+        let expected = Node::new_call_expression(member(computed, "baz"), vec![], false);
+        assert_eq!(expected, expression("new Foo.Bar(1)[0].baz()"));
+
+        // 引数のないnewと、予約語のプロパティ名
+        let expected = member(Node::new_new_expression(id("Date"), vec![]), "default");
+        assert_eq!(expected, expression("new Date().default"));
+        assert_eq!(
+            Node::new_new_expression(Node::new_new_expression(id("X"), vec![]), vec![]),
+            expression("new new X()()")
+        );
+
+        let expected = Node::new_call_expression(
+            id("f"),
+            vec![id("a"), Node::new_spread_element(id("b"))],
+            false,
+        );
+        assert_eq!(expected, expression("f(a, ...b,)"));
+    }
+
+    #[test]
+    fn test_optional_chaining() {
+        let optional = Node::new_member_expression(id("a"), id("b"), false, true);
+        let call = Node::new_call_expression(member(optional, "c"), vec![], false);
+        assert_eq!(Node::new_chain_expression(call), expression("a?.b.c()"));
+
+        let computed = Node::new_member_expression(id("a"), id("i"), true, true);
+        let expected =
+            Node::new_chain_expression(Node::new_call_expression(computed, vec![], true));
+        assert_eq!(expected, expression("a?.[i]?.()"));
+    }
+
+    #[test]
+    fn test_array_literal() {
+        let expected =
+            Node::new_array_expression(vec![num(1.0), None, Node::new_spread_element(id("a"))]);
+        assert_eq!(expected, expression("[1, , ...a]"));
+        assert_eq!(Node::new_array_expression(vec![]), expression("[]"));
+    }
+
+    #[test]
+    fn test_object_literal() {
+        let getter = Node::new_function_expression(None, vec![], block_returning(num(1.0)));
+        let method =
+            Node::new_function_expression(None, vec![id("x")], Node::new_block_statement(vec![]));
+        let expected = Node::new_object_expression(vec![
+            Node::new_property(id("a"), num(1.0), PropertyKind::Init, false),
+            Node::new_property(id("b"), id("b"), PropertyKind::Init, false),
+            Node::new_property(id("c"), num(2.0), PropertyKind::Init, true),
+            Node::new_property(
+                Node::new_string_literal("d e".to_string()),
+                num(3.0),
+                PropertyKind::Init,
+                false,
+            ),
+            Node::new_property(id("m"), method, PropertyKind::Init, false),
+            Node::new_property(id("e"), getter, PropertyKind::Get, false),
+            Node::new_property(id("get"), num(4.0), PropertyKind::Init, false),
+            Node::new_spread_element(id("f")),
+        ]);
+        let input = "x = {a: 1, b, [c]: 2, 'd e': 3, m(x) {}, get e() { return 1; }, get: 4, ...f}";
+        assert_eq!(
+            Node::new_assignment_expression("=", id("x"), expected),
+            expression(input)
+        );
+    }
+
+    #[test]
+    fn test_functions() {
+        let expected = Node::new_arrow_function_expression(
+            vec![id("a"), id("b")],
+            block_returning(binary("+", id("a"), id("b"))),
+        );
+        assert_eq!(expected, expression("(a, b) => a + b"));
+
+        let expected = Node::new_arrow_function_expression(vec![id("x")], block_returning(id("x")));
+        assert_eq!(expected, expression("x => { return x; }"));
+
+        // 本体がオブジェクトリテラルのアロー関数
+        let expected = Node::new_arrow_function_expression(
+            vec![],
+            block_returning(Node::new_object_expression(vec![])),
+        );
+        assert_eq!(expected, expression("() => ({})"));
+
+        let expected =
+            Node::new_function_expression(id("f"), vec![id("a")], block_returning(id("a")));
+        assert_eq!(expected, expression("(function f(a) { return a; })"));
+
+        let expected = Node::new_arrow_function_expression(
+            vec![
+                id("a"),
+                Node::new_assignment_pattern(id("b"), binary("in", id("c"), id("d"))),
+                Node::new_rest_element(id("e")),
+            ],
+            block_returning(id("a")),
+        );
+        assert_eq!(expected, expression("(a, b = c in d, ...e) => a"));
+        // 残余引数のあとには引数を書けない
+        assert!(parse("function f(...a, b) {}").is_err());
+    }
+
+    #[test]
+    fn test_lexical_declarations() {
+        let program = parse("let a = 1, b; const c = 2; let = 3; for (const x of y) ;")
+            .expect("failed to parse");
+        assert_eq!(
+            vec![
+                Node::new_variable_declaration(
+                    "let",
+                    vec![
+                        Node::new_variable_declarator(id("a"), num(1.0)),
+                        Node::new_variable_declarator(id("b"), None),
+                    ]
+                ),
+                Node::new_variable_declaration(
+                    "const",
+                    vec![Node::new_variable_declarator(id("c"), num(2.0))]
+                ),
+                // `let`は変数名にもなる
+                Node::new_expression_statement(Node::new_assignment_expression(
+                    "=",
+                    id("let"),
+                    num(3.0)
+                )),
+                Node::new_for_of_statement(
+                    Node::new_variable_declaration(
+                        "const",
+                        vec![Node::new_variable_declarator(id("x"), None)]
+                    ),
+                    id("y"),
+                    Node::new_empty_statement()
+                ),
+            ],
+            program
+                .body()
+                .iter()
+                .map(|n| Some(n.clone()))
+                .collect::<Vec<_>>()
+        );
+        assert!(parse("const c;").is_err());
+        assert!(parse("for (const i; i < 1; ) ;").is_err());
+    }
+
+    #[test]
+    fn test_literals() {
+        let expected = Node::new_template_literal(
+            vec!["a".to_string(), "c".to_string()],
+            vec![binary("+", id("b"), num(1.0))],
+        );
+        assert_eq!(expected, expression("`a${b + 1}c`"));
+        assert_eq!(
+            Node::new_regexp_literal("ab+".to_string(), "g".to_string()),
+            expression("/ab+/g")
+        );
+        assert_eq!(
+            Node::new_sequence_expression(vec![
+                Node::new_boolean_literal(true),
+                Node::new_null_literal(),
+                Node::new_this_expression(),
+            ]),
+            expression("true, null, this")
+        );
+    }
+
+    #[test]
+    fn test_automatic_semicolon_insertion() {
+        let program = parse("a\n++b").expect("failed to parse");
+        assert_eq!(2, program.body().len());
+        assert_eq!(
+            Node::ExpressionStatement(Node::new_update_expression("++", true, id("b"))),
+            *program.body()[1]
+        );
+
+        let program = parse("function f() { return\n1 }").expect("failed to parse");
+        let expected = Node::new_function_declaration(
+            id("f"),
+            vec![],
+            Node::new_block_statement(vec![
+                Node::new_return_statement(None),
+                Node::new_expression_statement(num(1.0)),
+            ]),
+        );
+        assert_eq!(expected, Some(program.body()[0].clone()));
+        assert!(parse("a b").is_err());
+    }
+
+    #[test]
+    fn test_syntax_errors() {
+        assert_eq!(
+            Err(Error::UnexpectedInput(
+                "unexpected end of input".to_string()
+            )),
+            parse("1 +")
+        );
+        assert_eq!(
+            Err(Error::UnexpectedInput(
+                "1:6: unexpected token Punctuator(\")\")".to_string()
+            )),
+            parse("f(1 ))")
+        );
+        assert!(parse("var a = 'unterminated").is_err());
+        assert!(parse("function f() {").is_err());
+        assert!(parse("a`tagged`").is_err());
+    }
+
+    #[test]
+    fn test_if_and_loops() {
+        let program = parse("if (a) b; else if (c) {} else ;").expect("failed to parse");
+        let expected = Node::new_if_statement(
+            id("a"),
+            Node::new_expression_statement(id("b")),
+            Node::new_if_statement(
+                id("c"),
+                Node::new_block_statement(vec![]),
+                Node::new_empty_statement(),
+            ),
+        );
+        assert_eq!(expected, Some(program.body()[0].clone()));
+
+        // 初期化式の`in`は括弧の中でだけ演算子になる
+        let program =
+            parse("for (var i = 0, n = (a in b); i; i++) continue;").expect("failed to parse");
+        let init = Node::new_variable_declaration(
+            "var",
+            vec![
+                Node::new_variable_declarator(id("i"), num(0.0)),
+                Node::new_variable_declarator(id("n"), binary("in", id("a"), id("b"))),
+            ],
+        );
+        let expected = Node::new_for_statement(
+            init,
+            id("i"),
+            Node::new_update_expression("++", false, id("i")),
+            Node::new_continue_statement(None),
+        );
+        assert_eq!(expected, Some(program.body()[0].clone()));
+        assert!(parse("for (var i = 0, n = a in b; i; i++) continue;").is_err());
+
+        let program =
+            parse("for (;;) break; while (x) {} do x; while (y) z").expect("failed to parse");
+        assert_eq!(
+            vec![
+                Node::new_for_statement(None, None, None, Node::new_break_statement(None)),
+                Node::new_while_statement(id("x"), Node::new_block_statement(vec![])),
+                Node::new_do_while_statement(Node::new_expression_statement(id("x")), id("y")),
+                Node::new_expression_statement(id("z")),
+            ],
+            program
+                .body()
+                .iter()
+                .map(|n| Some(n.clone()))
+                .collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn test_for_in_and_of() {
+        let program = parse("for (x in o, p) {} for (var v of a) ; for (a.b of c) ;")
+            .expect("failed to parse");
+        let var_v = Node::new_variable_declaration(
+            "var",
+            vec![Node::new_variable_declarator(id("v"), None)],
+        );
+        assert_eq!(
+            vec![
+                Node::new_for_in_statement(
+                    id("x"),
+                    Node::new_sequence_expression(vec![id("o"), id("p")]),
+                    Node::new_block_statement(vec![]),
+                ),
+                Node::new_for_of_statement(var_v, id("a"), Node::new_empty_statement()),
+                Node::new_for_of_statement(
+                    member(id("a"), "b"),
+                    id("c"),
+                    Node::new_empty_statement()
+                ),
+            ],
+            program
+                .body()
+                .iter()
+                .map(|n| Some(n.clone()))
+                .collect::<Vec<_>>()
+        );
+        // for-ofの右辺にカンマは書けない
+        assert!(parse("for (a of b, c) ;").is_err());
+        assert!(parse("for (var a = 1 of b) ;").is_err());
+        assert!(parse("for (f() in b) ;").is_err());
+    }
+
+    #[test]
+    fn test_switch() {
+        let program =
+            parse("switch (x) { case 1: a; b; default: case 2: break; }").expect("failed to parse");
+        let expected = Node::new_switch_statement(
+            id("x"),
+            vec![
+                Node::new_switch_case(
+                    num(1.0),
+                    vec![
+                        Node::new_expression_statement(id("a")),
+                        Node::new_expression_statement(id("b")),
+                    ],
+                ),
+                Node::new_switch_case(None, vec![]),
+                Node::new_switch_case(num(2.0), vec![Node::new_break_statement(None)]),
+            ],
+        );
+        assert_eq!(expected, Some(program.body()[0].clone()));
+        assert!(parse("switch (x) { default: default: }").is_err());
+        assert!(parse("switch (x) { case 1: continue; }").is_err());
+    }
+
+    #[test]
+    fn test_try_and_throw() {
+        let program =
+            parse("try { throw e } catch (x) {} finally { f() }").expect("failed to parse");
+        let expected = Node::new_try_statement(
+            Node::new_block_statement(vec![Node::new_throw_statement(id("e"))]),
+            Node::new_catch_clause(id("x"), Node::new_block_statement(vec![])),
+            Node::new_block_statement(vec![Node::new_expression_statement(
+                Node::new_call_expression(id("f"), vec![], false),
+            )]),
+        );
+        assert_eq!(expected, Some(program.body()[0].clone()));
+
+        let program = parse("try {} catch {}").expect("failed to parse");
+        let expected = Node::new_try_statement(
+            Node::new_block_statement(vec![]),
+            Node::new_catch_clause(None, Node::new_block_statement(vec![])),
+            None,
+        );
+        assert_eq!(expected, Some(program.body()[0].clone()));
+        assert!(parse("try {}").is_err());
+        assert!(parse("throw\n1").is_err());
+    }
+
+    #[test]
+    fn test_labels() {
+        let program =
+            parse("a: b: while (1) { continue a; } c: { break c; }").expect("failed to parse");
+        let expected = Node::new_labeled_statement(
+            "a".to_string(),
+            Node::new_labeled_statement(
+                "b".to_string(),
+                Node::new_while_statement(
+                    num(1.0),
+                    Node::new_block_statement(vec![Node::new_continue_statement(Some(
+                        "a".to_string(),
+                    ))]),
+                ),
+            ),
+        );
+        assert_eq!(expected, Some(program.body()[0].clone()));
+        assert_eq!(
+            Node::new_labeled_statement(
+                "c".to_string(),
+                Node::new_block_statement(vec![Node::new_break_statement(Some("c".to_string()))]),
+            ),
+            Some(program.body()[1].clone())
+        );
+
+        // breakのあとの改行ではセミコロンが挿入される
+        let program = parse("a: while (1) { break\na }").expect("failed to parse");
+        let expected = Node::new_labeled_statement(
+            "a".to_string(),
+            Node::new_while_statement(
+                num(1.0),
+                Node::new_block_statement(vec![
+                    Node::new_break_statement(None),
+                    Node::new_expression_statement(id("a")),
+                ]),
+            ),
+        );
+        assert_eq!(expected, Some(program.body()[0].clone()));
+    }
+
+    #[test]
+    fn test_invalid_jumps() {
+        assert!(parse("break;").is_err());
+        assert!(parse("continue;").is_err());
+        assert!(parse("a: { continue a; }").is_err());
+        assert!(parse("while (1) { break b; }").is_err());
+        assert!(parse("a: a: ;").is_err());
+        assert_eq!(
+            Err(Error::UnexpectedInput(
+                "1:1: return statement outside of function".to_string()
+            )),
+            parse("return 1")
+        );
+        // 関数の中から外側の繰り返しは対象にできない
+        assert!(parse("while (1) { (function () { break; }); }").is_err());
+        assert!(
+            parse("function f() { while (1) { if (a) break; else continue; } return; }").is_ok()
+        );
+    }
+
+    #[test]
+    fn test_too_much_nesting() {
+        let n = 10000;
+        let inputs = [
+            format!("{}1{}", "(".repeat(n), ")".repeat(n)),
+            format!("{}1{}", "[".repeat(n), "]".repeat(n)),
+            format!("{}1", "!".repeat(n)),
+            format!("{}1", "a = ".repeat(n)),
+            format!("{}a", "new ".repeat(n)),
+            format!("1{}", " + 1".repeat(n)),
+            format!("2{}", " ** 2".repeat(n)),
+            format!("a{}", ".a".repeat(n)),
+            format!("f{}", "()".repeat(n)),
+            format!("{}{}", "{".repeat(n), "}".repeat(n)),
+            format!("{}{}", "function f() {".repeat(n), "}".repeat(n)),
+        ];
+        for input in inputs {
+            match parse(&input) {
+                Err(Error::UnexpectedInput(message)) => {
+                    assert!(message.ends_with("too much nesting"), "{}", message)
+                }
+                result => panic!("{:?}", result.map(|_| ())),
+            }
+        }
+
+        // ふつうのコードで使う程度の深さは読める
+        assert!(parse(&format!("{}1{}", "(".repeat(50), ")".repeat(50))).is_ok());
+        assert!(parse(&format!("1{}", " + 1".repeat(200))).is_ok());
+        assert!(parse(&format!("a{}", ".a()".repeat(100))).is_ok());
+        assert!(parse(&format!("{}{}", "if (a) {".repeat(50), "}".repeat(50))).is_ok());
     }
 }
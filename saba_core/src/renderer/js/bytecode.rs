@@ -0,0 +1,1990 @@
+//! 構文木をバイトコードにするコンパイラ
+//! 関数ごとに、スタックマシンの命令列と定数、名前、プロパティキーの表を作る
+//! 関数とブロックのスコープの変数は、名前で探さずにスコープの深さと位置で読み書きする
+
+use crate::renderer::js::ast::is_lexical_declaration;
+use crate::renderer::js::ast::parameter_name;
+use crate::renderer::js::ast::uses_arguments;
+use crate::renderer::js::ast::var_declared_names;
+use crate::renderer::js::ast::Node;
+use crate::renderer::js::ast::Program;
+use crate::renderer::js::ast::PropertyKind;
+use crate::renderer::js::object::PropertyKey;
+use crate::renderer::js::runtime::ErrorKind;
+use crate::renderer::js::runtime::REGEXP_NOT_SUPPORTED;
+use crate::renderer::js::value::RuntimeValue;
+use alloc::rc::Rc;
+use alloc::string::String;
+use alloc::string::ToString;
+use alloc::vec::Vec;
+
+/// 二項演算子。数値どうしの演算はVMが直接計算する
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BinaryOperator {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Rem,
+    LessThan,
+    GreaterThan,
+    LessThanOrEqual,
+    GreaterThanOrEqual,
+    StrictEquals,
+    StrictNotEquals,
+    Other(&'static str),
+}
+
+impl BinaryOperator {
+    fn new(operator: &'static str) -> Self {
+        match operator {
+            "+" => BinaryOperator::Add,
+            "-" => BinaryOperator::Sub,
+            "*" => BinaryOperator::Mul,
+            "/" => BinaryOperator::Div,
+            "%" => BinaryOperator::Rem,
+            "<" => BinaryOperator::LessThan,
+            ">" => BinaryOperator::GreaterThan,
+            "<=" => BinaryOperator::LessThanOrEqual,
+            ">=" => BinaryOperator::GreaterThanOrEqual,
+            "===" => BinaryOperator::StrictEquals,
+            "!==" => BinaryOperator::StrictNotEquals,
+            _ => BinaryOperator::Other(operator),
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            BinaryOperator::Add => "+",
+            BinaryOperator::Sub => "-",
+            BinaryOperator::Mul => "*",
+            BinaryOperator::Div => "/",
+            BinaryOperator::Rem => "%",
+            BinaryOperator::LessThan => "<",
+            BinaryOperator::GreaterThan => ">",
+            BinaryOperator::LessThanOrEqual => "<=",
+            BinaryOperator::GreaterThanOrEqual => ">=",
+            BinaryOperator::StrictEquals => "===",
+            BinaryOperator::StrictNotEquals => "!==",
+            BinaryOperator::Other(operator) => operator,
+        }
+    }
+}
+
+/// VMの命令。オペランドの数値は、関数の`Code`の表の位置かジャンプ先
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Op {
+    /// 定数表の値を積む
+    Constant(u32),
+    Undefined,
+    Pop,
+    Dup,
+    /// 上の2つを複製する
+    Dup2,
+
+    /// 値をレジスタに移す
+    Store(u32),
+    /// レジスタの値を積む。値がなければundefined
+    Load(u32),
+    Clear(u32),
+    /// 値があればレジスタに写す。文の値(completion value)を外側の文に伝える
+    Move {
+        from: u32,
+        to: u32,
+    },
+
+    /// 名前でスコープを辿って変数を読む
+    GetName(u32),
+    /// 名前で変数に書き込む。積まれた値はそのまま残す
+    SetName(u32),
+    /// 宣言されていない変数ではundefinedになる`typeof`
+    TypeofName(u32),
+    /// `depth`個外側のスコープの`index`番目の変数を読む。`name`はエラーの表示に使う
+    GetLocal {
+        depth: u32,
+        index: u32,
+        name: u32,
+    },
+    SetLocal {
+        depth: u32,
+        index: u32,
+        name: u32,
+    },
+    /// 今のスコープの変数を初期化する
+    InitName(u32),
+    InitLocal(u32),
+    /// 文の位置の`let`と`const`。スコープに変数がなければ宣言してから初期化する
+    InitLexical {
+        name: u32,
+        mutable: bool,
+    },
+    DeclareVar(u32),
+    DeclareLexical {
+        name: u32,
+        mutable: bool,
+    },
+    /// 今のスコープに同じ名前の変数があればSyntaxErrorを投げる
+    CheckDeclarable(u32),
+    PushScope,
+    PopScope,
+    /// for文の繰り返しごとに変数を作り直す
+    CopyScope,
+    This,
+    /// 関数の引数。足りない引数はundefined
+    Argument(u32),
+    RestArguments(u32),
+    /// 関数の`arguments`のオブジェクト
+    CreateArguments,
+
+    Binary(BinaryOperator),
+    Not,
+    Negate,
+    BitwiseNot,
+    ToNumber,
+    Increment,
+    Decrement,
+    Typeof,
+    /// `a[b]`のbをプロパティキーの文字列かシンボルにする
+    ToPropertyKey,
+    /// キー表のキーでプロパティを読む
+    GetNamed(u32),
+    GetElement,
+    SetNamed(u32),
+    SetElement,
+    Delete,
+
+    NewObject,
+    DefineField,
+    DefineGetter,
+    DefineSetter,
+    CopyProperties,
+    /// 配列と次の添字を積む
+    NewArray,
+    ArrayPush,
+    ArrayHole,
+    ArraySpread,
+    ArrayEnd,
+    /// テンプレートリテラルの文字列をつなげる
+    Concat,
+
+    /// 関数表の関数を、今のスコープで作る
+    Closure(u32),
+    /// 名前がプロパティキーで決まる関数
+    KeyedClosure {
+        function: u32,
+        kind: PropertyKind,
+    },
+    /// `[this, 関数, 引数...]`で呼び出す
+    Call(u32),
+    /// `[関数, 引数...]`で`new`する
+    Construct(u32),
+    /// 展開した引数は、引数の後ろに積んだ数で数える
+    PushArgument,
+    SpreadArgument,
+    CallSpread,
+    ConstructSpread,
+
+    Jump(u32),
+    JumpIfFalse(u32),
+    JumpIfTrue(u32),
+    /// 短絡評価。結果が決まったら値を残して飛ぶ
+    JumpIfFalseOrPop(u32),
+    JumpIfTrueOrPop(u32),
+    JumpIfNotNullishOrPop(u32),
+    /// `?.`の左側がnullかundefinedなら、値を捨ててチェーンの外に飛ぶ
+    JumpIfNullish(u32),
+    JumpIfNotUndefined(u32),
+
+    /// for-inとfor-ofで取り出す値を用意する
+    IterateKeys,
+    IterateValues,
+    /// 次の値を積む。値がなければ飛ぶ
+    IterNext(u32),
+    IterPop,
+
+    /// 例外を捕まえる位置。捕まえた例外は`completion`番目に保存する
+    PushHandler {
+        target: u32,
+        completion: u32,
+    },
+    PopHandler,
+    /// 保存した例外の値を積む
+    LoadException(u32),
+    Rethrow(u32),
+    Throw,
+    ThrowError {
+        kind: ErrorKind,
+        message: u32,
+    },
+    Return,
+    /// スクリプトの最後。レジスタの値を文の値として返す
+    ReturnRegister(u32),
+    /// スクリプトの文と文の間と、繰り返しの終わり。必要ならGCを実行する
+    Collect,
+}
+
+impl Op {
+    fn target_mut(&mut self) -> Option<&mut u32> {
+        match self {
+            Op::Jump(target)
+            | Op::JumpIfFalse(target)
+            | Op::JumpIfTrue(target)
+            | Op::JumpIfFalseOrPop(target)
+            | Op::JumpIfTrueOrPop(target)
+            | Op::JumpIfNotNullishOrPop(target)
+            | Op::JumpIfNullish(target)
+            | Op::JumpIfNotUndefined(target)
+            | Op::IterNext(target)
+            | Op::PushHandler { target, .. } => Some(target),
+            _ => None,
+        }
+    }
+}
+
+/// 関数やスクリプトの命令列と、命令が参照する表
+#[derive(Debug, Clone, Default)]
+pub struct Code {
+    pub ops: Vec<Op>,
+    /// 数値と文字列などのプリミティブ値
+    pub constants: Vec<RuntimeValue>,
+    /// 変数の名前とエラーメッセージ
+    pub names: Vec<String>,
+    pub keys: Vec<PropertyKey>,
+    pub functions: Vec<Rc<FunctionCode>>,
+    pub registers: usize,
+    pub completions: usize,
+}
+
+/// コンパイルした関数。実行時にスコープと組み合わせて関数のオブジェクトになる
+#[derive(Debug, Clone)]
+pub struct FunctionCode {
+    pub name: String,
+    /// 名前のある関数式。本体から自分自身を名前で参照できるスコープを作る
+    pub self_binding: bool,
+    pub params: Vec<Option<Rc<Node>>>,
+    pub body: Option<Rc<Node>>,
+    pub is_arrow: bool,
+    pub uses_arguments: bool,
+    pub code: Code,
+}
+
+/// コンパイル中のスコープ。実行時に作るスコープと1対1で対応する
+#[derive(Debug, Clone, Default)]
+struct Scope {
+    /// 実行時のスコープの変数と同じ順に並べる
+    names: Vec<String>,
+    /// 実行するまで変数が決まらないスコープ。ここにない名前は名前で探す
+    dynamic: bool,
+}
+
+impl Scope {
+    fn declare_var(&mut self, name: &str) {
+        if !self.names.iter().any(|n| n == name) {
+            self.names.push(name.to_string());
+        }
+    }
+
+    fn declare_lexical(&mut self, name: &str) {
+        self.names.retain(|n| n != name);
+        self.names.push(name.to_string());
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum JumpKind {
+    Loop,
+    Switch,
+    Label,
+}
+
+/// 文を抜けるときに後始末が必要な構造と、break/continueの飛び先
+#[derive(Debug, Clone)]
+enum Control {
+    Scope(Scope),
+    Iterator,
+    Handler,
+    /// 抜ける前にfinallyの本体を実行する
+    Finally(Option<Rc<Node>>),
+    Jump {
+        kind: JumpKind,
+        labels: Vec<String>,
+        /// ラベルのないbreakの飛び先。繰り返しとswitchの文の値を外側に伝える
+        break_label: usize,
+        /// ラベルのあるbreakの飛び先。文の値は伝えない
+        labeled_break: usize,
+        continue_label: usize,
+    },
+}
+
+#[derive(Debug, Default)]
+struct FunctionState {
+    code: Code,
+    controls: Vec<Control>,
+    /// 関数全体のスコープより内側の構造の始まり
+    base: usize,
+    /// ラベルの位置。置かれる前はNone
+    labels: Vec<Option<usize>>,
+    /// `?.`で抜ける先 (値を捨てる数が1つと2つ)
+    chains: Vec<(usize, usize)>,
+}
+
+/// 関数やスクリプトを、外側の関数のスコープを見ながらコンパイルする
+#[derive(Debug, Default)]
+struct Compiler {
+    functions: Vec<FunctionState>,
+}
+
+/// スクリプトをコンパイルする。変数はグローバルのスコープに名前で作る
+pub fn compile_program(program: &Program) -> Code {
+    let mut compiler = Compiler::default();
+    let scope = Scope {
+        names: Vec::new(),
+        dynamic: true,
+    };
+    compiler.functions.push(FunctionState {
+        controls: Vec::from([Control::Scope(scope)]),
+        base: 1,
+        ..Default::default()
+    });
+
+    let body = program
+        .body()
+        .iter()
+        .map(|node| Some(node.clone()))
+        .collect::<Vec<_>>();
+    let result = compiler.register();
+    compiler.declarations(&body, true);
+    for statement in &body {
+        compiler.statement(statement, Some(result));
+        compiler.emit(Op::Collect);
+    }
+    compiler.emit(Op::ReturnRegister(result));
+    compiler.finish()
+}
+
+/// 文の位置に書かれた`let`と`const`があるかどうか (`if (a) let b = 1;`など)
+/// そのような変数は実行したときに今のスコープに作られるため、コンパイル時には位置が決まらない
+fn has_unhoisted_lexical(statement: &Option<Rc<Node>>, in_list: bool) -> bool {
+    let in_list_any = |statements: &[Option<Rc<Node>>]| {
+        statements
+            .iter()
+            .any(|statement| has_unhoisted_lexical(statement, true))
+    };
+    match statement.as_deref() {
+        Some(Node::VariableDecration { kind, .. }) => *kind != "var" && !in_list,
+        Some(Node::BlockStatement { body }) => in_list_any(body),
+        Some(Node::IfStatement {
+            consequent,
+            alternate,
+            ..
+        }) => has_unhoisted_lexical(consequent, false) || has_unhoisted_lexical(alternate, false),
+        Some(Node::WhileStatement { body, .. })
+        | Some(Node::DoWhileStatement { body, .. })
+        | Some(Node::ForStatement { body, .. })
+        | Some(Node::ForInStatement { body, .. })
+        | Some(Node::ForOfStatement { body, .. })
+        | Some(Node::LabeledStatement { body, .. }) => has_unhoisted_lexical(body, false),
+        Some(Node::SwitchStatement { cases, .. }) => {
+            cases.iter().any(|case| match case.as_deref() {
+                Some(Node::SwitchCase { consequent, .. }) => in_list_any(consequent),
+                _ => false,
+            })
+        }
+        Some(Node::TryStatement {
+            block,
+            handler,
+            finalizer,
+        }) => {
+            let handler = match handler.as_deref() {
+                Some(Node::CatchClause { body, .. }) => has_unhoisted_lexical(body, true),
+                _ => false,
+            };
+            has_unhoisted_lexical(block, true) || handler || has_unhoisted_lexical(finalizer, true)
+        }
+        _ => false,
+    }
+}
+
+/// 文の並びが作るスコープ
+fn scope_for(statements: &[Option<Rc<Node>>]) -> Scope {
+    Scope {
+        names: Vec::new(),
+        dynamic: statements
+            .iter()
+            .any(|statement| has_unhoisted_lexical(statement, true)),
+    }
+}
+
+/// オブジェクトリテラルの関数の名前の前につける文字列
+pub fn property_function_prefix(kind: PropertyKind) -> Option<&'static str> {
+    match kind {
+        PropertyKind::Init => None,
+        PropertyKind::Get => Some("get"),
+        PropertyKind::Set => Some("set"),
+    }
+}
+
+/// 名前のない関数式とアロー関数
+fn is_anonymous_function(node: &Option<Rc<Node>>) -> bool {
+    matches!(
+        node.as_deref(),
+        Some(Node::FunctionExpression { id: None, .. })
+            | Some(Node::ArrowFunctionExpression { .. })
+    )
+}
+
+impl Compiler {
+    fn state(&mut self) -> &mut FunctionState {
+        self.functions.last_mut().expect("no function")
+    }
+
+    fn emit(&mut self, op: Op) {
+        self.state().code.ops.push(op);
+    }
+
+    fn label(&mut self) -> usize {
+        let labels = &mut self.state().labels;
+        labels.push(None);
+        labels.len() - 1
+    }
+
+    fn place(&mut self, label: usize) {
+        let state = self.state();
+        state.labels[label] = Some(state.code.ops.len());
+    }
+
+    fn register(&mut self) -> u32 {
+        let code = &mut self.state().code;
+        code.registers += 1;
+        (code.registers - 1) as u32
+    }
+
+    fn completion(&mut self) -> u32 {
+        let code = &mut self.state().code;
+        code.completions += 1;
+        (code.completions - 1) as u32
+    }
+
+    fn constant(&mut self, value: RuntimeValue) {
+        let constants = &mut self.state().code.constants;
+        // -0と+0、NaNを区別する
+        let same = |c: &RuntimeValue| match (c, &value) {
+            (RuntimeValue::Number(a), RuntimeValue::Number(b)) => a.to_bits() == b.to_bits(),
+            (c, value) => c == value,
+        };
+        let index = match constants.iter().position(same) {
+            Some(index) => index,
+            None => {
+                constants.push(value);
+                constants.len() - 1
+            }
+        };
+        self.emit(Op::Constant(index as u32));
+    }
+
+    fn name(&mut self, name: &str) -> u32 {
+        let names = &mut self.state().code.names;
+        match names.iter().position(|n| n == name) {
+            Some(index) => index as u32,
+            None => {
+                names.push(name.to_string());
+                (names.len() - 1) as u32
+            }
+        }
+    }
+
+    fn key(&mut self, name: &str) -> u32 {
+        let keys = &mut self.state().code.keys;
+        let key = PropertyKey::from(name);
+        match keys.iter().position(|k| *k == key) {
+            Some(index) => index as u32,
+            None => {
+                keys.push(key);
+                (keys.len() - 1) as u32
+            }
+        }
+    }
+
+    /// ジャンプ先をラベルから命令の位置にする
+    fn finish(&mut self) -> Code {
+        let mut state = self.functions.pop().expect("no function");
+        for op in &mut state.code.ops {
+            if let Some(target) = op.target_mut() {
+                *target = state.labels[*target as usize].expect("label is not placed") as u32;
+            }
+        }
+        state.code
+    }
+
+    fn push_scope(&mut self, scope: Scope) {
+        self.emit(Op::PushScope);
+        self.state().controls.push(Control::Scope(scope));
+    }
+
+    fn pop_scope(&mut self) {
+        self.state().controls.pop();
+        self.emit(Op::PopScope);
+    }
+
+    fn current_scope(&mut self) -> &mut Scope {
+        for control in self.state().controls.iter_mut().rev() {
+            if let Control::Scope(scope) = control {
+                return scope;
+            }
+        }
+        unreachable!("function has no scope")
+    }
+
+    /// コンパイル時に変数の位置が決まる場合は、スコープの深さと位置
+    fn resolve(&self, name: &str) -> Option<(u32, u32)> {
+        let mut depth = 0;
+        for function in self.functions.iter().rev() {
+            for control in function.controls.iter().rev() {
+                if let Control::Scope(scope) = control {
+                    if let Some(index) = scope.names.iter().position(|n| n == name) {
+                        return Some((depth, index as u32));
+                    }
+                    if scope.dynamic {
+                        return None;
+                    }
+                    depth += 1;
+                }
+            }
+        }
+        None
+    }
+
+    fn get_variable(&mut self, name: &str) {
+        let op = match self.resolve(name) {
+            Some((depth, index)) => Op::GetLocal {
+                depth,
+                index,
+                name: self.name(name),
+            },
+            None => Op::GetName(self.name(name)),
+        };
+        self.emit(op);
+    }
+
+    fn set_variable(&mut self, name: &str) {
+        let op = match self.resolve(name) {
+            Some((depth, index)) => Op::SetLocal {
+                depth,
+                index,
+                name: self.name(name),
+            },
+            None => Op::SetName(self.name(name)),
+        };
+        self.emit(op);
+    }
+
+    /// 文の並びの中の宣言を、実行する前にスコープに作る (hoisting)
+    /// 変数の並びを先に決めておき、関数の本体からも位置で参照できるようにする
+    fn declarations(&mut self, body: &[Option<Rc<Node>>], function_scope: bool) {
+        let mut vars = Vec::new();
+        if function_scope {
+            for statement in body {
+                var_declared_names(statement, &mut vars);
+            }
+        }
+        // `let`と`const`は変更できるかどうか、関数の宣言はその節
+        let mut declarations = Vec::new();
+        for statement in body {
+            match statement.as_deref() {
+                Some(Node::VariableDecration {
+                    kind,
+                    declarations: declarators,
+                }) if *kind != "var" => {
+                    for declarator in declarators {
+                        if let Some(Node::VariableDeclarator { id, .. }) = declarator.as_deref() {
+                            if let Some(Node::Identifier(name)) = id.as_deref() {
+                                declarations.push((name, Ok(*kind == "let")));
+                            }
+                        }
+                    }
+                }
+                Some(Node::FunctionDeclaration { id, .. }) => {
+                    if let Some(Node::Identifier(name)) = id.as_deref() {
+                        declarations.push((name, Err(statement)));
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        // 実行時と同じ順に宣言する。関数は、宣言を作り終えたスコープでコンパイルする
+        let scope = self.current_scope();
+        if !scope.dynamic {
+            for name in &vars {
+                scope.declare_var(name);
+            }
+            for (name, declaration) in &declarations {
+                match declaration {
+                    Ok(_) => scope.declare_lexical(name),
+                    Err(_) => scope.declare_var(name),
+                }
+            }
+        }
+
+        for name in &vars {
+            let name = self.name(name);
+            self.emit(Op::DeclareVar(name));
+        }
+        for (name, declaration) in declarations {
+            match declaration {
+                Ok(mutable) => {
+                    let name = self.name(name);
+                    self.emit(Op::CheckDeclarable(name));
+                    self.emit(Op::DeclareLexical { name, mutable });
+                }
+                Err(statement) => {
+                    if let Some(Node::FunctionDeclaration { params, body, .. }) =
+                        statement.as_deref()
+                    {
+                        let function = self.function(name, false, params, body, false);
+                        self.emit(Op::Closure(function));
+                        let name = self.name(name);
+                        self.emit(Op::InitName(name));
+                    }
+                }
+            }
+        }
+    }
+
+    /// 関数をコンパイルし、今の関数の関数表での位置を返す
+    fn function(
+        &mut self,
+        name: &str,
+        self_binding: bool,
+        params: &[Option<Rc<Node>>],
+        body: &Option<Rc<Node>>,
+        is_arrow: bool,
+    ) -> u32 {
+        let mut controls = Vec::new();
+        if self_binding {
+            controls.push(Control::Scope(Scope {
+                names: Vec::from([name.to_string()]),
+                dynamic: false,
+            }));
+        }
+        // 既定値の式からは、まだ宣言されていない本体の変数を参照できない
+        // 既定値の式の中で作る関数が後から参照する変数も、名前で探す
+        controls.push(Control::Scope(Scope {
+            names: Vec::new(),
+            dynamic: true,
+        }));
+        let base = controls.len();
+        self.functions.push(FunctionState {
+            controls,
+            base,
+            ..Default::default()
+        });
+
+        let uses_arguments = !is_arrow && uses_arguments(params, body);
+        if uses_arguments {
+            self.current_scope().declare_lexical("arguments");
+            let name = self.name("arguments");
+            self.emit(Op::DeclareLexical {
+                name,
+                mutable: true,
+            });
+            self.emit(Op::CreateArguments);
+            self.init_variable("arguments");
+        }
+        for param in params {
+            if let Some(name) = parameter_name(param) {
+                self.current_scope().declare_lexical(name);
+                let name = self.name(name);
+                self.emit(Op::DeclareLexical {
+                    name,
+                    mutable: true,
+                });
+            }
+        }
+        for (i, param) in params.iter().enumerate() {
+            let name = match param.as_deref() {
+                Some(Node::Identifier(name)) => {
+                    self.emit(Op::Argument(i as u32));
+                    name
+                }
+                Some(Node::AssignmentPattern { left, right }) => match left.as_deref() {
+                    Some(Node::Identifier(name)) => {
+                        let label = self.label();
+                        self.emit(Op::Argument(i as u32));
+                        self.emit(Op::JumpIfNotUndefined(label as u32));
+                        self.emit(Op::Pop);
+                        self.expression(right);
+                        self.place(label);
+                        name
+                    }
+                    _ => continue,
+                },
+                Some(Node::RestElement(argument)) => match argument.as_deref() {
+                    Some(Node::Identifier(name)) => {
+                        self.emit(Op::RestArguments(i as u32));
+                        name
+                    }
+                    _ => continue,
+                },
+                _ => continue,
+            };
+            self.init_variable(name);
+        }
+
+        if let Some(Node::BlockStatement { body }) = body.as_deref() {
+            let dynamic = scope_for(body).dynamic;
+            self.current_scope().dynamic = dynamic;
+            self.declarations(body, true);
+            for statement in body {
+                self.statement(statement, None);
+            }
+        }
+        self.emit(Op::Undefined);
+        self.emit(Op::Return);
+
+        let function = FunctionCode {
+            name: name.to_string(),
+            self_binding,
+            params: params.to_vec(),
+            body: body.clone(),
+            is_arrow,
+            uses_arguments,
+            code: self.finish(),
+        };
+        let functions = &mut self.state().code.functions;
+        functions.push(Rc::new(function));
+        (functions.len() - 1) as u32
+    }
+
+    /// 今のスコープにある変数を、積まれた値で初期化する
+    fn init_variable(&mut self, name: &str) {
+        let index = self.current_scope().names.iter().position(|n| n == name);
+        let op = match index {
+            Some(index) => Op::InitLocal(index as u32),
+            None => Op::InitName(self.name(name)),
+        };
+        self.emit(op);
+    }
+
+    /// 関数式とアロー関数を評価する。名前のない関数には`name`をつける
+    fn named_expression(&mut self, node: &Option<Rc<Node>>, name: &str) {
+        match node.as_deref() {
+            Some(Node::FunctionExpression {
+                id: Some(id),
+                params,
+                body,
+            }) => {
+                let id = match id.as_ref() {
+                    Node::Identifier(id) => id.as_str(),
+                    _ => name,
+                };
+                let function = self.function(id, true, params, body, false);
+                self.emit(Op::Closure(function));
+            }
+            Some(Node::FunctionExpression {
+                id: None,
+                params,
+                body,
+            }) => {
+                let function = self.function(name, false, params, body, false);
+                self.emit(Op::Closure(function));
+            }
+            Some(Node::ArrowFunctionExpression { params, body }) => {
+                let function = self.function(name, false, params, body, true);
+                self.emit(Op::Closure(function));
+            }
+            _ => self.expression(node),
+        }
+    }
+
+    /// 文を抜けるときに、`depth`より内側の構造の後始末をする
+    fn exit_to(&mut self, depth: usize) {
+        let mut i = self.state().controls.len();
+        while i > depth {
+            i -= 1;
+            match self.state().controls[i].clone() {
+                Control::Scope(_) => self.emit(Op::PopScope),
+                Control::Iterator => self.emit(Op::IterPop),
+                Control::Handler => self.emit(Op::PopHandler),
+                Control::Finally(finalizer) => {
+                    // finallyの本体は、そのtry文の外側の構造の中で実行する
+                    let inner = self.state().controls.split_off(i);
+                    self.statement(&finalizer, None);
+                    self.state().controls.extend(inner);
+                }
+                Control::Jump { .. } => {}
+            }
+        }
+    }
+
+    fn jump_target(&mut self, label: &Option<String>, is_continue: bool) -> Option<(usize, usize)> {
+        let controls = &self.state().controls;
+        for (i, control) in controls.iter().enumerate().rev() {
+            if let Control::Jump {
+                kind,
+                labels,
+                break_label,
+                labeled_break,
+                continue_label,
+            } = control
+            {
+                let target = match (label, is_continue) {
+                    (None, false) if *kind != JumpKind::Label => *break_label,
+                    (None, true) if *kind == JumpKind::Loop => *continue_label,
+                    (Some(label), false) if labels.contains(label) => *labeled_break,
+                    (Some(label), true) if *kind == JumpKind::Loop && labels.contains(label) => {
+                        *continue_label
+                    }
+                    _ => continue,
+                };
+                return Some((i, target));
+            }
+        }
+        None
+    }
+
+    /// 文をコンパイルする。値のある文で最後まで実行した場合は、その値を`target`のレジスタに入れる
+    fn statement(&mut self, node: &Option<Rc<Node>>, target: Option<u32>) {
+        let node = match node {
+            Some(node) => node,
+            None => return,
+        };
+
+        match node.as_ref() {
+            Node::ExpressionStatement(expression) => {
+                self.expression(expression);
+                match target {
+                    Some(target) => self.emit(Op::Store(target)),
+                    None => self.emit(Op::Pop),
+                }
+            }
+            Node::VariableDecration { kind, declarations } => {
+                for declaration in declarations {
+                    let (name, init) = match declaration.as_deref() {
+                        Some(Node::VariableDeclarator { id: Some(id), init }) => {
+                            match id.as_ref() {
+                                Node::Identifier(name) => (name, init),
+                                _ => continue,
+                            }
+                        }
+                        _ => continue,
+                    };
+
+                    if *kind == "var" {
+                        // 宣言はすでにhoistingで作られている。初期値のない再宣言では値を変えない
+                        if init.is_some() {
+                            self.named_expression(init, name);
+                            self.set_variable(name);
+                            self.emit(Op::Pop);
+                        }
+                    } else {
+                        self.named_expression(init, name);
+                        let scope = self.current_scope();
+                        match scope.names.iter().position(|n| n == name) {
+                            Some(index) => self.emit(Op::InitLocal(index as u32)),
+                            None => {
+                                let name = self.name(name);
+                                self.emit(Op::InitLexical {
+                                    name,
+                                    mutable: *kind == "let",
+                                });
+                            }
+                        }
+                    }
+                }
+            }
+            Node::BlockStatement { body } => {
+                let result = target.map(|_| self.register());
+                if let Some(result) = result {
+                    self.emit(Op::Clear(result));
+                }
+                // `let`、`const`、関数の宣言はブロックのスコープに作る
+                let lexical = body.iter().any(is_lexical_declaration);
+                if lexical {
+                    self.push_scope(scope_for(body));
+                    self.declarations(body, false);
+                }
+                for statement in body {
+                    self.statement(statement, result);
+                }
+                if lexical {
+                    self.pop_scope();
+                }
+                if let (Some(from), Some(to)) = (result, target) {
+                    self.emit(Op::Move { from, to });
+                }
+            }
+            Node::IfStatement {
+                test,
+                consequent,
+                alternate,
+            } => {
+                let else_label = self.label();
+                let end = self.label();
+                self.expression(test);
+                self.emit(Op::JumpIfFalse(else_label as u32));
+                self.statement(consequent, target);
+                self.emit(Op::Jump(end as u32));
+                self.place(else_label);
+                self.statement(alternate, target);
+                self.place(end);
+            }
+            Node::WhileStatement { .. }
+            | Node::DoWhileStatement { .. }
+            | Node::ForStatement { .. }
+            | Node::ForInStatement { .. }
+            | Node::ForOfStatement { .. } => self.iteration(node, Vec::new(), target),
+            Node::LabeledStatement { .. } => {
+                // 連続したラベルはまとめて1つの文につける
+                let mut labels = Vec::new();
+                let mut statement = node;
+                while let Node::LabeledStatement { label, body } = statement.as_ref() {
+                    labels.push(label.clone());
+                    statement = match body {
+                        Some(body) => body,
+                        None => return,
+                    };
+                }
+
+                match statement.as_ref() {
+                    Node::WhileStatement { .. }
+                    | Node::DoWhileStatement { .. }
+                    | Node::ForStatement { .. }
+                    | Node::ForInStatement { .. }
+                    | Node::ForOfStatement { .. } => self.iteration(statement, labels, target),
+                    _ => {
+                        let end = self.label();
+                        self.state().controls.push(Control::Jump {
+                            kind: JumpKind::Label,
+                            labels,
+                            break_label: end,
+                            labeled_break: end,
+                            continue_label: end,
+                        });
+                        self.statement(&Some(statement.clone()), target);
+                        self.state().controls.pop();
+                        self.place(end);
+                    }
+                }
+            }
+            Node::SwitchStatement {
+                discriminant,
+                cases,
+            } => self.switch(discriminant, cases, target),
+            Node::BreakStatement { label } | Node::ContinueStatement { label } => {
+                let is_continue = matches!(node.as_ref(), Node::ContinueStatement { .. });
+                // 構文解析で対象のないbreakとcontinueは弾かれている
+                if let Some((depth, label)) = self.jump_target(label, is_continue) {
+                    self.exit_to(depth + 1);
+                    self.emit(Op::Jump(label as u32));
+                }
+            }
+            Node::ThrowStatement { argument } => {
+                self.expression(argument);
+                self.emit(Op::Throw);
+            }
+            Node::TryStatement {
+                block,
+                handler,
+                finalizer,
+            } => self.try_statement(block, handler, finalizer, target),
+            Node::ReturnStatement { argument } => {
+                self.expression(argument);
+                let base = self.state().base;
+                let has_finally = self.state().controls[base..]
+                    .iter()
+                    .any(|control| matches!(control, Control::Finally(_)));
+                if has_finally {
+                    self.exit_to(base);
+                }
+                self.emit(Op::Return);
+            }
+            // 関数の宣言はhoistingで作られている
+            _ => {}
+        }
+    }
+
+    /// 繰り返しの文をコンパイルする。`labels`はその文についたラベル
+    fn iteration(&mut self, node: &Rc<Node>, labels: Vec<String>, target: Option<u32>) {
+        let result = target.map(|_| self.register());
+        if let Some(result) = result {
+            self.emit(Op::Clear(result));
+        }
+        let break_label = self.label();
+        let labeled_break = self.label();
+        let continue_label = self.label();
+        let control = Control::Jump {
+            kind: JumpKind::Loop,
+            labels,
+            break_label,
+            labeled_break,
+            continue_label,
+        };
+        // ラベルのないbreakと最後まで繰り返した場合は、繰り返しの値が文の値になる
+        let end = |compiler: &mut Self| {
+            compiler.place(break_label);
+            if let (Some(from), Some(to)) = (result, target) {
+                compiler.emit(Op::Move { from, to });
+            }
+            compiler.place(labeled_break);
+        };
+
+        match node.as_ref() {
+            Node::WhileStatement { test, body } => {
+                self.state().controls.push(control);
+                self.place(continue_label);
+                self.emit(Op::Collect);
+                self.expression(test);
+                self.emit(Op::JumpIfFalse(break_label as u32));
+                self.statement(body, result);
+                self.emit(Op::Jump(continue_label as u32));
+                self.state().controls.pop();
+                end(self);
+            }
+            Node::DoWhileStatement { body, test } => {
+                let top = self.label();
+                self.state().controls.push(control);
+                self.place(top);
+                self.statement(body, result);
+                self.place(continue_label);
+                self.emit(Op::Collect);
+                self.expression(test);
+                self.emit(Op::JumpIfTrue(top as u32));
+                self.state().controls.pop();
+                end(self);
+            }
+            Node::ForStatement {
+                init,
+                test,
+                update,
+                body,
+            } => {
+                // `let`で宣言した変数は、for文のスコープに作る
+                let lexical = matches!(
+                    init.as_deref(),
+                    Some(Node::VariableDecration { kind, .. }) if *kind != "var"
+                );
+                if lexical {
+                    self.push_scope(scope_for(core::slice::from_ref(body)));
+                    self.declarations(core::slice::from_ref(init), false);
+                }
+                match init.as_deref() {
+                    Some(Node::VariableDecration { .. }) => self.statement(init, None),
+                    Some(_) => {
+                        self.expression(init);
+                        self.emit(Op::Pop);
+                    }
+                    None => {}
+                }
+                // 繰り返しごとに変数を作り直し、クロージャがそれぞれの値を捕捉できるようにする
+                if lexical {
+                    self.emit(Op::CopyScope);
+                }
+
+                let top = self.label();
+                self.state().controls.push(control);
+                self.place(top);
+                if test.is_some() {
+                    self.expression(test);
+                    self.emit(Op::JumpIfFalse(break_label as u32));
+                }
+                self.statement(body, result);
+                self.place(continue_label);
+                self.emit(Op::Collect);
+                if lexical {
+                    self.emit(Op::CopyScope);
+                }
+                if update.is_some() {
+                    self.expression(update);
+                    self.emit(Op::Pop);
+                }
+                self.emit(Op::Jump(top as u32));
+                self.state().controls.pop();
+                end(self);
+                if lexical {
+                    self.pop_scope();
+                }
+            }
+            Node::ForInStatement { left, right, body }
+            | Node::ForOfStatement { left, right, body } => {
+                self.expression(right);
+                if let Node::ForInStatement { .. } = node.as_ref() {
+                    self.emit(Op::IterateKeys);
+                } else {
+                    self.emit(Op::IterateValues);
+                }
+
+                // `var x`の形なら変数を宣言してから、その変数に代入していく
+                // `let x`と`const x`の形なら、繰り返しごとに新しいスコープに変数を作る
+                let (assignee, lexical) = match left.as_deref() {
+                    Some(Node::VariableDecration { kind, declarations }) => {
+                        let id = match declarations.first().and_then(|d| d.as_deref()) {
+                            Some(Node::VariableDeclarator { id, .. }) => id.clone(),
+                            _ => None,
+                        };
+                        if *kind == "var" {
+                            self.statement(left, None);
+                            (id, None)
+                        } else {
+                            (id, Some(*kind))
+                        }
+                    }
+                    _ => (left.clone(), None),
+                };
+
+                self.state().controls.push(Control::Iterator);
+                self.state().controls.push(control);
+                self.place(continue_label);
+                self.emit(Op::Collect);
+                self.emit(Op::IterNext(break_label as u32));
+                match (lexical, assignee.as_deref()) {
+                    (Some(kind), Some(Node::Identifier(name))) => {
+                        self.push_scope(Scope {
+                            names: Vec::from([name.clone()]),
+                            dynamic: has_unhoisted_lexical(body, false),
+                        });
+                        let name = self.name(name);
+                        self.emit(Op::DeclareLexical {
+                            name,
+                            mutable: kind == "let",
+                        });
+                        self.emit(Op::InitLocal(0));
+                        self.statement(body, result);
+                        self.pop_scope();
+                    }
+                    _ => {
+                        self.assign_value(&assignee);
+                        self.statement(body, result);
+                    }
+                }
+                self.emit(Op::Jump(continue_label as u32));
+                self.state().controls.pop();
+                end(self);
+                self.state().controls.pop();
+                self.emit(Op::IterPop);
+            }
+            _ => self.statement(&Some(node.clone()), target),
+        }
+    }
+
+    fn switch(
+        &mut self,
+        discriminant: &Option<Rc<Node>>,
+        cases: &[Option<Rc<Node>>],
+        target: Option<u32>,
+    ) {
+        let value = self.register();
+        self.expression(discriminant);
+        self.emit(Op::Store(value));
+
+        // すべての節の宣言が、switch文全体で1つのスコープに入る
+        let statements = cases
+            .iter()
+            .filter_map(|case| match case.as_deref() {
+                Some(Node::SwitchCase { consequent, .. }) => Some(consequent.iter()),
+                _ => None,
+            })
+            .flatten()
+            .cloned()
+            .collect::<Vec<_>>();
+        let lexical = statements.iter().any(is_lexical_declaration);
+        if lexical {
+            self.push_scope(scope_for(&statements));
+            self.declarations(&statements, false);
+        }
+
+        let result = target.map(|_| self.register());
+        if let Some(result) = result {
+            self.emit(Op::Clear(result));
+        }
+        let break_label = self.label();
+        let labeled_break = self.label();
+        let case_labels = cases.iter().map(|_| self.label()).collect::<Vec<_>>();
+        for (case, label) in cases.iter().zip(&case_labels) {
+            if let Some(Node::SwitchCase {
+                test: Some(test), ..
+            }) = case.as_deref()
+            {
+                self.emit(Op::Load(value));
+                self.expression(&Some(test.clone()));
+                self.emit(Op::Binary(BinaryOperator::StrictEquals));
+                self.emit(Op::JumpIfTrue(*label as u32));
+            }
+        }
+        // 一致する節がなければdefaultの節から実行する
+        let default = cases
+            .iter()
+            .position(|case| matches!(case.as_deref(), Some(Node::SwitchCase { test: None, .. })));
+        match default {
+            Some(i) => self.emit(Op::Jump(case_labels[i] as u32)),
+            None => self.emit(Op::Jump(labeled_break as u32)),
+        }
+
+        // 一致したところから、breakまで後ろの節も実行する
+        self.state().controls.push(Control::Jump {
+            kind: JumpKind::Switch,
+            labels: Vec::new(),
+            break_label,
+            labeled_break,
+            continue_label: break_label,
+        });
+        for (case, label) in cases.iter().zip(&case_labels) {
+            self.place(*label);
+            if let Some(Node::SwitchCase { consequent, .. }) = case.as_deref() {
+                for statement in consequent {
+                    self.statement(statement, result);
+                }
+            }
+        }
+        self.state().controls.pop();
+        self.place(break_label);
+        if let (Some(from), Some(to)) = (result, target) {
+            self.emit(Op::Move { from, to });
+        }
+        self.place(labeled_break);
+        if lexical {
+            self.pop_scope();
+        }
+    }
+
+    fn try_statement(
+        &mut self,
+        block: &Option<Rc<Node>>,
+        handler: &Option<Rc<Node>>,
+        finalizer: &Option<Rc<Node>>,
+        target: Option<u32>,
+    ) {
+        let result = target.map(|_| self.register());
+        if let Some(result) = result {
+            self.emit(Op::Clear(result));
+        }
+
+        // finallyは、tryとcatchの本体から投げられた例外も捕まえる
+        let finally = finalizer
+            .as_ref()
+            .map(|_| (self.completion(), self.label()));
+        if let Some((completion, label)) = finally {
+            self.emit(Op::PushHandler {
+                target: label as u32,
+                completion,
+            });
+            self.state()
+                .controls
+                .push(Control::Finally(finalizer.clone()));
+            self.state().controls.push(Control::Handler);
+        }
+
+        match handler.as_deref() {
+            Some(Node::CatchClause { param, body }) => {
+                let completion = self.completion();
+                let catch_label = self.label();
+                let end = self.label();
+                self.emit(Op::PushHandler {
+                    target: catch_label as u32,
+                    completion,
+                });
+                self.state().controls.push(Control::Handler);
+                self.statement(block, result);
+                self.state().controls.pop();
+                self.emit(Op::PopHandler);
+                self.emit(Op::Jump(end as u32));
+
+                self.place(catch_label);
+                let name = match param.as_deref() {
+                    Some(Node::Identifier(name)) => Some(name.clone()),
+                    _ => None,
+                };
+                self.push_scope(Scope {
+                    names: name.iter().cloned().collect(),
+                    dynamic: has_unhoisted_lexical(body, true),
+                });
+                self.emit(Op::LoadException(completion));
+                match name {
+                    Some(name) => {
+                        let name = self.name(&name);
+                        self.emit(Op::InitName(name));
+                    }
+                    None => self.emit(Op::Pop),
+                }
+                self.statement(body, result);
+                self.pop_scope();
+                self.place(end);
+            }
+            _ => self.statement(block, result),
+        }
+
+        // finallyが途中で終わった場合は、その結果で上書きする
+        if let Some((completion, label)) = finally {
+            let end = self.label();
+            self.state().controls.pop();
+            self.emit(Op::PopHandler);
+            self.state().controls.pop();
+            self.statement(finalizer, None);
+            self.emit(Op::Jump(end as u32));
+            self.place(label);
+            self.statement(finalizer, None);
+            self.emit(Op::Rethrow(completion));
+            self.place(end);
+        }
+        if let (Some(from), Some(to)) = (result, target) {
+            self.emit(Op::Move { from, to });
+        }
+    }
+
+    /// `a.b`と`a[b]`のキーを積む
+    fn property_key(&mut self, property: &Option<Rc<Node>>, computed: bool) {
+        match property.as_deref() {
+            Some(Node::Identifier(name)) if !computed => {
+                self.constant(RuntimeValue::String(name.clone()))
+            }
+            _ => {
+                self.expression(property);
+                self.emit(Op::ToPropertyKey);
+            }
+        }
+    }
+
+    /// 積まれた値を代入先に書き込み、値を捨てる
+    fn assign_value(&mut self, target: &Option<Rc<Node>>) {
+        match target.as_deref() {
+            Some(Node::Identifier(name)) => {
+                self.set_variable(name);
+                self.emit(Op::Pop);
+            }
+            Some(Node::MemberExpression {
+                object,
+                property,
+                computed,
+                ..
+            }) => {
+                let value = self.register();
+                self.emit(Op::Store(value));
+                self.expression(object);
+                match property.as_deref() {
+                    Some(Node::Identifier(name)) if !computed => {
+                        let key = self.key(name);
+                        self.emit(Op::Load(value));
+                        self.emit(Op::SetNamed(key));
+                    }
+                    _ => {
+                        self.property_key(property, *computed);
+                        self.emit(Op::Load(value));
+                        self.emit(Op::SetElement);
+                    }
+                }
+                self.emit(Op::Pop);
+            }
+            _ => {
+                self.emit(Op::Pop);
+                self.invalid_assignment_target();
+            }
+        }
+    }
+
+    /// 構文解析の時点で弾かれている
+    fn invalid_assignment_target(&mut self) {
+        let message = self.name("Invalid left-hand side in assignment");
+        self.emit(Op::ThrowError {
+            kind: ErrorKind::ReferenceError,
+            message,
+        });
+        self.emit(Op::Undefined);
+    }
+
+    /// 代入先のプロパティのオブジェクトとキーを積み、キーの位置を返す
+    /// `a.b`の形ならキー表の位置を返し、キーは積まない
+    fn member_reference(
+        &mut self,
+        object: &Option<Rc<Node>>,
+        property: &Option<Rc<Node>>,
+        computed: bool,
+    ) -> Option<u32> {
+        self.expression(object);
+        match property.as_deref() {
+            Some(Node::Identifier(name)) if !computed => Some(self.key(name)),
+            _ => {
+                self.property_key(property, computed);
+                None
+            }
+        }
+    }
+
+    fn arguments(&mut self, arguments: &[Option<Rc<Node>>]) -> Option<u32> {
+        let spread = arguments
+            .iter()
+            .any(|a| matches!(a.as_deref(), Some(Node::SpreadElement(_))));
+        if !spread {
+            for argument in arguments {
+                self.expression(argument);
+            }
+            return Some(arguments.len() as u32);
+        }
+
+        self.constant(RuntimeValue::Number(0.0));
+        for argument in arguments {
+            match argument.as_deref() {
+                Some(Node::SpreadElement(argument)) => {
+                    self.expression(argument);
+                    self.emit(Op::SpreadArgument);
+                }
+                _ => {
+                    self.expression(argument);
+                    self.emit(Op::PushArgument);
+                }
+            }
+        }
+        None
+    }
+
+    /// `?.`を含む式。左側がnullかundefinedならチェーン全体がundefinedになる
+    fn chain(&mut self, node: &Option<Rc<Node>>) {
+        let short_call = self.label();
+        let short = self.label();
+        let end = self.label();
+        self.state().chains.push((short, short_call));
+        match node.as_deref() {
+            Some(Node::MemberExpression { .. }) => self.member(node),
+            Some(Node::CallExpression { .. }) => self.call(node),
+            _ => self.expression(node),
+        }
+        self.state().chains.pop();
+        self.emit(Op::Jump(end as u32));
+        self.place(short_call);
+        self.emit(Op::Pop);
+        self.place(short);
+        self.emit(Op::Undefined);
+        self.place(end);
+    }
+
+    fn chain_exit(&mut self, call: bool) -> u32 {
+        let (short, short_call) = *self.state().chains.last().expect("not in a chain");
+        if call {
+            short_call as u32
+        } else {
+            short as u32
+        }
+    }
+
+    fn member(&mut self, node: &Option<Rc<Node>>) {
+        if let Some(Node::MemberExpression {
+            object,
+            property,
+            computed,
+            optional,
+        }) = node.as_deref()
+        {
+            if *optional && self.state().chains.is_empty() {
+                return self.chain(node);
+            }
+            self.expression(object);
+            if *optional {
+                let exit = self.chain_exit(false);
+                self.emit(Op::JumpIfNullish(exit));
+            }
+            match property.as_deref() {
+                Some(Node::Identifier(name)) if !computed => {
+                    let key = self.key(name);
+                    self.emit(Op::GetNamed(key));
+                }
+                _ => {
+                    self.property_key(property, *computed);
+                    self.emit(Op::GetElement);
+                }
+            }
+        }
+    }
+
+    fn call(&mut self, node: &Option<Rc<Node>>) {
+        let (callee, arguments, optional) = match node.as_deref() {
+            Some(Node::CallExpression {
+                callee,
+                arguments,
+                optional,
+            }) => (callee, arguments, *optional),
+            _ => return,
+        };
+        if optional && self.state().chains.is_empty() {
+            return self.chain(node);
+        }
+
+        // `a.b()`の形なら、aがthisになる
+        match callee.as_deref() {
+            Some(Node::MemberExpression {
+                object,
+                property,
+                computed,
+                optional: member_optional,
+            }) => {
+                if *member_optional && self.state().chains.is_empty() {
+                    return self.chain(node);
+                }
+                self.expression(object);
+                if *member_optional {
+                    let exit = self.chain_exit(false);
+                    self.emit(Op::JumpIfNullish(exit));
+                }
+                self.emit(Op::Dup);
+                match property.as_deref() {
+                    Some(Node::Identifier(name)) if !computed => {
+                        let key = self.key(name);
+                        self.emit(Op::GetNamed(key));
+                    }
+                    _ => {
+                        self.property_key(property, *computed);
+                        self.emit(Op::GetElement);
+                    }
+                }
+            }
+            _ => {
+                self.emit(Op::Undefined);
+                self.expression(callee);
+            }
+        }
+        if optional {
+            let exit = self.chain_exit(true);
+            self.emit(Op::JumpIfNullish(exit));
+        }
+        match self.arguments(arguments) {
+            Some(count) => self.emit(Op::Call(count)),
+            None => self.emit(Op::CallSpread),
+        }
+    }
+
+    /// 式をコンパイルする。式の値を1つ積む
+    fn expression(&mut self, node: &Option<Rc<Node>>) {
+        let node = match node {
+            Some(node) => node,
+            None => return self.emit(Op::Undefined),
+        };
+
+        match node.as_ref() {
+            Node::NumberLiteral(value) => self.constant(RuntimeValue::Number(*value)),
+            Node::StringLiteral(value) => self.constant(RuntimeValue::String(value.clone())),
+            Node::BooleanLiteral(value) => self.constant(RuntimeValue::Boolean(*value)),
+            Node::NullLiteral => self.constant(RuntimeValue::Null),
+            Node::Identifier(name) => self.get_variable(name),
+            Node::ThisExpression => self.emit(Op::This),
+            Node::TemplateLiteral {
+                quasis,
+                expressions,
+            } => {
+                self.constant(RuntimeValue::String(String::new()));
+                for (i, quasi) in quasis.iter().enumerate() {
+                    if !quasi.is_empty() {
+                        self.constant(RuntimeValue::String(quasi.clone()));
+                        self.emit(Op::Concat);
+                    }
+                    if let Some(expression) = expressions.get(i) {
+                        self.expression(expression);
+                        self.emit(Op::Concat);
+                    }
+                }
+            }
+            Node::ArrayExpression { elements } => {
+                self.emit(Op::NewArray);
+                for element in elements {
+                    match element.as_deref() {
+                        Some(Node::SpreadElement(argument)) => {
+                            self.expression(argument);
+                            self.emit(Op::ArraySpread);
+                        }
+                        Some(_) => {
+                            self.expression(element);
+                            self.emit(Op::ArrayPush);
+                        }
+                        // 省略された要素は穴になり、長さだけが増える
+                        None => self.emit(Op::ArrayHole),
+                    }
+                }
+                self.emit(Op::ArrayEnd);
+            }
+            Node::ObjectExpression { properties } => {
+                self.emit(Op::NewObject);
+                for property in properties {
+                    match property.as_deref() {
+                        Some(Node::SpreadElement(argument)) => {
+                            self.expression(argument);
+                            self.emit(Op::CopyProperties);
+                        }
+                        Some(Node::Property {
+                            key,
+                            value,
+                            kind,
+                            computed,
+                        }) => {
+                            self.property_key(key, *computed);
+                            // 関数の名前はプロパティの名前になる
+                            let name = match key.as_deref() {
+                                Some(Node::Identifier(name)) if !computed => Some(name),
+                                _ => None,
+                            };
+                            match (name, is_anonymous_function(value)) {
+                                (Some(name), _) => {
+                                    let name = match property_function_prefix(*kind) {
+                                        Some(prefix) => prefix.to_string() + " " + name,
+                                        None => name.clone(),
+                                    };
+                                    self.named_expression(value, &name);
+                                }
+                                (None, true) => {
+                                    let function = match value.as_deref() {
+                                        Some(Node::FunctionExpression { params, body, .. }) => {
+                                            self.function("", false, params, body, false)
+                                        }
+                                        Some(Node::ArrowFunctionExpression { params, body }) => {
+                                            self.function("", false, params, body, true)
+                                        }
+                                        _ => unreachable!(),
+                                    };
+                                    self.emit(Op::KeyedClosure {
+                                        function,
+                                        kind: *kind,
+                                    });
+                                }
+                                (None, false) => self.expression(value),
+                            }
+                            // 同じ名前のゲッターとセッターは1つのプロパティになる
+                            match kind {
+                                PropertyKind::Init => self.emit(Op::DefineField),
+                                PropertyKind::Get => self.emit(Op::DefineGetter),
+                                PropertyKind::Set => self.emit(Op::DefineSetter),
+                            }
+                        }
+                        _ => {}
+                    }
+                }
+            }
+            Node::FunctionExpression { .. } | Node::ArrowFunctionExpression { .. } => {
+                self.named_expression(&Some(node.clone()), "")
+            }
+            Node::MemberExpression { .. } => self.member(&Some(node.clone())),
+            Node::CallExpression { .. } => self.call(&Some(node.clone())),
+            Node::ChainExpression(expression) => self.chain(expression),
+            Node::NewExpression { callee, arguments } => {
+                self.expression(callee);
+                match self.arguments(arguments) {
+                    Some(count) => self.emit(Op::Construct(count)),
+                    None => self.emit(Op::ConstructSpread),
+                }
+            }
+            Node::BinaryExpression {
+                operator,
+                left,
+                right,
+            } => {
+                self.expression(left);
+                self.expression(right);
+                self.emit(Op::Binary(BinaryOperator::new(operator)));
+            }
+            Node::LogicalExpression {
+                operator,
+                left,
+                right,
+            } => {
+                // 左辺だけで結果が決まる場合は右辺を評価しない
+                let end = self.label() as u32;
+                self.expression(left);
+                match *operator {
+                    "&&" => self.emit(Op::JumpIfFalseOrPop(end)),
+                    "||" => self.emit(Op::JumpIfTrueOrPop(end)),
+                    _ => self.emit(Op::JumpIfNotNullishOrPop(end)),
+                }
+                self.expression(right);
+                self.place(end as usize);
+            }
+            Node::ConditionalExpression {
+                test,
+                consequent,
+                alternate,
+            } => {
+                let else_label = self.label();
+                let end = self.label();
+                self.expression(test);
+                self.emit(Op::JumpIfFalse(else_label as u32));
+                self.expression(consequent);
+                self.emit(Op::Jump(end as u32));
+                self.place(else_label);
+                self.expression(alternate);
+                self.place(end);
+            }
+            Node::SequenceExpression { expressions } => {
+                if expressions.is_empty() {
+                    self.emit(Op::Undefined);
+                }
+                for (i, expression) in expressions.iter().enumerate() {
+                    self.expression(expression);
+                    if i + 1 < expressions.len() {
+                        self.emit(Op::Pop);
+                    }
+                }
+            }
+            Node::UnaryExpression { operator, argument } => match *operator {
+                "typeof" => match argument.as_deref() {
+                    // 宣言されていない変数もReferenceErrorにしない
+                    Some(Node::Identifier(name)) if self.resolve(name).is_none() => {
+                        let name = self.name(name);
+                        self.emit(Op::TypeofName(name));
+                    }
+                    _ => {
+                        self.expression(argument);
+                        self.emit(Op::Typeof);
+                    }
+                },
+                "delete" => match argument.as_deref() {
+                    Some(Node::MemberExpression {
+                        object,
+                        property,
+                        computed,
+                        ..
+                    }) => {
+                        self.expression(object);
+                        self.property_key(property, *computed);
+                        self.emit(Op::Delete);
+                    }
+                    // 宣言された変数は削除できない
+                    Some(Node::Identifier(_)) => self.constant(RuntimeValue::Boolean(false)),
+                    _ => {
+                        self.expression(argument);
+                        self.emit(Op::Pop);
+                        self.constant(RuntimeValue::Boolean(true));
+                    }
+                },
+                "void" => {
+                    self.expression(argument);
+                    self.emit(Op::Pop);
+                    self.emit(Op::Undefined);
+                }
+                "!" => {
+                    self.expression(argument);
+                    self.emit(Op::Not);
+                }
+                "-" => {
+                    self.expression(argument);
+                    self.emit(Op::Negate);
+                }
+                "~" => {
+                    self.expression(argument);
+                    self.emit(Op::BitwiseNot);
+                }
+                _ => {
+                    self.expression(argument);
+                    self.emit(Op::ToNumber);
+                }
+            },
+            Node::UpdateExpression {
+                operator,
+                prefix,
+                argument,
+            } => {
+                let update = if *operator == "++" {
+                    Op::Increment
+                } else {
+                    Op::Decrement
+                };
+                match argument.as_deref() {
+                    Some(Node::Identifier(name)) => {
+                        self.get_variable(name);
+                        self.emit(Op::ToNumber);
+                        if !prefix {
+                            self.emit(Op::Dup);
+                        }
+                        self.emit(update);
+                        self.set_variable(name);
+                        if !prefix {
+                            self.emit(Op::Pop);
+                        }
+                    }
+                    Some(Node::MemberExpression {
+                        object,
+                        property,
+                        computed,
+                        ..
+                    }) => {
+                        let key = self.member_reference(object, property, *computed);
+                        match key {
+                            Some(key) => {
+                                self.emit(Op::Dup);
+                                self.emit(Op::GetNamed(key));
+                            }
+                            None => {
+                                self.emit(Op::Dup2);
+                                self.emit(Op::GetElement);
+                            }
+                        }
+                        self.emit(Op::ToNumber);
+                        // 後置の場合は、更新する前の値を結果にする
+                        let old_value = (!prefix).then(|| self.register());
+                        if let Some(old_value) = old_value {
+                            self.emit(Op::Store(old_value));
+                            self.emit(Op::Load(old_value));
+                        }
+                        self.emit(update);
+                        match key {
+                            Some(key) => self.emit(Op::SetNamed(key)),
+                            None => self.emit(Op::SetElement),
+                        }
+                        if let Some(old_value) = old_value {
+                            self.emit(Op::Pop);
+                            self.emit(Op::Load(old_value));
+                        }
+                    }
+                    _ => self.invalid_assignment_target(),
+                }
+            }
+            Node::AssignmentExpression {
+                operator,
+                left,
+                right,
+            } => match left.as_deref() {
+                Some(Node::Identifier(name)) => {
+                    if *operator == "=" {
+                        // `f = function () {}`の関数の名前はf
+                        self.named_expression(right, name);
+                    } else {
+                        // `a += b`は`a = a + b`として計算する
+                        self.get_variable(name);
+                        self.expression(right);
+                        let operator = BinaryOperator::new(operator.trim_end_matches('='));
+                        self.emit(Op::Binary(operator));
+                    }
+                    self.set_variable(name);
+                }
+                Some(Node::MemberExpression {
+                    object,
+                    property,
+                    computed,
+                    ..
+                }) => {
+                    let key = self.member_reference(object, property, *computed);
+                    if *operator != "=" {
+                        match key {
+                            Some(key) => {
+                                self.emit(Op::Dup);
+                                self.emit(Op::GetNamed(key));
+                            }
+                            None => {
+                                self.emit(Op::Dup2);
+                                self.emit(Op::GetElement);
+                            }
+                        }
+                    }
+                    self.expression(right);
+                    if *operator != "=" {
+                        let operator = BinaryOperator::new(operator.trim_end_matches('='));
+                        self.emit(Op::Binary(operator));
+                    }
+                    match key {
+                        Some(key) => self.emit(Op::SetNamed(key)),
+                        None => self.emit(Op::SetElement),
+                    }
+                }
+                _ => self.invalid_assignment_target(),
+            },
+            Node::RegExpLiteral { .. } => {
+                let message = self.name(REGEXP_NOT_SUPPORTED);
+                self.emit(Op::ThrowError {
+                    kind: ErrorKind::SyntaxError,
+                    message,
+                });
+                self.emit(Op::Undefined);
+            }
+            // 文や、他のノードの一部としてだけ現れるノードは、式の位置に来ない
+            Node::ExpressionStatement(_)
+            | Node::VariableDecration { .. }
+            | Node::VariableDeclarator { .. }
+            | Node::Property { .. }
+            | Node::SpreadElement(_)
+            | Node::AssignmentPattern { .. }
+            | Node::RestElement(_)
+            | Node::BlockStatement { .. }
+            | Node::EmptyStatement
+            | Node::ReturnStatement { .. }
+            | Node::IfStatement { .. }
+            | Node::ForStatement { .. }
+            | Node::ForInStatement { .. }
+            | Node::ForOfStatement { .. }
+            | Node::WhileStatement { .. }
+            | Node::DoWhileStatement { .. }
+            | Node::SwitchStatement { .. }
+            | Node::SwitchCase { .. }
+            | Node::LabeledStatement { .. }
+            | Node::BreakStatement { .. }
+            | Node::ContinueStatement { .. }
+            | Node::ThrowStatement { .. }
+            | Node::TryStatement { .. }
+            | Node::CatchClause { .. }
+            | Node::FunctionDeclaration { .. } => self.emit(Op::Undefined),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::renderer::js::ast::JsParser;
+    use crate::renderer::js::token::JsLexer;
+
+    fn compile(input: &str) -> Code {
+        let program = JsParser::new(JsLexer::new(input.to_string()))
+            .parse_ast()
+            .expect("failed to parse");
+        compile_program(&program)
+    }
+
+    #[test]
+    fn test_resolve_locals() {
+        let code = compile("function f(a) { var b = a; { let c = b; return () => c + a; } }");
+        let f = &code.functions[0].code;
+        // 関数とブロックの変数は位置で読み書きし、グローバルの変数だけを名前で探す
+        assert!(f.ops.contains(&Op::GetLocal {
+            depth: 0,
+            index: 0,
+            name: f.names.iter().position(|n| n == "a").unwrap() as u32,
+        }));
+        assert!(!f.ops.iter().any(|op| matches!(op, Op::GetName(_))));
+        let arrow = &f.functions[0].code;
+        assert!(arrow.ops.iter().any(|op| matches!(
+            op,
+            Op::GetLocal {
+                depth: 1,
+                index: 0,
+                ..
+            }
+        )));
+        assert!(arrow.ops.iter().any(|op| matches!(
+            op,
+            Op::GetLocal {
+                depth: 2,
+                index: 0,
+                ..
+            }
+        )));
+
+        // スクリプトの変数はグローバルのスコープに名前で作る
+        let code = compile("var x = 1; x");
+        assert!(code.ops.iter().any(|op| matches!(op, Op::GetName(_))));
+    }
+
+    #[test]
+    fn test_finally_paths() {
+        let code = compile(
+            "for (let i = 0; i < 3; i++) { if (i) continue; try { break; } finally { f(); } }",
+        );
+        for op in &code.ops {
+            let mut op = *op;
+            if let Some(target) = op.target_mut() {
+                assert!((*target as usize) < code.ops.len());
+            }
+        }
+        // finallyの本体は、breakで抜ける経路と通常の経路と例外の経路に置く
+        let f = code.names.iter().position(|n| n == "f").unwrap() as u32;
+        let count = code.ops.iter().filter(|op| **op == Op::GetName(f)).count();
+        assert_eq!(3, count);
+        let count = code
+            .ops
+            .iter()
+            .filter(|op| matches!(op, Op::PushHandler { .. }))
+            .count();
+        assert_eq!(1, count);
+    }
+}
@@ -0,0 +1,229 @@
+//! 変数のスコープ (Environment Record)
+//! https://262.ecma-international.org/11.0/#sec-environment-records
+
+use crate::renderer::js::heap::Trace;
+use crate::renderer::js::heap::Tracer;
+use crate::renderer::js::value::RuntimeValue;
+use alloc::rc::Rc;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::cell::RefCell;
+
+/// 変数を読み書きできなかった理由
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BindingError {
+    /// どのスコープにも宣言されていない
+    NotDefined,
+    /// `let`と`const`の宣言より前 (TDZ)
+    Uninitialized,
+    /// `const`への代入
+    Immutable,
+}
+
+#[derive(Debug, Clone)]
+struct Binding {
+    name: String,
+    /// 初期化される前はNone
+    value: Option<RuntimeValue>,
+    mutable: bool,
+}
+
+#[derive(Debug, Clone)]
+pub struct Environment {
+    bindings: Vec<Binding>,
+    outer: Option<Rc<RefCell<Environment>>>,
+    /// 関数とグローバルのスコープのthis。ブロックとアロー関数のスコープはNone
+    this: Option<RuntimeValue>,
+}
+
+impl Environment {
+    pub fn new(outer: Option<Rc<RefCell<Environment>>>) -> Self {
+        Self {
+            bindings: Vec::new(),
+            outer,
+            this: None,
+        }
+    }
+
+    /// `var`の宣言先になる、関数やグローバルのスコープ
+    pub fn new_function(outer: Option<Rc<RefCell<Environment>>>, this: RuntimeValue) -> Self {
+        Self {
+            bindings: Vec::new(),
+            outer,
+            this: Some(this),
+        }
+    }
+
+    pub fn this_value(&self) -> RuntimeValue {
+        match (&self.this, &self.outer) {
+            (Some(this), _) => this.clone(),
+            (None, Some(outer)) => outer.borrow().this_value(),
+            (None, None) => RuntimeValue::Undefined,
+        }
+    }
+
+    pub fn get_binding(&self, name: &str) -> Result<RuntimeValue, BindingError> {
+        match self.bindings.iter().find(|b| b.name == name) {
+            Some(binding) => binding.value.clone().ok_or(BindingError::Uninitialized),
+            None => match &self.outer {
+                Some(outer) => outer.borrow().get_binding(name),
+                None => Err(BindingError::NotDefined),
+            },
+        }
+    }
+
+    /// 宣言されていないか、初期化前の場合はNone
+    pub fn get_variable(&self, name: String) -> Option<RuntimeValue> {
+        self.get_binding(&name).ok()
+    }
+
+    pub fn has_own_binding(&self, name: &str) -> bool {
+        self.bindings.iter().any(|b| b.name == name)
+    }
+
+    /// `var`や関数の宣言。同じスコープでの再宣言では値を変えない
+    pub fn declare_var(&mut self, name: String) {
+        if !self.has_own_binding(&name) {
+            self.bindings.push(Binding {
+                name,
+                value: Some(RuntimeValue::Undefined),
+                mutable: true,
+            });
+        }
+    }
+
+    /// `let`と`const`の宣言。初期化されるまでは読み書きできない
+    pub fn declare_lexical(&mut self, name: String, mutable: bool) {
+        self.bindings.retain(|b| b.name != name);
+        self.bindings.push(Binding {
+            name,
+            value: None,
+            mutable,
+        });
+    }
+
+    /// このスコープの変数を初期化する。宣言されていなければ宣言する
+    pub fn initialize(&mut self, name: String, value: RuntimeValue) {
+        match self.bindings.iter_mut().find(|b| b.name == name) {
+            Some(binding) => binding.value = Some(value),
+            None => self.bindings.push(Binding {
+                name,
+                value: Some(value),
+                mutable: true,
+            }),
+        }
+    }
+
+    pub fn set_binding(&mut self, name: &str, value: RuntimeValue) -> Result<(), BindingError> {
+        match self.bindings.iter_mut().find(|b| b.name == name) {
+            Some(Binding { value: None, .. }) => Err(BindingError::Uninitialized),
+            Some(Binding { mutable: false, .. }) => Err(BindingError::Immutable),
+            Some(binding) => {
+                binding.value = Some(value);
+                Ok(())
+            }
+            // 外側のスコープの変数を更新する
+            None => match &self.outer {
+                Some(outer) => outer.borrow_mut().set_binding(name, value),
+                None => Err(BindingError::NotDefined),
+            },
+        }
+    }
+    pub fn outer(&self) -> Option<Rc<RefCell<Environment>>> {
+        self.outer.clone()
+    }
+
+    /// `depth`個外側のスコープの`index`番目の変数を読む
+    /// バイトコードのVMが、コンパイル時に決めた位置で変数を読み書きする
+    pub fn get_slot(&self, depth: usize, index: usize) -> Result<RuntimeValue, BindingError> {
+        if depth > 0 {
+            return match &self.outer {
+                Some(outer) => outer.borrow().get_slot(depth - 1, index),
+                None => Err(BindingError::NotDefined),
+            };
+        }
+        match self.bindings.get(index) {
+            Some(binding) => binding.value.clone().ok_or(BindingError::Uninitialized),
+            None => Err(BindingError::NotDefined),
+        }
+    }
+
+    pub fn set_slot(
+        &mut self,
+        depth: usize,
+        index: usize,
+        value: RuntimeValue,
+    ) -> Result<(), BindingError> {
+        if depth > 0 {
+            return match &self.outer {
+                Some(outer) => outer.borrow_mut().set_slot(depth - 1, index, value),
+                None => Err(BindingError::NotDefined),
+            };
+        }
+        match self.bindings.get_mut(index) {
+            Some(Binding { value: None, .. }) => Err(BindingError::Uninitialized),
+            Some(Binding { mutable: false, .. }) => Err(BindingError::Immutable),
+            Some(binding) => {
+                binding.value = Some(value);
+                Ok(())
+            }
+            None => Err(BindingError::NotDefined),
+        }
+    }
+
+    pub fn initialize_slot(&mut self, index: usize, value: RuntimeValue) {
+        if let Some(binding) = self.bindings.get_mut(index) {
+            binding.value = Some(value);
+        }
+    }
+}
+
+impl Trace for Environment {
+    fn trace(&self, tracer: &mut Tracer) {
+        // クロージャなどで共有される環境は一度だけ辿る
+        if !tracer.visit(self as *const Self) {
+            return;
+        }
+        for binding in &self.bindings {
+            if let Some(value) = &binding.value {
+                tracer.mark_value(value);
+            }
+        }
+        if let Some(this) = &self.this {
+            tracer.mark_value(this);
+        }
+        if let Some(outer) = &self.outer {
+            outer.borrow().trace(tracer);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::string::ToString;
+
+    #[test]
+    fn test_bindings() {
+        let global = Rc::new(RefCell::new(Environment::new_function(
+            None,
+            RuntimeValue::Undefined,
+        )));
+        global.borrow_mut().declare_var("a".to_string());
+        let mut block = Environment::new(Some(global.clone()));
+        block.declare_lexical("b".to_string(), false);
+
+        assert_eq!(Err(BindingError::Uninitialized), block.get_binding("b"));
+        block.initialize("b".to_string(), RuntimeValue::Number(1.0));
+        assert_eq!(Ok(RuntimeValue::Number(1.0)), block.get_binding("b"));
+        assert_eq!(
+            Err(BindingError::Immutable),
+            block.set_binding("b", RuntimeValue::Null)
+        );
+
+        // 外側のスコープの変数を更新する
+        assert_eq!(Ok(()), block.set_binding("a", RuntimeValue::Null));
+        assert_eq!(Ok(RuntimeValue::Null), global.borrow().get_binding("a"));
+        assert_eq!(Err(BindingError::NotDefined), block.get_binding("c"));
+    }
+}
@@ -0,0 +1,233 @@
+//! JSのオブジェクトを置くヒープと、マーク&スイープのガベージコレクタ
+//! オブジェクト同士は`Rc`ではなく`ObjectId`で参照するため、循環していても回収できる
+
+use crate::renderer::js::object::JsObject;
+use crate::renderer::js::value::RuntimeValue;
+use alloc::collections::BTreeSet;
+use alloc::vec;
+use alloc::vec::Vec;
+
+/// 最初のガベージコレクションまでに確保できるオブジェクトの数
+const INITIAL_THRESHOLD: usize = 1024;
+
+/// ヒープ上のオブジェクトを指す
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct ObjectId(usize);
+
+/// 他のオブジェクトへの参照を持ち、GCがそれを辿れるもの
+pub trait Trace {
+    fn trace(&self, tracer: &mut Tracer);
+}
+
+/// マークの途中で、まだ中身を辿っていないオブジェクトを覚えておく
+#[derive(Debug, Default)]
+pub struct Tracer {
+    pending: Vec<ObjectId>,
+    /// 一度辿った環境などの、ヒープの外にある値のアドレス
+    visited: BTreeSet<usize>,
+}
+
+impl Tracer {
+    pub fn mark_object(&mut self, id: ObjectId) {
+        self.pending.push(id);
+    }
+
+    pub fn mark_value(&mut self, value: &RuntimeValue) {
+        if let RuntimeValue::Object(id) = value {
+            self.mark_object(*id);
+        }
+    }
+
+    /// ヒープの外にあるものを初めて辿る場合はtrue
+    /// 複数のオブジェクトから共有される環境を何度も辿らないようにする
+    pub fn visit<T>(&mut self, item: *const T) -> bool {
+        self.visited.insert(item as usize)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Heap {
+    objects: Vec<Option<JsObject>>,
+    /// 解放されて再利用できる場所
+    free: Vec<usize>,
+    /// 前回のガベージコレクションから確保した数
+    allocated: usize,
+    threshold: usize,
+    /// Rustの変数が持っている、評価の途中のオブジェクト。GCのルートになる
+    /// 確保したオブジェクトは自動的に加わり、`unpin`で使い終わった分を取り除く
+    pinned: Vec<ObjectId>,
+}
+
+impl Default for Heap {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Heap {
+    pub fn new() -> Self {
+        Self {
+            objects: Vec::new(),
+            free: Vec::new(),
+            allocated: 0,
+            threshold: INITIAL_THRESHOLD,
+            pinned: Vec::new(),
+        }
+    }
+
+    pub fn allocate(&mut self, object: JsObject) -> ObjectId {
+        self.allocated += 1;
+        let id = match self.free.pop() {
+            Some(i) => {
+                self.objects[i] = Some(object);
+                ObjectId(i)
+            }
+            None => {
+                self.objects.push(Some(object));
+                ObjectId(self.objects.len() - 1)
+            }
+        };
+        self.pinned.push(id);
+        id
+    }
+
+    /// オブジェクトの値を、`unpin`されるまで回収しないようにする
+    pub fn pin(&mut self, value: &RuntimeValue) {
+        if let RuntimeValue::Object(id) = value {
+            self.pinned.push(*id);
+        }
+    }
+
+    /// 回収しないようにしているオブジェクトの数。`unpin`に渡して元に戻す
+    pub fn pinned(&self) -> usize {
+        self.pinned.len()
+    }
+
+    /// `pinned`が`len`を返したときより後に加えたものを取り除く
+    pub fn unpin(&mut self, len: usize) {
+        self.pinned.truncate(len);
+    }
+
+    /// 解放されたオブジェクトを指すIDを使うのは、ルートの登録漏れによるバグ
+    pub fn get(&self, id: ObjectId) -> &JsObject {
+        match &self.objects[id.0] {
+            Some(object) => object,
+            None => panic!("object {:?} has been collected", id),
+        }
+    }
+
+    pub fn get_mut(&mut self, id: ObjectId) -> &mut JsObject {
+        match &mut self.objects[id.0] {
+            Some(object) => object,
+            None => panic!("object {:?} has been collected", id),
+        }
+    }
+
+    pub fn is_live(&self, id: ObjectId) -> bool {
+        self.objects.get(id.0).is_some_and(|o| o.is_some())
+    }
+
+    /// 生きているオブジェクトの数
+    pub fn len(&self) -> usize {
+        self.objects.len() - self.free.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// これまでに同時に生きていたオブジェクトの最大の数
+    /// 解放された場所を再利用してから新しい場所を作るため、確保した場所の数と同じになる
+    pub fn peak_len(&self) -> usize {
+        self.objects.len()
+    }
+
+    /// 前回から十分な数のオブジェクトを確保した場合はtrue
+    pub fn should_collect(&self) -> bool {
+        self.allocated >= self.threshold
+    }
+
+    /// `mark_roots`で印をつけたものから辿れないオブジェクトを解放し、解放した数を返す
+    pub fn collect<F: FnOnce(&mut Tracer)>(&mut self, mark_roots: F) -> usize {
+        let mut tracer = Tracer::default();
+        mark_roots(&mut tracer);
+        for id in &self.pinned {
+            tracer.mark_object(*id);
+        }
+
+        let mut marked = vec![false; self.objects.len()];
+        while let Some(id) = tracer.pending.pop() {
+            if marked[id.0] {
+                continue;
+            }
+            marked[id.0] = true;
+            if let Some(object) = &self.objects[id.0] {
+                object.trace(&mut tracer);
+            }
+        }
+
+        let mut freed = 0;
+        for (i, object) in self.objects.iter_mut().enumerate() {
+            if !marked[i] && object.is_some() {
+                *object = None;
+                self.free.push(i);
+                freed += 1;
+            }
+        }
+
+        self.allocated = 0;
+        self.threshold = core::cmp::max(INITIAL_THRESHOLD, self.len() * 2);
+        freed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::renderer::js::object::ObjectKind;
+    use crate::renderer::js::object::PropertyDescriptor;
+    use crate::renderer::js::object::PropertyKey;
+
+    fn link(heap: &mut Heap, from: ObjectId, to: ObjectId) {
+        heap.get_mut(from).define_own_property(
+            PropertyKey::from("next"),
+            PropertyDescriptor::data(RuntimeValue::Object(to), true, true, true),
+        );
+    }
+
+    #[test]
+    fn test_collect_cycles() {
+        let mut heap = Heap::new();
+        let root = heap.allocate(JsObject::new(ObjectKind::Ordinary, None));
+        let a = heap.allocate(JsObject::new(ObjectKind::Ordinary, Some(root)));
+        let b = heap.allocate(JsObject::new(ObjectKind::Ordinary, None));
+        let c = heap.allocate(JsObject::new(ObjectKind::Ordinary, None));
+        // aはプロトタイプ経由でrootを参照し、bとcは互いに参照し合う
+        link(&mut heap, a, b);
+        link(&mut heap, b, c);
+        link(&mut heap, c, b);
+        // 確保したオブジェクトはルートになっているので、外してから回収する
+        heap.unpin(0);
+
+        assert_eq!(0, heap.collect(|tracer| tracer.mark_object(a)));
+        assert_eq!(4, heap.len());
+
+        // aから辿れなくなった循環は回収される
+        link(&mut heap, a, a);
+        assert_eq!(2, heap.collect(|tracer| tracer.mark_object(a)));
+        assert!(heap.is_live(root));
+        assert!(!heap.is_live(b) && !heap.is_live(c));
+
+        // 空いた場所は再利用される
+        let d = heap.allocate(JsObject::new(ObjectKind::Ordinary, None));
+        assert!(d == b || d == c);
+        assert_eq!(3, heap.len());
+        assert_eq!(4, heap.peak_len());
+
+        // 新しく確保したものは、unpinするまでどこからも参照されていなくても回収しない
+        assert_eq!(2, heap.collect(|_| {}));
+        assert!(heap.is_live(d));
+        heap.unpin(0);
+        assert_eq!(1, heap.collect(|_| {}));
+    }
+}
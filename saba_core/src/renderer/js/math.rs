@@ -0,0 +1,499 @@
+//! no_std環境で使う浮動小数点数の計算
+//! 精度は最後の数ビットまでは保証しない
+
+use core::f64::consts::LN_2;
+
+/// 2^52以上の数はすでに整数
+const INTEGER_THRESHOLD: f64 = 4503599627370496.0;
+
+pub fn abs(x: f64) -> f64 {
+    if x.is_sign_negative() {
+        -x
+    } else {
+        x
+    }
+}
+
+/// 小数点以下を切り捨てる。符号は保つ
+pub fn trunc(x: f64) -> f64 {
+    if x.is_nan() || x.is_infinite() || abs(x) >= INTEGER_THRESHOLD {
+        return x;
+    }
+    let t = x as i64 as f64;
+    if t == 0.0 && x.is_sign_negative() {
+        -0.0
+    } else {
+        t
+    }
+}
+
+pub fn floor(x: f64) -> f64 {
+    let t = trunc(x);
+    if t > x {
+        t - 1.0
+    } else {
+        t
+    }
+}
+
+pub fn ceil(x: f64) -> f64 {
+    -floor(-x)
+}
+
+pub fn is_integer(x: f64) -> bool {
+    x.is_finite() && trunc(x) == x
+}
+
+/// 2^kを掛ける
+fn scale(x: f64, k: i64) -> f64 {
+    let mut x = x;
+    let mut k = k;
+    // 指数部の範囲に収まるように少しずつ掛ける
+    while k > 1000 {
+        x *= f64::from_bits(((1000 + 1023) as u64) << 52);
+        k -= 1000;
+    }
+    while k < -1000 {
+        x *= f64::from_bits(((-1000 + 1023) as u64) << 52);
+        k += 1000;
+    }
+    x * f64::from_bits(((k + 1023) as u64) << 52)
+}
+
+/// x = k * ln2 + r と分けて、rをテイラー展開する
+pub fn exp(x: f64) -> f64 {
+    if x.is_nan() {
+        return x;
+    }
+    if x > 709.8 {
+        return f64::INFINITY;
+    }
+    if x < -745.2 {
+        return 0.0;
+    }
+
+    let k = floor(x / LN_2 + 0.5);
+    let r = x - k * LN_2;
+    let mut term = 1.0;
+    let mut sum = 1.0;
+    for i in 1..30 {
+        term *= r / i as f64;
+        sum += term;
+        if abs(term) < 1e-17 * sum {
+            break;
+        }
+    }
+    scale(sum, k as i64)
+}
+
+/// x = m * 2^e と分けて、ln(m)をatanhの級数で求める
+pub fn ln(x: f64) -> f64 {
+    if x.is_nan() || x < 0.0 {
+        return f64::NAN;
+    }
+    if x == 0.0 {
+        return f64::NEG_INFINITY;
+    }
+    if x.is_infinite() {
+        return x;
+    }
+
+    let mut x = x;
+    let mut e: i64 = 0;
+    // 非正規化数は先に大きくしておく
+    if x < f64::MIN_POSITIVE {
+        x *= 18014398509481984.0;
+        e -= 54;
+    }
+    let bits = x.to_bits();
+    e += ((bits >> 52) & 0x7ff) as i64 - 1023;
+    let mut m = f64::from_bits((bits & 0x000f_ffff_ffff_ffff) | (1023 << 52));
+    // mを[√½, √2)に収める
+    if m > core::f64::consts::SQRT_2 {
+        m /= 2.0;
+        e += 1;
+    }
+
+    let s = (m - 1.0) / (m + 1.0);
+    let s2 = s * s;
+    let mut term = s;
+    let mut sum = 0.0;
+    let mut i = 1.0;
+    while i < 80.0 {
+        sum += term / i;
+        term *= s2;
+        i += 2.0;
+    }
+    2.0 * sum + e as f64 * LN_2
+}
+
+/// 奇数の整数かどうか
+fn is_odd_integer(x: f64) -> bool {
+    is_integer(x) && trunc(x / 2.0) * 2.0 != x
+}
+
+/// Number::exponentiate
+/// https://262.ecma-international.org/11.0/#sec-numeric-types-number-exponentiate
+pub fn pow(base: f64, exponent: f64) -> f64 {
+    if exponent.is_nan() {
+        return f64::NAN;
+    }
+    if exponent == 0.0 {
+        return 1.0;
+    }
+    if base.is_nan() {
+        return f64::NAN;
+    }
+    if exponent.is_infinite() {
+        let magnitude = abs(base);
+        return if magnitude == 1.0 {
+            f64::NAN
+        } else if (magnitude > 1.0) == (exponent > 0.0) {
+            f64::INFINITY
+        } else {
+            0.0
+        };
+    }
+    if base.is_infinite() || base == 0.0 {
+        // 無限大と0は、符号と指数の符号だけで決まる
+        let large = (base.is_infinite()) == (exponent > 0.0);
+        let negative = base.is_sign_negative() && is_odd_integer(exponent);
+        return match (large, negative) {
+            (true, false) => f64::INFINITY,
+            (true, true) => f64::NEG_INFINITY,
+            (false, false) => 0.0,
+            (false, true) => -0.0,
+        };
+    }
+    if base < 0.0 && !is_integer(exponent) {
+        return f64::NAN;
+    }
+
+    let negative = base < 0.0 && is_odd_integer(exponent);
+    let magnitude = abs(base);
+    let result = if is_integer(exponent) && abs(exponent) <= 1024.0 {
+        // 整数乗は二乗を繰り返して求める
+        let mut n = abs(exponent) as u64;
+        let mut b = magnitude;
+        let mut r = 1.0;
+        while n > 0 {
+            if n & 1 == 1 {
+                r *= b;
+            }
+            b *= b;
+            n >>= 1;
+        }
+        if exponent < 0.0 {
+            1.0 / r
+        } else {
+            r
+        }
+    } else {
+        exp(exponent * ln(magnitude))
+    };
+
+    if negative {
+        -result
+    } else {
+        result
+    }
+}
+
+/// 平方根。仮数を整数として平方根を求め、余りを使って正しく丸める
+pub fn sqrt(x: f64) -> f64 {
+    if x.is_nan() || x < 0.0 {
+        return f64::NAN;
+    }
+    if x == 0.0 || x.is_infinite() {
+        return x;
+    }
+    // x = mantissa * 2^exponentとし、非正規化数は仮数の最上位ビットが52ビット目になるようにずらす
+    let bits = x.to_bits();
+    let (mut mantissa, mut exponent) = match (bits >> 52) as i32 {
+        0 => (bits, -1074),
+        e => ((bits & ((1 << 52) - 1)) | (1 << 52), e - 1075),
+    };
+    while mantissa < 1 << 52 {
+        mantissa <<= 1;
+        exponent -= 1;
+    }
+    if exponent % 2 != 0 {
+        mantissa <<= 1;
+        exponent -= 1;
+    }
+
+    // 62ビット以上の整数の平方根を求める
+    let mut n = (mantissa as u128) << 72;
+    let mut root: u128 = 0;
+    let mut bit: u128 = 1 << 126;
+    while bit > n {
+        bit >>= 2;
+    }
+    while bit != 0 {
+        if n >= root + bit {
+            n -= root + bit;
+            root = (root >> 1) + bit;
+        } else {
+            root >>= 1;
+        }
+        bit >>= 2;
+    }
+    // 余りがある場合は最下位ビットを立て、ちょうど中間として偶数に丸められないようにする
+    if n != 0 {
+        root |= 1;
+    }
+    let scale = f64::from_bits(((exponent / 2 - 36 + 1023) as u64) << 52);
+    root as f64 * scale
+}
+
+/// 立方根
+pub fn cbrt(x: f64) -> f64 {
+    if x.is_nan() || x == 0.0 || x.is_infinite() {
+        return x;
+    }
+    let a = abs(x);
+    let mut y = pow(a, 1.0 / 3.0);
+    y -= (y * y * y - a) / (3.0 * y * y);
+    if x < 0.0 {
+        -y
+    } else {
+        y
+    }
+}
+
+/// 2を底とする対数。2の累乗は正確に求める
+pub fn log2(x: f64) -> f64 {
+    let bits = x.to_bits();
+    let exponent = ((bits >> 52) & 0x7ff) as i64;
+    if x > 0.0 && exponent != 0 && exponent != 0x7ff && bits & 0x000f_ffff_ffff_ffff == 0 {
+        return (exponent - 1023) as f64;
+    }
+    ln(x) / LN_2
+}
+
+/// 10を底とする対数。10の累乗は正確に求める
+pub fn log10(x: f64) -> f64 {
+    let y = ln(x) / core::f64::consts::LN_10;
+    let rounded = floor(y + 0.5);
+    if y.is_finite() && pow(10.0, rounded) == x {
+        rounded
+    } else {
+        y
+    }
+}
+
+/// π/2を2つに分けた値。sinとcosの引数を小さくするときの誤差を減らす
+const PIO2_HI: f64 = 1.570_796_326_734_125_6;
+const PIO2_LO: f64 = 6.077_100_506_506_192e-11;
+
+/// x = k * π/2 + r と分けて、rと、kを4で割った余りを返す
+fn reduce_quarter_turns(x: f64) -> (f64, u8) {
+    let k = floor(x / core::f64::consts::FRAC_PI_2 + 0.5);
+    let r = (x - k * PIO2_HI) - k * PIO2_LO;
+    (r, (k % 4.0 + 4.0) as u8 % 4)
+}
+
+/// |r| <= π/4 のsinとcosをテイラー展開で求める
+fn sin_cos_kernel(r: f64) -> (f64, f64) {
+    let r2 = r * r;
+    let mut sin = r;
+    let mut cos = 1.0;
+    let mut sin_term = r;
+    let mut cos_term = 1.0;
+    let mut i = 1.0;
+    while i < 30.0 {
+        cos_term *= -r2 / (i * (i + 1.0));
+        sin_term *= -r2 / ((i + 1.0) * (i + 2.0));
+        cos += cos_term;
+        sin += sin_term;
+        i += 2.0;
+    }
+    (sin, cos)
+}
+
+pub fn sin(x: f64) -> f64 {
+    if x.is_nan() || x.is_infinite() {
+        return f64::NAN;
+    }
+    if x == 0.0 {
+        return x;
+    }
+    let (r, quadrant) = reduce_quarter_turns(x);
+    let (s, c) = sin_cos_kernel(r);
+    match quadrant {
+        0 => s,
+        1 => c,
+        2 => -s,
+        _ => -c,
+    }
+}
+
+pub fn cos(x: f64) -> f64 {
+    if x.is_nan() || x.is_infinite() {
+        return f64::NAN;
+    }
+    let (r, quadrant) = reduce_quarter_turns(x);
+    let (s, c) = sin_cos_kernel(r);
+    match quadrant {
+        0 => c,
+        1 => -s,
+        2 => -c,
+        _ => s,
+    }
+}
+
+pub fn tan(x: f64) -> f64 {
+    if x == 0.0 {
+        return x;
+    }
+    sin(x) / cos(x)
+}
+
+/// 逆正接。atan(x) = 2 * atan(x / (1 + √(1 + x²))) で引数を小さくしてから級数で求める
+pub fn atan(x: f64) -> f64 {
+    if x.is_nan() || x == 0.0 {
+        return x;
+    }
+    if x < 0.0 {
+        return -atan(-x);
+    }
+    if x > 1.0 {
+        return core::f64::consts::FRAC_PI_2 - atan(1.0 / x);
+    }
+
+    let mut x = x;
+    let mut scale = 1.0;
+    for _ in 0..2 {
+        x /= 1.0 + sqrt(1.0 + x * x);
+        scale *= 2.0;
+    }
+    let x2 = x * x;
+    let mut term = x;
+    let mut sum = 0.0;
+    let mut i = 1.0;
+    while i < 60.0 {
+        sum += term / i;
+        term *= -x2;
+        i += 2.0;
+    }
+    scale * sum
+}
+
+/// 点(x, y)の偏角
+/// https://262.ecma-international.org/11.0/#sec-math.atan2
+pub fn atan2(y: f64, x: f64) -> f64 {
+    use core::f64::consts::FRAC_PI_2;
+    use core::f64::consts::FRAC_PI_4;
+    use core::f64::consts::PI;
+
+    if x.is_nan() || y.is_nan() {
+        return f64::NAN;
+    }
+    let sign = if y.is_sign_negative() { -1.0 } else { 1.0 };
+    if y == 0.0 {
+        return if x.is_sign_negative() { sign * PI } else { y };
+    }
+    if x == 0.0 {
+        return sign * FRAC_PI_2;
+    }
+    match (x.is_infinite(), y.is_infinite()) {
+        (true, true) if x > 0.0 => sign * FRAC_PI_4,
+        (true, true) => sign * 3.0 * FRAC_PI_4,
+        (true, false) if x > 0.0 => sign * 0.0,
+        (true, false) => sign * PI,
+        (false, true) => sign * FRAC_PI_2,
+        _ => {
+            let a = atan(abs(y / x));
+            if x > 0.0 {
+                sign * a
+            } else {
+                sign * (PI - a)
+            }
+        }
+    }
+}
+
+pub fn asin(x: f64) -> f64 {
+    if x.is_nan() || abs(x) > 1.0 {
+        return f64::NAN;
+    }
+    if x == 0.0 {
+        return x;
+    }
+    atan2(x, sqrt(1.0 - x * x))
+}
+
+pub fn acos(x: f64) -> f64 {
+    if x.is_nan() || abs(x) > 1.0 {
+        return f64::NAN;
+    }
+    atan2(sqrt(1.0 - x * x), x)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn close(expected: f64, actual: f64) -> bool {
+        abs(expected - actual) <= abs(expected) * 1e-14
+    }
+
+    #[test]
+    fn test_rounding() {
+        assert_eq!(-1.0, trunc(-1.7));
+        assert!(trunc(-0.3).is_sign_negative());
+        assert_eq!(-2.0, floor(-1.2));
+        assert_eq!(2.0, ceil(1.2));
+        assert_eq!(1e300, floor(1e300));
+    }
+
+    #[test]
+    fn test_exp_and_ln() {
+        assert!(close(core::f64::consts::E, exp(1.0)));
+        assert!(close(1e-10, exp(ln(1e-10))));
+        assert!(close(LN_2 * 1000.0, ln(pow(2.0, 1000.0))));
+        assert_eq!(f64::INFINITY, exp(1000.0));
+        assert!(ln(-1.0).is_nan());
+    }
+
+    #[test]
+    fn test_pow() {
+        assert_eq!(1024.0, pow(2.0, 10.0));
+        assert_eq!(0.25, pow(2.0, -2.0));
+        assert_eq!(-8.0, pow(-2.0, 3.0));
+        assert!(close(core::f64::consts::SQRT_2, pow(2.0, 0.5)));
+        assert!(pow(-2.0, 0.5).is_nan());
+        assert!(pow(1.0, f64::INFINITY).is_nan());
+        assert_eq!(f64::NEG_INFINITY, pow(-0.0, -3.0));
+        assert_eq!(1.0, pow(f64::NAN, 0.0));
+    }
+
+    #[test]
+    fn test_sqrt_and_roots() {
+        assert_eq!(2.0, sqrt(4.0));
+        assert_eq!(core::f64::consts::SQRT_2, sqrt(2.0));
+        // 2^-1060の非正規化数
+        assert_eq!(f64::from_bits(493 << 52), sqrt(f64::from_bits(1 << 14)));
+        assert!(sqrt(-1.0).is_nan());
+        assert_eq!(3.0, cbrt(27.0));
+        assert_eq!(-2.0, cbrt(-8.0));
+        assert_eq!(10.0, log2(1024.0));
+        assert_eq!(3.0, log10(1000.0));
+    }
+
+    #[test]
+    fn test_trigonometric() {
+        use core::f64::consts::PI;
+        assert!(close(0.5, sin(PI / 6.0)));
+        assert!(close(-1.0, cos(PI)));
+        assert!(close(1.0, tan(PI / 4.0)));
+        assert!(abs(sin(PI)) < 1e-15);
+        assert!(close(0.8414709848078965, sin(1.0)));
+        assert!(close(PI / 4.0, atan(1.0)));
+        assert!(close(-3.0 * PI / 4.0, atan2(-1.0, -1.0)));
+        assert!(close(PI / 2.0, asin(1.0)));
+        assert_eq!(0.0, acos(1.0));
+        assert!(sin(-0.0).is_sign_negative());
+        assert!(atan2(-0.0, 1.0).is_sign_negative());
+    }
+}
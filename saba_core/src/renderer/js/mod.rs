@@ -1,3 +1,10 @@
 pub mod ast;
+pub mod bytecode;
+pub mod environment;
+pub mod heap;
+pub mod math;
+pub mod object;
 pub mod runtime;
+pub mod test262;
 pub mod token;
+pub mod value;
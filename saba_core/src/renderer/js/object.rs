@@ -0,0 +1,918 @@
+//! JSのオブジェクトとプロパティ
+//! ユーザーのコードを呼ばずにできる内部メソッド(通常のオブジェクトと配列)をここで扱う
+//! https://262.ecma-international.org/11.0/#sec-ordinary-object-internal-methods-and-internal-slots
+
+use crate::renderer::dom::node::Node as DomNode;
+use crate::renderer::js::ast::Node;
+use crate::renderer::js::bytecode::FunctionCode;
+use crate::renderer::js::environment::Environment;
+use crate::renderer::js::heap::ObjectId;
+use crate::renderer::js::heap::Trace;
+use crate::renderer::js::heap::Tracer;
+use crate::renderer::js::runtime::builtins::Builtin;
+use crate::renderer::js::value::RuntimeValue;
+use crate::renderer::js::value::Symbol;
+use alloc::collections::BTreeMap;
+use alloc::rc::Rc;
+use alloc::string::String;
+use alloc::string::ToString;
+use alloc::vec::Vec;
+use core::cell::RefCell;
+use core::fmt::Display;
+use core::fmt::Formatter;
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub enum PropertyKey {
+    String(String),
+    Symbol(Symbol),
+}
+
+impl PropertyKey {
+    /// 配列の添字(2^32 - 1未満の整数を表す正規の文字列)の場合はその値
+    pub fn array_index(&self) -> Option<u32> {
+        match self {
+            // 先頭の0や符号のある文字列は添字ではない
+            PropertyKey::String(s) => {
+                let bytes = s.as_bytes();
+                if bytes.is_empty() || (bytes[0] == b'0' && bytes.len() > 1) {
+                    return None;
+                }
+                if !bytes.iter().all(|b| b.is_ascii_digit()) {
+                    return None;
+                }
+                s.parse::<u32>().ok().filter(|i| *i != u32::MAX)
+            }
+            PropertyKey::Symbol(_) => None,
+        }
+    }
+}
+
+impl From<&str> for PropertyKey {
+    fn from(s: &str) -> Self {
+        PropertyKey::String(s.to_string())
+    }
+}
+
+/// 配列の添字
+impl From<usize> for PropertyKey {
+    fn from(index: usize) -> Self {
+        PropertyKey::String(index.to_string())
+    }
+}
+
+impl Display for PropertyKey {
+    fn fmt(&self, f: &mut Formatter) -> core::fmt::Result {
+        match self {
+            PropertyKey::String(s) => write!(f, "{}", s),
+            PropertyKey::Symbol(s) => write!(f, "{}", RuntimeValue::Symbol(s.clone())),
+        }
+    }
+}
+
+/// ブラウザやエンジンが提供する関数
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HostFunction {
+    /// ECMAScriptの標準の組み込み関数
+    Builtin(Builtin),
+    GetElementById,
+    TextContentGetter,
+    TextContentSetter,
+    IdGetter,
+    /// test262の`$262.evalScript`
+    EvalScript,
+}
+
+impl HostFunction {
+    pub fn name(&self) -> &'static str {
+        match self {
+            HostFunction::Builtin(builtin) => builtin.name(),
+            HostFunction::GetElementById => "getElementById",
+            HostFunction::TextContentGetter => "get textContent",
+            HostFunction::TextContentSetter => "set textContent",
+            HostFunction::IdGetter => "get id",
+            HostFunction::EvalScript => "evalScript",
+        }
+    }
+
+    /// 関数のlengthプロパティになる引数の数
+    pub fn length(&self) -> usize {
+        match self {
+            HostFunction::Builtin(builtin) => builtin.length(),
+            HostFunction::GetElementById
+            | HostFunction::TextContentSetter
+            | HostFunction::EvalScript => 1,
+            _ => 0,
+        }
+    }
+}
+
+/// スクリプトで定義された関数と、定義された場所のスコープ
+#[derive(Debug, Clone)]
+pub struct Closure {
+    pub params: Vec<Option<Rc<Node>>>,
+    pub body: Option<Rc<Node>>,
+    pub env: Rc<RefCell<Environment>>,
+    /// アロー関数はthisを持たず、newで呼べない
+    pub is_arrow: bool,
+    /// 呼び出すたびに`arguments`のオブジェクトを作る
+    pub uses_arguments: bool,
+    /// バイトコードにコンパイルした本体。Noneなら構文木を評価する
+    pub code: Option<Rc<FunctionCode>>,
+}
+
+#[derive(Debug, Clone)]
+pub enum ObjectKind {
+    Ordinary,
+    /// lengthプロパティが添字のプロパティと連動する
+    Array,
+    /// 関数の`arguments`。添字とlengthを持ち、配列のように繰り返せる
+    Arguments,
+    Function(Rc<Closure>),
+    HostFunction(HostFunction),
+    /// Function.prototype.bindで作った関数
+    BoundFunction {
+        target: ObjectId,
+        this: RuntimeValue,
+        arguments: Vec<RuntimeValue>,
+    },
+    /// Errorとそのサブクラスのコンストラクタで作ったオブジェクト
+    Error,
+    /// `new String("a")`のように、プリミティブ値を包んだオブジェクト
+    Primitive(RuntimeValue),
+    /// 1970年1月1日からのミリ秒。不正な日時はNaN
+    Date(f64),
+    /// 削除された要素はNoneのまま残し、forEachの途中で削除されても順番を保つ
+    Map(Vec<Option<(RuntimeValue, RuntimeValue)>>),
+    Set(Vec<Option<RuntimeValue>>),
+    /// DOMのノードを包んだオブジェクト
+    DomNode(Rc<RefCell<DomNode>>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum PropertyValue {
+    Data {
+        value: RuntimeValue,
+        writable: bool,
+    },
+    /// getとsetは関数か、ない場合はundefined
+    Accessor {
+        get: RuntimeValue,
+        set: RuntimeValue,
+    },
+}
+
+/// オブジェクトが持つプロパティ
+#[derive(Debug, Clone, PartialEq)]
+pub struct Property {
+    value: PropertyValue,
+    enumerable: bool,
+    configurable: bool,
+}
+
+impl Property {
+    /// 書き込み、列挙、再定義のすべてができるデータプロパティ
+    pub fn data(value: RuntimeValue) -> Self {
+        Self {
+            value: PropertyValue::Data {
+                value,
+                writable: true,
+            },
+            enumerable: true,
+            configurable: true,
+        }
+    }
+
+    pub fn value(&self) -> &PropertyValue {
+        &self.value
+    }
+
+    pub fn enumerable(&self) -> bool {
+        self.enumerable
+    }
+
+    pub fn configurable(&self) -> bool {
+        self.configurable
+    }
+
+    pub fn writable(&self) -> bool {
+        matches!(self.value, PropertyValue::Data { writable: true, .. })
+    }
+}
+
+/// Object.definePropertyに渡すような、属性の一部だけを指定した記述子
+/// https://262.ecma-international.org/11.0/#sec-property-descriptor-specification-type
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PropertyDescriptor {
+    pub value: Option<RuntimeValue>,
+    pub writable: Option<bool>,
+    pub get: Option<RuntimeValue>,
+    pub set: Option<RuntimeValue>,
+    pub enumerable: Option<bool>,
+    pub configurable: Option<bool>,
+}
+
+impl PropertyDescriptor {
+    /// 属性をすべて指定したデータプロパティの記述子
+    pub fn data(value: RuntimeValue, writable: bool, enumerable: bool, configurable: bool) -> Self {
+        Self {
+            value: Some(value),
+            writable: Some(writable),
+            enumerable: Some(enumerable),
+            configurable: Some(configurable),
+            ..Default::default()
+        }
+    }
+
+    /// 属性をすべて指定したアクセサプロパティの記述子
+    pub fn accessor(
+        get: RuntimeValue,
+        set: RuntimeValue,
+        enumerable: bool,
+        configurable: bool,
+    ) -> Self {
+        Self {
+            get: Some(get),
+            set: Some(set),
+            enumerable: Some(enumerable),
+            configurable: Some(configurable),
+            ..Default::default()
+        }
+    }
+
+    fn is_accessor(&self) -> bool {
+        self.get.is_some() || self.set.is_some()
+    }
+
+    fn is_data(&self) -> bool {
+        self.value.is_some() || self.writable.is_some()
+    }
+}
+
+/// オブジェクトのプロパティの表
+/// 0から続く添字は配列に詰めて置き、それ以外のキーは追加された順に並べて索引から引く
+#[derive(Debug, Clone, Default)]
+struct Properties {
+    /// 添字が0から続く要素。削除された要素はNone
+    elements: Vec<Option<Property>>,
+    /// elementsに続かない添字と、文字列とシンボルのキー。削除されたものはNoneにして順番を保つ
+    entries: Vec<Option<(PropertyKey, Property)>>,
+    /// キーからentriesの位置を引く
+    index: BTreeMap<PropertyKey, usize>,
+    /// entriesにある添字の数
+    sparse: usize,
+    /// entriesの中のNoneの数
+    removed: usize,
+}
+
+impl Properties {
+    fn get(&self, key: &PropertyKey) -> Option<&Property> {
+        if let Some(i) = key.array_index() {
+            if let Some(element) = self.elements.get(i as usize) {
+                return element.as_ref();
+            }
+        }
+        let i = *self.index.get(key)?;
+        self.entries[i].as_ref().map(|(_, property)| property)
+    }
+
+    fn get_mut(&mut self, key: &PropertyKey) -> Option<&mut Property> {
+        if let Some(i) = key.array_index() {
+            if let Some(element) = self.elements.get_mut(i as usize) {
+                return element.as_mut();
+            }
+        }
+        let i = *self.index.get(key)?;
+        self.entries[i].as_mut().map(|(_, property)| property)
+    }
+
+    /// まだないキーのプロパティを追加する
+    fn insert(&mut self, key: PropertyKey, property: Property) {
+        if let Some(i) = key.array_index() {
+            let i = i as usize;
+            if i < self.elements.len() {
+                self.elements[i] = Some(property);
+                return;
+            }
+            if i == self.elements.len() {
+                self.elements.push(Some(property));
+                // 続きの添字がentriesにあれば、要素に移す
+                while self.sparse > 0 {
+                    match self.remove_entry(&PropertyKey::from(self.elements.len())) {
+                        Some(property) => self.elements.push(Some(property)),
+                        None => break,
+                    }
+                }
+                return;
+            }
+            self.sparse += 1;
+        }
+        self.index.insert(key.clone(), self.entries.len());
+        self.entries.push(Some((key, property)));
+    }
+
+    fn remove(&mut self, key: &PropertyKey) {
+        if let Some(i) = key.array_index() {
+            if let Some(element) = self.elements.get_mut(i as usize) {
+                *element = None;
+                // 末尾の穴は詰めて、elementsの後ろの添字はentriesにあるようにする
+                while let Some(None) = self.elements.last() {
+                    self.elements.pop();
+                }
+                return;
+            }
+        }
+        self.remove_entry(key);
+    }
+
+    fn remove_entry(&mut self, key: &PropertyKey) -> Option<Property> {
+        let i = self.index.remove(key)?;
+        let (key, property) = self.entries[i].take()?;
+        if key.array_index().is_some() {
+            self.sparse -= 1;
+        }
+        self.removed += 1;
+        // 削除したものが半分を超えたら詰める
+        if self.removed * 2 > self.entries.len() {
+            self.entries.retain(|entry| entry.is_some());
+            for (i, (key, _)) in self.entries.iter().flatten().enumerate() {
+                self.index.insert(key.clone(), i);
+            }
+            self.removed = 0;
+        }
+        Some(property)
+    }
+
+    /// 添字のキーを昇順に並べる
+    fn indices(&self) -> Vec<u32> {
+        let mut indices: Vec<u32> = (0..self.elements.len())
+            .filter(|i| self.elements[*i].is_some())
+            .map(|i| i as u32)
+            .collect();
+        // entriesの添字は、どれもelementsの添字より大きい
+        let start = indices.len();
+        indices.extend(
+            self.entries
+                .iter()
+                .flatten()
+                .filter_map(|(key, _)| key.array_index()),
+        );
+        indices[start..].sort_unstable();
+        indices
+    }
+
+    fn values(&self) -> impl Iterator<Item = &Property> {
+        self.elements
+            .iter()
+            .flatten()
+            .chain(self.entries.iter().flatten().map(|(_, property)| property))
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct JsObject {
+    kind: ObjectKind,
+    prototype: Option<ObjectId>,
+    extensible: bool,
+    properties: Properties,
+}
+
+impl JsObject {
+    pub fn new(kind: ObjectKind, prototype: Option<ObjectId>) -> Self {
+        let mut object = Self {
+            kind,
+            prototype,
+            extensible: true,
+            properties: Properties::default(),
+        };
+        if let ObjectKind::Array = object.kind {
+            object.properties.insert(
+                PropertyKey::from("length"),
+                Property {
+                    value: PropertyValue::Data {
+                        value: RuntimeValue::Number(0.0),
+                        writable: true,
+                    },
+                    enumerable: false,
+                    configurable: false,
+                },
+            );
+        }
+        object
+    }
+
+    pub fn kind(&self) -> &ObjectKind {
+        &self.kind
+    }
+
+    pub fn kind_mut(&mut self) -> &mut ObjectKind {
+        &mut self.kind
+    }
+
+    pub fn prototype(&self) -> Option<ObjectId> {
+        self.prototype
+    }
+
+    /// 循環しないことは呼び出し側で確かめる
+    pub fn set_prototype(&mut self, prototype: Option<ObjectId>) -> bool {
+        if prototype == self.prototype {
+            return true;
+        }
+        if !self.extensible {
+            return false;
+        }
+        self.prototype = prototype;
+        true
+    }
+
+    pub fn is_extensible(&self) -> bool {
+        self.extensible
+    }
+
+    pub fn prevent_extensions(&mut self) {
+        self.extensible = false;
+    }
+
+    pub fn is_callable(&self) -> bool {
+        matches!(
+            self.kind,
+            ObjectKind::Function(_)
+                | ObjectKind::HostFunction(_)
+                | ObjectKind::BoundFunction { .. }
+        )
+    }
+
+    pub fn get_own_property(&self, key: &PropertyKey) -> Option<&Property> {
+        self.properties.get(key)
+    }
+
+    /// 配列のlength
+    fn array_length(&self) -> u32 {
+        match self.get_own_property(&PropertyKey::from("length")) {
+            Some(Property {
+                value:
+                    PropertyValue::Data {
+                        value: RuntimeValue::Number(n),
+                        ..
+                    },
+                ..
+            }) => *n as u32,
+            _ => 0,
+        }
+    }
+
+    /// [[DefineOwnProperty]]。定義できなかった場合はfalse
+    /// https://262.ecma-international.org/11.0/#sec-array-exotic-objects-defineownproperty-p-desc
+    pub fn define_own_property(&mut self, key: PropertyKey, desc: PropertyDescriptor) -> bool {
+        if let ObjectKind::Array = self.kind {
+            if key == PropertyKey::from("length") {
+                return self.set_array_length(desc);
+            }
+            if let Some(index) = key.array_index() {
+                let length = self.array_length();
+                let length_writable = self
+                    .get_own_property(&PropertyKey::from("length"))
+                    .is_some_and(|p| p.writable());
+                if index >= length && !length_writable {
+                    return false;
+                }
+                if !self.ordinary_define_own_property(key, desc) {
+                    return false;
+                }
+                if index >= length {
+                    self.write_array_length(index as f64 + 1.0);
+                }
+                return true;
+            }
+        }
+        self.ordinary_define_own_property(key, desc)
+    }
+
+    fn write_array_length(&mut self, length: f64) {
+        if let Some(Property {
+            value: PropertyValue::Data { value, .. },
+            ..
+        }) = self.properties.get_mut(&PropertyKey::from("length"))
+        {
+            *value = RuntimeValue::Number(length);
+        }
+    }
+
+    /// ArraySetLength。valueはToUint32で変換できる数であることを呼び出し側で確かめる
+    /// https://262.ecma-international.org/11.0/#sec-arraysetlength
+    fn set_array_length(&mut self, desc: PropertyDescriptor) -> bool {
+        let new_length = match &desc.value {
+            Some(RuntimeValue::Number(n)) => *n as u32,
+            Some(_) => return false,
+            None => return self.ordinary_define_own_property(PropertyKey::from("length"), desc),
+        };
+        let old_length = self.array_length();
+        let length_writable = self
+            .get_own_property(&PropertyKey::from("length"))
+            .is_some_and(|p| p.writable());
+        if !length_writable && new_length != old_length {
+            return false;
+        }
+        let length_desc = PropertyDescriptor {
+            value: Some(RuntimeValue::Number(old_length as f64)),
+            ..desc.clone()
+        };
+        if !self.ordinary_define_own_property(PropertyKey::from("length"), length_desc) {
+            return false;
+        }
+        if new_length >= old_length {
+            self.write_array_length(new_length as f64);
+            return true;
+        }
+
+        // 後ろの要素から削除していき、削除できない要素があればその直後を長さにする
+        let mut indices = self.properties.indices();
+        indices.retain(|i| *i >= new_length);
+        while let Some(index) = indices.pop() {
+            if !self.delete(&PropertyKey::String(index.to_string())) {
+                self.write_array_length(index as f64 + 1.0);
+                return false;
+            }
+        }
+        self.write_array_length(new_length as f64);
+        true
+    }
+
+    /// ValidateAndApplyPropertyDescriptor
+    /// https://262.ecma-international.org/11.0/#sec-validateandapplypropertydescriptor
+    fn ordinary_define_own_property(&mut self, key: PropertyKey, desc: PropertyDescriptor) -> bool {
+        let current = match self.properties.get(&key) {
+            Some(property) => property.clone(),
+            None => {
+                if !self.extensible {
+                    return false;
+                }
+                let value = if desc.is_accessor() {
+                    PropertyValue::Accessor {
+                        get: desc.get.unwrap_or(RuntimeValue::Undefined),
+                        set: desc.set.unwrap_or(RuntimeValue::Undefined),
+                    }
+                } else {
+                    PropertyValue::Data {
+                        value: desc.value.unwrap_or(RuntimeValue::Undefined),
+                        writable: desc.writable.unwrap_or(false),
+                    }
+                };
+                self.properties.insert(
+                    key,
+                    Property {
+                        value,
+                        enumerable: desc.enumerable.unwrap_or(false),
+                        configurable: desc.configurable.unwrap_or(false),
+                    },
+                );
+                return true;
+            }
+        };
+
+        // 再定義できないプロパティは、属性を変えない記述子だけを受け付ける
+        if !current.configurable {
+            if desc.configurable == Some(true) {
+                return false;
+            }
+            if desc.enumerable.is_some_and(|e| e != current.enumerable) {
+                return false;
+            }
+            match &current.value {
+                PropertyValue::Data { value, writable } => {
+                    if desc.is_accessor() {
+                        return false;
+                    }
+                    if !writable {
+                        if desc.writable == Some(true) {
+                            return false;
+                        }
+                        if desc.value.as_ref().is_some_and(|v| !same_value(v, value)) {
+                            return false;
+                        }
+                    }
+                }
+                PropertyValue::Accessor { get, set } => {
+                    if desc.is_data() {
+                        return false;
+                    }
+                    if desc.get.as_ref().is_some_and(|g| !same_value(g, get))
+                        || desc.set.as_ref().is_some_and(|s| !same_value(s, set))
+                    {
+                        return false;
+                    }
+                }
+            }
+        }
+
+        let mut property = current;
+        if let Some(enumerable) = desc.enumerable {
+            property.enumerable = enumerable;
+        }
+        if let Some(configurable) = desc.configurable {
+            property.configurable = configurable;
+        }
+        // データとアクセサを入れ替えるときは、指定されていない属性を初期値にする
+        property.value = match property.value {
+            PropertyValue::Data { value, writable } if !desc.is_accessor() => PropertyValue::Data {
+                value: desc.value.unwrap_or(value),
+                writable: desc.writable.unwrap_or(writable),
+            },
+            PropertyValue::Data { .. } => PropertyValue::Accessor {
+                get: desc.get.unwrap_or(RuntimeValue::Undefined),
+                set: desc.set.unwrap_or(RuntimeValue::Undefined),
+            },
+            PropertyValue::Accessor { get, set } if !desc.is_data() => PropertyValue::Accessor {
+                get: desc.get.unwrap_or(get),
+                set: desc.set.unwrap_or(set),
+            },
+            PropertyValue::Accessor { .. } => PropertyValue::Data {
+                value: desc.value.unwrap_or(RuntimeValue::Undefined),
+                writable: desc.writable.unwrap_or(false),
+            },
+        };
+        if let Some(current) = self.properties.get_mut(&key) {
+            *current = property;
+        }
+        true
+    }
+
+    /// [[Delete]]。再定義できないプロパティは削除できない
+    pub fn delete(&mut self, key: &PropertyKey) -> bool {
+        match self.properties.get(key) {
+            Some(property) if property.configurable => {
+                self.properties.remove(key);
+                true
+            }
+            Some(_) => false,
+            None => true,
+        }
+    }
+
+    /// [[OwnPropertyKeys]]。添字を昇順に、その後に文字列とシンボルを追加された順に並べる
+    /// https://262.ecma-international.org/11.0/#sec-ordinaryownpropertykeys
+    pub fn own_property_keys(&self) -> Vec<PropertyKey> {
+        let mut keys: Vec<PropertyKey> = self
+            .properties
+            .indices()
+            .into_iter()
+            .map(|i| PropertyKey::String(i.to_string()))
+            .collect();
+        let entries = || self.properties.entries.iter().flatten();
+        for (key, _) in entries() {
+            if matches!(key, PropertyKey::String(_)) && key.array_index().is_none() {
+                keys.push(key.clone());
+            }
+        }
+        for (key, _) in entries() {
+            if matches!(key, PropertyKey::Symbol(_)) {
+                keys.push(key.clone());
+            }
+        }
+        keys
+    }
+}
+
+impl Trace for JsObject {
+    fn trace(&self, tracer: &mut Tracer) {
+        if let Some(prototype) = self.prototype {
+            tracer.mark_object(prototype);
+        }
+        match &self.kind {
+            ObjectKind::Function(closure) => RefCell::borrow(&closure.env).trace(tracer),
+            ObjectKind::BoundFunction {
+                target,
+                this,
+                arguments,
+            } => {
+                tracer.mark_object(*target);
+                tracer.mark_value(this);
+                for argument in arguments {
+                    tracer.mark_value(argument);
+                }
+            }
+            ObjectKind::Map(entries) => {
+                for (key, value) in entries.iter().flatten() {
+                    tracer.mark_value(key);
+                    tracer.mark_value(value);
+                }
+            }
+            ObjectKind::Set(values) => {
+                for value in values.iter().flatten() {
+                    tracer.mark_value(value);
+                }
+            }
+            _ => {}
+        }
+        for property in self.properties.values() {
+            match &property.value {
+                PropertyValue::Data { value, .. } => tracer.mark_value(value),
+                PropertyValue::Accessor { get, set } => {
+                    tracer.mark_value(get);
+                    tracer.mark_value(set);
+                }
+            }
+        }
+    }
+}
+
+/// SameValue。NaN同士は等しく、+0と-0は異なる
+pub fn same_value(a: &RuntimeValue, b: &RuntimeValue) -> bool {
+    match (a, b) {
+        (RuntimeValue::Number(x), RuntimeValue::Number(y)) => {
+            (x.is_nan() && y.is_nan()) || (x == y && x.is_sign_negative() == y.is_sign_negative())
+        }
+        _ => a.strict_equals(b),
+    }
+}
+
+/// SameValueZero。NaN同士は等しく、+0と-0も等しい
+pub fn same_value_zero(a: &RuntimeValue, b: &RuntimeValue) -> bool {
+    match (a, b) {
+        (RuntimeValue::Number(x), RuntimeValue::Number(y)) => (x.is_nan() && y.is_nan()) || x == y,
+        _ => a.strict_equals(b),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::format;
+
+    fn key(s: &str) -> PropertyKey {
+        PropertyKey::from(s)
+    }
+
+    fn number(n: f64) -> RuntimeValue {
+        RuntimeValue::Number(n)
+    }
+
+    #[test]
+    fn test_property_order() {
+        let mut object = JsObject::new(ObjectKind::Ordinary, None);
+        let symbol = PropertyKey::Symbol(Symbol::new(None));
+        for k in [
+            key("b"),
+            symbol.clone(),
+            key("2"),
+            key("a"),
+            key("1"),
+            key("01"),
+        ] {
+            assert!(object
+                .define_own_property(k, PropertyDescriptor::data(number(0.0), true, true, true)));
+        }
+        assert_eq!(
+            [key("1"), key("2"), key("b"), key("a"), key("01"), symbol],
+            object.own_property_keys().as_slice()
+        );
+    }
+
+    #[test]
+    fn test_elements_and_sparse_indices() {
+        let mut object = JsObject::new(ObjectKind::Array, None);
+        let data = || PropertyDescriptor::data(number(0.0), true, true, true);
+        for k in ["0", "1", "5", "x", "3", "2", "4", "6"] {
+            assert!(object.define_own_property(key(k), data()));
+        }
+        // 離れた添字も、間が埋まれば続きの要素になる
+        assert_eq!(7, object.properties.elements.len());
+        assert_eq!(0, object.properties.sparse);
+
+        assert!(object.delete(&key("6")));
+        assert!(object.delete(&key("1")));
+        assert!(object.get_own_property(&key("1")).is_none());
+        assert!(object.get_own_property(&key("5")).is_some());
+        assert_eq!(
+            ["0", "2", "3", "4", "5", "length", "x"].map(key),
+            object.own_property_keys().as_slice()
+        );
+
+        // 削除したキーを詰めても、追加された順は変わらない
+        let mut object = JsObject::new(ObjectKind::Ordinary, None);
+        for i in 0..10 {
+            assert!(object.define_own_property(key(&format!("k{}", i)), data()));
+        }
+        for i in 0..8 {
+            assert!(object.delete(&key(&format!("k{}", i))));
+        }
+        assert!(object.define_own_property(key("a"), data()));
+        assert_eq!(
+            ["k8", "k9", "a"].map(key),
+            object.own_property_keys().as_slice()
+        );
+        assert!(object.get_own_property(&key("k9")).is_some());
+    }
+
+    #[test]
+    fn test_non_configurable_property() {
+        let mut object = JsObject::new(ObjectKind::Ordinary, None);
+        assert!(object.define_own_property(
+            key("x"),
+            PropertyDescriptor::data(number(1.0), false, true, false)
+        ));
+
+        // 同じ値の再定義はできるが、値や属性は変えられない
+        assert!(object.define_own_property(
+            key("x"),
+            PropertyDescriptor {
+                value: Some(number(1.0)),
+                ..Default::default()
+            }
+        ));
+        assert!(!object.define_own_property(
+            key("x"),
+            PropertyDescriptor {
+                value: Some(number(2.0)),
+                ..Default::default()
+            }
+        ));
+        assert!(!object.define_own_property(
+            key("x"),
+            PropertyDescriptor {
+                enumerable: Some(false),
+                ..Default::default()
+            }
+        ));
+        assert!(!object.define_own_property(
+            key("x"),
+            PropertyDescriptor::accessor(
+                RuntimeValue::Undefined,
+                RuntimeValue::Undefined,
+                true,
+                false
+            )
+        ));
+        assert!(!object.delete(&key("x")));
+
+        object.prevent_extensions();
+        assert!(!object.define_own_property(
+            key("y"),
+            PropertyDescriptor::data(number(1.0), true, true, true)
+        ));
+    }
+
+    #[test]
+    fn test_data_to_accessor() {
+        let mut object = JsObject::new(ObjectKind::Ordinary, None);
+        object.define_own_property(
+            key("x"),
+            PropertyDescriptor::data(number(1.0), true, true, true),
+        );
+        assert!(object.define_own_property(
+            key("x"),
+            PropertyDescriptor {
+                get: Some(RuntimeValue::Null),
+                ..Default::default()
+            }
+        ));
+        assert_eq!(
+            Some(&PropertyValue::Accessor {
+                get: RuntimeValue::Null,
+                set: RuntimeValue::Undefined
+            }),
+            object.get_own_property(&key("x")).map(|p| p.value())
+        );
+        assert!(object
+            .get_own_property(&key("x"))
+            .is_some_and(|p| p.enumerable()));
+    }
+
+    #[test]
+    fn test_array_length() {
+        let mut array = JsObject::new(ObjectKind::Array, None);
+        array.define_own_property(
+            key("4"),
+            PropertyDescriptor::data(number(4.0), true, true, true),
+        );
+        array.define_own_property(
+            key("1"),
+            PropertyDescriptor::data(number(1.0), true, true, false),
+        );
+        assert_eq!(5, array.array_length());
+
+        // 削除できない要素のところで縮めるのをやめる
+        assert!(!array.define_own_property(
+            key("length"),
+            PropertyDescriptor {
+                value: Some(number(0.0)),
+                ..Default::default()
+            }
+        ));
+        assert_eq!(2, array.array_length());
+        assert!(array.get_own_property(&key("4")).is_none());
+
+        assert!(array.define_own_property(
+            key("length"),
+            PropertyDescriptor {
+                value: Some(number(2.0)),
+                writable: Some(false),
+                ..Default::default()
+            }
+        ));
+        assert!(!array.define_own_property(
+            key("2"),
+            PropertyDescriptor::data(number(2.0), true, true, true)
+        ));
+        assert_eq!(None, key("4294967295").array_index());
+    }
+}
@@ -1,365 +1,2610 @@
 use crate::renderer::dom::api::get_element_by_id;
+use crate::renderer::dom::api::get_text_content;
 use crate::renderer::dom::node::Node as DomNode;
 use crate::renderer::dom::node::NodeKind as DomNodeKind;
+use crate::renderer::js::ast::is_lexical_declaration;
+use crate::renderer::js::ast::parameter_name;
+use crate::renderer::js::ast::uses_arguments;
+use crate::renderer::js::ast::var_declared_names;
+use crate::renderer::js::ast::JsParser;
 use crate::renderer::js::ast::Node;
 use crate::renderer::js::ast::Program;
+use crate::renderer::js::ast::PropertyKind;
+use crate::renderer::js::bytecode::compile_program;
+use crate::renderer::js::bytecode::FunctionCode;
+use crate::renderer::js::environment::BindingError;
+use crate::renderer::js::environment::Environment;
+use crate::renderer::js::heap::Heap;
+use crate::renderer::js::heap::ObjectId;
+use crate::renderer::js::heap::Trace;
+use crate::renderer::js::heap::Tracer;
+use crate::renderer::js::math::pow;
+use crate::renderer::js::object::Closure;
+use crate::renderer::js::object::HostFunction;
+use crate::renderer::js::object::JsObject;
+use crate::renderer::js::object::ObjectKind;
+use crate::renderer::js::object::PropertyDescriptor;
+use crate::renderer::js::object::PropertyKey;
+use crate::renderer::js::object::PropertyValue;
+use crate::renderer::js::runtime::vm::VmState;
+use crate::renderer::js::token::JsLexer;
+use crate::renderer::js::value::string_code_unit_at;
+use crate::renderer::js::value::string_length;
+use crate::renderer::js::value::string_less_than;
+use crate::renderer::js::value::string_to_number;
+use crate::renderer::js::value::to_int32;
+use crate::renderer::js::value::to_uint32;
+use crate::renderer::js::value::RuntimeValue;
 use alloc::format;
 use alloc::rc::Rc;
+use alloc::rc::Weak;
 use alloc::string::String;
 use alloc::string::ToString;
+use alloc::vec;
 use alloc::vec::Vec;
 use core::borrow::Borrow;
 use core::cell::RefCell;
-use core::fmt::Display;
-use core::fmt::Formatter;
-use core::ops::Add;
-use core::ops::Sub;
+use date::Clock;
+use date::FixedClock;
 
-type VariableMap = Vec<(String, Option<RuntimeValue>)>;
+mod array;
+pub mod builtins;
+mod collections;
+pub mod date;
+mod json;
+mod number;
+mod string;
+mod vm;
 
+/// 正規表現のリテラルを評価したときのSyntaxErrorのメッセージ
+pub const REGEXP_NOT_SUPPORTED: &str = "regular expressions are not supported";
+
+/// 関数の呼び出しを入れ子にできる深さ
+/// JSの呼び出しはRustのスタックを使うため、使い切ってブラウザごと落ちる前にRangeErrorにする
+/// リリースビルドでは、1回の呼び出しで数KBを使う
+const MAX_CALL_DEPTH: usize = 256;
+
+/// Errorとそのサブクラス
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorKind {
+    Error,
+    EvalError,
+    RangeError,
+    ReferenceError,
+    SyntaxError,
+    TypeError,
+    URIError,
+}
+
+impl ErrorKind {
+    pub const ALL: [ErrorKind; 7] = [
+        ErrorKind::Error,
+        ErrorKind::EvalError,
+        ErrorKind::RangeError,
+        ErrorKind::ReferenceError,
+        ErrorKind::SyntaxError,
+        ErrorKind::TypeError,
+        ErrorKind::URIError,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            ErrorKind::Error => "Error",
+            ErrorKind::EvalError => "EvalError",
+            ErrorKind::RangeError => "RangeError",
+            ErrorKind::ReferenceError => "ReferenceError",
+            ErrorKind::SyntaxError => "SyntaxError",
+            ErrorKind::TypeError => "TypeError",
+            ErrorKind::URIError => "URIError",
+        }
+    }
+}
+
+/// 途中で終わった文の実行結果 (abrupt completion)
+/// 最後まで実行された場合(normal completion)は`Ok`で値を返す
 #[derive(Debug, Clone, PartialEq)]
-pub enum RuntimeValue {
-    Number(u64),
-    StringLiteral(String),
-    HtmlElement {
-        object: Rc<RefCell<DomNode>>,
-        property: Option<String>,
+pub enum Completion {
+    Return(RuntimeValue),
+    Throw(RuntimeValue),
+    /// エンジンが投げる例外。catchされたときにErrorオブジェクトになる
+    Error(ErrorKind, String),
+    /// 対象のラベル
+    Break(Option<String>),
+    Continue(Option<String>),
+}
+
+impl Completion {
+    /// エンジンが投げる例外
+    fn error(kind: ErrorKind, message: &str) -> Self {
+        Completion::Error(kind, message.to_string())
+    }
+
+    /// 例外で終わったかどうか
+    fn is_throw(&self) -> bool {
+        matches!(self, Completion::Throw(_) | Completion::Error(..))
+    }
+}
+
+/// 値の読み書きをする場所 (Reference Record)
+#[derive(Debug, Clone)]
+enum Reference {
+    Binding(String),
+    Property {
+        base: RuntimeValue,
+        key: PropertyKey,
     },
 }
 
-impl Add<RuntimeValue> for RuntimeValue {
-    type Output = RuntimeValue;
+/// ToPrimitiveで優先する型
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum PreferredType {
+    Default,
+    Number,
+    String,
+}
 
-    fn add(self, rhs: RuntimeValue) -> RuntimeValue {
-        if let (RuntimeValue::Number(left_num), RuntimeValue::Number(right_num)) = (&self, &rhs) {
-            return RuntimeValue::Number(left_num + right_num);
-        }
+/// エンジンが最初に作っておくオブジェクト
+#[derive(Debug, Clone, Copy)]
+struct Intrinsics {
+    object_prototype: ObjectId,
+    function_prototype: ObjectId,
+    array_prototype: ObjectId,
+    string_prototype: ObjectId,
+    number_prototype: ObjectId,
+    boolean_prototype: ObjectId,
+    symbol_prototype: ObjectId,
+    /// `ErrorKind::ALL`の順に並べる
+    error_prototypes: [ObjectId; 7],
+    date_prototype: ObjectId,
+    map_prototype: ObjectId,
+    set_prototype: ObjectId,
+    document_prototype: ObjectId,
+    element_prototype: ObjectId,
+}
 
-        RuntimeValue::StringLiteral(self.to_string() + &rhs.to_string())
+impl Intrinsics {
+    fn error_prototype(&self, kind: ErrorKind) -> ObjectId {
+        self.error_prototypes[kind as usize]
+    }
+}
+
+impl Trace for Intrinsics {
+    fn trace(&self, tracer: &mut Tracer) {
+        tracer.mark_object(self.object_prototype);
+        tracer.mark_object(self.function_prototype);
+        tracer.mark_object(self.array_prototype);
+        tracer.mark_object(self.string_prototype);
+        tracer.mark_object(self.number_prototype);
+        tracer.mark_object(self.boolean_prototype);
+        tracer.mark_object(self.symbol_prototype);
+        for prototype in self.error_prototypes {
+            tracer.mark_object(prototype);
+        }
+        tracer.mark_object(self.date_prototype);
+        tracer.mark_object(self.map_prototype);
+        tracer.mark_object(self.set_prototype);
+        tracer.mark_object(self.document_prototype);
+        tracer.mark_object(self.element_prototype);
     }
 }
 
-impl Sub<RuntimeValue> for RuntimeValue {
-    type Output = RuntimeValue;
+/// スクリプトを実行する方法
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Engine {
+    /// 構文木を直接評価する
+    TreeWalker,
+    /// バイトコードにコンパイルしてVMで実行する
+    Bytecode,
+}
+
+#[derive(Debug, Clone)]
+pub struct JsRuntime {
+    dom_root: Rc<RefCell<DomNode>>,
+    env: Rc<RefCell<Environment>>,
+    heap: Heap,
+    intrinsics: Intrinsics,
+    /// DOMのノードを包んだオブジェクト。同じノードには同じオブジェクトを返す
+    /// GCのルートではないため、スクリプトから参照されなくなったものは回収される
+    dom_wrappers: Vec<(Weak<RefCell<DomNode>>, ObjectId)>,
+    /// Dateが使う現在時刻
+    clock: Rc<dyn Clock>,
+    /// Math.randomの疑似乱数の状態 (xorshift)
+    random_state: u64,
+    engine: Engine,
+    /// バイトコードを実行中のフレームの値
+    vm: VmState,
+    /// 実行中の関数やブロックのスコープ。GCのルートになる
+    scopes: Vec<Rc<RefCell<Environment>>>,
+    /// 実行中の関数の呼び出しの数
+    call_depth: usize,
+}
+
+impl JsRuntime {
+    pub fn new(dom_root: Rc<RefCell<DomNode>>) -> Self {
+        let mut heap = Heap::new();
+        let object_prototype = heap.allocate(JsObject::new(ObjectKind::Ordinary, None));
+        let mut allocate = |kind| heap.allocate(JsObject::new(kind, Some(object_prototype)));
+        let function_prototype = allocate(ObjectKind::Ordinary);
+        let array_prototype = allocate(ObjectKind::Array);
+        let string_prototype = allocate(ObjectKind::Primitive(RuntimeValue::String(String::new())));
+        let number_prototype = allocate(ObjectKind::Primitive(RuntimeValue::Number(0.0)));
+        let boolean_prototype = allocate(ObjectKind::Primitive(RuntimeValue::Boolean(false)));
+        let symbol_prototype = allocate(ObjectKind::Ordinary);
+        let error_prototype = allocate(ObjectKind::Ordinary);
+        let date_prototype = allocate(ObjectKind::Ordinary);
+        let map_prototype = allocate(ObjectKind::Ordinary);
+        let set_prototype = allocate(ObjectKind::Ordinary);
+        let document_prototype = allocate(ObjectKind::Ordinary);
+        let element_prototype = allocate(ObjectKind::Ordinary);
+        // トップレベルのthisになるグローバルオブジェクト
+        let global_object = allocate(ObjectKind::Ordinary);
+        // TypeErrorなどのプロトタイプは、Error.prototypeを継承する
+        let error_prototypes = ErrorKind::ALL.map(|kind| match kind {
+            ErrorKind::Error => error_prototype,
+            _ => heap.allocate(JsObject::new(ObjectKind::Ordinary, Some(error_prototype))),
+        });
+        let intrinsics = Intrinsics {
+            object_prototype,
+            function_prototype,
+            array_prototype,
+            string_prototype,
+            number_prototype,
+            boolean_prototype,
+            symbol_prototype,
+            error_prototypes,
+            date_prototype,
+            map_prototype,
+            set_prototype,
+            document_prototype,
+            element_prototype,
+        };
+
+        let mut global = Environment::new_function(None, RuntimeValue::Object(global_object));
+        global.initialize(
+            "globalThis".to_string(),
+            RuntimeValue::Object(global_object),
+        );
+        global.initialize("undefined".to_string(), RuntimeValue::Undefined);
+        global.initialize("NaN".to_string(), RuntimeValue::Number(f64::NAN));
+        global.initialize("Infinity".to_string(), RuntimeValue::Number(f64::INFINITY));
+
+        let mut runtime = Self {
+            dom_root: dom_root.clone(),
+            env: Rc::new(RefCell::new(global)),
+            heap,
+            intrinsics,
+            dom_wrappers: Vec::new(),
+            clock: Rc::new(FixedClock(0.0)),
+            random_state: 0x2545_f491_4f6c_dd1d,
+            engine: Engine::Bytecode,
+            vm: VmState::default(),
+            scopes: Vec::new(),
+            call_depth: 0,
+        };
+        runtime.install_builtins();
+
+        // DOMのメソッドと属性は列挙できる (WebIDL)
+        runtime.define_host_method(
+            intrinsics.document_prototype,
+            HostFunction::GetElementById,
+            true,
+        );
+        runtime.define_host_accessor(
+            intrinsics.element_prototype,
+            "textContent",
+            HostFunction::TextContentGetter,
+            Some(HostFunction::TextContentSetter),
+        );
+        runtime.define_host_accessor(
+            intrinsics.element_prototype,
+            "id",
+            HostFunction::IdGetter,
+            None,
+        );
+
+        let document = runtime.wrap_dom_node(dom_root);
+        runtime
+            .env
+            .borrow_mut()
+            .initialize("document".to_string(), RuntimeValue::Object(document));
+        // 組み込みのオブジェクトはグローバルのスコープから辿れる
+        runtime.heap.unpin(0);
+        runtime
+    }
+
+    /// ホスト関数のオブジェクトを作る
+    fn create_host_function(&mut self, function: HostFunction) -> ObjectId {
+        let id = self.heap.allocate(JsObject::new(
+            ObjectKind::HostFunction(function),
+            Some(self.intrinsics.function_prototype),
+        ));
+        let object = self.heap.get_mut(id);
+        object.define_own_property(
+            PropertyKey::from("length"),
+            PropertyDescriptor::data(
+                RuntimeValue::Number(function.length() as f64),
+                false,
+                false,
+                true,
+            ),
+        );
+        object.define_own_property(
+            PropertyKey::from("name"),
+            PropertyDescriptor::data(
+                RuntimeValue::String(function.name().to_string()),
+                false,
+                false,
+                true,
+            ),
+        );
+        id
+    }
+
+    fn define_host_method(&mut self, object: ObjectId, function: HostFunction, enumerable: bool) {
+        let method = self.create_host_function(function);
+        self.heap.get_mut(object).define_own_property(
+            PropertyKey::from(function.name()),
+            PropertyDescriptor::data(RuntimeValue::Object(method), true, enumerable, true),
+        );
+    }
 
-    fn sub(self, rhs: RuntimeValue) -> RuntimeValue {
-        if let (RuntimeValue::Number(left_num), RuntimeValue::Number(right_num)) = (&self, &rhs) {
-            return RuntimeValue::Number(left_num - right_num);
+    fn define_host_accessor(
+        &mut self,
+        object: ObjectId,
+        name: &str,
+        getter: HostFunction,
+        setter: Option<HostFunction>,
+    ) {
+        let get = RuntimeValue::Object(self.create_host_function(getter));
+        let set = match setter {
+            Some(setter) => RuntimeValue::Object(self.create_host_function(setter)),
+            None => RuntimeValue::Undefined,
+        };
+        self.heap.get_mut(object).define_own_property(
+            PropertyKey::from(name),
+            PropertyDescriptor::accessor(get, set, true, true),
+        );
+    }
+
+    /// DOMのノードを包んだオブジェクトを返す
+    fn wrap_dom_node(&mut self, node: Rc<RefCell<DomNode>>) -> ObjectId {
+        for (wrapped, id) in &self.dom_wrappers {
+            if wrapped.upgrade().is_some_and(|n| Rc::ptr_eq(&n, &node)) {
+                return *id;
+            }
         }
 
-        // NaNとして処理
-        RuntimeValue::Number(u64::MIN)
+        let prototype = match RefCell::borrow(&node).kind() {
+            DomNodeKind::Document => self.intrinsics.document_prototype,
+            _ => self.intrinsics.element_prototype,
+        };
+        let id = self.heap.allocate(JsObject::new(
+            ObjectKind::DomNode(node.clone()),
+            Some(prototype),
+        ));
+        self.dom_wrappers.push((Rc::downgrade(&node), id));
+        id
     }
-}
 
-impl Display for RuntimeValue {
-    fn fmt(&self, f: &mut Formatter) -> core::fmt::Result {
-        let s = match self {
-            RuntimeValue::Number(value) => format!("{}", value),
-            RuntimeValue::StringLiteral(value) => value.to_string(),
-            RuntimeValue::HtmlElement {
-                object,
-                property: _,
+    /// ホスト関数のthisが包んでいるDOMのノード
+    fn this_dom_node(&self, this: &RuntimeValue) -> Result<Rc<RefCell<DomNode>>, Completion> {
+        if let RuntimeValue::Object(id) = this {
+            if let ObjectKind::DomNode(node) = self.heap.get(*id).kind() {
+                return Ok(node.clone());
+            }
+        }
+        Err(Completion::error(
+            ErrorKind::TypeError,
+            "Illegal invocation",
+        ))
+    }
+
+    /// ブラウザやエンジンが提供する関数を呼び出す
+    fn call_host_function(
+        &mut self,
+        function: HostFunction,
+        this: RuntimeValue,
+        arguments: &[RuntimeValue],
+    ) -> Result<RuntimeValue, Completion> {
+        let argument = arguments
+            .first()
+            .cloned()
+            .unwrap_or(RuntimeValue::Undefined);
+        match function {
+            HostFunction::Builtin(builtin) => self.call_builtin(builtin, this, arguments),
+            HostFunction::GetElementById => {
+                let id = self.coerce_to_string(argument)?;
+                Ok(match get_element_by_id(Some(self.dom_root.clone()), &id) {
+                    Some(node) => RuntimeValue::Object(self.wrap_dom_node(node)),
+                    None => RuntimeValue::Null,
+                })
+            }
+            HostFunction::TextContentGetter => {
+                let node = self.this_dom_node(&this)?;
+                Ok(RuntimeValue::String(get_text_content(node)))
+            }
+            HostFunction::TextContentSetter => {
+                let node = self.this_dom_node(&this)?;
+                // target.textContent = "foobar"; のようにテキストを更新する
+                let text = self.coerce_to_string(argument)?;
+                node.borrow_mut()
+                    .set_first_child(Some(Rc::new(RefCell::new(DomNode::new(
+                        DomNodeKind::Text(text),
+                    )))));
+                Ok(RuntimeValue::Undefined)
+            }
+            HostFunction::IdGetter => {
+                let node = self.this_dom_node(&this)?;
+                let element = RefCell::borrow(&node).get_element();
+                Ok(RuntimeValue::String(
+                    element
+                        .and_then(|e| e.get_attribute("id"))
+                        .unwrap_or_default(),
+                ))
+            }
+            // スクリプトとして、グローバルのスコープで実行する
+            HostFunction::EvalScript => {
+                let source = self.coerce_to_string(argument)?;
+                let program = JsParser::new(JsLexer::new(source))
+                    .parse_ast()
+                    .map_err(|e| Completion::error(ErrorKind::SyntaxError, &format!("{:?}", e)))?;
+                Ok(self
+                    .run_script(&program)?
+                    .unwrap_or(RuntimeValue::Undefined))
+            }
+        }
+    }
+
+    fn is_callable(&self, value: &RuntimeValue) -> bool {
+        match value {
+            RuntimeValue::Object(id) => self.heap.get(*id).is_callable(),
+            _ => false,
+        }
+    }
+
+    /// 関数を呼び出す
+    /// 呼び出しはGCを実行できる位置で、呼び出している間は関数とthisと引数を回収しない
+    fn call(
+        &mut self,
+        function: &RuntimeValue,
+        this: RuntimeValue,
+        arguments: &[RuntimeValue],
+    ) -> Result<RuntimeValue, Completion> {
+        if self.call_depth >= MAX_CALL_DEPTH {
+            return Err(Completion::error(
+                ErrorKind::RangeError,
+                "Maximum call stack size exceeded",
+            ));
+        }
+        let pinned = self.heap.pinned();
+        self.heap.pin(function);
+        self.heap.pin(&this);
+        for argument in arguments {
+            self.heap.pin(argument);
+        }
+        self.collect_if_needed();
+
+        self.call_depth += 1;
+        let result = self.call_callable(function, this, arguments);
+        self.call_depth -= 1;
+        self.heap.unpin(pinned);
+        match &result {
+            Ok(value) => self.heap.pin(value),
+            Err(completion) => self.pin_completion(completion),
+        }
+        result
+    }
+
+    /// 関数の種類に応じて呼び出す
+    fn call_callable(
+        &mut self,
+        function: &RuntimeValue,
+        this: RuntimeValue,
+        arguments: &[RuntimeValue],
+    ) -> Result<RuntimeValue, Completion> {
+        if let RuntimeValue::Object(id) = function {
+            match self.heap.get(*id).kind() {
+                ObjectKind::HostFunction(f) => {
+                    let f = *f;
+                    return self.call_host_function(f, this, arguments);
+                }
+                ObjectKind::Function(closure) => {
+                    let closure = closure.clone();
+                    return self.call_closure(&closure, this, arguments);
+                }
+                ObjectKind::BoundFunction {
+                    target,
+                    this,
+                    arguments: bound_arguments,
+                } => {
+                    let target = RuntimeValue::Object(*target);
+                    let this = this.clone();
+                    let mut bound_arguments = bound_arguments.clone();
+                    bound_arguments.extend_from_slice(arguments);
+                    return self.call(&target, this, &bound_arguments);
+                }
+                _ => {}
+            }
+        }
+        Err(Completion::error(
+            ErrorKind::TypeError,
+            &format!("{} is not a function", function),
+        ))
+    }
+
+    /// スクリプトで定義された関数を呼び出す
+    fn call_closure(
+        &mut self,
+        closure: &Closure,
+        this: RuntimeValue,
+        arguments: &[RuntimeValue],
+    ) -> Result<RuntimeValue, Completion> {
+        // アロー関数は外側のthisを使う
+        let env = if closure.is_arrow {
+            Environment::new(Some(closure.env.clone()))
+        } else {
+            Environment::new_function(Some(closure.env.clone()), this)
+        };
+        let env = Rc::new(RefCell::new(env));
+        if let Some(function) = &closure.code {
+            let result = self.run_code(&function.code, env, arguments)?;
+            return Ok(result.unwrap_or(RuntimeValue::Undefined));
+        }
+        if closure.uses_arguments {
+            let object = self.create_arguments(arguments)?;
+            let mut env = env.borrow_mut();
+            env.declare_lexical("arguments".to_string(), true);
+            env.initialize("arguments".to_string(), RuntimeValue::Object(object));
+        }
+        self.bind_parameters(&closure.params, arguments, env.clone())?;
+
+        let body = match closure.body.as_deref() {
+            Some(Node::BlockStatement { body }) => body,
+            _ => return Ok(RuntimeValue::Undefined),
+        };
+        self.instantiate_declarations(body, env.clone(), true)?;
+
+        // returnで終わった場合はその値、最後まで実行した場合はundefinedになる
+        let pinned = self.heap.pinned();
+        for statement in body {
+            match self.eval(statement, env.clone()) {
+                Ok(_) => {}
+                Err(Completion::Return(value)) => return Ok(value),
+                Err(c) => return Err(c),
+            }
+            self.heap.unpin(pinned);
+        }
+        Ok(RuntimeValue::Undefined)
+    }
+
+    /// 引数を関数のスコープの変数にする
+    /// 足りない引数はundefinedになり、余った引数は無視する
+    fn bind_parameters(
+        &mut self,
+        params: &[Option<Rc<Node>>],
+        arguments: &[RuntimeValue],
+        env: Rc<RefCell<Environment>>,
+    ) -> Result<(), Completion> {
+        // 既定値の式からは、まだ初期化されていない後ろの引数を参照できない
+        for param in params {
+            if let Some(name) = parameter_name(param) {
+                env.borrow_mut().declare_lexical(name.to_string(), true);
+            }
+        }
+
+        for (i, param) in params.iter().enumerate() {
+            let argument = arguments.get(i).cloned().unwrap_or(RuntimeValue::Undefined);
+            let (name, value) = match param.as_deref() {
+                Some(Node::Identifier(name)) => (name, argument),
+                Some(Node::AssignmentPattern { left, right }) => {
+                    let value = match argument {
+                        RuntimeValue::Undefined => self.eval_value(right, env.clone())?,
+                        v => v,
+                    };
+                    match left.as_deref() {
+                        Some(Node::Identifier(name)) => (name, value),
+                        _ => continue,
+                    }
+                }
+                Some(Node::RestElement(argument)) => {
+                    let rest = arguments.get(i..).unwrap_or_default().to_vec();
+                    let array = self.create_array(rest)?;
+                    match argument.as_deref() {
+                        Some(Node::Identifier(name)) => (name, RuntimeValue::Object(array)),
+                        _ => continue,
+                    }
+                }
+                _ => continue,
+            };
+            env.borrow_mut().initialize(name.to_string(), value);
+        }
+        Ok(())
+    }
+
+    /// 文の並びの中の宣言を、実行する前にスコープに作る (hoisting)
+    /// `function_scope`がtrueなら、関数やスクリプト全体の`var`の宣言も含む
+    fn instantiate_declarations(
+        &mut self,
+        body: &[Option<Rc<Node>>],
+        env: Rc<RefCell<Environment>>,
+        function_scope: bool,
+    ) -> Result<(), Completion> {
+        if function_scope {
+            let mut names = Vec::new();
+            for statement in body {
+                var_declared_names(statement, &mut names);
+            }
+            for name in names {
+                env.borrow_mut().declare_var(name);
+            }
+        }
+
+        for statement in body {
+            match statement.as_deref() {
+                Some(Node::VariableDecration { kind, declarations }) if *kind != "var" => {
+                    for declaration in declarations {
+                        if let Some(Node::VariableDeclarator { id, .. }) = declaration.as_deref() {
+                            if let Some(Node::Identifier(name)) = id.as_deref() {
+                                if env.borrow_mut().has_own_binding(name) {
+                                    return Err(Completion::error(
+                                        ErrorKind::SyntaxError,
+                                        &format!("Identifier '{}' has already been declared", name),
+                                    ));
+                                }
+                                env.borrow_mut()
+                                    .declare_lexical(name.to_string(), *kind == "let");
+                            }
+                        }
+                    }
+                }
+                Some(Node::FunctionDeclaration { id, params, body }) => {
+                    if let Some(Node::Identifier(name)) = id.as_deref() {
+                        let function = self.create_function(name, params, body, env.clone(), false);
+                        env.borrow_mut()
+                            .initialize(name.to_string(), RuntimeValue::Object(function));
+                    }
+                }
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+
+    /// 関数のオブジェクトを作る
+    fn create_function(
+        &mut self,
+        name: &str,
+        params: &[Option<Rc<Node>>],
+        body: &Option<Rc<Node>>,
+        env: Rc<RefCell<Environment>>,
+        is_arrow: bool,
+    ) -> ObjectId {
+        let closure = Closure {
+            params: params.to_vec(),
+            body: body.clone(),
+            env,
+            is_arrow,
+            uses_arguments: !is_arrow && uses_arguments(params, body),
+            code: None,
+        };
+        self.allocate_function(closure, name)
+    }
+
+    /// コンパイルした関数のオブジェクトを作る
+    fn create_compiled_function(
+        &mut self,
+        function: &Rc<FunctionCode>,
+        name: &str,
+        env: Rc<RefCell<Environment>>,
+    ) -> ObjectId {
+        // 名前のある関数式は、本体から自分自身を名前で参照できる
+        let scope = if function.self_binding {
+            Rc::new(RefCell::new(Environment::new(Some(env))))
+        } else {
+            env
+        };
+        let closure = Closure {
+            params: function.params.clone(),
+            body: function.body.clone(),
+            env: scope.clone(),
+            is_arrow: function.is_arrow,
+            uses_arguments: function.uses_arguments,
+            code: Some(function.clone()),
+        };
+        let id = self.allocate_function(closure, name);
+        if function.self_binding {
+            scope
+                .borrow_mut()
+                .initialize(name.to_string(), RuntimeValue::Object(id));
+        }
+        id
+    }
+
+    /// 関数のオブジェクトを確保し、lengthとname、prototypeのプロパティを定義する
+    fn allocate_function(&mut self, closure: Closure, name: &str) -> ObjectId {
+        let is_arrow = closure.is_arrow;
+        // lengthは既定値と残余引数より前の引数の数
+        let length = closure
+            .params
+            .iter()
+            .take_while(|p| matches!(p.as_deref(), Some(Node::Identifier(_))))
+            .count();
+        let function = self.heap.allocate(JsObject::new(
+            ObjectKind::Function(Rc::new(closure)),
+            Some(self.intrinsics.function_prototype),
+        ));
+        let object = self.heap.get_mut(function);
+        object.define_own_property(
+            PropertyKey::from("length"),
+            PropertyDescriptor::data(RuntimeValue::Number(length as f64), false, false, true),
+        );
+        object.define_own_property(
+            PropertyKey::from("name"),
+            PropertyDescriptor::data(RuntimeValue::String(name.to_string()), false, false, true),
+        );
+
+        // newで作るオブジェクトのプロトタイプ
+        if !is_arrow {
+            let prototype = self.heap.allocate(JsObject::new(
+                ObjectKind::Ordinary,
+                Some(self.intrinsics.object_prototype),
+            ));
+            self.heap.get_mut(prototype).define_own_property(
+                PropertyKey::from("constructor"),
+                PropertyDescriptor::data(RuntimeValue::Object(function), true, false, true),
+            );
+            self.heap.get_mut(function).define_own_property(
+                PropertyKey::from("prototype"),
+                PropertyDescriptor::data(RuntimeValue::Object(prototype), true, false, false),
+            );
+        }
+        function
+    }
+
+    /// 関数式とアロー関数を評価する。名前のない関数には`name`をつける
+    fn eval_function(
+        &mut self,
+        node: &Option<Rc<Node>>,
+        name: &str,
+        env: Rc<RefCell<Environment>>,
+    ) -> Result<RuntimeValue, Completion> {
+        let function = match node.as_deref() {
+            Some(Node::FunctionExpression { id, params, body }) => match id.as_deref() {
+                // 名前のある関数式は、本体から自分自身を名前で参照できる
+                Some(Node::Identifier(id)) => {
+                    let scope = Rc::new(RefCell::new(Environment::new(Some(env))));
+                    let function = self.create_function(id, params, body, scope.clone(), false);
+                    scope
+                        .borrow_mut()
+                        .initialize(id.to_string(), RuntimeValue::Object(function));
+                    function
+                }
+                _ => self.create_function(name, params, body, env, false),
+            },
+            Some(Node::ArrowFunctionExpression { params, body }) => {
+                self.create_function(name, params, body, env, true)
+            }
+            _ => return self.eval_value(node, env),
+        };
+        Ok(RuntimeValue::Object(function))
+    }
+
+    /// `new`で呼べる関数かどうか
+    fn is_constructor(&self, value: &RuntimeValue) -> bool {
+        let id = match value {
+            RuntimeValue::Object(id) => *id,
+            _ => return false,
+        };
+        match self.heap.get(id).kind() {
+            ObjectKind::Function(closure) => !closure.is_arrow,
+            ObjectKind::HostFunction(HostFunction::Builtin(builtin)) => builtin.is_constructor(),
+            ObjectKind::BoundFunction { target, .. } => {
+                self.is_constructor(&RuntimeValue::Object(*target))
+            }
+            _ => false,
+        }
+    }
+
+    /// [[Construct]]。関数のprototypeをプロトタイプに持つオブジェクトをthisにして呼び出す
+    fn construct(
+        &mut self,
+        function: &RuntimeValue,
+        arguments: &[RuntimeValue],
+    ) -> Result<RuntimeValue, Completion> {
+        let id = match function {
+            RuntimeValue::Object(id) if self.is_constructor(function) => *id,
+            _ => {
+                return Err(Completion::error(
+                    ErrorKind::TypeError,
+                    &format!("{} is not a constructor", function),
+                ))
+            }
+        };
+        match self.heap.get(id).kind() {
+            ObjectKind::HostFunction(HostFunction::Builtin(builtin)) => {
+                let builtin = *builtin;
+                return self.construct_builtin(builtin, arguments);
+            }
+            ObjectKind::BoundFunction {
+                target,
+                arguments: bound_arguments,
+                ..
             } => {
-                format!("HtmlElement: {:#?}", object)
+                let target = RuntimeValue::Object(*target);
+                let mut bound_arguments = bound_arguments.clone();
+                bound_arguments.extend_from_slice(arguments);
+                return self.construct(&target, &bound_arguments);
             }
+            _ => {}
+        }
+
+        let prototype = match self.get(id, &PropertyKey::from("prototype"), function.clone())? {
+            RuntimeValue::Object(prototype) => prototype,
+            _ => self.intrinsics.object_prototype,
         };
-        write!(f, "{}", s)
+        let this = RuntimeValue::Object(
+            self.heap
+                .allocate(JsObject::new(ObjectKind::Ordinary, Some(prototype))),
+        );
+        // オブジェクトを返した場合は、thisの代わりにそれが結果になる
+        match self.call(function, this.clone(), arguments)? {
+            result @ RuntimeValue::Object(_) => Ok(result),
+            _ => Ok(this),
+        }
     }
-}
 
-#[derive(Debug, Clone, PartialEq, Eq)]
-pub struct Function {
-    id: String,
-    params: Vec<Option<Rc<Node>>>,
-    body: Option<Rc<Node>>,
-}
+    /// OrdinaryHasInstance。`instanceof`の結果
+    fn has_instance(
+        &mut self,
+        function: &RuntimeValue,
+        value: &RuntimeValue,
+    ) -> Result<bool, Completion> {
+        let function_id = match function {
+            RuntimeValue::Object(id) if self.is_callable(function) => *id,
+            _ => {
+                return Err(Completion::error(
+                    ErrorKind::TypeError,
+                    "Right-hand side of 'instanceof' is not callable",
+                ))
+            }
+        };
+        let mut current = match value {
+            RuntimeValue::Object(id) => self.heap.get(*id).prototype(),
+            _ => return Ok(false),
+        };
 
-impl Function {
-    fn new(id: String, params: Vec<Option<Rc<Node>>>, body: Option<Rc<Node>>) -> Self {
-        Self { id, params, body }
+        let prototype = match self.get(
+            function_id,
+            &PropertyKey::from("prototype"),
+            function.clone(),
+        )? {
+            RuntimeValue::Object(prototype) => prototype,
+            // ホスト関数はprototypeプロパティを持たない
+            _ if matches!(
+                self.heap.get(function_id).kind(),
+                ObjectKind::HostFunction(_)
+            ) =>
+            {
+                return Ok(false)
+            }
+            _ => {
+                return Err(Completion::error(
+                    ErrorKind::TypeError,
+                    "Function has non-object prototype in instanceof check",
+                ))
+            }
+        };
+        while let Some(id) = current {
+            if id == prototype {
+                return Ok(true);
+            }
+            current = self.heap.get(id).prototype();
+        }
+        Ok(false)
     }
-}
 
-#[derive(Debug, Clone)]
-pub struct Environment {
-    variables: VariableMap,
-    outer: Option<Rc<RefCell<Environment>>>,
-}
+    /// ルートから辿れないオブジェクトを解放し、解放した数を返す
+    /// Rustの変数が持っている評価の途中の値は、ヒープでpinしておく
+    pub fn collect_garbage(&mut self) -> usize {
+        let env = self.env.clone();
+        let intrinsics = self.intrinsics;
+        let vm = &self.vm;
+        let scopes = &self.scopes;
+        let freed = self.heap.collect(|tracer| {
+            RefCell::borrow(&env).trace(tracer);
+            for scope in scopes {
+                RefCell::borrow(scope).trace(tracer);
+            }
+            intrinsics.trace(tracer);
+            vm.trace(tracer);
+        });
+
+        // IDは再利用されるため、次にオブジェクトを確保する前に解放されたラッパーを捨てる
+        let heap = &self.heap;
+        self.dom_wrappers
+            .retain(|(node, id)| heap.is_live(*id) && node.strong_count() > 0);
+        freed
+    }
 
-impl Environment {
-    fn new(outer: Option<Rc<RefCell<Environment>>>) -> Self {
-        Self {
-            variables: VariableMap::new(),
-            outer,
+    /// 前回から十分な数のオブジェクトを確保していたらGCを実行する
+    /// 繰り返しの終わりと関数の呼び出しで呼ぶ
+    pub(super) fn collect_if_needed(&mut self) {
+        if self.heap.should_collect() {
+            self.collect_garbage();
         }
     }
 
-    pub fn get_variable(&self, name: String) -> Option<RuntimeValue> {
-        for variable in &self.variables {
-            if variable.0 == name {
-                return variable.1.clone();
+    /// 文や繰り返しを1つ終えたところでGCを実行する
+    /// `pinned`より後の評価の途中の値は使い終わっている。それまでの文の値`result`は残す
+    fn collect_between(
+        &mut self,
+        pinned: usize,
+        result: &Option<RuntimeValue>,
+        env: &Rc<RefCell<Environment>>,
+    ) {
+        self.heap.unpin(pinned);
+        if let Some(value) = result {
+            self.heap.pin(value);
+        }
+        self.scopes.push(env.clone());
+        self.collect_if_needed();
+        self.scopes.pop();
+    }
+
+    /// 例外やreturnで返す値を、受け取った側の評価が終わるまで回収しないようにする
+    fn pin_completion(&mut self, completion: &Completion) {
+        if let Completion::Throw(value) | Completion::Return(value) = completion {
+            self.heap.pin(value);
+        }
+    }
+
+    /// 値がないものは偽として扱う
+    fn is_truthy(value: &Option<RuntimeValue>) -> bool {
+        value.as_ref().is_some_and(|v| v.to_boolean())
+    }
+
+    /// ToPrimitive
+    /// https://262.ecma-international.org/11.0/#sec-toprimitive
+    fn coerce_to_primitive(
+        &mut self,
+        value: RuntimeValue,
+        hint: PreferredType,
+    ) -> Result<RuntimeValue, Completion> {
+        let id = match value {
+            RuntimeValue::Object(id) => id,
+            v => return Ok(v),
+        };
+        // Dateは、優先する型がない場合は文字列にする
+        let hint = match (hint, self.heap.get(id).kind()) {
+            (PreferredType::Default, ObjectKind::Date(_)) => PreferredType::String,
+            (hint, _) => hint,
+        };
+
+        // OrdinaryToPrimitive。文字列を優先する場合はtoStringを先に呼ぶ
+        let methods = if hint == PreferredType::String {
+            ["toString", "valueOf"]
+        } else {
+            ["valueOf", "toString"]
+        };
+        for name in methods {
+            let method = self.get(id, &PropertyKey::from(name), value.clone())?;
+            if self.is_callable(&method) {
+                let result = self.call(&method, value.clone(), &[])?;
+                if !matches!(result, RuntimeValue::Object(_)) {
+                    return Ok(result);
+                }
             }
         }
-        if let Some(env) = &self.outer {
-            env.borrow_mut().get_variable(name)
+        Err(Completion::error(
+            ErrorKind::TypeError,
+            "Cannot convert object to primitive value",
+        ))
+    }
+
+    /// ToNumber
+    /// https://262.ecma-international.org/11.0/#sec-tonumber
+    fn coerce_to_number(&mut self, value: RuntimeValue) -> Result<f64, Completion> {
+        match value {
+            RuntimeValue::Undefined => Ok(f64::NAN),
+            RuntimeValue::Null => Ok(0.0),
+            RuntimeValue::Boolean(b) => Ok(if b { 1.0 } else { 0.0 }),
+            RuntimeValue::Number(n) => Ok(n),
+            RuntimeValue::String(s) => Ok(string_to_number(&s)),
+            RuntimeValue::Symbol(_) => Err(Completion::error(
+                ErrorKind::TypeError,
+                "Cannot convert a Symbol value to a number",
+            )),
+            RuntimeValue::Object(_) => {
+                let primitive = self.coerce_to_primitive(value, PreferredType::Number)?;
+                self.coerce_to_number(primitive)
+            }
+        }
+    }
+
+    /// ToString
+    /// https://262.ecma-international.org/11.0/#sec-tostring
+    fn coerce_to_string(&mut self, value: RuntimeValue) -> Result<String, Completion> {
+        match value {
+            RuntimeValue::Symbol(_) => Err(Completion::error(
+                ErrorKind::TypeError,
+                "Cannot convert a Symbol value to a string",
+            )),
+            RuntimeValue::Object(_) => {
+                let primitive = self.coerce_to_primitive(value, PreferredType::String)?;
+                self.coerce_to_string(primitive)
+            }
+            v => Ok(v.to_string()),
+        }
+    }
+
+    /// `==`で比べる
+    /// https://262.ecma-international.org/11.0/#sec-abstract-equality-comparison
+    fn loose_equals(&mut self, x: RuntimeValue, y: RuntimeValue) -> Result<bool, Completion> {
+        match (&x, &y) {
+            (
+                RuntimeValue::Undefined | RuntimeValue::Null,
+                RuntimeValue::Undefined | RuntimeValue::Null,
+            ) => Ok(true),
+            (RuntimeValue::Number(_), RuntimeValue::String(s)) => {
+                Ok(x.strict_equals(&RuntimeValue::Number(string_to_number(s))))
+            }
+            (RuntimeValue::String(s), RuntimeValue::Number(_)) => {
+                Ok(RuntimeValue::Number(string_to_number(s)).strict_equals(&y))
+            }
+            (RuntimeValue::Boolean(b), _) => {
+                let x = RuntimeValue::Number(if *b { 1.0 } else { 0.0 });
+                self.loose_equals(x, y)
+            }
+            (_, RuntimeValue::Boolean(b)) => {
+                let y = RuntimeValue::Number(if *b { 1.0 } else { 0.0 });
+                self.loose_equals(x, y)
+            }
+            (
+                RuntimeValue::Number(_) | RuntimeValue::String(_) | RuntimeValue::Symbol(_),
+                RuntimeValue::Object(_),
+            ) => {
+                let y = self.coerce_to_primitive(y, PreferredType::Default)?;
+                self.loose_equals(x, y)
+            }
+            (
+                RuntimeValue::Object(_),
+                RuntimeValue::Number(_) | RuntimeValue::String(_) | RuntimeValue::Symbol(_),
+            ) => {
+                let x = self.coerce_to_primitive(x, PreferredType::Default)?;
+                self.loose_equals(x, y)
+            }
+            _ => Ok(x.strict_equals(&y)),
+        }
+    }
+
+    /// `x < y`を比べる。どちらかがNaNの場合はNone
+    /// `left_first`は、どちらを先にプリミティブ値に変換するか
+    /// https://262.ecma-international.org/11.0/#sec-abstract-relational-comparison
+    fn less_than(
+        &mut self,
+        x: RuntimeValue,
+        y: RuntimeValue,
+        left_first: bool,
+    ) -> Result<Option<bool>, Completion> {
+        let (px, py) = if left_first {
+            let px = self.coerce_to_primitive(x, PreferredType::Number)?;
+            (px, self.coerce_to_primitive(y, PreferredType::Number)?)
         } else {
-            None
+            let py = self.coerce_to_primitive(y, PreferredType::Number)?;
+            (self.coerce_to_primitive(x, PreferredType::Number)?, py)
+        };
+
+        if let (RuntimeValue::String(a), RuntimeValue::String(b)) = (&px, &py) {
+            return Ok(Some(string_less_than(a, b)));
+        }
+        let nx = self.coerce_to_number(px)?;
+        let ny = self.coerce_to_number(py)?;
+        if nx.is_nan() || ny.is_nan() {
+            return Ok(None);
+        }
+        Ok(Some(nx < ny))
+    }
+
+    /// 二項演算子を適用する。複合代入の演算にも使う
+    fn apply_binary_operator(
+        &mut self,
+        operator: &str,
+        left: RuntimeValue,
+        right: RuntimeValue,
+    ) -> Result<RuntimeValue, Completion> {
+        let result = match operator {
+            "+" => {
+                let left = self.coerce_to_primitive(left, PreferredType::Default)?;
+                let right = self.coerce_to_primitive(right, PreferredType::Default)?;
+                // どちらかが文字列なら文字列の連結になる
+                if matches!(left, RuntimeValue::String(_))
+                    || matches!(right, RuntimeValue::String(_))
+                {
+                    let left = self.coerce_to_string(left)?;
+                    RuntimeValue::String(left + &self.coerce_to_string(right)?)
+                } else {
+                    RuntimeValue::Number(
+                        self.coerce_to_number(left)? + self.coerce_to_number(right)?,
+                    )
+                }
+            }
+            "==" => RuntimeValue::Boolean(self.loose_equals(left, right)?),
+            "!=" => RuntimeValue::Boolean(!self.loose_equals(left, right)?),
+            "===" => RuntimeValue::Boolean(left.strict_equals(&right)),
+            "!==" => RuntimeValue::Boolean(!left.strict_equals(&right)),
+            "<" => RuntimeValue::Boolean(self.less_than(left, right, true)? == Some(true)),
+            ">" => RuntimeValue::Boolean(self.less_than(right, left, false)? == Some(true)),
+            "<=" => RuntimeValue::Boolean(self.less_than(right, left, false)? == Some(false)),
+            ">=" => RuntimeValue::Boolean(self.less_than(left, right, true)? == Some(false)),
+            "in" => {
+                let object = match right {
+                    RuntimeValue::Object(id) => id,
+                    v => {
+                        return Err(Completion::error(
+                            ErrorKind::TypeError,
+                            &format!("Cannot use 'in' operator to search for a key in {}", v),
+                        ))
+                    }
+                };
+                let key = self.coerce_to_property_key(left)?;
+                RuntimeValue::Boolean(self.has_property(object, &key))
+            }
+            "instanceof" => RuntimeValue::Boolean(self.has_instance(&right, &left)?),
+            _ => {
+                let l = self.coerce_to_number(left)?;
+                let r = self.coerce_to_number(right)?;
+                let n = match operator {
+                    "-" => l - r,
+                    "*" => l * r,
+                    "/" => l / r,
+                    "%" => l % r,
+                    "**" => pow(l, r),
+                    "<<" => to_int32(l).wrapping_shl(to_uint32(r) & 31) as f64,
+                    ">>" => (to_int32(l) >> (to_uint32(r) & 31)) as f64,
+                    ">>>" => (to_uint32(l) >> (to_uint32(r) & 31)) as f64,
+                    "&" => (to_int32(l) & to_int32(r)) as f64,
+                    "|" => (to_int32(l) | to_int32(r)) as f64,
+                    "^" => (to_int32(l) ^ to_int32(r)) as f64,
+                    _ => f64::NAN,
+                };
+                RuntimeValue::Number(n)
+            }
+        };
+        Ok(result)
+    }
+
+    /// 文字列などのプリミティブ値のプロパティを探し始めるオブジェクト
+    fn primitive_prototype(&self, value: &RuntimeValue) -> ObjectId {
+        match value {
+            RuntimeValue::String(_) => self.intrinsics.string_prototype,
+            RuntimeValue::Number(_) => self.intrinsics.number_prototype,
+            RuntimeValue::Boolean(_) => self.intrinsics.boolean_prototype,
+            RuntimeValue::Symbol(_) => self.intrinsics.symbol_prototype,
+            _ => self.intrinsics.object_prototype,
         }
     }
 
-    fn add_variable(&mut self, name: String, value: Option<RuntimeValue>) {
-        self.variables.push((name, value));
+    /// ToObject。プリミティブ値は包んだオブジェクトにする
+    fn coerce_to_object(&mut self, value: &RuntimeValue) -> Result<ObjectId, Completion> {
+        match value {
+            RuntimeValue::Object(id) => Ok(*id),
+            RuntimeValue::Undefined | RuntimeValue::Null => Err(Completion::error(
+                ErrorKind::TypeError,
+                "Cannot convert undefined or null to object",
+            )),
+            v => Ok(self.create_primitive_wrapper(v.clone())),
+        }
     }
 
-    fn update_variable(&mut self, name: String, value: Option<RuntimeValue>) {
-        for i in 0..self.variables.len() {
-            if self.variables[i].0 == name {
-                self.variables.remove(i);
-                self.variables.push((name, value));
-                return;
+    /// プリミティブ値を包んだオブジェクトを作る
+    /// Stringのオブジェクトは、文字列の長さと各文字を読み取り専用のプロパティとして持つ
+    fn create_primitive_wrapper(&mut self, value: RuntimeValue) -> ObjectId {
+        let prototype = self.primitive_prototype(&value);
+        let id = self.heap.allocate(JsObject::new(
+            ObjectKind::Primitive(value.clone()),
+            Some(prototype),
+        ));
+        if let RuntimeValue::String(s) = value {
+            let object = self.heap.get_mut(id);
+            for (i, unit) in s.encode_utf16().enumerate() {
+                object.define_own_property(
+                    PropertyKey::String(i.to_string()),
+                    PropertyDescriptor::data(
+                        RuntimeValue::String(String::from_utf16_lossy(&[unit])),
+                        false,
+                        true,
+                        false,
+                    ),
+                );
             }
+            object.define_own_property(
+                PropertyKey::from("length"),
+                PropertyDescriptor::data(
+                    RuntimeValue::Number(string_length(&s) as f64),
+                    false,
+                    false,
+                    false,
+                ),
+            );
         }
+        id
     }
-}
 
-#[derive(Debug, Clone)]
-pub struct JsRuntime {
-    dom_root: Rc<RefCell<DomNode>>,
-    functions: Vec<Function>,
-    env: Rc<RefCell<Environment>>,
-}
+    /// [[Get]]。`receiver`はゲッターのthisになる
+    fn get(
+        &mut self,
+        object: ObjectId,
+        key: &PropertyKey,
+        receiver: RuntimeValue,
+    ) -> Result<RuntimeValue, Completion> {
+        let mut current = Some(object);
+        while let Some(id) = current {
+            let object = self.heap.get(id);
+            match object.get_own_property(key).map(|p| p.value().clone()) {
+                Some(PropertyValue::Data { value, .. }) => {
+                    // 取り出した後でプロパティが消されても、使い終わるまでは回収しない
+                    self.heap.pin(&value);
+                    return Ok(value);
+                }
+                Some(PropertyValue::Accessor { get, .. }) => {
+                    if let RuntimeValue::Undefined = get {
+                        return Ok(RuntimeValue::Undefined);
+                    }
+                    return self.call(&get, receiver, &[]);
+                }
+                None => current = object.prototype(),
+            }
+        }
+        Ok(RuntimeValue::Undefined)
+    }
 
-impl JsRuntime {
-    pub fn new(dom_root: Rc<RefCell<DomNode>>) -> Self {
-        Self {
-            dom_root,
-            functions: Vec::new(),
-            env: Rc::new(RefCell::new(Environment::new(None))),
+    /// [[DefineOwnProperty]]。配列のlengthに入れる値はここで確かめる
+    fn define_own_property(
+        &mut self,
+        object: ObjectId,
+        key: PropertyKey,
+        mut desc: PropertyDescriptor,
+    ) -> Result<bool, Completion> {
+        if let ObjectKind::Array = self.heap.get(object).kind() {
+            if let (PropertyKey::String(k), Some(value)) = (&key, &desc.value) {
+                if k == "length" {
+                    let n = self.coerce_to_number(value.clone())?;
+                    if to_uint32(n) as f64 != n {
+                        return Err(Completion::error(
+                            ErrorKind::RangeError,
+                            "Invalid array length",
+                        ));
+                    }
+                    desc.value = Some(RuntimeValue::Number(n));
+                }
+            }
         }
+        Ok(self.heap.get_mut(object).define_own_property(key, desc))
     }
 
-    /**
-     *  bool: ブラウザAPIが呼ばれたかどうか
-     *  Option<RuntimeValue>: ブラウザAPIの呼び出しによって得られた結果
-     */
-    fn call_browser_api(
+    /// CreateDataProperty
+    fn create_data_property(
         &mut self,
-        func: &RuntimeValue,
-        arguments: &[Option<Rc<Node>>],
+        object: ObjectId,
+        key: PropertyKey,
+        value: RuntimeValue,
+    ) -> Result<bool, Completion> {
+        self.define_own_property(
+            object,
+            key,
+            PropertyDescriptor::data(value, true, true, true),
+        )
+    }
+
+    /// [[Set]]。書き込めなかった場合はfalse
+    /// https://262.ecma-international.org/11.0/#sec-ordinaryset
+    fn set(
+        &mut self,
+        object: ObjectId,
+        key: PropertyKey,
+        value: RuntimeValue,
+        receiver: RuntimeValue,
+    ) -> Result<bool, Completion> {
+        // プロトタイプチェーンから、同じ名前のプロパティを探す
+        let mut current = Some(object);
+        let mut found = None;
+        while let Some(id) = current {
+            let object = self.heap.get(id);
+            if let Some(property) = object.get_own_property(&key) {
+                found = Some(property.value().clone());
+                break;
+            }
+            current = object.prototype();
+        }
+
+        match found {
+            Some(PropertyValue::Accessor { set, .. }) => {
+                if let RuntimeValue::Undefined = set {
+                    return Ok(false);
+                }
+                self.call(&set, receiver, &[value])?;
+                Ok(true)
+            }
+            Some(PropertyValue::Data {
+                writable: false, ..
+            }) => Ok(false),
+            _ => {
+                let receiver = match receiver {
+                    RuntimeValue::Object(id) => id,
+                    _ => return Ok(false),
+                };
+                let desc = match self.heap.get(receiver).get_own_property(&key) {
+                    Some(property) if property.writable() => PropertyDescriptor {
+                        value: Some(value),
+                        ..Default::default()
+                    },
+                    Some(_) => return Ok(false),
+                    None => PropertyDescriptor::data(value, true, true, true),
+                };
+                self.define_own_property(receiver, key, desc)
+            }
+        }
+    }
+
+    /// [[HasProperty]]
+    fn has_property(&self, object: ObjectId, key: &PropertyKey) -> bool {
+        let mut current = Some(object);
+        while let Some(id) = current {
+            let object = self.heap.get(id);
+            if object.get_own_property(key).is_some() {
+                return true;
+            }
+            current = object.prototype();
+        }
+        false
+    }
+
+    /// 文字列が持つlengthと添字のプロパティ
+    fn string_own_property(s: &str, key: &PropertyKey) -> Option<RuntimeValue> {
+        if *key == PropertyKey::from("length") {
+            return Some(RuntimeValue::Number(string_length(s) as f64));
+        }
+        key.array_index()
+            .and_then(|i| string_code_unit_at(s, i as usize))
+            .map(RuntimeValue::String)
+    }
+
+    /// 値のプロパティを読む。プリミティブ値はプロトタイプのプロパティを読む
+    fn get_property(
+        &mut self,
+        base: &RuntimeValue,
+        key: &PropertyKey,
+    ) -> Result<RuntimeValue, Completion> {
+        match base {
+            RuntimeValue::Undefined | RuntimeValue::Null => Err(Completion::error(
+                ErrorKind::TypeError,
+                &format!("Cannot read property '{}' of {}", key, base),
+            )),
+            RuntimeValue::Object(id) => self.get(*id, key, base.clone()),
+            _ => {
+                if let RuntimeValue::String(s) = base {
+                    if let Some(value) = Self::string_own_property(s, key) {
+                        return Ok(value);
+                    }
+                }
+                let prototype = self.primitive_prototype(base);
+                self.get(prototype, key, base.clone())
+            }
+        }
+    }
+
+    /// 値のプロパティに書き込む。書き込めなかった場合は何もしない
+    fn set_property(
+        &mut self,
+        base: &RuntimeValue,
+        key: PropertyKey,
+        value: RuntimeValue,
+    ) -> Result<(), Completion> {
+        match base {
+            RuntimeValue::Undefined | RuntimeValue::Null => Err(Completion::error(
+                ErrorKind::TypeError,
+                &format!("Cannot set property '{}' of {}", key, base),
+            )),
+            RuntimeValue::Object(id) => {
+                self.set(*id, key, value, base.clone())?;
+                Ok(())
+            }
+            _ => {
+                // プリミティブ値にはプロパティを追加できないが、セッターは呼ばれる
+                let prototype = self.primitive_prototype(base);
+                self.set(prototype, key, value, base.clone())?;
+                Ok(())
+            }
+        }
+    }
+
+    /// delete演算子でプロパティを削除する
+    fn delete_property(
+        &mut self,
+        base: &RuntimeValue,
+        key: &PropertyKey,
+    ) -> Result<bool, Completion> {
+        match base {
+            RuntimeValue::Undefined | RuntimeValue::Null => Err(Completion::error(
+                ErrorKind::TypeError,
+                &format!("Cannot delete property '{}' of {}", key, base),
+            )),
+            RuntimeValue::Object(id) => Ok(self.heap.get_mut(*id).delete(key)),
+            RuntimeValue::String(s) => Ok(Self::string_own_property(s, key).is_none()),
+            _ => Ok(true),
+        }
+    }
+
+    /// ToPropertyKey
+    fn coerce_to_property_key(&mut self, value: RuntimeValue) -> Result<PropertyKey, Completion> {
+        match self.coerce_to_primitive(value, PreferredType::String)? {
+            RuntimeValue::Symbol(symbol) => Ok(PropertyKey::Symbol(symbol)),
+            v => Ok(PropertyKey::String(self.coerce_to_string(v)?)),
+        }
+    }
+
+    /// `a.b`と`a[b]`、オブジェクトリテラルのプロパティ名
+    fn property_key(
+        &mut self,
+        property: &Option<Rc<Node>>,
+        computed: bool,
         env: Rc<RefCell<Environment>>,
-    ) -> (bool, Option<RuntimeValue>) {
-        if func == &RuntimeValue::StringLiteral("document.getElementById".to_string()) {
-            let arg = match self.eval(&arguments[0], env.clone()) {
-                Some(a) => a,
-                None => return (true, None),
-            };
-            let target = match get_element_by_id(Some(self.dom_root.clone()), &arg.to_string()) {
-                Some(n) => n,
-                None => return (true, None),
-            };
-            return (
+    ) -> Result<PropertyKey, Completion> {
+        if !computed {
+            if let Some(Node::Identifier(name)) = property.as_deref() {
+                return Ok(PropertyKey::String(name.clone()));
+            }
+        }
+        let key = self.eval_value(property, env)?;
+        self.coerce_to_property_key(key)
+    }
+
+    /// 代入先の式を評価する
+    fn eval_reference(
+        &mut self,
+        target: &Option<Rc<Node>>,
+        env: Rc<RefCell<Environment>>,
+    ) -> Result<Reference, Completion> {
+        match target.as_deref() {
+            Some(Node::Identifier(name)) => Ok(Reference::Binding(name.clone())),
+            Some(Node::MemberExpression {
+                object,
+                property,
+                computed,
+                ..
+            }) => {
+                let base = self.eval_value(object, env.clone())?;
+                let key = self.property_key(property, *computed, env)?;
+                Ok(Reference::Property { base, key })
+            }
+            // 構文解析の時点で弾かれている
+            _ => Err(Completion::error(
+                ErrorKind::ReferenceError,
+                "Invalid left-hand side in assignment",
+            )),
+        }
+    }
+
+    /// 変数を読み書きできなかったときに投げる例外
+    fn binding_error(name: &str, error: BindingError) -> Completion {
+        match error {
+            BindingError::NotDefined => Completion::error(
+                ErrorKind::ReferenceError,
+                &format!("{} is not defined", name),
+            ),
+            BindingError::Uninitialized => Completion::error(
+                ErrorKind::ReferenceError,
+                &format!("Cannot access '{}' before initialization", name),
+            ),
+            BindingError::Immutable => {
+                Completion::error(ErrorKind::TypeError, "Assignment to constant variable.")
+            }
+        }
+    }
+
+    fn get_value(
+        &mut self,
+        reference: &Reference,
+        env: Rc<RefCell<Environment>>,
+    ) -> Result<RuntimeValue, Completion> {
+        match reference {
+            Reference::Binding(name) => {
+                let value = RefCell::borrow(&env).get_binding(name);
+                value.map_err(|e| Self::binding_error(name, e))
+            }
+            Reference::Property { base, key } => self.get_property(base, key),
+        }
+    }
+
+    fn put_value(
+        &mut self,
+        reference: Reference,
+        value: RuntimeValue,
+        env: Rc<RefCell<Environment>>,
+    ) -> Result<(), Completion> {
+        match reference {
+            Reference::Binding(name) => {
+                let result = env.borrow_mut().set_binding(&name, value.clone());
+                match result {
+                    // 宣言されていない変数への代入は、グローバル変数を作る
+                    Err(BindingError::NotDefined) => {
+                        self.env.borrow_mut().initialize(name, value);
+                        Ok(())
+                    }
+                    Err(e) => Err(Self::binding_error(&name, e)),
+                    Ok(()) => Ok(()),
+                }
+            }
+            Reference::Property { base, key } => self.set_property(&base, key, value),
+        }
+    }
+
+    /// 変数かプロパティに値を代入する
+    fn assign(
+        &mut self,
+        target: &Option<Rc<Node>>,
+        value: RuntimeValue,
+        env: Rc<RefCell<Environment>>,
+    ) -> Result<(), Completion> {
+        let reference = self.eval_reference(target, env.clone())?;
+        self.put_value(reference, value, env)
+    }
+
+    /// 配列のlengthを読む
+    fn length_of(&mut self, object: ObjectId) -> Result<usize, Completion> {
+        let length = self.get(
+            object,
+            &PropertyKey::from("length"),
+            RuntimeValue::Object(object),
+        )?;
+        Ok(to_uint32(self.coerce_to_number(length)?) as usize)
+    }
+
+    /// for-inで列挙するプロパティ名。プロトタイプのプロパティも含む
+    fn enumerable_keys(&mut self, value: &RuntimeValue) -> Vec<RuntimeValue> {
+        let mut keys = Vec::new();
+        let mut visited = Vec::new();
+        let mut current = match value {
+            RuntimeValue::Object(id) => Some(*id),
+            RuntimeValue::String(s) => {
+                for i in 0..string_length(s) {
+                    visited.push(PropertyKey::String(i.to_string()));
+                    keys.push(RuntimeValue::String(i.to_string()));
+                }
+                Some(self.primitive_prototype(value))
+            }
+            RuntimeValue::Undefined | RuntimeValue::Null => None,
+            _ => Some(self.primitive_prototype(value)),
+        };
+
+        while let Some(id) = current {
+            let object = self.heap.get(id);
+            for key in object.own_property_keys() {
+                // 手前のオブジェクトにある同じ名前のプロパティで隠される
+                if matches!(key, PropertyKey::Symbol(_)) || visited.contains(&key) {
+                    continue;
+                }
+                let enumerable = object
+                    .get_own_property(&key)
+                    .is_some_and(|p| p.enumerable());
+                if let (true, PropertyKey::String(s)) = (enumerable, &key) {
+                    keys.push(RuntimeValue::String(s.clone()));
+                }
+                visited.push(key);
+            }
+            current = object.prototype();
+        }
+        keys
+    }
+
+    /// for-ofやスプレッド構文で取り出す値
+    /// 配列の要素、文字列の文字、Mapの[キー, 値]の組とSetの値だけを繰り返す
+    fn iterate(&mut self, value: RuntimeValue) -> Result<Vec<RuntimeValue>, Completion> {
+        if let RuntimeValue::Object(id) = value {
+            match self.heap.get(id).kind().clone() {
+                ObjectKind::Map(entries) => {
+                    let mut values = Vec::new();
+                    for (key, value) in entries.into_iter().flatten() {
+                        values.push(RuntimeValue::Object(self.create_array(vec![key, value])?));
+                    }
+                    return Ok(values);
+                }
+                ObjectKind::Set(values) => {
+                    let values = values.into_iter().flatten().collect::<Vec<_>>();
+                    for value in &values {
+                        self.heap.pin(value);
+                    }
+                    return Ok(values);
+                }
+                _ => {}
+            }
+        }
+        match value {
+            RuntimeValue::String(s) => Ok(s
+                .chars()
+                .map(|c| RuntimeValue::String(c.to_string()))
+                .collect()),
+            RuntimeValue::Object(id)
+                if matches!(
+                    self.heap.get(id).kind(),
+                    ObjectKind::Array | ObjectKind::Arguments
+                ) =>
+            {
+                let mut values = Vec::new();
+                let mut i = 0;
+                // 繰り返しの途中で追加された要素も取り出す
+                while i < self.length_of(id)? {
+                    values.push(self.get(
+                        id,
+                        &PropertyKey::String(i.to_string()),
+                        RuntimeValue::Object(id),
+                    )?);
+                    i += 1;
+                }
+                Ok(values)
+            }
+            v => Err(Completion::error(
+                ErrorKind::TypeError,
+                &format!("{} is not iterable", v),
+            )),
+        }
+    }
+
+    /// 関数の`arguments`のオブジェクトを作る
+    fn create_arguments(&mut self, arguments: &[RuntimeValue]) -> Result<ObjectId, Completion> {
+        let object = self.heap.allocate(JsObject::new(
+            ObjectKind::Arguments,
+            Some(self.intrinsics.object_prototype),
+        ));
+        for (i, value) in arguments.iter().enumerate() {
+            self.create_data_property(object, PropertyKey::String(i.to_string()), value.clone())?;
+        }
+        self.heap.get_mut(object).define_own_property(
+            PropertyKey::from("length"),
+            PropertyDescriptor::data(
+                RuntimeValue::Number(arguments.len() as f64),
                 true,
-                Some(RuntimeValue::HtmlElement {
-                    object: target,
-                    property: None,
-                }),
-            );
+                false,
+                true,
+            ),
+        );
+        Ok(object)
+    }
+
+    /// 要素を持つ配列を作る
+    fn create_array(&mut self, values: Vec<RuntimeValue>) -> Result<ObjectId, Completion> {
+        let array = self.heap.allocate(JsObject::new(
+            ObjectKind::Array,
+            Some(self.intrinsics.array_prototype),
+        ));
+        for (i, value) in values.into_iter().enumerate() {
+            self.create_data_property(array, PropertyKey::String(i.to_string()), value)?;
         }
+        Ok(array)
+    }
+
+    /// CopyDataProperties。`{...source}`のように列挙できる自身のプロパティを写す
+    fn copy_data_properties(
+        &mut self,
+        target: ObjectId,
+        source: RuntimeValue,
+    ) -> Result<(), Completion> {
+        let id = match source {
+            RuntimeValue::Object(id) => id,
+            RuntimeValue::String(s) => {
+                for (i, c) in s.chars().enumerate() {
+                    self.create_data_property(
+                        target,
+                        PropertyKey::String(i.to_string()),
+                        RuntimeValue::String(c.to_string()),
+                    )?;
+                }
+                return Ok(());
+            }
+            _ => return Ok(()),
+        };
+
+        for key in self.heap.get(id).own_property_keys() {
+            let enumerable = self
+                .heap
+                .get(id)
+                .get_own_property(&key)
+                .is_some_and(|p| p.enumerable());
+            if enumerable {
+                let value = self.get(id, &key, RuntimeValue::Object(id))?;
+                self.create_data_property(target, key, value)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// 繰り返しの本体を実行し、次の繰り返しに進むかどうかを返す
+    fn eval_loop_body(
+        &mut self,
+        body: &Option<Rc<Node>>,
+        labels: &[String],
+        result: &mut Option<RuntimeValue>,
+        env: Rc<RefCell<Environment>>,
+    ) -> Result<bool, Completion> {
+        match self.eval(body, env) {
+            Ok(value) => {
+                if value.is_some() {
+                    *result = value;
+                }
+                Ok(true)
+            }
+            Err(Completion::Continue(None)) => Ok(true),
+            Err(Completion::Continue(Some(label))) if labels.contains(&label) => Ok(true),
+            Err(Completion::Break(None)) => Ok(false),
+            Err(c) => Err(c),
+        }
+    }
+
+    /// 繰り返しの文を実行する。`labels`はその文についたラベル
+    fn eval_iteration(
+        &mut self,
+        node: &Rc<Node>,
+        labels: &[String],
+        env: Rc<RefCell<Environment>>,
+    ) -> Result<Option<RuntimeValue>, Completion> {
+        let mut result = None;
+
+        match node.borrow() {
+            Node::WhileStatement { test, body } => {
+                let pinned = self.heap.pinned();
+                while Self::is_truthy(&self.eval(test, env.clone())?) {
+                    if !self.eval_loop_body(body, labels, &mut result, env.clone())? {
+                        break;
+                    }
+                    self.collect_between(pinned, &result, &env);
+                }
+            }
+            Node::DoWhileStatement { body, test } => {
+                let pinned = self.heap.pinned();
+                loop {
+                    if !self.eval_loop_body(body, labels, &mut result, env.clone())? {
+                        break;
+                    }
+                    if !Self::is_truthy(&self.eval(test, env.clone())?) {
+                        break;
+                    }
+                    self.collect_between(pinned, &result, &env);
+                }
+            }
+            Node::ForStatement {
+                init,
+                test,
+                update,
+                body,
+            } => {
+                // `let`で宣言した変数は、for文のスコープに作る
+                let lexical = matches!(
+                    init.as_deref(),
+                    Some(Node::VariableDecration { kind, .. }) if *kind != "var"
+                );
+                let mut env = env;
+                if lexical {
+                    env = Rc::new(RefCell::new(Environment::new(Some(env))));
+                    self.instantiate_declarations(core::slice::from_ref(init), env.clone(), false)?;
+                }
+
+                self.eval(init, env.clone())?;
+                // 繰り返しごとに変数を作り直し、クロージャがそれぞれの値を捕捉できるようにする
+                // 更新式は次の繰り返しのスコープで評価する
+                let next_iteration = |env: Rc<RefCell<Environment>>| {
+                    if lexical {
+                        Rc::new(RefCell::new(RefCell::borrow(&env).clone()))
+                    } else {
+                        env
+                    }
+                };
+                env = next_iteration(env);
+                let pinned = self.heap.pinned();
+                loop {
+                    if test.is_some() && !Self::is_truthy(&self.eval(test, env.clone())?) {
+                        break;
+                    }
+                    if !self.eval_loop_body(body, labels, &mut result, env.clone())? {
+                        break;
+                    }
+                    env = next_iteration(env);
+                    self.eval(update, env.clone())?;
+                    self.collect_between(pinned, &result, &env);
+                }
+            }
+            Node::ForInStatement { left, right, body }
+            | Node::ForOfStatement { left, right, body } => {
+                let value = self.eval_value(right, env.clone())?;
+                let values = if let Node::ForInStatement { .. } = node.borrow() {
+                    self.enumerable_keys(&value)
+                } else {
+                    self.iterate(value)?
+                };
+
+                // `var x`の形なら変数を宣言してから、その変数に代入していく
+                // `let x`と`const x`の形なら、繰り返しごとに新しいスコープに変数を作る
+                let (target, lexical) = match left.as_deref() {
+                    Some(Node::VariableDecration { kind, declarations }) => {
+                        let id = match declarations.first().and_then(|d| d.as_deref()) {
+                            Some(Node::VariableDeclarator { id, .. }) => id.clone(),
+                            _ => None,
+                        };
+                        if *kind == "var" {
+                            self.eval(left, env.clone())?;
+                            (id, None)
+                        } else {
+                            (id, Some(*kind))
+                        }
+                    }
+                    _ => (left.clone(), None),
+                };
+
+                let pinned = self.heap.pinned();
+                for value in values {
+                    let iteration_env = match (lexical, target.as_deref()) {
+                        (Some(kind), Some(Node::Identifier(name))) => {
+                            let mut scope = Environment::new(Some(env.clone()));
+                            scope.declare_lexical(name.to_string(), kind == "let");
+                            scope.initialize(name.to_string(), value);
+                            Rc::new(RefCell::new(scope))
+                        }
+                        _ => {
+                            self.assign(&target, value, env.clone())?;
+                            env.clone()
+                        }
+                    };
+                    if !self.eval_loop_body(body, labels, &mut result, iteration_env)? {
+                        break;
+                    }
+                    self.collect_between(pinned, &result, &env);
+                }
+            }
+            _ => return self.eval(&Some(node.clone()), env),
+        }
+
+        Ok(result)
+    }
+
+    /// 式を評価する。値がない場合はundefinedになる
+    fn eval_value(
+        &mut self,
+        node: &Option<Rc<Node>>,
+        env: Rc<RefCell<Environment>>,
+    ) -> Result<RuntimeValue, Completion> {
+        Ok(self.eval(node, env)?.unwrap_or(RuntimeValue::Undefined))
+    }
+
+    /// 引数を評価する。`...a`は展開する
+    fn eval_arguments(
+        &mut self,
+        arguments: &[Option<Rc<Node>>],
+        env: Rc<RefCell<Environment>>,
+    ) -> Result<Vec<RuntimeValue>, Completion> {
+        let mut values = Vec::new();
+        for argument in arguments {
+            match argument.as_deref() {
+                Some(Node::SpreadElement(argument)) => {
+                    let value = self.eval_value(argument, env.clone())?;
+                    values.extend(self.iterate(value)?);
+                }
+                _ => values.push(self.eval_value(argument, env.clone())?),
+            }
+        }
+        Ok(values)
+    }
 
-        (false, None)
+    /// 関数を呼び出す
+    fn eval_call(
+        &mut self,
+        callee: &Option<Rc<Node>>,
+        arguments: &[Option<Rc<Node>>],
+        optional: bool,
+        env: Rc<RefCell<Environment>>,
+    ) -> Result<Option<RuntimeValue>, Completion> {
+        // `a.b()`の形なら、aがthisになる
+        let (function, this) = match callee.as_deref() {
+            Some(Node::MemberExpression {
+                object,
+                property,
+                computed,
+                optional: member_optional,
+            }) => {
+                let base = match self.eval(object, env.clone())? {
+                    Some(value) => value,
+                    None => return Ok(None),
+                };
+                if *member_optional && base.is_nullish() {
+                    return Ok(None);
+                }
+                let key = self.property_key(property, *computed, env.clone())?;
+                (self.get_property(&base, &key)?, base)
+            }
+            _ => match self.eval(callee, env.clone())? {
+                Some(value) => (value, RuntimeValue::Undefined),
+                None => return Ok(None),
+            },
+        };
+        if optional && function.is_nullish() {
+            return Ok(None);
+        }
+
+        let arguments = self.eval_arguments(arguments, env)?;
+        if !self.is_callable(&function) {
+            return Err(Completion::error(
+                ErrorKind::TypeError,
+                &format!("{} is not a function", function),
+            ));
+        }
+        self.call(&function, this, &arguments).map(Some)
     }
 
+    /// 式や文を評価する。値のない文はNoneを返す
+    /// 式がNoneを返すのは、`?.`の左側がnullかundefinedでチェーンの評価をやめたときだけ
+    /// 評価している間は`env`と途中の値を回収せず、終わったら結果だけを残す
     fn eval(
         &mut self,
         node: &Option<Rc<Node>>,
         env: Rc<RefCell<Environment>>,
-    ) -> Option<RuntimeValue> {
+    ) -> Result<Option<RuntimeValue>, Completion> {
+        let pinned = self.heap.pinned();
+        let scopes = self.scopes.len();
+        if !self.scopes.last().is_some_and(|s| Rc::ptr_eq(s, &env)) {
+            self.scopes.push(env.clone());
+        }
+
+        let result = self.eval_node(node, env);
+        self.heap.unpin(pinned);
+        self.scopes.truncate(scopes);
+        match &result {
+            Ok(Some(value)) => self.heap.pin(value),
+            Ok(None) => {}
+            Err(completion) => self.pin_completion(completion),
+        }
+        result
+    }
+
+    fn eval_node(
+        &mut self,
+        node: &Option<Rc<Node>>,
+        env: Rc<RefCell<Environment>>,
+    ) -> Result<Option<RuntimeValue>, Completion> {
         let node = match node {
             Some(n) => n,
-            None => return None,
+            None => return Ok(None),
         };
 
         match node.borrow() {
-            Node::ExpressionStatement(expr) => return self.eval(&expr, env.clone()),
-            Node::AdditiveExpression {
+            Node::ExpressionStatement(expr) => Ok(Some(self.eval_value(expr, env.clone())?)),
+            Node::BinaryExpression {
                 operator,
                 left,
                 right,
             } => {
-                let left_value = match self.eval(&left, env.clone()) {
-                    Some(value) => value,
-                    None => return None,
+                let left_value = self.eval_value(left, env.clone())?;
+                let right_value = self.eval_value(right, env.clone())?;
+                Ok(Some(self.apply_binary_operator(
+                    operator,
+                    left_value,
+                    right_value,
+                )?))
+            }
+            Node::UnaryExpression { operator, argument } => {
+                let value = match *operator {
+                    "typeof" => {
+                        // 宣言されていない変数もReferenceErrorにしない
+                        if let Some(Node::Identifier(name)) = argument.as_deref() {
+                            let binding = RefCell::borrow(&env).get_binding(name);
+                            if binding == Err(BindingError::NotDefined) {
+                                return Ok(Some(RuntimeValue::String("undefined".to_string())));
+                            }
+                        }
+                        let value = self.eval_value(argument, env.clone())?;
+                        RuntimeValue::String(value.type_of(&self.heap).to_string())
+                    }
+                    "delete" => match argument.as_deref() {
+                        Some(Node::MemberExpression { .. }) => {
+                            match self.eval_reference(argument, env.clone())? {
+                                Reference::Property { base, key } => {
+                                    RuntimeValue::Boolean(self.delete_property(&base, &key)?)
+                                }
+                                Reference::Binding(_) => RuntimeValue::Boolean(true),
+                            }
+                        }
+                        // 宣言された変数は削除できない
+                        Some(Node::Identifier(_)) => RuntimeValue::Boolean(false),
+                        _ => {
+                            self.eval(argument, env.clone())?;
+                            RuntimeValue::Boolean(true)
+                        }
+                    },
+                    "void" => {
+                        self.eval(argument, env.clone())?;
+                        RuntimeValue::Undefined
+                    }
+                    "!" => {
+                        RuntimeValue::Boolean(!self.eval_value(argument, env.clone())?.to_boolean())
+                    }
+                    _ => {
+                        let value = self.eval_value(argument, env.clone())?;
+                        let n = self.coerce_to_number(value)?;
+                        match *operator {
+                            "-" => RuntimeValue::Number(-n),
+                            "~" => RuntimeValue::Number(!to_int32(n) as f64),
+                            _ => RuntimeValue::Number(n),
+                        }
+                    }
                 };
-                let right_value = match self.eval(&right, env.clone()) {
-                    Some(value) => value,
-                    None => return None,
+                Ok(Some(value))
+            }
+            Node::UpdateExpression {
+                operator,
+                prefix,
+                argument,
+            } => {
+                let reference = self.eval_reference(argument, env.clone())?;
+                let old_value = self.get_value(&reference, env.clone())?;
+                let old_value = self.coerce_to_number(old_value)?;
+                let new_value = if *operator == "++" {
+                    old_value + 1.0
+                } else {
+                    old_value - 1.0
                 };
-
-                if operator == &'+' {
-                    Some(left_value + right_value)
-                } else if operator == &'-' {
-                    Some(left_value - right_value)
+                self.put_value(reference, RuntimeValue::Number(new_value), env)?;
+                Ok(Some(RuntimeValue::Number(if *prefix {
+                    new_value
                 } else {
-                    None
-                }
+                    old_value
+                })))
             }
             Node::AssignmentExpression {
                 operator,
                 left,
                 right,
             } => {
-                if operator != &'=' {
-                    return None;
+                let reference = self.eval_reference(left, env.clone())?;
+                let value = if *operator == "=" {
+                    // `f = function () {}`の関数の名前はf
+                    match &reference {
+                        Reference::Binding(name) => self.eval_function(right, name, env.clone())?,
+                        _ => self.eval_value(right, env.clone())?,
+                    }
+                } else {
+                    // `a += b`は`a = a + b`として計算する
+                    let current = self.get_value(&reference, env.clone())?;
+                    let right_value = self.eval_value(right, env.clone())?;
+                    self.apply_binary_operator(
+                        operator.trim_end_matches('='),
+                        current,
+                        right_value,
+                    )?
+                };
+                self.put_value(reference, value.clone(), env)?;
+                Ok(Some(value))
+            }
+            Node::MemberExpression {
+                object,
+                property,
+                computed,
+                optional,
+            } => {
+                let base = match self.eval(object, env.clone())? {
+                    Some(value) => value,
+                    None => return Ok(None),
+                };
+                // `?.`の左側がnullかundefinedなら、チェーン全体がundefinedになる
+                if *optional && base.is_nullish() {
+                    return Ok(None);
+                }
+                let key = self.property_key(property, *computed, env)?;
+                Ok(Some(self.get_property(&base, &key)?))
+            }
+            Node::ChainExpression(expression) => Ok(Some(self.eval_value(expression, env)?)),
+            Node::NumberLiteral(value) => Ok(Some(RuntimeValue::Number(*value))),
+            Node::StringLiteral(value) => Ok(Some(RuntimeValue::String(value.to_string()))),
+            Node::BooleanLiteral(value) => Ok(Some(RuntimeValue::Boolean(*value))),
+            Node::NullLiteral => Ok(Some(RuntimeValue::Null)),
+            Node::ArrayExpression { elements } => {
+                let array = self.heap.allocate(JsObject::new(
+                    ObjectKind::Array,
+                    Some(self.intrinsics.array_prototype),
+                ));
+                let mut length = 0;
+                for element in elements {
+                    match element.as_deref() {
+                        Some(Node::SpreadElement(argument)) => {
+                            let value = self.eval_value(argument, env.clone())?;
+                            for value in self.iterate(value)? {
+                                let key = PropertyKey::String(length.to_string());
+                                self.create_data_property(array, key, value)?;
+                                length += 1;
+                            }
+                        }
+                        Some(_) => {
+                            let value = self.eval_value(element, env.clone())?;
+                            let key = PropertyKey::String(length.to_string());
+                            self.create_data_property(array, key, value)?;
+                            length += 1;
+                        }
+                        // 省略された要素は穴になり、長さだけが増える
+                        None => length += 1,
+                    }
+                }
+                self.set(
+                    array,
+                    PropertyKey::from("length"),
+                    RuntimeValue::Number(length as f64),
+                    RuntimeValue::Object(array),
+                )?;
+                Ok(Some(RuntimeValue::Object(array)))
+            }
+            Node::ObjectExpression { properties } => {
+                let object = self.heap.allocate(JsObject::new(
+                    ObjectKind::Ordinary,
+                    Some(self.intrinsics.object_prototype),
+                ));
+                for property in properties {
+                    match property.as_deref() {
+                        Some(Node::SpreadElement(argument)) => {
+                            let source = self.eval_value(argument, env.clone())?;
+                            self.copy_data_properties(object, source)?;
+                        }
+                        Some(Node::Property {
+                            key,
+                            value,
+                            kind,
+                            computed,
+                        }) => {
+                            let key = self.property_key(key, *computed, env.clone())?;
+                            let name = match &key {
+                                PropertyKey::String(s) => s.clone(),
+                                PropertyKey::Symbol(symbol) => match symbol.description() {
+                                    Some(description) => format!("[{}]", description),
+                                    None => String::new(),
+                                },
+                            };
+                            let desc = match kind {
+                                PropertyKind::Init => {
+                                    let value = self.eval_function(value, &name, env.clone())?;
+                                    PropertyDescriptor::data(value, true, true, true)
+                                }
+                                // 同じ名前のゲッターとセッターは1つのプロパティになる
+                                PropertyKind::Get | PropertyKind::Set => {
+                                    let prefix = if *kind == PropertyKind::Get {
+                                        "get"
+                                    } else {
+                                        "set"
+                                    };
+                                    let name = format!("{} {}", prefix, name);
+                                    let function = self.eval_function(value, &name, env.clone())?;
+                                    let (get, set) = if *kind == PropertyKind::Get {
+                                        (Some(function), None)
+                                    } else {
+                                        (None, Some(function))
+                                    };
+                                    PropertyDescriptor {
+                                        get,
+                                        set,
+                                        enumerable: Some(true),
+                                        configurable: Some(true),
+                                        ..Default::default()
+                                    }
+                                }
+                            };
+                            self.define_own_property(object, key, desc)?;
+                        }
+                        _ => {}
+                    }
                 }
-                // 変数の再割り当て
-                if let Some(node) = left {
-                    if let Node::Identifier(id) = node.borrow() {
-                        let new_value = self.eval(right, env.clone());
-                        env.borrow_mut().update_variable(id.to_string(), new_value);
-                        return None;
+                Ok(Some(RuntimeValue::Object(object)))
+            }
+            Node::TemplateLiteral {
+                quasis,
+                expressions,
+            } => {
+                let mut result = String::new();
+                for (i, quasi) in quasis.iter().enumerate() {
+                    result.push_str(quasi);
+                    if let Some(expr) = expressions.get(i) {
+                        let value = self.eval_value(expr, env.clone())?;
+                        result.push_str(&self.coerce_to_string(value)?);
                     }
                 }
-
-                // 左辺がDOMノード(HtmlElement)の場合、DOMツリーを更新
-                if let Some(RuntimeValue::HtmlElement { object, property }) =
-                    self.eval(left, env.clone())
-                {
-                    let right_value = match self.eval(right, env.clone()) {
-                        Some(value) => value,
-                        None => return None,
+                Ok(Some(RuntimeValue::String(result)))
+            }
+            Node::VariableDecration { kind, declarations } => {
+                for declaration in declarations {
+                    let (name, init) = match declaration.as_deref() {
+                        Some(Node::VariableDeclarator { id: Some(id), init }) => {
+                            match id.borrow() {
+                                Node::Identifier(name) => (name, init),
+                                _ => continue,
+                            }
+                        }
+                        _ => continue,
                     };
 
-                    if let Some(p) = property {
-                        // target.textContent = "foobar"; のようにテキストを更新する
-                        if p == "textContent" {
-                            object
-                                .borrow_mut()
-                                .set_first_child(Some(Rc::new(RefCell::new(DomNode::new(
-                                    DomNodeKind::Text(right_value.to_string()),
-                                )))));
+                    if *kind == "var" {
+                        // 宣言はすでにhoistingで作られている。初期値のない再宣言では値を変えない
+                        if init.is_some() {
+                            let value = self.eval_function(init, name, env.clone())?;
+                            self.put_value(Reference::Binding(name.clone()), value, env.clone())?;
+                        } else if RefCell::borrow(&env).get_binding(name)
+                            == Err(BindingError::NotDefined)
+                        {
+                            self.env.borrow_mut().declare_var(name.clone());
+                        }
+                    } else {
+                        let value = self.eval_function(init, name, env.clone())?;
+                        if !env.borrow_mut().has_own_binding(name) {
+                            env.borrow_mut()
+                                .declare_lexical(name.clone(), *kind == "let");
                         }
+                        env.borrow_mut().initialize(name.clone(), value);
                     }
                 }
-
-                None
+                Ok(None)
             }
-            Node::MemberExpression { object, property } => {
-                let object_value = match self.eval(object, env.clone()) {
-                    Some(value) => value,
-                    None => return None,
-                };
-                let property_value = match self.eval(property, env.clone()) {
-                    Some(value) => value,
-                    // プロパティが存在しないため、object_valueをここで返す
-                    None => return Some(object_value),
+            Node::Identifier(name) => {
+                let reference = Reference::Binding(name.to_string());
+                Ok(Some(self.get_value(&reference, env)?))
+            }
+            Node::LogicalExpression {
+                operator,
+                left,
+                right,
+            } => {
+                let left_value = self.eval_value(left, env.clone())?;
+                // 左辺だけで結果が決まる場合は右辺を評価しない
+                let short_circuit = match *operator {
+                    "&&" => !left_value.to_boolean(),
+                    "||" => left_value.to_boolean(),
+                    _ => !left_value.is_nullish(),
                 };
-
-                // オブジェクトがDOMノードの場合、HtmlElementのプロパティを更新する
-                if let RuntimeValue::HtmlElement { object, property } = object_value {
-                    assert!(property.is_none());
-                    return Some(RuntimeValue::HtmlElement {
-                        object,
-                        property: Some(property_value.to_string()),
-                    });
+                if short_circuit {
+                    return Ok(Some(left_value));
                 }
-
-                // document.getElementByIdは"document.getElementById"という文字列として扱う
-                // このメソッドへの呼び出しは、document.getElementByIdという名前の関数呼び出しになる
-                return Some(
-                    object_value + RuntimeValue::StringLiteral(".".to_string()) + property_value,
-                );
+                Ok(Some(self.eval_value(right, env.clone())?))
             }
-            Node::NumberLiteral(value) => Some(RuntimeValue::Number(*value)),
-            Node::VariableDecration { declarations } => {
-                for declaration in declarations {
-                    self.eval(&declaration, env.clone());
+            Node::ConditionalExpression {
+                test,
+                consequent,
+                alternate,
+            } => {
+                let test_value = self.eval(test, env.clone())?;
+                if Self::is_truthy(&test_value) {
+                    self.eval(consequent, env.clone())
+                } else {
+                    self.eval(alternate, env.clone())
                 }
-                None
             }
-            Node::VariableDeclarator { id, init } => {
-                if let Some(node) = id {
-                    if let Node::Identifier(id) = node.borrow() {
-                        let init = self.eval(&init, env.clone());
-                        env.borrow_mut().add_variable(id.to_string(), init);
-                    }
+            Node::SequenceExpression { expressions } => {
+                let mut result = None;
+                for expr in expressions {
+                    result = self.eval(expr, env.clone())?;
                 }
-                None
+                Ok(result)
             }
-            Node::Identifier(name) => match env.borrow_mut().get_variable(name.to_string()) {
-                Some(v) => Some(v),
-                // 変数が見つからない場合は文字列リテラルとして扱う (ex. var a = 42; の`a`が文字列)
-                None => Some(RuntimeValue::StringLiteral(name.to_string())),
-            },
-            Node::StringLiteral(value) => Some(RuntimeValue::StringLiteral(value.to_string())),
             Node::BlockStatement { body } => {
+                // `let`、`const`、関数の宣言はブロックのスコープに作る
+                let env = if body.iter().any(is_lexical_declaration) {
+                    let scope = Rc::new(RefCell::new(Environment::new(Some(env))));
+                    self.instantiate_declarations(body, scope.clone(), false)?;
+                    scope
+                } else {
+                    env
+                };
+
+                // 値のない文は、それまでの文の値を変えない
                 let mut result: Option<RuntimeValue> = None;
+                let pinned = self.heap.pinned();
                 for stmt in body {
-                    result = self.eval(&stmt, env.clone());
+                    if let Some(value) = self.eval(stmt, env.clone())? {
+                        result = Some(value);
+                    }
+                    self.heap.unpin(pinned);
+                    if let Some(value) = &result {
+                        self.heap.pin(value);
+                    }
                 }
-                result
+                Ok(result)
             }
-            Node::ReturnStatement { argument } => self.eval(&argument, env.clone()),
-            Node::FunctionDeclaration { id, params, body } => {
-                if let Some(RuntimeValue::StringLiteral(id)) = self.eval(&id, env.clone()) {
-                    let clonned_body = match body {
-                        Some(b) => Some(b.clone()),
-                        None => return None,
+            Node::EmptyStatement => Ok(None),
+            Node::IfStatement {
+                test,
+                consequent,
+                alternate,
+            } => {
+                if Self::is_truthy(&self.eval(test, env.clone())?) {
+                    self.eval(consequent, env.clone())
+                } else {
+                    self.eval(alternate, env.clone())
+                }
+            }
+            Node::WhileStatement { .. }
+            | Node::DoWhileStatement { .. }
+            | Node::ForStatement { .. }
+            | Node::ForInStatement { .. }
+            | Node::ForOfStatement { .. } => self.eval_iteration(node, &[], env),
+            Node::LabeledStatement { .. } => {
+                // 連続したラベルはまとめて1つの文につける
+                let mut labels = Vec::new();
+                let mut statement = node;
+                while let Node::LabeledStatement { label, body } = statement.borrow() {
+                    labels.push(label.clone());
+                    statement = match body {
+                        Some(body) => body,
+                        None => return Ok(None),
                     };
-                    self.functions
-                        .push(Function::new(id, params.to_vec(), clonned_body));
                 }
-                None
+
+                match self.eval_iteration(statement, &labels, env) {
+                    Err(Completion::Break(Some(label))) if labels.contains(&label) => Ok(None),
+                    result => result,
+                }
             }
-            Node::CallExpression { callee, arguments } => {
-                let new_env = Rc::new(RefCell::new(Environment::new(Some(env))));
+            Node::SwitchStatement {
+                discriminant,
+                cases,
+            } => {
+                let value = self.eval_value(discriminant, env.clone())?;
 
-                let callee_value = match self.eval(&callee, new_env.clone()) {
-                    Some(value) => value,
-                    None => return None,
+                // すべての節の宣言が、switch文全体で1つのスコープに入る
+                let statements = cases
+                    .iter()
+                    .filter_map(|case| match case.as_deref() {
+                        Some(Node::SwitchCase { consequent, .. }) => Some(consequent.iter()),
+                        _ => None,
+                    })
+                    .flatten()
+                    .cloned()
+                    .collect::<Vec<_>>();
+                let env = if statements.iter().any(is_lexical_declaration) {
+                    let scope = Rc::new(RefCell::new(Environment::new(Some(env))));
+                    self.instantiate_declarations(&statements, scope.clone(), false)?;
+                    scope
+                } else {
+                    env
                 };
 
-                let api_result = self.call_browser_api(&callee_value, arguments, new_env.clone());
-                if api_result.0 {
-                    return api_result.1;
+                let mut start = None;
+                for (i, case) in cases.iter().enumerate() {
+                    if let Some(Node::SwitchCase {
+                        test: Some(test), ..
+                    }) = case.as_deref()
+                    {
+                        let case_value = self.eval_value(&Some(test.clone()), env.clone())?;
+                        if value.strict_equals(&case_value) {
+                            start = Some(i);
+                            break;
+                        }
+                    }
                 }
+                let start = start.or_else(|| {
+                    cases.iter().position(|case| {
+                        matches!(case.as_deref(), Some(Node::SwitchCase { test: None, .. }))
+                    })
+                });
 
-                let function = {
-                    let mut f: Option<Function> = None;
-
-                    for func in &self.functions {
-                        if callee_value == RuntimeValue::StringLiteral(func.id.to_string()) {
-                            f = Some(func.clone());
+                // 一致したところから、breakまで後ろの節も実行する
+                let mut result = None;
+                let start = match start {
+                    Some(start) => start,
+                    None => return Ok(None),
+                };
+                for case in &cases[start..] {
+                    if let Some(Node::SwitchCase { consequent, .. }) = case.as_deref() {
+                        for stmt in consequent {
+                            match self.eval(stmt, env.clone()) {
+                                Ok(Some(v)) => result = Some(v),
+                                Ok(None) => {}
+                                Err(Completion::Break(None)) => return Ok(result),
+                                Err(c) => return Err(c),
+                            }
                         }
                     }
+                }
+                Ok(result)
+            }
+            Node::SwitchCase { .. } => Ok(None),
+            Node::BreakStatement { label } => Err(Completion::Break(label.clone())),
+            Node::ContinueStatement { label } => Err(Completion::Continue(label.clone())),
+            Node::ThrowStatement { argument } => {
+                Err(Completion::Throw(self.eval_value(argument, env.clone())?))
+            }
+            Node::TryStatement {
+                block,
+                handler,
+                finalizer,
+            } => {
+                let mut result = self.eval(block, env.clone());
 
-                    match f {
-                        Some(f) => f,
-                        None => panic!("function {:?} doesn't exist", callee),
-                    }
-                };
-                // 関数呼び出し時に渡される引数を新しく作成したスコープのローカル変数として割り当てる
-                assert!(arguments.len() == function.params.len());
-                for (i, item) in arguments.iter().enumerate() {
-                    if let Some(RuntimeValue::StringLiteral(name)) =
-                        self.eval(&function.params[i], new_env.clone())
+                if let (Err(completion), Some(handler)) = (&result, handler) {
+                    if let (true, Node::CatchClause { param, body }) =
+                        (completion.is_throw(), handler.borrow())
                     {
-                        new_env
-                            .borrow_mut()
-                            .add_variable(name, self.eval(item, new_env.clone()));
+                        let value = self.exception_value(completion.clone());
+                        let catch_env = Rc::new(RefCell::new(Environment::new(Some(env.clone()))));
+                        if let Some(Node::Identifier(name)) = param.as_deref() {
+                            catch_env.borrow_mut().initialize(name.to_string(), value);
+                        }
+                        result = self.eval(body, catch_env);
                     }
                 }
 
-                // 関数の中身を新しいスコープとともにevalメソッドで解釈する
-                self.eval(&function.body.clone(), new_env.clone())
+                // finallyが途中で終わった場合は、その結果で上書きする
+                if finalizer.is_some() {
+                    self.eval(finalizer, env.clone())?;
+                }
+                result
+            }
+            Node::CatchClause { .. } => Ok(None),
+            Node::ReturnStatement { argument } => {
+                Err(Completion::Return(self.eval_value(argument, env.clone())?))
+            }
+            Node::FunctionDeclaration { id, params, body } => {
+                // 関数はhoistingで作られている。宣言より前のスコープを経ずに評価された場合だけ作る
+                if let Some(Node::Identifier(name)) = id.as_deref() {
+                    if !env.borrow_mut().has_own_binding(name) {
+                        let function = self.create_function(name, params, body, env.clone(), false);
+                        env.borrow_mut()
+                            .initialize(name.to_string(), RuntimeValue::Object(function));
+                    }
+                }
+                Ok(None)
+            }
+            Node::FunctionExpression { .. } | Node::ArrowFunctionExpression { .. } => {
+                Ok(Some(self.eval_function(&Some(node.clone()), "", env)?))
+            }
+            Node::ThisExpression => Ok(Some(RefCell::borrow(&env).this_value())),
+            Node::NewExpression { callee, arguments } => {
+                let function = self.eval_value(callee, env.clone())?;
+                let arguments = self.eval_arguments(arguments, env)?;
+                Ok(Some(self.construct(&function, &arguments)?))
             }
+            Node::CallExpression {
+                callee,
+                arguments,
+                optional,
+            } => self.eval_call(callee, arguments, *optional, env),
+            Node::RegExpLiteral { .. } => Err(Completion::error(
+                ErrorKind::SyntaxError,
+                REGEXP_NOT_SUPPORTED,
+            )),
+            // 他のノードの一部として評価する
+            Node::VariableDeclarator { .. }
+            | Node::Property { .. }
+            | Node::SpreadElement(_)
+            | Node::AssignmentPattern { .. }
+            | Node::RestElement(_) => Ok(None),
         }
     }
 
+    /// 捕捉されなかった例外が投げられたところでスクリプトの実行をやめる
     pub fn execute(&mut self, program: &Program) {
-        for node in program.body() {
-            self.eval(&Some(node.clone()), self.env.clone());
+        let _ = self.eval_program(program);
+    }
+
+    /// スクリプトを実行する。捕捉されなかった例外は文字列にして返す
+    pub fn evaluate(&mut self, program: &Program) -> Result<Option<RuntimeValue>, String> {
+        match self.evaluate_value(program) {
+            Ok(value) => Ok(value),
+            Err(value) => Err(self.display_value(value)),
+        }
+    }
+
+    /// スクリプトを実行する。捕捉されなかった例外は投げられた値のまま返す
+    pub fn evaluate_value(
+        &mut self,
+        program: &Program,
+    ) -> Result<Option<RuntimeValue>, RuntimeValue> {
+        self.eval_program(program)
+            .map_err(|completion| self.exception_value(completion))
+    }
+
+    /// 値が、グローバル変数`name`のコンストラクタのインスタンスであればtrue
+    pub fn is_instance_of(&mut self, value: &RuntimeValue, name: &str) -> bool {
+        let constructor = match RefCell::borrow(&self.env).get_binding(name) {
+            Ok(constructor) => constructor,
+            Err(_) => return false,
+        };
+        let pinned = self.heap.pinned();
+        self.heap.pin(value);
+        let result = self.has_instance(&constructor, value);
+        self.heap.unpin(pinned);
+        result.unwrap_or(false)
+    }
+
+    /// スクリプトを実行する方法を切り替える
+    pub fn set_engine(&mut self, engine: Engine) {
+        self.engine = engine;
+    }
+
+    /// Dateが使う時計を差し替える
+    pub fn set_clock(&mut self, clock: Rc<dyn Clock>) {
+        self.clock = clock;
+    }
+
+    /// グローバル変数を定義する。テストのハーネスなどが使う
+    pub fn define_global(&mut self, name: &str, value: RuntimeValue) {
+        self.env.borrow_mut().initialize(name.to_string(), value);
+    }
+
+    /// test262のハーネスが使う`$262`オブジェクトを定義する
+    /// https://github.com/tc39/test262/blob/main/INTERPRETING.md#host-defined-functions
+    pub fn define_test262_host(&mut self) {
+        let host = self.heap.allocate(JsObject::new(
+            ObjectKind::Ordinary,
+            Some(self.intrinsics.object_prototype),
+        ));
+        self.define_host_method(host, HostFunction::EvalScript, false);
+        self.define_global("$262", RuntimeValue::Object(host));
+    }
+
+    /// 例外として投げられた値。エンジンが投げる例外はここでErrorオブジェクトにする
+    fn exception_value(&mut self, completion: Completion) -> RuntimeValue {
+        match completion {
+            Completion::Throw(value) => value,
+            Completion::Error(kind, message) => {
+                RuntimeValue::Object(self.create_error(kind, &message))
+            }
+            // 関数の外のreturnなどは構文解析で弾かれている
+            _ => RuntimeValue::Undefined,
+        }
+    }
+
+    /// Errorオブジェクトを作る
+    fn create_error(&mut self, kind: ErrorKind, message: &str) -> ObjectId {
+        let error = self.heap.allocate(JsObject::new(
+            ObjectKind::Error,
+            Some(self.intrinsics.error_prototype(kind)),
+        ));
+        self.heap.get_mut(error).define_own_property(
+            PropertyKey::from("message"),
+            PropertyDescriptor::data(RuntimeValue::String(message.to_string()), true, false, true),
+        );
+        error
+    }
+
+    /// エラーの表示などのために値を文字列にする。変換で例外が起きた場合もそのまま表示する
+    pub fn display_value(&mut self, value: RuntimeValue) -> String {
+        match self.coerce_to_string(value.clone()) {
+            Ok(s) => s,
+            Err(_) => value.to_string(),
+        }
+    }
+
+    /// スクリプトを実行し、最後に値を持った文の値を返す
+    fn eval_program(&mut self, program: &Program) -> Result<Option<RuntimeValue>, Completion> {
+        let pinned = self.heap.pinned();
+        let result = self.run_script(program);
+        self.heap.unpin(pinned);
+        result
+    }
+
+    /// グローバルのスコープでスクリプトを実行する。文と文の間でGCを実行する
+    fn run_script(&mut self, program: &Program) -> Result<Option<RuntimeValue>, Completion> {
+        if self.engine == Engine::Bytecode {
+            let code = compile_program(program);
+            return self.run_code(&code, self.env.clone(), &[]);
+        }
+
+        let body = program
+            .body()
+            .iter()
+            .map(|node| Some(node.clone()))
+            .collect::<Vec<_>>();
+        self.instantiate_declarations(&body, self.env.clone(), true)?;
+
+        let mut result = None;
+        let pinned = self.heap.pinned();
+        let env = self.env.clone();
+        for node in &body {
+            if let Some(value) = self.eval(node, env.clone())? {
+                result = Some(value);
+            }
+            self.collect_between(pinned, &result, &env);
         }
+        Ok(result)
     }
 }
 
@@ -377,14 +2622,14 @@ mod tests {
         let input = "42".to_string();
         let lexer = JsLexer::new(input);
         let mut parser = JsParser::new(lexer);
-        let ast = parser.parse_ast();
+        let ast = parser.parse_ast().expect("failed to parse");
         let mut runtime = JsRuntime::new(dom);
-        let expected = [Some(RuntimeValue::Number(42))];
+        let expected = [Some(RuntimeValue::Number(42.0))];
         let mut i = 0;
 
         for node in ast.body() {
             let result = runtime.eval(&Some(node.clone()), runtime.env.clone());
-            assert_eq!(expected[i], result);
+            assert_eq!(Ok(expected[i].clone()), result);
             i += 1;
         }
     }
@@ -396,14 +2641,14 @@ mod tests {
         let input = "1 + 2".to_string();
         let lexer = JsLexer::new(input);
         let mut parser = JsParser::new(lexer);
-        let ast = parser.parse_ast();
+        let ast = parser.parse_ast().expect("failed to parse");
         let mut runtime = JsRuntime::new(dom);
-        let expected = [Some(RuntimeValue::Number(3))];
+        let expected = [Some(RuntimeValue::Number(3.0))];
         let mut i = 0;
 
         for node in ast.body() {
             let result = runtime.eval(&Some(node.clone()), runtime.env.clone());
-            assert_eq!(expected[i], result);
+            assert_eq!(Ok(expected[i].clone()), result);
             i += 1;
         }
     }
@@ -414,14 +2659,14 @@ mod tests {
         let input = "2 - 1".to_string();
         let lexer = JsLexer::new(input);
         let mut parser = JsParser::new(lexer);
-        let ast = parser.parse_ast();
+        let ast = parser.parse_ast().expect("failed to parse");
         let mut runtime = JsRuntime::new(dom);
-        let expected = [Some(RuntimeValue::Number(1))];
+        let expected = [Some(RuntimeValue::Number(1.0))];
         let mut i = 0;
 
         for node in ast.body() {
             let result = runtime.eval(&Some(node.clone()), runtime.env.clone());
-            assert_eq!(expected[i], result);
+            assert_eq!(Ok(expected[i].clone()), result);
             i += 1;
         }
     }
@@ -432,14 +2677,14 @@ mod tests {
         let input = "var foo=42;".to_string();
         let lexer = JsLexer::new(input);
         let mut parser = JsParser::new(lexer);
-        let ast = parser.parse_ast();
+        let ast = parser.parse_ast().expect("failed to parse");
         let mut runtime = JsRuntime::new(dom);
         let expected = [None];
         let mut i = 0;
 
         for node in ast.body() {
             let result = runtime.eval(&Some(node.clone()), runtime.env.clone());
-            assert_eq!(expected[i], result);
+            assert_eq!(Ok(expected[i].clone()), result);
             i += 1;
         }
     }
@@ -450,14 +2695,14 @@ mod tests {
         let input = "var foo=42; foo+1".to_string();
         let lexer = JsLexer::new(input);
         let mut parser = JsParser::new(lexer);
-        let ast = parser.parse_ast();
+        let ast = parser.parse_ast().expect("failed to parse");
         let mut runtime = JsRuntime::new(dom);
-        let expected = [None, Some(RuntimeValue::Number(43))];
+        let expected = [None, Some(RuntimeValue::Number(43.0))];
         let mut i = 0;
 
         for node in ast.body() {
             let result = runtime.eval(&Some(node.clone()), runtime.env.clone());
-            assert_eq!(expected[i], result);
+            assert_eq!(Ok(expected[i].clone()), result);
             i += 1;
         }
     }
@@ -468,14 +2713,19 @@ mod tests {
         let input = "var foo=42; foo=1; foo".to_string();
         let lexer = JsLexer::new(input);
         let mut parser = JsParser::new(lexer);
-        let ast = parser.parse_ast();
+        let ast = parser.parse_ast().expect("failed to parse");
         let mut runtime = JsRuntime::new(dom);
-        let expected = [None, None, Some(RuntimeValue::Number(1))];
+        // 代入式の値は代入した値になる
+        let expected = [
+            None,
+            Some(RuntimeValue::Number(1.0)),
+            Some(RuntimeValue::Number(1.0)),
+        ];
         let mut i = 0;
 
         for node in ast.body() {
             let result = runtime.eval(&Some(node.clone()), runtime.env.clone());
-            assert_eq!(expected[i], result);
+            assert_eq!(Ok(expected[i].clone()), result);
             i += 1;
         }
     }
@@ -486,14 +2736,14 @@ mod tests {
         let input = "function foo() { return 42; } foo()+1;".to_string();
         let lexer = JsLexer::new(input);
         let mut parser = JsParser::new(lexer);
-        let ast = parser.parse_ast();
+        let ast = parser.parse_ast().expect("failed to parse");
         let mut runtime = JsRuntime::new(dom);
-        let expected = [None, Some(RuntimeValue::Number(43))];
+        let expected = [None, Some(RuntimeValue::Number(43.0))];
         let mut i = 0;
 
         for node in ast.body() {
             let result = runtime.eval(&Some(node.clone()), runtime.env.clone());
-            assert_eq!(expected[i], result);
+            assert_eq!(Ok(expected[i].clone()), result);
             i += 1;
         }
     }
@@ -504,16 +2754,730 @@ mod tests {
         let input = "var a=42; function foo() { var a=1; return a; } foo()+a".to_string();
         let lexer = JsLexer::new(input);
         let mut parser = JsParser::new(lexer);
-        let ast = parser.parse_ast();
+        let ast = parser.parse_ast().expect("failed to parse");
+        let mut runtime = JsRuntime::new(dom);
+
+        let expected = [None, None, Some(RuntimeValue::Number(43.0))];
+        let mut i = 0;
+
+        for node in ast.body() {
+            let result = runtime.eval(&Some(node.clone()), runtime.env.clone());
+            assert_eq!(Ok(expected[i].clone()), result);
+            i += 1;
+        }
+    }
+
+    #[test]
+    fn test_logical_and_conditional() {
+        let dom = Rc::new(RefCell::new(DomNode::new(DomNodeKind::Document)));
+        let input = "var a=1; a || (a=2); a; 0 && 3; 0 || 4; a ? 5 : 6, 0 ? 7 : 8".to_string();
+        let lexer = JsLexer::new(input);
+        let mut parser = JsParser::new(lexer);
+        let ast = parser.parse_ast().expect("failed to parse");
         let mut runtime = JsRuntime::new(dom);
 
-        let expected = [None, None, Some(RuntimeValue::Number(43))];
+        let expected = [
+            None,
+            Some(RuntimeValue::Number(1.0)),
+            Some(RuntimeValue::Number(1.0)),
+            Some(RuntimeValue::Number(0.0)),
+            Some(RuntimeValue::Number(4.0)),
+            Some(RuntimeValue::Number(8.0)),
+        ];
         let mut i = 0;
 
         for node in ast.body() {
             let result = runtime.eval(&Some(node.clone()), runtime.env.clone());
-            assert_eq!(expected[i], result);
+            assert_eq!(Ok(expected[i].clone()), result);
             i += 1;
         }
     }
+
+    /// プログラムの最後の文の値
+    /// 構文木の評価とバイトコードの実行で、同じ結果になることを確かめる
+    pub(super) fn eval_last(input: &str) -> Result<Option<RuntimeValue>, Completion> {
+        let lexer = JsLexer::new(input.to_string());
+        let mut parser = JsParser::new(lexer);
+        let ast = parser.parse_ast().expect("failed to parse");
+        let results = [Engine::TreeWalker, Engine::Bytecode].map(|engine| {
+            let dom = Rc::new(RefCell::new(DomNode::new(DomNodeKind::Document)));
+            let mut runtime = JsRuntime::new(dom);
+            runtime.set_engine(engine);
+            runtime.eval_program(&ast)
+        });
+        // NaNも比べられるように、表示した文字列で比べる
+        assert_eq!(
+            format!("{:?}", results[0]),
+            format!("{:?}", results[1]),
+            "{}",
+            input
+        );
+        results[1].clone()
+    }
+
+    pub(super) fn string(s: &str) -> Result<Option<RuntimeValue>, Completion> {
+        Ok(Some(RuntimeValue::String(s.to_string())))
+    }
+
+    #[test]
+    fn test_return_stops_function() {
+        let input = "function f() { if (1) { return 1; } return 2; } f()";
+        assert_eq!(Ok(Some(RuntimeValue::Number(1.0))), eval_last(input));
+
+        // returnのない関数はundefinedを返す
+        let input = "function g() { 3; } g()";
+        assert_eq!(Ok(Some(RuntimeValue::Undefined)), eval_last(input));
+    }
+
+    #[test]
+    fn test_loops() {
+        let input = "var i=3; var s=0; while (i) { s = s + i; i = i - 1; } s";
+        assert_eq!(Ok(Some(RuntimeValue::Number(6.0))), eval_last(input));
+
+        let input = "var n=0; do { n = n + 1; } while (0); n";
+        assert_eq!(Ok(Some(RuntimeValue::Number(1.0))), eval_last(input));
+
+        let input = "var t=0; for (var j=4; j; j=j-1) { switch (j) { case 2: continue; case 4: t = t + 100; } t = t + j; } t";
+        assert_eq!(Ok(Some(RuntimeValue::Number(108.0))), eval_last(input));
+
+        let input = "var k=0; while (1) { k = k + 1; switch (k) { case 3: break; default: continue; } break; } k";
+        assert_eq!(Ok(Some(RuntimeValue::Number(3.0))), eval_last(input));
+    }
+
+    #[test]
+    fn test_labeled_statements() {
+        let input = "var c=0; outer: for (var a=2; a; a=a-1) { for (var b=2; b; b=b-1) { if (a - 1) { continue outer; } c = c + 1; } } c";
+        assert_eq!(Ok(Some(RuntimeValue::Number(2.0))), eval_last(input));
+
+        let input = "var d=0; outer: while (1) { while (1) { break outer; } d = 1; } x: { d = d + 5; break x; d = 9; } d";
+        assert_eq!(Ok(Some(RuntimeValue::Number(5.0))), eval_last(input));
+    }
+
+    #[test]
+    fn test_switch() {
+        let input = "var r=''; switch (3) { case 1: r = r + 'a'; default: r = r + 'd'; case 2: r = r + 'b'; break; case 4: r = r + 'c'; } r";
+        assert_eq!(string("db"), eval_last(input));
+
+        let input = "var r=''; switch ('x') { case 'x': r = 'x'; case 'y': r = r + 'y'; } r";
+        assert_eq!(string("xy"), eval_last(input));
+    }
+
+    #[test]
+    fn test_try_catch_finally() {
+        let input = "var log=''; try { log = log + 'a'; throw 'x'; log = log + 'b'; } catch (e) { log = log + e; } finally { log = log + 'f'; } log";
+        assert_eq!(string("axf"), eval_last(input));
+
+        let input = "function g() { throw 'err'; } try { g(); } catch (e) { e }";
+        assert_eq!(string("err"), eval_last(input));
+
+        // finallyのreturnは、tryのreturnを上書きする
+        let input = "function h() { try { return 1; } finally { return 2; } } h()";
+        assert_eq!(Ok(Some(RuntimeValue::Number(2.0))), eval_last(input));
+
+        let input = "function k() { try { throw 1; } finally { 3; } } k()";
+        assert_eq!(
+            Err(Completion::Throw(RuntimeValue::Number(1.0))),
+            eval_last(input)
+        );
+    }
+
+    #[test]
+    fn test_uncaught_exception_stops_script() {
+        let dom = Rc::new(RefCell::new(DomNode::new(DomNodeKind::Document)));
+        let lexer = JsLexer::new("var a=1; throw 'e'; a=2;".to_string());
+        let mut parser = JsParser::new(lexer);
+        let ast = parser.parse_ast().expect("failed to parse");
+        let mut runtime = JsRuntime::new(dom);
+        runtime.execute(&ast);
+        assert_eq!(
+            Some(RuntimeValue::Number(1.0)),
+            runtime.env.borrow_mut().get_variable("a".to_string())
+        );
+    }
+
+    #[test]
+    fn test_for_in_and_of() {
+        let input = "var s=''; for (var ch of 'abc') { s = ch + s; } s";
+        assert_eq!(string("cba"), eval_last(input));
+
+        let input = "var k=''; for (var i in 'ab') { k = k + i; } k";
+        assert_eq!(string("01"), eval_last(input));
+    }
+
+    pub(super) fn number(n: f64) -> Result<Option<RuntimeValue>, Completion> {
+        Ok(Some(RuntimeValue::Number(n)))
+    }
+
+    pub(super) fn boolean(b: bool) -> Result<Option<RuntimeValue>, Completion> {
+        Ok(Some(RuntimeValue::Boolean(b)))
+    }
+
+    #[test]
+    fn test_numbers() {
+        assert_eq!(number(-1.0), eval_last("2 - 3"));
+        assert_eq!(number(0.5), eval_last("1 / 2"));
+        assert_eq!(number(f64::INFINITY), eval_last("1 / 0"));
+        assert_eq!(number(f64::NAN), eval_last("'a' - 1"));
+        assert_eq!(number(-1.0), eval_last("-7 % 3"));
+        assert_eq!(number(512.0), eval_last("2 ** 3 ** 2"));
+        assert_eq!(number(-2.0), eval_last("~1"));
+        assert_eq!(number(4294967295.0), eval_last("-1 >>> 0"));
+        assert_eq!(number(-8.0), eval_last("-16 >> 1"));
+        assert_eq!(number(6.0), eval_last("'3' * '2'"));
+        assert_eq!(number(1.0), eval_last("true + null"));
+    }
+
+    #[test]
+    fn test_string_conversion() {
+        assert_eq!(string("12"), eval_last("'1' + 2"));
+        assert_eq!(string("0.30000000000000004"), eval_last("0.1 + 0.2 + ''"));
+        assert_eq!(
+            string("1e+21undefinednull"),
+            eval_last("1e21 + '' + undefined + null")
+        );
+        assert_eq!(string("a1true"), eval_last("`a${1}${true}`"));
+        assert_eq!(number(3.0), eval_last("'abc'.length"));
+        assert_eq!(string("b"), eval_last("'abc'[1]"));
+    }
+
+    #[test]
+    fn test_equality_and_comparison() {
+        assert_eq!(boolean(true), eval_last("null == undefined"));
+        assert_eq!(boolean(false), eval_last("null === undefined"));
+        assert_eq!(boolean(true), eval_last("'1' == 1"));
+        assert_eq!(boolean(true), eval_last("true == '1'"));
+        assert_eq!(boolean(false), eval_last("NaN == NaN"));
+        assert_eq!(boolean(false), eval_last("null == 0"));
+        assert_eq!(boolean(true), eval_last("'10' < '9'"));
+        assert_eq!(boolean(false), eval_last("'10' < 9"));
+        assert_eq!(boolean(false), eval_last("NaN <= 1"));
+        assert_eq!(boolean(true), eval_last("1 <= 1"));
+    }
+
+    #[test]
+    fn test_unary_operators() {
+        assert_eq!(string("undefined"), eval_last("typeof x"));
+        assert_eq!(string("object"), eval_last("typeof null"));
+        assert_eq!(
+            string("function"),
+            eval_last("typeof document.getElementById")
+        );
+        assert_eq!(boolean(true), eval_last("!''"));
+        assert_eq!(Ok(Some(RuntimeValue::Undefined)), eval_last("void 0"));
+        assert_eq!(number(-0.0), eval_last("+' -0 '"));
+    }
+
+    #[test]
+    fn test_update_and_compound_assignment() {
+        let input = "var a=1; var b=a++; a += 10; a *= 2; b + ',' + a + ',' + --a";
+        assert_eq!(string("1,24,23"), eval_last(input));
+
+        let input = "var s='x'; s += 1; s";
+        assert_eq!(string("x1"), eval_last(input));
+    }
+
+    #[test]
+    fn test_regexp_is_not_supported() {
+        // 正規表現はundefinedにせず、どちらの方法でもSyntaxErrorを投げる
+        let error = Err(Completion::Error(
+            ErrorKind::SyntaxError,
+            REGEXP_NOT_SUPPORTED.to_string(),
+        ));
+        assert_eq!(error, eval_last("typeof /a/"));
+        assert_eq!(error, eval_last("/ab+c/.test('abbc')"));
+        assert_eq!(error, eval_last("'abc'.replace(/b/, 'x')"));
+
+        let input = "var r; try { /a/g; } catch (e) { r = e instanceof SyntaxError; } r";
+        assert_eq!(Ok(Some(RuntimeValue::Boolean(true))), eval_last(input));
+    }
+
+    #[test]
+    fn test_reference_error() {
+        assert_eq!(
+            Err(Completion::Error(
+                ErrorKind::ReferenceError,
+                "y is not defined".to_string()
+            )),
+            eval_last("y + 1")
+        );
+
+        // 宣言されていない変数への代入はグローバル変数を作る
+        assert_eq!(number(2.0), eval_last("function f() { z = 2; } f(); z"));
+
+        let input = "var r; try { undefined.x; } catch (e) { r = String(e); } r";
+        assert_eq!(
+            string("TypeError: Cannot read property 'x' of undefined"),
+            eval_last(input)
+        );
+    }
+
+    #[test]
+    fn test_optional_chaining() {
+        assert_eq!(
+            Ok(Some(RuntimeValue::Undefined)),
+            eval_last("var o = null; o?.a.b.c")
+        );
+        assert_eq!(Ok(Some(RuntimeValue::Undefined)), eval_last("var u; u?.()"));
+        assert_eq!(number(1.0), eval_last("'a'?.length"));
+    }
+
+    #[test]
+    fn test_dom_api() {
+        use crate::renderer::html::parser::HtmlParser;
+        use crate::renderer::html::token::HtmlTokenizer;
+
+        let html = "<html><body><p id=\"target\">hello</p></body></html>".to_string();
+        let window = HtmlParser::new(HtmlTokenizer::new(html)).construct_tree();
+        let dom = RefCell::borrow(&window).document();
+        let input = "var t = document.getElementById('target'); var before = t.textContent; t.textContent = 'x' + 1; document.getElementById('none') === null && document.getElementById('target') === t ? before : ''";
+        let lexer = JsLexer::new(input.to_string());
+        let mut parser = JsParser::new(lexer);
+        let ast = parser.parse_ast().expect("failed to parse");
+        let mut runtime = JsRuntime::new(dom.clone());
+
+        let mut result = Ok(None);
+        for node in ast.body() {
+            result = runtime.eval(&Some(node.clone()), runtime.env.clone());
+        }
+        assert_eq!(string("hello"), result);
+
+        let target = get_element_by_id(Some(dom), &"target".to_string()).expect("no element");
+        assert_eq!("x1", get_text_content(target));
+    }
+
+    #[test]
+    fn test_objects() {
+        assert_eq!(
+            number(3.0),
+            eval_last("var o = {a: 1, 'b': 2}; o.c = o.a + o.b; o['c']")
+        );
+        assert_eq!(
+            string("1,a,b,1"),
+            eval_last("var o = {a: 1}; var s = ''; for (var k in {...o, b: 2, 1: 3}) s += k + ','; s + o.a")
+        );
+        assert_eq!(
+            boolean(true),
+            eval_last("var o = {x: 1}; 'x' in o && 'toString' in o")
+        );
+        assert_eq!(
+            boolean(false),
+            eval_last("var o = {x: 1}; delete o.x; 'x' in o")
+        );
+        assert_eq!(string("[object Object]"), eval_last("'' + {}"));
+        assert_eq!(
+            string("[object Array]"),
+            eval_last("Object.prototype.toString.call([])")
+        );
+    }
+
+    #[test]
+    fn test_arrays() {
+        assert_eq!(
+            number(4.0),
+            eval_last("var a = [1, , 3]; a[3] = 4; a.length")
+        );
+        assert_eq!(boolean(false), eval_last("var a = [1, , 3]; 1 in a"));
+        assert_eq!(
+            number(1.0),
+            eval_last("var a = [1, 2, 3]; a.length = 1; a.length")
+        );
+        assert_eq!(
+            Ok(Some(RuntimeValue::Undefined)),
+            eval_last("var a = [1, 2, 3]; a.length = 1; a[2]")
+        );
+        assert_eq!(
+            string("1a2b"),
+            eval_last("var s = ''; for (var x of [1, ...'a', 2, ...['b']]) s += x; s")
+        );
+        assert!(throws(
+            eval_last("var a = []; a.length = -1"),
+            ErrorKind::RangeError
+        ));
+    }
+
+    #[test]
+    fn test_builtins() {
+        assert_eq!(
+            string("A-B-C"),
+            eval_last("['a', 'b', 'c'].map(s => s.toUpperCase()).join('-')")
+        );
+        assert_eq!(number(3.0), eval_last("Math.max(1, '3', 2)"));
+        assert_eq!(
+            string("{\"a\":[1,\"x\",null]}"),
+            eval_last("JSON.stringify(JSON.parse('{\"a\": [1, \"x\", null]}'))")
+        );
+        // エンジンが投げる例外もErrorオブジェクトとして捕まえられる
+        assert_eq!(
+            string("TypeError true"),
+            eval_last(
+                "var r; try { null.f; } catch (e) { r = e.name + ' ' + (e instanceof Error); } r"
+            )
+        );
+        assert!(throws(eval_last("new Array(-1)"), ErrorKind::RangeError));
+    }
+
+    #[test]
+    fn test_clock_and_evaluate() {
+        let dom = Rc::new(RefCell::new(DomNode::new(DomNodeKind::Document)));
+        let mut runtime = JsRuntime::new(dom);
+        runtime.set_clock(Rc::new(FixedClock(1704164645006.0)));
+        runtime.define_global("answer", RuntimeValue::Number(42.0));
+        let mut parser = JsParser::new(JsLexer::new(
+            "new Date().toISOString() + ' ' + Date.now() % 1000 + ' ' + answer".to_string(),
+        ));
+        assert_eq!(
+            Ok(Some(RuntimeValue::String(
+                "2024-01-02T03:04:05.006Z 6 42".to_string()
+            ))),
+            runtime.evaluate(&parser.parse_ast().expect("failed to parse"))
+        );
+
+        // 捕まえられなかった例外は文字列にする
+        let mut parser = JsParser::new(JsLexer::new("throw new RangeError('no')".to_string()));
+        assert_eq!(
+            Err("RangeError: no".to_string()),
+            runtime.evaluate(&parser.parse_ast().expect("failed to parse"))
+        );
+    }
+
+    #[test]
+    fn test_collect_garbage() {
+        let dom = Rc::new(RefCell::new(DomNode::new(DomNodeKind::Document)));
+        let mut runtime = JsRuntime::new(dom);
+        let input = "var kept = {}; var dropped = {}; dropped.self = dropped; dropped = null";
+        let mut parser = JsParser::new(JsLexer::new(input.to_string()));
+        runtime.execute(&parser.parse_ast().expect("failed to parse"));
+
+        let before = runtime.heap.len();
+        assert_eq!(1, runtime.collect_garbage());
+        assert_eq!(before - 1, runtime.heap.len());
+        assert!(matches!(
+            runtime.env.borrow_mut().get_variable("kept".to_string()),
+            Some(RuntimeValue::Object(id)) if runtime.heap.is_live(id)
+        ));
+    }
+
+    #[test]
+    fn test_collect_garbage_keeps_completion_value() {
+        // 文の値は、その後の文でガベージコレクションが起きても回収しない
+        let input = "for (var i = 0; i < 3000; i++) { ({n: i}); } var end = 1;";
+        let ast = JsParser::new(JsLexer::new(input.to_string()))
+            .parse_ast()
+            .expect("failed to parse");
+        for engine in [Engine::TreeWalker, Engine::Bytecode] {
+            let dom = Rc::new(RefCell::new(DomNode::new(DomNodeKind::Document)));
+            let mut runtime = JsRuntime::new(dom);
+            runtime.set_engine(engine);
+            let value = runtime.eval_program(&ast).expect("failed to evaluate");
+            let value = value.expect("no completion value");
+            assert!(runtime.heap.len() < 3000, "{:?}", engine);
+            assert_eq!(
+                Ok(RuntimeValue::Number(2999.0)),
+                runtime.get_property(&value, &PropertyKey::from("n")),
+                "{:?}",
+                engine
+            );
+        }
+    }
+
+    #[test]
+    fn test_collect_garbage_while_running() {
+        // 繰り返しの途中や関数の中でも回収するので、同時に生きているオブジェクトは少ない
+        let inputs = [
+            "for (var i = 0; i < 10000; i++) { var o = { a: [1, 2] }; }",
+            "var i = 0; while (i++ < 10000) { ({ a: [1, 2] }); }",
+            "function f() { for (let i = 0; i < 10000; i++) { let o = { a: [i] }; } } f()",
+            "[1].forEach(function () { for (var x of new Array(10000).fill(0)) { ({ x }); } })",
+            "function g() { return { a: [1] }; } for (var i = 0; i < 10000; i++) g();",
+        ];
+        for input in inputs {
+            let ast = JsParser::new(JsLexer::new(input.to_string()))
+                .parse_ast()
+                .expect("failed to parse");
+            for engine in [Engine::TreeWalker, Engine::Bytecode] {
+                let dom = Rc::new(RefCell::new(DomNode::new(DomNodeKind::Document)));
+                let mut runtime = JsRuntime::new(dom);
+                runtime.set_engine(engine);
+                runtime.eval_program(&ast).expect("failed to evaluate");
+                assert!(
+                    runtime.heap.peak_len() < 5000,
+                    "{:?} {}: {}",
+                    engine,
+                    input,
+                    runtime.heap.peak_len()
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_collect_garbage_keeps_values_in_use() {
+        // churnを呼ぶとGCが実行される。評価の途中の値は回収されない
+        let churn =
+            "function churn() { for (var i = 0; i < 3000; i++) { ({ a: [i] }); } return 0; }";
+        let cases = [
+            ("[{ v: 1 }, churn()][0].v", "1"),
+            ("({ a: { v: 2 }, b: churn() }).a.v", "2"),
+            ("({ valueOf() { return 3; } }) + churn()", "3"),
+            ("[{ v: 4 }].concat(churn())[0].v", "4"),
+            ("{ let x = { v: 5 }; churn(); x.v; }", "5"),
+            (
+                "function t() { try { throw { v: 6 }; } finally { churn(); } } try { t(); } catch (e) { e.v; }",
+                "6",
+            ),
+            (
+                "var o = { get p() { var r = { v: 7 }; delete o.q; churn(); return r; }, q: {} }; o.p.v",
+                "7",
+            ),
+            (
+                "[{ v: 2 }, { v: 1 }, { v: 3 }].sort((x, y) => churn() + x.v - y.v).map(x => x.v).join()",
+                "1,2,3",
+            ),
+            (
+                "var s = ''; for (var x of [{ v: 8 }, { v: 9 }]) { churn(); s += x.v; } s",
+                "89",
+            ),
+            (
+                "var m = new Map([[{ k: 1 }, { v: 10 }]]); var r = 0; m.forEach((v, k) => { m.clear(); churn(); r = v.v + k.k; }); r",
+                "11",
+            ),
+        ];
+        for (input, expected) in cases {
+            let input = format!("{} {}", churn, input);
+            let result = eval_last(&input).map(|v| v.map(|v| v.to_string()));
+            assert_eq!(Ok(Some(expected.to_string())), result, "{}", input);
+        }
+    }
+
+    #[test]
+    fn test_call_stack_overflow() {
+        // デバッグビルドでは1回の呼び出しでスタックを多く使うため、大きなスタックのスレッドで実行する
+        extern crate std;
+        let overflow = "RangeError: Maximum call stack size exceeded";
+        let cases = [
+            ("function r() { r(); } r()", Err(overflow)),
+            ("var o = { get x() { return o.x; } }; o.x", Err(overflow)),
+            // 捕まえた後も実行を続けられる
+            (
+                "function d(n) { try { return d(n + 1); } catch (e) { return e instanceof RangeError && n > 100; } } d(0)",
+                Ok("true"),
+            ),
+            ("function c(n) { return n ? c(n - 1) + 1 : 0; } c(200)", Ok("200")),
+        ];
+        let thread = std::thread::Builder::new()
+            .stack_size(64 << 20)
+            .spawn(move || {
+                for engine in [Engine::TreeWalker, Engine::Bytecode] {
+                    let dom = Rc::new(RefCell::new(DomNode::new(DomNodeKind::Document)));
+                    let mut runtime = JsRuntime::new(dom);
+                    runtime.set_engine(engine);
+                    for (input, expected) in cases {
+                        let ast = JsParser::new(JsLexer::new(input.to_string()))
+                            .parse_ast()
+                            .expect("failed to parse");
+                        let result = runtime
+                            .evaluate(&ast)
+                            .map(|v| v.map(|v| v.to_string()).unwrap_or_default());
+                        let expected = expected.map(|s| s.to_string()).map_err(|e| e.to_string());
+                        assert_eq!(expected, result, "{:?} {}", engine, input);
+                    }
+                }
+            });
+        thread
+            .expect("failed to spawn a thread")
+            .join()
+            .expect("the thread panicked");
+    }
+
+    #[test]
+    fn test_arguments() {
+        let cases = [
+            ("function f() { return arguments.length; } f(1, 2)", "2"),
+            ("function f(a) { return arguments[1] + a; } f(1, 2)", "3"),
+            // アロー関数は外側の関数のargumentsを参照する
+            ("function f() { return (() => arguments[0])(); } f('a', 'b')", "a"),
+            ("function f(arguments) { return arguments; } f(4)", "4"),
+            ("function f() { let arguments = 5; return arguments; } f()", "5"),
+            ("function f() { return [...arguments].join('-'); } f(1, 2, 3)", "1-2-3"),
+            (
+                "function f() { return Object.prototype.toString.call(arguments); } f()",
+                "[object Arguments]",
+            ),
+            (
+                "function f() { return Object.keys(arguments).join() + ':' + arguments.length; } f(7, 8)",
+                "0,1:2",
+            ),
+        ];
+        for engine in [Engine::TreeWalker, Engine::Bytecode] {
+            for (input, expected) in cases {
+                let ast = JsParser::new(JsLexer::new(input.to_string()))
+                    .parse_ast()
+                    .expect("failed to parse");
+                let dom = Rc::new(RefCell::new(DomNode::new(DomNodeKind::Document)));
+                let mut runtime = JsRuntime::new(dom);
+                runtime.set_engine(engine);
+                let value = runtime.eval_program(&ast);
+                assert_eq!(
+                    Ok(Some(RuntimeValue::String(expected.to_string()))),
+                    value.map(|v| v.map(|v| RuntimeValue::String(v.to_string()))),
+                    "{:?}: {}",
+                    engine,
+                    input
+                );
+            }
+        }
+        assert!(throws(
+            eval_last("var f = () => arguments; f()"),
+            ErrorKind::ReferenceError
+        ));
+    }
+
+    pub(super) fn throws(
+        result: Result<Option<RuntimeValue>, Completion>,
+        kind: ErrorKind,
+    ) -> bool {
+        matches!(result, Err(Completion::Error(k, _)) if k == kind)
+    }
+
+    #[test]
+    fn test_closures() {
+        assert_eq!(
+            number(3.0),
+            eval_last(
+                "function counter() { var n = 0; return function () { return ++n; }; }
+                 var c = counter(); c(); c(); c()"
+            )
+        );
+        // 関数は値として渡せる
+        assert_eq!(
+            number(6.0),
+            eval_last("var twice = f => x => f(f(x)); twice(x => x * 2)(1.5)")
+        );
+        assert_eq!(
+            number(120.0),
+            eval_last("var fact = function f(n) { return n <= 1 ? 1 : n * f(n - 1); }; fact(5)")
+        );
+        assert_eq!(string("f"), eval_last("let f = () => {}; f.name"));
+        assert!(throws(
+            eval_last("undefinedFunction()"),
+            ErrorKind::ReferenceError
+        ));
+        assert!(throws(eval_last("var x = 1; x()"), ErrorKind::TypeError));
+    }
+
+    #[test]
+    fn test_lexical_scope() {
+        assert_eq!(number(1.0), eval_last("let a = 1; { let a = 2; } a"));
+        assert_eq!(number(2.0), eval_last("var a = 1; { var a = 2; } a"));
+        assert!(throws(
+            eval_last("{ a; let a = 1; }"),
+            ErrorKind::ReferenceError
+        ));
+        assert!(throws(
+            eval_last("const c = 1; c = 2"),
+            ErrorKind::TypeError
+        ));
+        assert!(throws(eval_last("let a; let a;"), ErrorKind::SyntaxError));
+        // 宣言より前でもtypeofは例外を投げる
+        assert!(throws(
+            eval_last("typeof x; let x;"),
+            ErrorKind::ReferenceError
+        ));
+        // for文の`let`は繰り返しごとに別の変数になる
+        assert_eq!(
+            string("012"),
+            eval_last(
+                "var fs = []; for (let i = 0; i < 3; i++) fs[i] = () => i;
+                 '' + fs[0]() + fs[1]() + fs[2]()"
+            )
+        );
+        assert_eq!(
+            string("ab"),
+            eval_last(
+                "var fs = []; for (const x of 'ab') fs[fs.length] = () => x; fs[0]() + fs[1]()"
+            )
+        );
+    }
+
+    #[test]
+    fn test_hoisting() {
+        assert_eq!(
+            number(3.0),
+            eval_last("var r = add(1, 2); function add(a, b) { return a + b; } r")
+        );
+        assert_eq!(
+            Ok(Some(RuntimeValue::Undefined)),
+            eval_last("function f() { var before = v; var v = 1; return before; } f()")
+        );
+        assert_eq!(
+            string("undefined"),
+            eval_last("function f() { if (false) { var v = 1; } return typeof v; } f()")
+        );
+    }
+
+    #[test]
+    fn test_this() {
+        assert_eq!(
+            number(2.0),
+            eval_last("var o = { n: 1, inc() { this.n++; return this.n; } }; o.inc()")
+        );
+        // アロー関数は外側のthisを使う
+        assert_eq!(
+            number(1.0),
+            eval_last("var o = { n: 1, f() { return (() => this.n)(); } }; o.f()")
+        );
+        assert_eq!(
+            string("ab"),
+            eval_last(
+                "function P(a) { this.a = a; } P.prototype.get = function () { return this.a + 'b'; };
+                 new P('a').get()"
+            )
+        );
+        assert_eq!(
+            boolean(true),
+            eval_last("function P() {} new P() instanceof P")
+        );
+        assert_eq!(
+            boolean(false),
+            eval_last("function P() {} ({}) instanceof P")
+        );
+        assert!(throws(eval_last("new (() => {})"), ErrorKind::TypeError));
+        // トップレベルのthisはグローバルオブジェクト
+        assert_eq!(string("object"), eval_last("typeof this"));
+        assert_eq!(boolean(true), eval_last("this === globalThis"));
+        assert_eq!(boolean(true), eval_last("var f = () => this; f() === this"));
+        assert_eq!(number(1.0), eval_last("this.x = 1; { this.x; }"));
+        assert_eq!(
+            number(3.0),
+            eval_last("var o = { get x() { return 1; }, set x(v) { this.y = v + this.x; } }; o.x = 2; o.y")
+        );
+    }
+
+    #[test]
+    fn test_parameters() {
+        assert_eq!(
+            Ok(Some(RuntimeValue::Undefined)),
+            eval_last("function f(a, b) { return b; } f(1)")
+        );
+        assert_eq!(
+            number(1.0),
+            eval_last("function f(a) { return a; } f(1, 2, 3)")
+        );
+        assert_eq!(
+            number(5.0),
+            eval_last("function f(a, b = a + 1) { return a + b; } f(2)")
+        );
+        assert_eq!(
+            number(3.0),
+            eval_last("function f(a, ...rest) { return rest.length; } f(...[1, 2], 3, 4)")
+        );
+        assert_eq!(
+            number(1.0),
+            eval_last("function f(a, b = 1, ...c) {} f.length")
+        );
+        assert!(throws(
+            eval_last("function f(a = b, b) {} f()"),
+            ErrorKind::ReferenceError
+        ));
+    }
 }
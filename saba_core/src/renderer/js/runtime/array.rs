@@ -0,0 +1,820 @@
+//! Arrayのコンストラクタとメソッド
+//! メソッドは配列以外のオブジェクトにも使えるように、lengthと添字のプロパティを読み書きする
+//! https://262.ecma-international.org/11.0/#sec-array-objects
+
+use crate::renderer::js::heap::ObjectId;
+use crate::renderer::js::object::same_value_zero;
+use crate::renderer::js::object::ObjectKind;
+use crate::renderer::js::object::PropertyKey;
+use crate::renderer::js::runtime::builtins::argument;
+use crate::renderer::js::runtime::builtins::Builtin;
+use crate::renderer::js::runtime::builtins::ObjectBuiltin;
+use crate::renderer::js::runtime::Completion;
+use crate::renderer::js::runtime::ErrorKind;
+use crate::renderer::js::runtime::JsRuntime;
+use crate::renderer::js::value::string_less_than;
+use crate::renderer::js::value::to_uint32;
+use crate::renderer::js::value::RuntimeValue;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::cmp::Ordering;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ArrayBuiltin {
+    Constructor,
+    From,
+    IsArray,
+    Of,
+    Concat,
+    Every,
+    Fill,
+    Filter,
+    Find,
+    FindIndex,
+    Flat,
+    ForEach,
+    Includes,
+    IndexOf,
+    Join,
+    LastIndexOf,
+    Map,
+    Pop,
+    Push,
+    Reduce,
+    ReduceRight,
+    Reverse,
+    Shift,
+    Slice,
+    Some,
+    Sort,
+    Splice,
+    ToString,
+    Unshift,
+}
+
+impl ArrayBuiltin {
+    const STATIC_METHODS: [ArrayBuiltin; 3] =
+        [ArrayBuiltin::From, ArrayBuiltin::IsArray, ArrayBuiltin::Of];
+
+    const PROTOTYPE_METHODS: [ArrayBuiltin; 25] = [
+        ArrayBuiltin::Concat,
+        ArrayBuiltin::Every,
+        ArrayBuiltin::Fill,
+        ArrayBuiltin::Filter,
+        ArrayBuiltin::Find,
+        ArrayBuiltin::FindIndex,
+        ArrayBuiltin::Flat,
+        ArrayBuiltin::ForEach,
+        ArrayBuiltin::Includes,
+        ArrayBuiltin::IndexOf,
+        ArrayBuiltin::Join,
+        ArrayBuiltin::LastIndexOf,
+        ArrayBuiltin::Map,
+        ArrayBuiltin::Pop,
+        ArrayBuiltin::Push,
+        ArrayBuiltin::Reduce,
+        ArrayBuiltin::ReduceRight,
+        ArrayBuiltin::Reverse,
+        ArrayBuiltin::Shift,
+        ArrayBuiltin::Slice,
+        ArrayBuiltin::Some,
+        ArrayBuiltin::Sort,
+        ArrayBuiltin::Splice,
+        ArrayBuiltin::ToString,
+        ArrayBuiltin::Unshift,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            ArrayBuiltin::Constructor => "Array",
+            ArrayBuiltin::From => "from",
+            ArrayBuiltin::IsArray => "isArray",
+            ArrayBuiltin::Of => "of",
+            ArrayBuiltin::Concat => "concat",
+            ArrayBuiltin::Every => "every",
+            ArrayBuiltin::Fill => "fill",
+            ArrayBuiltin::Filter => "filter",
+            ArrayBuiltin::Find => "find",
+            ArrayBuiltin::FindIndex => "findIndex",
+            ArrayBuiltin::Flat => "flat",
+            ArrayBuiltin::ForEach => "forEach",
+            ArrayBuiltin::Includes => "includes",
+            ArrayBuiltin::IndexOf => "indexOf",
+            ArrayBuiltin::Join => "join",
+            ArrayBuiltin::LastIndexOf => "lastIndexOf",
+            ArrayBuiltin::Map => "map",
+            ArrayBuiltin::Pop => "pop",
+            ArrayBuiltin::Push => "push",
+            ArrayBuiltin::Reduce => "reduce",
+            ArrayBuiltin::ReduceRight => "reduceRight",
+            ArrayBuiltin::Reverse => "reverse",
+            ArrayBuiltin::Shift => "shift",
+            ArrayBuiltin::Slice => "slice",
+            ArrayBuiltin::Some => "some",
+            ArrayBuiltin::Sort => "sort",
+            ArrayBuiltin::Splice => "splice",
+            ArrayBuiltin::ToString => "toString",
+            ArrayBuiltin::Unshift => "unshift",
+        }
+    }
+
+    pub fn length(&self) -> usize {
+        match self {
+            ArrayBuiltin::Of
+            | ArrayBuiltin::Flat
+            | ArrayBuiltin::Pop
+            | ArrayBuiltin::Reverse
+            | ArrayBuiltin::Shift
+            | ArrayBuiltin::ToString => 0,
+            ArrayBuiltin::Slice | ArrayBuiltin::Splice => 2,
+            _ => 1,
+        }
+    }
+}
+
+impl JsRuntime {
+    pub(super) fn install_array(&mut self) {
+        let prototype = self.intrinsics.array_prototype;
+        let constructor =
+            self.define_constructor(Builtin::Array(ArrayBuiltin::Constructor), prototype);
+        for method in ArrayBuiltin::STATIC_METHODS {
+            self.define_builtin_method(constructor, Builtin::Array(method));
+        }
+        for method in ArrayBuiltin::PROTOTYPE_METHODS {
+            self.define_builtin_method(prototype, Builtin::Array(method));
+        }
+    }
+
+    pub(super) fn is_array(&self, value: &RuntimeValue) -> bool {
+        match value {
+            RuntimeValue::Object(id) => matches!(self.heap.get(*id).kind(), ObjectKind::Array),
+            _ => false,
+        }
+    }
+
+    fn set_length(&mut self, object: ObjectId, length: usize) -> Result<(), Completion> {
+        self.set_or_throw(
+            object,
+            PropertyKey::from("length"),
+            RuntimeValue::Number(length as f64),
+        )
+    }
+
+    /// コールバックを受け取るメソッドの、関数とthisの引数
+    fn callback_arguments(
+        &self,
+        arguments: &[RuntimeValue],
+    ) -> Result<(RuntimeValue, RuntimeValue), Completion> {
+        let callback = argument(arguments, 0);
+        self.require_callable(&callback)?;
+        Ok((callback, argument(arguments, 1)))
+    }
+
+    /// `new Array(3)`は長さ3の空の配列、それ以外は引数を要素にした配列を作る
+    fn construct_array(&mut self, arguments: &[RuntimeValue]) -> Result<RuntimeValue, Completion> {
+        if let [RuntimeValue::Number(n)] = arguments {
+            if to_uint32(*n) as f64 != *n {
+                return Err(Completion::error(
+                    ErrorKind::RangeError,
+                    "Invalid array length",
+                ));
+            }
+            let array = self.create_array(Vec::new())?;
+            self.set_length(array, *n as usize)?;
+            return Ok(RuntimeValue::Object(array));
+        }
+        Ok(RuntimeValue::Object(self.create_array(arguments.to_vec())?))
+    }
+
+    /// Array.from。繰り返せる値と、配列のようなオブジェクトから配列を作る
+    fn array_from(&mut self, arguments: &[RuntimeValue]) -> Result<RuntimeValue, Completion> {
+        let items = argument(arguments, 0);
+        let map = argument(arguments, 1);
+        if !matches!(map, RuntimeValue::Undefined) {
+            self.require_callable(&map)?;
+        }
+
+        let iterable = match &items {
+            RuntimeValue::String(_) => true,
+            RuntimeValue::Object(id) => matches!(
+                self.heap.get(*id).kind(),
+                ObjectKind::Array | ObjectKind::Arguments | ObjectKind::Map(_) | ObjectKind::Set(_)
+            ),
+            _ => false,
+        };
+        let values = if iterable {
+            self.iterate(items)?
+        } else {
+            let object = self.coerce_to_object(&items)?;
+            let mut values = Vec::new();
+            for k in 0..self.length_of(object)? {
+                values.push(self.get(
+                    object,
+                    &PropertyKey::from(k),
+                    RuntimeValue::Object(object),
+                )?);
+            }
+            values
+        };
+
+        if let RuntimeValue::Undefined = map {
+            return Ok(RuntimeValue::Object(self.create_array(values)?));
+        }
+        let this = argument(arguments, 2);
+        let mut mapped = Vec::new();
+        for (k, value) in values.into_iter().enumerate() {
+            mapped.push(self.call(&map, this.clone(), &[value, RuntimeValue::Number(k as f64)])?);
+        }
+        Ok(RuntimeValue::Object(self.create_array(mapped)?))
+    }
+
+    pub(super) fn call_array(
+        &mut self,
+        function: ArrayBuiltin,
+        this: RuntimeValue,
+        arguments: &[RuntimeValue],
+    ) -> Result<RuntimeValue, Completion> {
+        let first = argument(arguments, 0);
+        match function {
+            ArrayBuiltin::Constructor => return self.construct_array(arguments),
+            ArrayBuiltin::From => return self.array_from(arguments),
+            ArrayBuiltin::IsArray => return Ok(RuntimeValue::Boolean(self.is_array(&first))),
+            ArrayBuiltin::Of => {
+                return Ok(RuntimeValue::Object(self.create_array(arguments.to_vec())?))
+            }
+            _ => {}
+        }
+
+        let object = self.coerce_to_object(&this)?;
+        let receiver = RuntimeValue::Object(object);
+        let length = self.length_of(object)?;
+        let result = match function {
+            ArrayBuiltin::Concat => {
+                let result = self.create_array(Vec::new())?;
+                let mut n = 0;
+                for item in core::iter::once(&receiver).chain(arguments) {
+                    let source = match item {
+                        RuntimeValue::Object(id) if self.is_array(item) => *id,
+                        _ => {
+                            self.create_data_property(result, PropertyKey::from(n), item.clone())?;
+                            n += 1;
+                            continue;
+                        }
+                    };
+                    for k in 0..self.length_of(source)? {
+                        let key = PropertyKey::from(k);
+                        if self.has_property(source, &key) {
+                            let value = self.get(source, &key, item.clone())?;
+                            self.create_data_property(result, PropertyKey::from(n), value)?;
+                        }
+                        n += 1;
+                    }
+                }
+                self.set_length(result, n)?;
+                RuntimeValue::Object(result)
+            }
+            ArrayBuiltin::Every
+            | ArrayBuiltin::Filter
+            | ArrayBuiltin::ForEach
+            | ArrayBuiltin::Map
+            | ArrayBuiltin::Some => {
+                let (callback, this_arg) = self.callback_arguments(arguments)?;
+                let mapped = self.create_array(Vec::new())?;
+                if function == ArrayBuiltin::Map {
+                    self.set_length(mapped, length)?;
+                }
+                let mut filtered = Vec::new();
+                // 存在しない要素は飛ばす
+                for k in 0..length {
+                    let key = PropertyKey::from(k);
+                    if !self.has_property(object, &key) {
+                        continue;
+                    }
+                    let value = self.get(object, &key, receiver.clone())?;
+                    let result = self.call(
+                        &callback,
+                        this_arg.clone(),
+                        &[
+                            value.clone(),
+                            RuntimeValue::Number(k as f64),
+                            receiver.clone(),
+                        ],
+                    )?;
+                    match function {
+                        ArrayBuiltin::Every if !result.to_boolean() => {
+                            return Ok(RuntimeValue::Boolean(false))
+                        }
+                        ArrayBuiltin::Some if result.to_boolean() => {
+                            return Ok(RuntimeValue::Boolean(true))
+                        }
+                        ArrayBuiltin::Filter if result.to_boolean() => filtered.push(value),
+                        ArrayBuiltin::Map => {
+                            self.create_data_property(mapped, key, result)?;
+                        }
+                        _ => {}
+                    }
+                }
+                match function {
+                    ArrayBuiltin::Every => RuntimeValue::Boolean(true),
+                    ArrayBuiltin::Some => RuntimeValue::Boolean(false),
+                    ArrayBuiltin::Filter => RuntimeValue::Object(self.create_array(filtered)?),
+                    ArrayBuiltin::Map => RuntimeValue::Object(mapped),
+                    _ => RuntimeValue::Undefined,
+                }
+            }
+            ArrayBuiltin::Fill => {
+                let start = self.relative_index(argument(arguments, 1), length, 0)?;
+                let end = self.relative_index(argument(arguments, 2), length, length)?;
+                for k in start..end {
+                    self.set_or_throw(object, PropertyKey::from(k), first.clone())?;
+                }
+                receiver
+            }
+            ArrayBuiltin::Find | ArrayBuiltin::FindIndex => {
+                let (callback, this_arg) = self.callback_arguments(arguments)?;
+                for k in 0..length {
+                    let value = self.get(object, &PropertyKey::from(k), receiver.clone())?;
+                    let index = RuntimeValue::Number(k as f64);
+                    let found = self.call(
+                        &callback,
+                        this_arg.clone(),
+                        &[value.clone(), index.clone(), receiver.clone()],
+                    )?;
+                    if found.to_boolean() {
+                        return Ok(match function {
+                            ArrayBuiltin::Find => value,
+                            _ => index,
+                        });
+                    }
+                }
+                match function {
+                    ArrayBuiltin::Find => RuntimeValue::Undefined,
+                    _ => RuntimeValue::Number(-1.0),
+                }
+            }
+            ArrayBuiltin::Flat => {
+                let depth = match first {
+                    RuntimeValue::Undefined => 1.0,
+                    v => self.coerce_to_integer(v)?,
+                };
+                let result = self.create_array(Vec::new())?;
+                self.flatten_into_array(result, object, length, 0, depth)?;
+                RuntimeValue::Object(result)
+            }
+            ArrayBuiltin::Includes | ArrayBuiltin::IndexOf => {
+                if length == 0 {
+                    return Ok(match function {
+                        ArrayBuiltin::Includes => RuntimeValue::Boolean(false),
+                        _ => RuntimeValue::Number(-1.0),
+                    });
+                }
+                let n = self.coerce_to_integer(argument(arguments, 1))?;
+                let start = if n >= 0.0 {
+                    n.min(length as f64) as usize
+                } else {
+                    (length as f64 + n).max(0.0) as usize
+                };
+                for k in start..length {
+                    let key = PropertyKey::from(k);
+                    // includesは存在しない要素をundefinedとして比べ、NaNも見つける
+                    if function == ArrayBuiltin::Includes {
+                        let value = self.get(object, &key, receiver.clone())?;
+                        if same_value_zero(&value, &first) {
+                            return Ok(RuntimeValue::Boolean(true));
+                        }
+                    } else if self.has_property(object, &key) {
+                        let value = self.get(object, &key, receiver.clone())?;
+                        if value.strict_equals(&first) {
+                            return Ok(RuntimeValue::Number(k as f64));
+                        }
+                    }
+                }
+                match function {
+                    ArrayBuiltin::Includes => RuntimeValue::Boolean(false),
+                    _ => RuntimeValue::Number(-1.0),
+                }
+            }
+            ArrayBuiltin::Join => {
+                let separator = match first {
+                    RuntimeValue::Undefined => ",".into(),
+                    v => self.coerce_to_string(v)?,
+                };
+                let mut result = String::new();
+                for k in 0..length {
+                    if k > 0 {
+                        result.push_str(&separator);
+                    }
+                    let element = self.get(object, &PropertyKey::from(k), receiver.clone())?;
+                    if !element.is_nullish() {
+                        result.push_str(&self.coerce_to_string(element)?);
+                    }
+                }
+                RuntimeValue::String(result)
+            }
+            ArrayBuiltin::LastIndexOf => {
+                if length == 0 {
+                    return Ok(RuntimeValue::Number(-1.0));
+                }
+                let n = if arguments.len() > 1 {
+                    self.coerce_to_integer(argument(arguments, 1))?
+                } else {
+                    length as f64 - 1.0
+                };
+                let start = if n >= 0.0 {
+                    n.min(length as f64 - 1.0)
+                } else {
+                    length as f64 + n
+                };
+                if start >= 0.0 {
+                    for k in (0..=start as usize).rev() {
+                        let key = PropertyKey::from(k);
+                        if self.has_property(object, &key)
+                            && self
+                                .get(object, &key, receiver.clone())?
+                                .strict_equals(&first)
+                        {
+                            return Ok(RuntimeValue::Number(k as f64));
+                        }
+                    }
+                }
+                RuntimeValue::Number(-1.0)
+            }
+            ArrayBuiltin::Pop => {
+                if length == 0 {
+                    self.set_length(object, 0)?;
+                    return Ok(RuntimeValue::Undefined);
+                }
+                let key = PropertyKey::from(length - 1);
+                let element = self.get(object, &key, receiver)?;
+                self.delete_or_throw(object, &key)?;
+                self.set_length(object, length - 1)?;
+                element
+            }
+            ArrayBuiltin::Push => {
+                for (i, value) in arguments.iter().enumerate() {
+                    self.set_or_throw(object, PropertyKey::from(length + i), value.clone())?;
+                }
+                let length = length + arguments.len();
+                self.set_length(object, length)?;
+                RuntimeValue::Number(length as f64)
+            }
+            ArrayBuiltin::Reduce | ArrayBuiltin::ReduceRight => {
+                let callback = first;
+                self.require_callable(&callback)?;
+                let mut indices: Vec<usize> = (0..length).collect();
+                if function == ArrayBuiltin::ReduceRight {
+                    indices.reverse();
+                }
+                let mut indices = indices.into_iter();
+
+                // 初期値がない場合は、最初に見つかった要素から始める
+                let mut accumulator = if arguments.len() >= 2 {
+                    argument(arguments, 1)
+                } else {
+                    loop {
+                        match indices.next() {
+                            Some(k) if self.has_property(object, &PropertyKey::from(k)) => {
+                                break self.get(object, &PropertyKey::from(k), receiver.clone())?
+                            }
+                            Some(_) => {}
+                            None => {
+                                return Err(Completion::error(
+                                    ErrorKind::TypeError,
+                                    "Reduce of empty array with no initial value",
+                                ))
+                            }
+                        }
+                    }
+                };
+                for k in indices {
+                    let key = PropertyKey::from(k);
+                    if !self.has_property(object, &key) {
+                        continue;
+                    }
+                    let value = self.get(object, &key, receiver.clone())?;
+                    accumulator = self.call(
+                        &callback,
+                        RuntimeValue::Undefined,
+                        &[
+                            accumulator,
+                            value,
+                            RuntimeValue::Number(k as f64),
+                            receiver.clone(),
+                        ],
+                    )?;
+                }
+                accumulator
+            }
+            ArrayBuiltin::Reverse => {
+                for lower in 0..length / 2 {
+                    let upper = length - 1 - lower;
+                    let lower_key = PropertyKey::from(lower);
+                    let upper_key = PropertyKey::from(upper);
+                    let lower_value = match self.has_property(object, &lower_key) {
+                        true => Some(self.get(object, &lower_key, receiver.clone())?),
+                        false => None,
+                    };
+                    let upper_value = match self.has_property(object, &upper_key) {
+                        true => Some(self.get(object, &upper_key, receiver.clone())?),
+                        false => None,
+                    };
+                    match upper_value {
+                        Some(value) => self.set_or_throw(object, lower_key.clone(), value)?,
+                        None => self.delete_or_throw(object, &lower_key)?,
+                    }
+                    match lower_value {
+                        Some(value) => self.set_or_throw(object, upper_key, value)?,
+                        None => self.delete_or_throw(object, &upper_key)?,
+                    }
+                }
+                receiver
+            }
+            ArrayBuiltin::Shift => {
+                if length == 0 {
+                    self.set_length(object, 0)?;
+                    return Ok(RuntimeValue::Undefined);
+                }
+                let first = self.get(object, &PropertyKey::from(0), receiver)?;
+                self.move_elements(object, 1..length, |k| k - 1)?;
+                self.delete_or_throw(object, &PropertyKey::from(length - 1))?;
+                self.set_length(object, length - 1)?;
+                first
+            }
+            ArrayBuiltin::Slice => {
+                let start = self.relative_index(first, length, 0)?;
+                let end = self.relative_index(argument(arguments, 1), length, length)?;
+                let result = self.create_array(Vec::new())?;
+                let mut n = 0;
+                for k in start..end.max(start) {
+                    let key = PropertyKey::from(k);
+                    if self.has_property(object, &key) {
+                        let value = self.get(object, &key, receiver.clone())?;
+                        self.create_data_property(result, PropertyKey::from(n), value)?;
+                    }
+                    n += 1;
+                }
+                self.set_length(result, n)?;
+                RuntimeValue::Object(result)
+            }
+            ArrayBuiltin::Sort => {
+                if !matches!(first, RuntimeValue::Undefined) && !self.is_callable(&first) {
+                    return Err(Completion::error(
+                        ErrorKind::TypeError,
+                        "The comparison function must be either a function or undefined",
+                    ));
+                }
+                // undefinedは後ろに集め、存在しない要素はさらにその後ろにする
+                let mut values = Vec::new();
+                let mut undefined_count = 0;
+                for k in 0..length {
+                    let key = PropertyKey::from(k);
+                    if self.has_property(object, &key) {
+                        match self.get(object, &key, receiver.clone())? {
+                            RuntimeValue::Undefined => undefined_count += 1,
+                            value => values.push(value),
+                        }
+                    }
+                }
+                let sorted = self.sort_values(values, &first)?;
+                let count = sorted.len() + undefined_count;
+                let undefined = core::iter::repeat(RuntimeValue::Undefined).take(undefined_count);
+                for (k, value) in sorted.into_iter().chain(undefined).enumerate() {
+                    self.set_or_throw(object, PropertyKey::from(k), value)?;
+                }
+                for k in count..length {
+                    self.delete_or_throw(object, &PropertyKey::from(k))?;
+                }
+                receiver
+            }
+            ArrayBuiltin::Splice => {
+                let start = self.relative_index(first, length, 0)?;
+                let delete_count = match arguments.len() {
+                    0 => 0,
+                    1 => length - start,
+                    _ => {
+                        let n = self.coerce_to_integer(argument(arguments, 1))?;
+                        n.max(0.0).min((length - start) as f64) as usize
+                    }
+                };
+                let items = arguments.get(2..).unwrap_or_default();
+
+                let removed = self.create_array(Vec::new())?;
+                for k in 0..delete_count {
+                    let key = PropertyKey::from(start + k);
+                    if self.has_property(object, &key) {
+                        let value = self.get(object, &key, receiver.clone())?;
+                        self.create_data_property(removed, PropertyKey::from(k), value)?;
+                    }
+                }
+                self.set_length(removed, delete_count)?;
+
+                // 削除した数と挿入する数の差だけ、後ろの要素をずらす
+                let item_count = items.len();
+                let tail = start + delete_count..length;
+                match item_count.cmp(&delete_count) {
+                    Ordering::Less => {
+                        self.move_elements(object, tail, |k| k - delete_count + item_count)?;
+                        for k in (length - delete_count + item_count..length).rev() {
+                            self.delete_or_throw(object, &PropertyKey::from(k))?;
+                        }
+                    }
+                    Ordering::Greater => {
+                        self.move_elements(object, tail.rev(), |k| k - delete_count + item_count)?
+                    }
+                    Ordering::Equal => {}
+                }
+                for (i, item) in items.iter().enumerate() {
+                    self.set_or_throw(object, PropertyKey::from(start + i), item.clone())?;
+                }
+                self.set_length(object, length - delete_count + item_count)?;
+                RuntimeValue::Object(removed)
+            }
+            ArrayBuiltin::ToString => {
+                let join = self.get(object, &PropertyKey::from("join"), receiver.clone())?;
+                if self.is_callable(&join) {
+                    self.call(&join, receiver, &[])?
+                } else {
+                    self.call_builtin(Builtin::Object(ObjectBuiltin::ToString), receiver, &[])?
+                }
+            }
+            ArrayBuiltin::Unshift => {
+                let count = arguments.len();
+                if count > 0 {
+                    self.move_elements(object, (0..length).rev(), |k| k + count)?;
+                    for (i, value) in arguments.iter().enumerate() {
+                        self.set_or_throw(object, PropertyKey::from(i), value.clone())?;
+                    }
+                }
+                self.set_length(object, length + count)?;
+                RuntimeValue::Number((length + count) as f64)
+            }
+            _ => RuntimeValue::Undefined,
+        };
+        Ok(result)
+    }
+
+    /// `from`の位置の要素を`to(from)`の位置に移す。存在しない要素は移し先を削除する
+    fn move_elements(
+        &mut self,
+        object: ObjectId,
+        from: impl Iterator<Item = usize>,
+        to: impl Fn(usize) -> usize,
+    ) -> Result<(), Completion> {
+        for k in from {
+            let from_key = PropertyKey::from(k);
+            let to_key = PropertyKey::from(to(k));
+            if self.has_property(object, &from_key) {
+                let value = self.get(object, &from_key, RuntimeValue::Object(object))?;
+                self.set_or_throw(object, to_key, value)?;
+            } else {
+                self.delete_or_throw(object, &to_key)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// FlattenIntoArray。`depth`の深さまでの配列を展開して`target`に追加し、次の位置を返す
+    fn flatten_into_array(
+        &mut self,
+        target: ObjectId,
+        source: ObjectId,
+        length: usize,
+        start: usize,
+        depth: f64,
+    ) -> Result<usize, Completion> {
+        let mut index = start;
+        for k in 0..length {
+            let key = PropertyKey::from(k);
+            if !self.has_property(source, &key) {
+                continue;
+            }
+            let element = self.get(source, &key, RuntimeValue::Object(source))?;
+            match element {
+                RuntimeValue::Object(id) if depth > 0.0 && self.is_array(&element) => {
+                    let length = self.length_of(id)?;
+                    index = self.flatten_into_array(target, id, length, index, depth - 1.0)?;
+                }
+                _ => {
+                    self.create_data_property(target, PropertyKey::from(index), element)?;
+                    index += 1;
+                }
+            }
+        }
+        Ok(index)
+    }
+
+    /// 比較関数が例外を投げることがあるため、自前の安定なマージソートで並べる
+    fn sort_values(
+        &mut self,
+        values: Vec<RuntimeValue>,
+        comparator: &RuntimeValue,
+    ) -> Result<Vec<RuntimeValue>, Completion> {
+        if values.len() <= 1 {
+            return Ok(values);
+        }
+        let mut left = values;
+        let right = left.split_off(left.len() / 2);
+        let left = self.sort_values(left, comparator)?;
+        let right = self.sort_values(right, comparator)?;
+
+        let mut merged = Vec::with_capacity(left.len() + right.len());
+        let mut left = left.into_iter().peekable();
+        let mut right = right.into_iter().peekable();
+        while let (Some(l), Some(r)) = (left.peek(), right.peek()) {
+            // 等しい場合は左の要素を先にする
+            if self.compare_values(r.clone(), l.clone(), comparator)? < 0.0 {
+                merged.extend(right.next());
+            } else {
+                merged.extend(left.next());
+            }
+        }
+        merged.extend(left);
+        merged.extend(right);
+        Ok(merged)
+    }
+
+    /// SortCompare。比較関数がない場合は文字列として比べる
+    fn compare_values(
+        &mut self,
+        x: RuntimeValue,
+        y: RuntimeValue,
+        comparator: &RuntimeValue,
+    ) -> Result<f64, Completion> {
+        if !matches!(comparator, RuntimeValue::Undefined) {
+            let result = self.call(comparator, RuntimeValue::Undefined, &[x, y])?;
+            let n = self.coerce_to_number(result)?;
+            return Ok(if n.is_nan() { 0.0 } else { n });
+        }
+        let x = self.coerce_to_string(x)?;
+        let y = self.coerce_to_string(y)?;
+        Ok(if string_less_than(&x, &y) {
+            -1.0
+        } else if string_less_than(&y, &x) {
+            1.0
+        } else {
+            0.0
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::renderer::js::runtime::tests::eval_last;
+    use crate::renderer::js::runtime::tests::number;
+    use crate::renderer::js::runtime::tests::string;
+    use crate::renderer::js::runtime::tests::throws;
+
+    #[test]
+    fn test_array_like_and_coercion() {
+        // 配列でないオブジェクトにも、lengthを数値に変換して使う
+        assert_eq!(
+            string("a,b"),
+            eval_last("Array.prototype.join.call({ length: '2', 0: 'a', 1: 'b', 2: 'c' })")
+        );
+        assert_eq!(
+            string("0,2"),
+            eval_last("Array.from({ length: 2 }, (v, i) => i * 2).join()")
+        );
+        assert_eq!(string("2,3"), eval_last("[1, 2, 3].slice(-2, '3').join()"));
+        assert_eq!(number(-1.0), eval_last("[1, 2, 3].indexOf(2, -1)"));
+        // includesはNaNを見つけるが、indexOfは見つけない
+        assert_eq!(
+            string("true,-1"),
+            eval_last("[[NaN].includes(NaN), [NaN].indexOf(NaN)].join()")
+        );
+        // 比較関数がなければ文字列として比べ、undefinedと穴は後ろに置く
+        assert_eq!(string("100,20,3"), eval_last("[3, 20, 100].sort().join()"));
+        assert_eq!(
+            string("1,2,,:4"),
+            eval_last("var a = [undefined, 2, , 1].sort(); a.join() + ':' + a.length")
+        );
+        assert_eq!(
+            string("1,2,3,4"),
+            eval_last("[1, [2, [3, [4]]]].flat(Infinity).join()")
+        );
+        assert_eq!(
+            string("1"),
+            eval_last("var a = [1, 2, 3]; a.length = 1; a.join()")
+        );
+        assert_eq!(number(1.0), eval_last("new Array('3').length"));
+    }
+
+    #[test]
+    fn test_array_errors() {
+        assert!(throws(eval_last("new Array(-1)"), ErrorKind::RangeError));
+        assert!(throws(eval_last("new Array(2.5)"), ErrorKind::RangeError));
+        assert!(throws(eval_last("[].length = -1"), ErrorKind::RangeError));
+        // 初期値のない空の配列は畳み込めない
+        assert!(throws(
+            eval_last("[].reduce((a, b) => a + b)"),
+            ErrorKind::TypeError
+        ));
+        assert!(throws(eval_last("[1].map(1)"), ErrorKind::TypeError));
+        assert!(throws(eval_last("[1].sort(1)"), ErrorKind::TypeError));
+        assert!(throws(
+            eval_last("Array.prototype.forEach.call(null, () => {})"),
+            ErrorKind::TypeError
+        ));
+    }
+}
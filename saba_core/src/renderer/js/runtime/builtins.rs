@@ -0,0 +1,1614 @@
+//! ECMAScriptの標準の組み込みオブジェクト
+//! Object、Function、Boolean、Symbol、Errorとグローバル関数はここで扱い、その他は種類ごとのモジュールに分ける
+//! https://262.ecma-international.org/11.0/#sec-global-object
+
+use crate::renderer::dom::node::NodeKind as DomNodeKind;
+use crate::renderer::js::ast::JsParser;
+use crate::renderer::js::ast::Node;
+use crate::renderer::js::heap::ObjectId;
+use crate::renderer::js::math::trunc;
+use crate::renderer::js::object::same_value;
+use crate::renderer::js::object::HostFunction;
+use crate::renderer::js::object::JsObject;
+use crate::renderer::js::object::ObjectKind;
+use crate::renderer::js::object::PropertyDescriptor;
+use crate::renderer::js::object::PropertyKey;
+use crate::renderer::js::object::PropertyValue;
+use crate::renderer::js::runtime::array::ArrayBuiltin;
+use crate::renderer::js::runtime::collections::MapBuiltin;
+use crate::renderer::js::runtime::collections::SetBuiltin;
+use crate::renderer::js::runtime::date::DateBuiltin;
+use crate::renderer::js::runtime::json::JsonBuiltin;
+use crate::renderer::js::runtime::number::MathBuiltin;
+use crate::renderer::js::runtime::number::NumberBuiltin;
+use crate::renderer::js::runtime::string::StringBuiltin;
+use crate::renderer::js::runtime::Completion;
+use crate::renderer::js::runtime::ErrorKind;
+use crate::renderer::js::runtime::JsRuntime;
+use crate::renderer::js::token::is_line_terminator;
+use crate::renderer::js::token::is_whitespace;
+use crate::renderer::js::token::JsLexer;
+use crate::renderer::js::value::string_to_number;
+use crate::renderer::js::value::to_int32;
+use crate::renderer::js::value::RuntimeValue;
+use crate::renderer::js::value::Symbol;
+use alloc::format;
+use alloc::string::String;
+use alloc::string::ToString;
+use alloc::vec::Vec;
+
+/// 組み込み関数
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Builtin {
+    Object(ObjectBuiltin),
+    Function(FunctionBuiltin),
+    Boolean(BooleanBuiltin),
+    Symbol(SymbolBuiltin),
+    Error(ErrorBuiltin),
+    Global(GlobalBuiltin),
+    Array(ArrayBuiltin),
+    String(StringBuiltin),
+    Number(NumberBuiltin),
+    Math(MathBuiltin),
+    Json(JsonBuiltin),
+    Date(DateBuiltin),
+    Map(MapBuiltin),
+    Set(SetBuiltin),
+}
+
+impl Builtin {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Builtin::Object(f) => f.name(),
+            Builtin::Function(f) => f.name(),
+            Builtin::Boolean(f) => f.name(),
+            Builtin::Symbol(f) => f.name(),
+            Builtin::Error(f) => f.name(),
+            Builtin::Global(f) => f.name(),
+            Builtin::Array(f) => f.name(),
+            Builtin::String(f) => f.name(),
+            Builtin::Number(f) => f.name(),
+            Builtin::Math(f) => f.name(),
+            Builtin::Json(f) => f.name(),
+            Builtin::Date(f) => f.name(),
+            Builtin::Map(f) => f.name(),
+            Builtin::Set(f) => f.name(),
+        }
+    }
+
+    pub fn length(&self) -> usize {
+        match self {
+            Builtin::Object(f) => f.length(),
+            Builtin::Function(f) => f.length(),
+            Builtin::Boolean(f) => f.length(),
+            Builtin::Symbol(f) => f.length(),
+            Builtin::Error(f) => f.length(),
+            Builtin::Global(f) => f.length(),
+            Builtin::Array(f) => f.length(),
+            Builtin::String(f) => f.length(),
+            Builtin::Number(f) => f.length(),
+            Builtin::Math(f) => f.length(),
+            Builtin::Json(f) => f.length(),
+            Builtin::Date(f) => f.length(),
+            Builtin::Map(f) => f.length(),
+            Builtin::Set(f) => f.length(),
+        }
+    }
+
+    /// `new`で呼べるコンストラクタかどうか
+    pub fn is_constructor(&self) -> bool {
+        matches!(
+            self,
+            Builtin::Object(ObjectBuiltin::Constructor)
+                | Builtin::Function(FunctionBuiltin::Constructor)
+                | Builtin::Boolean(BooleanBuiltin::Constructor)
+                | Builtin::Error(ErrorBuiltin::Constructor(_))
+                | Builtin::Array(ArrayBuiltin::Constructor)
+                | Builtin::String(StringBuiltin::Constructor)
+                | Builtin::Number(NumberBuiltin::Constructor)
+                | Builtin::Date(DateBuiltin::Constructor)
+                | Builtin::Map(MapBuiltin::Constructor)
+                | Builtin::Set(SetBuiltin::Constructor)
+        )
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ObjectBuiltin {
+    Constructor,
+    Assign,
+    Create,
+    DefineProperties,
+    DefineProperty,
+    Entries,
+    Freeze,
+    GetOwnPropertyDescriptor,
+    GetOwnPropertyNames,
+    GetPrototypeOf,
+    Is,
+    IsExtensible,
+    IsFrozen,
+    IsSealed,
+    Keys,
+    PreventExtensions,
+    Seal,
+    SetPrototypeOf,
+    Values,
+    HasOwnProperty,
+    IsPrototypeOf,
+    PropertyIsEnumerable,
+    ToLocaleString,
+    ToString,
+    ValueOf,
+}
+
+impl ObjectBuiltin {
+    const STATIC_METHODS: [ObjectBuiltin; 18] = [
+        ObjectBuiltin::Assign,
+        ObjectBuiltin::Create,
+        ObjectBuiltin::DefineProperties,
+        ObjectBuiltin::DefineProperty,
+        ObjectBuiltin::Entries,
+        ObjectBuiltin::Freeze,
+        ObjectBuiltin::GetOwnPropertyDescriptor,
+        ObjectBuiltin::GetOwnPropertyNames,
+        ObjectBuiltin::GetPrototypeOf,
+        ObjectBuiltin::Is,
+        ObjectBuiltin::IsExtensible,
+        ObjectBuiltin::IsFrozen,
+        ObjectBuiltin::IsSealed,
+        ObjectBuiltin::Keys,
+        ObjectBuiltin::PreventExtensions,
+        ObjectBuiltin::Seal,
+        ObjectBuiltin::SetPrototypeOf,
+        ObjectBuiltin::Values,
+    ];
+
+    const PROTOTYPE_METHODS: [ObjectBuiltin; 6] = [
+        ObjectBuiltin::HasOwnProperty,
+        ObjectBuiltin::IsPrototypeOf,
+        ObjectBuiltin::PropertyIsEnumerable,
+        ObjectBuiltin::ToLocaleString,
+        ObjectBuiltin::ToString,
+        ObjectBuiltin::ValueOf,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            ObjectBuiltin::Constructor => "Object",
+            ObjectBuiltin::Assign => "assign",
+            ObjectBuiltin::Create => "create",
+            ObjectBuiltin::DefineProperties => "defineProperties",
+            ObjectBuiltin::DefineProperty => "defineProperty",
+            ObjectBuiltin::Entries => "entries",
+            ObjectBuiltin::Freeze => "freeze",
+            ObjectBuiltin::GetOwnPropertyDescriptor => "getOwnPropertyDescriptor",
+            ObjectBuiltin::GetOwnPropertyNames => "getOwnPropertyNames",
+            ObjectBuiltin::GetPrototypeOf => "getPrototypeOf",
+            ObjectBuiltin::Is => "is",
+            ObjectBuiltin::IsExtensible => "isExtensible",
+            ObjectBuiltin::IsFrozen => "isFrozen",
+            ObjectBuiltin::IsSealed => "isSealed",
+            ObjectBuiltin::Keys => "keys",
+            ObjectBuiltin::PreventExtensions => "preventExtensions",
+            ObjectBuiltin::Seal => "seal",
+            ObjectBuiltin::SetPrototypeOf => "setPrototypeOf",
+            ObjectBuiltin::Values => "values",
+            ObjectBuiltin::HasOwnProperty => "hasOwnProperty",
+            ObjectBuiltin::IsPrototypeOf => "isPrototypeOf",
+            ObjectBuiltin::PropertyIsEnumerable => "propertyIsEnumerable",
+            ObjectBuiltin::ToLocaleString => "toLocaleString",
+            ObjectBuiltin::ToString => "toString",
+            ObjectBuiltin::ValueOf => "valueOf",
+        }
+    }
+
+    pub fn length(&self) -> usize {
+        match self {
+            ObjectBuiltin::Assign
+            | ObjectBuiltin::Create
+            | ObjectBuiltin::DefineProperties
+            | ObjectBuiltin::GetOwnPropertyDescriptor
+            | ObjectBuiltin::Is
+            | ObjectBuiltin::SetPrototypeOf => 2,
+            ObjectBuiltin::DefineProperty => 3,
+            ObjectBuiltin::ToLocaleString | ObjectBuiltin::ToString | ObjectBuiltin::ValueOf => 0,
+            _ => 1,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FunctionBuiltin {
+    Constructor,
+    Apply,
+    Bind,
+    Call,
+    ToString,
+}
+
+impl FunctionBuiltin {
+    pub fn name(&self) -> &'static str {
+        match self {
+            FunctionBuiltin::Constructor => "Function",
+            FunctionBuiltin::Apply => "apply",
+            FunctionBuiltin::Bind => "bind",
+            FunctionBuiltin::Call => "call",
+            FunctionBuiltin::ToString => "toString",
+        }
+    }
+
+    pub fn length(&self) -> usize {
+        match self {
+            FunctionBuiltin::Apply => 2,
+            FunctionBuiltin::ToString => 0,
+            _ => 1,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BooleanBuiltin {
+    Constructor,
+    ToString,
+    ValueOf,
+}
+
+impl BooleanBuiltin {
+    pub fn name(&self) -> &'static str {
+        match self {
+            BooleanBuiltin::Constructor => "Boolean",
+            BooleanBuiltin::ToString => "toString",
+            BooleanBuiltin::ValueOf => "valueOf",
+        }
+    }
+
+    pub fn length(&self) -> usize {
+        match self {
+            BooleanBuiltin::Constructor => 1,
+            _ => 0,
+        }
+    }
+}
+
+/// `Symbol`は関数として呼んで作り、`new`では呼べない
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SymbolBuiltin {
+    Constructor,
+    ToString,
+    ValueOf,
+    Description,
+}
+
+impl SymbolBuiltin {
+    pub fn name(&self) -> &'static str {
+        match self {
+            SymbolBuiltin::Constructor => "Symbol",
+            SymbolBuiltin::ToString => "toString",
+            SymbolBuiltin::ValueOf => "valueOf",
+            SymbolBuiltin::Description => "get description",
+        }
+    }
+
+    pub fn length(&self) -> usize {
+        0
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorBuiltin {
+    Constructor(ErrorKind),
+    ToString,
+}
+
+impl ErrorBuiltin {
+    pub fn name(&self) -> &'static str {
+        match self {
+            ErrorBuiltin::Constructor(kind) => kind.name(),
+            ErrorBuiltin::ToString => "toString",
+        }
+    }
+
+    pub fn length(&self) -> usize {
+        match self {
+            ErrorBuiltin::Constructor(_) => 1,
+            ErrorBuiltin::ToString => 0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GlobalBuiltin {
+    IsFinite,
+    IsNaN,
+    ParseFloat,
+    ParseInt,
+}
+
+impl GlobalBuiltin {
+    pub fn name(&self) -> &'static str {
+        match self {
+            GlobalBuiltin::IsFinite => "isFinite",
+            GlobalBuiltin::IsNaN => "isNaN",
+            GlobalBuiltin::ParseFloat => "parseFloat",
+            GlobalBuiltin::ParseInt => "parseInt",
+        }
+    }
+
+    pub fn length(&self) -> usize {
+        match self {
+            GlobalBuiltin::ParseInt => 2,
+            _ => 1,
+        }
+    }
+}
+
+/// 引数を取り出す。渡されていない場合はundefined
+pub(super) fn argument(arguments: &[RuntimeValue], index: usize) -> RuntimeValue {
+    arguments
+        .get(index)
+        .cloned()
+        .unwrap_or(RuntimeValue::Undefined)
+}
+
+/// 文字列の先頭の空白と改行を取り除く
+pub(super) fn trim_start(s: &str) -> &str {
+    s.trim_start_matches(|c| is_whitespace(c) || is_line_terminator(c))
+}
+
+impl JsRuntime {
+    /// 組み込みオブジェクトを作り、グローバル変数に定義する
+    pub(super) fn install_builtins(&mut self) {
+        let intrinsics = self.intrinsics;
+
+        let object = self.define_constructor(
+            Builtin::Object(ObjectBuiltin::Constructor),
+            intrinsics.object_prototype,
+        );
+        for method in ObjectBuiltin::STATIC_METHODS {
+            self.define_builtin_method(object, Builtin::Object(method));
+        }
+        for method in ObjectBuiltin::PROTOTYPE_METHODS {
+            self.define_builtin_method(intrinsics.object_prototype, Builtin::Object(method));
+        }
+
+        self.define_constructor(
+            Builtin::Function(FunctionBuiltin::Constructor),
+            intrinsics.function_prototype,
+        );
+        for method in [
+            FunctionBuiltin::Apply,
+            FunctionBuiltin::Bind,
+            FunctionBuiltin::Call,
+            FunctionBuiltin::ToString,
+        ] {
+            self.define_builtin_method(intrinsics.function_prototype, Builtin::Function(method));
+        }
+
+        self.define_constructor(
+            Builtin::Boolean(BooleanBuiltin::Constructor),
+            intrinsics.boolean_prototype,
+        );
+        for method in [BooleanBuiltin::ToString, BooleanBuiltin::ValueOf] {
+            self.define_builtin_method(intrinsics.boolean_prototype, Builtin::Boolean(method));
+        }
+
+        self.define_constructor(
+            Builtin::Symbol(SymbolBuiltin::Constructor),
+            intrinsics.symbol_prototype,
+        );
+        for method in [SymbolBuiltin::ToString, SymbolBuiltin::ValueOf] {
+            self.define_builtin_method(intrinsics.symbol_prototype, Builtin::Symbol(method));
+        }
+        self.define_host_accessor(
+            intrinsics.symbol_prototype,
+            "description",
+            HostFunction::Builtin(Builtin::Symbol(SymbolBuiltin::Description)),
+            None,
+        );
+
+        // TypeErrorなどのコンストラクタは、Errorのコンストラクタを継承する
+        let mut error = None;
+        for kind in ErrorKind::ALL {
+            let prototype = intrinsics.error_prototype(kind);
+            let constructor =
+                self.define_constructor(Builtin::Error(ErrorBuiltin::Constructor(kind)), prototype);
+            self.define_value(
+                prototype,
+                "name",
+                RuntimeValue::String(kind.name().to_string()),
+            );
+            self.define_value(prototype, "message", RuntimeValue::String(String::new()));
+            match error {
+                None => error = Some(constructor),
+                Some(error) => {
+                    self.heap.get_mut(constructor).set_prototype(Some(error));
+                }
+            }
+        }
+        self.define_builtin_method(
+            intrinsics.error_prototype(ErrorKind::Error),
+            Builtin::Error(ErrorBuiltin::ToString),
+        );
+
+        for function in [
+            GlobalBuiltin::IsFinite,
+            GlobalBuiltin::IsNaN,
+            GlobalBuiltin::ParseFloat,
+            GlobalBuiltin::ParseInt,
+        ] {
+            let id = self.create_host_function(HostFunction::Builtin(Builtin::Global(function)));
+            self.env
+                .borrow_mut()
+                .initialize(function.name().to_string(), RuntimeValue::Object(id));
+        }
+
+        self.install_array();
+        self.install_string();
+        self.install_number();
+        self.install_math();
+        self.install_json();
+        self.install_date();
+        self.install_collections();
+    }
+
+    /// 組み込み関数を、オブジェクトの列挙できないメソッドとして定義する
+    pub(super) fn define_builtin_method(&mut self, object: ObjectId, builtin: Builtin) {
+        self.define_host_method(object, HostFunction::Builtin(builtin), false);
+    }
+
+    /// コンストラクタを作り、プロトタイプと結びつけて、同じ名前のグローバル変数に入れる
+    pub(super) fn define_constructor(&mut self, builtin: Builtin, prototype: ObjectId) -> ObjectId {
+        let constructor = self.create_host_function(HostFunction::Builtin(builtin));
+        self.heap.get_mut(constructor).define_own_property(
+            PropertyKey::from("prototype"),
+            PropertyDescriptor::data(RuntimeValue::Object(prototype), false, false, false),
+        );
+        self.define_value(prototype, "constructor", RuntimeValue::Object(constructor));
+        self.env.borrow_mut().initialize(
+            builtin.name().to_string(),
+            RuntimeValue::Object(constructor),
+        );
+        constructor
+    }
+
+    /// 列挙できないデータプロパティを定義する
+    pub(super) fn define_value(&mut self, object: ObjectId, name: &str, value: RuntimeValue) {
+        self.heap.get_mut(object).define_own_property(
+            PropertyKey::from(name),
+            PropertyDescriptor::data(value, true, false, true),
+        );
+    }
+
+    /// 書き換えられない定数のプロパティを定義する
+    pub(super) fn define_constant(&mut self, object: ObjectId, name: &str, value: RuntimeValue) {
+        self.heap.get_mut(object).define_own_property(
+            PropertyKey::from(name),
+            PropertyDescriptor::data(value, false, false, false),
+        );
+    }
+
+    /// MathやJSONのような、関数をまとめたオブジェクトをグローバル変数に定義する
+    pub(super) fn define_namespace(&mut self, name: &str, methods: &[Builtin]) -> ObjectId {
+        let namespace = self.heap.allocate(JsObject::new(
+            ObjectKind::Ordinary,
+            Some(self.intrinsics.object_prototype),
+        ));
+        for method in methods {
+            self.define_builtin_method(namespace, *method);
+        }
+        self.env
+            .borrow_mut()
+            .initialize(name.to_string(), RuntimeValue::Object(namespace));
+        namespace
+    }
+
+    /// 組み込み関数を呼び出す
+    pub(super) fn call_builtin(
+        &mut self,
+        builtin: Builtin,
+        this: RuntimeValue,
+        arguments: &[RuntimeValue],
+    ) -> Result<RuntimeValue, Completion> {
+        match builtin {
+            Builtin::Object(f) => self.call_object(f, this, arguments),
+            Builtin::Function(f) => self.call_function(f, this, arguments),
+            Builtin::Boolean(f) => self.call_boolean(f, this, arguments),
+            Builtin::Symbol(f) => self.call_symbol(f, this, arguments),
+            Builtin::Error(f) => self.call_error(f, this, arguments),
+            Builtin::Global(f) => self.call_global(f, arguments),
+            Builtin::Array(f) => self.call_array(f, this, arguments),
+            Builtin::String(f) => self.call_string(f, this, arguments),
+            Builtin::Number(f) => self.call_number(f, this, arguments),
+            Builtin::Math(f) => self.call_math(f, arguments),
+            Builtin::Json(f) => self.call_json(f, arguments),
+            Builtin::Date(f) => self.call_date(f, this, arguments),
+            Builtin::Map(f) => self.call_map(f, this, arguments),
+            Builtin::Set(f) => self.call_set(f, this, arguments),
+        }
+    }
+
+    /// 組み込みのコンストラクタを`new`で呼び出す
+    pub(super) fn construct_builtin(
+        &mut self,
+        builtin: Builtin,
+        arguments: &[RuntimeValue],
+    ) -> Result<RuntimeValue, Completion> {
+        match builtin {
+            Builtin::Boolean(_) => {
+                let value = RuntimeValue::Boolean(argument(arguments, 0).to_boolean());
+                Ok(RuntimeValue::Object(self.create_primitive_wrapper(value)))
+            }
+            Builtin::String(_) => self.construct_string(arguments),
+            Builtin::Number(_) => self.construct_number(arguments),
+            Builtin::Date(_) => self.construct_date(arguments),
+            Builtin::Map(_) => self.construct_map(arguments),
+            Builtin::Set(_) => self.construct_set(arguments),
+            // ObjectとFunction、Array、Errorは、newをつけずに呼んだ場合と同じ
+            _ => self.call_builtin(builtin, RuntimeValue::Undefined, arguments),
+        }
+    }
+
+    /// ToIntegerOrInfinity。NaNは0にする
+    pub(super) fn coerce_to_integer(&mut self, value: RuntimeValue) -> Result<f64, Completion> {
+        let n = self.coerce_to_number(value)?;
+        if n.is_nan() {
+            return Ok(0.0);
+        }
+        // -0は+0にする
+        Ok(trunc(n) + 0.0)
+    }
+
+    /// sliceなどの引数の位置。負の値は末尾から数え、0からlengthの間に収める
+    pub(super) fn relative_index(
+        &mut self,
+        value: RuntimeValue,
+        length: usize,
+        default: usize,
+    ) -> Result<usize, Completion> {
+        if let RuntimeValue::Undefined = value {
+            return Ok(default);
+        }
+        let n = self.coerce_to_integer(value)?;
+        Ok(if n < 0.0 {
+            (length as f64 + n).max(0.0) as usize
+        } else {
+            n.min(length as f64) as usize
+        })
+    }
+
+    /// 書き込めなかった場合にTypeErrorを投げる[[Set]]
+    pub(super) fn set_or_throw(
+        &mut self,
+        object: ObjectId,
+        key: PropertyKey,
+        value: RuntimeValue,
+    ) -> Result<(), Completion> {
+        if self.set(object, key.clone(), value, RuntimeValue::Object(object))? {
+            return Ok(());
+        }
+        Err(Completion::error(
+            ErrorKind::TypeError,
+            &format!("Cannot assign to read only property '{}' of object", key),
+        ))
+    }
+
+    /// 削除できなかった場合にTypeErrorを投げる[[Delete]]
+    pub(super) fn delete_or_throw(
+        &mut self,
+        object: ObjectId,
+        key: &PropertyKey,
+    ) -> Result<(), Completion> {
+        if self.heap.get_mut(object).delete(key) {
+            return Ok(());
+        }
+        Err(Completion::error(
+            ErrorKind::TypeError,
+            &format!("Cannot delete property '{}' of object", key),
+        ))
+    }
+
+    /// 列挙できる自身のプロパティのうち、名前が文字列のもの
+    pub(super) fn enumerable_own_keys(&self, object: ObjectId) -> Vec<PropertyKey> {
+        let object = self.heap.get(object);
+        object
+            .own_property_keys()
+            .into_iter()
+            .filter(|key| {
+                matches!(key, PropertyKey::String(_))
+                    && object.get_own_property(key).is_some_and(|p| p.enumerable())
+            })
+            .collect()
+    }
+
+    /// CreateListFromArrayLike。applyの引数などの配列のようなオブジェクトから値を取り出す
+    pub(super) fn list_from_array_like(
+        &mut self,
+        value: &RuntimeValue,
+    ) -> Result<Vec<RuntimeValue>, Completion> {
+        let id = match value {
+            RuntimeValue::Object(id) => *id,
+            _ => {
+                return Err(Completion::error(
+                    ErrorKind::TypeError,
+                    "CreateListFromArrayLike called on non-object",
+                ))
+            }
+        };
+        let mut values = Vec::new();
+        for i in 0..self.length_of(id)? {
+            values.push(self.get(id, &PropertyKey::from(i), value.clone())?);
+        }
+        Ok(values)
+    }
+
+    /// 値が呼び出せる関数でなければTypeErrorを投げる
+    pub(super) fn require_callable(&self, value: &RuntimeValue) -> Result<(), Completion> {
+        if self.is_callable(value) {
+            return Ok(());
+        }
+        Err(Completion::error(
+            ErrorKind::TypeError,
+            &format!("{} is not a function", value),
+        ))
+    }
+
+    fn call_object(
+        &mut self,
+        function: ObjectBuiltin,
+        this: RuntimeValue,
+        arguments: &[RuntimeValue],
+    ) -> Result<RuntimeValue, Completion> {
+        let first = argument(arguments, 0);
+        let result = match function {
+            ObjectBuiltin::Constructor => match first {
+                RuntimeValue::Undefined | RuntimeValue::Null => {
+                    RuntimeValue::Object(self.heap.allocate(JsObject::new(
+                        ObjectKind::Ordinary,
+                        Some(self.intrinsics.object_prototype),
+                    )))
+                }
+                v => RuntimeValue::Object(self.coerce_to_object(&v)?),
+            },
+            ObjectBuiltin::Assign => {
+                let target = self.coerce_to_object(&first)?;
+                for source in arguments.iter().skip(1) {
+                    if source.is_nullish() {
+                        continue;
+                    }
+                    let source = self.coerce_to_object(source)?;
+                    for key in self.enumerable_own_keys(source) {
+                        let value = self.get(source, &key, RuntimeValue::Object(source))?;
+                        self.set_or_throw(target, key, value)?;
+                    }
+                }
+                RuntimeValue::Object(target)
+            }
+            ObjectBuiltin::Create => {
+                let prototype = match first {
+                    RuntimeValue::Object(id) => Some(id),
+                    RuntimeValue::Null => None,
+                    v => {
+                        return Err(Completion::error(
+                            ErrorKind::TypeError,
+                            &format!("Object prototype may only be an Object or null: {}", v),
+                        ))
+                    }
+                };
+                let object = self
+                    .heap
+                    .allocate(JsObject::new(ObjectKind::Ordinary, prototype));
+                let properties = argument(arguments, 1);
+                if !matches!(properties, RuntimeValue::Undefined) {
+                    self.define_properties(object, &properties)?;
+                }
+                RuntimeValue::Object(object)
+            }
+            ObjectBuiltin::DefineProperties => {
+                let object = self.require_object(&first, "Object.defineProperties")?;
+                self.define_properties(object, &argument(arguments, 1))?;
+                first
+            }
+            ObjectBuiltin::DefineProperty => {
+                let object = self.require_object(&first, "Object.defineProperty")?;
+                let key = self.coerce_to_property_key(argument(arguments, 1))?;
+                let desc = self.coerce_to_property_descriptor(&argument(arguments, 2))?;
+                if !self.define_own_property(object, key.clone(), desc)? {
+                    return Err(Completion::error(
+                        ErrorKind::TypeError,
+                        &format!("Cannot redefine property: {}", key),
+                    ));
+                }
+                first
+            }
+            ObjectBuiltin::Entries | ObjectBuiltin::Keys | ObjectBuiltin::Values => {
+                let object = self.coerce_to_object(&first)?;
+                let mut values = Vec::new();
+                for key in self.enumerable_own_keys(object) {
+                    let name = RuntimeValue::String(key.to_string());
+                    let value = match function {
+                        ObjectBuiltin::Keys => name,
+                        _ => {
+                            let value = self.get(object, &key, RuntimeValue::Object(object))?;
+                            match function {
+                                ObjectBuiltin::Values => value,
+                                _ => RuntimeValue::Object(
+                                    self.create_array(alloc::vec![name, value])?,
+                                ),
+                            }
+                        }
+                    };
+                    values.push(value);
+                }
+                RuntimeValue::Object(self.create_array(values)?)
+            }
+            ObjectBuiltin::Freeze | ObjectBuiltin::Seal => {
+                if let RuntimeValue::Object(id) = first {
+                    let freeze = function == ObjectBuiltin::Freeze;
+                    for key in self.heap.get(id).own_property_keys() {
+                        let is_data = self
+                            .heap
+                            .get(id)
+                            .get_own_property(&key)
+                            .is_some_and(|p| matches!(p.value(), PropertyValue::Data { .. }));
+                        let desc = PropertyDescriptor {
+                            configurable: Some(false),
+                            writable: if freeze && is_data { Some(false) } else { None },
+                            ..Default::default()
+                        };
+                        self.define_own_property(id, key, desc)?;
+                    }
+                    self.heap.get_mut(id).prevent_extensions();
+                }
+                first
+            }
+            ObjectBuiltin::IsFrozen | ObjectBuiltin::IsSealed => {
+                let id = match first {
+                    RuntimeValue::Object(id) => id,
+                    _ => return Ok(RuntimeValue::Boolean(true)),
+                };
+                let object = self.heap.get(id);
+                let frozen = !object.is_extensible()
+                    && object.own_property_keys().iter().all(|key| {
+                        object.get_own_property(key).is_some_and(|p| {
+                            !p.configurable()
+                                && (function == ObjectBuiltin::IsSealed || !p.writable())
+                        })
+                    });
+                RuntimeValue::Boolean(frozen)
+            }
+            ObjectBuiltin::GetOwnPropertyDescriptor => {
+                let object = self.coerce_to_object(&first)?;
+                let key = self.coerce_to_property_key(argument(arguments, 1))?;
+                match self.heap.get(object).get_own_property(&key).cloned() {
+                    Some(property) => {
+                        let object = self.heap.allocate(JsObject::new(
+                            ObjectKind::Ordinary,
+                            Some(self.intrinsics.object_prototype),
+                        ));
+                        let mut fields = Vec::new();
+                        match property.value() {
+                            PropertyValue::Data { value, writable } => {
+                                fields.push(("value", value.clone()));
+                                fields.push(("writable", RuntimeValue::Boolean(*writable)));
+                            }
+                            PropertyValue::Accessor { get, set } => {
+                                fields.push(("get", get.clone()));
+                                fields.push(("set", set.clone()));
+                            }
+                        }
+                        fields.push(("enumerable", RuntimeValue::Boolean(property.enumerable())));
+                        fields.push((
+                            "configurable",
+                            RuntimeValue::Boolean(property.configurable()),
+                        ));
+                        for (name, value) in fields {
+                            self.create_data_property(object, PropertyKey::from(name), value)?;
+                        }
+                        RuntimeValue::Object(object)
+                    }
+                    None => RuntimeValue::Undefined,
+                }
+            }
+            ObjectBuiltin::GetOwnPropertyNames => {
+                let object = self.coerce_to_object(&first)?;
+                let names = self
+                    .heap
+                    .get(object)
+                    .own_property_keys()
+                    .into_iter()
+                    .filter_map(|key| match key {
+                        PropertyKey::String(s) => Some(RuntimeValue::String(s)),
+                        PropertyKey::Symbol(_) => None,
+                    })
+                    .collect();
+                RuntimeValue::Object(self.create_array(names)?)
+            }
+            ObjectBuiltin::GetPrototypeOf => {
+                let object = self.coerce_to_object(&first)?;
+                match self.heap.get(object).prototype() {
+                    Some(prototype) => RuntimeValue::Object(prototype),
+                    None => RuntimeValue::Null,
+                }
+            }
+            ObjectBuiltin::Is => RuntimeValue::Boolean(same_value(&first, &argument(arguments, 1))),
+            ObjectBuiltin::IsExtensible => RuntimeValue::Boolean(match first {
+                RuntimeValue::Object(id) => self.heap.get(id).is_extensible(),
+                _ => false,
+            }),
+            ObjectBuiltin::PreventExtensions => {
+                if let RuntimeValue::Object(id) = first {
+                    self.heap.get_mut(id).prevent_extensions();
+                }
+                first
+            }
+            ObjectBuiltin::SetPrototypeOf => {
+                if first.is_nullish() {
+                    return Err(Completion::error(
+                        ErrorKind::TypeError,
+                        "Object.setPrototypeOf called on null or undefined",
+                    ));
+                }
+                let prototype = match argument(arguments, 1) {
+                    RuntimeValue::Object(id) => Some(id),
+                    RuntimeValue::Null => None,
+                    v => {
+                        return Err(Completion::error(
+                            ErrorKind::TypeError,
+                            &format!("Object prototype may only be an Object or null: {}", v),
+                        ))
+                    }
+                };
+                if let RuntimeValue::Object(id) = first {
+                    self.set_prototype_of(id, prototype)?;
+                }
+                first
+            }
+            ObjectBuiltin::HasOwnProperty => {
+                let key = self.coerce_to_property_key(first)?;
+                let object = self.coerce_to_object(&this)?;
+                RuntimeValue::Boolean(self.heap.get(object).get_own_property(&key).is_some())
+            }
+            ObjectBuiltin::IsPrototypeOf => {
+                let mut current = match first {
+                    RuntimeValue::Object(id) => self.heap.get(id).prototype(),
+                    _ => return Ok(RuntimeValue::Boolean(false)),
+                };
+                let object = self.coerce_to_object(&this)?;
+                while let Some(id) = current {
+                    if id == object {
+                        return Ok(RuntimeValue::Boolean(true));
+                    }
+                    current = self.heap.get(id).prototype();
+                }
+                RuntimeValue::Boolean(false)
+            }
+            ObjectBuiltin::PropertyIsEnumerable => {
+                let key = self.coerce_to_property_key(first)?;
+                let object = self.coerce_to_object(&this)?;
+                RuntimeValue::Boolean(
+                    self.heap
+                        .get(object)
+                        .get_own_property(&key)
+                        .is_some_and(|p| p.enumerable()),
+                )
+            }
+            ObjectBuiltin::ToLocaleString => {
+                let method = self.get_property(&this, &PropertyKey::from("toString"))?;
+                self.call(&method, this, &[])?
+            }
+            ObjectBuiltin::ToString => {
+                RuntimeValue::String(format!("[object {}]", self.to_string_tag(&this)))
+            }
+            ObjectBuiltin::ValueOf => RuntimeValue::Object(self.coerce_to_object(&this)?),
+        };
+        Ok(result)
+    }
+
+    /// Object.prototype.toStringが表示するオブジェクトの種類
+    fn to_string_tag(&self, value: &RuntimeValue) -> &'static str {
+        match value {
+            RuntimeValue::Undefined => "Undefined",
+            RuntimeValue::Null => "Null",
+            RuntimeValue::Boolean(_) => "Boolean",
+            RuntimeValue::Number(_) => "Number",
+            RuntimeValue::String(_) => "String",
+            RuntimeValue::Symbol(_) => "Symbol",
+            RuntimeValue::Object(id) => match self.heap.get(*id).kind() {
+                ObjectKind::Ordinary => "Object",
+                ObjectKind::Array => "Array",
+                ObjectKind::Arguments => "Arguments",
+                ObjectKind::Function(_)
+                | ObjectKind::HostFunction(_)
+                | ObjectKind::BoundFunction { .. } => "Function",
+                ObjectKind::Error => "Error",
+                ObjectKind::Primitive(value) => self.to_string_tag(value),
+                ObjectKind::Date(_) => "Date",
+                ObjectKind::Map(_) => "Map",
+                ObjectKind::Set(_) => "Set",
+                ObjectKind::DomNode(node) => match node.borrow().kind() {
+                    DomNodeKind::Document => "HTMLDocument",
+                    _ => "HTMLElement",
+                },
+            },
+        }
+    }
+
+    /// 値がオブジェクトでなければTypeErrorを投げる
+    pub(super) fn require_object(
+        &self,
+        value: &RuntimeValue,
+        caller: &str,
+    ) -> Result<ObjectId, Completion> {
+        match value {
+            RuntimeValue::Object(id) => Ok(*id),
+            v => Err(Completion::error(
+                ErrorKind::TypeError,
+                &format!("{} called on non-object: {}", caller, v),
+            )),
+        }
+    }
+
+    /// プロトタイプを変える。循環する場合と拡張できないオブジェクトではTypeErrorを投げる
+    fn set_prototype_of(
+        &mut self,
+        object: ObjectId,
+        prototype: Option<ObjectId>,
+    ) -> Result<(), Completion> {
+        let mut current = prototype;
+        while let Some(id) = current {
+            if id == object {
+                return Err(Completion::error(
+                    ErrorKind::TypeError,
+                    "Cyclic __proto__ value",
+                ));
+            }
+            current = self.heap.get(id).prototype();
+        }
+        if self.heap.get_mut(object).set_prototype(prototype) {
+            return Ok(());
+        }
+        Err(Completion::error(
+            ErrorKind::TypeError,
+            "Cannot set prototype of a non-extensible object",
+        ))
+    }
+
+    /// ObjectDefineProperties
+    fn define_properties(
+        &mut self,
+        object: ObjectId,
+        properties: &RuntimeValue,
+    ) -> Result<(), Completion> {
+        let properties = self.coerce_to_object(properties)?;
+        let mut descriptors = Vec::new();
+        for key in self.enumerable_own_keys(properties) {
+            let desc = self.get(properties, &key, RuntimeValue::Object(properties))?;
+            descriptors.push((key, self.coerce_to_property_descriptor(&desc)?));
+        }
+        for (key, desc) in descriptors {
+            if !self.define_own_property(object, key.clone(), desc)? {
+                return Err(Completion::error(
+                    ErrorKind::TypeError,
+                    &format!("Cannot redefine property: {}", key),
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// ToPropertyDescriptor
+    /// https://262.ecma-international.org/11.0/#sec-topropertydescriptor
+    fn coerce_to_property_descriptor(
+        &mut self,
+        value: &RuntimeValue,
+    ) -> Result<PropertyDescriptor, Completion> {
+        let id = match value {
+            RuntimeValue::Object(id) => *id,
+            v => {
+                return Err(Completion::error(
+                    ErrorKind::TypeError,
+                    &format!("Property description must be an object: {}", v),
+                ))
+            }
+        };
+        let mut desc = PropertyDescriptor::default();
+        for field in [
+            "enumerable",
+            "configurable",
+            "value",
+            "writable",
+            "get",
+            "set",
+        ] {
+            let key = PropertyKey::from(field);
+            if !self.has_property(id, &key) {
+                continue;
+            }
+            let v = self.get(id, &key, value.clone())?;
+            match field {
+                "enumerable" => desc.enumerable = Some(v.to_boolean()),
+                "configurable" => desc.configurable = Some(v.to_boolean()),
+                "value" => desc.value = Some(v),
+                "writable" => desc.writable = Some(v.to_boolean()),
+                _ => {
+                    if !self.is_callable(&v) && !matches!(v, RuntimeValue::Undefined) {
+                        return Err(Completion::error(
+                            ErrorKind::TypeError,
+                            &format!("{} must be a function: {}", field, v),
+                        ));
+                    }
+                    if field == "get" {
+                        desc.get = Some(v);
+                    } else {
+                        desc.set = Some(v);
+                    }
+                }
+            }
+        }
+        if (desc.get.is_some() || desc.set.is_some())
+            && (desc.value.is_some() || desc.writable.is_some())
+        {
+            return Err(Completion::error(
+                ErrorKind::TypeError,
+                "Invalid property descriptor. Cannot both specify accessors and a value or writable attribute",
+            ));
+        }
+        Ok(desc)
+    }
+
+    fn call_function(
+        &mut self,
+        function: FunctionBuiltin,
+        this: RuntimeValue,
+        arguments: &[RuntimeValue],
+    ) -> Result<RuntimeValue, Completion> {
+        if function != FunctionBuiltin::Constructor {
+            self.require_callable(&this)?;
+        }
+        match function {
+            FunctionBuiltin::Constructor => self.create_dynamic_function(arguments),
+            FunctionBuiltin::Apply => {
+                let list = match argument(arguments, 1) {
+                    RuntimeValue::Undefined | RuntimeValue::Null => Vec::new(),
+                    v => self.list_from_array_like(&v)?,
+                };
+                self.call(&this, argument(arguments, 0), &list)
+            }
+            FunctionBuiltin::Bind => {
+                let target = match this {
+                    RuntimeValue::Object(id) => id,
+                    _ => unreachable!(),
+                };
+                let bound_arguments = arguments.iter().skip(1).cloned().collect::<Vec<_>>();
+                let length = match self.get(target, &PropertyKey::from("length"), this.clone())? {
+                    RuntimeValue::Number(n) => {
+                        let n = trunc(n) - bound_arguments.len() as f64;
+                        if n.is_nan() || n < 0.0 {
+                            0.0
+                        } else {
+                            n
+                        }
+                    }
+                    _ => 0.0,
+                };
+                let name = match self.get(target, &PropertyKey::from("name"), this.clone())? {
+                    RuntimeValue::String(s) => s,
+                    _ => String::new(),
+                };
+
+                let prototype = self.heap.get(target).prototype();
+                let bound = self.heap.allocate(JsObject::new(
+                    ObjectKind::BoundFunction {
+                        target,
+                        this: argument(arguments, 0),
+                        arguments: bound_arguments,
+                    },
+                    prototype,
+                ));
+                let object = self.heap.get_mut(bound);
+                object.define_own_property(
+                    PropertyKey::from("length"),
+                    PropertyDescriptor::data(RuntimeValue::Number(length), false, false, true),
+                );
+                object.define_own_property(
+                    PropertyKey::from("name"),
+                    PropertyDescriptor::data(
+                        RuntimeValue::String(format!("bound {}", name)),
+                        false,
+                        false,
+                        true,
+                    ),
+                );
+                Ok(RuntimeValue::Object(bound))
+            }
+            FunctionBuiltin::Call => {
+                let rest = arguments.get(1..).unwrap_or_default();
+                self.call(&this, argument(arguments, 0), rest)
+            }
+            FunctionBuiltin::ToString => {
+                // 関数のソースコードは残していない
+                let name = match &this {
+                    RuntimeValue::Object(id) => self
+                        .heap
+                        .get(*id)
+                        .get_own_property(&PropertyKey::from("name"))
+                        .and_then(|p| match p.value() {
+                            PropertyValue::Data {
+                                value: RuntimeValue::String(s),
+                                ..
+                            } => Some(s.clone()),
+                            _ => None,
+                        })
+                        .unwrap_or_default(),
+                    _ => String::new(),
+                };
+                Ok(RuntimeValue::String(format!(
+                    "function {}() {{ [native code] }}",
+                    name
+                )))
+            }
+        }
+    }
+
+    /// `new Function("a", "b", "return a + b")`のように、文字列から関数を作る
+    /// https://262.ecma-international.org/11.0/#sec-createdynamicfunction
+    fn create_dynamic_function(
+        &mut self,
+        arguments: &[RuntimeValue],
+    ) -> Result<RuntimeValue, Completion> {
+        let mut strings = Vec::new();
+        for value in arguments {
+            strings.push(self.coerce_to_string(value.clone())?);
+        }
+        let body = strings.pop().unwrap_or_default();
+        let source = format!(
+            "(function anonymous({}\n) {{\n{}\n}})",
+            strings.join(","),
+            body
+        );
+
+        let program = JsParser::new(JsLexer::new(source))
+            .parse_ast()
+            .map_err(|e| Completion::error(ErrorKind::SyntaxError, &format!("{:?}", e)))?;
+        let expression = match program.body().as_slice() {
+            [statement] => match statement.as_ref() {
+                Node::ExpressionStatement(expression) => expression.clone(),
+                _ => None,
+            },
+            _ => None,
+        };
+        if !matches!(expression.as_deref(), Some(Node::FunctionExpression { .. })) {
+            return Err(Completion::error(
+                ErrorKind::SyntaxError,
+                "Invalid function body",
+            ));
+        }
+        // 関数はグローバルのスコープで作る
+        Ok(self
+            .run_script(&program)?
+            .unwrap_or(RuntimeValue::Undefined))
+    }
+
+    /// Booleanのメソッドのthisの値
+    fn this_boolean_value(&self, this: &RuntimeValue) -> Result<bool, Completion> {
+        match this {
+            RuntimeValue::Boolean(b) => return Ok(*b),
+            RuntimeValue::Object(id) => {
+                if let ObjectKind::Primitive(RuntimeValue::Boolean(b)) = self.heap.get(*id).kind() {
+                    return Ok(*b);
+                }
+            }
+            _ => {}
+        }
+        Err(Completion::error(
+            ErrorKind::TypeError,
+            "Boolean.prototype method called on incompatible receiver",
+        ))
+    }
+
+    fn call_boolean(
+        &mut self,
+        function: BooleanBuiltin,
+        this: RuntimeValue,
+        arguments: &[RuntimeValue],
+    ) -> Result<RuntimeValue, Completion> {
+        match function {
+            BooleanBuiltin::Constructor => {
+                Ok(RuntimeValue::Boolean(argument(arguments, 0).to_boolean()))
+            }
+            BooleanBuiltin::ToString => Ok(RuntimeValue::String(
+                self.this_boolean_value(&this)?.to_string(),
+            )),
+            BooleanBuiltin::ValueOf => Ok(RuntimeValue::Boolean(self.this_boolean_value(&this)?)),
+        }
+    }
+
+    /// Symbolのメソッドのthisの値
+    fn this_symbol_value(&self, this: &RuntimeValue) -> Result<Symbol, Completion> {
+        match this {
+            RuntimeValue::Symbol(symbol) => return Ok(symbol.clone()),
+            RuntimeValue::Object(id) => {
+                if let ObjectKind::Primitive(RuntimeValue::Symbol(symbol)) =
+                    self.heap.get(*id).kind()
+                {
+                    return Ok(symbol.clone());
+                }
+            }
+            _ => {}
+        }
+        Err(Completion::error(
+            ErrorKind::TypeError,
+            "Symbol.prototype method called on incompatible receiver",
+        ))
+    }
+
+    fn call_symbol(
+        &mut self,
+        function: SymbolBuiltin,
+        this: RuntimeValue,
+        arguments: &[RuntimeValue],
+    ) -> Result<RuntimeValue, Completion> {
+        match function {
+            SymbolBuiltin::Constructor => {
+                let description = match argument(arguments, 0) {
+                    RuntimeValue::Undefined => None,
+                    value => Some(self.coerce_to_string(value)?),
+                };
+                Ok(RuntimeValue::Symbol(Symbol::new(description)))
+            }
+            SymbolBuiltin::ToString => Ok(RuntimeValue::String(
+                RuntimeValue::Symbol(self.this_symbol_value(&this)?).to_string(),
+            )),
+            SymbolBuiltin::ValueOf => Ok(RuntimeValue::Symbol(self.this_symbol_value(&this)?)),
+            SymbolBuiltin::Description => Ok(match self.this_symbol_value(&this)?.description() {
+                Some(description) => RuntimeValue::String(description),
+                None => RuntimeValue::Undefined,
+            }),
+        }
+    }
+
+    fn call_error(
+        &mut self,
+        function: ErrorBuiltin,
+        this: RuntimeValue,
+        arguments: &[RuntimeValue],
+    ) -> Result<RuntimeValue, Completion> {
+        match function {
+            // newをつけずに呼んでもErrorオブジェクトを作る
+            ErrorBuiltin::Constructor(kind) => {
+                let error = self.heap.allocate(JsObject::new(
+                    ObjectKind::Error,
+                    Some(self.intrinsics.error_prototype(kind)),
+                ));
+                let message = argument(arguments, 0);
+                if !matches!(message, RuntimeValue::Undefined) {
+                    let message = RuntimeValue::String(self.coerce_to_string(message)?);
+                    self.define_value(error, "message", message);
+                }
+                Ok(RuntimeValue::Object(error))
+            }
+            ErrorBuiltin::ToString => {
+                let id = self.require_object(&this, "Error.prototype.toString")?;
+                let name = match self.get(id, &PropertyKey::from("name"), this.clone())? {
+                    RuntimeValue::Undefined => "Error".to_string(),
+                    v => self.coerce_to_string(v)?,
+                };
+                let message = match self.get(id, &PropertyKey::from("message"), this.clone())? {
+                    RuntimeValue::Undefined => String::new(),
+                    v => self.coerce_to_string(v)?,
+                };
+                Ok(RuntimeValue::String(
+                    match (name.is_empty(), message.is_empty()) {
+                        (true, _) => message,
+                        (_, true) => name,
+                        _ => format!("{}: {}", name, message),
+                    },
+                ))
+            }
+        }
+    }
+
+    fn call_global(
+        &mut self,
+        function: GlobalBuiltin,
+        arguments: &[RuntimeValue],
+    ) -> Result<RuntimeValue, Completion> {
+        let first = argument(arguments, 0);
+        let result = match function {
+            GlobalBuiltin::IsFinite => self.coerce_to_number(first)?.is_finite(),
+            GlobalBuiltin::IsNaN => self.coerce_to_number(first)?.is_nan(),
+            GlobalBuiltin::ParseFloat => {
+                let s = self.coerce_to_string(first)?;
+                return Ok(RuntimeValue::Number(parse_float(trim_start(&s))));
+            }
+            GlobalBuiltin::ParseInt => {
+                let s = self.coerce_to_string(first)?;
+                let radix = self.coerce_to_number(argument(arguments, 1))?;
+                return Ok(RuntimeValue::Number(parse_int(
+                    trim_start(&s),
+                    to_int32(radix),
+                )));
+            }
+        };
+        Ok(RuntimeValue::Boolean(result))
+    }
+}
+
+/// parseInt。空白を取り除いた文字列を受け取る
+/// https://262.ecma-international.org/11.0/#sec-parseint-string-radix
+fn parse_int(s: &str, radix: i32) -> f64 {
+    let (negative, s) = match s.as_bytes().first() {
+        Some(b'-') => (true, &s[1..]),
+        Some(b'+') => (false, &s[1..]),
+        _ => (false, s),
+    };
+    let mut radix = radix;
+    let mut strip_prefix = true;
+    if radix != 0 {
+        if !(2..=36).contains(&radix) {
+            return f64::NAN;
+        }
+        if radix != 16 {
+            strip_prefix = false;
+        }
+    } else {
+        radix = 10;
+    }
+    let mut s = s;
+    if strip_prefix && (s.starts_with("0x") || s.starts_with("0X")) {
+        s = &s[2..];
+        radix = 16;
+    }
+
+    let end = s
+        .find(|c: char| !c.is_digit(radix as u32))
+        .unwrap_or(s.len());
+    let digits = &s[..end];
+    if digits.is_empty() {
+        return f64::NAN;
+    }
+    // 10進数は、丸めの誤差が出ないように数値の変換に任せる
+    let value = if radix == 10 {
+        string_to_number(digits)
+    } else {
+        digits.chars().fold(0.0, |value, c| {
+            value * radix as f64 + c.to_digit(radix as u32).unwrap_or(0) as f64
+        })
+    };
+    if negative {
+        -value
+    } else {
+        value
+    }
+}
+
+/// parseFloat。空白を取り除いた文字列の、先頭の10進数として読める部分を数値にする
+/// https://262.ecma-international.org/11.0/#sec-parsefloat-string
+fn parse_float(s: &str) -> f64 {
+    let bytes = s.as_bytes();
+    let mut i = 0;
+    if i < bytes.len() && (bytes[i] == b'+' || bytes[i] == b'-') {
+        i += 1;
+    }
+    if s[i..].starts_with("Infinity") {
+        return if bytes.first() == Some(&b'-') {
+            f64::NEG_INFINITY
+        } else {
+            f64::INFINITY
+        };
+    }
+
+    let count_digits = |i: &mut usize| {
+        let start = *i;
+        while *i < bytes.len() && bytes[*i].is_ascii_digit() {
+            *i += 1;
+        }
+        *i - start
+    };
+    let mut digits = count_digits(&mut i);
+    if i < bytes.len() && bytes[i] == b'.' {
+        let point = i;
+        i += 1;
+        let fraction = count_digits(&mut i);
+        if digits == 0 && fraction == 0 {
+            i = point;
+        }
+        digits += fraction;
+    }
+    if digits == 0 {
+        return f64::NAN;
+    }
+    // 指数部は、数字が続く場合だけ読む
+    if i < bytes.len() && (bytes[i] == b'e' || bytes[i] == b'E') {
+        let mut j = i + 1;
+        if j < bytes.len() && (bytes[j] == b'+' || bytes[j] == b'-') {
+            j += 1;
+        }
+        if count_digits(&mut j) > 0 {
+            i = j;
+        }
+    }
+    s[..i].parse::<f64>().unwrap_or(f64::NAN)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::renderer::js::runtime::tests::boolean;
+    use crate::renderer::js::runtime::tests::eval_last;
+    use crate::renderer::js::runtime::tests::number;
+    use crate::renderer::js::runtime::tests::string;
+    use crate::renderer::js::runtime::tests::throws;
+
+    #[test]
+    fn test_parse_int_and_float() {
+        assert_eq!(255.0, parse_int("0xff", 0));
+        assert_eq!(-42.0, parse_int("-42px", 10));
+        assert_eq!(5.0, parse_int("101", 2));
+        assert!(parse_int("12", 1).is_nan());
+        assert!(parse_int("z", 10).is_nan());
+        assert_eq!(2.5, parse_float("2.5abc"));
+        assert_eq!(1.0, parse_float("1e"));
+        assert_eq!(0.5, parse_float(".5"));
+        assert_eq!(f64::NEG_INFINITY, parse_float("-Infinityx"));
+        assert!(parse_float(".").is_nan());
+    }
+
+    #[test]
+    fn test_object_coercion() {
+        // プリミティブ値はオブジェクトに変換する
+        assert_eq!(string("0,1"), eval_last("Object.keys('ab').join()"));
+        assert_eq!(
+            string("0,1"),
+            eval_last("Object.keys(Object.assign({}, null, undefined, 'ab')).join()")
+        );
+        assert_eq!(number(1.0), eval_last("Object.freeze(1)"));
+        assert_eq!(
+            string("[object Null],[object Undefined],[object Number]"),
+            eval_last(
+                "var s = Object.prototype.toString;
+                 [s.call(null), s.call(undefined), s.call(1)].join()"
+            )
+        );
+        assert_eq!(
+            string("true,false"),
+            eval_last("[Object.is(NaN, NaN), Object.is(0, -0)].join()")
+        );
+        assert_eq!(
+            boolean(true),
+            eval_last("Object.prototype.hasOwnProperty.call('ab', 'length')")
+        );
+    }
+
+    #[test]
+    fn test_object_errors() {
+        assert!(throws(eval_last("Object.keys(null)"), ErrorKind::TypeError));
+        assert!(throws(eval_last("Object.create(1)"), ErrorKind::TypeError));
+        assert!(throws(
+            eval_last("Object.setPrototypeOf({}, 1)"),
+            ErrorKind::TypeError
+        ));
+        assert!(throws(
+            eval_last("Object.defineProperty(1, 'a', {})"),
+            ErrorKind::TypeError
+        ));
+        // getは関数でなければならず、値とアクセサの両方は指定できない
+        assert!(throws(
+            eval_last("Object.defineProperty({}, 'a', { get: 1 })"),
+            ErrorKind::TypeError
+        ));
+        assert!(throws(
+            eval_last("Object.defineProperty({}, 'a', { get() {}, value: 1 })"),
+            ErrorKind::TypeError
+        ));
+        assert!(throws(
+            eval_last(
+                "var o = Object.freeze({ a: 1 }); Object.defineProperty(o, 'a', { value: 2 })"
+            ),
+            ErrorKind::TypeError
+        ));
+        assert!(throws(
+            eval_last("Object.prototype.hasOwnProperty.call(null, 'a')"),
+            ErrorKind::TypeError
+        ));
+    }
+
+    #[test]
+    fn test_function_builtins() {
+        // 配列のようなオブジェクトを引数にできる
+        assert_eq!(
+            string("a,undefined"),
+            eval_last(
+                "function f(a, b) { return [a, String(b)].join(); } f.apply(null, { length: 2, 0: 'a' })"
+            )
+        );
+        assert_eq!(
+            string("bound f,1"),
+            eval_last("function f(a, b) {} var g = f.bind(null, 1); [g.name, g.length].join()")
+        );
+        assert_eq!(
+            number(3.0),
+            eval_last("function P(a, b) { this.v = a + b; } new (P.bind(null, 1))(2).v")
+        );
+        assert!(throws(
+            eval_last("Function.prototype.call.call(1)"),
+            ErrorKind::TypeError
+        ));
+        assert!(throws(
+            eval_last("function f() {} f.apply(null, 1)"),
+            ErrorKind::TypeError
+        ));
+        assert!(throws(
+            eval_last("Function.prototype.toString.call({})"),
+            ErrorKind::TypeError
+        ));
+        assert!(throws(eval_last("new (() => {})()"), ErrorKind::TypeError));
+    }
+
+    #[test]
+    fn test_error_and_globals() {
+        // newなしでも作れ、messageがundefinedなら独自のプロパティを作らない
+        assert_eq!(
+            string("x,false"),
+            eval_last(
+                "[Error('x').message, new Error(undefined).hasOwnProperty('message')].join()"
+            )
+        );
+        assert_eq!(
+            string("TypeError: m"),
+            eval_last("String(new TypeError('m'))")
+        );
+        assert_eq!(
+            string("m"),
+            eval_last("Error.prototype.toString.call({ name: '', message: 'm' })")
+        );
+        assert!(throws(
+            eval_last("Error.prototype.toString.call(1)"),
+            ErrorKind::TypeError
+        ));
+        assert_eq!(number(1.0), eval_last("new Boolean(false) ? 1 : 2"));
+        assert!(throws(
+            eval_last("Boolean.prototype.valueOf.call(1)"),
+            ErrorKind::TypeError
+        ));
+        assert_eq!(
+            string("26,8,-5,true,true"),
+            eval_last(
+                "[parseInt('  0x1A'), parseInt('08'), parseFloat('  -.5e1x'), isFinite('12'),
+                  isNaN(undefined)].join()"
+            )
+        );
+    }
+
+    #[test]
+    fn test_symbol() {
+        assert_eq!(string("symbol"), eval_last("typeof Symbol('a')"));
+        assert_eq!(
+            string("Symbol(a),Symbol(),a,undefined,1"),
+            eval_last(
+                "var a = Symbol('a'), b = Symbol();
+                 [a.toString(), String(b), a.description, String(b.description), Symbol(1).description].join()"
+            )
+        );
+        // 作るたびに異なる値になり、プロパティのキーにできる
+        assert_eq!(boolean(false), eval_last("Symbol('a') === Symbol('a')"));
+        assert_eq!(
+            string("1,,true"),
+            eval_last(
+                "var s = Symbol('s'), o = {}; o[s] = 1;
+                 [o[s], Object.keys(o).join(), s.valueOf() === s].join()"
+            )
+        );
+        assert_eq!(
+            string("object,true"),
+            eval_last("var s = Symbol(); var o = Object(s); [typeof o, o.valueOf() === s].join()")
+        );
+        assert!(throws(eval_last("new Symbol()"), ErrorKind::TypeError));
+        assert!(throws(eval_last("Symbol() + ''"), ErrorKind::TypeError));
+        assert!(throws(
+            eval_last("Symbol.prototype.toString.call('a')"),
+            ErrorKind::TypeError
+        ));
+    }
+}
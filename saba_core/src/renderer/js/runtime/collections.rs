@@ -0,0 +1,412 @@
+//! MapとSetのコンストラクタとメソッド
+//! https://262.ecma-international.org/11.0/#sec-keyed-collections
+
+use crate::renderer::js::heap::ObjectId;
+use crate::renderer::js::object::same_value_zero;
+use crate::renderer::js::object::HostFunction;
+use crate::renderer::js::object::JsObject;
+use crate::renderer::js::object::ObjectKind;
+use crate::renderer::js::object::PropertyKey;
+use crate::renderer::js::runtime::builtins::argument;
+use crate::renderer::js::runtime::builtins::Builtin;
+use crate::renderer::js::runtime::Completion;
+use crate::renderer::js::runtime::ErrorKind;
+use crate::renderer::js::runtime::JsRuntime;
+use crate::renderer::js::value::RuntimeValue;
+use alloc::format;
+use alloc::vec::Vec;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MapBuiltin {
+    Constructor,
+    Get,
+    Set,
+    Has,
+    Delete,
+    Clear,
+    ForEach,
+    Size,
+}
+
+impl MapBuiltin {
+    pub fn name(&self) -> &'static str {
+        match self {
+            MapBuiltin::Constructor => "Map",
+            MapBuiltin::Get => "get",
+            MapBuiltin::Set => "set",
+            MapBuiltin::Has => "has",
+            MapBuiltin::Delete => "delete",
+            MapBuiltin::Clear => "clear",
+            MapBuiltin::ForEach => "forEach",
+            MapBuiltin::Size => "get size",
+        }
+    }
+
+    pub fn length(&self) -> usize {
+        match self {
+            MapBuiltin::Set => 2,
+            MapBuiltin::Clear | MapBuiltin::Size | MapBuiltin::Constructor => 0,
+            _ => 1,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SetBuiltin {
+    Constructor,
+    Add,
+    Has,
+    Delete,
+    Clear,
+    ForEach,
+    Size,
+}
+
+impl SetBuiltin {
+    pub fn name(&self) -> &'static str {
+        match self {
+            SetBuiltin::Constructor => "Set",
+            SetBuiltin::Add => "add",
+            SetBuiltin::Has => "has",
+            SetBuiltin::Delete => "delete",
+            SetBuiltin::Clear => "clear",
+            SetBuiltin::ForEach => "forEach",
+            SetBuiltin::Size => "get size",
+        }
+    }
+
+    pub fn length(&self) -> usize {
+        match self {
+            SetBuiltin::Clear | SetBuiltin::Size | SetBuiltin::Constructor => 0,
+            _ => 1,
+        }
+    }
+}
+
+/// キーの-0は+0にする
+fn normalize_key(key: RuntimeValue) -> RuntimeValue {
+    match key {
+        RuntimeValue::Number(n) if n == 0.0 => RuntimeValue::Number(0.0),
+        key => key,
+    }
+}
+
+impl JsRuntime {
+    pub(super) fn install_collections(&mut self) {
+        let prototype = self.intrinsics.map_prototype;
+        self.define_constructor(Builtin::Map(MapBuiltin::Constructor), prototype);
+        for method in [
+            MapBuiltin::Get,
+            MapBuiltin::Set,
+            MapBuiltin::Has,
+            MapBuiltin::Delete,
+            MapBuiltin::Clear,
+            MapBuiltin::ForEach,
+        ] {
+            self.define_builtin_method(prototype, Builtin::Map(method));
+        }
+        self.define_host_accessor(
+            prototype,
+            "size",
+            HostFunction::Builtin(Builtin::Map(MapBuiltin::Size)),
+            None,
+        );
+
+        let prototype = self.intrinsics.set_prototype;
+        self.define_constructor(Builtin::Set(SetBuiltin::Constructor), prototype);
+        for method in [
+            SetBuiltin::Add,
+            SetBuiltin::Has,
+            SetBuiltin::Delete,
+            SetBuiltin::Clear,
+            SetBuiltin::ForEach,
+        ] {
+            self.define_builtin_method(prototype, Builtin::Set(method));
+        }
+        self.define_host_accessor(
+            prototype,
+            "size",
+            HostFunction::Builtin(Builtin::Set(SetBuiltin::Size)),
+            None,
+        );
+    }
+
+    /// `new Map(iterable)`と`new Set(iterable)`。値はsetとaddのメソッドで追加する
+    fn construct_collection(
+        &mut self,
+        kind: ObjectKind,
+        prototype: ObjectId,
+        adder: &str,
+        iterable: RuntimeValue,
+    ) -> Result<RuntimeValue, Completion> {
+        let is_map = matches!(kind, ObjectKind::Map(_));
+        let collection =
+            RuntimeValue::Object(self.heap.allocate(JsObject::new(kind, Some(prototype))));
+        if iterable.is_nullish() {
+            return Ok(collection);
+        }
+
+        let adder = match &collection {
+            RuntimeValue::Object(id) => {
+                self.get(*id, &PropertyKey::from(adder), collection.clone())?
+            }
+            _ => RuntimeValue::Undefined,
+        };
+        self.require_callable(&adder)?;
+        for item in self.iterate(iterable)? {
+            let arguments = if is_map {
+                let entry = match item {
+                    RuntimeValue::Object(id) => id,
+                    v => {
+                        return Err(Completion::error(
+                            ErrorKind::TypeError,
+                            &format!("Iterator value {} is not an entry object", v),
+                        ))
+                    }
+                };
+                let key = self.get(entry, &PropertyKey::from(0), item.clone())?;
+                let value = self.get(entry, &PropertyKey::from(1), item)?;
+                Vec::from([key, value])
+            } else {
+                Vec::from([item])
+            };
+            self.call(&adder, collection.clone(), &arguments)?;
+        }
+        Ok(collection)
+    }
+
+    pub(super) fn construct_map(
+        &mut self,
+        arguments: &[RuntimeValue],
+    ) -> Result<RuntimeValue, Completion> {
+        self.construct_collection(
+            ObjectKind::Map(Vec::new()),
+            self.intrinsics.map_prototype,
+            "set",
+            argument(arguments, 0),
+        )
+    }
+
+    pub(super) fn construct_set(
+        &mut self,
+        arguments: &[RuntimeValue],
+    ) -> Result<RuntimeValue, Completion> {
+        self.construct_collection(
+            ObjectKind::Set(Vec::new()),
+            self.intrinsics.set_prototype,
+            "add",
+            argument(arguments, 0),
+        )
+    }
+
+    fn incompatible_receiver(collection: &str, method: &str) -> Completion {
+        Completion::error(
+            ErrorKind::TypeError,
+            &format!(
+                "Method {}.prototype.{} called on incompatible receiver",
+                collection, method
+            ),
+        )
+    }
+
+    fn map_entries(
+        &mut self,
+        this: &RuntimeValue,
+        method: &str,
+    ) -> Result<&mut Vec<Option<(RuntimeValue, RuntimeValue)>>, Completion> {
+        if let RuntimeValue::Object(id) = this {
+            if let ObjectKind::Map(entries) = self.heap.get_mut(*id).kind_mut() {
+                return Ok(entries);
+            }
+        }
+        Err(Self::incompatible_receiver("Map", method))
+    }
+
+    fn set_values(
+        &mut self,
+        this: &RuntimeValue,
+        method: &str,
+    ) -> Result<&mut Vec<Option<RuntimeValue>>, Completion> {
+        if let RuntimeValue::Object(id) = this {
+            if let ObjectKind::Set(values) = self.heap.get_mut(*id).kind_mut() {
+                return Ok(values);
+            }
+        }
+        Err(Self::incompatible_receiver("Set", method))
+    }
+
+    pub(super) fn call_map(
+        &mut self,
+        function: MapBuiltin,
+        this: RuntimeValue,
+        arguments: &[RuntimeValue],
+    ) -> Result<RuntimeValue, Completion> {
+        if function == MapBuiltin::Constructor {
+            return Err(Completion::error(
+                ErrorKind::TypeError,
+                "Constructor Map requires 'new'",
+            ));
+        }
+        let method = function.name().trim_start_matches("get ");
+        let key = normalize_key(argument(arguments, 0));
+        let entries = self.map_entries(&this, method)?;
+        let position = entries
+            .iter()
+            .position(|e| e.as_ref().is_some_and(|(k, _)| same_value_zero(k, &key)));
+
+        let result = match function {
+            MapBuiltin::Get => match position {
+                Some(i) => match &entries[i] {
+                    Some((_, value)) => value.clone(),
+                    None => RuntimeValue::Undefined,
+                },
+                None => RuntimeValue::Undefined,
+            },
+            MapBuiltin::Set => {
+                let value = argument(arguments, 1);
+                match position {
+                    Some(i) => entries[i] = Some((key, value)),
+                    None => entries.push(Some((key, value))),
+                }
+                this
+            }
+            MapBuiltin::Has => RuntimeValue::Boolean(position.is_some()),
+            MapBuiltin::Delete => {
+                if let Some(i) = position {
+                    entries[i] = None;
+                }
+                RuntimeValue::Boolean(position.is_some())
+            }
+            MapBuiltin::Clear => {
+                entries.iter_mut().for_each(|e| *e = None);
+                RuntimeValue::Undefined
+            }
+            MapBuiltin::Size => RuntimeValue::Number(entries.iter().flatten().count() as f64),
+            MapBuiltin::ForEach => {
+                let callback = argument(arguments, 0);
+                self.require_callable(&callback)?;
+                let this_arg = argument(arguments, 1);
+                // コールバックの中で追加された要素も呼び出す
+                let mut i = 0;
+                while let Some(entry) = self.map_entries(&this, method)?.get(i).cloned() {
+                    if let Some((key, value)) = entry {
+                        self.call(&callback, this_arg.clone(), &[value, key, this.clone()])?;
+                    }
+                    i += 1;
+                }
+                RuntimeValue::Undefined
+            }
+            MapBuiltin::Constructor => unreachable!(),
+        };
+        Ok(result)
+    }
+
+    pub(super) fn call_set(
+        &mut self,
+        function: SetBuiltin,
+        this: RuntimeValue,
+        arguments: &[RuntimeValue],
+    ) -> Result<RuntimeValue, Completion> {
+        if function == SetBuiltin::Constructor {
+            return Err(Completion::error(
+                ErrorKind::TypeError,
+                "Constructor Set requires 'new'",
+            ));
+        }
+        let method = function.name().trim_start_matches("get ");
+        let value = normalize_key(argument(arguments, 0));
+        let values = self.set_values(&this, method)?;
+        let position = values
+            .iter()
+            .position(|v| v.as_ref().is_some_and(|v| same_value_zero(v, &value)));
+
+        let result = match function {
+            SetBuiltin::Add => {
+                if position.is_none() {
+                    values.push(Some(value));
+                }
+                this
+            }
+            SetBuiltin::Has => RuntimeValue::Boolean(position.is_some()),
+            SetBuiltin::Delete => {
+                if let Some(i) = position {
+                    values[i] = None;
+                }
+                RuntimeValue::Boolean(position.is_some())
+            }
+            SetBuiltin::Clear => {
+                values.iter_mut().for_each(|v| *v = None);
+                RuntimeValue::Undefined
+            }
+            SetBuiltin::Size => RuntimeValue::Number(values.iter().flatten().count() as f64),
+            SetBuiltin::ForEach => {
+                let callback = argument(arguments, 0);
+                self.require_callable(&callback)?;
+                let this_arg = argument(arguments, 1);
+                let mut i = 0;
+                while let Some(value) = self.set_values(&this, method)?.get(i).cloned() {
+                    if let Some(value) = value {
+                        self.call(
+                            &callback,
+                            this_arg.clone(),
+                            &[value.clone(), value, this.clone()],
+                        )?;
+                    }
+                    i += 1;
+                }
+                RuntimeValue::Undefined
+            }
+            SetBuiltin::Constructor => unreachable!(),
+        };
+        Ok(result)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::renderer::js::runtime::tests::eval_last;
+    use crate::renderer::js::runtime::tests::number;
+    use crate::renderer::js::runtime::tests::string;
+    use crate::renderer::js::runtime::tests::throws;
+    use crate::renderer::js::runtime::ErrorKind;
+
+    #[test]
+    fn test_keys() {
+        // キーはSameValueZeroで比べるので、NaN同士と0と-0は同じキーになる
+        assert_eq!(
+            string("b,1,1"),
+            eval_last(
+                "[new Map([[1, 'a'], [1, 'b']]).get(1), new Map([[NaN, 1]]).get(NaN),
+                  new Map([[0, 1]]).get(-0)].join()"
+            )
+        );
+        assert_eq!(number(2.0), eval_last("new Set([1, 1, '1']).size"));
+        assert_eq!(number(3.0), eval_last("new Set('abc').size"));
+        // forEachの途中で削除された要素は呼ばず、追加された要素は呼ぶ
+        assert_eq!(
+            string("1,3,4"),
+            eval_last(
+                "var s = new Set([1, 2, 3]); var seen = [];
+                 s.forEach(v => { seen.push(v); if (v === 1) { s.delete(2); s.add(4); } });
+                 seen.join()"
+            )
+        );
+    }
+
+    #[test]
+    fn test_errors() {
+        assert!(throws(eval_last("Map()"), ErrorKind::TypeError));
+        assert!(throws(eval_last("Set()"), ErrorKind::TypeError));
+        // 項目はキーと値を持つオブジェクトでなければならない
+        assert!(throws(eval_last("new Map([1])"), ErrorKind::TypeError));
+        assert!(throws(eval_last("new Set(1)"), ErrorKind::TypeError));
+        assert!(throws(
+            eval_last("Map.prototype.get.call({}, 1)"),
+            ErrorKind::TypeError
+        ));
+        assert!(throws(
+            eval_last("Set.prototype.add.call(new Map(), 1)"),
+            ErrorKind::TypeError
+        ));
+    }
+}
@@ -0,0 +1,830 @@
+//! Dateのコンストラクタとメソッド
+//! タイムゾーンは扱わず、地方時はUTCと同じものとする
+//! https://262.ecma-international.org/11.0/#sec-date-objects
+
+use crate::renderer::js::math::abs;
+use crate::renderer::js::math::floor;
+use crate::renderer::js::math::trunc;
+use crate::renderer::js::object::JsObject;
+use crate::renderer::js::object::ObjectKind;
+use crate::renderer::js::object::PropertyKey;
+use crate::renderer::js::runtime::builtins::argument;
+use crate::renderer::js::runtime::builtins::Builtin;
+use crate::renderer::js::runtime::Completion;
+use crate::renderer::js::runtime::ErrorKind;
+use crate::renderer::js::runtime::JsRuntime;
+use crate::renderer::js::runtime::PreferredType;
+use crate::renderer::js::value::RuntimeValue;
+use alloc::format;
+use alloc::string::String;
+use alloc::string::ToString;
+use alloc::vec::Vec;
+use core::fmt::Debug;
+
+const MS_PER_DAY: f64 = 86_400_000.0;
+const MS_PER_HOUR: f64 = 3_600_000.0;
+const MS_PER_MINUTE: f64 = 60_000.0;
+const MS_PER_SECOND: f64 = 1_000.0;
+
+const WEEK_DAYS: [&str; 7] = ["Sun", "Mon", "Tue", "Wed", "Thu", "Fri", "Sat"];
+const MONTHS: [&str; 12] = [
+    "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+];
+
+/// Date.now()と引数のない`new Date()`が使う現在時刻
+pub trait Clock: Debug {
+    /// 1970年1月1日からのミリ秒
+    fn now(&self) -> f64;
+}
+
+/// 常に同じ時刻を返す時計
+#[derive(Debug)]
+pub struct FixedClock(pub f64);
+
+impl Clock for FixedClock {
+    fn now(&self) -> f64 {
+        self.0
+    }
+}
+
+/// 日時の要素。並びはsetFullYearなどの引数の順番と同じ
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DateField {
+    FullYear,
+    Month,
+    Date,
+    Hours,
+    Minutes,
+    Seconds,
+    Milliseconds,
+    /// 曜日。読み取りだけができる
+    Day,
+}
+
+impl DateField {
+    const SETTABLE: [DateField; 7] = [
+        DateField::FullYear,
+        DateField::Month,
+        DateField::Date,
+        DateField::Hours,
+        DateField::Minutes,
+        DateField::Seconds,
+        DateField::Milliseconds,
+    ];
+
+    /// (getter, UTCのgetter, setter, UTCのsetter)の名前
+    fn names(&self) -> [&'static str; 4] {
+        match self {
+            DateField::FullYear => [
+                "getFullYear",
+                "getUTCFullYear",
+                "setFullYear",
+                "setUTCFullYear",
+            ],
+            DateField::Month => ["getMonth", "getUTCMonth", "setMonth", "setUTCMonth"],
+            DateField::Date => ["getDate", "getUTCDate", "setDate", "setUTCDate"],
+            DateField::Hours => ["getHours", "getUTCHours", "setHours", "setUTCHours"],
+            DateField::Minutes => ["getMinutes", "getUTCMinutes", "setMinutes", "setUTCMinutes"],
+            DateField::Seconds => ["getSeconds", "getUTCSeconds", "setSeconds", "setUTCSeconds"],
+            DateField::Milliseconds => [
+                "getMilliseconds",
+                "getUTCMilliseconds",
+                "setMilliseconds",
+                "setUTCMilliseconds",
+            ],
+            DateField::Day => ["getDay", "getUTCDay", "", ""],
+        }
+    }
+
+    /// setterが受け取る引数の数。setHoursは時、分、秒、ミリ秒を受け取る
+    fn setter_length(&self) -> usize {
+        match self {
+            DateField::FullYear | DateField::Minutes => 3,
+            DateField::Month | DateField::Seconds => 2,
+            DateField::Hours => 4,
+            _ => 1,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DateBuiltin {
+    Constructor,
+    Now,
+    Parse,
+    Utc,
+    Get(DateField),
+    GetUtc(DateField),
+    Set(DateField),
+    SetUtc(DateField),
+    GetTime,
+    SetTime,
+    GetTimezoneOffset,
+    ValueOf,
+    ToIsoString,
+    ToJson,
+    ToString,
+    ToDateString,
+    ToTimeString,
+    ToUtcString,
+}
+
+impl DateBuiltin {
+    pub fn name(&self) -> &'static str {
+        match self {
+            DateBuiltin::Constructor => "Date",
+            DateBuiltin::Now => "now",
+            DateBuiltin::Parse => "parse",
+            DateBuiltin::Utc => "UTC",
+            DateBuiltin::Get(field) => field.names()[0],
+            DateBuiltin::GetUtc(field) => field.names()[1],
+            DateBuiltin::Set(field) => field.names()[2],
+            DateBuiltin::SetUtc(field) => field.names()[3],
+            DateBuiltin::GetTime => "getTime",
+            DateBuiltin::SetTime => "setTime",
+            DateBuiltin::GetTimezoneOffset => "getTimezoneOffset",
+            DateBuiltin::ValueOf => "valueOf",
+            DateBuiltin::ToIsoString => "toISOString",
+            DateBuiltin::ToJson => "toJSON",
+            DateBuiltin::ToString => "toString",
+            DateBuiltin::ToDateString => "toDateString",
+            DateBuiltin::ToTimeString => "toTimeString",
+            DateBuiltin::ToUtcString => "toUTCString",
+        }
+    }
+
+    pub fn length(&self) -> usize {
+        match self {
+            DateBuiltin::Constructor | DateBuiltin::Utc => 7,
+            DateBuiltin::Parse | DateBuiltin::SetTime | DateBuiltin::ToJson => 1,
+            DateBuiltin::Set(field) | DateBuiltin::SetUtc(field) => field.setter_length(),
+            _ => 0,
+        }
+    }
+}
+
+/// 負の数でも0以上の余りを返す
+fn modulo(a: f64, b: f64) -> f64 {
+    a - b * floor(a / b)
+}
+
+fn day(t: f64) -> f64 {
+    floor(t / MS_PER_DAY)
+}
+
+fn is_leap_year(year: f64) -> bool {
+    modulo(year, 4.0) == 0.0 && (modulo(year, 100.0) != 0.0 || modulo(year, 400.0) == 0.0)
+}
+
+/// その年の1月1日の、1970年1月1日からの日数
+fn day_from_year(year: f64) -> f64 {
+    365.0 * (year - 1970.0) + floor((year - 1969.0) / 4.0) - floor((year - 1901.0) / 100.0)
+        + floor((year - 1601.0) / 400.0)
+}
+
+/// その月の1日の、年の始めからの日数
+fn day_from_month(month: usize, leap: bool) -> f64 {
+    const CUMULATIVE: [f64; 12] = [
+        0.0, 31.0, 59.0, 90.0, 120.0, 151.0, 181.0, 212.0, 243.0, 273.0, 304.0, 334.0,
+    ];
+    CUMULATIVE[month] + if leap && month >= 2 { 1.0 } else { 0.0 }
+}
+
+fn year_from_time(t: f64) -> f64 {
+    let day = day(t);
+    let mut year = floor(day / 365.2425) + 1970.0;
+    while day_from_year(year) > day {
+        year -= 1.0;
+    }
+    while day_from_year(year + 1.0) <= day {
+        year += 1.0;
+    }
+    year
+}
+
+fn month_from_time(t: f64) -> usize {
+    let year = year_from_time(t);
+    let day_within_year = day(t) - day_from_year(year);
+    let leap = is_leap_year(year);
+    (1..12)
+        .take_while(|m| day_from_month(*m, leap) <= day_within_year)
+        .count()
+}
+
+fn date_from_time(t: f64) -> f64 {
+    let year = year_from_time(t);
+    day(t) - day_from_year(year) - day_from_month(month_from_time(t), is_leap_year(year)) + 1.0
+}
+
+fn field_from_time(t: f64, field: DateField) -> f64 {
+    match field {
+        DateField::FullYear => year_from_time(t),
+        DateField::Month => month_from_time(t) as f64,
+        DateField::Date => date_from_time(t),
+        DateField::Hours => modulo(floor(t / MS_PER_HOUR), 24.0),
+        DateField::Minutes => modulo(floor(t / MS_PER_MINUTE), 60.0),
+        DateField::Seconds => modulo(floor(t / MS_PER_SECOND), 60.0),
+        DateField::Milliseconds => modulo(t, MS_PER_SECOND),
+        DateField::Day => modulo(day(t) + 4.0, 7.0),
+    }
+}
+
+/// https://262.ecma-international.org/11.0/#sec-maketime
+fn make_time(hour: f64, min: f64, sec: f64, ms: f64) -> f64 {
+    if !(hour.is_finite() && min.is_finite() && sec.is_finite() && ms.is_finite()) {
+        return f64::NAN;
+    }
+    trunc(hour) * MS_PER_HOUR + trunc(min) * MS_PER_MINUTE + trunc(sec) * MS_PER_SECOND + trunc(ms)
+}
+
+/// https://262.ecma-international.org/11.0/#sec-makeday
+fn make_day(year: f64, month: f64, date: f64) -> f64 {
+    if !(year.is_finite() && month.is_finite() && date.is_finite()) {
+        return f64::NAN;
+    }
+    let year = trunc(year) + floor(trunc(month) / 12.0);
+    // TimeClipで範囲外になる年は、日数を数えずにNaNにする
+    if abs(year) > 400_000.0 {
+        return f64::NAN;
+    }
+    let month = modulo(trunc(month), 12.0) as usize;
+    day_from_year(year) + day_from_month(month, is_leap_year(year)) + trunc(date) - 1.0
+}
+
+fn make_date(day: f64, time: f64) -> f64 {
+    if !(day.is_finite() && time.is_finite()) {
+        return f64::NAN;
+    }
+    day * MS_PER_DAY + time
+}
+
+/// https://262.ecma-international.org/11.0/#sec-timeclip
+fn time_clip(time: f64) -> f64 {
+    if !time.is_finite() || abs(time) > 8.64e15 {
+        return f64::NAN;
+    }
+    // -0は+0にする
+    trunc(time) + 0.0
+}
+
+/// 年、月、日、時、分、秒、ミリ秒から時刻を作る
+fn time_from_fields(fields: &[f64; 7]) -> f64 {
+    let [year, month, date, hours, minutes, seconds, ms] = *fields;
+    make_date(
+        make_day(year, month, date),
+        make_time(hours, minutes, seconds, ms),
+    )
+}
+
+fn format_year(year: f64) -> String {
+    if year < 0.0 {
+        format!("-{:06}", -year)
+    } else {
+        format!("{:04}", year)
+    }
+}
+
+fn format_date(t: f64) -> String {
+    format!(
+        "{} {} {:02} {}",
+        WEEK_DAYS[field_from_time(t, DateField::Day) as usize],
+        MONTHS[month_from_time(t)],
+        date_from_time(t),
+        format_year(year_from_time(t))
+    )
+}
+
+fn format_time(t: f64) -> String {
+    format!(
+        "{:02}:{:02}:{:02}",
+        field_from_time(t, DateField::Hours),
+        field_from_time(t, DateField::Minutes),
+        field_from_time(t, DateField::Seconds)
+    )
+}
+
+/// Date.prototype.toString
+fn format_date_time(t: f64) -> String {
+    format!("{} {}", format_date(t), format_time_zone(t))
+}
+
+/// Date.prototype.toTimeString
+fn format_time_zone(t: f64) -> String {
+    format!("{} GMT+0000 (Coordinated Universal Time)", format_time(t))
+}
+
+/// Date.prototype.toISOString
+fn format_iso(t: f64) -> String {
+    let year = year_from_time(t);
+    let year = if (0.0..=9999.0).contains(&year) {
+        format!("{:04}", year)
+    } else if year < 0.0 {
+        format!("-{:06}", -year)
+    } else {
+        format!("+{:06}", year)
+    };
+    format!(
+        "{}-{:02}-{:02}T{}.{:03}Z",
+        year,
+        month_from_time(t) + 1,
+        date_from_time(t),
+        format_time(t),
+        field_from_time(t, DateField::Milliseconds)
+    )
+}
+
+fn format_utc(t: f64) -> String {
+    format!(
+        "{}, {:02} {} {} {} GMT",
+        WEEK_DAYS[field_from_time(t, DateField::Day) as usize],
+        date_from_time(t),
+        MONTHS[month_from_time(t)],
+        format_year(year_from_time(t)),
+        format_time(t)
+    )
+}
+
+/// 決まった桁数の数字を読む
+fn parse_digits(s: &mut &str, count: usize) -> Option<f64> {
+    let digits = s.get(..count)?;
+    if !digits.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    *s = &s[count..];
+    digits.parse::<u32>().ok().map(|n| n as f64)
+}
+
+/// 先頭の文字が`c`なら読み進める
+fn eat(s: &mut &str, c: char) -> bool {
+    match s.strip_prefix(c) {
+        Some(rest) => {
+            *s = rest;
+            true
+        }
+        None => false,
+    }
+}
+
+/// `Z`や`+09:00`、`+0900`のようなUTCとの差をミリ秒で読む
+fn parse_offset(s: &mut &str) -> Option<f64> {
+    if eat(s, 'Z') {
+        return Some(0.0);
+    }
+    let sign = if eat(s, '+') {
+        1.0
+    } else if eat(s, '-') {
+        -1.0
+    } else {
+        return None;
+    };
+    let hours = parse_digits(s, 2)?;
+    eat(s, ':');
+    let minutes = parse_digits(s, 2)?;
+    Some(sign * (hours * MS_PER_HOUR + minutes * MS_PER_MINUTE))
+}
+
+/// `HH:mm`、`HH:mm:ss`、`HH:mm:ss.sss`を読む
+fn parse_time(s: &mut &str) -> Option<f64> {
+    let hours = parse_digits(s, 2)?;
+    if !eat(s, ':') {
+        return None;
+    }
+    let minutes = parse_digits(s, 2)?;
+    let mut seconds = 0.0;
+    let mut ms = 0.0;
+    if eat(s, ':') {
+        seconds = parse_digits(s, 2)?;
+        if eat(s, '.') {
+            ms = parse_digits(s, 3)?;
+        }
+    }
+    if hours > 24.0 || minutes > 59.0 || seconds > 59.0 {
+        return None;
+    }
+    if hours == 24.0 && (minutes, seconds, ms) != (0.0, 0.0, 0.0) {
+        return None;
+    }
+    Some(make_time(hours, minutes, seconds, ms))
+}
+
+/// Date Time String Format
+/// https://262.ecma-international.org/11.0/#sec-date-time-string-format
+fn parse_iso(mut s: &str) -> Option<f64> {
+    let s = &mut s;
+    let year = if eat(s, '+') {
+        parse_digits(s, 6)?
+    } else if eat(s, '-') {
+        let year = parse_digits(s, 6)?;
+        // -000000は不正
+        if year == 0.0 {
+            return None;
+        }
+        -year
+    } else {
+        parse_digits(s, 4)?
+    };
+    let mut month = 1.0;
+    let mut date = 1.0;
+    if eat(s, '-') {
+        month = parse_digits(s, 2)?;
+        if eat(s, '-') {
+            date = parse_digits(s, 2)?;
+        }
+    }
+    if !(1.0..=12.0).contains(&month) || !(1.0..=31.0).contains(&date) {
+        return None;
+    }
+
+    let mut time = 0.0;
+    let mut offset = 0.0;
+    if eat(s, 'T') {
+        time = parse_time(s)?;
+        // 時刻があってUTCとの差がない場合は地方時だが、地方時はUTCと同じ
+        if !s.is_empty() {
+            offset = parse_offset(s)?;
+        }
+    }
+    if !s.is_empty() {
+        return None;
+    }
+    Some(make_date(make_day(year, month - 1.0, date), time) - offset)
+}
+
+/// toString()とtoUTCString()の形式
+/// `Tue Jan 02 2024 03:04:05 GMT+0000 (...)`と`Tue, 02 Jan 2024 03:04:05 GMT`
+fn parse_date_string(s: &str) -> Option<f64> {
+    let s = s.split('(').next().unwrap_or("");
+    let mut words = s
+        .split_whitespace()
+        .map(|w| w.trim_end_matches(','))
+        .peekable();
+    if words.peek().is_some_and(|w| WEEK_DAYS.contains(w)) {
+        words.next();
+    }
+
+    let month_index = |w: &str| MONTHS.iter().position(|m| *m == w);
+    let first = words.next()?;
+    let (month, date) = match month_index(first) {
+        Some(month) => (month, words.next()?),
+        None => (month_index(words.next()?)?, first),
+    };
+    let date = date.parse::<u32>().ok()? as f64;
+    let year = words.next()?.parse::<i32>().ok()? as f64;
+
+    let mut time = 0.0;
+    let mut offset = 0.0;
+    if let Some(mut word) = words.next() {
+        time = parse_time(&mut word)?;
+        if !word.is_empty() {
+            return None;
+        }
+        if let Some(zone) = words.next() {
+            let mut zone = zone.strip_prefix("GMT").or(zone.strip_prefix("UTC"))?;
+            if !zone.is_empty() {
+                offset = parse_offset(&mut zone)?;
+            }
+        }
+    }
+    if words.next().is_some() {
+        return None;
+    }
+    Some(make_date(make_day(year, month as f64, date), time) - offset)
+}
+
+/// Date.parse。読めない文字列はNaN
+pub fn parse_date(s: &str) -> f64 {
+    let s = s.trim();
+    let t = parse_iso(s).or_else(|| parse_date_string(s));
+    time_clip(t.unwrap_or(f64::NAN))
+}
+
+impl JsRuntime {
+    pub(super) fn install_date(&mut self) {
+        let prototype = self.intrinsics.date_prototype;
+        let constructor =
+            self.define_constructor(Builtin::Date(DateBuiltin::Constructor), prototype);
+        for method in [DateBuiltin::Now, DateBuiltin::Parse, DateBuiltin::Utc] {
+            self.define_builtin_method(constructor, Builtin::Date(method));
+        }
+
+        let mut methods = Vec::new();
+        for field in DateField::SETTABLE {
+            methods.push(DateBuiltin::Get(field));
+            methods.push(DateBuiltin::GetUtc(field));
+        }
+        methods.push(DateBuiltin::Get(DateField::Day));
+        methods.push(DateBuiltin::GetUtc(DateField::Day));
+        for field in DateField::SETTABLE {
+            methods.push(DateBuiltin::Set(field));
+            methods.push(DateBuiltin::SetUtc(field));
+        }
+        methods.extend([
+            DateBuiltin::GetTime,
+            DateBuiltin::SetTime,
+            DateBuiltin::GetTimezoneOffset,
+            DateBuiltin::ValueOf,
+            DateBuiltin::ToIsoString,
+            DateBuiltin::ToJson,
+            DateBuiltin::ToString,
+            DateBuiltin::ToDateString,
+            DateBuiltin::ToTimeString,
+            DateBuiltin::ToUtcString,
+        ]);
+        for method in methods {
+            self.define_builtin_method(prototype, Builtin::Date(method));
+        }
+    }
+
+    /// 引数を年、月、日、時、分、秒、ミリ秒として時刻にする
+    /// 0から99の年は1900年代とする
+    fn time_from_arguments(&mut self, arguments: &[RuntimeValue]) -> Result<f64, Completion> {
+        let mut fields = [f64::NAN, 0.0, 1.0, 0.0, 0.0, 0.0, 0.0];
+        for (field, value) in fields.iter_mut().zip(arguments) {
+            *field = self.coerce_to_number(value.clone())?;
+        }
+        let year = fields[0];
+        if !year.is_nan() && (0.0..=99.0).contains(&trunc(year)) {
+            fields[0] = 1900.0 + trunc(year);
+        }
+        Ok(time_clip(time_from_fields(&fields)))
+    }
+
+    pub(super) fn construct_date(
+        &mut self,
+        arguments: &[RuntimeValue],
+    ) -> Result<RuntimeValue, Completion> {
+        let t = match arguments {
+            [] => time_clip(self.clock.now()),
+            [value] => {
+                let value = match value {
+                    RuntimeValue::Object(id) => match self.heap.get(*id).kind() {
+                        ObjectKind::Date(t) => RuntimeValue::Number(*t),
+                        _ => self.coerce_to_primitive(value.clone(), PreferredType::Default)?,
+                    },
+                    v => v.clone(),
+                };
+                match value {
+                    RuntimeValue::String(s) => parse_date(&s),
+                    v => time_clip(self.coerce_to_number(v)?),
+                }
+            }
+            _ => self.time_from_arguments(arguments)?,
+        };
+        let date = self.heap.allocate(JsObject::new(
+            ObjectKind::Date(t),
+            Some(self.intrinsics.date_prototype),
+        ));
+        Ok(RuntimeValue::Object(date))
+    }
+
+    /// Dateのメソッドのthisの時刻
+    fn this_time_value(&self, this: &RuntimeValue) -> Result<f64, Completion> {
+        if let RuntimeValue::Object(id) = this {
+            if let ObjectKind::Date(t) = self.heap.get(*id).kind() {
+                return Ok(*t);
+            }
+        }
+        Err(Completion::error(
+            ErrorKind::TypeError,
+            "this is not a Date object.",
+        ))
+    }
+
+    fn set_time_value(&mut self, this: &RuntimeValue, t: f64) -> RuntimeValue {
+        if let RuntimeValue::Object(id) = this {
+            *self.heap.get_mut(*id).kind_mut() = ObjectKind::Date(t);
+        }
+        RuntimeValue::Number(t)
+    }
+
+    /// setFullYearやsetHoursなどで、`field`から始まる要素を引数の値にする
+    fn set_date_fields(
+        &mut self,
+        this: &RuntimeValue,
+        field: DateField,
+        arguments: &[RuntimeValue],
+    ) -> Result<RuntimeValue, Completion> {
+        let mut t = self.this_time_value(this)?;
+        let mut values = Vec::new();
+        for i in 0..field.setter_length().min(arguments.len()).max(1) {
+            values.push(self.coerce_to_number(argument(arguments, i))?);
+        }
+        if t.is_nan() {
+            // 不正な日時でも、setFullYearは1970年1月1日を基準にする
+            if field != DateField::FullYear {
+                return Ok(RuntimeValue::Number(f64::NAN));
+            }
+            t = 0.0;
+        }
+
+        let mut fields = DateField::SETTABLE.map(|f| field_from_time(t, f));
+        let start = DateField::SETTABLE
+            .iter()
+            .position(|f| *f == field)
+            .unwrap_or(0);
+        for (i, value) in values.into_iter().enumerate() {
+            fields[start + i] = value;
+        }
+        Ok(self.set_time_value(this, time_clip(time_from_fields(&fields))))
+    }
+
+    pub(super) fn call_date(
+        &mut self,
+        function: DateBuiltin,
+        this: RuntimeValue,
+        arguments: &[RuntimeValue],
+    ) -> Result<RuntimeValue, Completion> {
+        // 不正な日時は文字列にできない
+        let formatted = |runtime: &Self, format: fn(f64) -> String| {
+            let t = runtime.this_time_value(&this)?;
+            Ok(RuntimeValue::String(if t.is_nan() {
+                "Invalid Date".to_string()
+            } else {
+                format(t)
+            }))
+        };
+
+        let result = match function {
+            // newをつけずに呼んだ場合は、現在時刻の文字列を返す
+            DateBuiltin::Constructor => {
+                let t = time_clip(self.clock.now());
+                RuntimeValue::String(if t.is_nan() {
+                    "Invalid Date".to_string()
+                } else {
+                    format_date_time(t)
+                })
+            }
+            DateBuiltin::Now => RuntimeValue::Number(time_clip(self.clock.now())),
+            DateBuiltin::Parse => {
+                let s = self.coerce_to_string(argument(arguments, 0))?;
+                RuntimeValue::Number(parse_date(&s))
+            }
+            DateBuiltin::Utc => {
+                let mut arguments = arguments.to_vec();
+                if arguments.len() == 1 {
+                    arguments.push(RuntimeValue::Number(0.0));
+                }
+                RuntimeValue::Number(self.time_from_arguments(&arguments)?)
+            }
+            DateBuiltin::Get(field) | DateBuiltin::GetUtc(field) => {
+                let t = self.this_time_value(&this)?;
+                RuntimeValue::Number(if t.is_nan() {
+                    f64::NAN
+                } else {
+                    field_from_time(t, field)
+                })
+            }
+            DateBuiltin::Set(field) | DateBuiltin::SetUtc(field) => {
+                self.set_date_fields(&this, field, arguments)?
+            }
+            DateBuiltin::GetTime | DateBuiltin::ValueOf => {
+                RuntimeValue::Number(self.this_time_value(&this)?)
+            }
+            DateBuiltin::SetTime => {
+                self.this_time_value(&this)?;
+                let t = self.coerce_to_number(argument(arguments, 0))?;
+                self.set_time_value(&this, time_clip(t))
+            }
+            DateBuiltin::GetTimezoneOffset => {
+                let t = self.this_time_value(&this)?;
+                RuntimeValue::Number(if t.is_nan() { f64::NAN } else { 0.0 })
+            }
+            DateBuiltin::ToIsoString => {
+                let t = self.this_time_value(&this)?;
+                if t.is_nan() {
+                    return Err(Completion::error(
+                        ErrorKind::RangeError,
+                        "Invalid time value",
+                    ));
+                }
+                RuntimeValue::String(format_iso(t))
+            }
+            // https://262.ecma-international.org/11.0/#sec-date.prototype.tojson
+            DateBuiltin::ToJson => {
+                let object = self.coerce_to_object(&this)?;
+                let value =
+                    self.coerce_to_primitive(RuntimeValue::Object(object), PreferredType::Number)?;
+                if let RuntimeValue::Number(n) = value {
+                    if !n.is_finite() {
+                        return Ok(RuntimeValue::Null);
+                    }
+                }
+                let to_iso_string = self.get(
+                    object,
+                    &PropertyKey::from("toISOString"),
+                    RuntimeValue::Object(object),
+                )?;
+                self.require_callable(&to_iso_string)?;
+                self.call(&to_iso_string, RuntimeValue::Object(object), &[])?
+            }
+            DateBuiltin::ToString => formatted(self, format_date_time)?,
+            DateBuiltin::ToDateString => formatted(self, format_date)?,
+            DateBuiltin::ToTimeString => formatted(self, format_time_zone)?,
+            DateBuiltin::ToUtcString => formatted(self, format_utc)?,
+        };
+        Ok(result)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::renderer::js::runtime::tests::eval_last;
+    use crate::renderer::js::runtime::tests::number;
+    use crate::renderer::js::runtime::tests::string;
+    use crate::renderer::js::runtime::tests::throws;
+
+    #[test]
+    fn test_day_and_time_math() {
+        // 2024年2月29日 12:34:56.789
+        let t = 1709210096789.0;
+        assert_eq!(2024.0, year_from_time(t));
+        assert_eq!(1, month_from_time(t));
+        assert_eq!(29.0, date_from_time(t));
+        assert_eq!(4.0, field_from_time(t, DateField::Day));
+        assert_eq!(12.0, field_from_time(t, DateField::Hours));
+        assert_eq!(789.0, field_from_time(t, DateField::Milliseconds));
+        assert_eq!(
+            t,
+            time_from_fields(&[2024.0, 1.0, 29.0, 12.0, 34.0, 56.0, 789.0])
+        );
+        // 1969年12月31日 23:59:59.999
+        assert_eq!(1969.0, year_from_time(-1.0));
+        assert_eq!(31.0, date_from_time(-1.0));
+        // 月があふれた場合は次の年になる
+        assert_eq!(make_day(2025.0, 0.0, 1.0), make_day(2024.0, 12.0, 1.0));
+    }
+
+    #[test]
+    fn test_format_and_parse() {
+        let t = 1704164645006.0;
+        assert_eq!("2024-01-02T03:04:05.006Z", format_iso(t));
+        assert_eq!("Tue, 02 Jan 2024 03:04:05 GMT", format_utc(t));
+        assert_eq!("Tue Jan 02 2024", format_date(t));
+        assert_eq!(t, parse_date("2024-01-02T03:04:05.006Z"));
+        assert_eq!(t - 6.0, parse_date("Tue, 02 Jan 2024 03:04:05 GMT"));
+        assert_eq!(
+            t - 6.0,
+            parse_date("Tue Jan 02 2024 03:04:05 GMT+0000 (Coordinated Universal Time)")
+        );
+        assert_eq!(
+            t - 6.0 - MS_PER_HOUR * 9.0,
+            parse_date("2024-01-02T03:04:05+09:00")
+        );
+        assert_eq!(-62198755200000.0, parse_date("-000001-01-01"));
+        assert_eq!("-000001-01-01T00:00:00.000Z", format_iso(-62198755200000.0));
+        assert!(parse_date("2024-13-01").is_nan());
+        assert!(parse_date("hello").is_nan());
+    }
+
+    #[test]
+    fn test_invalid_dates() {
+        assert_eq!(
+            string("NaN,Invalid Date,NaN"),
+            eval_last(
+                "[new Date(NaN).getTime(), String(new Date('x')), new Date(8.64e15 + 1).getTime()].join()"
+            )
+        );
+        // 不正な日時を文字列にできるのはtoStringだけ
+        assert!(throws(
+            eval_last("new Date(NaN).toISOString()"),
+            ErrorKind::RangeError
+        ));
+        assert_eq!(
+            Ok(Some(RuntimeValue::Null)),
+            eval_last("new Date(NaN).toJSON()")
+        );
+        assert!(throws(
+            eval_last("Date.prototype.getTime.call({})"),
+            ErrorKind::TypeError
+        ));
+        assert!(throws(
+            eval_last("Date.prototype.setTime.call({}, 0)"),
+            ErrorKind::TypeError
+        ));
+    }
+
+    #[test]
+    fn test_date_coercion() {
+        assert_eq!(
+            number(1704153600000.0),
+            eval_last("new Date('2024-01-02').getTime()")
+        );
+        // 引数は数値に変換し、あふれた月は次の年になる
+        assert_eq!(
+            string("2025-01-01T00:00:00.000Z"),
+            eval_last("new Date(Date.UTC('2024', 12)).toISOString()")
+        );
+        assert_eq!(number(4.0), eval_last("new Date(2024, 1, 29, 12).getDay()"));
+        // toJSONはthisのtoISOStringを呼ぶ
+        assert_eq!(
+            string("x"),
+            eval_last("Date.prototype.toJSON.call({ toISOString() { return 'x'; } })")
+        );
+        // 関数として呼ぶと、今の時刻の文字列を返す
+        assert_eq!(
+            string("Thu Jan 01 1970 00:00:00 GMT+0000 (Coordinated Universal Time)"),
+            eval_last("Date(2024)")
+        );
+    }
+}
@@ -0,0 +1,631 @@
+//! JSON.parseとJSON.stringify
+//! https://262.ecma-international.org/11.0/#sec-json-object
+
+use crate::renderer::js::heap::ObjectId;
+use crate::renderer::js::object::JsObject;
+use crate::renderer::js::object::ObjectKind;
+use crate::renderer::js::object::PropertyKey;
+use crate::renderer::js::runtime::builtins::argument;
+use crate::renderer::js::runtime::builtins::Builtin;
+use crate::renderer::js::runtime::Completion;
+use crate::renderer::js::runtime::ErrorKind;
+use crate::renderer::js::runtime::JsRuntime;
+use crate::renderer::js::value::number_to_string;
+use crate::renderer::js::value::string_to_number;
+use crate::renderer::js::value::RuntimeValue;
+use alloc::format;
+use alloc::string::String;
+use alloc::string::ToString;
+use alloc::vec::Vec;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum JsonBuiltin {
+    Parse,
+    Stringify,
+}
+
+impl JsonBuiltin {
+    pub fn name(&self) -> &'static str {
+        match self {
+            JsonBuiltin::Parse => "parse",
+            JsonBuiltin::Stringify => "stringify",
+        }
+    }
+
+    pub fn length(&self) -> usize {
+        match self {
+            JsonBuiltin::Parse => 2,
+            JsonBuiltin::Stringify => 3,
+        }
+    }
+}
+
+/// JSONの文字列リテラルにする
+/// https://262.ecma-international.org/11.0/#sec-quotejsonstring
+fn quote(s: &str) -> String {
+    let mut quoted = String::from("\"");
+    for c in s.chars() {
+        match c {
+            '\u{8}' => quoted.push_str("\\b"),
+            '\t' => quoted.push_str("\\t"),
+            '\n' => quoted.push_str("\\n"),
+            '\u{c}' => quoted.push_str("\\f"),
+            '\r' => quoted.push_str("\\r"),
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            c if (c as u32) < 0x20 => quoted.push_str(&format!("\\u{:04x}", c as u32)),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+/// JSONのテキストを値にする構文解析器
+/// https://www.rfc-editor.org/rfc/rfc8259
+struct JsonParser<'a> {
+    runtime: &'a mut JsRuntime,
+    input: Vec<char>,
+    position: usize,
+}
+
+impl<'a> JsonParser<'a> {
+    fn new(runtime: &'a mut JsRuntime, text: &str) -> Self {
+        Self {
+            runtime,
+            input: text.chars().collect(),
+            position: 0,
+        }
+    }
+
+    fn unexpected(&self) -> Completion {
+        let message = match self.input.get(self.position) {
+            Some(c) => format!(
+                "Unexpected token {} in JSON at position {}",
+                c, self.position
+            ),
+            None => "Unexpected end of JSON input".to_string(),
+        };
+        Completion::error(ErrorKind::SyntaxError, &message)
+    }
+
+    fn skip_whitespace(&mut self) {
+        while let Some(' ' | '\t' | '\n' | '\r') = self.input.get(self.position) {
+            self.position += 1;
+        }
+    }
+
+    fn peek(&mut self) -> Option<char> {
+        self.skip_whitespace();
+        self.input.get(self.position).copied()
+    }
+
+    fn expect(&mut self, c: char) -> Result<(), Completion> {
+        if self.peek() != Some(c) {
+            return Err(self.unexpected());
+        }
+        self.position += 1;
+        Ok(())
+    }
+
+    /// 値の後に空白以外が残っていればエラーにする
+    fn parse_text(&mut self) -> Result<RuntimeValue, Completion> {
+        let value = self.parse_value()?;
+        if self.peek().is_some() {
+            return Err(self.unexpected());
+        }
+        Ok(value)
+    }
+
+    fn parse_value(&mut self) -> Result<RuntimeValue, Completion> {
+        match self.peek() {
+            Some('{') => self.parse_object(),
+            Some('[') => self.parse_array(),
+            Some('"') => Ok(RuntimeValue::String(self.parse_string()?)),
+            Some('-' | '0'..='9') => self.parse_number(),
+            Some('t') => self.parse_literal("true", RuntimeValue::Boolean(true)),
+            Some('f') => self.parse_literal("false", RuntimeValue::Boolean(false)),
+            Some('n') => self.parse_literal("null", RuntimeValue::Null),
+            _ => Err(self.unexpected()),
+        }
+    }
+
+    fn parse_literal(
+        &mut self,
+        literal: &str,
+        value: RuntimeValue,
+    ) -> Result<RuntimeValue, Completion> {
+        for c in literal.chars() {
+            if self.input.get(self.position) != Some(&c) {
+                return Err(self.unexpected());
+            }
+            self.position += 1;
+        }
+        Ok(value)
+    }
+
+    fn parse_object(&mut self) -> Result<RuntimeValue, Completion> {
+        self.expect('{')?;
+        let object = self.runtime.heap.allocate(JsObject::new(
+            ObjectKind::Ordinary,
+            Some(self.runtime.intrinsics.object_prototype),
+        ));
+        if self.peek() == Some('}') {
+            self.position += 1;
+            return Ok(RuntimeValue::Object(object));
+        }
+        loop {
+            if self.peek() != Some('"') {
+                return Err(self.unexpected());
+            }
+            let key = self.parse_string()?;
+            self.expect(':')?;
+            let value = self.parse_value()?;
+            self.runtime
+                .create_data_property(object, PropertyKey::String(key), value)?;
+            match self.peek() {
+                Some(',') => self.position += 1,
+                Some('}') => {
+                    self.position += 1;
+                    return Ok(RuntimeValue::Object(object));
+                }
+                _ => return Err(self.unexpected()),
+            }
+        }
+    }
+
+    fn parse_array(&mut self) -> Result<RuntimeValue, Completion> {
+        self.expect('[')?;
+        let mut values = Vec::new();
+        if self.peek() == Some(']') {
+            self.position += 1;
+        } else {
+            loop {
+                values.push(self.parse_value()?);
+                match self.peek() {
+                    Some(',') => self.position += 1,
+                    Some(']') => {
+                        self.position += 1;
+                        break;
+                    }
+                    _ => return Err(self.unexpected()),
+                }
+            }
+        }
+        Ok(RuntimeValue::Object(self.runtime.create_array(values)?))
+    }
+
+    fn parse_string(&mut self) -> Result<String, Completion> {
+        self.expect('"')?;
+        let mut units: Vec<u16> = Vec::new();
+        loop {
+            let c = match self.input.get(self.position) {
+                Some(c) => *c,
+                None => return Err(self.unexpected()),
+            };
+            match c {
+                '"' => {
+                    self.position += 1;
+                    return Ok(String::from_utf16_lossy(&units));
+                }
+                '\\' => {
+                    self.position += 1;
+                    let escaped = match self.input.get(self.position) {
+                        Some('"') => '"',
+                        Some('\\') => '\\',
+                        Some('/') => '/',
+                        Some('b') => '\u{8}',
+                        Some('f') => '\u{c}',
+                        Some('n') => '\n',
+                        Some('r') => '\r',
+                        Some('t') => '\t',
+                        Some('u') => {
+                            let hex: String =
+                                self.input.iter().skip(self.position + 1).take(4).collect();
+                            match u16::from_str_radix(&hex, 16) {
+                                Ok(unit) if hex.len() == 4 => {
+                                    // サロゲートペアは、続く\uと合わせて1文字にする
+                                    units.push(unit);
+                                    self.position += 5;
+                                    continue;
+                                }
+                                _ => {
+                                    self.position += 1;
+                                    return Err(self.unexpected());
+                                }
+                            }
+                        }
+                        _ => return Err(self.unexpected()),
+                    };
+                    let mut buffer = [0; 2];
+                    units.extend_from_slice(escaped.encode_utf16(&mut buffer));
+                    self.position += 1;
+                }
+                // 制御文字はエスケープしなければならない
+                c if (c as u32) < 0x20 => return Err(self.unexpected()),
+                c => {
+                    let mut buffer = [0; 2];
+                    units.extend_from_slice(c.encode_utf16(&mut buffer));
+                    self.position += 1;
+                }
+            }
+        }
+    }
+
+    fn parse_number(&mut self) -> Result<RuntimeValue, Completion> {
+        let start = self.position;
+        let digits = |parser: &mut Self| {
+            let from = parser.position;
+            while parser
+                .input
+                .get(parser.position)
+                .is_some_and(|c| c.is_ascii_digit())
+            {
+                parser.position += 1;
+            }
+            parser.position > from
+        };
+
+        if self.input.get(self.position) == Some(&'-') {
+            self.position += 1;
+        }
+        // 0から始まる整数部は0だけ
+        if self.input.get(self.position) == Some(&'0') {
+            self.position += 1;
+        } else if !digits(self) {
+            return Err(self.unexpected());
+        }
+        if self.input.get(self.position) == Some(&'.') {
+            self.position += 1;
+            if !digits(self) {
+                return Err(self.unexpected());
+            }
+        }
+        if let Some('e' | 'E') = self.input.get(self.position) {
+            self.position += 1;
+            if let Some('+' | '-') = self.input.get(self.position) {
+                self.position += 1;
+            }
+            if !digits(self) {
+                return Err(self.unexpected());
+            }
+        }
+
+        let text: String = self.input[start..self.position].iter().collect();
+        Ok(RuntimeValue::Number(string_to_number(&text)))
+    }
+}
+
+/// JSON.stringifyの途中の状態
+/// https://262.ecma-international.org/11.0/#sec-json.stringify
+struct JsonSerializer {
+    replacer: Option<RuntimeValue>,
+    property_list: Option<Vec<PropertyKey>>,
+    gap: String,
+    indent: String,
+    /// 循環参照を見つけるための、シリアライズ中のオブジェクト
+    stack: Vec<ObjectId>,
+}
+
+impl JsRuntime {
+    pub(super) fn install_json(&mut self) {
+        self.define_namespace(
+            "JSON",
+            &[
+                Builtin::Json(JsonBuiltin::Parse),
+                Builtin::Json(JsonBuiltin::Stringify),
+            ],
+        );
+    }
+
+    pub(super) fn call_json(
+        &mut self,
+        function: JsonBuiltin,
+        arguments: &[RuntimeValue],
+    ) -> Result<RuntimeValue, Completion> {
+        match function {
+            JsonBuiltin::Parse => {
+                let text = self.coerce_to_string(argument(arguments, 0))?;
+                let value = JsonParser::new(self, &text).parse_text()?;
+                let reviver = argument(arguments, 1);
+                if !self.is_callable(&reviver) {
+                    return Ok(value);
+                }
+                let root = self.heap.allocate(JsObject::new(
+                    ObjectKind::Ordinary,
+                    Some(self.intrinsics.object_prototype),
+                ));
+                self.create_data_property(root, PropertyKey::from(""), value)?;
+                self.internalize_json_property(root, PropertyKey::from(""), &reviver)
+            }
+            JsonBuiltin::Stringify => self.json_stringify(
+                argument(arguments, 0),
+                argument(arguments, 1),
+                argument(arguments, 2),
+            ),
+        }
+    }
+
+    /// JSON.parseのreviverを、深いプロパティから順に呼び出す
+    /// https://262.ecma-international.org/11.0/#sec-internalizejsonproperty
+    fn internalize_json_property(
+        &mut self,
+        holder: ObjectId,
+        name: PropertyKey,
+        reviver: &RuntimeValue,
+    ) -> Result<RuntimeValue, Completion> {
+        let value = self.get(holder, &name, RuntimeValue::Object(holder))?;
+        if let RuntimeValue::Object(id) = value {
+            let keys = if self.is_array(&value) {
+                (0..self.length_of(id)?).map(PropertyKey::from).collect()
+            } else {
+                self.enumerable_own_keys(id)
+            };
+            for key in keys {
+                let element = self.internalize_json_property(id, key.clone(), reviver)?;
+                if let RuntimeValue::Undefined = element {
+                    self.heap.get_mut(id).delete(&key);
+                } else {
+                    self.create_data_property(id, key, element)?;
+                }
+            }
+        }
+        let name = RuntimeValue::String(name.to_string());
+        self.call(reviver, RuntimeValue::Object(holder), &[name, value])
+    }
+
+    fn json_stringify(
+        &mut self,
+        value: RuntimeValue,
+        replacer: RuntimeValue,
+        space: RuntimeValue,
+    ) -> Result<RuntimeValue, Completion> {
+        let mut serializer = JsonSerializer {
+            replacer: None,
+            property_list: None,
+            gap: String::new(),
+            indent: String::new(),
+            stack: Vec::new(),
+        };
+
+        if self.is_callable(&replacer) {
+            serializer.replacer = Some(replacer);
+        } else if self.is_array(&replacer) {
+            // 配列のreplacerは、出力するプロパティ名の一覧になる
+            let mut keys: Vec<PropertyKey> = Vec::new();
+            for item in self.list_from_array_like(&replacer)? {
+                let key = match item {
+                    RuntimeValue::String(s) => s,
+                    RuntimeValue::Number(n) => number_to_string(n),
+                    RuntimeValue::Object(id)
+                        if matches!(
+                            self.heap.get(id).kind(),
+                            ObjectKind::Primitive(
+                                RuntimeValue::String(_) | RuntimeValue::Number(_)
+                            )
+                        ) =>
+                    {
+                        self.coerce_to_string(item)?
+                    }
+                    _ => continue,
+                };
+                let key = PropertyKey::String(key);
+                if !keys.contains(&key) {
+                    keys.push(key);
+                }
+            }
+            serializer.property_list = Some(keys);
+        }
+
+        let space = match space {
+            RuntimeValue::Object(id) => match self.heap.get(id).kind() {
+                ObjectKind::Primitive(RuntimeValue::Number(_)) => {
+                    RuntimeValue::Number(self.coerce_to_number(space)?)
+                }
+                ObjectKind::Primitive(RuntimeValue::String(_)) => {
+                    RuntimeValue::String(self.coerce_to_string(space)?)
+                }
+                _ => space,
+            },
+            v => v,
+        };
+        // インデントは10文字まで
+        serializer.gap = match space {
+            RuntimeValue::Number(_) => {
+                let n = self.coerce_to_integer(space)?.clamp(0.0, 10.0);
+                " ".repeat(n as usize)
+            }
+            RuntimeValue::String(s) => s.chars().take(10).collect(),
+            _ => String::new(),
+        };
+
+        let wrapper = self.heap.allocate(JsObject::new(
+            ObjectKind::Ordinary,
+            Some(self.intrinsics.object_prototype),
+        ));
+        self.create_data_property(wrapper, PropertyKey::from(""), value)?;
+        let result =
+            self.serialize_json_property(&mut serializer, PropertyKey::from(""), wrapper)?;
+        Ok(match result {
+            Some(s) => RuntimeValue::String(s),
+            None => RuntimeValue::Undefined,
+        })
+    }
+
+    /// 値をJSONの文字列にする。出力しない値の場合はNone
+    /// https://262.ecma-international.org/11.0/#sec-serializejsonproperty
+    fn serialize_json_property(
+        &mut self,
+        serializer: &mut JsonSerializer,
+        key: PropertyKey,
+        holder: ObjectId,
+    ) -> Result<Option<String>, Completion> {
+        let mut value = self.get(holder, &key, RuntimeValue::Object(holder))?;
+        if let RuntimeValue::Object(id) = value {
+            let to_json = self.get(id, &PropertyKey::from("toJSON"), value.clone())?;
+            if self.is_callable(&to_json) {
+                value = self.call(&to_json, value, &[RuntimeValue::String(key.to_string())])?;
+            }
+        }
+        if let Some(replacer) = serializer.replacer.clone() {
+            value = self.call(
+                &replacer,
+                RuntimeValue::Object(holder),
+                &[RuntimeValue::String(key.to_string()), value],
+            )?;
+        }
+
+        // プリミティブ値を包んだオブジェクトは、中の値として扱う
+        if let RuntimeValue::Object(id) = value {
+            value = match self.heap.get(id).kind() {
+                ObjectKind::Primitive(RuntimeValue::Number(_)) => {
+                    RuntimeValue::Number(self.coerce_to_number(value)?)
+                }
+                ObjectKind::Primitive(RuntimeValue::String(_)) => {
+                    RuntimeValue::String(self.coerce_to_string(value)?)
+                }
+                ObjectKind::Primitive(RuntimeValue::Boolean(b)) => RuntimeValue::Boolean(*b),
+                _ => value,
+            };
+        }
+
+        let serialized = match value {
+            RuntimeValue::Null => "null".to_string(),
+            RuntimeValue::Boolean(b) => b.to_string(),
+            RuntimeValue::String(s) => quote(&s),
+            RuntimeValue::Number(n) if n.is_finite() => number_to_string(n),
+            RuntimeValue::Number(_) => "null".to_string(),
+            RuntimeValue::Object(id) if !self.heap.get(id).is_callable() => {
+                if serializer.stack.contains(&id) {
+                    return Err(Completion::error(
+                        ErrorKind::TypeError,
+                        "Converting circular structure to JSON",
+                    ));
+                }
+                serializer.stack.push(id);
+                let stepback = serializer.indent.clone();
+                serializer.indent.push_str(&serializer.gap.clone());
+
+                let (open, close, parts) = if self.is_array(&value) {
+                    let mut parts = Vec::new();
+                    for i in 0..self.length_of(id)? {
+                        let part = self.serialize_json_property(serializer, i.into(), id)?;
+                        parts.push(part.unwrap_or_else(|| "null".to_string()));
+                    }
+                    ('[', ']', parts)
+                } else {
+                    let keys = match &serializer.property_list {
+                        Some(keys) => keys.clone(),
+                        None => self.enumerable_own_keys(id),
+                    };
+                    let separator = if serializer.gap.is_empty() { ":" } else { ": " };
+                    let mut parts = Vec::new();
+                    for key in keys {
+                        let name = quote(&key.to_string());
+                        if let Some(part) = self.serialize_json_property(serializer, key, id)? {
+                            parts.push(format!("{}{}{}", name, separator, part));
+                        }
+                    }
+                    ('{', '}', parts)
+                };
+
+                let serialized = if parts.is_empty() {
+                    format!("{}{}", open, close)
+                } else if serializer.gap.is_empty() {
+                    format!("{}{}{}", open, parts.join(","), close)
+                } else {
+                    let separator = format!(",\n{}", serializer.indent);
+                    format!(
+                        "{}\n{}{}\n{}{}",
+                        open,
+                        serializer.indent,
+                        parts.join(&separator),
+                        stepback,
+                        close
+                    )
+                };
+                serializer.stack.pop();
+                serializer.indent = stepback;
+                serialized
+            }
+            _ => return Ok(None),
+        };
+        Ok(Some(serialized))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::renderer::js::runtime::tests::eval_last;
+    use crate::renderer::js::runtime::tests::number;
+    use crate::renderer::js::runtime::tests::string;
+    use crate::renderer::js::runtime::tests::throws;
+
+    #[test]
+    fn test_quote() {
+        assert_eq!("\"abc\"", quote("abc"));
+        assert_eq!("\"a\\\"b\\\\c\"", quote("a\"b\\c"));
+        assert_eq!("\"\\n\\t\\u0001\"", quote("\n\t\u{1}"));
+    }
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(
+            number(2.0),
+            eval_last(r#"JSON.parse(' {"a": [1, 2]} ').a[1]"#)
+        );
+        assert_eq!(string("A"), eval_last(r#"JSON.parse('"\\u0041"')"#));
+        // 引数は文字列に変換してから読む
+        assert_eq!(
+            number(12.0),
+            eval_last("JSON.parse({ toString() { return '12'; } })")
+        );
+        assert_eq!(
+            string("2,4"),
+            eval_last("JSON.parse('[1, 2]', (k, v) => typeof v === 'number' ? v * 2 : v).join()")
+        );
+        assert!(throws(
+            eval_last("JSON.parse('[1,]')"),
+            ErrorKind::SyntaxError
+        ));
+        assert!(throws(
+            eval_last("JSON.parse(\"'a'\")"),
+            ErrorKind::SyntaxError
+        ));
+        assert!(throws(
+            eval_last("JSON.parse('01')"),
+            ErrorKind::SyntaxError
+        ));
+        assert!(throws(eval_last("JSON.parse('')"), ErrorKind::SyntaxError));
+    }
+
+    #[test]
+    fn test_stringify() {
+        // undefinedと関数は、オブジェクトでは省き、配列ではnullにする
+        assert_eq!(
+            string("{\"c\":1}"),
+            eval_last("JSON.stringify({ a: undefined, b: function () {}, c: 1 })")
+        );
+        assert_eq!(
+            string("[null,null,null]"),
+            eval_last("JSON.stringify([undefined, function () {}, NaN])")
+        );
+        assert_eq!(
+            Ok(Some(RuntimeValue::Undefined)),
+            eval_last("JSON.stringify(undefined)")
+        );
+        assert_eq!(
+            string("5"),
+            eval_last("JSON.stringify({ toJSON() { return 5; } })")
+        );
+        assert_eq!(
+            string("{\n  \"a\": [\n    1\n  ]\n}"),
+            eval_last("JSON.stringify({ a: [1] }, null, 2)")
+        );
+        assert!(throws(
+            eval_last("var o = {}; o.o = o; JSON.stringify(o)"),
+            ErrorKind::TypeError
+        ));
+    }
+}
@@ -0,0 +1,588 @@
+//! NumberのコンストラクタとメソッドとMath
+//! https://262.ecma-international.org/11.0/#sec-number-objects
+//! https://262.ecma-international.org/11.0/#sec-math-object
+
+use crate::renderer::js::math;
+use crate::renderer::js::math::abs;
+use crate::renderer::js::math::floor;
+use crate::renderer::js::math::is_integer;
+use crate::renderer::js::object::ObjectKind;
+use crate::renderer::js::runtime::builtins::argument;
+use crate::renderer::js::runtime::builtins::Builtin;
+use crate::renderer::js::runtime::Completion;
+use crate::renderer::js::runtime::ErrorKind;
+use crate::renderer::js::runtime::JsRuntime;
+use crate::renderer::js::value::number_to_string;
+use crate::renderer::js::value::RuntimeValue;
+use alloc::format;
+use alloc::string::String;
+use alloc::string::ToString;
+use alloc::vec::Vec;
+use core::cell::RefCell;
+use core::f64::consts;
+
+/// 2^53 - 1
+const MAX_SAFE_INTEGER: f64 = 9007199254740991.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NumberBuiltin {
+    Constructor,
+    IsFinite,
+    IsInteger,
+    IsNaN,
+    IsSafeInteger,
+    ToFixed,
+    ToString,
+    ValueOf,
+}
+
+impl NumberBuiltin {
+    pub fn name(&self) -> &'static str {
+        match self {
+            NumberBuiltin::Constructor => "Number",
+            NumberBuiltin::IsFinite => "isFinite",
+            NumberBuiltin::IsInteger => "isInteger",
+            NumberBuiltin::IsNaN => "isNaN",
+            NumberBuiltin::IsSafeInteger => "isSafeInteger",
+            NumberBuiltin::ToFixed => "toFixed",
+            NumberBuiltin::ToString => "toString",
+            NumberBuiltin::ValueOf => "valueOf",
+        }
+    }
+
+    pub fn length(&self) -> usize {
+        match self {
+            NumberBuiltin::ValueOf => 0,
+            _ => 1,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MathBuiltin {
+    Abs,
+    Acos,
+    Asin,
+    Atan,
+    Atan2,
+    Cbrt,
+    Ceil,
+    Cos,
+    Exp,
+    Floor,
+    Hypot,
+    Log,
+    Log10,
+    Log2,
+    Max,
+    Min,
+    Pow,
+    Random,
+    Round,
+    Sign,
+    Sin,
+    Sqrt,
+    Tan,
+    Trunc,
+}
+
+impl MathBuiltin {
+    const ALL: [MathBuiltin; 24] = [
+        MathBuiltin::Abs,
+        MathBuiltin::Acos,
+        MathBuiltin::Asin,
+        MathBuiltin::Atan,
+        MathBuiltin::Atan2,
+        MathBuiltin::Cbrt,
+        MathBuiltin::Ceil,
+        MathBuiltin::Cos,
+        MathBuiltin::Exp,
+        MathBuiltin::Floor,
+        MathBuiltin::Hypot,
+        MathBuiltin::Log,
+        MathBuiltin::Log10,
+        MathBuiltin::Log2,
+        MathBuiltin::Max,
+        MathBuiltin::Min,
+        MathBuiltin::Pow,
+        MathBuiltin::Random,
+        MathBuiltin::Round,
+        MathBuiltin::Sign,
+        MathBuiltin::Sin,
+        MathBuiltin::Sqrt,
+        MathBuiltin::Tan,
+        MathBuiltin::Trunc,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            MathBuiltin::Abs => "abs",
+            MathBuiltin::Acos => "acos",
+            MathBuiltin::Asin => "asin",
+            MathBuiltin::Atan => "atan",
+            MathBuiltin::Atan2 => "atan2",
+            MathBuiltin::Cbrt => "cbrt",
+            MathBuiltin::Ceil => "ceil",
+            MathBuiltin::Cos => "cos",
+            MathBuiltin::Exp => "exp",
+            MathBuiltin::Floor => "floor",
+            MathBuiltin::Hypot => "hypot",
+            MathBuiltin::Log => "log",
+            MathBuiltin::Log10 => "log10",
+            MathBuiltin::Log2 => "log2",
+            MathBuiltin::Max => "max",
+            MathBuiltin::Min => "min",
+            MathBuiltin::Pow => "pow",
+            MathBuiltin::Random => "random",
+            MathBuiltin::Round => "round",
+            MathBuiltin::Sign => "sign",
+            MathBuiltin::Sin => "sin",
+            MathBuiltin::Sqrt => "sqrt",
+            MathBuiltin::Tan => "tan",
+            MathBuiltin::Trunc => "trunc",
+        }
+    }
+
+    pub fn length(&self) -> usize {
+        match self {
+            MathBuiltin::Atan2
+            | MathBuiltin::Hypot
+            | MathBuiltin::Max
+            | MathBuiltin::Min
+            | MathBuiltin::Pow => 2,
+            MathBuiltin::Random => 0,
+            _ => 1,
+        }
+    }
+}
+
+/// Number.prototype.toFixed。正確な10進数の展開を丸め、ちょうど中間の場合は大きい方にする
+/// https://262.ecma-international.org/11.0/#sec-number.prototype.tofixed
+fn to_fixed(x: f64, fraction_digits: usize) -> String {
+    if x < 0.0 {
+        return format!("-{}", to_fixed(-x, fraction_digits));
+    }
+    // 最も小さい非正規化数でも、小数点以下1074桁で正確に表せる
+    // -0は符号をつけずに表す
+    let exact = format!("{:.*}", 1100, abs(x));
+    let (integer, fraction) = exact.split_once('.').unwrap_or((&exact, ""));
+    let mut digits: Vec<u8> = integer
+        .bytes()
+        .chain(fraction.bytes().take(fraction_digits))
+        .collect();
+    if fraction
+        .as_bytes()
+        .get(fraction_digits)
+        .is_some_and(|d| *d >= b'5')
+    {
+        let mut i = digits.len();
+        loop {
+            if i == 0 {
+                digits.insert(0, b'1');
+                break;
+            }
+            i -= 1;
+            if digits[i] == b'9' {
+                digits[i] = b'0';
+            } else {
+                digits[i] += 1;
+                break;
+            }
+        }
+    }
+
+    let (integer, fraction) = digits.split_at(digits.len() - fraction_digits);
+    let mut s = String::from_utf8_lossy(integer).to_string();
+    if fraction_digits > 0 {
+        s.push('.');
+        s.push_str(&String::from_utf8_lossy(fraction));
+    }
+    s
+}
+
+/// 10進数以外の基数で数値を文字列にする
+fn number_to_radix_string(x: f64, radix: u32) -> String {
+    if !x.is_finite() || x == 0.0 {
+        return number_to_string(x);
+    }
+    let digit = |d: f64| char::from_digit(d as u32, radix).unwrap_or('0');
+
+    let magnitude = abs(x);
+    let mut integer = floor(magnitude);
+    let mut fraction = magnitude - integer;
+    let mut digits = Vec::new();
+    loop {
+        digits.push(digit(integer % radix as f64));
+        integer = floor(integer / radix as f64);
+        if integer == 0.0 {
+            break;
+        }
+    }
+    if x < 0.0 {
+        digits.push('-');
+    }
+    digits.reverse();
+
+    // 2の累乗の基数では小数部は正確に表せる。それ以外は仮数の精度の分だけ出す
+    if fraction > 0.0 {
+        digits.push('.');
+        let max_digits = if radix.is_power_of_two() {
+            1100
+        } else {
+            (52.0 / math::log2(radix as f64)) as usize + 2
+        };
+        while fraction > 0.0 && digits.len() < max_digits {
+            fraction *= radix as f64;
+            let d = floor(fraction);
+            digits.push(digit(d));
+            fraction -= d;
+        }
+    }
+    digits.into_iter().collect()
+}
+
+/// Math.round。ちょうど中間の値は正の無限大の方向に丸める
+fn round(x: f64) -> f64 {
+    if !x.is_finite() || x == 0.0 {
+        return x;
+    }
+    if 0.0 < x && x < 0.5 {
+        return 0.0;
+    }
+    if (-0.5..0.0).contains(&x) {
+        return -0.0;
+    }
+    let f = floor(x);
+    if x - f >= 0.5 {
+        f + 1.0
+    } else {
+        f
+    }
+}
+
+/// Math.hypot。大きな値でもあふれないように、最大の値で割ってから計算する
+fn hypot(values: &[f64]) -> f64 {
+    if values.iter().any(|v| v.is_infinite()) {
+        return f64::INFINITY;
+    }
+    if values.iter().any(|v| v.is_nan()) {
+        return f64::NAN;
+    }
+    let max = values.iter().fold(0.0, |max, v| abs(*v).max(max));
+    if max == 0.0 {
+        return 0.0;
+    }
+    let sum = values.iter().map(|v| (v / max) * (v / max)).sum::<f64>();
+    max * math::sqrt(sum)
+}
+
+impl JsRuntime {
+    pub(super) fn install_number(&mut self) {
+        let prototype = self.intrinsics.number_prototype;
+        let constructor =
+            self.define_constructor(Builtin::Number(NumberBuiltin::Constructor), prototype);
+        for method in [
+            NumberBuiltin::IsFinite,
+            NumberBuiltin::IsInteger,
+            NumberBuiltin::IsNaN,
+            NumberBuiltin::IsSafeInteger,
+        ] {
+            self.define_builtin_method(constructor, Builtin::Number(method));
+        }
+        for method in [
+            NumberBuiltin::ToFixed,
+            NumberBuiltin::ToString,
+            NumberBuiltin::ValueOf,
+        ] {
+            self.define_builtin_method(prototype, Builtin::Number(method));
+        }
+
+        // Number.parseIntは、グローバルのparseIntと同じ関数
+        for name in ["parseFloat", "parseInt"] {
+            let function = RefCell::borrow(&self.env).get_variable(name.to_string());
+            if let Some(function) = function {
+                self.define_value(constructor, name, function);
+            }
+        }
+        for (name, value) in [
+            ("EPSILON", f64::EPSILON),
+            ("MAX_SAFE_INTEGER", MAX_SAFE_INTEGER),
+            ("MAX_VALUE", f64::MAX),
+            ("MIN_SAFE_INTEGER", -MAX_SAFE_INTEGER),
+            ("MIN_VALUE", 5e-324),
+            ("NaN", f64::NAN),
+            ("NEGATIVE_INFINITY", f64::NEG_INFINITY),
+            ("POSITIVE_INFINITY", f64::INFINITY),
+        ] {
+            self.define_constant(constructor, name, RuntimeValue::Number(value));
+        }
+    }
+
+    pub(super) fn install_math(&mut self) {
+        let methods = MathBuiltin::ALL.map(Builtin::Math);
+        let object = self.define_namespace("Math", &methods);
+        for (name, value) in [
+            ("E", consts::E),
+            ("LN10", consts::LN_10),
+            ("LN2", consts::LN_2),
+            ("LOG10E", consts::LOG10_E),
+            ("LOG2E", consts::LOG2_E),
+            ("PI", consts::PI),
+            ("SQRT1_2", consts::FRAC_1_SQRT_2),
+            ("SQRT2", consts::SQRT_2),
+        ] {
+            self.define_constant(object, name, RuntimeValue::Number(value));
+        }
+    }
+
+    pub(super) fn construct_number(
+        &mut self,
+        arguments: &[RuntimeValue],
+    ) -> Result<RuntimeValue, Completion> {
+        let n = match arguments.first() {
+            Some(value) => self.coerce_to_number(value.clone())?,
+            None => 0.0,
+        };
+        Ok(RuntimeValue::Object(
+            self.create_primitive_wrapper(RuntimeValue::Number(n)),
+        ))
+    }
+
+    /// Numberのメソッドのthisの値
+    fn this_number_value(&self, this: &RuntimeValue) -> Result<f64, Completion> {
+        match this {
+            RuntimeValue::Number(n) => return Ok(*n),
+            RuntimeValue::Object(id) => {
+                if let ObjectKind::Primitive(RuntimeValue::Number(n)) = self.heap.get(*id).kind() {
+                    return Ok(*n);
+                }
+            }
+            _ => {}
+        }
+        Err(Completion::error(
+            ErrorKind::TypeError,
+            "Number.prototype.valueOf requires that 'this' be a Number",
+        ))
+    }
+
+    pub(super) fn call_number(
+        &mut self,
+        function: NumberBuiltin,
+        this: RuntimeValue,
+        arguments: &[RuntimeValue],
+    ) -> Result<RuntimeValue, Completion> {
+        let first = argument(arguments, 0);
+        let number = match first {
+            RuntimeValue::Number(n) => Some(n),
+            _ => None,
+        };
+        let result = match function {
+            NumberBuiltin::Constructor => RuntimeValue::Number(match arguments.first() {
+                Some(value) => self.coerce_to_number(value.clone())?,
+                None => 0.0,
+            }),
+            NumberBuiltin::IsFinite => RuntimeValue::Boolean(number.is_some_and(f64::is_finite)),
+            NumberBuiltin::IsInteger => RuntimeValue::Boolean(number.is_some_and(is_integer)),
+            NumberBuiltin::IsNaN => RuntimeValue::Boolean(number.is_some_and(f64::is_nan)),
+            NumberBuiltin::IsSafeInteger => RuntimeValue::Boolean(
+                number.is_some_and(|n| is_integer(n) && abs(n) <= MAX_SAFE_INTEGER),
+            ),
+            NumberBuiltin::ToFixed => {
+                let x = self.this_number_value(&this)?;
+                let digits = self.coerce_to_integer(first)?;
+                if !(0.0..=100.0).contains(&digits) {
+                    return Err(Completion::error(
+                        ErrorKind::RangeError,
+                        "toFixed() digits argument must be between 0 and 100",
+                    ));
+                }
+                RuntimeValue::String(if !x.is_finite() || abs(x) >= 1e21 {
+                    number_to_string(x)
+                } else {
+                    to_fixed(x, digits as usize)
+                })
+            }
+            NumberBuiltin::ToString => {
+                let x = self.this_number_value(&this)?;
+                let radix = match first {
+                    RuntimeValue::Undefined => 10.0,
+                    v => self.coerce_to_integer(v)?,
+                };
+                if !(2.0..=36.0).contains(&radix) {
+                    return Err(Completion::error(
+                        ErrorKind::RangeError,
+                        "toString() radix must be between 2 and 36",
+                    ));
+                }
+                RuntimeValue::String(match radix as u32 {
+                    10 => number_to_string(x),
+                    radix => number_to_radix_string(x, radix),
+                })
+            }
+            NumberBuiltin::ValueOf => RuntimeValue::Number(self.this_number_value(&this)?),
+        };
+        Ok(result)
+    }
+
+    /// xorshift64*で0以上1未満の疑似乱数を作る
+    fn next_random(&mut self) -> f64 {
+        let mut x = self.random_state;
+        x ^= x >> 12;
+        x ^= x << 25;
+        x ^= x >> 27;
+        self.random_state = x;
+        (x.wrapping_mul(0x2545_f491_4f6c_dd1d) >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    pub(super) fn call_math(
+        &mut self,
+        function: MathBuiltin,
+        arguments: &[RuntimeValue],
+    ) -> Result<RuntimeValue, Completion> {
+        // 引数はすべて、計算の前に数値にする
+        let mut numbers = Vec::new();
+        for value in arguments {
+            numbers.push(self.coerce_to_number(value.clone())?);
+        }
+        let x = numbers.first().copied().unwrap_or(f64::NAN);
+        let y = numbers.get(1).copied().unwrap_or(f64::NAN);
+
+        let n = match function {
+            MathBuiltin::Abs => abs(x),
+            MathBuiltin::Acos => math::acos(x),
+            MathBuiltin::Asin => math::asin(x),
+            MathBuiltin::Atan => math::atan(x),
+            MathBuiltin::Atan2 => math::atan2(x, y),
+            MathBuiltin::Cbrt => math::cbrt(x),
+            MathBuiltin::Ceil => math::ceil(x),
+            MathBuiltin::Cos => math::cos(x),
+            MathBuiltin::Exp => math::exp(x),
+            MathBuiltin::Floor => floor(x),
+            MathBuiltin::Hypot => hypot(&numbers),
+            MathBuiltin::Log => math::ln(x),
+            MathBuiltin::Log10 => math::log10(x),
+            MathBuiltin::Log2 => math::log2(x),
+            // +0は-0より大きいものとして扱う
+            MathBuiltin::Max => numbers.iter().fold(f64::NEG_INFINITY, |max, &n| {
+                if max.is_nan() || n.is_nan() {
+                    f64::NAN
+                } else if n > max || (n == 0.0 && max == 0.0 && !n.is_sign_negative()) {
+                    n
+                } else {
+                    max
+                }
+            }),
+            MathBuiltin::Min => numbers.iter().fold(f64::INFINITY, |min, &n| {
+                if min.is_nan() || n.is_nan() {
+                    f64::NAN
+                } else if n < min || (n == 0.0 && min == 0.0 && n.is_sign_negative()) {
+                    n
+                } else {
+                    min
+                }
+            }),
+            MathBuiltin::Pow => math::pow(x, y),
+            MathBuiltin::Random => self.next_random(),
+            MathBuiltin::Round => round(x),
+            MathBuiltin::Sign => {
+                if x.is_nan() || x == 0.0 {
+                    x
+                } else if x > 0.0 {
+                    1.0
+                } else {
+                    -1.0
+                }
+            }
+            MathBuiltin::Sin => math::sin(x),
+            MathBuiltin::Sqrt => math::sqrt(x),
+            MathBuiltin::Tan => math::tan(x),
+            MathBuiltin::Trunc => math::trunc(x),
+        };
+        Ok(RuntimeValue::Number(n))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::renderer::js::runtime::tests::eval_last;
+    use crate::renderer::js::runtime::tests::number;
+    use crate::renderer::js::runtime::tests::string;
+    use crate::renderer::js::runtime::tests::throws;
+
+    #[test]
+    fn test_to_fixed() {
+        assert_eq!("1", to_fixed(0.5, 0));
+        assert_eq!("3", to_fixed(2.5, 0));
+        // 1.005は1.00499999999999989...なので切り捨てる
+        assert_eq!("1.00", to_fixed(1.005, 2));
+        assert_eq!("10.00", to_fixed(9.999, 2));
+        assert_eq!("-0.00", to_fixed(-0.0000001, 2));
+        assert_eq!("0.000", to_fixed(0.0, 3));
+    }
+
+    #[test]
+    fn test_radix_and_round() {
+        assert_eq!("ff", number_to_radix_string(255.0, 16));
+        assert_eq!("-101.1", number_to_radix_string(-5.5, 2));
+        assert_eq!("z", number_to_radix_string(35.0, 36));
+        assert_eq!(3.0, round(2.5));
+        assert_eq!(-2.0, round(-2.5));
+        assert_eq!(0.0, round(0.49999999999999994));
+        assert!(round(-0.2).is_sign_negative());
+    }
+
+    #[test]
+    fn test_number_coercion() {
+        // 前後の空白は無視し、数値として読めない文字列はNaNになる
+        assert_eq!(
+            string("12,31,1000,NaN,0,NaN,5,7"),
+            eval_last(
+                "[Number('  12  '), Number('0x1f'), Number('1e3'), Number('12px'), Number(null),
+                  Number(undefined), Number([5]), Number({ valueOf() { return '7'; } })].join()"
+            )
+        );
+        assert_eq!(
+            string("false,false,false"),
+            eval_last("[Number.isInteger('5'), Number.isNaN('abc'), Number.isSafeInteger(2 ** 53)].join()")
+        );
+        assert_eq!(
+            string("ff,0.0,1.50"),
+            eval_last("[(255).toString(16), (-0).toFixed(1), (1.5).toFixed('2')].join()")
+        );
+        assert_eq!(
+            string("-Infinity,Infinity,NaN,3"),
+            eval_last("[Math.max(), Math.min(), Math.max(1, NaN), Math.max('3', 2)].join()")
+        );
+        assert_eq!(
+            string("5,1,-4,-1"),
+            eval_last(
+                "[Math.hypot(3, 4), Math.pow(NaN, 0), Math.trunc(-4.7), Math.sign(-3)].join()"
+            )
+        );
+        // -0.5を丸めると-0になる
+        assert_eq!(number(f64::NEG_INFINITY), eval_last("1 / Math.round(-0.5)"));
+    }
+
+    #[test]
+    fn test_number_errors() {
+        assert!(throws(eval_last("(1).toFixed(101)"), ErrorKind::RangeError));
+        assert!(throws(
+            eval_last("(255).toString(1)"),
+            ErrorKind::RangeError
+        ));
+        assert!(throws(
+            eval_last("(255).toString(37)"),
+            ErrorKind::RangeError
+        ));
+        // thisが数値でなければ変換しない
+        assert!(throws(
+            eval_last("Number.prototype.toFixed.call('1')"),
+            ErrorKind::TypeError
+        ));
+        assert!(throws(
+            eval_last("Number.prototype.valueOf.call({})"),
+            ErrorKind::TypeError
+        ));
+    }
+}
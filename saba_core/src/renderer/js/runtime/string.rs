@@ -0,0 +1,633 @@
+//! Stringのコンストラクタとメソッド
+//! 位置と長さは、JSと同じようにUTF-16のコード単位で数える
+//! https://262.ecma-international.org/11.0/#sec-string-objects
+
+use crate::renderer::js::math::is_integer;
+use crate::renderer::js::object::ObjectKind;
+use crate::renderer::js::object::PropertyDescriptor;
+use crate::renderer::js::object::PropertyKey;
+use crate::renderer::js::runtime::builtins::argument;
+use crate::renderer::js::runtime::builtins::trim_start;
+use crate::renderer::js::runtime::builtins::Builtin;
+use crate::renderer::js::runtime::Completion;
+use crate::renderer::js::runtime::ErrorKind;
+use crate::renderer::js::runtime::JsRuntime;
+use crate::renderer::js::token::is_line_terminator;
+use crate::renderer::js::token::is_whitespace;
+use crate::renderer::js::value::number_to_string;
+use crate::renderer::js::value::to_uint32;
+use crate::renderer::js::value::RuntimeValue;
+use alloc::format;
+use alloc::string::String;
+use alloc::string::ToString;
+use alloc::vec::Vec;
+
+/// repeatとpadで作れる文字列の長さの上限
+const MAX_STRING_LENGTH: usize = 1 << 28;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StringBuiltin {
+    Constructor,
+    FromCharCode,
+    FromCodePoint,
+    CharAt,
+    CharCodeAt,
+    CodePointAt,
+    Concat,
+    EndsWith,
+    Includes,
+    IndexOf,
+    LastIndexOf,
+    LocaleCompare,
+    PadEnd,
+    PadStart,
+    Repeat,
+    Replace,
+    ReplaceAll,
+    Slice,
+    Split,
+    StartsWith,
+    Substr,
+    Substring,
+    ToLowerCase,
+    ToString,
+    ToUpperCase,
+    Trim,
+    TrimEnd,
+    TrimStart,
+    ValueOf,
+}
+
+impl StringBuiltin {
+    const PROTOTYPE_METHODS: [StringBuiltin; 26] = [
+        StringBuiltin::CharAt,
+        StringBuiltin::CharCodeAt,
+        StringBuiltin::CodePointAt,
+        StringBuiltin::Concat,
+        StringBuiltin::EndsWith,
+        StringBuiltin::Includes,
+        StringBuiltin::IndexOf,
+        StringBuiltin::LastIndexOf,
+        StringBuiltin::LocaleCompare,
+        StringBuiltin::PadEnd,
+        StringBuiltin::PadStart,
+        StringBuiltin::Repeat,
+        StringBuiltin::Replace,
+        StringBuiltin::ReplaceAll,
+        StringBuiltin::Slice,
+        StringBuiltin::Split,
+        StringBuiltin::StartsWith,
+        StringBuiltin::Substr,
+        StringBuiltin::Substring,
+        StringBuiltin::ToLowerCase,
+        StringBuiltin::ToString,
+        StringBuiltin::ToUpperCase,
+        StringBuiltin::Trim,
+        StringBuiltin::TrimEnd,
+        StringBuiltin::TrimStart,
+        StringBuiltin::ValueOf,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            StringBuiltin::Constructor => "String",
+            StringBuiltin::FromCharCode => "fromCharCode",
+            StringBuiltin::FromCodePoint => "fromCodePoint",
+            StringBuiltin::CharAt => "charAt",
+            StringBuiltin::CharCodeAt => "charCodeAt",
+            StringBuiltin::CodePointAt => "codePointAt",
+            StringBuiltin::Concat => "concat",
+            StringBuiltin::EndsWith => "endsWith",
+            StringBuiltin::Includes => "includes",
+            StringBuiltin::IndexOf => "indexOf",
+            StringBuiltin::LastIndexOf => "lastIndexOf",
+            StringBuiltin::LocaleCompare => "localeCompare",
+            StringBuiltin::PadEnd => "padEnd",
+            StringBuiltin::PadStart => "padStart",
+            StringBuiltin::Repeat => "repeat",
+            StringBuiltin::Replace => "replace",
+            StringBuiltin::ReplaceAll => "replaceAll",
+            StringBuiltin::Slice => "slice",
+            StringBuiltin::Split => "split",
+            StringBuiltin::StartsWith => "startsWith",
+            StringBuiltin::Substr => "substr",
+            StringBuiltin::Substring => "substring",
+            StringBuiltin::ToLowerCase => "toLowerCase",
+            StringBuiltin::ToString => "toString",
+            StringBuiltin::ToUpperCase => "toUpperCase",
+            StringBuiltin::Trim => "trim",
+            StringBuiltin::TrimEnd => "trimEnd",
+            StringBuiltin::TrimStart => "trimStart",
+            StringBuiltin::ValueOf => "valueOf",
+        }
+    }
+
+    pub fn length(&self) -> usize {
+        match self {
+            StringBuiltin::Replace
+            | StringBuiltin::ReplaceAll
+            | StringBuiltin::Slice
+            | StringBuiltin::Split
+            | StringBuiltin::Substr
+            | StringBuiltin::Substring => 2,
+            StringBuiltin::ToLowerCase
+            | StringBuiltin::ToString
+            | StringBuiltin::ToUpperCase
+            | StringBuiltin::Trim
+            | StringBuiltin::TrimEnd
+            | StringBuiltin::TrimStart
+            | StringBuiltin::ValueOf => 0,
+            _ => 1,
+        }
+    }
+}
+
+/// `from`以降で最初に`needle`が現れる位置
+fn find_units(haystack: &[u16], needle: &[u16], from: usize) -> Option<usize> {
+    if from + needle.len() > haystack.len() {
+        return None;
+    }
+    (from..=haystack.len() - needle.len()).find(|&i| haystack[i..].starts_with(needle))
+}
+
+fn is_trimmed(c: char) -> bool {
+    is_whitespace(c) || is_line_terminator(c)
+}
+
+/// GetSubstitution。置換後の文字列の`$&`などを、一致した部分や前後の文字列にする
+/// https://262.ecma-international.org/11.0/#sec-getsubstitution
+fn substitute(matched: &[u16], s: &[u16], position: usize, replacement: &str) -> Vec<u16> {
+    let mut result = Vec::new();
+    let mut chars = replacement.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c != '$' {
+            let mut buffer = [0; 2];
+            result.extend_from_slice(c.encode_utf16(&mut buffer));
+            continue;
+        }
+        match chars.peek() {
+            Some('$') => result.push(b'$' as u16),
+            Some('&') => result.extend_from_slice(matched),
+            Some('`') => result.extend_from_slice(&s[..position]),
+            Some('\'') => result.extend_from_slice(&s[(position + matched.len()).min(s.len())..]),
+            _ => {
+                result.push(b'$' as u16);
+                continue;
+            }
+        }
+        chars.next();
+    }
+    result
+}
+
+impl JsRuntime {
+    pub(super) fn install_string(&mut self) {
+        let prototype = self.intrinsics.string_prototype;
+        // String.prototypeは空文字列を包んだオブジェクト
+        self.heap.get_mut(prototype).define_own_property(
+            PropertyKey::from("length"),
+            PropertyDescriptor::data(RuntimeValue::Number(0.0), false, false, false),
+        );
+        let constructor =
+            self.define_constructor(Builtin::String(StringBuiltin::Constructor), prototype);
+        for method in [StringBuiltin::FromCharCode, StringBuiltin::FromCodePoint] {
+            self.define_builtin_method(constructor, Builtin::String(method));
+        }
+        for method in StringBuiltin::PROTOTYPE_METHODS {
+            self.define_builtin_method(prototype, Builtin::String(method));
+        }
+    }
+
+    pub(super) fn construct_string(
+        &mut self,
+        arguments: &[RuntimeValue],
+    ) -> Result<RuntimeValue, Completion> {
+        let s = match arguments.first() {
+            Some(value) => self.coerce_to_string(value.clone())?,
+            None => String::new(),
+        };
+        Ok(RuntimeValue::Object(
+            self.create_primitive_wrapper(RuntimeValue::String(s)),
+        ))
+    }
+
+    /// toStringとvalueOfのthisの値。文字列か、文字列を包んだオブジェクトだけを受け付ける
+    fn this_string_value(&self, this: &RuntimeValue) -> Result<String, Completion> {
+        match this {
+            RuntimeValue::String(s) => return Ok(s.clone()),
+            RuntimeValue::Object(id) => {
+                if let ObjectKind::Primitive(RuntimeValue::String(s)) = self.heap.get(*id).kind() {
+                    return Ok(s.clone());
+                }
+            }
+            _ => {}
+        }
+        Err(Completion::error(
+            ErrorKind::TypeError,
+            "String.prototype.valueOf requires that 'this' be a String",
+        ))
+    }
+
+    /// 位置を0から`length`の間に収める
+    fn clamp_position(&mut self, value: RuntimeValue, length: usize) -> Result<usize, Completion> {
+        let n = self.coerce_to_integer(value)?;
+        Ok(n.max(0.0).min(length as f64) as usize)
+    }
+
+    pub(super) fn call_string(
+        &mut self,
+        function: StringBuiltin,
+        this: RuntimeValue,
+        arguments: &[RuntimeValue],
+    ) -> Result<RuntimeValue, Completion> {
+        let first = argument(arguments, 0);
+        match function {
+            // String(symbol)だけは、例外を投げずにシンボルの説明を返す
+            StringBuiltin::Constructor => {
+                return Ok(RuntimeValue::String(match arguments.first() {
+                    None => String::new(),
+                    Some(symbol @ RuntimeValue::Symbol(_)) => symbol.to_string(),
+                    Some(value) => self.coerce_to_string(value.clone())?,
+                }))
+            }
+            StringBuiltin::FromCharCode => {
+                let mut units = Vec::new();
+                for value in arguments {
+                    units.push(to_uint32(self.coerce_to_number(value.clone())?) as u16);
+                }
+                return Ok(RuntimeValue::String(String::from_utf16_lossy(&units)));
+            }
+            StringBuiltin::FromCodePoint => {
+                let mut s = String::new();
+                for value in arguments {
+                    let n = self.coerce_to_number(value.clone())?;
+                    if !is_integer(n) || !(0.0..=1114111.0).contains(&n) {
+                        return Err(Completion::error(
+                            ErrorKind::RangeError,
+                            &format!("Invalid code point {}", number_to_string(n)),
+                        ));
+                    }
+                    s.push(char::from_u32(n as u32).unwrap_or(char::REPLACEMENT_CHARACTER));
+                }
+                return Ok(RuntimeValue::String(s));
+            }
+            StringBuiltin::ToString | StringBuiltin::ValueOf => {
+                return Ok(RuntimeValue::String(self.this_string_value(&this)?))
+            }
+            _ => {}
+        }
+
+        if this.is_nullish() {
+            return Err(Completion::error(
+                ErrorKind::TypeError,
+                &format!(
+                    "String.prototype.{} called on null or undefined",
+                    function.name()
+                ),
+            ));
+        }
+        let s = self.coerce_to_string(this)?;
+        let units: Vec<u16> = s.encode_utf16().collect();
+        let length = units.len();
+        let substring = |start: usize, end: usize| {
+            RuntimeValue::String(String::from_utf16_lossy(&units[start..end.max(start)]))
+        };
+
+        let result = match function {
+            StringBuiltin::CharAt | StringBuiltin::CharCodeAt | StringBuiltin::CodePointAt => {
+                let position = self.coerce_to_integer(first)?;
+                if position < 0.0 || position >= length as f64 {
+                    return Ok(match function {
+                        StringBuiltin::CharAt => RuntimeValue::String(String::new()),
+                        StringBuiltin::CharCodeAt => RuntimeValue::Number(f64::NAN),
+                        _ => RuntimeValue::Undefined,
+                    });
+                }
+                let i = position as usize;
+                match function {
+                    StringBuiltin::CharAt => substring(i, i + 1),
+                    StringBuiltin::CharCodeAt => RuntimeValue::Number(units[i] as f64),
+                    _ => {
+                        // サロゲートペアは1つのコードポイントにする
+                        let code_point = char::decode_utf16(units[i..].iter().copied())
+                            .next()
+                            .and_then(|c| c.ok())
+                            .map(|c| c as u32)
+                            .unwrap_or(units[i] as u32);
+                        RuntimeValue::Number(code_point as f64)
+                    }
+                }
+            }
+            StringBuiltin::Concat => {
+                let mut result = s;
+                for value in arguments {
+                    result.push_str(&self.coerce_to_string(value.clone())?);
+                }
+                RuntimeValue::String(result)
+            }
+            StringBuiltin::EndsWith | StringBuiltin::Includes | StringBuiltin::StartsWith => {
+                let search: Vec<u16> = self.coerce_to_string(first)?.encode_utf16().collect();
+                let position = argument(arguments, 1);
+                let found = match function {
+                    StringBuiltin::EndsWith => {
+                        let end = match position {
+                            RuntimeValue::Undefined => length,
+                            v => self.clamp_position(v, length)?,
+                        };
+                        end >= search.len() && units[end - search.len()..end] == search[..]
+                    }
+                    StringBuiltin::Includes => {
+                        let start = self.clamp_position(position, length)?;
+                        find_units(&units, &search, start).is_some()
+                    }
+                    _ => {
+                        let start = self.clamp_position(position, length)?;
+                        units[start..].starts_with(&search)
+                    }
+                };
+                RuntimeValue::Boolean(found)
+            }
+            StringBuiltin::IndexOf => {
+                let search: Vec<u16> = self.coerce_to_string(first)?.encode_utf16().collect();
+                let start = self.clamp_position(argument(arguments, 1), length)?;
+                RuntimeValue::Number(match find_units(&units, &search, start) {
+                    Some(i) => i as f64,
+                    None => -1.0,
+                })
+            }
+            StringBuiltin::LastIndexOf => {
+                let search: Vec<u16> = self.coerce_to_string(first)?.encode_utf16().collect();
+                let n = self.coerce_to_number(argument(arguments, 1))?;
+                let position = if n.is_nan() {
+                    length
+                } else {
+                    self.clamp_position(RuntimeValue::Number(n), length)?
+                };
+                let found = match length.checked_sub(search.len()) {
+                    Some(last) => (0..=position.min(last))
+                        .rev()
+                        .find(|&i| units[i..].starts_with(&search)),
+                    None => None,
+                };
+                RuntimeValue::Number(found.map_or(-1.0, |i| i as f64))
+            }
+            StringBuiltin::LocaleCompare => {
+                let that = self.coerce_to_string(first)?;
+                RuntimeValue::Number(match s.encode_utf16().cmp(that.encode_utf16()) {
+                    core::cmp::Ordering::Less => -1.0,
+                    core::cmp::Ordering::Equal => 0.0,
+                    core::cmp::Ordering::Greater => 1.0,
+                })
+            }
+            StringBuiltin::PadEnd | StringBuiltin::PadStart => {
+                let max_length = self.coerce_to_integer(first)?;
+                let fill = match argument(arguments, 1) {
+                    RuntimeValue::Undefined => " ".to_string(),
+                    v => self.coerce_to_string(v)?,
+                };
+                if max_length <= length as f64 || fill.is_empty() {
+                    return Ok(RuntimeValue::String(s));
+                }
+                if max_length > MAX_STRING_LENGTH as f64 {
+                    return Err(Completion::error(
+                        ErrorKind::RangeError,
+                        "Invalid string length",
+                    ));
+                }
+                let padding: Vec<u16> = fill
+                    .encode_utf16()
+                    .cycle()
+                    .take(max_length as usize - length)
+                    .collect();
+                let padding = String::from_utf16_lossy(&padding);
+                RuntimeValue::String(match function {
+                    StringBuiltin::PadEnd => s + &padding,
+                    _ => padding + &s,
+                })
+            }
+            StringBuiltin::Repeat => {
+                let count = self.coerce_to_integer(first)?;
+                if count < 0.0 || count.is_infinite() {
+                    return Err(Completion::error(
+                        ErrorKind::RangeError,
+                        &format!("Invalid count value: {}", number_to_string(count)),
+                    ));
+                }
+                if length as f64 * count > MAX_STRING_LENGTH as f64 {
+                    return Err(Completion::error(
+                        ErrorKind::RangeError,
+                        "Invalid string length",
+                    ));
+                }
+                RuntimeValue::String(s.repeat(count as usize))
+            }
+            StringBuiltin::Replace | StringBuiltin::ReplaceAll => {
+                let all = function == StringBuiltin::ReplaceAll;
+                return self.replace_string(&s, &units, arguments, all);
+            }
+            StringBuiltin::Slice => {
+                let start = self.relative_index(first, length, 0)?;
+                let end = self.relative_index(argument(arguments, 1), length, length)?;
+                substring(start, end)
+            }
+            StringBuiltin::Split => {
+                let limit = match argument(arguments, 1) {
+                    RuntimeValue::Undefined => u32::MAX,
+                    v => to_uint32(self.coerce_to_number(v)?),
+                } as usize;
+                let separator = match first {
+                    RuntimeValue::Undefined => None,
+                    v => Some(
+                        self.coerce_to_string(v)?
+                            .encode_utf16()
+                            .collect::<Vec<u16>>(),
+                    ),
+                };
+                let mut parts = Vec::new();
+                match separator {
+                    _ if limit == 0 => {}
+                    None => parts.push(RuntimeValue::String(s)),
+                    // 空文字列は、区切り文字が空でない場合だけ1つの要素になる
+                    Some(separator) if length == 0 => {
+                        if !separator.is_empty() {
+                            parts.push(RuntimeValue::String(s));
+                        }
+                    }
+                    Some(separator) if separator.is_empty() => {
+                        for i in 0..length.min(limit) {
+                            parts.push(substring(i, i + 1));
+                        }
+                    }
+                    Some(separator) => {
+                        let mut start = 0;
+                        while let Some(i) = find_units(&units, &separator, start) {
+                            parts.push(substring(start, i));
+                            if parts.len() == limit {
+                                break;
+                            }
+                            start = i + separator.len();
+                        }
+                        if parts.len() < limit {
+                            parts.push(substring(start, length));
+                        }
+                    }
+                }
+                RuntimeValue::Object(self.create_array(parts)?)
+            }
+            StringBuiltin::Substr => {
+                let start = self.relative_index(first, length, 0)?;
+                let count = match argument(arguments, 1) {
+                    RuntimeValue::Undefined => length as f64,
+                    v => self.coerce_to_integer(v)?,
+                };
+                let end = (start as f64 + count.max(0.0)).min(length as f64) as usize;
+                substring(start, end)
+            }
+            StringBuiltin::Substring => {
+                let start = self.clamp_position(first, length)?;
+                let end = match argument(arguments, 1) {
+                    RuntimeValue::Undefined => length,
+                    v => self.clamp_position(v, length)?,
+                };
+                substring(start.min(end), start.max(end))
+            }
+            StringBuiltin::ToLowerCase => RuntimeValue::String(s.to_lowercase()),
+            StringBuiltin::ToUpperCase => RuntimeValue::String(s.to_uppercase()),
+            StringBuiltin::Trim => {
+                RuntimeValue::String(trim_start(&s).trim_end_matches(is_trimmed).to_string())
+            }
+            StringBuiltin::TrimEnd => {
+                RuntimeValue::String(s.trim_end_matches(is_trimmed).to_string())
+            }
+            StringBuiltin::TrimStart => RuntimeValue::String(trim_start(&s).to_string()),
+            _ => RuntimeValue::Undefined,
+        };
+        Ok(result)
+    }
+
+    /// 文字列を検索して置き換える。正規表現はまだない
+    fn replace_string(
+        &mut self,
+        s: &str,
+        units: &[u16],
+        arguments: &[RuntimeValue],
+        all: bool,
+    ) -> Result<RuntimeValue, Completion> {
+        let search = self.coerce_to_string(argument(arguments, 0))?;
+        let search_units: Vec<u16> = search.encode_utf16().collect();
+        let replace_value = argument(arguments, 1);
+        let replacement = match self.is_callable(&replace_value) {
+            true => None,
+            false => Some(self.coerce_to_string(replace_value.clone())?),
+        };
+
+        // 置き換える前に、一致する位置をすべて見つけておく
+        let mut positions = Vec::new();
+        let mut position = find_units(units, &search_units, 0);
+        while let Some(p) = position {
+            positions.push(p);
+            if !all {
+                break;
+            }
+            position = find_units(units, &search_units, p + search_units.len().max(1));
+        }
+
+        let mut result = Vec::new();
+        let mut end = 0;
+        for p in positions {
+            result.extend_from_slice(&units[end..p]);
+            match &replacement {
+                Some(replacement) => {
+                    result.extend(substitute(&search_units, units, p, replacement));
+                }
+                None => {
+                    let value = self.call(
+                        &replace_value,
+                        RuntimeValue::Undefined,
+                        &[
+                            RuntimeValue::String(search.clone()),
+                            RuntimeValue::Number(p as f64),
+                            RuntimeValue::String(s.to_string()),
+                        ],
+                    )?;
+                    result.extend(self.coerce_to_string(value)?.encode_utf16());
+                }
+            }
+            end = p + search_units.len();
+        }
+        result.extend_from_slice(&units[end..]);
+        Ok(RuntimeValue::String(String::from_utf16_lossy(&result)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::renderer::js::runtime::tests::boolean;
+    use crate::renderer::js::runtime::tests::eval_last;
+    use crate::renderer::js::runtime::tests::string;
+    use crate::renderer::js::runtime::tests::throws;
+
+    #[test]
+    fn test_string_coercion() {
+        // 位置は整数に切り捨て、範囲の外は空の文字列かNaNになる
+        assert_eq!(
+            string(",b,NaN"),
+            eval_last("['abc'.charAt(-1), 'abc'.charAt(1.7), 'abc'.charCodeAt(5)].join()")
+        );
+        assert_eq!(
+            string("bc,ab,b"),
+            eval_last("['abc'.slice(-2), 'abc'.substring(2, 0), 'abc'.substr(-2, 1)].join()")
+        );
+        assert_eq!(string("abab"), eval_last("'ab'.repeat('2')"));
+        assert_eq!(
+            string("abc,abc121"),
+            eval_last("['abc'.padStart(5, ''), 'abc'.padEnd(6, 12)].join()")
+        );
+        // thisが文字列でなければ文字列に変換する
+        assert_eq!(
+            string("TRUE"),
+            eval_last("String.prototype.toUpperCase.call(true)")
+        );
+        assert_eq!(
+            string("a,b|3|1"),
+            eval_last("'a-b-c'.split('-', 2).join() + '|' + 'abc'.split('').length + '|' + 'abc'.split().length")
+        );
+        assert_eq!(string("aaaa"), eval_last("'aaa'.replace('a', '$&$&')"));
+        assert_eq!(string("a1c"), eval_last("'abc'.replace('b', (m, i) => i)"));
+        assert_eq!(string("AB"), eval_last("String.fromCharCode(65.9, '66')"));
+        assert_eq!(
+            boolean(true),
+            eval_last("'abc'.startsWith('b', 1) && 'abc'.endsWith('b', 2)")
+        );
+        assert_eq!(
+            string("12null"),
+            eval_last("String.prototype.concat.call(1, 2, null)")
+        );
+    }
+
+    #[test]
+    fn test_string_errors() {
+        assert!(throws(eval_last("'ab'.repeat(-1)"), ErrorKind::RangeError));
+        assert!(throws(
+            eval_last("'ab'.repeat(Infinity)"),
+            ErrorKind::RangeError
+        ));
+        assert!(throws(
+            eval_last("String.fromCodePoint(-1)"),
+            ErrorKind::RangeError
+        ));
+        assert!(throws(
+            eval_last("String.fromCodePoint(1.5)"),
+            ErrorKind::RangeError
+        ));
+        assert!(throws(
+            eval_last("String.prototype.trim.call(null)"),
+            ErrorKind::TypeError
+        ));
+        assert!(throws(
+            eval_last("String.prototype.valueOf.call({})"),
+            ErrorKind::TypeError
+        ));
+    }
+}
@@ -0,0 +1,806 @@
+//! バイトコードを実行するスタックマシン
+//! 関数の呼び出しごとにフレームを作り、命令が積んだ値は全てのフレームで共有するスタックで受け渡す
+
+use crate::renderer::js::bytecode::property_function_prefix;
+use crate::renderer::js::bytecode::BinaryOperator;
+use crate::renderer::js::bytecode::Code;
+use crate::renderer::js::bytecode::Op;
+use crate::renderer::js::environment::BindingError;
+use crate::renderer::js::environment::Environment;
+use crate::renderer::js::heap::ObjectId;
+use crate::renderer::js::heap::Trace;
+use crate::renderer::js::heap::Tracer;
+use crate::renderer::js::object::JsObject;
+use crate::renderer::js::object::ObjectKind;
+use crate::renderer::js::object::PropertyDescriptor;
+use crate::renderer::js::object::PropertyKey;
+use crate::renderer::js::runtime::Completion;
+use crate::renderer::js::runtime::ErrorKind;
+use crate::renderer::js::runtime::JsRuntime;
+use crate::renderer::js::runtime::Reference;
+use crate::renderer::js::value::to_int32;
+use crate::renderer::js::value::RuntimeValue;
+use alloc::format;
+use alloc::rc::Rc;
+use alloc::string::String;
+use alloc::string::ToString;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::cell::RefCell;
+
+/// try文の例外を捕まえる位置と、そこに戻るためのフレームの状態
+#[derive(Debug)]
+struct Handler {
+    target: usize,
+    completion: usize,
+    stack: usize,
+    iterators: usize,
+    env: Rc<RefCell<Environment>>,
+}
+
+/// 実行中の全てのフレームが共有する値の置き場所
+/// 呼び出し中の関数の値もGCのルートとして辿れるように、ランタイムに持たせる
+#[derive(Debug, Clone, Default)]
+pub(super) struct VmState {
+    /// 命令が積んだ値
+    stack: Vec<RuntimeValue>,
+    registers: Vec<Option<RuntimeValue>>,
+    /// 捕まえた例外。finallyの後で投げ直す
+    completions: Vec<Option<Completion>>,
+    /// for-inとfor-ofで取り出す値と、次の位置
+    iterators: Vec<(Vec<RuntimeValue>, usize)>,
+}
+
+impl VmState {
+    fn pop(&mut self) -> RuntimeValue {
+        self.stack.pop().expect("stack underflow")
+    }
+
+    fn peek(&self) -> &RuntimeValue {
+        self.stack.last().expect("stack underflow")
+    }
+
+    /// 積まれた数
+    fn pop_count(&mut self) -> usize {
+        match self.pop() {
+            RuntimeValue::Number(n) => n as usize,
+            _ => 0,
+        }
+    }
+
+    fn pop_key(&mut self) -> PropertyKey {
+        match self.pop() {
+            RuntimeValue::Symbol(symbol) => PropertyKey::Symbol(symbol),
+            RuntimeValue::String(s) => PropertyKey::String(s),
+            v => PropertyKey::String(v.to_string()),
+        }
+    }
+
+    fn peek_object(&self) -> ObjectId {
+        match self.peek() {
+            RuntimeValue::Object(id) => *id,
+            v => unreachable!("{} is not an object", v),
+        }
+    }
+}
+
+impl Trace for VmState {
+    fn trace(&self, tracer: &mut Tracer) {
+        for value in &self.stack {
+            tracer.mark_value(value);
+        }
+        for value in self.registers.iter().flatten() {
+            tracer.mark_value(value);
+        }
+        for completion in self.completions.iter().flatten() {
+            if let Completion::Throw(value) | Completion::Return(value) = completion {
+                tracer.mark_value(value);
+            }
+        }
+        for (values, _) in &self.iterators {
+            for value in values {
+                tracer.mark_value(value);
+            }
+        }
+    }
+}
+
+/// 実行中の関数やスクリプトの状態。値は`VmState`のこのフレームの位置から後ろに置く
+#[derive(Debug)]
+struct Frame<'a> {
+    code: &'a Code,
+    pc: usize,
+    env: Rc<RefCell<Environment>>,
+    arguments: &'a [RuntimeValue],
+    stack: usize,
+    registers: usize,
+    completions: usize,
+    iterators: usize,
+    handlers: Vec<Handler>,
+    /// フレームを始めたときにpinしていた数。GCを実行するときは、途中の値はスタックにある
+    pinned: usize,
+    /// `JsRuntime::scopes`の中の、このフレームのスコープの位置
+    scope: usize,
+}
+
+impl<'a> Frame<'a> {
+    fn name(&self, index: u32) -> &'a str {
+        &self.code.names[index as usize]
+    }
+}
+
+/// 数値どうしの二項演算。結果が数値の演算の型変換を省く
+fn number_operation(operator: BinaryOperator, l: f64, r: f64) -> Option<RuntimeValue> {
+    let value = match operator {
+        BinaryOperator::Add => RuntimeValue::Number(l + r),
+        BinaryOperator::Sub => RuntimeValue::Number(l - r),
+        BinaryOperator::Mul => RuntimeValue::Number(l * r),
+        BinaryOperator::Div => RuntimeValue::Number(l / r),
+        BinaryOperator::Rem => RuntimeValue::Number(l % r),
+        BinaryOperator::LessThan => RuntimeValue::Boolean(l < r),
+        BinaryOperator::GreaterThan => RuntimeValue::Boolean(l > r),
+        BinaryOperator::LessThanOrEqual => RuntimeValue::Boolean(l <= r),
+        BinaryOperator::GreaterThanOrEqual => RuntimeValue::Boolean(l >= r),
+        BinaryOperator::StrictEquals => RuntimeValue::Boolean(l == r),
+        BinaryOperator::StrictNotEquals => RuntimeValue::Boolean(l != r),
+        BinaryOperator::Other(_) => return None,
+    };
+    Some(value)
+}
+
+impl JsRuntime {
+    /// バイトコードを実行する。関数は`Return`、スクリプトは`ReturnRegister`の値を返す
+    pub(super) fn run_code(
+        &mut self,
+        code: &Code,
+        env: Rc<RefCell<Environment>>,
+        arguments: &[RuntimeValue],
+    ) -> Result<Option<RuntimeValue>, Completion> {
+        self.scopes.push(env.clone());
+        let mut frame = Frame {
+            code,
+            pc: 0,
+            env,
+            arguments,
+            stack: self.vm.stack.len(),
+            registers: self.vm.registers.len(),
+            completions: self.vm.completions.len(),
+            iterators: self.vm.iterators.len(),
+            handlers: Vec::new(),
+            pinned: self.heap.pinned(),
+            scope: self.scopes.len() - 1,
+        };
+        let registers = frame.registers + code.registers;
+        self.vm.registers.resize(registers, None);
+        let completions = frame.completions + code.completions;
+        self.vm.completions.resize(completions, None);
+
+        let result = loop {
+            let completion = match self.run_frame(&mut frame) {
+                Ok(result) => break Ok(result),
+                Err(completion) => completion,
+            };
+            // 例外はtry文で捕まえる。それ以外は呼び出し元に返す
+            let handler = match frame.handlers.pop() {
+                Some(handler) if completion.is_throw() => handler,
+                _ => break Err(completion),
+            };
+            self.vm.stack.truncate(handler.stack);
+            self.vm.iterators.truncate(handler.iterators);
+            self.set_frame_env(&mut frame, handler.env);
+            self.vm.completions[frame.completions + handler.completion] = Some(completion);
+            frame.pc = handler.target;
+        };
+
+        // フレームの値を片付ける
+        self.vm.stack.truncate(frame.stack);
+        self.vm.registers.truncate(frame.registers);
+        self.vm.completions.truncate(frame.completions);
+        self.vm.iterators.truncate(frame.iterators);
+        self.scopes.truncate(frame.scope);
+        self.heap.unpin(frame.pinned);
+        if let Ok(Some(value)) = &result {
+            self.heap.pin(value);
+        }
+        result
+    }
+
+    /// フレームのスコープを変える。GCのルートになるスコープも変える
+    fn set_frame_env(&mut self, frame: &mut Frame, env: Rc<RefCell<Environment>>) {
+        self.scopes[frame.scope] = env.clone();
+        frame.env = env;
+    }
+
+    /// スタックから値を取り出す。命令が使い終わるまで、取り出した値は回収しない
+    fn pop(&mut self) -> RuntimeValue {
+        let value = self.vm.pop();
+        self.heap.pin(&value);
+        value
+    }
+
+    /// 命令を順に実行する。例外が投げられたら、フレームをその状態のままにして返す
+    fn run_frame(&mut self, frame: &mut Frame) -> Result<Option<RuntimeValue>, Completion> {
+        loop {
+            let op = frame.code.ops[frame.pc];
+            frame.pc += 1;
+
+            match op {
+                Op::Constant(index) => {
+                    let value = frame.code.constants[index as usize].clone();
+                    self.vm.stack.push(value);
+                }
+                Op::Undefined => self.vm.stack.push(RuntimeValue::Undefined),
+                Op::Pop => {
+                    self.pop();
+                }
+                Op::Dup => self.vm.stack.push(self.vm.peek().clone()),
+                Op::Dup2 => {
+                    let len = self.vm.stack.len();
+                    self.vm.stack.extend_from_within(len - 2..);
+                }
+
+                Op::Store(register) => {
+                    self.vm.registers[frame.registers + register as usize] = Some(self.pop())
+                }
+                Op::Load(register) => {
+                    let value = self.vm.registers[frame.registers + register as usize].clone();
+                    self.vm.stack.push(value.unwrap_or(RuntimeValue::Undefined));
+                }
+                Op::Clear(register) => {
+                    self.vm.registers[frame.registers + register as usize] = None
+                }
+                Op::Move { from, to } => {
+                    if let Some(value) = self.vm.registers[frame.registers + from as usize].clone()
+                    {
+                        self.vm.registers[frame.registers + to as usize] = Some(value);
+                    }
+                }
+
+                Op::GetName(name) => {
+                    let name = frame.name(name);
+                    let value = RefCell::borrow(&frame.env).get_binding(name);
+                    let value = value.map_err(|e| Self::binding_error(name, e))?;
+                    self.vm.stack.push(value);
+                }
+                Op::SetName(name) => {
+                    let reference = Reference::Binding(frame.name(name).to_string());
+                    self.put_value(reference, self.vm.peek().clone(), frame.env.clone())?;
+                }
+                Op::TypeofName(name) => {
+                    let value = match RefCell::borrow(&frame.env).get_binding(frame.name(name)) {
+                        Ok(value) => value.type_of(&self.heap),
+                        Err(BindingError::NotDefined) => "undefined",
+                        Err(e) => return Err(Self::binding_error(frame.name(name), e)),
+                    };
+                    self.vm.stack.push(RuntimeValue::String(value.to_string()));
+                }
+                Op::GetLocal { depth, index, name } => {
+                    let value =
+                        RefCell::borrow(&frame.env).get_slot(depth as usize, index as usize);
+                    let value = value.map_err(|e| Self::binding_error(frame.name(name), e))?;
+                    self.vm.stack.push(value);
+                }
+                Op::SetLocal { depth, index, name } => {
+                    let value = self.vm.peek().clone();
+                    let result =
+                        frame
+                            .env
+                            .borrow_mut()
+                            .set_slot(depth as usize, index as usize, value);
+                    result.map_err(|e| Self::binding_error(frame.name(name), e))?;
+                }
+                Op::InitName(name) => {
+                    let value = self.pop();
+                    let name = frame.name(name).to_string();
+                    frame.env.borrow_mut().initialize(name, value);
+                }
+                Op::InitLocal(index) => {
+                    let value = self.pop();
+                    frame
+                        .env
+                        .borrow_mut()
+                        .initialize_slot(index as usize, value);
+                }
+                Op::InitLexical { name, mutable } => {
+                    let value = self.pop();
+                    let name = frame.name(name);
+                    let mut env = frame.env.borrow_mut();
+                    if !env.has_own_binding(name) {
+                        env.declare_lexical(name.to_string(), mutable);
+                    }
+                    env.initialize(name.to_string(), value);
+                }
+                Op::DeclareVar(name) => {
+                    let name = frame.name(name).to_string();
+                    frame.env.borrow_mut().declare_var(name);
+                }
+                Op::DeclareLexical { name, mutable } => {
+                    let name = frame.name(name).to_string();
+                    frame.env.borrow_mut().declare_lexical(name, mutable);
+                }
+                Op::CheckDeclarable(name) => {
+                    let name = frame.name(name);
+                    if frame.env.borrow_mut().has_own_binding(name) {
+                        return Err(Completion::error(
+                            ErrorKind::SyntaxError,
+                            &format!("Identifier '{}' has already been declared", name),
+                        ));
+                    }
+                }
+                Op::PushScope => {
+                    let scope = Environment::new(Some(frame.env.clone()));
+                    self.set_frame_env(frame, Rc::new(RefCell::new(scope)));
+                }
+                Op::PopScope => {
+                    let outer = RefCell::borrow(&frame.env).outer();
+                    self.set_frame_env(frame, outer.expect("no outer scope"));
+                }
+                Op::CopyScope => {
+                    let scope = RefCell::borrow(&frame.env).clone();
+                    self.set_frame_env(frame, Rc::new(RefCell::new(scope)));
+                }
+                Op::This => {
+                    let this = RefCell::borrow(&frame.env).this_value();
+                    self.vm.stack.push(this);
+                }
+                Op::Argument(index) => {
+                    let argument = frame.arguments.get(index as usize).cloned();
+                    self.vm
+                        .stack
+                        .push(argument.unwrap_or(RuntimeValue::Undefined));
+                }
+                Op::RestArguments(index) => {
+                    let rest = frame
+                        .arguments
+                        .get(index as usize..)
+                        .unwrap_or_default()
+                        .to_vec();
+                    let array = self.create_array(rest)?;
+                    self.vm.stack.push(RuntimeValue::Object(array));
+                }
+                Op::CreateArguments => {
+                    let object = self.create_arguments(frame.arguments)?;
+                    self.vm.stack.push(RuntimeValue::Object(object));
+                }
+
+                Op::Binary(operator) => {
+                    let right = self.pop();
+                    let left = self.pop();
+                    let result = match (&left, &right) {
+                        (RuntimeValue::Number(l), RuntimeValue::Number(r)) => {
+                            number_operation(operator, *l, *r)
+                        }
+                        _ => None,
+                    };
+                    let result = match result {
+                        Some(result) => result,
+                        None => self.apply_binary_operator(operator.as_str(), left, right)?,
+                    };
+                    self.vm.stack.push(result);
+                }
+                Op::Not => {
+                    let value = self.pop();
+                    self.vm
+                        .stack
+                        .push(RuntimeValue::Boolean(!value.to_boolean()));
+                }
+                Op::Negate | Op::BitwiseNot | Op::ToNumber | Op::Increment | Op::Decrement => {
+                    let value = self.pop();
+                    let n = match value {
+                        RuntimeValue::Number(n) => n,
+                        v => self.coerce_to_number(v)?,
+                    };
+                    let n = match op {
+                        Op::Negate => -n,
+                        Op::BitwiseNot => !to_int32(n) as f64,
+                        Op::Increment => n + 1.0,
+                        Op::Decrement => n - 1.0,
+                        _ => n,
+                    };
+                    self.vm.stack.push(RuntimeValue::Number(n));
+                }
+                Op::Typeof => {
+                    let value = self.pop();
+                    let type_of = value.type_of(&self.heap).to_string();
+                    self.vm.stack.push(RuntimeValue::String(type_of));
+                }
+                Op::ToPropertyKey => {
+                    let value = self.pop();
+                    let key = match self.coerce_to_property_key(value)? {
+                        PropertyKey::String(s) => RuntimeValue::String(s),
+                        PropertyKey::Symbol(symbol) => RuntimeValue::Symbol(symbol),
+                    };
+                    self.vm.stack.push(key);
+                }
+                Op::GetNamed(key) => {
+                    let base = self.pop();
+                    let value = self.get_property(&base, &frame.code.keys[key as usize])?;
+                    self.vm.stack.push(value);
+                }
+                Op::GetElement => {
+                    let key = self.vm.pop_key();
+                    let base = self.pop();
+                    let value = self.get_property(&base, &key)?;
+                    self.vm.stack.push(value);
+                }
+                Op::SetNamed(key) => {
+                    let value = self.pop();
+                    let base = self.pop();
+                    let key = frame.code.keys[key as usize].clone();
+                    self.set_property(&base, key, value.clone())?;
+                    self.vm.stack.push(value);
+                }
+                Op::SetElement => {
+                    let value = self.pop();
+                    let key = self.vm.pop_key();
+                    let base = self.pop();
+                    self.set_property(&base, key, value.clone())?;
+                    self.vm.stack.push(value);
+                }
+                Op::Delete => {
+                    let key = self.vm.pop_key();
+                    let base = self.pop();
+                    let deleted = self.delete_property(&base, &key)?;
+                    self.vm.stack.push(RuntimeValue::Boolean(deleted));
+                }
+
+                Op::NewObject => {
+                    let object = self.heap.allocate(JsObject::new(
+                        ObjectKind::Ordinary,
+                        Some(self.intrinsics.object_prototype),
+                    ));
+                    self.vm.stack.push(RuntimeValue::Object(object));
+                }
+                Op::DefineField | Op::DefineGetter | Op::DefineSetter => {
+                    let value = self.pop();
+                    let key = self.vm.pop_key();
+                    let desc = match op {
+                        Op::DefineField => PropertyDescriptor::data(value, true, true, true),
+                        _ => {
+                            let (get, set) = if op == Op::DefineGetter {
+                                (Some(value), None)
+                            } else {
+                                (None, Some(value))
+                            };
+                            PropertyDescriptor {
+                                get,
+                                set,
+                                enumerable: Some(true),
+                                configurable: Some(true),
+                                ..Default::default()
+                            }
+                        }
+                    };
+                    self.define_own_property(self.vm.peek_object(), key, desc)?;
+                }
+                Op::CopyProperties => {
+                    let source = self.pop();
+                    self.copy_data_properties(self.vm.peek_object(), source)?;
+                }
+                Op::NewArray => {
+                    let array = self.heap.allocate(JsObject::new(
+                        ObjectKind::Array,
+                        Some(self.intrinsics.array_prototype),
+                    ));
+                    self.vm.stack.push(RuntimeValue::Object(array));
+                    self.vm.stack.push(RuntimeValue::Number(0.0));
+                }
+                Op::ArrayPush | Op::ArrayHole | Op::ArraySpread => {
+                    let values = match op {
+                        Op::ArrayPush => vec![self.pop()],
+                        Op::ArraySpread => {
+                            let value = self.pop();
+                            self.iterate(value)?
+                        }
+                        _ => Vec::new(),
+                    };
+                    let mut length = self.vm.pop_count();
+                    let array = self.vm.peek_object();
+                    for value in values {
+                        let key = PropertyKey::String(length.to_string());
+                        self.create_data_property(array, key, value)?;
+                        length += 1;
+                    }
+                    // 省略された要素は穴になり、長さだけが増える
+                    if op == Op::ArrayHole {
+                        length += 1;
+                    }
+                    self.vm.stack.push(RuntimeValue::Number(length as f64));
+                }
+                Op::ArrayEnd => {
+                    let length = self.pop();
+                    let array = self.vm.peek_object();
+                    self.set(
+                        array,
+                        PropertyKey::from("length"),
+                        length,
+                        RuntimeValue::Object(array),
+                    )?;
+                }
+                Op::Concat => {
+                    let value = self.pop();
+                    let string = match value {
+                        RuntimeValue::String(s) => s,
+                        v => self.coerce_to_string(v)?,
+                    };
+                    if let Some(RuntimeValue::String(s)) = self.vm.stack.last_mut() {
+                        s.push_str(&string);
+                    }
+                }
+
+                Op::Closure(function) => {
+                    let function = &frame.code.functions[function as usize];
+                    let id =
+                        self.create_compiled_function(function, &function.name, frame.env.clone());
+                    self.vm.stack.push(RuntimeValue::Object(id));
+                }
+                Op::KeyedClosure { function, kind } => {
+                    // 関数の名前は、すでに積まれているプロパティのキー
+                    let name = match self.vm.peek() {
+                        RuntimeValue::Symbol(symbol) => match symbol.description() {
+                            Some(description) => format!("[{}]", description),
+                            None => String::new(),
+                        },
+                        RuntimeValue::String(s) => s.clone(),
+                        key => key.to_string(),
+                    };
+                    let name = match property_function_prefix(kind) {
+                        Some(prefix) => format!("{} {}", prefix, name),
+                        None => name,
+                    };
+                    let function = &frame.code.functions[function as usize];
+                    let id = self.create_compiled_function(function, &name, frame.env.clone());
+                    self.vm.stack.push(RuntimeValue::Object(id));
+                }
+                Op::Call(_) | Op::CallSpread => {
+                    let count = match op {
+                        Op::Call(count) => count as usize,
+                        _ => self.vm.pop_count(),
+                    };
+                    // 呼び出している間も、関数と引数はスタックに置いたままにしておく
+                    let start = self.vm.stack.len() - count;
+                    let arguments = self.vm.stack[start..].to_vec();
+                    let function = self.vm.stack[start - 1].clone();
+                    let this = self.vm.stack[start - 2].clone();
+                    let result = self.call(&function, this, &arguments)?;
+                    self.vm.stack.truncate(start - 2);
+                    self.vm.stack.push(result);
+                }
+                Op::Construct(_) | Op::ConstructSpread => {
+                    let count = match op {
+                        Op::Construct(count) => count as usize,
+                        _ => self.vm.pop_count(),
+                    };
+                    let start = self.vm.stack.len() - count;
+                    let arguments = self.vm.stack[start..].to_vec();
+                    let function = self.vm.stack[start - 1].clone();
+                    let result = self.construct(&function, &arguments)?;
+                    self.vm.stack.truncate(start - 1);
+                    self.vm.stack.push(result);
+                }
+                Op::PushArgument | Op::SpreadArgument => {
+                    let value = self.pop();
+                    let count = self.vm.pop_count();
+                    let values = match op {
+                        Op::SpreadArgument => self.iterate(value)?,
+                        _ => vec![value],
+                    };
+                    let count = count + values.len();
+                    self.vm.stack.extend(values);
+                    self.vm.stack.push(RuntimeValue::Number(count as f64));
+                }
+
+                Op::Jump(target) => frame.pc = target as usize,
+                Op::JumpIfFalse(target) => {
+                    if !self.pop().to_boolean() {
+                        frame.pc = target as usize;
+                    }
+                }
+                Op::JumpIfTrue(target) => {
+                    if self.pop().to_boolean() {
+                        frame.pc = target as usize;
+                    }
+                }
+                Op::JumpIfFalseOrPop(target)
+                | Op::JumpIfTrueOrPop(target)
+                | Op::JumpIfNotNullishOrPop(target) => {
+                    let value = self.vm.peek();
+                    let jump = match op {
+                        Op::JumpIfFalseOrPop(_) => !value.to_boolean(),
+                        Op::JumpIfTrueOrPop(_) => value.to_boolean(),
+                        _ => !value.is_nullish(),
+                    };
+                    if jump {
+                        frame.pc = target as usize;
+                    } else {
+                        self.pop();
+                    }
+                }
+                Op::JumpIfNullish(target) => {
+                    if self.vm.peek().is_nullish() {
+                        self.pop();
+                        frame.pc = target as usize;
+                    }
+                }
+                Op::JumpIfNotUndefined(target) => {
+                    if !matches!(self.vm.peek(), RuntimeValue::Undefined) {
+                        frame.pc = target as usize;
+                    }
+                }
+
+                Op::IterateKeys => {
+                    let value = self.pop();
+                    let keys = self.enumerable_keys(&value);
+                    self.vm.iterators.push((keys, 0));
+                }
+                Op::IterateValues => {
+                    let value = self.pop();
+                    let values = self.iterate(value)?;
+                    self.vm.iterators.push((values, 0));
+                }
+                Op::IterNext(target) => {
+                    let (values, next) = self.vm.iterators.last_mut().expect("no iterator");
+                    match values.get_mut(*next) {
+                        Some(value) => {
+                            let value = core::mem::replace(value, RuntimeValue::Undefined);
+                            *next += 1;
+                            self.vm.stack.push(value);
+                        }
+                        None => frame.pc = target as usize,
+                    }
+                }
+                Op::IterPop => {
+                    self.vm.iterators.pop();
+                }
+
+                Op::PushHandler { target, completion } => frame.handlers.push(Handler {
+                    target: target as usize,
+                    completion: completion as usize,
+                    stack: self.vm.stack.len(),
+                    iterators: self.vm.iterators.len(),
+                    env: frame.env.clone(),
+                }),
+                Op::PopHandler => {
+                    frame.handlers.pop();
+                }
+                Op::LoadException(completion) => {
+                    let completion =
+                        self.vm.completions[frame.completions + completion as usize].take();
+                    let value = self.exception_value(completion.expect("no exception"));
+                    self.vm.stack.push(value);
+                }
+                Op::Rethrow(completion) => {
+                    let completion =
+                        self.vm.completions[frame.completions + completion as usize].take();
+                    return Err(completion.expect("no exception"));
+                }
+                Op::Throw => return Err(Completion::Throw(self.pop())),
+                Op::ThrowError { kind, message } => {
+                    return Err(Completion::error(kind, frame.name(message)))
+                }
+                Op::Return => return Ok(Some(self.pop())),
+                Op::ReturnRegister(register) => {
+                    return Ok(self.vm.registers[frame.registers + register as usize].take())
+                }
+                Op::Collect => {
+                    self.heap.unpin(frame.pinned);
+                    self.collect_if_needed();
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::renderer::dom::node::Node as DomNode;
+    use crate::renderer::dom::node::NodeKind as DomNodeKind;
+    use crate::renderer::js::ast::JsParser;
+    use crate::renderer::js::runtime::Engine;
+    use crate::renderer::js::token::JsLexer;
+
+    /// 両方の方法で実行し、結果を表示した文字列で比べる
+    fn run_both(input: &str) -> String {
+        let program = JsParser::new(JsLexer::new(input.to_string()))
+            .parse_ast()
+            .expect("failed to parse");
+        let [tree_walker, bytecode] = [Engine::TreeWalker, Engine::Bytecode].map(|engine| {
+            let dom = Rc::new(RefCell::new(DomNode::new(DomNodeKind::Document)));
+            let mut runtime = JsRuntime::new(dom);
+            runtime.set_engine(engine);
+            match runtime.evaluate(&program) {
+                Ok(value) => format!("{:?}", value),
+                Err(e) => format!("Uncaught {}", e),
+            }
+        });
+        assert_eq!(tree_walker, bytecode, "{}", input);
+        bytecode
+    }
+
+    #[test]
+    fn test_control_flow() {
+        assert_eq!(
+            "Some(String(\"0ff2ff\"))",
+            run_both(
+                "var s = ''; for (var i = 0; i < 5; i++) {
+                   try { if (i == 3) break; if (i % 2) continue; s += i; } finally { s += 'f'; }
+                   s += i; s = s.slice(0, -1);
+                 } s"
+            )
+        );
+        run_both("function f() { try { return 1; } finally { return 2; } } f()");
+        run_both(
+            "function f() { for (var x of [1, 2]) { try { throw x; } catch (e) { return e; } finally { x; } } } f()",
+        );
+        run_both(
+            "var s = ''; outer: for (var a in { x: 1, y: 2 }) {
+               for (var b of [1, 2, 3]) { if (b == 2) continue outer; if (a == 'y') break outer; s += a + b; }
+             } s",
+        );
+        run_both("l: { 1; break l; } ");
+        run_both("do { 1; if (true) break; } while (false)");
+        run_both("while (true) { 2; { 3; break; } }");
+        run_both("switch (2) { case 1: 'a'; case 2: 'b'; case 3: 'c'; break; default: 'd'; }");
+        run_both("switch (9) { case 1: let x = 1; default: x = 2; x; }");
+        run_both("try { 1; throw 2; } catch (e) { } ");
+        run_both("try { 1; } finally { 2; }");
+        run_both("var e = 0; try { throw 1; } catch (e) { e = 5; } e");
+        run_both("try { try { throw 1; } finally { 2; } } catch (x) { x; }");
+        run_both("var n = 0; for (;;) { try { continue; } finally { if (++n > 2) break; } } n");
+    }
+
+    #[test]
+    fn test_scopes() {
+        assert_eq!(
+            "Some(String(\"0,1,2\"))",
+            run_both(
+                "var fs = []; for (let i = 0; i < 3; i++) { fs.push(() => i); } fs.map(f => f()).join()"
+            )
+        );
+        run_both(
+            "var fs = []; for (const k in { a: 1, b: 2 }) fs.push(() => k); fs[0]() + fs[1]()",
+        );
+        run_both("let x = 1; { let x = 2; { x = 3; } } x");
+        run_both("{ f(); function f() { return 1; } }");
+        run_both("x; let x = 1;");
+        run_both("const c = 1; c = 2;");
+        run_both("let y = 1; let y = 2;");
+        run_both("function f(a, b = a + 1, ...rest) { return [a, b, rest.length]; } f(1).join() + f(1, 5, 6, 7).join()");
+        run_both("function f(a = b, b) { return a; } f()");
+        run_both("if (true) let z = 1; z");
+        run_both("function f() { if (true) let z = 2; return z; } f()");
+        run_both("var g = function h(n) { return n ? h(n - 1) + 1 : 0; }; g(3) + g.name");
+        run_both("undeclared = 4; typeof undeclared + typeof nothing");
+        run_both("function f() { return this; } typeof f() + typeof f.call({})");
+        run_both("function outer() { var a = 1; return function () { return a++; }; } var c = outer(); c(); c()");
+        run_both("function f(a, b = arguments.length) { var c = a; return [a, b, c, arguments[0]].join(); } f(1)");
+        run_both("function f() { arguments[0] = 2; var g = () => arguments.length; return arguments[0] + g(); } f(1, 1)");
+    }
+
+    #[test]
+    fn test_expressions() {
+        run_both(
+            "var o = { a: { b: null } }; [o?.a?.b?.c, o.x?.(), o.a.b ?? 'n', o?.['a']].join()",
+        );
+        // 関数の名前はプロパティのキーになる
+        assert_eq!(
+            "Some(String(\"f1,get g,k,2\"))",
+            run_both(
+                "var o = { ['f' + 1]: function () {}, get g() { return 1; }, 'k': () => 0, 2: function () {} };
+                 [o.f1.name, Object.getOwnPropertyDescriptor(o, 'g').get.name, o.k.name, o[2].name].join()"
+            )
+        );
+        run_both("var a = [1, , 3, ...'ab']; a.length + ':' + a.join()");
+        run_both(
+            "var o = { x: 1 }; o.x += 2; o['y'] = o.x++; [o.x, o.y, ++o.x, delete o.x, o.x].join()",
+        );
+        run_both("Math.max(...[1, 5], 3) + new Array(...[2, 3]).length");
+        run_both("`a${1 + 1}b${'c'}`");
+        run_both("var i = 0; i++ + ++i + (i -= 1) + -i + ~i + +'3' + !i");
+        run_both("0 / 0");
+        run_both("null.x");
+        run_both("undefined()");
+        run_both("void 1 || 0 && 2");
+    }
+}
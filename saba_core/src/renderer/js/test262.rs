@@ -0,0 +1,600 @@
+//! test262の形式のテストを動かし、機能のディレクトリごとにどれだけ通るかを数える
+//! ファイルの読み込みはホストが行い、ハーネスとテストのソースを渡す
+//! https://github.com/tc39/test262/blob/main/INTERPRETING.md
+
+use crate::renderer::dom::node::Node as DomNode;
+use crate::renderer::dom::node::NodeKind as DomNodeKind;
+use crate::renderer::js::ast::JsParser;
+use crate::renderer::js::runtime::Engine;
+use crate::renderer::js::runtime::JsRuntime;
+use crate::renderer::js::token::JsLexer;
+use alloc::format;
+use alloc::rc::Rc;
+use alloc::string::String;
+use alloc::string::ToString;
+use alloc::vec::Vec;
+use core::cell::RefCell;
+use core::fmt::Display;
+use core::fmt::Formatter;
+
+/// harness/sta.jsを小さくしたもの
+pub const STA: &str = r#"
+function Test262Error(message) {
+  this.message = message || "";
+}
+Test262Error.prototype.toString = function () {
+  return "Test262Error: " + this.message;
+};
+Test262Error.thrower = function (message) {
+  throw new Test262Error(message);
+};
+function $DONOTEVALUATE() {
+  throw "Test262: This statement should not be evaluated.";
+}
+"#;
+
+/// harness/assert.jsを小さくしたもの
+pub const ASSERT: &str = r#"
+function assert(mustBeTrue, message) {
+  if (mustBeTrue === true) {
+    return;
+  }
+  if (message === undefined) {
+    message = "Expected true but got " + String(mustBeTrue);
+  }
+  throw new Test262Error(message);
+}
+assert._isSameValue = function (a, b) {
+  if (a === b) {
+    return a !== 0 || 1 / a === 1 / b;
+  }
+  return a !== a && b !== b;
+};
+assert.sameValue = function (actual, expected, message) {
+  if (assert._isSameValue(actual, expected)) {
+    return;
+  }
+  throw new Test262Error((message ? message + " " : "") +
+    "Expected SameValue(" + String(actual) + ", " + String(expected) + ") to be true");
+};
+assert.notSameValue = function (actual, unexpected, message) {
+  if (!assert._isSameValue(actual, unexpected)) {
+    return;
+  }
+  throw new Test262Error((message ? message + " " : "") +
+    "Expected SameValue(" + String(actual) + ", " + String(unexpected) + ") to be false");
+};
+assert.throws = function (expectedErrorConstructor, func, message) {
+  var prefix = message ? message + " " : "";
+  try {
+    func();
+  } catch (thrown) {
+    if (typeof thrown !== "object" || thrown === null) {
+      throw new Test262Error(prefix + "Thrown value was not an object");
+    }
+    if (thrown.constructor !== expectedErrorConstructor) {
+      throw new Test262Error(prefix + "Expected a " + expectedErrorConstructor.name +
+        " but got a " + thrown.constructor.name);
+    }
+    return;
+  }
+  throw new Test262Error(prefix + "Expected a " + expectedErrorConstructor.name +
+    " to be thrown but no exception was thrown at all");
+};
+"#;
+
+/// harness/compareArray.jsを小さくしたもの
+pub const COMPARE_ARRAY: &str = r#"
+function compareArray(a, b) {
+  if (b.length !== a.length) {
+    return false;
+  }
+  for (var i = 0; i < a.length; i++) {
+    if (!assert._isSameValue(b[i], a[i])) {
+      return false;
+    }
+  }
+  return true;
+}
+assert.compareArray = function (actual, expected, message) {
+  if (compareArray(actual, expected)) {
+    return;
+  }
+  throw new Test262Error((message ? message + " " : "") + "Expected [" +
+    actual.join(", ") + "] and [" + expected.join(", ") + "] to have the same contents");
+};
+"#;
+
+/// negativeで期待するエラーが起きる段階
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Phase {
+    Parse,
+    Resolution,
+    Runtime,
+}
+
+/// エラーになることを期待するテストの、段階とエラーの種類
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Negative {
+    pub phase: Phase,
+    pub error_type: String,
+}
+
+/// テストのファイルの先頭の`/*--- ... ---*/`に書かれた情報
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Metadata {
+    pub includes: Vec<String>,
+    pub flags: Vec<String>,
+    pub features: Vec<String>,
+    pub negative: Option<Negative>,
+}
+
+impl Metadata {
+    /// YAMLのうち、test262のfrontmatterが使う形だけを読む
+    pub fn parse(source: &str) -> Self {
+        let mut metadata = Metadata::default();
+        let yaml = match source.split_once("/*---") {
+            Some((_, rest)) => rest.split_once("---*/").map_or("", |(yaml, _)| yaml),
+            None => return metadata,
+        };
+
+        let mut key = "";
+        let mut phase = None;
+        let mut error_type = None;
+        for line in yaml.lines() {
+            if line.trim().is_empty() {
+                continue;
+            }
+            let indented = line.starts_with(' ') || line.starts_with('\t');
+            let line = line.trim();
+            // `includes:`の後の`- a.js`のような、ブロックの形のリスト
+            if indented {
+                if let Some(item) = line.strip_prefix("- ") {
+                    if let Some(list) = metadata.list_mut(key) {
+                        list.push(item.trim().to_string());
+                    }
+                } else if let Some((name, value)) = line.split_once(':') {
+                    if key == "negative" {
+                        match name.trim() {
+                            "phase" => phase = Some(value.trim().to_string()),
+                            "type" => error_type = Some(value.trim().to_string()),
+                            _ => {}
+                        }
+                    }
+                }
+                continue;
+            }
+
+            let (name, value) = line.split_once(':').unwrap_or((line, ""));
+            key = name.trim();
+            // `flags: [onlyStrict, raw]`のような、1行の形のリスト
+            if let Some(items) = value
+                .trim()
+                .strip_prefix('[')
+                .and_then(|v| v.strip_suffix(']'))
+            {
+                if let Some(list) = metadata.list_mut(key) {
+                    list.extend(
+                        items
+                            .split(',')
+                            .map(|item| item.trim().to_string())
+                            .filter(|item| !item.is_empty()),
+                    );
+                }
+            }
+        }
+
+        let phase = match phase.as_deref() {
+            Some("parse") => Some(Phase::Parse),
+            Some("resolution") => Some(Phase::Resolution),
+            Some("runtime") => Some(Phase::Runtime),
+            _ => None,
+        };
+        if let (Some(phase), Some(error_type)) = (phase, error_type) {
+            metadata.negative = Some(Negative { phase, error_type });
+        }
+        metadata
+    }
+
+    fn list_mut(&mut self, key: &str) -> Option<&mut Vec<String>> {
+        match key {
+            "includes" => Some(&mut self.includes),
+            "flags" => Some(&mut self.flags),
+            "features" => Some(&mut self.features),
+            _ => None,
+        }
+    }
+
+    pub fn has_flag(&self, flag: &str) -> bool {
+        self.flags.iter().any(|f| f == flag)
+    }
+}
+
+/// 1つのテストの結果
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Outcome {
+    Pass,
+    /// 失敗した理由
+    Fail(String),
+    /// 実行しなかった理由
+    Skip(String),
+}
+
+/// ディレクトリごとの結果
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DirectorySummary {
+    pub name: String,
+    pub passed: usize,
+    pub skipped: usize,
+    /// 失敗したテストのパスと理由
+    pub failed: Vec<(String, String)>,
+}
+
+impl DirectorySummary {
+    pub fn total(&self) -> usize {
+        self.passed + self.skipped + self.failed.len()
+    }
+}
+
+/// すべてのテストの結果
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Summary {
+    pub directories: Vec<DirectorySummary>,
+}
+
+impl Summary {
+    pub fn passed(&self) -> usize {
+        self.directories.iter().map(|d| d.passed).sum()
+    }
+
+    pub fn skipped(&self) -> usize {
+        self.directories.iter().map(|d| d.skipped).sum()
+    }
+
+    pub fn failed(&self) -> usize {
+        self.directories.iter().map(|d| d.failed.len()).sum()
+    }
+
+    pub fn total(&self) -> usize {
+        self.directories.iter().map(|d| d.total()).sum()
+    }
+
+    fn record(&mut self, path: &str, outcome: Outcome) {
+        let name = feature_directory(path);
+        let index = match self.directories.iter().position(|d| d.name == name) {
+            Some(index) => index,
+            None => {
+                self.directories.push(DirectorySummary {
+                    name: name.to_string(),
+                    ..Default::default()
+                });
+                self.directories.len() - 1
+            }
+        };
+        let directory = &mut self.directories[index];
+        match outcome {
+            Outcome::Pass => directory.passed += 1,
+            Outcome::Skip(_) => directory.skipped += 1,
+            Outcome::Fail(reason) => directory.failed.push((path.to_string(), reason)),
+        }
+    }
+}
+
+impl Display for Summary {
+    fn fmt(&self, f: &mut Formatter) -> core::fmt::Result {
+        for directory in &self.directories {
+            writeln!(
+                f,
+                "{}: {}/{} passed, {} skipped",
+                directory.name,
+                directory.passed,
+                directory.total(),
+                directory.skipped
+            )?;
+            for (path, reason) in &directory.failed {
+                writeln!(f, "  FAIL {}: {}", path, reason)?;
+            }
+        }
+        write!(
+            f,
+            "total: {}/{} passed, {} skipped",
+            self.passed(),
+            self.total(),
+            self.skipped()
+        )
+    }
+}
+
+/// `built-ins/Array/prototype/push/length.js`の`built-ins/Array`のような、機能を表すディレクトリ
+fn feature_directory(path: &str) -> &str {
+    let mut end = 0;
+    for (i, _) in path.match_indices('/').take(2) {
+        end = i;
+    }
+    if end == 0 {
+        return path;
+    }
+    &path[..end]
+}
+
+/// ハーネスとテストを読み込み、実行する
+#[derive(Debug, Clone)]
+pub struct Test262Suite {
+    /// harnessディレクトリのファイル名とソース
+    harness: Vec<(String, String)>,
+    /// testディレクトリからのパスとソース
+    tests: Vec<(String, String)>,
+    engine: Engine,
+}
+
+impl Default for Test262Suite {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Test262Suite {
+    /// 小さくしたsta.js、assert.js、compareArray.jsをハーネスとして持つ
+    pub fn new() -> Self {
+        Self {
+            harness: Vec::from([
+                ("sta.js".to_string(), STA.to_string()),
+                ("assert.js".to_string(), ASSERT.to_string()),
+                ("compareArray.js".to_string(), COMPARE_ARRAY.to_string()),
+            ]),
+            tests: Vec::new(),
+            engine: Engine::Bytecode,
+        }
+    }
+
+    /// テストを実行する方法を切り替える
+    pub fn set_engine(&mut self, engine: Engine) {
+        self.engine = engine;
+    }
+
+    /// ハーネスのファイルを追加する。同じ名前のファイルは置き換える
+    pub fn add_harness(&mut self, name: &str, source: String) {
+        match self.harness.iter_mut().find(|(n, _)| n == name) {
+            Some(harness) => harness.1 = source,
+            None => self.harness.push((name.to_string(), source)),
+        }
+    }
+
+    pub fn add_test(&mut self, path: &str, source: String) {
+        self.tests.push((path.to_string(), source));
+    }
+
+    /// テストをパスの順に実行する
+    pub fn run(&self) -> Summary {
+        let mut tests: Vec<&(String, String)> = self.tests.iter().collect();
+        tests.sort_by(|a, b| a.0.cmp(&b.0));
+        let mut summary = Summary::default();
+        for (path, source) in tests {
+            summary.record(path, self.run_test(source));
+        }
+        summary
+    }
+
+    /// 構文木の評価とバイトコードの実行で結果が違うテストを、パスの順に返す
+    /// 結果は構文木の評価、バイトコードの実行の順に並べる
+    pub fn differences(&self) -> Vec<(String, Outcome, Outcome)> {
+        let mut tests: Vec<&(String, String)> = self.tests.iter().collect();
+        tests.sort_by(|a, b| a.0.cmp(&b.0));
+        let mut differences = Vec::new();
+        for (path, source) in tests {
+            let tree_walker = self.run_test_in(source, Engine::TreeWalker);
+            let bytecode = self.run_test_in(source, Engine::Bytecode);
+            if tree_walker != bytecode {
+                differences.push((path.clone(), tree_walker, bytecode));
+            }
+        }
+        differences
+    }
+
+    /// 1つのテストを、新しいランタイムで実行する
+    pub fn run_test(&self, source: &str) -> Outcome {
+        self.run_test_in(source, self.engine)
+    }
+
+    fn run_test_in(&self, source: &str, engine: Engine) -> Outcome {
+        let metadata = Metadata::parse(source);
+        if metadata.has_flag("module") || metadata.has_flag("async") {
+            return Outcome::Skip("modules and async tests are not supported".to_string());
+        }
+        // strictモードはないので、strictモードだけのテストは実行しない
+        if metadata.has_flag("onlyStrict") {
+            return Outcome::Skip("strict mode is not supported".to_string());
+        }
+
+        let mut includes = Vec::new();
+        if !metadata.has_flag("raw") {
+            includes.push("assert.js");
+            includes.push("sta.js");
+        }
+        includes.extend(metadata.includes.iter().map(String::as_str));
+        let mut harness = Vec::new();
+        for name in includes {
+            match self.harness.iter().find(|(n, _)| n == name) {
+                Some((_, source)) => harness.push(source.as_str()),
+                None => return Outcome::Fail(format!("missing harness file {}", name)),
+            }
+        }
+
+        let mut parser = JsParser::new(JsLexer::new(source.to_string()));
+        let program = match (parser.parse_ast(), &metadata.negative) {
+            // 構文解析のエラーはSyntaxErrorになる
+            (Err(e), Some(negative)) if negative.phase == Phase::Parse => {
+                if negative.error_type == "SyntaxError" {
+                    return Outcome::Pass;
+                }
+                return Outcome::Fail(format!(
+                    "expected {} but got SyntaxError: {:?}",
+                    negative.error_type, e
+                ));
+            }
+            (Err(e), _) => return Outcome::Fail(format!("SyntaxError: {:?}", e)),
+            (Ok(_), Some(negative)) if negative.phase != Phase::Runtime => {
+                return Outcome::Fail(format!("expected {} while parsing", negative.error_type))
+            }
+            (Ok(program), _) => program,
+        };
+
+        let dom = Rc::new(RefCell::new(DomNode::new(DomNodeKind::Document)));
+        let mut runtime = JsRuntime::new(dom);
+        runtime.set_engine(engine);
+        runtime.define_test262_host();
+        // ハーネスは関数を定義するだけなので、読み込む順番は問わない
+        for harness in harness {
+            let mut parser = JsParser::new(JsLexer::new(harness.to_string()));
+            let result = match parser.parse_ast() {
+                Ok(program) => runtime.evaluate(&program),
+                Err(e) => Err(format!("SyntaxError: {:?}", e)),
+            };
+            if let Err(e) = result {
+                return Outcome::Fail(format!("harness failed: {}", e));
+            }
+        }
+
+        match (runtime.evaluate_value(&program), metadata.negative) {
+            (Ok(_), None) => Outcome::Pass,
+            (Ok(_), Some(negative)) => Outcome::Fail(format!(
+                "expected {} but no exception was thrown",
+                negative.error_type
+            )),
+            (Err(e), None) => Outcome::Fail(runtime.display_value(e)),
+            // 例外の文字列ではなく、投げられた値のコンストラクタを確かめる
+            (Err(e), Some(negative)) => {
+                if runtime.is_instance_of(&e, &negative.error_type) {
+                    Outcome::Pass
+                } else {
+                    Outcome::Fail(format!(
+                        "expected {} but got {}",
+                        negative.error_type,
+                        runtime.display_value(e)
+                    ))
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_metadata() {
+        let source = "// Copyright
+/*---
+description: >
+  multi-line: description
+includes: [compareArray.js, propertyHelper.js]
+flags:
+  - noStrict
+  - raw
+negative:
+  phase: parse
+  type: SyntaxError
+features: [Symbol]
+---*/
+1 = 2;";
+        assert_eq!(
+            Metadata {
+                includes: Vec::from([
+                    "compareArray.js".to_string(),
+                    "propertyHelper.js".to_string()
+                ]),
+                flags: Vec::from(["noStrict".to_string(), "raw".to_string()]),
+                features: Vec::from(["Symbol".to_string()]),
+                negative: Some(Negative {
+                    phase: Phase::Parse,
+                    error_type: "SyntaxError".to_string(),
+                }),
+            },
+            Metadata::parse(source)
+        );
+        assert_eq!(Metadata::default(), Metadata::parse("var x;"));
+    }
+
+    #[test]
+    fn test_run_test() {
+        let suite = Test262Suite::new();
+        assert_eq!(Outcome::Pass, suite.run_test("assert.sameValue(1 + 1, 2);"));
+        assert_eq!(
+            Outcome::Fail("Test262Error: Expected SameValue(1, 2) to be true".to_string()),
+            suite.run_test("assert.sameValue(1, 2);")
+        );
+        assert!(matches!(
+            suite.run_test("assert.throws(TypeError, function () {});"),
+            Outcome::Fail(_)
+        ));
+        assert_eq!(
+            Outcome::Pass,
+            suite.run_test("/*---\nnegative:\n  phase: runtime\n  type: ReferenceError\n---*/\nx;")
+        );
+        assert_eq!(
+            Outcome::Pass,
+            suite.run_test("/*---\nnegative:\n  phase: parse\n  type: SyntaxError\n---*/\n1 = ;")
+        );
+        assert_eq!(
+            Outcome::Pass,
+            suite.run_test(
+                "/*---\nnegative:\n  phase: runtime\n  type: Test262Error\n---*/\nthrow new Test262Error();"
+            )
+        );
+        // 例外が起きた段階と、投げられた値のコンストラクタが一致しなければ失敗する
+        for source in [
+            "/*---\nnegative:\n  phase: parse\n  type: ReferenceError\n---*/\n1 = ;",
+            "/*---\nnegative:\n  phase: runtime\n  type: SyntaxError\n---*/\n1 = ;",
+            "/*---\nnegative:\n  phase: parse\n  type: ReferenceError\n---*/\nx;",
+            "/*---\nnegative:\n  phase: runtime\n  type: ReferenceError\n---*/\nthrow new TypeError();",
+            "/*---\nnegative:\n  phase: runtime\n  type: ReferenceError\n---*/\nthrow 'ReferenceError: x';",
+        ] {
+            assert!(matches!(suite.run_test(source), Outcome::Fail(_)), "{}", source);
+        }
+        assert!(matches!(
+            suite.run_test("/*---\nincludes: [missing.js]\n---*/"),
+            Outcome::Fail(_)
+        ));
+        assert!(matches!(
+            suite.run_test("/*---\nflags: [module]\n---*/"),
+            Outcome::Skip(_)
+        ));
+        // rawのテストはハーネスを読み込まない
+        assert!(matches!(
+            suite.run_test("/*---\nflags: [raw]\n---*/\nassert(true);"),
+            Outcome::Fail(_)
+        ));
+    }
+
+    #[test]
+    fn test_summary() {
+        let mut suite = Test262Suite::new();
+        suite.add_test(
+            "built-ins/Math/max/zero.js",
+            "assert(Math.max(0) === 0);".to_string(),
+        );
+        suite.add_test(
+            "built-ins/Math/abs/one.js",
+            "assert(Math.abs(-1) === 1);".to_string(),
+        );
+        suite.add_test(
+            "language/types/number.js",
+            "assert(typeof 1 === 'string');".to_string(),
+        );
+        let summary = suite.run();
+        assert_eq!(2, summary.passed());
+        assert_eq!(1, summary.failed());
+        assert_eq!(
+            Vec::from(["built-ins/Math", "language/types"]),
+            summary
+                .directories
+                .iter()
+                .map(|d| d.name.as_str())
+                .collect::<Vec<_>>()
+        );
+        assert!(summary
+            .to_string()
+            .ends_with("total: 2/3 passed, 0 skipped"));
+    }
+}
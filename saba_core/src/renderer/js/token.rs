@@ -1,21 +1,168 @@
+//! ECMAScriptの字句解析
+//! https://262.ecma-international.org/11.0/#sec-ecmascript-language-lexical-grammar
+
+use crate::error::Error;
+use alloc::format;
 use alloc::string::String;
-use alloc::string::ToString;
 use alloc::vec::Vec;
 
-static RESERVED_KEYWORDS: [&str; 3] = ["var", "function", "return"];
+/// 予約語
+/// `let`や`async`などの文脈によって意味が変わる語は識別子として扱う
+static RESERVED_WORDS: [&str; 38] = [
+    "await",
+    "break",
+    "case",
+    "catch",
+    "class",
+    "const",
+    "continue",
+    "debugger",
+    "default",
+    "delete",
+    "do",
+    "else",
+    "enum",
+    "export",
+    "extends",
+    "false",
+    "finally",
+    "for",
+    "function",
+    "if",
+    "import",
+    "in",
+    "instanceof",
+    "new",
+    "null",
+    "return",
+    "super",
+    "switch",
+    "this",
+    "throw",
+    "true",
+    "try",
+    "typeof",
+    "var",
+    "void",
+    "while",
+    "with",
+    "yield",
+];
+
+/// 区切り子。最長一致で読むため、長いものから並べる
+static PUNCTUATORS: [&str; 54] = [
+    ">>>=", "...", "===", "!==", "**=", "<<=", ">>=", ">>>", "=>", "==", "!=", "<=", ">=", "&&",
+    "||", "??", "?.", "++", "--", "+=", "-=", "*=", "/=", "%=", "&=", "|=", "^=", "<<", ">>", "**",
+    "{", "}", "(", ")", "[", "]", ".", ";", ",", "<", ">", "+", "-", "*", "/", "%", "&", "|", "^",
+    "!", "~", "?", ":", "=",
+];
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum Token {
-    Punctuator(char),
-    Number(u64),
+    Punctuator(&'static str),
+    Number(f64),
+    /// `n`を除いた10進数の数字列
+    BigInt(String),
     Identifier(String),
-    Keyword(String),
+    Keyword(&'static str),
     StringLiteral(String),
+    /// `` `abc` ``
+    NoSubstitutionTemplate(String),
+    /// `` `abc${ ``
+    TemplateHead(String),
+    /// `}abc${`
+    TemplateMiddle(String),
+    /// `` }abc` ``
+    TemplateTail(String),
+    RegularExpression {
+        pattern: String,
+        flags: String,
+    },
+}
+
+/// ソース上の位置。行と列は1から数える
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Position {
+    line: usize,
+    column: usize,
+}
+
+impl Position {
+    pub fn new(line: usize, column: usize) -> Self {
+        Self { line, column }
+    }
+
+    pub fn line(&self) -> usize {
+        self.line
+    }
+
+    pub fn column(&self) -> usize {
+        self.column
+    }
+}
+
+/// 位置の情報がついたトークン
+#[derive(Debug, Clone, PartialEq)]
+pub struct Lexeme {
+    token: Token,
+    position: Position,
+    newline_before: bool,
+}
+
+impl Lexeme {
+    pub fn new(token: Token, position: Position, newline_before: bool) -> Self {
+        Self {
+            token,
+            position,
+            newline_before,
+        }
+    }
+
+    pub fn token(&self) -> &Token {
+        &self.token
+    }
+
+    pub fn into_token(self) -> Token {
+        self.token
+    }
+
+    pub fn position(&self) -> Position {
+        self.position
+    }
+
+    /// 直前のトークンとの間に改行があるかどうか。自動セミコロン挿入に使う
+    pub fn newline_before(&self) -> bool {
+        self.newline_before
+    }
+}
+
+pub fn is_line_terminator(c: char) -> bool {
+    matches!(c, '\n' | '\r' | '\u{2028}' | '\u{2029}')
+}
+
+pub fn is_whitespace(c: char) -> bool {
+    c == '\u{FEFF}' || (c.is_whitespace() && !is_line_terminator(c) && c != '\u{85}')
+}
+
+fn is_identifier_start(c: char) -> bool {
+    c.is_alphabetic() || c == '$' || c == '_'
+}
+
+fn is_identifier_part(c: char) -> bool {
+    c.is_alphanumeric() || c == '$' || c == '_' || c == '\u{200C}' || c == '\u{200D}'
 }
 
 pub struct JsLexer {
     pos: usize,
     input: Vec<char>,
+    line: usize,
+    column: usize,
+    /// 次の`/`を正規表現の始まりとして読むかどうか
+    regex_allowed: bool,
+    /// 閉じていない`{`と`${`。`${`に対応する`}`のあとはテンプレートの続きになる
+    braces: Vec<bool>,
+    /// エラーのあとは何も返さない
+    finished: bool,
 }
 
 impl JsLexer {
@@ -23,129 +170,542 @@ impl JsLexer {
         Self {
             pos: 0,
             input: input.chars().collect(),
+            line: 1,
+            column: 1,
+            regex_allowed: true,
+            braces: Vec::new(),
+            finished: false,
+        }
+    }
+
+    fn peek_char(&self, offset: usize) -> Option<char> {
+        self.input.get(self.pos + offset).copied()
+    }
+
+    fn starts_with(&self, s: &str) -> bool {
+        s.chars()
+            .enumerate()
+            .all(|(i, c)| self.peek_char(i) == Some(c))
+    }
+
+    fn current_position(&self) -> Position {
+        Position::new(self.line, self.column)
+    }
+
+    fn error(&self, position: Position, message: &str) -> Error {
+        Error::UnexpectedInput(format!(
+            "{}:{}: {}",
+            position.line(),
+            position.column(),
+            message
+        ))
+    }
+
+    /// 1文字進める。CRLFは1つの改行として数える
+    fn advance(&mut self) -> Option<char> {
+        let c = self.peek_char(0)?;
+        self.pos += 1;
+        if is_line_terminator(c) && !(c == '\r' && self.peek_char(0) == Some('\n')) {
+            self.line += 1;
+            self.column = 1;
+        } else {
+            self.column += 1;
+        }
+        Some(c)
+    }
+
+    fn skip_line(&mut self) {
+        while let Some(c) = self.peek_char(0) {
+            if is_line_terminator(c) {
+                return;
+            }
+            self.advance();
         }
     }
 
-    fn contains(&self, keyword: &str) -> bool {
-        for i in 0..keyword.len() {
-            if keyword
-                .chars()
-                .nth(i)
-                .expect("failed to access to i-th char")
-                != self.input[self.pos + i]
+    /// 最初の行で、ここまでに空白しかないかどうか
+    fn at_first_line_start(&self) -> bool {
+        self.input[..self.pos].iter().all(|c| is_whitespace(*c))
+    }
+
+    /// 空白とコメントを読み飛ばし、改行があったかどうかを返す
+    fn skip_whitespace_and_comments(&mut self) -> Result<bool, Error> {
+        let mut newline = false;
+        loop {
+            let c = match self.peek_char(0) {
+                Some(c) => c,
+                None => return Ok(newline),
+            };
+
+            if is_whitespace(c) {
+                self.advance();
+            } else if is_line_terminator(c) {
+                newline = true;
+                self.advance();
+            } else if self.starts_with("//")
+                || self.starts_with("<!--")
+                || (self.starts_with("-->") && (newline || self.at_first_line_start()))
             {
-                return false;
+                // `<!--`と行頭の`-->`は1行のコメントとして扱う (Annex B.1.3)
+                self.skip_line();
+            } else if self.starts_with("/*") {
+                let start = self.current_position();
+                self.advance();
+                self.advance();
+                loop {
+                    if self.starts_with("*/") {
+                        self.advance();
+                        self.advance();
+                        break;
+                    }
+                    match self.advance() {
+                        Some(c) if is_line_terminator(c) => newline = true,
+                        Some(_) => {}
+                        None => return Err(self.error(start, "unterminated comment")),
+                    }
+                }
+            } else {
+                return Ok(newline);
             }
         }
+    }
+
+    fn hex_digits(&mut self, count: usize) -> Option<u32> {
+        let mut value = 0;
+        for i in 0..count {
+            value = value * 16 + self.peek_char(i)?.to_digit(16)?;
+        }
+        for _ in 0..count {
+            self.advance();
+        }
+        Some(value)
+    }
+
+    /// `\u`のあとの`XXXX`か`{X...}`を読む
+    fn unicode_escape(&mut self, start: Position) -> Result<u32, Error> {
+        if self.peek_char(0) != Some('{') {
+            return self
+                .hex_digits(4)
+                .ok_or_else(|| self.error(start, "invalid unicode escape"));
+        }
 
-        true
+        self.advance();
+        let mut value: u32 = 0;
+        let mut digits = 0;
+        while let Some(d) = self.peek_char(0).and_then(|c| c.to_digit(16)) {
+            value = value * 16 + d;
+            if value > 0x10FFFF {
+                return Err(self.error(start, "unicode escape out of range"));
+            }
+            digits += 1;
+            self.advance();
+        }
+        if digits == 0 || self.advance() != Some('}') {
+            return Err(self.error(start, "invalid unicode escape"));
+        }
+        Ok(value)
     }
 
-    fn check_reserved_word(&self) -> Option<String> {
-        for word in RESERVED_KEYWORDS {
-            if self.contains(word) {
-                return Some(word.to_string());
+    /// `\`のあとを読み、エスケープが表す文字を`result`に加える
+    fn consume_escape(&mut self, result: &mut String, in_template: bool) -> Result<(), Error> {
+        let start = self.current_position();
+        let c = match self.advance() {
+            Some(c) => c,
+            None => return Err(self.error(start, "unterminated escape sequence")),
+        };
+
+        match c {
+            'b' => result.push('\u{8}'),
+            'f' => result.push('\u{C}'),
+            'n' => result.push('\n'),
+            'r' => result.push('\r'),
+            't' => result.push('\t'),
+            'v' => result.push('\u{B}'),
+            'x' => {
+                let value = self
+                    .hex_digits(2)
+                    .ok_or_else(|| self.error(start, "invalid hexadecimal escape"))?;
+                result.push(char::from_u32(value).unwrap_or('\u{FFFD}'));
+            }
+            'u' => {
+                let value = self.unicode_escape(start)?;
+                // サロゲートペアは1文字にまとめる。対になっていないサロゲートは置換文字にする
+                if (0xD800..0xDC00).contains(&value) && self.starts_with("\\u") {
+                    let (pos, line, column) = (self.pos, self.line, self.column);
+                    self.advance();
+                    self.advance();
+                    if let Ok(low @ 0xDC00..=0xDFFF) = self.unicode_escape(start) {
+                        let code = 0x10000 + ((value - 0xD800) << 10) + (low - 0xDC00);
+                        result.push(char::from_u32(code).unwrap_or('\u{FFFD}'));
+                        return Ok(());
+                    }
+                    self.pos = pos;
+                    self.line = line;
+                    self.column = column;
+                }
+                result.push(char::from_u32(value).unwrap_or('\u{FFFD}'));
             }
+            '0' if !self.peek_char(0).map_or(false, |c| c.is_ascii_digit()) => result.push('\0'),
+            '0'..='7' if !in_template => {
+                // 8進数のエスケープ (Annex B.1.2)
+                let mut value = c.to_digit(8).unwrap_or(0);
+                let max_digits = if c <= '3' { 3 } else { 2 };
+                for _ in 1..max_digits {
+                    match self.peek_char(0).and_then(|c| c.to_digit(8)) {
+                        Some(d) => {
+                            value = value * 8 + d;
+                            self.advance();
+                        }
+                        None => break,
+                    }
+                }
+                result.push(char::from_u32(value).unwrap_or('\u{FFFD}'));
+            }
+            // `\8`と`\9`はその文字を表す (Annex B.1.2)
+            '8' | '9' if !in_template => result.push(c),
+            '0'..='9' => return Err(self.error(start, "invalid escape sequence")),
+            // 行の継続は何も表さない
+            '\r' => {
+                if self.peek_char(0) == Some('\n') {
+                    self.advance();
+                }
+            }
+            '\n' | '\u{2028}' | '\u{2029}' => {}
+            _ => result.push(c),
         }
 
-        None
+        Ok(())
     }
 
-    fn consume_string(&mut self) -> String {
+    fn consume_string(&mut self, start: Position) -> Result<String, Error> {
+        let quote = self.advance();
         let mut result = String::new();
-        self.pos += 1;
 
         loop {
-            if self.pos >= self.input.len() {
-                return result;
+            match self.peek_char(0) {
+                None => return Err(self.error(start, "unterminated string literal")),
+                Some('\\') => {
+                    self.advance();
+                    self.consume_escape(&mut result, false)?;
+                }
+                // U+2028とU+2029は文字列の中に書ける
+                Some('\n') | Some('\r') => {
+                    return Err(self.error(start, "unterminated string literal"))
+                }
+                Some(c) => {
+                    self.advance();
+                    if Some(c) == quote {
+                        return Ok(result);
+                    }
+                    result.push(c);
+                }
             }
+        }
+    }
 
-            if self.input[self.pos] == '"' {
-                self.pos += 1;
-                return result;
-            }
+    /// `` ` ``か`}`のあとから、`` ` ``か`${`までを読む
+    fn consume_template(&mut self, start: Position, head: bool) -> Result<Token, Error> {
+        let mut result = String::new();
 
-            result.push(self.input[self.pos]);
-            self.pos += 1;
+        loop {
+            match self.advance() {
+                None => return Err(self.error(start, "unterminated template literal")),
+                Some('`') => {
+                    return Ok(if head {
+                        Token::NoSubstitutionTemplate(result)
+                    } else {
+                        Token::TemplateTail(result)
+                    });
+                }
+                Some('$') if self.peek_char(0) == Some('{') => {
+                    self.advance();
+                    self.braces.push(true);
+                    return Ok(if head {
+                        Token::TemplateHead(result)
+                    } else {
+                        Token::TemplateMiddle(result)
+                    });
+                }
+                Some('\\') => self.consume_escape(&mut result, true)?,
+                // テンプレートの中の改行はLFにそろえる
+                Some('\r') => {
+                    if self.peek_char(0) == Some('\n') {
+                        self.advance();
+                    }
+                    result.push('\n');
+                }
+                Some(c) => result.push(c),
+            }
         }
     }
 
-    fn consume_identifier(&mut self) -> String {
+    fn consume_identifier(&mut self, start: Position) -> Result<(String, bool), Error> {
         let mut result = String::new();
+        let mut escaped = false;
 
-        loop {
-            if self.pos >= self.input.len() {
-                return result;
+        while let Some(c) = self.peek_char(0) {
+            let c = if c == '\\' {
+                let escape_start = self.current_position();
+                self.advance();
+                if self.advance() != Some('u') {
+                    return Err(self.error(escape_start, "invalid identifier escape"));
+                }
+                escaped = true;
+                match char::from_u32(self.unicode_escape(escape_start)?) {
+                    Some(c) => c,
+                    None => return Err(self.error(escape_start, "invalid identifier escape")),
+                }
+            } else if is_identifier_part(c) {
+                self.advance();
+                c
+            } else {
+                break;
+            };
+
+            let valid = if result.is_empty() {
+                is_identifier_start(c)
+            } else {
+                is_identifier_part(c)
+            };
+            if !valid {
+                return Err(self.error(start, "invalid identifier"));
+            }
+            result.push(c);
+        }
+
+        Ok((result, escaped))
+    }
+
+    fn consume_digits(&mut self, radix: u32) -> String {
+        let mut digits = String::new();
+        while let Some(c) = self.peek_char(0) {
+            if !c.is_digit(radix) {
+                break;
             }
+            digits.push(c);
+            self.advance();
+        }
+        digits
+    }
+
+    fn consume_number(&mut self, start: Position) -> Result<Token, Error> {
+        let radix = match (self.peek_char(0), self.peek_char(1)) {
+            (Some('0'), Some('x' | 'X')) => 16,
+            (Some('0'), Some('o' | 'O')) => 8,
+            (Some('0'), Some('b' | 'B')) => 2,
+            _ => 10,
+        };
+
+        let token = if radix != 10 {
+            self.advance();
+            self.advance();
+            let digits = self.consume_digits(radix);
+            if digits.is_empty() {
+                return Err(self.error(start, "missing digits in numeric literal"));
+            }
+            let value = digits.chars().fold(0.0, |value, c| {
+                value * radix as f64 + c.to_digit(radix).unwrap_or(0) as f64
+            });
+            if self.peek_char(0) == Some('n') {
+                self.advance();
+                Token::BigInt(format!("{}", value))
+            } else {
+                Token::Number(value)
+            }
+        } else {
+            let mut text = self.consume_digits(10);
 
-            if self.input[self.pos].is_ascii_alphanumeric() || self.input[self.pos] == '$' {
-                result.push(self.input[self.pos]);
-                self.pos += 1;
+            // 0から始まる数字だけの列は8進数 (Annex B.1.1)
+            let legacy = text.len() > 1 && text.starts_with('0');
+            if legacy && text.chars().all(|c| c.is_digit(8)) {
+                let value = text.chars().fold(0.0, |value, c| {
+                    value * 8.0 + c.to_digit(8).unwrap_or(0) as f64
+                });
+                Token::Number(value)
+            } else if self.peek_char(0) == Some('n') && !legacy {
+                self.advance();
+                Token::BigInt(text)
             } else {
-                return result;
+                if self.peek_char(0) == Some('.') {
+                    self.advance();
+                    text.push('.');
+                    text.push_str(&self.consume_digits(10));
+                }
+                if let Some('e' | 'E') = self.peek_char(0) {
+                    self.advance();
+                    text.push('e');
+                    if let Some(sign @ ('+' | '-')) = self.peek_char(0) {
+                        self.advance();
+                        text.push(sign);
+                    }
+                    let exponent = self.consume_digits(10);
+                    if exponent.is_empty() {
+                        return Err(self.error(start, "missing exponent in numeric literal"));
+                    }
+                    text.push_str(&exponent);
+                }
+                if text.starts_with('.') {
+                    text.insert(0, '0');
+                }
+                match text.parse::<f64>() {
+                    Ok(value) => Token::Number(value),
+                    Err(_) => return Err(self.error(start, "invalid numeric literal")),
+                }
+            }
+        };
+
+        // `3in`のように数値の直後に識別子や数字は続けられない
+        if let Some(c) = self.peek_char(0) {
+            if is_identifier_start(c) || c.is_ascii_digit() || c == '\\' {
+                return Err(self.error(start, "identifier starts immediately after number"));
             }
         }
+
+        Ok(token)
     }
 
-    fn consume_number(&mut self) -> u64 {
-        let mut num = 0;
+    fn consume_regular_expression(&mut self, start: Position) -> Result<Token, Error> {
+        // 最初の`/`
+        self.advance();
+        let mut pattern = String::new();
+        let mut in_class = false;
+
         loop {
-            if self.pos >= self.input.len() {
-                return num;
+            let c = match self.advance() {
+                Some(c) if !is_line_terminator(c) => c,
+                _ => return Err(self.error(start, "unterminated regular expression")),
+            };
+            match c {
+                '/' if !in_class => break,
+                '[' => in_class = true,
+                ']' => in_class = false,
+                '\\' => {
+                    pattern.push(c);
+                    match self.advance() {
+                        Some(c) if !is_line_terminator(c) => pattern.push(c),
+                        _ => return Err(self.error(start, "unterminated regular expression")),
+                    }
+                    continue;
+                }
+                _ => {}
+            }
+            pattern.push(c);
+        }
+
+        let mut flags = String::new();
+        while let Some(c) = self.peek_char(0) {
+            if !is_identifier_part(c) {
+                break;
             }
+            flags.push(c);
+            self.advance();
+        }
 
-            let c = self.input[self.pos];
+        Ok(Token::RegularExpression { pattern, flags })
+    }
 
-            match c {
-                '0'..='9' => {
-                    num = num * 10 + (c.to_digit(10).unwrap() as u64);
-                    self.pos += 1;
+    fn consume_punctuator(&mut self, start: Position) -> Result<Token, Error> {
+        for punctuator in PUNCTUATORS {
+            if !self.starts_with(punctuator) {
+                continue;
+            }
+            // `a?.5:0`は条件演算子
+            if punctuator == "?." && self.peek_char(2).map_or(false, |c| c.is_ascii_digit()) {
+                continue;
+            }
+            for _ in 0..punctuator.len() {
+                self.advance();
+            }
+            match punctuator {
+                "{" => self.braces.push(false),
+                "}" => {
+                    self.braces.pop();
                 }
-                _ => break,
+                _ => {}
             }
+            return Ok(Token::Punctuator(punctuator));
         }
 
-        return num;
+        let c = self.peek_char(0).unwrap_or(' ');
+        Err(self.error(start, &format!("unexpected character {:?}", c)))
     }
-}
 
-impl Iterator for JsLexer {
-    type Item = Token;
+    fn consume_token(&mut self, start: Position) -> Result<Token, Error> {
+        let c = match self.peek_char(0) {
+            Some(c) => c,
+            None => return Err(self.error(start, "unexpected end of input")),
+        };
 
-    fn next(&mut self) -> Option<Self::Item> {
-        if self.pos >= self.input.len() {
-            return None;
+        if is_identifier_start(c) || c == '\\' {
+            let (name, escaped) = self.consume_identifier(start)?;
+            if !escaped {
+                if let Some(word) = RESERVED_WORDS.iter().find(|w| **w == name) {
+                    return Ok(Token::Keyword(word));
+                }
+            }
+            return Ok(Token::Identifier(name));
         }
 
-        while self.input[self.pos] == ' ' || self.input[self.pos] == '\n' {
-            self.pos += 1;
-
-            if self.pos >= self.input.len() {
-                return None;
+        match c {
+            '0'..='9' => self.consume_number(start),
+            '.' if self.peek_char(1).map_or(false, |c| c.is_ascii_digit()) => {
+                self.consume_number(start)
+            }
+            '"' | '\'' => Ok(Token::StringLiteral(self.consume_string(start)?)),
+            '`' => {
+                self.advance();
+                self.consume_template(start, true)
+            }
+            '}' if self.braces.last() == Some(&true) => {
+                self.advance();
+                self.braces.pop();
+                self.consume_template(start, false)
             }
+            '/' if self.regex_allowed => self.consume_regular_expression(start),
+            _ => self.consume_punctuator(start),
         }
+    }
 
-        if let Some(keyword) = self.check_reserved_word() {
-            self.pos += keyword.len();
-            let token = Some(Token::Keyword(keyword));
-            return token;
+    fn next_lexeme(&mut self) -> Result<Option<Lexeme>, Error> {
+        let newline_before = self.skip_whitespace_and_comments()?;
+        if self.pos >= self.input.len() {
+            return Ok(None);
         }
 
-        let c = self.input[self.pos];
+        let position = self.current_position();
+        let token = self.consume_token(position)?;
 
-        let token = match c {
-            '+' | '-' | '=' | ';' | '(' | ')' | '{' | '}' | ',' | '.' => {
-                let t = Token::Punctuator(c);
-                self.pos += 1;
-                t
-            }
-            '0'..='9' => Token::Number(self.consume_number()),
-            'a'..='z' | 'A'..='Z' | '_' | '$' => Token::Identifier(self.consume_identifier()),
-            '"' => Token::StringLiteral(self.consume_string()),
-            _ => unimplemented!("char: {:?} is not supported", c),
+        // 式が終わったところでは`/`は除算になる
+        self.regex_allowed = match &token {
+            Token::Punctuator(p) => !matches!(*p, ")" | "]" | "++" | "--"),
+            Token::Keyword(k) => !matches!(*k, "this" | "super" | "true" | "false" | "null"),
+            Token::TemplateHead(_) | Token::TemplateMiddle(_) => true,
+            _ => false,
         };
 
-        Some(token)
+        Ok(Some(Lexeme::new(token, position, newline_before)))
+    }
+}
+
+impl Iterator for JsLexer {
+    type Item = Result<Lexeme, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.finished {
+            return None;
+        }
+
+        match self.next_lexeme() {
+            Ok(Some(lexeme)) => Some(Ok(lexeme)),
+            Ok(None) => {
+                self.finished = true;
+                None
+            }
+            Err(e) => {
+                self.finished = true;
+                Some(Err(e))
+            }
+        }
     }
 }
 
@@ -153,6 +713,41 @@ impl Iterator for JsLexer {
 mod tests {
     use super::*;
     use alloc::string::ToString;
+    use alloc::vec;
+
+    fn tokens(input: &str) -> Vec<Token> {
+        JsLexer::new(input.to_string())
+            .map(|lexeme| lexeme.expect("failed to tokenize").into_token())
+            .collect()
+    }
+
+    fn punctuators(input: &str) -> Vec<&'static str> {
+        tokens(input)
+            .into_iter()
+            .map(|token| match token {
+                Token::Punctuator(p) => p,
+                _ => panic!("{:?} is not a punctuator", token),
+            })
+            .collect()
+    }
+
+    fn string(input: &str) -> String {
+        match tokens(input).as_slice() {
+            [Token::StringLiteral(s)] => s.clone(),
+            t => panic!("{:?} is not a string literal", t),
+        }
+    }
+
+    fn number(input: &str) -> f64 {
+        match tokens(input).as_slice() {
+            [Token::Number(n)] => *n,
+            t => panic!("{:?} is not a number", t),
+        }
+    }
+
+    fn has_error(input: &str) -> bool {
+        JsLexer::new(input.to_string()).any(|lexeme| lexeme.is_err())
+    }
 
     #[test]
     fn test_imput() {
@@ -163,116 +758,304 @@ mod tests {
 
     #[test]
     fn test_num() {
-        let input = "42".to_string();
-        let mut lexer = JsLexer::new(input).peekable();
-        let expected = [Token::Number(42)].to_vec();
-        let mut i = 0;
-        while lexer.peek().is_some() {
-            assert_eq!(Some(expected[i].clone()), lexer.next());
-            i += 1;
-        }
-        assert!(lexer.peek().is_none());
+        assert_eq!(vec![Token::Number(42.0)], tokens("42"));
     }
 
     #[test]
     fn test_add_nums() {
-        let input = "1 + 2".to_string();
-        let mut lexer = JsLexer::new(input).peekable();
-        let expected = [Token::Number(1), Token::Punctuator('+'), Token::Number(2)].to_vec();
-        let mut i = 0;
-        while lexer.peek().is_some() {
-            assert_eq!(Some(expected[i].clone()), lexer.next());
-            i += 1;
-        }
-        assert!(lexer.peek().is_none());
+        let expected = vec![
+            Token::Number(1.0),
+            Token::Punctuator("+"),
+            Token::Number(2.0),
+        ];
+        assert_eq!(expected, tokens("1 + 2"));
     }
 
     #[test]
     fn test_assign_variable() {
-        let input = "var foo=\"bar\";".to_string();
-        let mut lexer = JsLexer::new(input).peekable();
-        let expected = [
-            Token::Keyword("var".to_string()),
+        let expected = vec![
+            Token::Keyword("var"),
             Token::Identifier("foo".to_string()),
-            Token::Punctuator('='),
+            Token::Punctuator("="),
             Token::StringLiteral("bar".to_string()),
-            Token::Punctuator(';'),
-        ]
-        .to_vec();
-        let mut i = 0;
-        while lexer.peek().is_some() {
-            assert_eq!(Some(expected[i].clone()), lexer.next());
-            i += 1;
-        }
-
-        assert!(lexer.peek().is_none());
+            Token::Punctuator(";"),
+        ];
+        assert_eq!(expected, tokens("var foo=\"bar\";"));
     }
 
     #[test]
     fn test_add_variable_and_num() {
-        let input = "var foo=42; var result=foo+1;".to_string();
-        let mut lexer = JsLexer::new(input).peekable();
-        let expected = [
-            Token::Keyword("var".to_string()),
+        let expected = vec![
+            Token::Keyword("var"),
             Token::Identifier("foo".to_string()),
-            Token::Punctuator('='),
-            Token::Number(42),
-            Token::Punctuator(';'),
-            Token::Keyword("var".to_string()),
+            Token::Punctuator("="),
+            Token::Number(42.0),
+            Token::Punctuator(";"),
+            Token::Keyword("var"),
             Token::Identifier("result".to_string()),
-            Token::Punctuator('='),
+            Token::Punctuator("="),
             Token::Identifier("foo".to_string()),
-            Token::Punctuator('+'),
-            Token::Number(1),
-            Token::Punctuator(';'),
-        ]
-        .to_vec();
-        let mut i = 0;
-        while lexer.peek().is_some() {
-            assert_eq!(Some(expected[i].clone()), lexer.next());
-            i += 1;
-        }
-
-        assert!(lexer.peek().is_none());
+            Token::Punctuator("+"),
+            Token::Number(1.0),
+            Token::Punctuator(";"),
+        ];
+        assert_eq!(expected, tokens("var foo=42; var result=foo+1;"));
     }
 
     #[test]
     fn test_add_local_variable_and_num() {
-        let input = "function foo() { var a=42; return a; } var result = foo() + 1".to_string();
-        let mut lexer = JsLexer::new(input).peekable();
-
-        let expected = [
-            Token::Keyword("function".to_string()),
+        let input = "function foo() { var a=42; return a; } var result = foo() + 1";
+        let expected = vec![
+            Token::Keyword("function"),
             Token::Identifier("foo".to_string()),
-            Token::Punctuator('('),
-            Token::Punctuator(')'),
-            Token::Punctuator('{'),
-            Token::Keyword("var".to_string()),
+            Token::Punctuator("("),
+            Token::Punctuator(")"),
+            Token::Punctuator("{"),
+            Token::Keyword("var"),
             Token::Identifier("a".to_string()),
-            Token::Punctuator('='),
-            Token::Number(42),
-            Token::Punctuator(';'),
-            Token::Keyword("return".to_string()),
+            Token::Punctuator("="),
+            Token::Number(42.0),
+            Token::Punctuator(";"),
+            Token::Keyword("return"),
             Token::Identifier("a".to_string()),
-            Token::Punctuator(';'),
-            Token::Punctuator('}'),
-            Token::Keyword("var".to_string()),
+            Token::Punctuator(";"),
+            Token::Punctuator("}"),
+            Token::Keyword("var"),
             Token::Identifier("result".to_string()),
-            Token::Punctuator('='),
+            Token::Punctuator("="),
             Token::Identifier("foo".to_string()),
-            Token::Punctuator('('),
-            Token::Punctuator(')'),
-            Token::Punctuator('+'),
-            Token::Number(1),
-            Token::Punctuator(';'),
-        ]
-        .to_vec();
-
-        let mut i = 0;
-        while lexer.peek().is_some() {
-            assert_eq!(Some(expected[i].clone()), lexer.next());
-            i += 1;
-        }
-        assert!(lexer.peek().is_none());
+            Token::Punctuator("("),
+            Token::Punctuator(")"),
+            Token::Punctuator("+"),
+            Token::Number(1.0),
+        ];
+        assert_eq!(expected, tokens(input));
+    }
+
+    #[test]
+    fn test_keyword_prefix() {
+        let expected = vec![
+            Token::Identifier("variable".to_string()),
+            Token::Identifier("returned".to_string()),
+            Token::Identifier("let".to_string()),
+            Token::Keyword("in"),
+            Token::Identifier("$_a1".to_string()),
+        ];
+        assert_eq!(expected, tokens("variable returned let in $_a1"));
+    }
+
+    #[test]
+    fn test_punctuators() {
+        assert_eq!(vec!["===", "!", "!"], punctuators("===!!"));
+        assert_eq!(
+            vec![">>>=", ">>>", ">>", ">", "**=", "**", "*", "...", "."],
+            punctuators(">>>= >>> >> > **= ** * ... .")
+        );
+        assert_eq!(
+            vec!["?.", "??", "?", "=>", "!==", "&&", "||", "<<=", "%", "~", "^="],
+            punctuators("?. ?? ? => !== && || <<= % ~ ^=")
+        );
+        // `?.`の直後が数字なら条件演算子と数値
+        assert_eq!(
+            vec![
+                Token::Identifier("a".to_string()),
+                Token::Punctuator("?"),
+                Token::Number(0.5),
+                Token::Punctuator(":"),
+                Token::Number(0.0),
+            ],
+            tokens("a?.5:0")
+        );
+    }
+
+    #[test]
+    fn test_numbers() {
+        assert_eq!(3.25, number("3.25"));
+        assert_eq!(0.5, number(".5"));
+        assert_eq!(1.0, number("1."));
+        assert_eq!(1500.0, number("1.5e3"));
+        assert_eq!(0.025, number("2.5E-2"));
+        assert_eq!(255.0, number("0xFf"));
+        assert_eq!(8.0, number("0o10"));
+        assert_eq!(5.0, number("0b101"));
+        assert_eq!(15.0, number("017"));
+        assert_eq!(19.0, number("019"));
+        assert_eq!(vec![Token::BigInt("123".to_string())], tokens("123n"));
+        assert_eq!(vec![Token::BigInt("255".to_string())], tokens("0xffn"));
+        assert!(has_error("3in"));
+        assert!(has_error("1e"));
+        assert!(has_error("0x"));
+    }
+
+    #[test]
+    fn test_member_of_number() {
+        let expected = vec![
+            Token::Number(1.0),
+            Token::Punctuator("."),
+            Token::Identifier("toString".to_string()),
+        ];
+        assert_eq!(expected, tokens("1..toString"));
+    }
+
+    #[test]
+    fn test_strings() {
+        assert_eq!("it's", string("'it\\'s'"));
+        assert_eq!("a\"b", string("\"a\\\"b\""));
+        assert_eq!(
+            "\t\n\r\u{8}\u{C}\u{B}\0\\",
+            string("'\\t\\n\\r\\b\\f\\v\\0\\\\'")
+        );
+        assert_eq!("AB", string("'\\x41\\u0042'"));
+        assert_eq!("\u{1F600}", string("'\\u{1F600}'"));
+        assert_eq!("\u{1F600}", string("'\\uD83D\\uDE00'"));
+        assert_eq!("\u{FFFD}x", string("'\\uD83Dx'"));
+        assert_eq!("ab", string("'a\\\nb'"));
+        assert_eq!("\u{1}\u{3F}", string("'\\1\\77'"));
+        assert_eq!("q", string("'\\q'"));
+        assert!(has_error("'abc"));
+        assert!(has_error("'a\nb'"));
+        assert!(has_error("'\\x4'"));
+        assert!(has_error("'\\u{110000}'"));
+        assert_eq!("8", string("'\\8'"));
+        assert!(has_error("`\\8`"));
+    }
+
+    #[test]
+    fn test_comments() {
+        let input = "a // line\n/* block\n */ b /**/ c <!-- html\n--> html\nd";
+        let expected = vec![
+            Token::Identifier("a".to_string()),
+            Token::Identifier("b".to_string()),
+            Token::Identifier("c".to_string()),
+            Token::Identifier("d".to_string()),
+        ];
+        assert_eq!(expected, tokens(input));
+        assert!(has_error("a /* open"));
+    }
+
+    #[test]
+    fn test_template() {
+        let expected = vec![
+            Token::TemplateHead("a".to_string()),
+            Token::Identifier("x".to_string()),
+            Token::TemplateMiddle("b".to_string()),
+            Token::Punctuator("{"),
+            Token::Punctuator("}"),
+            Token::TemplateTail("c\n\u{41}".to_string()),
+        ];
+        assert_eq!(expected, tokens("`a${x}b${{}}c\r\n\\u0041`"));
+        assert_eq!(
+            vec![Token::NoSubstitutionTemplate("line\nnext".to_string())],
+            tokens("`line\nnext`")
+        );
+
+        // 置換の中の入れ子のテンプレート
+        let expected = vec![
+            Token::TemplateHead("".to_string()),
+            Token::NoSubstitutionTemplate("in".to_string()),
+            Token::TemplateTail("".to_string()),
+        ];
+        assert_eq!(expected, tokens("`${`in`}`"));
+        assert!(has_error("`abc"));
+        assert!(has_error("`\\1`"));
+    }
+
+    #[test]
+    fn test_regular_expression() {
+        let regex = |pattern: &str, flags: &str| Token::RegularExpression {
+            pattern: pattern.to_string(),
+            flags: flags.to_string(),
+        };
+
+        let expected = vec![
+            Token::Identifier("x".to_string()),
+            Token::Punctuator("="),
+            regex("a[/]\\/b", "gi"),
+            Token::Punctuator(";"),
+        ];
+        assert_eq!(expected, tokens("x = /a[/]\\/b/gi;"));
+
+        // 値のあとの`/`は除算
+        let expected = vec![
+            Token::Identifier("a".to_string()),
+            Token::Punctuator("/"),
+            Token::Identifier("b".to_string()),
+            Token::Punctuator("/"),
+            Token::Identifier("g".to_string()),
+        ];
+        assert_eq!(expected, tokens("a / b / g"));
+
+        let expected = vec![
+            Token::Punctuator("("),
+            Token::Number(1.0),
+            Token::Punctuator(")"),
+            Token::Punctuator("/="),
+            Token::Number(2.0),
+        ];
+        assert_eq!(expected, tokens("(1) /= 2"));
+
+        let expected = vec![
+            Token::Keyword("return"),
+            regex("x", ""),
+            Token::Punctuator("."),
+            Token::Identifier("test".to_string()),
+        ];
+        assert_eq!(expected, tokens("return /x/.test"));
+        assert!(has_error("/abc\n/"));
+    }
+
+    #[test]
+    fn test_positions_and_newlines() {
+        let input = "var a\r\n  = 1; /* \n */ b\u{2028}c";
+        let lexemes: Vec<Lexeme> = JsLexer::new(input.to_string())
+            .map(|lexeme| lexeme.expect("failed to tokenize"))
+            .collect();
+        let summary: Vec<(usize, usize, bool)> = lexemes
+            .iter()
+            .map(|l| {
+                (
+                    l.position().line(),
+                    l.position().column(),
+                    l.newline_before(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            vec![
+                (1, 1, false),
+                (1, 5, false),
+                (2, 3, true),
+                (2, 5, false),
+                (2, 6, false),
+                (3, 5, true),
+                (4, 1, true),
+            ],
+            summary
+        );
+    }
+
+    #[test]
+    fn test_unicode_identifiers() {
+        let expected = vec![
+            Token::Identifier("café".to_string()),
+            Token::Identifier("変数".to_string()),
+            Token::Identifier("ab".to_string()),
+            Token::Identifier("var".to_string()),
+        ];
+        assert_eq!(expected, tokens("café 変数 a\\u0062 v\\u{61}r"));
+        assert!(has_error("\\u0031"));
+    }
+
+    #[test]
+    fn test_unexpected_character() {
+        let mut lexer = JsLexer::new("a # b".to_string());
+        assert!(lexer.next().expect("a").is_ok());
+        assert_eq!(
+            Some(Err(Error::UnexpectedInput(
+                "1:3: unexpected character '#'".to_string()
+            ))),
+            lexer.next()
+        );
+        assert_eq!(None, lexer.next());
     }
 }
@@ -0,0 +1,390 @@
+//! ECMAScriptの言語の値と、ユーザーのコードを呼ばずにできる値の変換
+//! https://262.ecma-international.org/11.0/#sec-ecmascript-language-types
+
+use crate::renderer::js::heap::Heap;
+use crate::renderer::js::heap::ObjectId;
+use crate::renderer::js::math::trunc;
+use crate::renderer::js::token::is_line_terminator;
+use crate::renderer::js::token::is_whitespace;
+use alloc::format;
+use alloc::rc::Rc;
+use alloc::string::String;
+use alloc::string::ToString;
+use core::cmp::Ordering;
+use core::fmt::Display;
+use core::fmt::Formatter;
+
+/// 作るたびに他のどのシンボルとも異なる値になる
+#[derive(Debug, Clone)]
+pub struct Symbol {
+    description: Rc<Option<String>>,
+}
+
+impl Symbol {
+    pub fn new(description: Option<String>) -> Self {
+        Self {
+            description: Rc::new(description),
+        }
+    }
+
+    pub fn description(&self) -> Option<String> {
+        (*self.description).clone()
+    }
+}
+
+impl PartialEq for Symbol {
+    fn eq(&self, other: &Self) -> bool {
+        Rc::ptr_eq(&self.description, &other.description)
+    }
+}
+
+impl Eq for Symbol {}
+
+/// プロパティの表の索引に使う。順序に意味はなく、同じシンボルが同じ位置に並べばよい
+impl Ord for Symbol {
+    fn cmp(&self, other: &Self) -> Ordering {
+        Rc::as_ptr(&self.description).cmp(&Rc::as_ptr(&other.description))
+    }
+}
+
+impl PartialOrd for Symbol {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum RuntimeValue {
+    Undefined,
+    Null,
+    Boolean(bool),
+    Number(f64),
+    String(String),
+    Symbol(Symbol),
+    Object(ObjectId),
+}
+
+impl RuntimeValue {
+    /// typeof演算子の結果
+    pub fn type_of(&self, heap: &Heap) -> &'static str {
+        match self {
+            RuntimeValue::Undefined => "undefined",
+            RuntimeValue::Null => "object",
+            RuntimeValue::Boolean(_) => "boolean",
+            RuntimeValue::Number(_) => "number",
+            RuntimeValue::String(_) => "string",
+            RuntimeValue::Symbol(_) => "symbol",
+            RuntimeValue::Object(id) if heap.get(*id).is_callable() => "function",
+            RuntimeValue::Object(_) => "object",
+        }
+    }
+
+    /// undefinedかnull
+    pub fn is_nullish(&self) -> bool {
+        matches!(self, RuntimeValue::Undefined | RuntimeValue::Null)
+    }
+
+    /// ToBoolean
+    /// https://262.ecma-international.org/11.0/#sec-toboolean
+    pub fn to_boolean(&self) -> bool {
+        match self {
+            RuntimeValue::Undefined | RuntimeValue::Null => false,
+            RuntimeValue::Boolean(b) => *b,
+            RuntimeValue::Number(n) => !(*n == 0.0 || n.is_nan()),
+            RuntimeValue::String(s) => !s.is_empty(),
+            RuntimeValue::Symbol(_) | RuntimeValue::Object(_) => true,
+        }
+    }
+
+    /// `===`で比べる。NaNはどの値とも等しくなく、+0と-0は等しい
+    /// https://262.ecma-international.org/11.0/#sec-strict-equality-comparison
+    pub fn strict_equals(&self, other: &RuntimeValue) -> bool {
+        match (self, other) {
+            (RuntimeValue::Undefined, RuntimeValue::Undefined) => true,
+            (RuntimeValue::Null, RuntimeValue::Null) => true,
+            (RuntimeValue::Boolean(a), RuntimeValue::Boolean(b)) => a == b,
+            (RuntimeValue::Number(a), RuntimeValue::Number(b)) => a == b,
+            (RuntimeValue::String(a), RuntimeValue::String(b)) => a == b,
+            (RuntimeValue::Symbol(a), RuntimeValue::Symbol(b)) => a == b,
+            (RuntimeValue::Object(a), RuntimeValue::Object(b)) => a == b,
+            _ => false,
+        }
+    }
+}
+
+impl PartialEq for RuntimeValue {
+    /// SameValueZeroで比べる。`===`と違い、NaN同士は等しい
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (RuntimeValue::Number(a), RuntimeValue::Number(b)) => {
+                a == b || (a.is_nan() && b.is_nan())
+            }
+            _ => self.strict_equals(other),
+        }
+    }
+}
+
+/// プリミティブ値は文字列に変換したものを表示する
+impl Display for RuntimeValue {
+    fn fmt(&self, f: &mut Formatter) -> core::fmt::Result {
+        match self {
+            RuntimeValue::Undefined => write!(f, "undefined"),
+            RuntimeValue::Null => write!(f, "null"),
+            RuntimeValue::Boolean(b) => write!(f, "{}", b),
+            RuntimeValue::Number(n) => write!(f, "{}", number_to_string(*n)),
+            RuntimeValue::String(s) => write!(f, "{}", s),
+            RuntimeValue::Symbol(s) => {
+                write!(f, "Symbol({})", s.description().unwrap_or_default())
+            }
+            RuntimeValue::Object(_) => write!(f, "[object Object]"),
+        }
+    }
+}
+
+/// Number::toString
+/// https://262.ecma-international.org/11.0/#sec-numeric-types-number-tostring
+pub fn number_to_string(n: f64) -> String {
+    if n.is_nan() {
+        return "NaN".to_string();
+    }
+    if n == 0.0 {
+        return "0".to_string();
+    }
+    if n.is_infinite() {
+        return if n > 0.0 { "Infinity" } else { "-Infinity" }.to_string();
+    }
+    if n < 0.0 {
+        return format!("-{}", number_to_string(-n));
+    }
+
+    // 元の値に戻る最短の桁(digits)と、小数点の位置(point)を求める
+    // n = 0.digits × 10^point
+    let formatted = format!("{:e}", n);
+    let (mantissa, exponent) = formatted.split_once('e').unwrap_or((&formatted, "0"));
+    let digits: String = mantissa.chars().filter(|c| *c != '.').collect();
+    let k = digits.len() as i64;
+    let point = exponent.parse::<i64>().unwrap_or(0) + 1;
+
+    if k <= point && point <= 21 {
+        digits + &"0".repeat((point - k) as usize)
+    } else if 0 < point && point <= 21 {
+        let (int, frac) = digits.split_at(point as usize);
+        format!("{}.{}", int, frac)
+    } else if -6 < point && point <= 0 {
+        format!("0.{}{}", "0".repeat(-point as usize), digits)
+    } else {
+        let e = point - 1;
+        let sign = if e < 0 { '-' } else { '+' };
+        let (first, rest) = digits.split_at(1);
+        if rest.is_empty() {
+            format!("{}e{}{}", first, sign, e.abs())
+        } else {
+            format!("{}.{}e{}{}", first, rest, sign, e.abs())
+        }
+    }
+}
+
+/// 符号、整数部、小数部、指数部からなる10進数の文字列かどうか
+fn is_decimal_literal(s: &str) -> bool {
+    let bytes = s.as_bytes();
+    let mut i = 0;
+    let count_digits = |i: &mut usize| {
+        let start = *i;
+        while *i < bytes.len() && bytes[*i].is_ascii_digit() {
+            *i += 1;
+        }
+        *i - start
+    };
+
+    let mut digits = count_digits(&mut i);
+    if i < bytes.len() && bytes[i] == b'.' {
+        i += 1;
+        digits += count_digits(&mut i);
+    }
+    if digits == 0 {
+        return false;
+    }
+    if i < bytes.len() && (bytes[i] == b'e' || bytes[i] == b'E') {
+        i += 1;
+        if i < bytes.len() && (bytes[i] == b'+' || bytes[i] == b'-') {
+            i += 1;
+        }
+        if count_digits(&mut i) == 0 {
+            return false;
+        }
+    }
+    i == bytes.len()
+}
+
+/// 文字列を数値に変換する。数値として読めない場合はNaN
+/// https://262.ecma-international.org/11.0/#sec-tonumber-applied-to-the-string-type
+pub fn string_to_number(s: &str) -> f64 {
+    let s = s.trim_matches(|c| is_whitespace(c) || is_line_terminator(c));
+    if s.is_empty() {
+        return 0.0;
+    }
+
+    for (prefix, radix) in [("0x", 16), ("0o", 8), ("0b", 2)] {
+        let digits = match s.get(..2) {
+            Some(p) if p.eq_ignore_ascii_case(prefix) => &s[2..],
+            _ => continue,
+        };
+        if digits.is_empty() {
+            return f64::NAN;
+        }
+        let mut value = 0.0;
+        for c in digits.chars() {
+            match c.to_digit(radix) {
+                Some(d) => value = value * radix as f64 + d as f64,
+                None => return f64::NAN,
+            }
+        }
+        return value;
+    }
+
+    let negative = s.starts_with('-');
+    let unsigned = s
+        .strip_prefix('-')
+        .or_else(|| s.strip_prefix('+'))
+        .unwrap_or(s);
+    if unsigned == "Infinity" {
+        return if negative {
+            f64::NEG_INFINITY
+        } else {
+            f64::INFINITY
+        };
+    }
+    // Rustのparseが受け付ける"inf"や"nan"は数値ではない
+    if !is_decimal_literal(unsigned) {
+        return f64::NAN;
+    }
+    s.parse::<f64>().unwrap_or(f64::NAN)
+}
+
+/// ToUint32
+/// https://262.ecma-international.org/11.0/#sec-touint32
+pub fn to_uint32(n: f64) -> u32 {
+    if !n.is_finite() || n == 0.0 {
+        return 0;
+    }
+    let modulo = trunc(n) % 4294967296.0;
+    if modulo < 0.0 {
+        (modulo + 4294967296.0) as u32
+    } else {
+        modulo as u32
+    }
+}
+
+/// ToInt32
+/// https://262.ecma-international.org/11.0/#sec-toint32
+pub fn to_int32(n: f64) -> i32 {
+    to_uint32(n) as i32
+}
+
+/// 文字列の長さ。UTF-16のコード単位で数える
+pub fn string_length(s: &str) -> usize {
+    s.encode_utf16().count()
+}
+
+/// 文字列の`index`番目のコード単位を1文字の文字列にする
+pub fn string_code_unit_at(s: &str, index: usize) -> Option<String> {
+    s.encode_utf16()
+        .nth(index)
+        .map(|unit| String::from_utf16_lossy(&[unit]))
+}
+
+/// 文字列をUTF-16のコード単位の列として辞書順で比べる
+pub fn string_less_than(a: &str, b: &str) -> bool {
+    a.encode_utf16().lt(b.encode_utf16())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_number_to_string() {
+        let cases = [
+            (0.0, "0"),
+            (-0.0, "0"),
+            (42.0, "42"),
+            (-1.5, "-1.5"),
+            (0.1, "0.1"),
+            (0.1 + 0.2, "0.30000000000000004"),
+            (1e21, "1e+21"),
+            (123456789012345680000.0, "123456789012345680000"),
+            (1.5e-7, "1.5e-7"),
+            (0.000001, "0.000001"),
+            (5e-324, "5e-324"),
+            (f64::MAX, "1.7976931348623157e+308"),
+            (f64::NAN, "NaN"),
+            (f64::NEG_INFINITY, "-Infinity"),
+        ];
+        for (n, expected) in cases {
+            assert_eq!(expected, number_to_string(n));
+        }
+    }
+
+    #[test]
+    fn test_string_to_number() {
+        assert_eq!(0.0, string_to_number(" \n\t"));
+        assert_eq!(12.0, string_to_number(" 12 "));
+        assert_eq!(-0.5, string_to_number("-.5"));
+        assert_eq!(5.0, string_to_number("5."));
+        assert_eq!(1500.0, string_to_number("1.5e3"));
+        assert_eq!(255.0, string_to_number("0xFF"));
+        assert_eq!(5.0, string_to_number("0b101"));
+        assert_eq!(f64::NEG_INFINITY, string_to_number("-Infinity"));
+        for s in ["abc", "1x", "0x", "-0x1", "inf", "NaN", "1e", ".", "1_000"] {
+            assert!(string_to_number(s).is_nan(), "{}", s);
+        }
+    }
+
+    #[test]
+    fn test_integer_conversions() {
+        assert_eq!(-1, to_int32(4294967295.0));
+        assert_eq!(4294967295, to_uint32(-1.0));
+        assert_eq!(1, to_int32(4294967297.5));
+        assert_eq!(0, to_int32(f64::NAN));
+        assert_eq!(-2147483648, to_int32(2147483648.0));
+    }
+
+    #[test]
+    fn test_equality() {
+        let nan = RuntimeValue::Number(f64::NAN);
+        assert!(!nan.strict_equals(&nan));
+        assert_eq!(nan, nan);
+        assert!(RuntimeValue::Number(0.0).strict_equals(&RuntimeValue::Number(-0.0)));
+        assert!(!RuntimeValue::Null.strict_equals(&RuntimeValue::Undefined));
+
+        let a = Symbol::new(Some("a".to_string()));
+        let b = Symbol::new(Some("a".to_string()));
+        assert!(RuntimeValue::Symbol(a.clone()).strict_equals(&RuntimeValue::Symbol(a)));
+        assert_ne!(
+            RuntimeValue::Symbol(b.clone()),
+            RuntimeValue::Symbol(Symbol::new(None))
+        );
+    }
+
+    #[test]
+    fn test_to_boolean_and_type_of() {
+        use crate::renderer::js::object::HostFunction;
+        use crate::renderer::js::object::JsObject;
+        use crate::renderer::js::object::ObjectKind;
+
+        let mut heap = Heap::new();
+        let object = heap.allocate(JsObject::new(ObjectKind::Ordinary, None));
+        let function = heap.allocate(JsObject::new(
+            ObjectKind::HostFunction(HostFunction::GetElementById),
+            None,
+        ));
+
+        assert!(!RuntimeValue::Number(f64::NAN).to_boolean());
+        assert!(!RuntimeValue::String(String::new()).to_boolean());
+        assert!(RuntimeValue::String("0".to_string()).to_boolean());
+        assert!(RuntimeValue::Object(object).to_boolean());
+        assert_eq!("object", RuntimeValue::Null.type_of(&heap));
+        assert_eq!("object", RuntimeValue::Object(object).type_of(&heap));
+        assert_eq!("function", RuntimeValue::Object(function).type_of(&heap));
+    }
+}
@@ -0,0 +1,824 @@
+use crate::font::collection::FontCollection;
+use crate::renderer::layout::computed_style::Length;
+use crate::renderer::layout::computed_style::TextAlign;
+use crate::renderer::layout::flex_layout::layout_flex_container;
+use crate::renderer::layout::float_layout::FloatContext;
+use crate::renderer::layout::inline_layout::place_outside_marker;
+use crate::renderer::layout::inline_layout::InlineFormattingContext;
+use crate::renderer::layout::inline_layout::InlineMeasure;
+use crate::renderer::layout::layout_object::LayoutObject;
+use crate::renderer::layout::layout_object::LayoutObjectKind;
+use crate::renderer::layout::layout_object::LayoutPoint;
+use crate::renderer::layout::layout_object::LayoutSize;
+use crate::renderer::layout::position_layout::apply_relative_offset;
+use crate::renderer::layout::table_layout::layout_table;
+use crate::renderer::layout::table_layout::table_intrinsic_widths;
+use alloc::rc::Rc;
+use alloc::vec::Vec;
+use core::cell::RefCell;
+
+/// 隣接するマージンの集合
+/// 正のマージンは最大値、負のマージンは最小値だけを保持し、その和が相殺後のマージンになる
+/// https://www.w3.org/TR/CSS2/box.html#collapsing-margins
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct MarginStrut {
+    positive: i64,
+    negative: i64,
+}
+
+impl MarginStrut {
+    pub fn new() -> Self {
+        Self {
+            positive: 0,
+            negative: 0,
+        }
+    }
+
+    pub fn append(&mut self, margin: i64) {
+        if margin >= 0 {
+            self.positive = core::cmp::max(self.positive, margin);
+        } else {
+            self.negative = core::cmp::min(self.negative, margin);
+        }
+    }
+
+    pub fn sum(&self) -> i64 {
+        self.positive + self.negative
+    }
+}
+
+/// ブロック方向にボックスを積み重ねていく際の現在位置
+#[derive(Debug, Clone, PartialEq)]
+pub struct BlockCursor {
+    /// 最後に配置したコンテンツの下端(マージンは含まない)
+    y: i64,
+    /// まだ位置が確定していない隣接マージン
+    strut: MarginStrut,
+    /// このカーソルで最初にマージンが確定した位置
+    first_resolved_y: Option<i64>,
+    /// 同じブロック整形コンテキストのボックスで共有するフロート
+    floats: Rc<RefCell<FloatContext>>,
+}
+
+impl BlockCursor {
+    /// 新しいブロック整形コンテキストのカーソル
+    pub fn new(y: i64) -> Self {
+        Self {
+            y,
+            strut: MarginStrut::new(),
+            first_resolved_y: None,
+            floats: Rc::new(RefCell::new(FloatContext::new())),
+        }
+    }
+
+    /// 同じブロック整形コンテキストにある子のボックスのカーソル
+    fn nested(&self, y: i64) -> Self {
+        Self {
+            y,
+            strut: MarginStrut::new(),
+            first_resolved_y: None,
+            floats: self.floats.clone(),
+        }
+    }
+
+    /// マージンを確定させた場合に、次のコンテンツを配置する位置
+    fn hypothetical_y(&self) -> i64 {
+        self.y + self.strut.sum()
+    }
+
+    /// 保留中のマージンを確定させ、次のコンテンツを配置する位置を返す
+    fn resolve(&mut self) -> i64 {
+        let y = self.y + self.strut.sum();
+        self.y = y;
+        self.strut = MarginStrut::new();
+        self.mark_resolved(y);
+        y
+    }
+
+    fn mark_resolved(&mut self, y: i64) {
+        if self.first_resolved_y.is_none() {
+            self.first_resolved_y = Some(y);
+        }
+    }
+
+    pub fn y(&self) -> i64 {
+        self.y
+    }
+}
+
+/// 新しいブロック整形コンテキストを作るボックスを`point`(マージンボックスの左上)に配置する
+pub fn layout_formatting_context_root(
+    node: &Rc<RefCell<LayoutObject>>,
+    point: LayoutPoint,
+    containing_width: i64,
+    fonts: &FontCollection,
+) {
+    let mut cursor = BlockCursor::new(point.y());
+    layout_block_level_box(node, point.x(), containing_width, &mut cursor, fonts);
+}
+
+/// ブロックレベルのボックスを配置する
+/// `cursor`には親のブロック整形コンテキストでの現在位置と保留中のマージンが入っている
+pub fn layout_block_level_box(
+    node: &Rc<RefCell<LayoutObject>>,
+    containing_x: i64,
+    containing_width: i64,
+    cursor: &mut BlockCursor,
+    fonts: &FontCollection,
+) {
+    let (shrink_to_fit, isolated_margins, clear) = {
+        let n = node.borrow();
+        let isolated = n.kind() == LayoutObjectKind::InlineBlock
+            || n.is_float()
+            || n.is_absolutely_positioned();
+        // テーブルの横幅は列の幅から決まる
+        (isolated || n.is_table(), isolated, n.style().clear())
+    };
+    let preferred_width = if shrink_to_fit {
+        max_content_width(node, fonts)
+    } else {
+        0
+    };
+    node.borrow_mut()
+        .resolve_box_model(containing_width, shrink_to_fit, preferred_width);
+
+    let (margin, border, padding, mut width, establishes_bfc, specified_height) = {
+        let n = node.borrow();
+        (
+            n.margin(),
+            n.border(),
+            n.padding(),
+            n.size().width(),
+            n.establishes_bfc(),
+            n.specified_height(),
+        )
+    };
+    let x = containing_x + margin.left;
+    let content_x = x + border.left + padding.left;
+    let content_width = width - border.horizontal() - padding.horizontal();
+
+    // BFCを作るボックスは子のマージンと相殺しない
+    let collapse_top = !establishes_bfc && border.top == 0 && padding.top == 0;
+    let collapse_bottom =
+        !establishes_bfc && border.bottom == 0 && padding.bottom == 0 && specified_height.is_none();
+
+    if isolated_margins {
+        // フロートとインラインブロックのマージンは他のマージンと相殺しない
+        cursor.resolve();
+        cursor.strut.append(margin.top);
+    } else {
+        cursor.strut.append(margin.top);
+    }
+
+    // クリアランス: ボーダーボックスの上端を、避けるフロートの下端まで下げる
+    // https://www.w3.org/TR/CSS2/visuren.html#clearance
+    let clearance = cursor.floats.borrow().clearance(clear);
+    let has_clearance = match clearance {
+        Some(bottom) if bottom > cursor.hypothetical_y() => {
+            cursor.y = bottom;
+            cursor.strut = MarginStrut::new();
+            true
+        }
+        _ => false,
+    };
+
+    let mut top = if collapse_top && !has_clearance {
+        None
+    } else {
+        Some(cursor.resolve())
+    };
+    let mut inner = match top {
+        Some(y) if establishes_bfc => BlockCursor::new(y + border.top + padding.top),
+        Some(y) => cursor.nested(y + border.top + padding.top),
+        // 上のボーダーとパディングがない場合、最初の子のマージンは自身のマージンと相殺する
+        None => BlockCursor {
+            y: cursor.y,
+            strut: cursor.strut,
+            first_resolved_y: None,
+            floats: cursor.floats.clone(),
+        },
+    };
+
+    if node.borrow().is_table() {
+        // 列の最小幅が収まらない場合、テーブルは包含ブロックからはみ出す
+        let (table_width, height) = layout_table(node, content_x, inner.y, content_width, fonts);
+        width = core::cmp::max(
+            width,
+            table_width + border.horizontal() + padding.horizontal(),
+        );
+        inner.y += height;
+    } else if node.borrow().is_flex_container() {
+        let height = layout_flex_container(
+            node,
+            content_x,
+            inner.y,
+            content_width,
+            specified_height,
+            fonts,
+        );
+        inner.y += height;
+    } else {
+        layout_block_children(node, content_x, content_width, &mut inner, fonts);
+    }
+
+    if top.is_none() {
+        match inner.first_resolved_y {
+            Some(y) => {
+                top = Some(y);
+                cursor.strut = MarginStrut::new();
+                cursor.mark_resolved(y);
+            }
+            None => {
+                if collapse_bottom {
+                    // 中身のないボックスは上下のマージンが相殺され、高さを持たない
+                    let y = cursor.y + inner.strut.sum();
+                    node.borrow_mut().set_point(LayoutPoint::new(x, y));
+                    node.borrow_mut().set_size(LayoutSize::new(width, 0));
+                    place_outside_marker(node, content_x, y, fonts);
+                    apply_relative_offset(node, containing_width);
+                    cursor.strut = inner.strut;
+                    cursor.strut.append(margin.bottom);
+                    return;
+                }
+
+                cursor.strut = inner.strut;
+                let y = cursor.resolve();
+                top = Some(y);
+                inner = cursor.nested(y);
+            }
+        }
+    }
+
+    let top = top.expect("top position should be resolved");
+    let content_top = top + border.top + padding.top;
+    let content_height = match specified_height {
+        Some(h) => h,
+        None if collapse_bottom => inner.y - content_top,
+        None => inner.y + inner.strut.sum() - content_top,
+    };
+    // BFCを作るボックスの高さは、中のフロートを含む
+    let content_height = match inner.floats.borrow().bottom() {
+        Some(bottom) if establishes_bfc && specified_height.is_none() => {
+            core::cmp::max(content_height, bottom - content_top)
+        }
+        _ => content_height,
+    };
+    let height = core::cmp::max(content_height, 0) + border.vertical() + padding.vertical();
+
+    node.borrow_mut().set_point(LayoutPoint::new(x, top));
+    node.borrow_mut().set_size(LayoutSize::new(width, height));
+    place_outside_marker(node, content_x, content_top, fonts);
+    // 相対位置指定のずれは、後に続くボックスの配置に影響しない
+    apply_relative_offset(node, containing_width);
+
+    cursor.y = top + height;
+    cursor.strut = if collapse_bottom {
+        // 最後の子の下マージンは自身の下マージンと相殺する
+        inner.strut
+    } else {
+        MarginStrut::new()
+    };
+    cursor.strut.append(margin.bottom);
+    if isolated_margins {
+        cursor.resolve();
+    }
+}
+
+/// ブロックコンテナの子を配置する
+/// ブロックレベルの子の間に連続して並ぶインラインレベルの子は、1つの無名ブロックとして扱う
+fn layout_block_children(
+    node: &Rc<RefCell<LayoutObject>>,
+    content_x: i64,
+    content_width: i64,
+    cursor: &mut BlockCursor,
+    fonts: &FontCollection,
+) {
+    let text_align = node.borrow().style().text_align();
+    let mut inline_run = Vec::new();
+    let mut child = node.borrow().first_child();
+
+    while let Some(c) = child {
+        if c.borrow().is_outside_marker() {
+            // リスト項目を配置した後に、最初の行の左側に置く
+        } else if c.borrow().is_block_level()
+            && !c.borrow().is_float()
+            && !c.borrow().is_absolutely_positioned()
+        {
+            layout_inline_run(
+                &inline_run,
+                content_x,
+                content_width,
+                text_align,
+                cursor,
+                fonts,
+            );
+            inline_run.clear();
+
+            // BFCを作るボックスは、同じBFCのフロートに重ならないように横に並べる
+            let (x, width) = if c.borrow().establishes_bfc() {
+                cursor.floats.borrow().available(
+                    cursor.hypothetical_y(),
+                    1,
+                    content_x,
+                    content_width,
+                )
+            } else {
+                (content_x, content_width)
+            };
+            layout_block_level_box(&c, x, width, cursor, fonts);
+        } else {
+            // フロートはインラインレベルの子と一緒に、行ボックスより先に配置する
+            // 絶対位置指定のボックスは、行ボックスの位置を静的位置として記録する
+            inline_run.push(c.clone());
+        }
+
+        child = c.borrow().next_sibling();
+    }
+
+    layout_inline_run(
+        &inline_run,
+        content_x,
+        content_width,
+        text_align,
+        cursor,
+        fonts,
+    );
+}
+
+/// インラインレベルのボックスを行ボックスに並べる
+/// 行ボックスができない場合は、マージンの相殺を妨げない
+fn layout_inline_run(
+    run: &[Rc<RefCell<LayoutObject>>],
+    content_x: i64,
+    content_width: i64,
+    text_align: TextAlign,
+    cursor: &mut BlockCursor,
+    fonts: &FontCollection,
+) {
+    if run.is_empty() {
+        return;
+    }
+
+    let context = InlineFormattingContext::new(run, content_width, text_align, fonts);
+    if context.is_empty() {
+        // フロートはマージンの相殺に関わらないので、マージンを確定させずに配置する
+        let mut floats = cursor.floats.borrow_mut();
+        context.place_floats(content_x, cursor.hypothetical_y(), &mut floats);
+        context.place_lines(content_x, cursor.y, &floats);
+        return;
+    }
+
+    let top = cursor.resolve();
+    let mut floats = cursor.floats.borrow_mut();
+    context.place_floats(content_x, top, &mut floats);
+    let height = context.place_lines(content_x, top, &floats);
+    cursor.y = top + height;
+}
+
+/// ボックスとその子孫をまとめて移動させる
+pub fn translate_subtree(node: &Rc<RefCell<LayoutObject>>, dx: i64, dy: i64) {
+    if dx == 0 && dy == 0 {
+        return;
+    }
+
+    node.borrow_mut().translate(dx, dy);
+
+    let mut child = node.borrow().first_child();
+    while let Some(c) = child {
+        translate_subtree(&c, dx, dy);
+        child = c.borrow().next_sibling();
+    }
+}
+
+/// 改行しない場合のボックスの横幅(max-content)をマージンを含めて返す
+pub fn max_content_width(node: &Rc<RefCell<LayoutObject>>, fonts: &FontCollection) -> i64 {
+    let kind = node.borrow().kind();
+    match kind {
+        LayoutObjectKind::Text | LayoutObjectKind::Inline => {
+            let mut measure = InlineMeasure::new(fonts);
+            measure_inline(node, fonts, &mut measure, max_content_contribution);
+            measure.max_content()
+        }
+        LayoutObjectKind::Marker => marker_width(node, fonts),
+        LayoutObjectKind::Block | LayoutObjectKind::InlineBlock | LayoutObjectKind::ListItem => {
+            let n = node.borrow();
+            let style = n.style();
+            let font_size = n.font_size_px();
+            let px = |l: Length| l.to_px(0, font_size).unwrap_or(0);
+            let edges = horizontal_edges(&n);
+            if n.is_replaced() {
+                return n.replaced_width(0) + edges;
+            }
+            if let Some(w) = style.width().to_px(0, font_size) {
+                return w + edges;
+            }
+            if n.is_table() {
+                drop(n);
+                return table_intrinsic_widths(node, fonts).1 + edges;
+            }
+
+            // 横に並ぶフレックスアイテムは、幅の和がコンテンツの幅になる
+            if n.is_flex_container() && style.flex_direction().is_row() {
+                let gap = px(style.column_gap());
+                let mut width = 0;
+                let mut count = 0;
+                let mut child = n.first_child();
+                drop(n);
+                while let Some(c) = child {
+                    if !c.borrow().is_absolutely_positioned() {
+                        width += max_content_width(&c, fonts);
+                        count += 1;
+                    }
+                    child = c.borrow().next_sibling();
+                }
+                return width + gap * core::cmp::max(count - 1, 0) + edges;
+            }
+
+            // ブロックレベルの子は縦に、インラインレベルの子は横に並ぶ
+            let mut width = 0;
+            let mut measure = InlineMeasure::new(fonts);
+            let mut child = n.first_child();
+            drop(n);
+            while let Some(c) = child {
+                // フロートは行の内容と横に並ぶ
+                if c.borrow().is_block_level() && !c.borrow().is_float() {
+                    width = core::cmp::max(width, measure.max_content());
+                    measure = InlineMeasure::new(fonts);
+                    width = core::cmp::max(width, max_content_contribution(&c, fonts));
+                } else {
+                    measure_inline(&c, fonts, &mut measure, max_content_contribution);
+                }
+                child = c.borrow().next_sibling();
+            }
+            core::cmp::max(width, measure.max_content()) + edges
+        }
+    }
+}
+
+/// できるだけ改行した場合のボックスの横幅(min-content)をマージンを含めて返す
+pub fn min_content_width(node: &Rc<RefCell<LayoutObject>>, fonts: &FontCollection) -> i64 {
+    let kind = node.borrow().kind();
+    match kind {
+        LayoutObjectKind::Text | LayoutObjectKind::Inline => {
+            let mut measure = InlineMeasure::new(fonts);
+            measure_inline(node, fonts, &mut measure, min_content_contribution);
+            measure.min_content()
+        }
+        LayoutObjectKind::Marker => marker_width(node, fonts),
+        LayoutObjectKind::Block | LayoutObjectKind::InlineBlock | LayoutObjectKind::ListItem => {
+            let n = node.borrow();
+            let edges = horizontal_edges(&n);
+            if n.is_replaced() {
+                return n.replaced_width(0) + edges;
+            }
+            let specified = n.style().width().to_px(0, n.font_size_px()).unwrap_or(0);
+            if n.is_table() {
+                drop(n);
+                let width = table_intrinsic_widths(node, fonts).0;
+                return core::cmp::max(width, specified) + edges;
+            }
+
+            let mut width = specified;
+            let mut measure = InlineMeasure::new(fonts);
+            let mut child = n.first_child();
+            drop(n);
+            while let Some(c) = child {
+                if c.borrow().is_block_level() && !c.borrow().is_float() {
+                    width = core::cmp::max(width, measure.min_content());
+                    measure = InlineMeasure::new(fonts);
+                    width = core::cmp::max(width, min_content_contribution(&c, fonts));
+                } else {
+                    measure_inline(&c, fonts, &mut measure, min_content_contribution);
+                }
+                child = c.borrow().next_sibling();
+            }
+            core::cmp::max(width, measure.min_content()) + edges
+        }
+    }
+}
+
+/// インラインレベルのボックスを、行に並べる要素として`measure`に加える
+/// テキストは行のレイアウトと同じように空白をまとめ、インラインブロックやフロートは`contribution`で測った幅の箱にする
+fn measure_inline(
+    node: &Rc<RefCell<LayoutObject>>,
+    fonts: &FontCollection,
+    measure: &mut InlineMeasure,
+    contribution: fn(&Rc<RefCell<LayoutObject>>, &FontCollection) -> i64,
+) {
+    if node.borrow().is_absolutely_positioned() {
+        return;
+    }
+
+    let kind = node.borrow().kind();
+    match kind {
+        LayoutObjectKind::Text | LayoutObjectKind::Marker => measure.push_text(node),
+        LayoutObjectKind::Inline => {
+            let (left, right) = horizontal_edge_widths(&node.borrow());
+            measure.open_box(node, left);
+            let mut child = node.borrow().first_child();
+            while let Some(c) = child {
+                measure_inline(&c, fonts, measure, contribution);
+                child = c.borrow().next_sibling();
+            }
+            measure.close_box(node, right);
+        }
+        LayoutObjectKind::Block | LayoutObjectKind::InlineBlock | LayoutObjectKind::ListItem => {
+            measure.push_atomic(node, contribution(node, fonts));
+        }
+    }
+}
+
+/// 親のmax-contentに含める子の幅。絶対位置指定のボックスは親の幅に影響しない
+fn max_content_contribution(node: &Rc<RefCell<LayoutObject>>, fonts: &FontCollection) -> i64 {
+    if node.borrow().is_absolutely_positioned() {
+        return 0;
+    }
+    max_content_width(node, fonts)
+}
+
+/// 親のmin-contentに含める子の幅
+fn min_content_contribution(node: &Rc<RefCell<LayoutObject>>, fonts: &FontCollection) -> i64 {
+    if node.borrow().is_absolutely_positioned() {
+        return 0;
+    }
+    min_content_width(node, fonts)
+}
+
+/// 行ボックスに並ぶマーカーの幅
+/// リスト項目の外側に置くマーカーは、リスト項目の幅に影響しない
+fn marker_width(node: &Rc<RefCell<LayoutObject>>, fonts: &FontCollection) -> i64 {
+    let n = node.borrow();
+    match n.marker() {
+        Some(marker) if !n.is_outside_marker() => marker.width(&n, fonts),
+        _ => 0,
+    }
+}
+
+/// 横方向のマージン、ボーダー、パディングの和
+fn horizontal_edges(node: &LayoutObject) -> i64 {
+    let (left, right) = horizontal_edge_widths(node);
+    left + right
+}
+
+/// 左側と右側それぞれの、マージン、ボーダー、パディングの和
+fn horizontal_edge_widths(node: &LayoutObject) -> (i64, i64) {
+    let style = node.style();
+    let font_size = node.font_size_px();
+    let px = |l: Length| l.to_px(0, font_size).unwrap_or(0);
+    (
+        px(style.margin().left) + px(style.padding().left) + px(style.border_width().left),
+        px(style.margin().right) + px(style.padding().right) + px(style.border_width().right),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::renderer::layout::layout_view::LayoutView;
+    use crate::renderer::layout::test_util::create_layout_view;
+    use alloc::string::ToString;
+
+    fn body(view: &LayoutView) -> Rc<RefCell<LayoutObject>> {
+        view.root().expect("root should exist")
+    }
+
+    fn first_child(node: &Rc<RefCell<LayoutObject>>) -> Rc<RefCell<LayoutObject>> {
+        node.borrow()
+            .first_child()
+            .expect("first child should exist")
+    }
+
+    fn next_sibling(node: &Rc<RefCell<LayoutObject>>) -> Rc<RefCell<LayoutObject>> {
+        node.borrow()
+            .next_sibling()
+            .expect("next sibling should exist")
+    }
+
+    #[test]
+    fn test_margin_strut() {
+        let mut strut = MarginStrut::new();
+        strut.append(10);
+        strut.append(30);
+        strut.append(-5);
+        strut.append(-20);
+        assert_eq!(10, strut.sum());
+    }
+
+    #[test]
+    fn test_sibling_margins_collapse() {
+        let html = r#"<html><head><style>
+            .a { margin: 10px 0 20px 0; height: 30px; }
+            .b { margin-top: 15px; height: 40px; }
+            </style></head>
+            <body><div class="a"></div><div class="b"></div></body></html>"#
+            .to_string();
+        let view = create_layout_view(html);
+        let a = first_child(&body(&view));
+        let b = next_sibling(&a);
+
+        assert_eq!(LayoutPoint::new(0, 10), a.borrow().point());
+        assert_eq!(LayoutSize::new(590, 30), a.borrow().size());
+        // max(20, 15) = 20
+        assert_eq!(LayoutPoint::new(0, 60), b.borrow().point());
+        assert_eq!(LayoutSize::new(590, 40), b.borrow().size());
+        assert_eq!(LayoutSize::new(590, 100), body(&view).borrow().size());
+    }
+
+    #[test]
+    fn test_negative_margins_collapse() {
+        let html = r#"<html><head><style>
+            .a { margin-bottom: 30px; height: 10px; }
+            .b { margin-top: -10px; height: 10px; }
+            </style></head>
+            <body><div class="a"></div><div class="b"></div></body></html>"#
+            .to_string();
+        let view = create_layout_view(html);
+        let a = first_child(&body(&view));
+        let b = next_sibling(&a);
+
+        assert_eq!(LayoutPoint::new(0, 0), a.borrow().point());
+        // 30 + (-10) = 20
+        assert_eq!(LayoutPoint::new(0, 30), b.borrow().point());
+    }
+
+    #[test]
+    fn test_parent_and_first_child_margins_collapse() {
+        let html = r#"<html><head><style>
+            .parent { margin-top: 10px; }
+            .child { margin-top: 25px; margin-bottom: 5px; height: 20px; }
+            .next { height: 10px; }
+            </style></head>
+            <body><div class="parent"><div class="child"></div></div><div class="next"></div></body></html>"#
+            .to_string();
+        let view = create_layout_view(html);
+        let parent = first_child(&body(&view));
+        let child = first_child(&parent);
+        let next = next_sibling(&parent);
+
+        assert_eq!(LayoutPoint::new(0, 25), parent.borrow().point());
+        assert_eq!(LayoutSize::new(590, 20), parent.borrow().size());
+        assert_eq!(LayoutPoint::new(0, 25), child.borrow().point());
+        // 子の下マージンは親を通り抜けて次の兄弟との間に入る
+        assert_eq!(LayoutPoint::new(0, 50), next.borrow().point());
+    }
+
+    #[test]
+    fn test_padding_prevents_collapse() {
+        let html = r#"<html><head><style>
+            .parent { margin-top: 10px; padding: 5px; }
+            .child { margin-top: 25px; height: 20px; }
+            </style></head>
+            <body><div class="parent"><div class="child"></div></div></body></html>"#
+            .to_string();
+        let view = create_layout_view(html);
+        let parent = first_child(&body(&view));
+        let child = first_child(&parent);
+
+        assert_eq!(LayoutPoint::new(0, 10), parent.borrow().point());
+        assert_eq!(LayoutSize::new(590, 55), parent.borrow().size());
+        assert_eq!(LayoutPoint::new(5, 40), child.borrow().point());
+        assert_eq!(LayoutSize::new(580, 20), child.borrow().size());
+    }
+
+    #[test]
+    fn test_border_prevents_collapse() {
+        let html = r#"<html><head><style>
+            .parent { border: 2px solid black; }
+            .child { margin: 10px; height: 20px; }
+            </style></head>
+            <body><div class="parent"><div class="child"></div></div></body></html>"#
+            .to_string();
+        let view = create_layout_view(html);
+        let parent = first_child(&body(&view));
+        let child = first_child(&parent);
+
+        assert_eq!(LayoutPoint::new(0, 0), parent.borrow().point());
+        assert_eq!(LayoutSize::new(590, 44), parent.borrow().size());
+        assert_eq!(LayoutPoint::new(12, 12), child.borrow().point());
+        assert_eq!(LayoutSize::new(566, 20), child.borrow().size());
+    }
+
+    #[test]
+    fn test_empty_block_collapses_through() {
+        let html = r#"<html><head><style>
+            .a { height: 10px; margin-bottom: 10px; }
+            .empty { margin-top: 30px; margin-bottom: 20px; }
+            .b { height: 10px; margin-top: 5px; }
+            </style></head>
+            <body><div class="a"></div><div class="empty"></div><div class="b"></div></body></html>"#
+            .to_string();
+        let view = create_layout_view(html);
+        let a = first_child(&body(&view));
+        let empty = next_sibling(&a);
+        let b = next_sibling(&empty);
+
+        assert_eq!(LayoutSize::new(590, 0), empty.borrow().size());
+        // 10, 30, 20, 5 のマージンはすべて相殺され、最大の30になる
+        assert_eq!(LayoutPoint::new(0, 40), b.borrow().point());
+    }
+
+    #[test]
+    fn test_overflow_establishes_bfc() {
+        let html = r#"<html><head><style>
+            .parent { overflow: hidden; margin-top: 10px; }
+            .child { margin-top: 25px; margin-bottom: 15px; height: 20px; }
+            </style></head>
+            <body><div class="parent"><div class="child"></div></div></body></html>"#
+            .to_string();
+        let view = create_layout_view(html);
+        let parent = first_child(&body(&view));
+        let child = first_child(&parent);
+
+        assert_eq!(LayoutPoint::new(0, 10), parent.borrow().point());
+        assert_eq!(LayoutSize::new(590, 60), parent.borrow().size());
+        assert_eq!(LayoutPoint::new(0, 35), child.borrow().point());
+    }
+
+    #[test]
+    fn test_flow_root_establishes_bfc() {
+        let html = r#"<html><head><style>
+            .parent { display: flow-root; }
+            .child { margin-top: 20px; height: 20px; }
+            </style></head>
+            <body><div class="parent"><div class="child"></div></div></body></html>"#
+            .to_string();
+        let view = create_layout_view(html);
+        let parent = first_child(&body(&view));
+        let child = first_child(&parent);
+
+        assert_eq!(LayoutPoint::new(0, 0), parent.borrow().point());
+        assert_eq!(LayoutSize::new(590, 40), parent.borrow().size());
+        assert_eq!(LayoutPoint::new(0, 20), child.borrow().point());
+    }
+
+    #[test]
+    fn test_inline_block_margins_do_not_collapse() {
+        let html = r#"<html><head><style>
+            .a { height: 10px; margin-bottom: 10px; }
+            .ib { display: inline-block; width: 100px; height: 20px; margin: 5px; }
+            .c { margin-top: 30px; }
+            </style></head>
+            <body><div class="a"></div><div><a class="ib"></a></div></body></html>"#
+            .to_string();
+        let view = create_layout_view(html);
+        let a = first_child(&body(&view));
+        let wrapper = next_sibling(&a);
+        let ib = first_child(&wrapper);
+
+        assert_eq!(LayoutPoint::new(0, 20), wrapper.borrow().point());
+        assert_eq!(LayoutSize::new(590, 30), wrapper.borrow().size());
+        assert_eq!(LayoutPoint::new(5, 25), ib.borrow().point());
+        assert_eq!(LayoutSize::new(100, 20), ib.borrow().size());
+    }
+
+    #[test]
+    fn test_auto_margins_center_block() {
+        let html = r#"<html><head><style>
+            .box { width: 190px; margin: 0 auto; height: 10px; }
+            </style></head>
+            <body><div class="box"></div></body></html>"#
+            .to_string();
+        let view = create_layout_view(html);
+        let b = first_child(&body(&view));
+
+        assert_eq!(LayoutPoint::new(200, 0), b.borrow().point());
+        assert_eq!(LayoutSize::new(190, 10), b.borrow().size());
+    }
+
+    #[test]
+    fn test_text_between_blocks() {
+        let html = r#"<html><head><style>
+            .a { margin-bottom: 10px; height: 10px; }
+            .b { margin-top: 10px; height: 10px; }
+            </style></head>
+            <body><div class="a"></div>text<div class="b"></div></body></html>"#
+            .to_string();
+        let view = create_layout_view(html);
+        let a = first_child(&body(&view));
+        let text = next_sibling(&a);
+        let b = next_sibling(&text);
+
+        // テキストの行がマージンの間に入るため、相殺されない
+        assert_eq!(LayoutPoint::new(0, 20), text.borrow().point());
+        assert_eq!(LayoutSize::new(32, 20), text.borrow().size());
+        assert_eq!(LayoutPoint::new(0, 50), b.borrow().point());
+    }
+
+    #[test]
+    fn test_intrinsic_width_follows_white_space() {
+        let html = "<html><head><style>\
+            .a { float: left; }\
+            .b { float: left; clear: left; white-space: pre; }\
+            </style></head>\
+            <body><div class=\"a\"><span>a</span><span> </span><span>b</span></div>\
+            <div class=\"b\">aa bb\ncccc</div></body></html>"
+            .to_string();
+        let view = create_layout_view(html);
+        let a = first_child(&body(&view));
+        let b = next_sibling(&a);
+
+        // 要素をまたいでまとめた空白も幅に含め、残した改行ではそれぞれの行の幅を測る
+        assert_eq!(LayoutSize::new(24, 20), a.borrow().size());
+        assert_eq!(LayoutSize::new(40, 40), b.borrow().size());
+    }
+}
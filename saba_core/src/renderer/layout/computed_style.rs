@@ -1,13 +1,18 @@
+use crate::constants::CHAR_HEIGHT;
 use crate::error::Error;
-use crate::renderer::dom::node;
+use crate::renderer::css::cssom::ComponentValue;
 use crate::renderer::dom::node::ElementKind;
 use crate::renderer::dom::node::Node;
 use crate::renderer::dom::node::NodeKind;
+use crate::renderer::layout::property::Property;
 use alloc::format;
 use alloc::rc::Rc;
 use alloc::string::String;
 use alloc::string::ToString;
+use alloc::vec;
+use alloc::vec::Vec;
 use core::cell::RefCell;
+use core::str::FromStr;
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct ComputedStyle {
@@ -16,8 +21,38 @@ pub struct ComputedStyle {
     display: Option<DisplayType>,
     font_size: Option<FontSize>,
     text_decoration: Option<TextDecoration>,
-    height: Option<f64>,
-    width: Option<f64>,
+    font_weight: Option<FontWeight>,
+    font_style: Option<FontStyle>,
+    font_family: Option<Vec<String>>,
+    line_height: Option<LineHeight>,
+    text_align: Option<TextAlign>,
+    white_space: Option<WhiteSpace>,
+    height: Option<Length>,
+    width: Option<Length>,
+    margin: Option<BoxEdges>,
+    padding: Option<BoxEdges>,
+    border_width: Option<BoxEdges>,
+    border_style: Option<BorderStyle>,
+    border_color: Option<Color>,
+    overflow: Option<Overflow>,
+    float: Option<Float>,
+    clear: Option<Clear>,
+    position: Option<Position>,
+    inset: Option<BoxEdges>,
+    z_index: Option<ZIndex>,
+    flex_direction: Option<FlexDirection>,
+    flex_wrap: Option<FlexWrap>,
+    justify_content: Option<JustifyContent>,
+    align_items: Option<AlignItems>,
+    flex_grow: Option<f64>,
+    flex_shrink: Option<f64>,
+    flex_basis: Option<Length>,
+    row_gap: Option<Length>,
+    column_gap: Option<Length>,
+    border_collapse: Option<BorderCollapse>,
+    border_spacing: Option<(Length, Length)>,
+    list_style_type: Option<ListStyleType>,
+    list_style_position: Option<ListStylePosition>,
 }
 
 impl ComputedStyle {
@@ -28,50 +63,326 @@ impl ComputedStyle {
             display: None,
             font_size: None,
             text_decoration: None,
+            font_weight: None,
+            font_style: None,
+            font_family: None,
+            line_height: None,
+            text_align: None,
+            white_space: None,
             height: None,
             width: None,
+            margin: None,
+            padding: None,
+            border_width: None,
+            border_style: None,
+            border_color: None,
+            overflow: None,
+            float: None,
+            clear: None,
+            position: None,
+            inset: None,
+            z_index: None,
+            flex_direction: None,
+            flex_wrap: None,
+            justify_content: None,
+            align_items: None,
+            flex_grow: None,
+            flex_shrink: None,
+            flex_basis: None,
+            row_gap: None,
+            column_gap: None,
+            border_collapse: None,
+            border_spacing: None,
+            list_style_type: None,
+            list_style_position: None,
         }
     }
 
-    pub fn defaulting(&mut self, node: &Rc<RefCell<Node>>, parent_style: Option<ComputedStyle>) {
-        if let Some(parent_style) = parent_style {
-            if self.background_color.is_none() && parent_style.background_color() != Color::white()
-            {
-                self.background_color = Some(parent_style.background_color());
+    /// すべてのプロパティが初期値のスタイル
+    /// border-colorの初期値はcurrentColorなので、要素のcolorが決まるまでNoneのままにする
+    pub fn initial() -> Self {
+        Self {
+            background_color: Some(Color::transparent()),
+            color: Some(Color::black()),
+            display: Some(DisplayType::Inline),
+            font_size: Some(FontSize::Medium),
+            text_decoration: Some(TextDecoration::None),
+            font_weight: Some(FontWeight::Normal),
+            font_style: Some(FontStyle::Normal),
+            font_family: Some(vec!["sans-serif".to_string()]),
+            line_height: Some(LineHeight::Normal),
+            text_align: Some(TextAlign::Left),
+            white_space: Some(WhiteSpace::Normal),
+            height: Some(Length::Auto),
+            width: Some(Length::Auto),
+            margin: Some(BoxEdges::zero()),
+            padding: Some(BoxEdges::zero()),
+            // medium
+            border_width: Some(BoxEdges::all(Length::Px(3.0))),
+            border_style: Some(BorderStyle::None),
+            border_color: None,
+            overflow: Some(Overflow::Visible),
+            float: Some(Float::None),
+            clear: Some(Clear::None),
+            position: Some(Position::Static),
+            inset: Some(BoxEdges::all(Length::Auto)),
+            z_index: Some(ZIndex::Auto),
+            flex_direction: Some(FlexDirection::Row),
+            flex_wrap: Some(FlexWrap::NoWrap),
+            justify_content: Some(JustifyContent::FlexStart),
+            align_items: Some(AlignItems::Stretch),
+            flex_grow: Some(0.0),
+            flex_shrink: Some(1.0),
+            flex_basis: Some(Length::Auto),
+            row_gap: Some(Length::Px(0.0)),
+            column_gap: Some(Length::Px(0.0)),
+            border_collapse: Some(BorderCollapse::Separate),
+            border_spacing: Some((Length::Px(0.0), Length::Px(0.0))),
+            list_style_type: Some(ListStyleType::Disc),
+            list_style_position: Some(ListStylePosition::Outside),
+        }
+    }
+
+    /// カスケードで値が決まっているかどうか
+    pub fn is_set(&self, property: Property) -> bool {
+        match property {
+            Property::BackgroundColor => self.background_color.is_some(),
+            Property::Color => self.color.is_some(),
+            Property::Display => self.display.is_some(),
+            Property::FontSize => self.font_size.is_some(),
+            Property::TextDecoration => self.text_decoration.is_some(),
+            Property::FontWeight => self.font_weight.is_some(),
+            Property::FontStyle => self.font_style.is_some(),
+            Property::FontFamily => self.font_family.is_some(),
+            Property::LineHeight => self.line_height.is_some(),
+            Property::TextAlign => self.text_align.is_some(),
+            Property::WhiteSpace => self.white_space.is_some(),
+            Property::Height => self.height.is_some(),
+            Property::Width => self.width.is_some(),
+            Property::Margin => self.margin.is_some(),
+            Property::Padding => self.padding.is_some(),
+            Property::BorderWidth => self.border_width.is_some(),
+            Property::BorderStyle => self.border_style.is_some(),
+            Property::BorderColor => self.border_color.is_some(),
+            Property::Overflow => self.overflow.is_some(),
+            Property::Float => self.float.is_some(),
+            Property::Clear => self.clear.is_some(),
+            Property::Position => self.position.is_some(),
+            Property::Inset => self.inset.is_some(),
+            Property::ZIndex => self.z_index.is_some(),
+            Property::FlexDirection => self.flex_direction.is_some(),
+            Property::FlexWrap => self.flex_wrap.is_some(),
+            Property::JustifyContent => self.justify_content.is_some(),
+            Property::AlignItems => self.align_items.is_some(),
+            Property::FlexGrow => self.flex_grow.is_some(),
+            Property::FlexShrink => self.flex_shrink.is_some(),
+            Property::FlexBasis => self.flex_basis.is_some(),
+            Property::RowGap => self.row_gap.is_some(),
+            Property::ColumnGap => self.column_gap.is_some(),
+            Property::BorderCollapse => self.border_collapse.is_some(),
+            Property::BorderSpacing => self.border_spacing.is_some(),
+            Property::ListStyleType => self.list_style_type.is_some(),
+            Property::ListStylePosition => self.list_style_position.is_some(),
+        }
+    }
+
+    /// `from`のスタイルから`property`の値を写す
+    /// `inherit`では親要素のスタイルから、`initial`では`ComputedStyle::initial()`から写す
+    pub fn copy_property(&mut self, property: Property, from: &ComputedStyle) {
+        match property {
+            Property::BackgroundColor => self.background_color = from.background_color,
+            Property::Color => self.color = from.color,
+            Property::Display => self.display = from.display,
+            Property::FontSize => self.font_size = from.font_size,
+            Property::TextDecoration => self.text_decoration = from.text_decoration,
+            Property::FontWeight => self.font_weight = from.font_weight,
+            Property::FontStyle => self.font_style = from.font_style,
+            Property::FontFamily => self.font_family = from.font_family.clone(),
+            Property::LineHeight => self.line_height = from.line_height,
+            Property::TextAlign => self.text_align = from.text_align,
+            Property::WhiteSpace => self.white_space = from.white_space,
+            Property::Height => self.height = from.height,
+            Property::Width => self.width = from.width,
+            Property::Margin => self.margin = from.margin,
+            Property::Padding => self.padding = from.padding,
+            Property::BorderWidth => self.border_width = from.border_width,
+            Property::BorderStyle => self.border_style = from.border_style,
+            Property::BorderColor => self.border_color = from.border_color,
+            Property::Overflow => self.overflow = from.overflow,
+            Property::Float => self.float = from.float,
+            Property::Clear => self.clear = from.clear,
+            Property::Position => self.position = from.position,
+            Property::Inset => self.inset = from.inset,
+            Property::ZIndex => self.z_index = from.z_index,
+            Property::FlexDirection => self.flex_direction = from.flex_direction,
+            Property::FlexWrap => self.flex_wrap = from.flex_wrap,
+            Property::JustifyContent => self.justify_content = from.justify_content,
+            Property::AlignItems => self.align_items = from.align_items,
+            Property::FlexGrow => self.flex_grow = from.flex_grow,
+            Property::FlexShrink => self.flex_shrink = from.flex_shrink,
+            Property::FlexBasis => self.flex_basis = from.flex_basis,
+            Property::RowGap => self.row_gap = from.row_gap,
+            Property::ColumnGap => self.column_gap = from.column_gap,
+            Property::BorderCollapse => self.border_collapse = from.border_collapse,
+            Property::BorderSpacing => self.border_spacing = from.border_spacing,
+            Property::ListStyleType => self.list_style_type = from.list_style_type,
+            Property::ListStylePosition => self.list_style_position = from.list_style_position,
+        }
+    }
+
+    /// `margin-top: inherit;`のように、辺ごとのプロパティの1つの辺だけを`from`から写す
+    pub fn copy_side(&mut self, property: Property, side: &str, from: &ComputedStyle) {
+        let initial = Self::initial();
+        let (target, source, initial) = match property {
+            Property::Margin => (&mut self.margin, from.margin, initial.margin),
+            Property::Padding => (&mut self.padding, from.padding, initial.padding),
+            Property::BorderWidth => (
+                &mut self.border_width,
+                from.border_width,
+                initial.border_width,
+            ),
+            Property::Inset => (&mut self.inset, from.inset, initial.inset),
+            _ => return,
+        };
+        let (source, mut edges) = match (source.or(initial), target.or(initial)) {
+            (Some(source), Some(edges)) => (source, edges),
+            _ => return,
+        };
+        edges.set_side(side, source.side(side));
+        *target = Some(edges);
+    }
+
+    /// ブラウザが持つデフォルトのスタイルシートにあたる、要素ごとのスタイル
+    /// 作者のスタイルシートで値が決まっていないプロパティにだけ適用する
+    fn apply_user_agent_style(&mut self, node: &Rc<RefCell<Node>>) {
+        let element = match node.borrow().kind() {
+            NodeKind::Element(element) => element,
+            NodeKind::Document => {
+                self.display.get_or_insert(DisplayType::Block);
+                return;
+            }
+            NodeKind::Text(_) => return,
+        };
+
+        match element.kind() {
+            ElementKind::Table => {
+                self.display.get_or_insert(DisplayType::Table);
+                let spacing = Length::Px(2.0);
+                self.border_spacing.get_or_insert((spacing, spacing));
             }
-            if self.color.is_none() && parent_style.color() != Color::black() {
-                self.color = Some(parent_style.color());
+            ElementKind::Caption => {
+                self.display.get_or_insert(DisplayType::TableCaption);
+                self.text_align.get_or_insert(TextAlign::Center);
             }
-            if self.font_size.is_none() && parent_style.font_size() != FontSize::Medium {
-                self.font_size = Some(parent_style.font_size());
+            ElementKind::Thead => {
+                self.display.get_or_insert(DisplayType::TableHeaderGroup);
             }
-            if self.text_decoration.is_none()
-                && parent_style.text_decoration() != TextDecoration::None
-            {
-                self.text_decoration = Some(parent_style.text_decoration());
+            ElementKind::Tbody => {
+                self.display.get_or_insert(DisplayType::TableRowGroup);
             }
+            ElementKind::Tfoot => {
+                self.display.get_or_insert(DisplayType::TableFooterGroup);
+            }
+            ElementKind::Tr => {
+                self.display.get_or_insert(DisplayType::TableRow);
+            }
+            ElementKind::Td | ElementKind::Th => {
+                self.display.get_or_insert(DisplayType::TableCell);
+                self.padding.get_or_insert(BoxEdges::all(Length::Px(1.0)));
+            }
+            ElementKind::Ul | ElementKind::Ol => {
+                // 入れ子のリストは上下のマージンを持たず、ulの記号は深さごとに変わる
+                let depth = list_nesting_depth(node);
+                let margin = if depth == 0 {
+                    Length::Em(1.0)
+                } else {
+                    Length::Px(0.0)
+                };
+                self.margin.get_or_insert(BoxEdges {
+                    top: margin,
+                    right: Length::Px(0.0),
+                    bottom: margin,
+                    left: Length::Px(0.0),
+                });
+                self.padding.get_or_insert(BoxEdges {
+                    top: Length::Px(0.0),
+                    right: Length::Px(0.0),
+                    bottom: Length::Px(0.0),
+                    left: Length::Px(40.0),
+                });
+                if element.kind() == ElementKind::Ol {
+                    self.list_style_type.get_or_insert(ListStyleType::Decimal);
+                } else {
+                    self.list_style_type.get_or_insert(match depth {
+                        0 => ListStyleType::Disc,
+                        1 => ListStyleType::Circle,
+                        _ => ListStyleType::Square,
+                    });
+                }
+            }
+            ElementKind::Li => {
+                self.display.get_or_insert(DisplayType::ListItem);
+            }
+            _ => {}
         }
-
-        if self.background_color.is_none() {
-            self.background_color = Some(Color::white());
-        }
-        if self.color.is_none() {
-            self.color = Some(Color::black());
-        }
-        if self.display.is_none() {
-            self.display = Some(DisplayType::default(node))
+        if element.is_block_element() {
+            self.display.get_or_insert(DisplayType::Block);
         }
-        if self.font_size.is_none() {
-            self.font_size = Some(FontSize::default(node));
+        match element.kind() {
+            ElementKind::H1 => {
+                self.font_size.get_or_insert(FontSize::XXLarge);
+                self.font_weight.get_or_insert(FontWeight::Bold);
+            }
+            ElementKind::H2 => {
+                self.font_size.get_or_insert(FontSize::XLarge);
+                self.font_weight.get_or_insert(FontWeight::Bold);
+            }
+            ElementKind::B | ElementKind::Strong => {
+                self.font_weight.get_or_insert(FontWeight::Bold);
+            }
+            ElementKind::Th => {
+                self.font_weight.get_or_insert(FontWeight::Bold);
+                self.text_align.get_or_insert(TextAlign::Center);
+            }
+            ElementKind::I | ElementKind::Em => {
+                self.font_style.get_or_insert(FontStyle::Italic);
+            }
+            ElementKind::A => {
+                self.text_decoration
+                    .get_or_insert(TextDecoration::Underline);
+            }
+            _ => {}
         }
-        if self.text_decoration.is_none() {
-            self.text_decoration = Some(TextDecoration::default(node));
+    }
+
+    /// カスケードで値が決まらなかったプロパティを、継承された値か初期値で埋める
+    /// https://www.w3.org/TR/css-cascade-4/#defaulting
+    pub fn defaulting(&mut self, node: &Rc<RefCell<Node>>, parent_style: Option<ComputedStyle>) {
+        self.apply_user_agent_style(node);
+
+        let initial = Self::initial();
+        for property in Property::ALL {
+            if self.is_set(property) {
+                continue;
+            }
+            match &parent_style {
+                Some(parent_style) if property.inherited() => {
+                    self.copy_property(property, parent_style)
+                }
+                _ => self.copy_property(property, &initial),
+            }
         }
-        if self.height.is_none() {
-            self.height = Some(0.0);
+
+        // currentColor
+        if self.border_color.is_none() {
+            self.border_color = self.color;
         }
-        if self.width.is_none() {
-            self.width = Some(0.0);
+        // emやパーセンテージは、この要素のフォントサイズをもとにした値を子要素に継承する
+        if let Some(LineHeight::Length(length)) = self.line_height {
+            let font_size = self.font_size().to_px();
+            self.line_height = Some(LineHeight::Length(Length::Px(
+                length.to_px(font_size, font_size).unwrap_or(0) as f64,
+            )));
         }
     }
 
@@ -81,17 +392,19 @@ impl ComputedStyle {
 
     pub fn background_color(&self) -> Color {
         self.background_color
-            .clone()
             .expect("failed to access CSS property: background_color")
     }
 
     pub fn set_color(&mut self, color: Color) {
         self.color = Some(color);
     }
-    pub fn color(&self) -> Color {
+    /// `currentColor`の値。カスケードの途中でまだ`color`が決まっていない場合はNone
+    pub fn current_color(&self) -> Option<Color> {
         self.color
-            .clone()
-            .expect("failed to access CSS property: color")
+    }
+
+    pub fn color(&self) -> Color {
+        self.color.expect("failed to access CSS property: color")
     }
 
     pub fn set_display(&mut self, display: DisplayType) {
@@ -104,131 +417,750 @@ impl ComputedStyle {
             .expect("failed to access CSS property: display")
     }
 
+    pub fn set_font_size(&mut self, font_size: FontSize) {
+        self.font_size = Some(font_size);
+    }
+
     pub fn font_size(&self) -> FontSize {
         self.font_size
             .expect("failed to access CSS property: font_size")
     }
 
+    pub fn set_text_decoration(&mut self, text_decoration: TextDecoration) {
+        self.text_decoration = Some(text_decoration);
+    }
+
     pub fn text_decoration(&self) -> TextDecoration {
         self.text_decoration
             .expect("failed to access CSS property: text_decoration")
     }
 
-    pub fn set_height(&mut self, height: f64) {
+    pub fn set_font_weight(&mut self, font_weight: FontWeight) {
+        self.font_weight = Some(font_weight);
+    }
+
+    pub fn font_weight(&self) -> FontWeight {
+        self.font_weight
+            .expect("failed to access CSS property: font_weight")
+    }
+
+    pub fn set_font_style(&mut self, font_style: FontStyle) {
+        self.font_style = Some(font_style);
+    }
+
+    pub fn font_style(&self) -> FontStyle {
+        self.font_style
+            .expect("failed to access CSS property: font_style")
+    }
+
+    pub fn set_font_family(&mut self, font_family: Vec<String>) {
+        self.font_family = Some(font_family);
+    }
+
+    /// 優先度の高い順に並んだフォントファミリー名
+    pub fn font_family(&self) -> Vec<String> {
+        self.font_family
+            .clone()
+            .expect("failed to access CSS property: font_family")
+    }
+
+    pub fn set_line_height(&mut self, line_height: LineHeight) {
+        self.line_height = Some(line_height);
+    }
+
+    pub fn line_height(&self) -> LineHeight {
+        self.line_height
+            .expect("failed to access CSS property: line_height")
+    }
+
+    pub fn set_text_align(&mut self, text_align: TextAlign) {
+        self.text_align = Some(text_align);
+    }
+
+    pub fn text_align(&self) -> TextAlign {
+        self.text_align
+            .expect("failed to access CSS property: text_align")
+    }
+
+    pub fn set_white_space(&mut self, white_space: WhiteSpace) {
+        self.white_space = Some(white_space);
+    }
+
+    pub fn white_space(&self) -> WhiteSpace {
+        self.white_space
+            .expect("failed to access CSS property: white_space")
+    }
+
+    pub fn set_height(&mut self, height: Length) {
         self.height = Some(height);
     }
 
-    pub fn height(&self) -> f64 {
+    pub fn height(&self) -> Length {
         self.height.expect("failed to access CSS property: height")
     }
 
-    pub fn set_width(&mut self, width: f64) {
+    pub fn set_width(&mut self, width: Length) {
         self.width = Some(width);
     }
 
-    pub fn width(&self) -> f64 {
+    pub fn width(&self) -> Length {
         self.width.expect("failed to access CSS property: width")
     }
+
+    pub fn set_margin(&mut self, margin: BoxEdges) {
+        self.margin = Some(margin);
+    }
+
+    pub fn margin(&self) -> BoxEdges {
+        self.margin.expect("failed to access CSS property: margin")
+    }
+
+    pub fn set_padding(&mut self, padding: BoxEdges) {
+        self.padding = Some(padding);
+    }
+
+    pub fn padding(&self) -> BoxEdges {
+        self.padding
+            .expect("failed to access CSS property: padding")
+    }
+
+    pub fn set_border_width(&mut self, border_width: BoxEdges) {
+        self.border_width = Some(border_width);
+    }
+
+    /// border-styleがnoneの場合、ボーダーの幅は0として扱う
+    pub fn border_width(&self) -> BoxEdges {
+        if self.border_style() == BorderStyle::None {
+            return BoxEdges::zero();
+        }
+        self.border_width
+            .expect("failed to access CSS property: border_width")
+    }
+
+    pub fn set_border_style(&mut self, border_style: BorderStyle) {
+        self.border_style = Some(border_style);
+    }
+
+    pub fn border_style(&self) -> BorderStyle {
+        self.border_style
+            .expect("failed to access CSS property: border_style")
+    }
+
+    pub fn set_border_color(&mut self, color: Color) {
+        self.border_color = Some(color);
+    }
+
+    pub fn border_color(&self) -> Color {
+        self.border_color
+            .expect("failed to access CSS property: border_color")
+    }
+
+    pub fn set_overflow(&mut self, overflow: Overflow) {
+        self.overflow = Some(overflow);
+    }
+
+    pub fn overflow(&self) -> Overflow {
+        self.overflow
+            .expect("failed to access CSS property: overflow")
+    }
+
+    pub fn set_float(&mut self, float: Float) {
+        self.float = Some(float);
+    }
+
+    pub fn float(&self) -> Float {
+        self.float.expect("failed to access CSS property: float")
+    }
+
+    pub fn set_clear(&mut self, clear: Clear) {
+        self.clear = Some(clear);
+    }
+
+    pub fn clear(&self) -> Clear {
+        self.clear.expect("failed to access CSS property: clear")
+    }
+
+    pub fn set_position(&mut self, position: Position) {
+        self.position = Some(position);
+    }
+
+    pub fn position(&self) -> Position {
+        self.position
+            .expect("failed to access CSS property: position")
+    }
+
+    /// top、right、bottom、leftの値
+    pub fn set_inset(&mut self, inset: BoxEdges) {
+        self.inset = Some(inset);
+    }
+
+    pub fn inset(&self) -> BoxEdges {
+        self.inset.expect("failed to access CSS property: inset")
+    }
+
+    pub fn set_z_index(&mut self, z_index: ZIndex) {
+        self.z_index = Some(z_index);
+    }
+
+    pub fn z_index(&self) -> ZIndex {
+        self.z_index
+            .expect("failed to access CSS property: z_index")
+    }
+
+    pub fn set_flex_direction(&mut self, flex_direction: FlexDirection) {
+        self.flex_direction = Some(flex_direction);
+    }
+
+    pub fn flex_direction(&self) -> FlexDirection {
+        self.flex_direction
+            .expect("failed to access CSS property: flex_direction")
+    }
+
+    pub fn set_flex_wrap(&mut self, flex_wrap: FlexWrap) {
+        self.flex_wrap = Some(flex_wrap);
+    }
+
+    pub fn flex_wrap(&self) -> FlexWrap {
+        self.flex_wrap
+            .expect("failed to access CSS property: flex_wrap")
+    }
+
+    pub fn set_justify_content(&mut self, justify_content: JustifyContent) {
+        self.justify_content = Some(justify_content);
+    }
+
+    pub fn justify_content(&self) -> JustifyContent {
+        self.justify_content
+            .expect("failed to access CSS property: justify_content")
+    }
+
+    pub fn set_align_items(&mut self, align_items: AlignItems) {
+        self.align_items = Some(align_items);
+    }
+
+    pub fn align_items(&self) -> AlignItems {
+        self.align_items
+            .expect("failed to access CSS property: align_items")
+    }
+
+    pub fn set_flex_grow(&mut self, flex_grow: f64) {
+        self.flex_grow = Some(flex_grow);
+    }
+
+    pub fn flex_grow(&self) -> f64 {
+        self.flex_grow
+            .expect("failed to access CSS property: flex_grow")
+    }
+
+    pub fn set_flex_shrink(&mut self, flex_shrink: f64) {
+        self.flex_shrink = Some(flex_shrink);
+    }
+
+    pub fn flex_shrink(&self) -> f64 {
+        self.flex_shrink
+            .expect("failed to access CSS property: flex_shrink")
+    }
+
+    pub fn set_flex_basis(&mut self, flex_basis: Length) {
+        self.flex_basis = Some(flex_basis);
+    }
+
+    pub fn flex_basis(&self) -> Length {
+        self.flex_basis
+            .expect("failed to access CSS property: flex_basis")
+    }
+
+    pub fn set_row_gap(&mut self, row_gap: Length) {
+        self.row_gap = Some(row_gap);
+    }
+
+    pub fn row_gap(&self) -> Length {
+        self.row_gap
+            .expect("failed to access CSS property: row_gap")
+    }
+
+    pub fn set_column_gap(&mut self, column_gap: Length) {
+        self.column_gap = Some(column_gap);
+    }
+
+    pub fn column_gap(&self) -> Length {
+        self.column_gap
+            .expect("failed to access CSS property: column_gap")
+    }
+
+    pub fn set_border_collapse(&mut self, border_collapse: BorderCollapse) {
+        self.border_collapse = Some(border_collapse);
+    }
+
+    pub fn border_collapse(&self) -> BorderCollapse {
+        self.border_collapse
+            .expect("failed to access CSS property: border_collapse")
+    }
+
+    /// 横方向と縦方向のセルの間隔
+    pub fn set_border_spacing(&mut self, horizontal: Length, vertical: Length) {
+        self.border_spacing = Some((horizontal, vertical));
+    }
+
+    pub fn border_spacing(&self) -> (Length, Length) {
+        self.border_spacing
+            .expect("failed to access CSS property: border_spacing")
+    }
+
+    pub fn set_list_style_type(&mut self, list_style_type: ListStyleType) {
+        self.list_style_type = Some(list_style_type);
+    }
+
+    pub fn list_style_type(&self) -> ListStyleType {
+        self.list_style_type
+            .expect("failed to access CSS property: list_style_type")
+    }
+
+    pub fn set_list_style_position(&mut self, list_style_position: ListStylePosition) {
+        self.list_style_position = Some(list_style_position);
+    }
+
+    pub fn list_style_position(&self) -> ListStylePosition {
+        self.list_style_position
+            .expect("failed to access CSS property: list_style_position")
+    }
+
+    /// カスケード中に一部の辺だけを上書きするため、未設定の場合は初期値を返す
+    pub fn margin_or_initial(&self) -> BoxEdges {
+        self.margin.unwrap_or(BoxEdges::zero())
+    }
+
+    pub fn padding_or_initial(&self) -> BoxEdges {
+        self.padding.unwrap_or(BoxEdges::zero())
+    }
+
+    pub fn inset_or_initial(&self) -> BoxEdges {
+        self.inset.unwrap_or(BoxEdges::all(Length::Auto))
+    }
+
+    pub fn border_width_or_initial(&self) -> BoxEdges {
+        self.border_width
+            .unwrap_or_else(|| Self::initial().border_width())
+    }
 }
 
-#[derive(Debug, Clone, PartialEq)]
+/// CSSの名前付きの色
+/// https://www.w3.org/TR/css-color-4/#named-colors
+const NAMED_COLORS: [(&str, u32); 148] = [
+    ("aliceblue", 0xf0f8ff),
+    ("antiquewhite", 0xfaebd7),
+    ("aqua", 0x00ffff),
+    ("aquamarine", 0x7fffd4),
+    ("azure", 0xf0ffff),
+    ("beige", 0xf5f5dc),
+    ("bisque", 0xffe4c4),
+    ("black", 0x000000),
+    ("blanchedalmond", 0xffebcd),
+    ("blue", 0x0000ff),
+    ("blueviolet", 0x8a2be2),
+    ("brown", 0xa52a2a),
+    ("burlywood", 0xdeb887),
+    ("cadetblue", 0x5f9ea0),
+    ("chartreuse", 0x7fff00),
+    ("chocolate", 0xd2691e),
+    ("coral", 0xff7f50),
+    ("cornflowerblue", 0x6495ed),
+    ("cornsilk", 0xfff8dc),
+    ("crimson", 0xdc143c),
+    ("cyan", 0x00ffff),
+    ("darkblue", 0x00008b),
+    ("darkcyan", 0x008b8b),
+    ("darkgoldenrod", 0xb8860b),
+    ("darkgray", 0xa9a9a9),
+    ("darkgreen", 0x006400),
+    ("darkgrey", 0xa9a9a9),
+    ("darkkhaki", 0xbdb76b),
+    ("darkmagenta", 0x8b008b),
+    ("darkolivegreen", 0x556b2f),
+    ("darkorange", 0xff8c00),
+    ("darkorchid", 0x9932cc),
+    ("darkred", 0x8b0000),
+    ("darksalmon", 0xe9967a),
+    ("darkseagreen", 0x8fbc8f),
+    ("darkslateblue", 0x483d8b),
+    ("darkslategray", 0x2f4f4f),
+    ("darkslategrey", 0x2f4f4f),
+    ("darkturquoise", 0x00ced1),
+    ("darkviolet", 0x9400d3),
+    ("deeppink", 0xff1493),
+    ("deepskyblue", 0x00bfff),
+    ("dimgray", 0x696969),
+    ("dimgrey", 0x696969),
+    ("dodgerblue", 0x1e90ff),
+    ("firebrick", 0xb22222),
+    ("floralwhite", 0xfffaf0),
+    ("forestgreen", 0x228b22),
+    ("fuchsia", 0xff00ff),
+    ("gainsboro", 0xdcdcdc),
+    ("ghostwhite", 0xf8f8ff),
+    ("gold", 0xffd700),
+    ("goldenrod", 0xdaa520),
+    ("gray", 0x808080),
+    ("green", 0x008000),
+    ("greenyellow", 0xadff2f),
+    ("grey", 0x808080),
+    ("honeydew", 0xf0fff0),
+    ("hotpink", 0xff69b4),
+    ("indianred", 0xcd5c5c),
+    ("indigo", 0x4b0082),
+    ("ivory", 0xfffff0),
+    ("khaki", 0xf0e68c),
+    ("lavender", 0xe6e6fa),
+    ("lavenderblush", 0xfff0f5),
+    ("lawngreen", 0x7cfc00),
+    ("lemonchiffon", 0xfffacd),
+    ("lightblue", 0xadd8e6),
+    ("lightcoral", 0xf08080),
+    ("lightcyan", 0xe0ffff),
+    ("lightgoldenrodyellow", 0xfafad2),
+    ("lightgray", 0xd3d3d3),
+    ("lightgreen", 0x90ee90),
+    ("lightgrey", 0xd3d3d3),
+    ("lightpink", 0xffb6c1),
+    ("lightsalmon", 0xffa07a),
+    ("lightseagreen", 0x20b2aa),
+    ("lightskyblue", 0x87cefa),
+    ("lightslategray", 0x778899),
+    ("lightslategrey", 0x778899),
+    ("lightsteelblue", 0xb0c4de),
+    ("lightyellow", 0xffffe0),
+    ("lime", 0x00ff00),
+    ("limegreen", 0x32cd32),
+    ("linen", 0xfaf0e6),
+    ("magenta", 0xff00ff),
+    ("maroon", 0x800000),
+    ("mediumaquamarine", 0x66cdaa),
+    ("mediumblue", 0x0000cd),
+    ("mediumorchid", 0xba55d3),
+    ("mediumpurple", 0x9370db),
+    ("mediumseagreen", 0x3cb371),
+    ("mediumslateblue", 0x7b68ee),
+    ("mediumspringgreen", 0x00fa9a),
+    ("mediumturquoise", 0x48d1cc),
+    ("mediumvioletred", 0xc71585),
+    ("midnightblue", 0x191970),
+    ("mintcream", 0xf5fffa),
+    ("mistyrose", 0xffe4e1),
+    ("moccasin", 0xffe4b5),
+    ("navajowhite", 0xffdead),
+    ("navy", 0x000080),
+    ("oldlace", 0xfdf5e6),
+    ("olive", 0x808000),
+    ("olivedrab", 0x6b8e23),
+    ("orange", 0xffa500),
+    ("orangered", 0xff4500),
+    ("orchid", 0xda70d6),
+    ("palegoldenrod", 0xeee8aa),
+    ("palegreen", 0x98fb98),
+    ("paleturquoise", 0xafeeee),
+    ("palevioletred", 0xdb7093),
+    ("papayawhip", 0xffefd5),
+    ("peachpuff", 0xffdab9),
+    ("peru", 0xcd853f),
+    ("pink", 0xffc0cb),
+    ("plum", 0xdda0dd),
+    ("powderblue", 0xb0e0e6),
+    ("purple", 0x800080),
+    ("rebeccapurple", 0x663399),
+    ("red", 0xff0000),
+    ("rosybrown", 0xbc8f8f),
+    ("royalblue", 0x4169e1),
+    ("saddlebrown", 0x8b4513),
+    ("salmon", 0xfa8072),
+    ("sandybrown", 0xf4a460),
+    ("seagreen", 0x2e8b57),
+    ("seashell", 0xfff5ee),
+    ("sienna", 0xa0522d),
+    ("silver", 0xc0c0c0),
+    ("skyblue", 0x87ceeb),
+    ("slateblue", 0x6a5acd),
+    ("slategray", 0x708090),
+    ("slategrey", 0x708090),
+    ("snow", 0xfffafa),
+    ("springgreen", 0x00ff7f),
+    ("steelblue", 0x4682b4),
+    ("tan", 0xd2b48c),
+    ("teal", 0x008080),
+    ("thistle", 0xd8bfd8),
+    ("tomato", 0xff6347),
+    ("turquoise", 0x40e0d0),
+    ("violet", 0xee82ee),
+    ("wheat", 0xf5deb3),
+    ("white", 0xffffff),
+    ("whitesmoke", 0xf5f5f5),
+    ("yellow", 0xffff00),
+    ("yellowgreen", 0x9acd32),
+];
+
+/// 0から255の範囲に収めて四捨五入する
+fn to_channel(value: f64) -> u8 {
+    if value <= 0.0 {
+        0
+    } else if value >= 255.0 {
+        255
+    } else {
+        (value + 0.5) as u8
+    }
+}
+
+/// 不透明度(0から255)を持つsRGBの色
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct Color {
-    name: Option<String>,
-    code: String,
+    r: u8,
+    g: u8,
+    b: u8,
+    a: u8,
 }
 
 impl Color {
+    pub fn new(r: u8, g: u8, b: u8, a: u8) -> Self {
+        Self { r, g, b, a }
+    }
+
+    /// 0xRRGGBB形式の不透明な色
+    pub fn from_u32(code: u32) -> Self {
+        Self::new((code >> 16) as u8, (code >> 8) as u8, code as u8, 255)
+    }
+
+    /// 名前付きの色と`transparent`
+    /// 大文字と小文字は区別しない
     pub fn from_name(name: &str) -> Result<Self, Error> {
-        let code = match name {
-            "black" => "#000000".to_string(),
-            "silver" => "#c0c0c0".to_string(),
-            "gray" => "#808080".to_string(),
-            "white" => "#ffffff".to_string(),
-            "maroon" => "#800000".to_string(),
-            "red" => "#ff0000".to_string(),
-            "purple" => "#800080".to_string(),
-            "fuchsia" => "#ff00ff".to_string(),
-            "green" => "#008000".to_string(),
-            "lime" => "#00ff00".to_string(),
-            "olive" => "#808000".to_string(),
-            "yellow" => "#ffff00".to_string(),
-            "navy" => "#000080".to_string(),
-            "blue" => "#0000ff".to_string(),
-            "teal" => "#008080".to_string(),
-            "aqua" => "#00ffff".to_string(),
-            "orange" => "#ffa500".to_string(),
-            "lightgray" => "#d3d3d3".to_string(),
-            _ => {
-                return Err(Error::UnexpectedInput(format!(
-                    "color name {:?} is not supported yet",
-                    name
-                )))
-            }
-        };
-        Ok(Self {
-            name: Some(name.to_string()),
-            code,
-        })
+        let name = name.to_ascii_lowercase();
+        if name == "transparent" {
+            return Ok(Self::transparent());
+        }
+
+        match NAMED_COLORS.iter().find(|(n, _)| *n == name) {
+            Some((_, code)) => Ok(Self::from_u32(*code)),
+            None => Err(Error::UnexpectedInput(format!(
+                "color name {:?} is not supported yet",
+                name
+            ))),
+        }
     }
 
+    /// `#rgb`、`#rgba`、`#rrggbb`、`#rrggbbaa`の形式の色
     pub fn from_code(code: &str) -> Result<Self, Error> {
-        if code.chars().nth(0) != Some('#') || code.len() != 7 {
+        let invalid = || Error::UnexpectedInput(format!("invalid color code {:?}", code));
+
+        let digits = code
+            .strip_prefix('#')
+            .ok_or_else(invalid)?
+            .chars()
+            .map(|c| c.to_digit(16).map(|d| d as u8))
+            .collect::<Option<Vec<u8>>>()
+            .ok_or_else(invalid)?;
+
+        match digits.len() {
+            // 1桁の場合は同じ数字を2回繰り返した値になる
+            3 | 4 => Ok(Self::new(
+                digits[0] * 17,
+                digits[1] * 17,
+                digits[2] * 17,
+                digits.get(3).map_or(255, |a| a * 17),
+            )),
+            6 | 8 => Ok(Self::new(
+                digits[0] * 16 + digits[1],
+                digits[2] * 16 + digits[3],
+                digits[4] * 16 + digits[5],
+                if digits.len() == 8 {
+                    digits[6] * 16 + digits[7]
+                } else {
+                    255
+                },
+            )),
+            _ => Err(invalid()),
+        }
+    }
+
+    /// `rgb()`、`rgba()`、`hsl()`、`hsla()`の関数で指定された色
+    /// カンマ区切りの構文と、空白と`/`で区切る構文の両方を受け付ける
+    pub fn from_function(name: &str, arguments: &[ComponentValue]) -> Result<Self, Error> {
+        let mut channels = Vec::new();
+        let mut alpha = None;
+        let mut after_slash = false;
+        for argument in arguments {
+            match argument {
+                ComponentValue::Delim(',') => {}
+                ComponentValue::Delim('/') if !after_slash => after_slash = true,
+                _ if after_slash && alpha.is_none() => alpha = Some(argument),
+                _ if !after_slash => channels.push(argument),
+                _ => {
+                    return Err(Error::UnexpectedInput(format!(
+                        "unexpected argument {:?} in {}()",
+                        argument, name
+                    )))
+                }
+            }
+        }
+        if alpha.is_none() && channels.len() == 4 {
+            alpha = channels.pop();
+        }
+        if channels.len() != 3 {
             return Err(Error::UnexpectedInput(format!(
-                "invalid color code {:?}",
-                code
+                "{}() needs 3 color channels",
+                name
             )));
         }
 
-        let name = match code {
-            "#000000" => "black".to_string(),
-            "#c0c0c0" => "silver".to_string(),
-            "#808080" => "gray".to_string(),
-            "#ffffff" => "white".to_string(),
-            "#800000" => "maroon".to_string(),
-            "#ff0000" => "red".to_string(),
-            "#800080" => "purple".to_string(),
-            "#ff00ff" => "fuchsia".to_string(),
-            "#008000" => "green".to_string(),
-            "#00ff00" => "lime".to_string(),
-            "#808000" => "olive".to_string(),
-            "#ffff00" => "yellow".to_string(),
-            "#000080" => "navy".to_string(),
-            "#0000ff" => "blue".to_string(),
-            "#008080" => "teal".to_string(),
-            "#00ffff" => "aqua".to_string(),
-            "#ffa500" => "orange".to_string(),
-            "#d3d3d3" => "lightgray".to_string(),
-            _ => {
+        let a = match alpha {
+            Some(ComponentValue::Number(n)) => to_channel(n * 255.0),
+            Some(ComponentValue::Percentage(p)) => to_channel(p * 255.0 / 100.0),
+            Some(value) => {
                 return Err(Error::UnexpectedInput(format!(
-                    "color code {:?} is not supported yet",
-                    code
+                    "invalid alpha value {:?}",
+                    value
                 )))
             }
+            None => 255,
         };
 
-        Ok(Self {
-            name: Some(name),
-            code: code.to_string(),
-        })
+        match name.to_ascii_lowercase().as_str() {
+            "rgb" | "rgba" => {
+                let channel = |value: &ComponentValue| match value {
+                    ComponentValue::Number(n) => Ok(to_channel(*n)),
+                    ComponentValue::Percentage(p) => Ok(to_channel(p * 255.0 / 100.0)),
+                    _ => Err(Error::UnexpectedInput(format!(
+                        "invalid rgb() channel {:?}",
+                        value
+                    ))),
+                };
+                Ok(Self::new(
+                    channel(channels[0])?,
+                    channel(channels[1])?,
+                    channel(channels[2])?,
+                    a,
+                ))
+            }
+            "hsl" | "hsla" => {
+                let hue = match channels[0] {
+                    ComponentValue::Number(n) => *n,
+                    ComponentValue::Dimension(n, unit) => match unit.as_str() {
+                        "deg" => *n,
+                        "grad" => n * 0.9,
+                        "rad" => n * 180.0 / core::f64::consts::PI,
+                        "turn" => n * 360.0,
+                        _ => {
+                            return Err(Error::UnexpectedInput(format!(
+                                "invalid angle unit {:?}",
+                                unit
+                            )))
+                        }
+                    },
+                    value => {
+                        return Err(Error::UnexpectedInput(format!(
+                            "invalid hsl() hue {:?}",
+                            value
+                        )))
+                    }
+                };
+                let percentage = |value: &ComponentValue| match value {
+                    ComponentValue::Percentage(p) | ComponentValue::Number(p) => {
+                        Ok(p.max(0.0).min(100.0) / 100.0)
+                    }
+                    _ => Err(Error::UnexpectedInput(format!(
+                        "invalid hsl() value {:?}",
+                        value
+                    ))),
+                };
+                let (r, g, b) = hsl_to_rgb(hue, percentage(channels[1])?, percentage(channels[2])?);
+                Ok(Self::new(r, g, b, a))
+            }
+            _ => Err(Error::UnexpectedInput(format!(
+                "color function {:?} is not supported yet",
+                name
+            ))),
+        }
     }
 
-    pub fn white() -> Self {
-        Self {
-            name: Some("white".to_string()),
-            code: "#ffffff".to_string(),
+    /// `currentColor`は要素の`color`によって決まるので、ここでは扱わない
+    pub fn from_component_value(value: &ComponentValue) -> Result<Self, Error> {
+        match value {
+            ComponentValue::Ident(name) => Self::from_name(name),
+            ComponentValue::HashToken(code) => Self::from_code(code),
+            ComponentValue::Function(name, arguments) => Self::from_function(name, arguments),
+            _ => Err(Error::UnexpectedInput(format!(
+                "{:?} is not a color",
+                value
+            ))),
         }
     }
 
+    pub fn white() -> Self {
+        Self::from_u32(0xffffff)
+    }
+
     pub fn black() -> Self {
-        Self {
-            name: Some("black".to_string()),
-            code: "#000000".to_string(),
-        }
+        Self::from_u32(0x000000)
+    }
+
+    pub fn transparent() -> Self {
+        Self::new(0, 0, 0, 0)
+    }
+
+    pub fn r(&self) -> u8 {
+        self.r
+    }
+
+    pub fn g(&self) -> u8 {
+        self.g
+    }
+
+    pub fn b(&self) -> u8 {
+        self.b
+    }
+
+    /// 不透明度。0は完全に透明、255は不透明
+    pub fn alpha(&self) -> u8 {
+        self.a
+    }
+
+    pub fn is_transparent(&self) -> bool {
+        self.a == 0
     }
 
+    /// 不透明度を除いた0xRRGGBB形式の値
     pub fn code_u32(&self) -> u32 {
-        u32::from_str_radix(&self.code.trim_start_matches('#'), 16).unwrap()
+        (self.r as u32) << 16 | (self.g as u32) << 8 | self.b as u32
+    }
+
+    /// 不透明な`background`の上にこの色を重ねた色
+    pub fn composite_over(&self, background: Color) -> Color {
+        let a = self.a as u32;
+        let mix = |src: u8, dst: u8| ((src as u32 * a + dst as u32 * (255 - a) + 127) / 255) as u8;
+        Self::new(
+            mix(self.r, background.r),
+            mix(self.g, background.g),
+            mix(self.b, background.b),
+            255,
+        )
+    }
+}
+
+/// 色相(度)、彩度、明度(0から1)をRGBに変換する
+/// https://www.w3.org/TR/css-color-4/#hsl-to-rgb
+fn hsl_to_rgb(hue: f64, saturation: f64, lightness: f64) -> (u8, u8, u8) {
+    let mut hue = hue - ((hue / 360.0) as i64 as f64) * 360.0;
+    if hue < 0.0 {
+        hue += 360.0;
     }
+
+    let f = |n: f64| {
+        let mut k = n + hue / 30.0;
+        if k >= 12.0 {
+            k -= 12.0;
+        }
+        let a = saturation * lightness.min(1.0 - lightness);
+        let v = lightness - a * (k - 3.0).min(9.0 - k).min(1.0).max(-1.0);
+        to_channel(v * 255.0)
+    };
+
+    (f(0.0), f(8.0), f(4.0))
 }
 
 #[derive(Debug, Copy, Clone, PartialEq)]
@@ -236,17 +1168,49 @@ pub enum FontSize {
     Medium,
     XLarge,
     XXLarge,
+    Px(i64),
 }
 
 impl FontSize {
-    fn default(node: &Rc<RefCell<Node>>) -> Self {
-        match &node.borrow().kind() {
-            node::NodeKind::Element(element) => match element.kind() {
-                ElementKind::H1 => Self::XXLarge,
-                ElementKind::H2 => Self::XLarge,
-                _ => Self::Medium,
+    /// emやパーセンテージは`parent_px`(親要素のフォントサイズ)に対する倍率として計算する
+    pub fn from_component_value(value: &ComponentValue, parent_px: i64) -> Result<Self, Error> {
+        if let ComponentValue::Ident(ident) = value {
+            return match ident.as_str() {
+                "medium" => Ok(Self::Medium),
+                "x-large" => Ok(Self::XLarge),
+                "xx-large" => Ok(Self::XXLarge),
+                "xx-small" => Ok(Self::Px(9)),
+                "x-small" => Ok(Self::Px(10)),
+                "small" => Ok(Self::Px(13)),
+                "large" => Ok(Self::Px(18)),
+                "smaller" => Ok(Self::Px(parent_px * 5 / 6)),
+                "larger" => Ok(Self::Px(parent_px * 6 / 5)),
+                _ => Err(Error::UnexpectedInput(format!(
+                    "font-size {:?} is not supported yet",
+                    ident
+                ))),
+            };
+        }
+
+        match Length::from_component_value(value)? {
+            Length::Auto => Err(Error::UnexpectedInput("font-size can't be auto".into())),
+            length => match length.to_px(parent_px, parent_px) {
+                Some(px) if px >= 0 => Ok(Self::Px(px)),
+                _ => Err(Error::UnexpectedInput(format!(
+                    "invalid font-size {:?}",
+                    value
+                ))),
             },
-            _ => Self::Medium,
+        }
+    }
+
+    /// ピクセル単位の大きさ
+    pub fn to_px(&self) -> i64 {
+        match self {
+            FontSize::Medium => CHAR_HEIGHT,
+            FontSize::XLarge => CHAR_HEIGHT * 2,
+            FontSize::XXLarge => CHAR_HEIGHT * 3,
+            FontSize::Px(px) => *px,
         }
     }
 }
@@ -255,28 +1219,40 @@ impl FontSize {
 pub enum DisplayType {
     Block,
     Inline,
+    InlineBlock,
+    FlowRoot,
+    Flex,
+    InlineFlex,
+    Table,
+    TableCaption,
+    TableHeaderGroup,
+    TableRowGroup,
+    TableFooterGroup,
+    TableRow,
+    TableCell,
+    ListItem,
     DisplayNone,
 }
 
-impl DisplayType {
-    fn default(node: &Rc<RefCell<Node>>) -> Self {
-        match &node.borrow().kind() {
-            NodeKind::Document => Self::Block,
-            NodeKind::Element(e) => {
-                if e.is_block_element() {
-                    DisplayType::Block
-                } else {
-                    DisplayType::Inline
-                }
-            }
-            NodeKind::Text(_) => Self::Inline,
-        }
-    }
+impl FromStr for DisplayType {
+    type Err = Error;
 
-    pub fn from_str(s: &str) -> Result<Self, Error> {
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
         match s {
             "block" => Ok(Self::Block),
             "inline" => Ok(Self::Inline),
+            "inline-block" => Ok(Self::InlineBlock),
+            "flow-root" => Ok(Self::FlowRoot),
+            "flex" => Ok(Self::Flex),
+            "inline-flex" => Ok(Self::InlineFlex),
+            "table" => Ok(Self::Table),
+            "table-caption" => Ok(Self::TableCaption),
+            "table-header-group" => Ok(Self::TableHeaderGroup),
+            "table-row-group" => Ok(Self::TableRowGroup),
+            "table-footer-group" => Ok(Self::TableFooterGroup),
+            "table-row" => Ok(Self::TableRow),
+            "table-cell" => Ok(Self::TableCell),
+            "list-item" => Ok(Self::ListItem),
             "none" => Ok(Self::DisplayNone),
             _ => Err(Error::UnexpectedInput(format!(
                 "display {:?} is not supported yet",
@@ -292,14 +1268,850 @@ pub enum TextDecoration {
     Underline,
 }
 
-impl TextDecoration {
-    fn default(node: &Rc<RefCell<Node>>) -> Self {
-        match &node.borrow().kind() {
-            NodeKind::Element(element) => match element.kind() {
-                ElementKind::A => TextDecoration::Underline,
-                _ => TextDecoration::None,
+impl FromStr for TextDecoration {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "none" => Ok(Self::None),
+            "underline" => Ok(Self::Underline),
+            _ => Err(Error::UnexpectedInput(format!(
+                "text-decoration {:?} is not supported yet",
+                s
+            ))),
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum FontWeight {
+    Normal,
+    Bold,
+}
+
+impl FontWeight {
+    /// 太さは2段階しか扱わないので、数値の太さは600以上を太字とする
+    pub fn from_component_value(value: &ComponentValue) -> Result<Self, Error> {
+        match value {
+            ComponentValue::Ident(ident) => match ident.as_str() {
+                "normal" => Ok(Self::Normal),
+                "bold" => Ok(Self::Bold),
+                "bolder" => Ok(Self::Bold),
+                "lighter" => Ok(Self::Normal),
+                _ => Err(Error::UnexpectedInput(format!(
+                    "font-weight {:?} is not supported yet",
+                    ident
+                ))),
+            },
+            ComponentValue::Number(n) if (1.0..=1000.0).contains(n) => {
+                if *n >= 600.0 {
+                    Ok(Self::Bold)
+                } else {
+                    Ok(Self::Normal)
+                }
+            }
+            _ => Err(Error::UnexpectedInput(format!(
+                "invalid font-weight {:?}",
+                value
+            ))),
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum FontStyle {
+    Normal,
+    Italic,
+    Oblique,
+}
+
+impl FromStr for FontStyle {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "normal" => Ok(Self::Normal),
+            "italic" => Ok(Self::Italic),
+            "oblique" => Ok(Self::Oblique),
+            _ => Err(Error::UnexpectedInput(format!(
+                "font-style {:?} is not supported yet",
+                s
+            ))),
+        }
+    }
+}
+
+/// `font-family: "Noto Sans JP", Arial, sans-serif;` のようなカンマ区切りのリストを読み込む
+/// 引用符のない名前が複数の識別子からなる場合は、空白でつなげる
+/// `flex`の一括指定を、flex-grow、flex-shrink、flex-basisの値に分ける
+/// 数値だけを指定した場合、flex-basisは0になる
+/// https://www.w3.org/TR/css-flexbox-1/#flex-property
+pub fn parse_flex(values: &[ComponentValue]) -> Result<(f64, f64, Length), Error> {
+    if let [ComponentValue::Ident(ident)] = values {
+        match ident.as_str() {
+            "none" => return Ok((0.0, 0.0, Length::Auto)),
+            "auto" => return Ok((1.0, 1.0, Length::Auto)),
+            _ => {}
+        }
+    }
+
+    let mut factors = Vec::new();
+    let mut basis = None;
+    for value in values {
+        match value {
+            ComponentValue::Number(n) if *n >= 0.0 && factors.len() < 2 && basis.is_none() => {
+                factors.push(*n)
+            }
+            _ if basis.is_none() => basis = Some(Length::from_component_value(value)?),
+            _ => return Err(Error::UnexpectedInput(format!("invalid flex {:?}", values))),
+        }
+    }
+    if factors.is_empty() && basis.is_none() {
+        return Err(Error::UnexpectedInput("empty flex value".into()));
+    }
+
+    Ok((
+        factors.first().copied().unwrap_or(1.0),
+        factors.get(1).copied().unwrap_or(1.0),
+        basis.unwrap_or(Length::Px(0.0)),
+    ))
+}
+
+pub fn parse_font_family(values: &[ComponentValue]) -> Result<Vec<String>, Error> {
+    let mut families = Vec::new();
+    let mut current = String::new();
+    for value in values {
+        match value {
+            ComponentValue::Ident(name) | ComponentValue::StringToken(name) => {
+                if !current.is_empty() {
+                    current.push(' ');
+                }
+                current.push_str(name);
+            }
+            ComponentValue::Delim(',') => {
+                if current.is_empty() {
+                    return Err(Error::UnexpectedInput("empty font family name".into()));
+                }
+                families.push(current);
+                current = String::new();
+            }
+            _ => {
+                return Err(Error::UnexpectedInput(format!(
+                    "invalid font-family {:?}",
+                    value
+                )))
+            }
+        }
+    }
+    if current.is_empty() {
+        return Err(Error::UnexpectedInput("empty font family name".into()));
+    }
+    families.push(current);
+
+    Ok(families)
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum LineHeight {
+    /// フォントが推奨する行の高さ
+    Normal,
+    /// フォントサイズに対する倍率
+    Number(f64),
+    Length(Length),
+}
+
+impl LineHeight {
+    pub fn from_component_value(value: &ComponentValue) -> Result<Self, Error> {
+        match value {
+            ComponentValue::Ident(ident) if ident == "normal" => Ok(Self::Normal),
+            ComponentValue::Number(n) if *n >= 0.0 => Ok(Self::Number(*n)),
+            _ => match Length::from_component_value(value)? {
+                Length::Auto => Err(Error::UnexpectedInput("line-height can't be auto".into())),
+                Length::Percent(n) => Ok(Self::Length(Length::Em(n / 100.0))),
+                length => Ok(Self::Length(length)),
+            },
+        }
+    }
+
+    /// ピクセル単位の行の高さ
+    /// normalの場合はNoneを返し、フォントの値を使う
+    pub fn to_px(&self, font_size: i64) -> Option<i64> {
+        match self {
+            LineHeight::Normal => None,
+            LineHeight::Number(n) => Some((*n * font_size as f64) as i64),
+            LineHeight::Length(length) => length.to_px(font_size, font_size),
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum TextAlign {
+    Left,
+    Center,
+    Right,
+    Justify,
+}
+
+impl FromStr for TextAlign {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "left" | "start" => Ok(Self::Left),
+            "center" => Ok(Self::Center),
+            "right" | "end" => Ok(Self::Right),
+            "justify" => Ok(Self::Justify),
+            _ => Err(Error::UnexpectedInput(format!(
+                "text-align {:?} is not supported yet",
+                s
+            ))),
+        }
+    }
+}
+
+/// 空白と改行をまとめるかどうかと、自動的に改行するかどうか
+/// https://www.w3.org/TR/css-text-3/#white-space-property
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum WhiteSpace {
+    Normal,
+    Pre,
+    Nowrap,
+    PreWrap,
+    PreLine,
+}
+
+impl WhiteSpace {
+    /// 連続する空白を1つにまとめ、行頭と行末の空白を取り除くかどうか
+    pub fn collapses_spaces(&self) -> bool {
+        matches!(self, Self::Normal | Self::Nowrap | Self::PreLine)
+    }
+
+    /// 改行文字の位置で改行するかどうか
+    pub fn preserves_newlines(&self) -> bool {
+        matches!(self, Self::Pre | Self::PreWrap | Self::PreLine)
+    }
+
+    /// 行に収まらない場合に、空白の後などで自動的に改行するかどうか
+    pub fn wraps(&self) -> bool {
+        matches!(self, Self::Normal | Self::PreWrap | Self::PreLine)
+    }
+}
+
+impl FromStr for WhiteSpace {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "normal" => Ok(Self::Normal),
+            "pre" => Ok(Self::Pre),
+            "nowrap" => Ok(Self::Nowrap),
+            "pre-wrap" => Ok(Self::PreWrap),
+            "pre-line" => Ok(Self::PreLine),
+            _ => Err(Error::UnexpectedInput(format!(
+                "white-space {:?} is not supported yet",
+                s
+            ))),
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Length {
+    Auto,
+    Px(f64),
+    Em(f64),
+    Percent(f64),
+}
+
+impl Length {
+    pub fn from_component_value(value: &ComponentValue) -> Result<Self, Error> {
+        match value {
+            ComponentValue::Ident(ident) if ident == "auto" => Ok(Self::Auto),
+            ComponentValue::Number(n) => Ok(Self::Px(*n)),
+            ComponentValue::Percentage(n) => Ok(Self::Percent(*n)),
+            ComponentValue::Dimension(n, unit) => match unit.as_str() {
+                "px" => Ok(Self::Px(*n)),
+                "em" | "rem" => Ok(Self::Em(*n)),
+                "pt" => Ok(Self::Px(*n * 4.0 / 3.0)),
+                _ => Err(Error::UnexpectedInput(format!(
+                    "unit {:?} is not supported yet",
+                    unit
+                ))),
             },
-            _ => TextDecoration::None,
+            _ => Err(Error::UnexpectedInput(format!(
+                "{:?} is not a length",
+                value
+            ))),
+        }
+    }
+
+    /// パーセンテージは`percentage_basis`に対する割合として、emは`font_size`に対する倍率として計算する
+    /// autoの場合はNoneを返す
+    pub fn to_px(&self, percentage_basis: i64, font_size: i64) -> Option<i64> {
+        match self {
+            Length::Auto => None,
+            Length::Px(n) => Some(*n as i64),
+            Length::Em(n) => Some((*n * font_size as f64) as i64),
+            Length::Percent(n) => Some((*n * percentage_basis as f64 / 100.0) as i64),
+        }
+    }
+
+    pub fn is_auto(&self) -> bool {
+        *self == Length::Auto
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct BoxEdges {
+    pub top: Length,
+    pub right: Length,
+    pub bottom: Length,
+    pub left: Length,
+}
+
+impl BoxEdges {
+    pub fn zero() -> Self {
+        Self::all(Length::Px(0.0))
+    }
+
+    pub fn all(length: Length) -> Self {
+        Self {
+            top: length,
+            right: length,
+            bottom: length,
+            left: length,
+        }
+    }
+
+    /// `margin: 1px 2px 3px;` のような1〜4個の値を上、右、下、左に展開する
+    pub fn from_shorthand(values: &[Length]) -> Option<Self> {
+        let (top, right, bottom, left) = match values {
+            [a] => (*a, *a, *a, *a),
+            [a, b] => (*a, *b, *a, *b),
+            [a, b, c] => (*a, *b, *c, *b),
+            [a, b, c, d] => (*a, *b, *c, *d),
+            _ => return None,
+        };
+        Some(Self {
+            top,
+            right,
+            bottom,
+            left,
+        })
+    }
+
+    /// "top"、"right"、"bottom"、"left"のいずれかの辺の値
+    pub fn side(&self, side: &str) -> Length {
+        match side {
+            "top" => self.top,
+            "right" => self.right,
+            "bottom" => self.bottom,
+            _ => self.left,
+        }
+    }
+
+    /// "top"、"right"、"bottom"、"left"のいずれかの辺を更新する
+    pub fn set_side(&mut self, side: &str, length: Length) {
+        match side {
+            "top" => self.top = length,
+            "right" => self.right = length,
+            "bottom" => self.bottom = length,
+            "left" => self.left = length,
+            _ => {}
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum BorderStyle {
+    None,
+    Solid,
+    Dashed,
+    Dotted,
+}
+
+impl FromStr for BorderStyle {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "none" | "hidden" => Ok(Self::None),
+            "solid" | "double" | "groove" | "ridge" | "inset" | "outset" => Ok(Self::Solid),
+            "dashed" => Ok(Self::Dashed),
+            "dotted" => Ok(Self::Dotted),
+            _ => Err(Error::UnexpectedInput(format!(
+                "border-style {:?} is not supported yet",
+                s
+            ))),
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Overflow {
+    Visible,
+    Hidden,
+    Scroll,
+    Auto,
+}
+
+impl FromStr for Overflow {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "visible" => Ok(Self::Visible),
+            "hidden" | "clip" => Ok(Self::Hidden),
+            "scroll" => Ok(Self::Scroll),
+            "auto" => Ok(Self::Auto),
+            _ => Err(Error::UnexpectedInput(format!(
+                "overflow {:?} is not supported yet",
+                s
+            ))),
         }
     }
 }
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Float {
+    None,
+    Left,
+    Right,
+}
+
+impl FromStr for Float {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "none" => Ok(Self::None),
+            "left" => Ok(Self::Left),
+            "right" => Ok(Self::Right),
+            _ => Err(Error::UnexpectedInput(format!(
+                "float {:?} is not supported yet",
+                s
+            ))),
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Clear {
+    None,
+    Left,
+    Right,
+    Both,
+}
+
+impl FromStr for Clear {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "none" => Ok(Self::None),
+            "left" => Ok(Self::Left),
+            "right" => Ok(Self::Right),
+            "both" => Ok(Self::Both),
+            _ => Err(Error::UnexpectedInput(format!(
+                "clear {:?} is not supported yet",
+                s
+            ))),
+        }
+    }
+}
+
+impl Clear {
+    /// `float`のフロートの下まで移動する必要があるかどうか
+    pub fn clears(&self, float: Float) -> bool {
+        matches!(
+            (self, float),
+            (Self::Left, Float::Left) | (Self::Right, Float::Right) | (Self::Both, _)
+        )
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Position {
+    Static,
+    Relative,
+    Absolute,
+    Fixed,
+    Sticky,
+}
+
+impl FromStr for Position {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "static" => Ok(Self::Static),
+            "relative" => Ok(Self::Relative),
+            "absolute" => Ok(Self::Absolute),
+            "fixed" => Ok(Self::Fixed),
+            "sticky" | "-webkit-sticky" => Ok(Self::Sticky),
+            _ => Err(Error::UnexpectedInput(format!(
+                "position {:?} is not supported yet",
+                s
+            ))),
+        }
+    }
+}
+
+impl Position {
+    /// 通常フローから取り除かれ、包含ブロックをもとに配置されるかどうか
+    pub fn is_absolute(&self) -> bool {
+        matches!(self, Self::Absolute | Self::Fixed)
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ZIndex {
+    Auto,
+    Index(i64),
+}
+
+impl ZIndex {
+    pub fn from_component_value(value: &ComponentValue) -> Result<Self, Error> {
+        match value {
+            ComponentValue::Ident(ident) if ident == "auto" => Ok(Self::Auto),
+            ComponentValue::Number(n) if *n == (*n as i64) as f64 => Ok(Self::Index(*n as i64)),
+            _ => Err(Error::UnexpectedInput(format!(
+                "z-index {:?} is not supported yet",
+                value
+            ))),
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum BorderCollapse {
+    Separate,
+    Collapse,
+}
+
+impl FromStr for BorderCollapse {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "separate" => Ok(Self::Separate),
+            "collapse" => Ok(Self::Collapse),
+            _ => Err(Error::UnexpectedInput(format!(
+                "border-collapse {:?} is not supported yet",
+                s
+            ))),
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum ListStyleType {
+    Disc,
+    Circle,
+    Square,
+    Decimal,
+    LowerAlpha,
+    UpperAlpha,
+    LowerRoman,
+    UpperRoman,
+    None,
+}
+
+impl FromStr for ListStyleType {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "disc" => Ok(Self::Disc),
+            "circle" => Ok(Self::Circle),
+            "square" => Ok(Self::Square),
+            "decimal" => Ok(Self::Decimal),
+            "lower-alpha" | "lower-latin" => Ok(Self::LowerAlpha),
+            "upper-alpha" | "upper-latin" => Ok(Self::UpperAlpha),
+            "lower-roman" => Ok(Self::LowerRoman),
+            "upper-roman" => Ok(Self::UpperRoman),
+            "none" => Ok(Self::None),
+            _ => Err(Error::UnexpectedInput(format!(
+                "list-style-type {:?} is not supported yet",
+                s
+            ))),
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum ListStylePosition {
+    Outside,
+    Inside,
+}
+
+impl FromStr for ListStylePosition {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "outside" => Ok(Self::Outside),
+            "inside" => Ok(Self::Inside),
+            _ => Err(Error::UnexpectedInput(format!(
+                "list-style-position {:?} is not supported yet",
+                s
+            ))),
+        }
+    }
+}
+
+/// 祖先にあるul要素とol要素の数
+fn list_nesting_depth(node: &Rc<RefCell<Node>>) -> usize {
+    let mut depth = 0;
+    let mut parent = node.borrow().parent().upgrade();
+    while let Some(p) = parent {
+        if matches!(
+            p.borrow().element_kind(),
+            Some(ElementKind::Ul) | Some(ElementKind::Ol)
+        ) {
+            depth += 1;
+        }
+        parent = p.borrow().parent().upgrade();
+    }
+    depth
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum FlexDirection {
+    Row,
+    RowReverse,
+    Column,
+    ColumnReverse,
+}
+
+impl FromStr for FlexDirection {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "row" => Ok(Self::Row),
+            "row-reverse" => Ok(Self::RowReverse),
+            "column" => Ok(Self::Column),
+            "column-reverse" => Ok(Self::ColumnReverse),
+            _ => Err(Error::UnexpectedInput(format!(
+                "flex-direction {:?} is not supported yet",
+                s
+            ))),
+        }
+    }
+}
+
+impl FlexDirection {
+    /// 主軸が横方向かどうか
+    pub fn is_row(&self) -> bool {
+        matches!(self, Self::Row | Self::RowReverse)
+    }
+
+    pub fn is_reverse(&self) -> bool {
+        matches!(self, Self::RowReverse | Self::ColumnReverse)
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum FlexWrap {
+    NoWrap,
+    Wrap,
+    WrapReverse,
+}
+
+impl FromStr for FlexWrap {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "nowrap" => Ok(Self::NoWrap),
+            "wrap" => Ok(Self::Wrap),
+            "wrap-reverse" => Ok(Self::WrapReverse),
+            _ => Err(Error::UnexpectedInput(format!(
+                "flex-wrap {:?} is not supported yet",
+                s
+            ))),
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum JustifyContent {
+    FlexStart,
+    FlexEnd,
+    Center,
+    SpaceBetween,
+    SpaceAround,
+    SpaceEvenly,
+}
+
+impl FromStr for JustifyContent {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "flex-start" | "start" | "left" | "normal" => Ok(Self::FlexStart),
+            "flex-end" | "end" | "right" => Ok(Self::FlexEnd),
+            "center" => Ok(Self::Center),
+            "space-between" => Ok(Self::SpaceBetween),
+            "space-around" => Ok(Self::SpaceAround),
+            "space-evenly" => Ok(Self::SpaceEvenly),
+            _ => Err(Error::UnexpectedInput(format!(
+                "justify-content {:?} is not supported yet",
+                s
+            ))),
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum AlignItems {
+    Stretch,
+    FlexStart,
+    FlexEnd,
+    Center,
+}
+
+impl FromStr for AlignItems {
+    type Err = Error;
+
+    /// ベースラインの位置を揃える処理はないので、baselineはflex-startとして扱う
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "stretch" | "normal" => Ok(Self::Stretch),
+            "flex-start" | "start" | "baseline" => Ok(Self::FlexStart),
+            "flex-end" | "end" => Ok(Self::FlexEnd),
+            "center" => Ok(Self::Center),
+            _ => Err(Error::UnexpectedInput(format!(
+                "align-items {:?} is not supported yet",
+                s
+            ))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::string::ToString;
+
+    #[test]
+    fn test_named_colors() {
+        assert_eq!(148, NAMED_COLORS.len());
+        assert_eq!(
+            Color::from_u32(0x663399),
+            Color::from_name("RebeccaPurple").expect("should be a color")
+        );
+        assert_eq!(
+            Color::new(0, 0, 0, 0),
+            Color::from_name("transparent").expect("should be a color")
+        );
+        assert!(Color::from_name("unknown").is_err());
+    }
+
+    #[test]
+    fn test_hex_colors() {
+        assert_eq!(
+            Color::new(0xff, 0x88, 0x00, 0xff),
+            Color::from_code("#f80").expect("should be a color")
+        );
+        assert_eq!(
+            Color::new(0xff, 0x88, 0x00, 0x44),
+            Color::from_code("#f804").expect("should be a color")
+        );
+        assert_eq!(
+            Color::new(0x12, 0x34, 0x56, 0x78),
+            Color::from_code("#12345678").expect("should be a color")
+        );
+        assert_eq!(0x123456, Color::from_code("#123456").unwrap().code_u32());
+        assert!(Color::from_code("#12345").is_err());
+        assert!(Color::from_code("#ggg").is_err());
+    }
+
+    #[test]
+    fn test_rgb_function() {
+        let number = |v: f64| ComponentValue::Number(v);
+        let legacy = [
+            number(255.0),
+            ComponentValue::Delim(','),
+            ComponentValue::Percentage(50.0),
+            ComponentValue::Delim(','),
+            number(0.0),
+            ComponentValue::Delim(','),
+            number(0.5),
+        ];
+        assert_eq!(
+            Color::new(255, 128, 0, 128),
+            Color::from_function("rgba", &legacy).expect("should be a color")
+        );
+
+        let modern = [
+            number(300.0),
+            number(0.0),
+            number(-1.0),
+            ComponentValue::Delim('/'),
+            ComponentValue::Percentage(25.0),
+        ];
+        assert_eq!(
+            Color::new(255, 0, 0, 64),
+            Color::from_function("rgb", &modern).expect("should be a color")
+        );
+        assert!(Color::from_function("rgb", &[number(0.0), number(0.0)]).is_err());
+    }
+
+    #[test]
+    fn test_hsl_function() {
+        let hsl = |hue: ComponentValue| {
+            Color::from_function(
+                "hsl",
+                &[
+                    hue,
+                    ComponentValue::Percentage(100.0),
+                    ComponentValue::Percentage(50.0),
+                ],
+            )
+            .expect("should be a color")
+        };
+        assert_eq!(Color::from_u32(0xff0000), hsl(ComponentValue::Number(0.0)));
+        assert_eq!(
+            Color::from_u32(0x00ff00),
+            hsl(ComponentValue::Number(120.0))
+        );
+        assert_eq!(
+            Color::from_u32(0x0000ff),
+            hsl(ComponentValue::Dimension(-120.0, "deg".to_string()))
+        );
+        assert_eq!(
+            Color::from_u32(0x00ff00),
+            hsl(ComponentValue::Dimension(1.0 / 3.0, "turn".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_composite_over() {
+        let translucent = Color::new(255, 0, 0, 128);
+        assert_eq!(
+            Color::from_u32(0x80007f),
+            translucent.composite_over(Color::from_u32(0x0000ff))
+        );
+    }
+
+    #[test]
+    fn test_parse_flex() {
+        let n = |v: f64| ComponentValue::Number(v);
+        assert_eq!((1.0, 1.0, Length::Px(0.0)), parse_flex(&[n(1.0)]).unwrap());
+        assert_eq!(
+            (2.0, 0.0, Length::Percent(50.0)),
+            parse_flex(&[n(2.0), n(0.0), ComponentValue::Percentage(50.0)]).unwrap()
+        );
+        assert_eq!(
+            (1.0, 1.0, Length::Px(30.0)),
+            parse_flex(&[ComponentValue::Dimension(30.0, "px".to_string())]).unwrap()
+        );
+        assert_eq!(
+            (0.0, 0.0, Length::Auto),
+            parse_flex(&[ComponentValue::Ident("none".to_string())]).unwrap()
+        );
+        assert!(parse_flex(&[]).is_err());
+    }
+}
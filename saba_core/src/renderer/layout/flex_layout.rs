@@ -0,0 +1,885 @@
+//! フレックスコンテナの子をフレックスアイテムとして並べる
+//! https://www.w3.org/TR/css-flexbox-1/#layout-algorithm
+
+use crate::font::collection::FontCollection;
+use crate::renderer::layout::block_layout::layout_formatting_context_root;
+use crate::renderer::layout::block_layout::max_content_width;
+use crate::renderer::layout::computed_style::AlignItems;
+use crate::renderer::layout::computed_style::FlexDirection;
+use crate::renderer::layout::computed_style::FlexWrap;
+use crate::renderer::layout::computed_style::JustifyContent;
+use crate::renderer::layout::computed_style::Length;
+use crate::renderer::layout::computed_style::TextAlign;
+use crate::renderer::layout::inline_layout::InlineFormattingContext;
+use crate::renderer::layout::layout_object::LayoutObject;
+use crate::renderer::layout::layout_object::LayoutObjectKind;
+use crate::renderer::layout::layout_object::LayoutPoint;
+use alloc::rc::Rc;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::cell::RefCell;
+
+#[derive(Debug, Clone)]
+enum FlexItemContent {
+    Box(Rc<RefCell<LayoutObject>>),
+    /// 連続するテキストは1つの無名のフレックスアイテムになる
+    Text(Vec<Rc<RefCell<LayoutObject>>>),
+}
+
+/// 主軸(main)と交差軸(cross)に分けた、フレックスアイテムの大きさ
+/// 大きさはすべてボーダーボックスの値
+#[derive(Debug, Clone)]
+struct FlexItem {
+    content: FlexItemContent,
+    grow: f64,
+    shrink: f64,
+    /// 主軸の始点側と終点側のマージン
+    main_margin: (i64, i64),
+    /// autoのマージンは余った領域を埋める
+    main_margin_auto: (bool, bool),
+    cross_margin: (i64, i64),
+    /// 主軸方向と交差軸方向のボーダーとパディングの和
+    main_extra: i64,
+    cross_extra: i64,
+    /// 交差軸方向の大きさが指定されていない場合、ラインの大きさまで引き伸ばせる
+    cross_auto: bool,
+    base_size: i64,
+    main_size: i64,
+    cross_size: i64,
+}
+
+impl FlexItem {
+    fn outer_main_size(&self) -> i64 {
+        self.main_size + self.main_margin.0 + self.main_margin.1
+    }
+
+    fn outer_cross_size(&self) -> i64 {
+        self.cross_size + self.cross_margin.0 + self.cross_margin.1
+    }
+}
+
+/// フレックスコンテナの中身を配置し、コンテンツボックスの高さを返す
+/// `content_height`はコンテナに指定された高さ
+pub fn layout_flex_container(
+    node: &Rc<RefCell<LayoutObject>>,
+    content_x: i64,
+    content_y: i64,
+    content_width: i64,
+    content_height: Option<i64>,
+    fonts: &FontCollection,
+) -> i64 {
+    let (style, font_size) = {
+        let n = node.borrow();
+        (n.style(), n.font_size_px())
+    };
+    let direction = style.flex_direction();
+    let is_row = direction.is_row();
+    let align = style.align_items();
+    let text_align = style.text_align();
+    let gap = |l: Length, basis: i64| l.to_px(basis, font_size).unwrap_or(0);
+    let row_gap = gap(style.row_gap(), content_height.unwrap_or(0));
+    let column_gap = gap(style.column_gap(), content_width);
+    let (main_gap, cross_gap) = if is_row {
+        (column_gap, row_gap)
+    } else {
+        (row_gap, column_gap)
+    };
+    let container_main = if is_row {
+        Some(content_width)
+    } else {
+        content_height
+    };
+    let container_cross = if is_row {
+        content_height
+    } else {
+        Some(content_width)
+    };
+
+    let mut items = collect_items(node, LayoutPoint::new(content_x, content_y))
+        .into_iter()
+        .map(|content| {
+            new_item(
+                content,
+                direction,
+                align,
+                content_width,
+                content_height,
+                text_align,
+                fonts,
+            )
+        })
+        .collect::<Vec<_>>();
+
+    // 主軸の大きさが決まっていない場合は改行しない
+    let lines = match (style.flex_wrap(), container_main) {
+        (FlexWrap::NoWrap, _) | (_, None) => vec![items.len()],
+        (_, Some(main)) => break_lines(&items, main, main_gap),
+    };
+
+    let mut start = 0;
+    let mut line_ranges = Vec::new();
+    for count in lines {
+        line_ranges.push(start..start + count);
+        start += count;
+    }
+
+    // ラインごとに伸縮させた大きさを決め、交差軸の大きさを測る
+    let mut line_cross_sizes = Vec::new();
+    for range in &line_ranges {
+        let line = &mut items[range.clone()];
+        match container_main {
+            Some(main) => resolve_flexible_lengths(line, main, main_gap),
+            None => line
+                .iter_mut()
+                .for_each(|item| item.main_size = item.base_size),
+        }
+        for item in line.iter_mut() {
+            if is_row {
+                item.cross_size = measure_cross_size(item, content_width, text_align, fonts);
+            }
+        }
+        let max_cross = line
+            .iter()
+            .map(|item| item.outer_cross_size())
+            .max()
+            .unwrap_or(0);
+        line_cross_sizes.push(max_cross);
+    }
+    // 1行のコンテナで交差軸の大きさが決まっている場合、ラインはコンテナと同じ大きさになる
+    if line_ranges.len() == 1 {
+        if let Some(cross) = container_cross {
+            line_cross_sizes[0] = cross;
+        }
+    }
+
+    let total_cross = line_cross_sizes.iter().sum::<i64>()
+        + cross_gap * (line_cross_sizes.len() as i64 - 1).max(0);
+    let mut used_main = 0;
+    let mut line_pos = 0;
+    for (range, line_cross) in line_ranges.iter().zip(line_cross_sizes) {
+        let line = &mut items[range.clone()];
+        for item in line.iter_mut() {
+            if align == AlignItems::Stretch && item.cross_auto {
+                item.cross_size = core::cmp::max(
+                    line_cross - item.cross_margin.0 - item.cross_margin.1,
+                    item.cross_extra,
+                );
+            }
+        }
+
+        let line_main = line.iter().map(|item| item.outer_main_size()).sum::<i64>()
+            + main_gap * (line.len() as i64 - 1).max(0);
+        used_main = core::cmp::max(used_main, line_main);
+        let main_positions = justify_line(
+            line,
+            container_main.unwrap_or(line_main),
+            main_gap,
+            style.justify_content(),
+        );
+
+        // wrap-reverseではラインを交差軸の終点側から積み重ねる
+        let wrap_reverse = style.flex_wrap() == FlexWrap::WrapReverse;
+        let physical_line_pos = if wrap_reverse {
+            container_cross.unwrap_or(total_cross) - line_pos - line_cross
+        } else {
+            line_pos
+        };
+
+        for (item, main_pos) in line.iter().zip(main_positions) {
+            let main_pos = if direction.is_reverse() {
+                container_main.unwrap_or(line_main) - main_pos - item.main_size
+            } else {
+                main_pos
+            };
+            let free = line_cross - item.outer_cross_size();
+            let mut cross_offset = match align {
+                AlignItems::Stretch | AlignItems::FlexStart => 0,
+                AlignItems::FlexEnd => free,
+                AlignItems::Center => free / 2,
+            };
+            if wrap_reverse {
+                cross_offset = free - cross_offset;
+            }
+            let cross_pos = physical_line_pos + cross_offset + item.cross_margin.0;
+
+            let (x, y) = if is_row {
+                (content_x + main_pos, content_y + cross_pos)
+            } else {
+                (content_x + cross_pos, content_y + main_pos)
+            };
+            layout_item(item, is_row, x, y, content_width, text_align, fonts);
+        }
+
+        line_pos += line_cross + cross_gap;
+    }
+
+    match content_height {
+        Some(height) => height,
+        None if is_row => total_cross,
+        None => used_main,
+    }
+}
+
+/// インフロー(in-flow)の子をフレックスアイテムにする
+/// 絶対位置指定の子はアイテムにせず、`static_point`(コンテナの左上)を静的位置にする
+fn collect_items(
+    node: &Rc<RefCell<LayoutObject>>,
+    static_point: LayoutPoint,
+) -> Vec<FlexItemContent> {
+    let mut items = Vec::new();
+    let mut text_run = Vec::new();
+    let mut child = node.borrow().first_child();
+    while let Some(c) = child {
+        if c.borrow().is_absolutely_positioned() {
+            c.borrow_mut().set_point(static_point);
+        } else if c.borrow().kind() == LayoutObjectKind::Text {
+            text_run.push(c.clone());
+        } else {
+            if !text_run.is_empty() {
+                items.push(FlexItemContent::Text(core::mem::take(&mut text_run)));
+            }
+            items.push(FlexItemContent::Box(c.clone()));
+        }
+        child = c.borrow().next_sibling();
+    }
+    if !text_run.is_empty() {
+        items.push(FlexItemContent::Text(text_run));
+    }
+    items
+}
+
+/// フレックスアイテムの余白と、伸縮させる前の大きさ(flex base size)を決める
+/// https://www.w3.org/TR/css-flexbox-1/#algo-main-item
+fn new_item(
+    content: FlexItemContent,
+    direction: FlexDirection,
+    align: AlignItems,
+    content_width: i64,
+    content_height: Option<i64>,
+    text_align: TextAlign,
+    fonts: &FontCollection,
+) -> FlexItem {
+    let is_row = direction.is_row();
+    let mut item = FlexItem {
+        content: content.clone(),
+        grow: 0.0,
+        shrink: 1.0,
+        main_margin: (0, 0),
+        main_margin_auto: (false, false),
+        cross_margin: (0, 0),
+        main_extra: 0,
+        cross_extra: 0,
+        cross_auto: true,
+        base_size: 0,
+        main_size: 0,
+        cross_size: 0,
+    };
+
+    let node = match content {
+        FlexItemContent::Box(node) => node,
+        FlexItemContent::Text(run) => {
+            let max_content = run.iter().map(|t| max_content_width(t, fonts)).sum();
+            if is_row {
+                item.base_size = max_content;
+            } else {
+                item.cross_size = match align {
+                    AlignItems::Stretch => content_width,
+                    _ => core::cmp::min(max_content, content_width),
+                };
+                item.base_size =
+                    InlineFormattingContext::new(&run, item.cross_size, text_align, fonts)
+                        .place(0, 0);
+            }
+            return item;
+        }
+    };
+
+    node.borrow_mut().set_override_size(None, None);
+    node.borrow_mut().resolve_box_model(content_width, true, 0);
+    let (style, margin, border, padding, font_size) = {
+        let n = node.borrow();
+        (
+            n.style(),
+            n.margin(),
+            n.border(),
+            n.padding(),
+            n.font_size_px(),
+        )
+    };
+    item.grow = style.flex_grow();
+    item.shrink = style.flex_shrink();
+    let auto = (
+        style.margin().left.is_auto(),
+        style.margin().top.is_auto(),
+        style.margin().right.is_auto(),
+        style.margin().bottom.is_auto(),
+    );
+    let horizontal_extra = border.horizontal() + padding.horizontal();
+    let vertical_extra = border.vertical() + padding.vertical();
+    let basis = match style.flex_basis() {
+        Length::Auto if is_row => style.width(),
+        Length::Auto => style.height(),
+        basis => basis,
+    };
+
+    if is_row {
+        item.main_margin = (margin.left, margin.right);
+        item.main_margin_auto = (auto.0, auto.2);
+        item.cross_margin = (margin.top, margin.bottom);
+        item.main_extra = horizontal_extra;
+        item.cross_extra = vertical_extra;
+        item.cross_auto = style.height().is_auto();
+        item.base_size = match basis.to_px(content_width, font_size) {
+            Some(size) => size + horizontal_extra,
+            None => max_content_width(&node, fonts) - margin.horizontal(),
+        };
+    } else {
+        item.main_margin = (margin.top, margin.bottom);
+        item.main_margin_auto = (auto.1, auto.3);
+        item.cross_margin = (margin.left, margin.right);
+        item.main_extra = vertical_extra;
+        item.cross_extra = horizontal_extra;
+        item.cross_auto = style.width().is_auto();
+
+        // 縦に並べる場合は、先に横幅を決めてから高さを測る
+        let available = content_width - margin.horizontal();
+        item.cross_size = match style.width().to_px(content_width, font_size) {
+            Some(width) => width + horizontal_extra,
+            None if align == AlignItems::Stretch => available,
+            None => core::cmp::min(
+                max_content_width(&node, fonts) - margin.horizontal(),
+                available,
+            ),
+        };
+        let specified = match basis {
+            // 高さが決まっていないコンテナに対するパーセンテージはautoとして扱う
+            Length::Percent(_) if content_height.is_none() => None,
+            basis => basis.to_px(content_height.unwrap_or(0), font_size),
+        };
+        item.base_size = match specified {
+            Some(size) => size + vertical_extra,
+            None => {
+                node.borrow_mut()
+                    .set_override_size(Some(item.cross_size), None);
+                layout_formatting_context_root(&node, LayoutPoint::new(0, 0), content_width, fonts);
+                let height = node.borrow().size().height();
+                height
+            }
+        };
+    }
+    if matches!(
+        direction,
+        FlexDirection::RowReverse | FlexDirection::ColumnReverse
+    ) {
+        item.main_margin = (item.main_margin.1, item.main_margin.0);
+        item.main_margin_auto = (item.main_margin_auto.1, item.main_margin_auto.0);
+    }
+
+    item
+}
+
+/// 主軸方向に収まるだけのアイテムを1行に入れ、各行のアイテム数を返す
+fn break_lines(items: &[FlexItem], container_main: i64, gap: i64) -> Vec<usize> {
+    let mut lines = Vec::new();
+    let mut count = 0;
+    let mut line_main = 0;
+    for item in items {
+        let outer = item.base_size + item.main_margin.0 + item.main_margin.1;
+        if count > 0 && line_main + gap + outer > container_main {
+            lines.push(count);
+            count = 0;
+            line_main = 0;
+        }
+        line_main += if count > 0 { gap + outer } else { outer };
+        count += 1;
+    }
+    lines.push(count);
+    lines
+}
+
+/// 余った領域をflex-growの比で分け、足りない領域をflex-shrinkとflex base sizeの積の比で縮める
+/// https://www.w3.org/TR/css-flexbox-1/#resolve-flexible-lengths
+fn resolve_flexible_lengths(line: &mut [FlexItem], container_main: i64, gap: i64) {
+    let gaps = gap * (line.len() as i64 - 1).max(0);
+    let outer_base = |item: &FlexItem| item.base_size + item.main_margin.0 + item.main_margin.1;
+    let free = container_main - gaps - line.iter().map(outer_base).sum::<i64>();
+    for item in line.iter_mut() {
+        item.main_size = item.base_size;
+    }
+
+    if free > 0 {
+        let total = line.iter().map(|item| item.grow).sum::<f64>();
+        if total <= 0.0 {
+            return;
+        }
+        // flex-growの和が1未満の場合は、余った領域の一部だけを分ける
+        let distributed = if total < 1.0 {
+            free as f64 * total
+        } else {
+            free as f64
+        };
+        // 端数が出ても合計が変わらないように、累積値を丸めて分ける
+        let mut accumulated = 0.0;
+        for item in line.iter_mut() {
+            let before = accumulated as i64;
+            accumulated += distributed * item.grow / total;
+            item.main_size += accumulated as i64 - before;
+        }
+        return;
+    }
+
+    // 縮めた結果がボーダーとパディングより小さくなるアイテムは固定し、残りのアイテムで縮め直す
+    let mut frozen = vec![false; line.len()];
+    loop {
+        let used = gaps
+            + line
+                .iter()
+                .zip(&frozen)
+                .map(|(item, frozen)| {
+                    if *frozen {
+                        item.outer_main_size()
+                    } else {
+                        outer_base(item)
+                    }
+                })
+                .sum::<i64>();
+        let remaining = container_main - used;
+        let scaled_total = line
+            .iter()
+            .zip(&frozen)
+            .filter(|(_, frozen)| !**frozen)
+            .map(|(item, _)| item.shrink * item.base_size as f64)
+            .sum::<f64>();
+        if remaining >= 0 || scaled_total <= 0.0 {
+            return;
+        }
+
+        let mut violated = false;
+        for (item, frozen) in line.iter_mut().zip(frozen.iter_mut()) {
+            if *frozen {
+                continue;
+            }
+            let ratio = item.shrink * item.base_size as f64 / scaled_total;
+            let size = item.base_size + (remaining as f64 * ratio) as i64;
+            if size < item.main_extra {
+                item.main_size = item.main_extra;
+                *frozen = true;
+                violated = true;
+            } else {
+                item.main_size = size;
+            }
+        }
+        if !violated {
+            return;
+        }
+    }
+}
+
+/// 主軸方向の大きさを決めたアイテムをレイアウトして、交差軸方向の大きさを測る
+fn measure_cross_size(
+    item: &FlexItem,
+    containing_width: i64,
+    text_align: TextAlign,
+    fonts: &FontCollection,
+) -> i64 {
+    match &item.content {
+        FlexItemContent::Box(node) => {
+            node.borrow_mut()
+                .set_override_size(Some(item.main_size), None);
+            layout_formatting_context_root(node, LayoutPoint::new(0, 0), containing_width, fonts);
+            let height = node.borrow().size().height();
+            height
+        }
+        FlexItemContent::Text(run) => {
+            InlineFormattingContext::new(run, item.main_size, text_align, fonts).place(0, 0)
+        }
+    }
+}
+
+/// justify-contentとautoのマージンに従って、ライン内のアイテムの主軸方向の位置を返す
+/// 位置は主軸の始点からボーダーボックスの始点までの距離
+fn justify_line(
+    line: &[FlexItem],
+    container_main: i64,
+    gap: i64,
+    justify: JustifyContent,
+) -> Vec<i64> {
+    let count = line.len() as i64;
+    let used =
+        line.iter().map(|item| item.outer_main_size()).sum::<i64>() + gap * (count - 1).max(0);
+    let free = container_main - used;
+
+    let auto_margins = line
+        .iter()
+        .map(|item| item.main_margin_auto.0 as i64 + item.main_margin_auto.1 as i64)
+        .sum::<i64>();
+    let auto_margin = if free > 0 && auto_margins > 0 {
+        free / auto_margins
+    } else {
+        0
+    };
+
+    let (offset, spacing) = if auto_margins > 0 && free > 0 {
+        // autoのマージンが余った領域を使うので、justify-contentは効かない
+        (0, 0)
+    } else {
+        match justify {
+            JustifyContent::FlexStart => (0, 0),
+            JustifyContent::FlexEnd => (free, 0),
+            JustifyContent::Center => (free / 2, 0),
+            JustifyContent::SpaceBetween if free > 0 && count > 1 => (0, free / (count - 1)),
+            JustifyContent::SpaceBetween => (0, 0),
+            JustifyContent::SpaceAround if free > 0 => (free / (count * 2), free / count),
+            JustifyContent::SpaceEvenly if free > 0 => (free / (count + 1), free / (count + 1)),
+            JustifyContent::SpaceAround | JustifyContent::SpaceEvenly => (free / 2, 0),
+        }
+    };
+
+    let mut positions = Vec::new();
+    let mut pos = offset;
+    for item in line {
+        if item.main_margin_auto.0 {
+            pos += auto_margin;
+        }
+        pos += item.main_margin.0;
+        positions.push(pos);
+        pos += item.main_size + item.main_margin.1 + gap + spacing;
+        if item.main_margin_auto.1 {
+            pos += auto_margin;
+        }
+    }
+    positions
+}
+
+/// 決まった大きさでアイテムをレイアウトし、ボーダーボックスの左上を(x, y)に合わせる
+fn layout_item(
+    item: &FlexItem,
+    is_row: bool,
+    x: i64,
+    y: i64,
+    containing_width: i64,
+    text_align: TextAlign,
+    fonts: &FontCollection,
+) {
+    let (width, height) = if is_row {
+        (item.main_size, item.cross_size)
+    } else {
+        (item.cross_size, item.main_size)
+    };
+
+    match &item.content {
+        FlexItemContent::Box(node) => {
+            let margin = node.borrow().margin();
+            let extra = if is_row {
+                item.cross_extra
+            } else {
+                item.main_extra
+            };
+            let content_height = height - extra;
+            node.borrow_mut()
+                .set_override_size(Some(width), Some(core::cmp::max(content_height, 0)));
+            layout_formatting_context_root(
+                node,
+                LayoutPoint::new(x - margin.left, y - margin.top),
+                containing_width,
+                fonts,
+            );
+        }
+        FlexItemContent::Text(run) => {
+            InlineFormattingContext::new(run, width, text_align, fonts).place(x, y);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::renderer::layout::layout_object::LayoutSize;
+    use crate::renderer::layout::layout_view::LayoutView;
+    use crate::renderer::layout::test_util::create_layout_view;
+    use alloc::string::String;
+    use alloc::string::ToString;
+
+    /// bodyの最初の子(フレックスコンテナ)と、その子のリスト
+    fn container_and_items(
+        view: &LayoutView,
+    ) -> (Rc<RefCell<LayoutObject>>, Vec<Rc<RefCell<LayoutObject>>>) {
+        let body = view.root().expect("root should exist");
+        let container = body.borrow().first_child().expect("container should exist");
+        let mut items = Vec::new();
+        let mut child = container.borrow().first_child();
+        while let Some(c) = child {
+            items.push(c.clone());
+            child = c.borrow().next_sibling();
+        }
+        (container, items)
+    }
+
+    fn points(items: &[Rc<RefCell<LayoutObject>>]) -> Vec<LayoutPoint> {
+        items.iter().map(|item| item.borrow().point()).collect()
+    }
+
+    fn flex_html(container: &str, items: &str, count: usize) -> String {
+        let mut html = String::from("<html><head><style>.c { display: flex; ");
+        html.push_str(container);
+        html.push_str(" } .i { ");
+        html.push_str(items);
+        html.push_str(" }</style></head><body><div class=\"c\">");
+        for _ in 0..count {
+            html.push_str("<div class=\"i\"></div>");
+        }
+        html.push_str("</div></body></html>");
+        html
+    }
+
+    #[test]
+    fn test_row_with_gap() {
+        let view = create_layout_view(flex_html("gap: 10px;", "width: 50px; height: 20px;", 3));
+        let (container, items) = container_and_items(&view);
+
+        assert_eq!(
+            vec![
+                LayoutPoint::new(0, 0),
+                LayoutPoint::new(60, 0),
+                LayoutPoint::new(120, 0)
+            ],
+            points(&items)
+        );
+        assert_eq!(LayoutSize::new(50, 20), items[0].borrow().size());
+        assert_eq!(LayoutSize::new(590, 20), container.borrow().size());
+    }
+
+    #[test]
+    fn test_justify_content() {
+        let view = create_layout_view(flex_html(
+            "justify-content: space-between;",
+            "width: 50px; height: 20px;",
+            3,
+        ));
+        let (_, items) = container_and_items(&view);
+        // (590 - 150) / 2 = 220
+        assert_eq!(
+            vec![
+                LayoutPoint::new(0, 0),
+                LayoutPoint::new(270, 0),
+                LayoutPoint::new(540, 0)
+            ],
+            points(&items)
+        );
+
+        let view = create_layout_view(flex_html(
+            "justify-content: center;",
+            "width: 50px; height: 20px;",
+            3,
+        ));
+        let (_, items) = container_and_items(&view);
+        assert_eq!(
+            vec![
+                LayoutPoint::new(220, 0),
+                LayoutPoint::new(270, 0),
+                LayoutPoint::new(320, 0)
+            ],
+            points(&items)
+        );
+
+        let view = create_layout_view(flex_html(
+            "justify-content: space-evenly; width: 200px;",
+            "width: 20px; height: 20px;",
+            3,
+        ));
+        let (_, items) = container_and_items(&view);
+        // (200 - 60) / 4 = 35
+        assert_eq!(
+            vec![
+                LayoutPoint::new(35, 0),
+                LayoutPoint::new(90, 0),
+                LayoutPoint::new(145, 0)
+            ],
+            points(&items)
+        );
+    }
+
+    #[test]
+    fn test_flex_grow() {
+        let html = r#"<html><head><style>
+            .c { display: flex; width: 300px; height: 10px; }
+            .a { flex: 1; }
+            .b { flex: 2; }
+            .fixed { width: 30px; }
+            </style></head>
+            <body><div class="c"><div class="a"></div><div class="fixed"></div><div class="b"></div></div></body></html>"#
+            .to_string();
+        let view = create_layout_view(html);
+        let (_, items) = container_and_items(&view);
+
+        // 残りの270pxを1:2で分ける
+        assert_eq!(LayoutSize::new(90, 10), items[0].borrow().size());
+        assert_eq!(LayoutPoint::new(90, 0), items[1].borrow().point());
+        assert_eq!(LayoutSize::new(180, 10), items[2].borrow().size());
+        assert_eq!(LayoutPoint::new(120, 0), items[2].borrow().point());
+    }
+
+    #[test]
+    fn test_flex_shrink() {
+        let html = r#"<html><head><style>
+            .c { display: flex; width: 300px; }
+            .a { width: 200px; height: 10px; }
+            .b { width: 200px; height: 10px; flex-shrink: 3; }
+            .none { width: 100px; height: 10px; flex: none; }
+            </style></head>
+            <body><div class="c"><div class="a"></div><div class="b"></div><div class="none"></div></div></body></html>"#
+            .to_string();
+        let view = create_layout_view(html);
+        let (_, items) = container_and_items(&view);
+
+        // はみ出す200pxを1:3で縮める
+        assert_eq!(200 - 50, items[0].borrow().size().width());
+        assert_eq!(200 - 150, items[1].borrow().size().width());
+        assert_eq!(100, items[2].borrow().size().width());
+        assert_eq!(LayoutPoint::new(200, 0), items[2].borrow().point());
+    }
+
+    #[test]
+    fn test_wrap() {
+        let view = create_layout_view(flex_html(
+            "flex-wrap: wrap; width: 200px; row-gap: 5px;",
+            "width: 80px; height: 20px;",
+            3,
+        ));
+        let (container, items) = container_and_items(&view);
+
+        assert_eq!(
+            vec![
+                LayoutPoint::new(0, 0),
+                LayoutPoint::new(80, 0),
+                LayoutPoint::new(0, 25)
+            ],
+            points(&items)
+        );
+        assert_eq!(LayoutSize::new(200, 45), container.borrow().size());
+    }
+
+    #[test]
+    fn test_column() {
+        let view = create_layout_view(flex_html(
+            "flex-direction: column; height: 100px; justify-content: flex-end;",
+            "height: 20px;",
+            2,
+        ));
+        let (container, items) = container_and_items(&view);
+
+        assert_eq!(
+            vec![LayoutPoint::new(0, 60), LayoutPoint::new(0, 80)],
+            points(&items)
+        );
+        // 交差軸方向に引き伸ばされる
+        assert_eq!(LayoutSize::new(590, 20), items[0].borrow().size());
+        assert_eq!(LayoutSize::new(590, 100), container.borrow().size());
+
+        let view = create_layout_view(flex_html(
+            "flex-direction: column; row-gap: 10px;",
+            "height: 20px;",
+            2,
+        ));
+        let (container, items) = container_and_items(&view);
+        assert_eq!(LayoutPoint::new(0, 30), items[1].borrow().point());
+        assert_eq!(LayoutSize::new(590, 50), container.borrow().size());
+    }
+
+    #[test]
+    fn test_reverse() {
+        let view = create_layout_view(flex_html(
+            "flex-direction: row-reverse;",
+            "width: 50px; height: 20px;",
+            2,
+        ));
+        let (_, items) = container_and_items(&view);
+
+        assert_eq!(
+            vec![LayoutPoint::new(540, 0), LayoutPoint::new(490, 0)],
+            points(&items)
+        );
+    }
+
+    #[test]
+    fn test_align_items() {
+        let view = create_layout_view(flex_html(
+            "height: 100px; align-items: center;",
+            "width: 50px; height: 20px;",
+            1,
+        ));
+        let (_, items) = container_and_items(&view);
+        assert_eq!(LayoutPoint::new(0, 40), items[0].borrow().point());
+
+        let view = create_layout_view(flex_html(
+            "height: 100px; align-items: flex-end;",
+            "width: 50px; height: 20px;",
+            1,
+        ));
+        let (_, items) = container_and_items(&view);
+        assert_eq!(LayoutPoint::new(0, 80), items[0].borrow().point());
+
+        // 高さが指定されていないアイテムはラインの高さまで引き伸ばされる
+        let view = create_layout_view(flex_html("height: 100px;", "width: 50px;", 1));
+        let (_, items) = container_and_items(&view);
+        assert_eq!(LayoutSize::new(50, 100), items[0].borrow().size());
+    }
+
+    #[test]
+    fn test_nav_bar() {
+        let html = r#"<html><head><style>
+            .nav { display: flex; column-gap: 10px; padding: 5px; }
+            a { padding: 0 8px; }
+            .right { margin-left: auto; }
+            </style></head>
+            <body><div class="nav">
+              <a>Home</a>
+              <a>About</a>
+              <a class="right">Login</a>
+            </div></body></html>"#
+            .to_string();
+        let view = create_layout_view(html);
+        let (container, items) = container_and_items(&view);
+
+        // 空白だけのテキストはアイテムにならず、インライン要素はブロックになる
+        assert_eq!(3, items.len());
+        assert!(items
+            .iter()
+            .all(|item| item.borrow().kind() == LayoutObjectKind::Block));
+
+        let home = items[0].borrow();
+        let about = items[1].borrow();
+        let login = items[2].borrow();
+        assert_eq!(LayoutPoint::new(5, 5), home.point());
+        assert_eq!(
+            home.point().x() + home.size().width() + 10,
+            about.point().x()
+        );
+        // autoのマージンで右端に寄せる
+        assert_eq!(5 + 580, login.point().x() + login.size().width());
+        assert_eq!(
+            home.size().height() + 10,
+            container.borrow().size().height()
+        );
+    }
+
+    #[test]
+    fn test_anonymous_text_item() {
+        let html = r#"<html><head><style>
+            .c { display: flex; justify-content: flex-end; }
+            </style></head>
+            <body><div class="c">text<div>box</div></div></body></html>"#
+            .to_string();
+        let view = create_layout_view(html);
+        let (_, items) = container_and_items(&view);
+
+        let text = items[0].borrow();
+        let block = items[1].borrow();
+        let fragment = text.fragments()[0].clone();
+        assert_eq!(
+            fragment.point().x() + fragment.size().width(),
+            block.point().x()
+        );
+        assert_eq!(590, block.point().x() + block.size().width());
+    }
+}
@@ -0,0 +1,275 @@
+//! フロートの配置
+//! https://www.w3.org/TR/CSS2/visuren.html#floats
+
+use crate::renderer::layout::block_layout::translate_subtree;
+use crate::renderer::layout::computed_style::Clear;
+use crate::renderer::layout::computed_style::Float;
+use crate::renderer::layout::layout_object::LayoutObject;
+use alloc::rc::Rc;
+use alloc::vec::Vec;
+use core::cell::RefCell;
+
+/// 配置したフロートのマージンボックス
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct PlacedFloat {
+    float: Float,
+    left: i64,
+    right: i64,
+    top: i64,
+    bottom: i64,
+}
+
+impl PlacedFloat {
+    /// `[top, bottom)`の範囲と縦方向に重なるかどうか
+    fn overlaps(&self, top: i64, bottom: i64) -> bool {
+        self.top < bottom && top < self.bottom
+    }
+}
+
+/// 1つのブロック整形コンテキストに配置されたフロート
+/// 座標はすべてページの座標で持つ
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct FloatContext {
+    floats: Vec<PlacedFloat>,
+}
+
+impl FloatContext {
+    pub fn new() -> Self {
+        Self { floats: Vec::new() }
+    }
+
+    /// `y`から高さ`height`の範囲で、フロートに重ならない横方向の領域を`(左端, 幅)`で返す
+    pub fn available(&self, y: i64, height: i64, left: i64, width: i64) -> (i64, i64) {
+        let bottom = y + core::cmp::max(height, 1);
+        let mut start = left;
+        let mut end = left + width;
+        for f in self.floats.iter().filter(|f| f.overlaps(y, bottom)) {
+            match f.float {
+                Float::Left => start = core::cmp::max(start, f.right),
+                Float::Right => end = core::cmp::min(end, f.left),
+                Float::None => {}
+            }
+        }
+        (start, core::cmp::max(end - start, 0))
+    }
+
+    /// `y`から高さ`height`の範囲に重なるフロートのうち、最も上にある下端
+    /// 領域が足りない場合は、その位置まで下げてもう一度探す
+    pub fn next_bottom(&self, y: i64, height: i64) -> Option<i64> {
+        let bottom = y + core::cmp::max(height, 1);
+        self.floats
+            .iter()
+            .filter(|f| f.overlaps(y, bottom))
+            .map(|f| f.bottom)
+            .min()
+    }
+
+    /// `clear`で避けるフロートの下端。該当するフロートがない場合はNone
+    pub fn clearance(&self, clear: Clear) -> Option<i64> {
+        self.floats
+            .iter()
+            .filter(|f| clear.clears(f.float))
+            .map(|f| f.bottom)
+            .max()
+    }
+
+    /// すべてのフロートの下端
+    pub fn bottom(&self) -> Option<i64> {
+        self.clearance(Clear::Both)
+    }
+
+    /// レイアウト済みのフロートを、`y`より下で包含ブロックの左右に寄せて配置する
+    /// https://www.w3.org/TR/CSS2/visuren.html#float-position
+    pub fn place(
+        &mut self,
+        node: &Rc<RefCell<LayoutObject>>,
+        y: i64,
+        content_x: i64,
+        content_width: i64,
+    ) {
+        let (float, clear, point, size, margin) = {
+            let n = node.borrow();
+            (
+                n.style().float(),
+                n.style().clear(),
+                n.point(),
+                n.size(),
+                n.margin(),
+            )
+        };
+        let width = size.width() + margin.horizontal();
+        let height = size.height() + margin.vertical();
+
+        // 先に配置したフロートより上には置かない
+        let mut y = y;
+        if let Some(last) = self.floats.last() {
+            y = core::cmp::max(y, last.top);
+        }
+        if let Some(bottom) = self.clearance(clear) {
+            y = core::cmp::max(y, bottom);
+        }
+
+        // 横に並べられる位置まで下げる
+        let (left, available) = loop {
+            let (left, available) = self.available(y, height, content_x, content_width);
+            match self.next_bottom(y, height) {
+                Some(bottom) if available < width => y = bottom,
+                _ => break (left, available),
+            }
+        };
+        let x = match float {
+            Float::Right => left + available - width,
+            Float::Left | Float::None => left,
+        };
+
+        translate_subtree(
+            node,
+            x + margin.left - point.x(),
+            y + margin.top - point.y(),
+        );
+        self.floats.push(PlacedFloat {
+            float,
+            left: x,
+            right: x + width,
+            top: y,
+            bottom: y + height,
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::renderer::layout::layout_object::LayoutObject;
+    use crate::renderer::layout::layout_object::LayoutPoint;
+    use crate::renderer::layout::layout_object::LayoutSize;
+    use crate::renderer::layout::test_util::body_children;
+    use crate::renderer::layout::test_util::create_layout_view_with_style;
+    use alloc::format;
+    use alloc::rc::Rc;
+    use alloc::vec;
+    use alloc::vec::Vec;
+    use core::cell::RefCell;
+
+    fn fragment_points(node: &Rc<RefCell<LayoutObject>>) -> Vec<LayoutPoint> {
+        node.borrow()
+            .fragments()
+            .iter()
+            .map(|f| f.point())
+            .collect()
+    }
+
+    #[test]
+    fn test_line_boxes_are_shortened() {
+        // 9文字の単語は空白を含めて80px。フロートの横の490pxには6語、590pxには7語が入る
+        let words = vec!["abcdefghi"; 20].join(" ");
+        let view = create_layout_view_with_style(
+            ".f { float: left; width: 100px; height: 50px; }",
+            &format!("<div class=\"f\"></div><p>{}</p>", words),
+        );
+        let nodes = body_children(&view);
+        assert_eq!(LayoutPoint::new(0, 0), nodes[0].borrow().point());
+        assert_eq!(LayoutSize::new(100, 50), nodes[0].borrow().size());
+
+        // 50pxより下から始まる4行目だけが左端から並ぶ
+        let p = nodes[1].clone();
+        let text = p.borrow().first_child().unwrap();
+        assert_eq!(
+            vec![
+                LayoutPoint::new(100, 0),
+                LayoutPoint::new(100, 20),
+                LayoutPoint::new(100, 40),
+                LayoutPoint::new(0, 60)
+            ],
+            fragment_points(&text)
+        );
+        assert_eq!(
+            LayoutSize::new(472, 20),
+            text.borrow().fragments()[0].size()
+        );
+        // 段落の箱はフロートに関係なく包含ブロックの幅を持つ
+        assert_eq!(LayoutPoint::new(0, 0), p.borrow().point());
+        assert_eq!(LayoutSize::new(590, 80), p.borrow().size());
+    }
+
+    #[test]
+    fn test_left_and_right_floats() {
+        let view = create_layout_view_with_style(
+            ".l { float: left; width: 300px; height: 50px; } .r { float: right; width: 200px; height: 30px; margin: 5px; }",
+            "<div class=\"l\"></div><div class=\"r\"></div><div class=\"l\"></div>",
+        );
+        let nodes = body_children(&view);
+        assert_eq!(LayoutPoint::new(0, 0), nodes[0].borrow().point());
+        // 右のフロートのマージンボックスは右端に寄せる
+        assert_eq!(LayoutPoint::new(385, 5), nodes[1].borrow().point());
+        // 横に入らないフロートは、先にあるフロートの下端まで下がる
+        // 右のフロートの下端は40px、左のフロートの下端は50px
+        assert_eq!(LayoutPoint::new(0, 50), nodes[2].borrow().point());
+        // フロートだけを持つbodyはBFCを作るので、フロートを含む高さになる
+        let root = view.root().unwrap();
+        assert_eq!(LayoutSize::new(590, 100), root.borrow().size());
+    }
+
+    #[test]
+    fn test_float_in_text_is_placed_at_line_top() {
+        let view = create_layout_view_with_style(
+            ".r { float: right; width: 40px; height: 40px; }",
+            "<p>abc <span class=\"r\">x</span>def</p>",
+        );
+        let p = body_children(&view)[0].clone();
+        let mut child = p.borrow().first_child();
+        let mut nodes = Vec::new();
+        while let Some(c) = child {
+            nodes.push(c.clone());
+            child = c.borrow().next_sibling();
+        }
+        // フロートは行の途中にあっても、行ボックスより先に右端に置かれる
+        assert_eq!(LayoutPoint::new(550, 0), nodes[1].borrow().point());
+        assert_eq!(vec![LayoutPoint::new(0, 0)], fragment_points(&nodes[0]));
+        assert_eq!(vec![LayoutPoint::new(32, 0)], fragment_points(&nodes[2]));
+        // BFCを作らない段落の高さにフロートは含まれない
+        assert_eq!(LayoutSize::new(590, 20), p.borrow().size());
+    }
+
+    #[test]
+    fn test_clear() {
+        let view = create_layout_view_with_style(
+            ".l { float: left; width: 100px; height: 30px; } .r { float: right; width: 100px; height: 60px; } .cl { clear: left; } .cb { clear: both; margin-top: 10px; }",
+            "<div class=\"l\"></div><div class=\"r\"></div><div class=\"cl\">a</div><div class=\"cb\">b</div>",
+        );
+        let nodes = body_children(&view);
+        // 左のフロートの下端に揃い、右のフロートの分だけ行が短くなる
+        assert_eq!(LayoutPoint::new(0, 30), nodes[2].borrow().point());
+        let text = nodes[2].borrow().first_child().unwrap();
+        assert_eq!(vec![LayoutPoint::new(0, 30)], fragment_points(&text));
+        // クリアランスがある場合、上のマージンは両方のフロートの下に吸収される
+        assert_eq!(LayoutPoint::new(0, 60), nodes[3].borrow().point());
+    }
+
+    #[test]
+    fn test_block_formatting_context_contains_floats() {
+        let view = create_layout_view_with_style(
+            ".f { float: left; width: 100px; height: 50px; } .bfc { overflow: hidden; }",
+            "<div class=\"bfc\"><div class=\"f\"></div></div><div><div class=\"f\"></div></div><p>x</p>",
+        );
+        let nodes = body_children(&view);
+        // BFCを作るボックスはフロートを含む高さになる
+        assert_eq!(LayoutSize::new(590, 50), nodes[0].borrow().size());
+        // そうでないボックスは高さを持たず、後ろの段落がフロートの横に並ぶ
+        assert_eq!(LayoutPoint::new(0, 50), nodes[1].borrow().point());
+        assert_eq!(LayoutSize::new(590, 0), nodes[1].borrow().size());
+        let text = nodes[2].borrow().first_child().unwrap();
+        assert_eq!(vec![LayoutPoint::new(100, 50)], fragment_points(&text));
+    }
+
+    #[test]
+    fn test_block_formatting_context_avoids_floats() {
+        let view = create_layout_view_with_style(
+            ".side { float: left; width: 150px; height: 100px; } .main { overflow: hidden; height: 40px; }",
+            "<div class=\"side\"></div><div class=\"main\"></div>",
+        );
+        let nodes = body_children(&view);
+        // サイドバーの横の領域に収まるように、幅を縮めて並べる
+        assert_eq!(LayoutPoint::new(150, 0), nodes[1].borrow().point());
+        assert_eq!(LayoutSize::new(440, 40), nodes[1].borrow().size());
+    }
+}
@@ -0,0 +1,1427 @@
+use crate::font::collection::FontCollection;
+use crate::font::unicode::grapheme_clusters;
+use crate::renderer::dom::node::NodeKind;
+use crate::renderer::layout::block_layout::layout_formatting_context_root;
+use crate::renderer::layout::block_layout::translate_subtree;
+use crate::renderer::layout::computed_style::TextAlign;
+use crate::renderer::layout::computed_style::WhiteSpace;
+use crate::renderer::layout::float_layout::FloatContext;
+use crate::renderer::layout::layout_object::LayoutFragment;
+use crate::renderer::layout::layout_object::LayoutObject;
+use crate::renderer::layout::layout_object::LayoutObjectKind;
+use crate::renderer::layout::layout_object::LayoutPoint;
+use crate::renderer::layout::layout_object::LayoutSize;
+use crate::renderer::layout::list_marker::ListMarker;
+use crate::renderer::layout::position_layout::apply_relative_offset;
+use alloc::rc::Rc;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::cell::RefCell;
+
+/// 日本語などの文字は単語の区切りがなくても、文字の間で改行できる
+fn is_cjk(c: char) -> bool {
+    matches!(c as u32,
+        0x3000..=0x303f // CJKの記号と句読点
+        | 0x3040..=0x309f // ひらがな
+        | 0x30a0..=0x30ff // カタカナ
+        | 0x3400..=0x4dbf // CJK統合漢字拡張A
+        | 0x4e00..=0x9fff // CJK統合漢字
+        | 0xac00..=0xd7af // ハングル
+        | 0xff00..=0xffef // 全角英数字と半角カタカナ
+    )
+}
+
+/// フォントとline-heightで決まるインラインボックスの高さ
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct FontExtent {
+    /// 行の高さに占める部分の上端からベースラインまでの高さ
+    ascent: i64,
+    /// line-heightで決まる高さ
+    height: i64,
+    /// 文字そのものの上端からベースラインまでの高さ
+    content_ascent: i64,
+    /// 文字そのものの高さ(コンテンツ領域)
+    content_height: i64,
+}
+
+impl FontExtent {
+    /// line-heightと文字の高さの差(ハーフレディング)は上下に等しく分ける
+    /// https://www.w3.org/TR/CSS2/visudet.html#leading
+    fn new(node: &Rc<RefCell<LayoutObject>>, fonts: &FontCollection) -> Self {
+        let n = node.borrow();
+        let style = n.style();
+        let font_size = n.font_size_px();
+        let metrics = fonts.select(&style.font_family());
+
+        let content_ascent = metrics.ascent(font_size);
+        let content_height = content_ascent + metrics.descent(font_size);
+        let height = style
+            .line_height()
+            .to_px(font_size)
+            .unwrap_or_else(|| metrics.line_height(font_size));
+        Self {
+            ascent: content_ascent + (height - content_height) / 2,
+            height,
+            content_ascent,
+            content_height,
+        }
+    }
+}
+
+/// 行に並べる要素
+#[derive(Debug, Clone)]
+enum InlineItem {
+    /// 空白を含まないテキスト
+    Text {
+        node: Rc<RefCell<LayoutObject>>,
+        text: String,
+        width: i64,
+        extent: FontExtent,
+        cjk: bool,
+    },
+    /// まとめられた空白。white-spaceによっては1文字ずつの空白
+    Space {
+        node: Rc<RefCell<LayoutObject>>,
+        width: i64,
+        extent: FontExtent,
+        white_space: WhiteSpace,
+    },
+    /// white-spaceで残した改行文字。ここで必ず改行する
+    LineBreak {
+        node: Rc<RefCell<LayoutObject>>,
+        extent: FontExtent,
+    },
+    /// インライン要素の開始(左側のマージン、ボーダー、パディング)
+    OpenBox {
+        node: Rc<RefCell<LayoutObject>>,
+        width: i64,
+        extent: FontExtent,
+    },
+    /// インライン要素の終了(右側のマージン、ボーダー、パディング)
+    CloseBox {
+        node: Rc<RefCell<LayoutObject>>,
+        width: i64,
+    },
+    /// インラインブロックのように内部を別にレイアウトするボックス
+    /// ベースラインはマージンボックスの下端とする
+    Atomic {
+        node: Rc<RefCell<LayoutObject>>,
+        width: i64,
+        height: i64,
+    },
+}
+
+impl InlineItem {
+    fn width(&self) -> i64 {
+        match self {
+            InlineItem::Text { width, .. }
+            | InlineItem::Space { width, .. }
+            | InlineItem::OpenBox { width, .. }
+            | InlineItem::CloseBox { width, .. }
+            | InlineItem::Atomic { width, .. } => *width,
+            InlineItem::LineBreak { .. } => 0,
+        }
+    }
+
+    fn node(&self) -> &Rc<RefCell<LayoutObject>> {
+        match self {
+            InlineItem::Text { node, .. }
+            | InlineItem::Space { node, .. }
+            | InlineItem::LineBreak { node, .. }
+            | InlineItem::OpenBox { node, .. }
+            | InlineItem::CloseBox { node, .. }
+            | InlineItem::Atomic { node, .. } => node,
+        }
+    }
+
+    /// ベースラインより上の高さ
+    fn ascent(&self) -> i64 {
+        match self {
+            InlineItem::Text { extent, .. }
+            | InlineItem::Space { extent, .. }
+            | InlineItem::LineBreak { extent, .. } => extent.ascent,
+            InlineItem::Atomic { height, .. } => *height,
+            InlineItem::OpenBox { .. } | InlineItem::CloseBox { .. } => 0,
+        }
+    }
+
+    /// ベースラインより下の深さ
+    fn descent(&self) -> i64 {
+        match self {
+            InlineItem::Text { extent, .. }
+            | InlineItem::Space { extent, .. }
+            | InlineItem::LineBreak { extent, .. } => extent.height - extent.ascent,
+            InlineItem::Atomic { .. }
+            | InlineItem::OpenBox { .. }
+            | InlineItem::CloseBox { .. } => 0,
+        }
+    }
+
+    fn is_space(&self) -> bool {
+        matches!(self, InlineItem::Space { .. })
+    }
+
+    /// 行頭にある場合に取り除く空白
+    fn is_collapsible_space(&self) -> bool {
+        matches!(self, InlineItem::Space { white_space, .. } if white_space.collapses_spaces())
+    }
+
+    /// 行末にある場合に取り除く空白
+    /// pre-wrapで残した空白は行末にぶら下げる代わりに、幅を持たないものとして取り除く
+    fn is_trailing_space(&self) -> bool {
+        matches!(self, InlineItem::Space { white_space, .. } if *white_space != WhiteSpace::Pre)
+    }
+
+    fn is_line_break(&self) -> bool {
+        matches!(self, InlineItem::LineBreak { .. })
+    }
+
+    fn is_box_edge(&self) -> bool {
+        matches!(
+            self,
+            InlineItem::OpenBox { .. } | InlineItem::CloseBox { .. }
+        )
+    }
+}
+
+#[derive(Debug, Clone)]
+struct LineBox {
+    items: Vec<InlineItem>,
+    width: i64,
+}
+
+impl LineBox {
+    fn new() -> Self {
+        Self {
+            items: Vec::new(),
+            width: 0,
+        }
+    }
+
+    fn has_content(&self) -> bool {
+        self.items.iter().any(|item| !item.is_box_edge())
+    }
+
+    /// 行ボックスの上端からベースラインまでの高さ
+    fn ascent(&self) -> i64 {
+        self.items.iter().map(|i| i.ascent()).max().unwrap_or(0)
+    }
+
+    fn height(&self) -> i64 {
+        self.ascent() + self.items.iter().map(|i| i.descent()).max().unwrap_or(0)
+    }
+
+    /// 行末の空白は行の幅に含めない
+    fn trim_end(&mut self) {
+        let mut end = self.items.len();
+        while let Some(index) = self.items[..end].iter().rposition(|i| !i.is_box_edge()) {
+            let item = &self.items[index];
+            if item.is_trailing_space() {
+                self.width -= item.width();
+                self.items.remove(index);
+            } else if !item.is_line_break() {
+                return;
+            }
+            end = index;
+        }
+    }
+
+    /// 改行文字で終わる行
+    fn ends_with_line_break(&self) -> bool {
+        self.items.iter().any(|i| i.is_line_break())
+    }
+}
+
+/// インライン整形コンテキスト
+/// インラインレベルのボックスの並びを行ボックスに分割する
+/// https://www.w3.org/TR/CSS2/visuren.html#inline-formatting
+#[derive(Debug, Clone)]
+pub struct InlineFormattingContext {
+    items: Vec<InlineItem>,
+    nodes: Vec<Rc<RefCell<LayoutObject>>>,
+    /// 行ボックスには並ばず、行ボックスより先に配置するフロート
+    floats: Vec<Rc<RefCell<LayoutObject>>>,
+    /// 通常フローから取り除かれる絶対位置指定のボックス
+    positioned: Vec<Rc<RefCell<LayoutObject>>>,
+    available_width: i64,
+    text_align: TextAlign,
+}
+
+impl InlineFormattingContext {
+    pub fn new(
+        children: &[Rc<RefCell<LayoutObject>>],
+        available_width: i64,
+        text_align: TextAlign,
+        fonts: &FontCollection,
+    ) -> Self {
+        let mut collector = ItemCollector {
+            available_width,
+            fonts,
+            items: Vec::new(),
+            nodes: Vec::new(),
+            floats: Vec::new(),
+            positioned: Vec::new(),
+            last_was_space: true,
+        };
+        for child in children {
+            collector.collect(child);
+        }
+
+        Self {
+            items: collector.items,
+            nodes: collector.nodes,
+            floats: collector.floats,
+            positioned: collector.positioned,
+            available_width,
+            text_align,
+        }
+    }
+
+    /// 高さを持つ行ボックスがない場合、ブロック方向には何も配置されない
+    pub fn is_empty(&self) -> bool {
+        break_lines(&self.items, self.available_width)
+            .iter()
+            .all(|line| line.height() == 0)
+    }
+
+    /// フロートと行ボックスを`(content_x, top)`から配置し、行ボックス全体の高さを返す
+    pub fn place(&self, content_x: i64, top: i64) -> i64 {
+        let mut floats = FloatContext::new();
+        self.place_floats(content_x, top, &mut floats);
+        self.place_lines(content_x, top, &floats)
+    }
+
+    /// 行の途中にあるフロートも、行ボックスより先に`top`より下に配置する
+    pub fn place_floats(&self, content_x: i64, top: i64, floats: &mut FloatContext) {
+        for node in &self.floats {
+            floats.place(node, top, content_x, self.available_width);
+        }
+    }
+
+    /// 行ボックスを`(content_x, top)`から順に配置し、行ボックス全体の高さを返す
+    /// フロートに重なる行ボックスは、フロートを避けて短くする
+    pub fn place_lines(&self, content_x: i64, top: i64, floats: &FloatContext) -> i64 {
+        for node in &self.nodes {
+            node.borrow_mut().set_fragments(Vec::new());
+        }
+        // 絶対位置指定のボックスは、行ボックスの先頭を静的位置として後で配置する
+        for node in &self.positioned {
+            node.borrow_mut()
+                .set_point(LayoutPoint::new(content_x, top));
+        }
+
+        let mut y = top;
+        // 開いているインライン要素と、この行での開始位置
+        let mut open_boxes: Vec<(Rc<RefCell<LayoutObject>>, i64, FontExtent)> = Vec::new();
+        let mut start = 0;
+        while start < self.items.len() {
+            let (line_y, left, width, line, next) = self.fit_line(start, y, content_x, floats);
+            if line.items.is_empty() {
+                break;
+            }
+            y = line_y;
+            start = next;
+            let is_last = line.ends_with_line_break()
+                || self.items[next..].iter().all(|item| item.is_space());
+            for (_, start, _) in &mut open_boxes {
+                *start = left;
+            }
+            let baseline = y + line.ascent();
+
+            // 行に収まらない場合は左に揃える
+            let free = core::cmp::max(width - line.width, 0);
+            let spaces = line.items.iter().filter(|item| item.is_space()).count() as i64;
+            // 両端揃えでは最後の行を除き、余った幅を空白に分配する
+            let justify = self.text_align == TextAlign::Justify && !is_last && spaces > 0;
+            let mut x = left
+                + match self.text_align {
+                    TextAlign::Center => free / 2,
+                    TextAlign::Right => free,
+                    TextAlign::Left | TextAlign::Justify => 0,
+                };
+            let mut space_index = 0;
+
+            for item in &line.items {
+                let mut width = item.width();
+                match item {
+                    InlineItem::Text {
+                        node, text, extent, ..
+                    } => {
+                        add_text_fragment(node, text, x, width, baseline, *extent, !justify);
+                    }
+                    InlineItem::Space { node, extent, .. } => {
+                        if justify {
+                            width += free / spaces + i64::from(space_index < free % spaces);
+                            space_index += 1;
+                        }
+                        add_text_fragment(node, " ", x, width, baseline, *extent, !justify);
+                    }
+                    InlineItem::OpenBox { node, extent, .. } => {
+                        open_boxes.push((node.clone(), x, *extent))
+                    }
+                    InlineItem::CloseBox { node, width } => {
+                        if let Some(index) =
+                            open_boxes.iter().rposition(|(n, _, _)| Rc::ptr_eq(n, node))
+                        {
+                            let (n, start, extent) = open_boxes.remove(index);
+                            add_box_fragment(&n, start, x + width, baseline, extent);
+                        }
+                    }
+                    InlineItem::LineBreak { .. } => {}
+                    InlineItem::Atomic { node, height, .. } => {
+                        let current = node.borrow().point();
+                        let margin = node.borrow().margin();
+                        translate_subtree(
+                            node,
+                            x + margin.left - current.x(),
+                            baseline - height + margin.top - current.y(),
+                        );
+                    }
+                }
+                x += width;
+            }
+
+            // 次の行に続くインライン要素は、行末までの断片を作る
+            for (node, start, extent) in &open_boxes {
+                add_box_fragment(node, *start, x, baseline, *extent);
+            }
+
+            y += line.height();
+        }
+
+        for node in &self.nodes {
+            update_bounding_box(node, content_x, top);
+        }
+        for node in &self.nodes {
+            if node.borrow().kind() == LayoutObjectKind::Inline {
+                apply_relative_offset(node, self.available_width);
+            }
+        }
+
+        y - top
+    }
+
+    /// `start`の要素から始まる行を、`y`より下でフロートに重ならない位置に置けるように分割する
+    /// 行の上端、左端、幅、行ボックス、次の行の開始位置を返す
+    fn fit_line(
+        &self,
+        start: usize,
+        mut y: i64,
+        content_x: i64,
+        floats: &FloatContext,
+    ) -> (i64, i64, i64, LineBox, usize) {
+        loop {
+            let (mut left, mut width) = floats.available(y, 1, content_x, self.available_width);
+            let (mut line, mut next) = next_line(&self.items, start, width);
+
+            // 行の高さの範囲で重なるフロートがあれば、さらに短くする
+            let (l, w) = floats.available(y, line.height(), content_x, self.available_width);
+            if (l, w) != (left, width) {
+                (left, width) = (l, w);
+                (line, next) = next_line(&self.items, start, width);
+            }
+
+            // 最初の単語も収まらない場合は、フロートの下端まで行を下げる
+            if line.width > width {
+                if let Some(bottom) = floats.next_bottom(y, line.height()) {
+                    y = bottom;
+                    continue;
+                }
+            }
+            return (y, left, width, line, next);
+        }
+    }
+}
+
+/// インラインレベルのボックスを、行に並べる要素の列に変換する
+struct ItemCollector<'a> {
+    available_width: i64,
+    fonts: &'a FontCollection,
+    items: Vec<InlineItem>,
+    nodes: Vec<Rc<RefCell<LayoutObject>>>,
+    floats: Vec<Rc<RefCell<LayoutObject>>>,
+    positioned: Vec<Rc<RefCell<LayoutObject>>>,
+    /// 直前の要素が空白かどうか
+    /// 連続する空白はインライン要素の境界をまたいでも1つにまとめる
+    last_was_space: bool,
+}
+
+impl<'a> ItemCollector<'a> {
+    fn text_width(&self, node: &Rc<RefCell<LayoutObject>>, text: &str) -> i64 {
+        let n = node.borrow();
+        self.fonts
+            .select(&n.style().font_family())
+            .text_width(text, n.font_size_px())
+    }
+
+    fn collect(&mut self, node: &Rc<RefCell<LayoutObject>>) {
+        if node.borrow().is_absolutely_positioned() {
+            self.positioned.push(node.clone());
+            return;
+        }
+
+        // フロートは仮の位置でレイアウトし、行ボックスを配置する前に移動させる
+        if node.borrow().is_float() {
+            layout_formatting_context_root(
+                node,
+                LayoutPoint::new(0, 0),
+                self.available_width,
+                self.fonts,
+            );
+            self.floats.push(node.clone());
+            return;
+        }
+
+        let kind = node.borrow().kind();
+        match kind {
+            LayoutObjectKind::Text => {
+                self.nodes.push(node.clone());
+                let text = match node.borrow().node_kind() {
+                    NodeKind::Text(t) => t,
+                    _ => return,
+                };
+                self.collect_text(node, &text);
+            }
+            LayoutObjectKind::Inline => {
+                self.nodes.push(node.clone());
+                node.borrow_mut()
+                    .resolve_box_model(self.available_width, false, 0);
+                let (margin, border, padding) = {
+                    let n = node.borrow();
+                    (n.margin(), n.border(), n.padding())
+                };
+
+                self.items.push(InlineItem::OpenBox {
+                    node: node.clone(),
+                    width: margin.left + border.left + padding.left,
+                    extent: FontExtent::new(node, self.fonts),
+                });
+                let mut child = node.borrow().first_child();
+                while let Some(c) = child {
+                    self.collect(&c);
+                    child = c.borrow().next_sibling();
+                }
+                self.items.push(InlineItem::CloseBox {
+                    node: node.clone(),
+                    width: margin.right + border.right + padding.right,
+                });
+            }
+            LayoutObjectKind::Marker => {
+                let marker = match node.borrow().marker() {
+                    Some(marker) => marker,
+                    None => return,
+                };
+                if node.borrow().is_outside_marker() {
+                    return;
+                }
+                self.nodes.push(node.clone());
+                let text = match &marker {
+                    ListMarker::Text(text) => text.clone(),
+                    ListMarker::Bullet(_) => String::new(),
+                };
+                // マーカーの後ろの空きは、続くテキストの先頭の空白とまとめる
+                self.items.push(InlineItem::Text {
+                    node: node.clone(),
+                    text,
+                    width: marker.width(&node.borrow(), self.fonts),
+                    extent: FontExtent::new(node, self.fonts),
+                    cjk: false,
+                });
+                self.last_was_space = true;
+            }
+            LayoutObjectKind::InlineBlock
+            | LayoutObjectKind::Block
+            | LayoutObjectKind::ListItem => {
+                // 仮の位置でレイアウトし、行に配置するときに移動させる
+                layout_formatting_context_root(
+                    node,
+                    LayoutPoint::new(0, 0),
+                    self.available_width,
+                    self.fonts,
+                );
+                let n = node.borrow();
+                self.items.push(InlineItem::Atomic {
+                    node: node.clone(),
+                    width: n.size().width() + n.margin().horizontal(),
+                    height: n.size().height() + n.margin().vertical(),
+                });
+                self.last_was_space = false;
+            }
+        }
+    }
+
+    /// テキストを単語と空白に分ける
+    /// 日本語などの文字は1文字ずつ別の要素にする
+    /// white-spaceによって、連続する空白は1つにまとめ、改行文字は空白にするか強制改行にする
+    /// https://www.w3.org/TR/css-text-3/#white-space-processing
+    fn collect_text(&mut self, node: &Rc<RefCell<LayoutObject>>, text: &str) {
+        let extent = FontExtent::new(node, self.fonts);
+        let white_space = node.borrow().style().white_space();
+
+        let mut word = String::new();
+        for cluster in grapheme_clusters(text) {
+            let c = match cluster.chars().next() {
+                Some(c) => c,
+                None => continue,
+            };
+
+            if matches!(c, '\n' | '\r') && white_space.preserves_newlines() {
+                self.push_word(node, &mut word, extent);
+                self.items.push(InlineItem::LineBreak {
+                    node: node.clone(),
+                    extent,
+                });
+                // 改行の後の空白は行頭の空白としてまとめる
+                self.last_was_space = true;
+            } else if c.is_whitespace() {
+                self.push_word(node, &mut word, extent);
+                if !white_space.collapses_spaces() {
+                    // タブはtab-sizeの初期値の、空白8文字分の幅にする
+                    let count = if c == '\t' { 8 } else { 1 };
+                    self.items.push(InlineItem::Space {
+                        node: node.clone(),
+                        width: self.text_width(node, " ") * count,
+                        extent,
+                        white_space,
+                    });
+                    self.last_was_space = false;
+                    continue;
+                }
+                if !self.last_was_space {
+                    self.items.push(InlineItem::Space {
+                        node: node.clone(),
+                        width: self.text_width(node, " "),
+                        extent,
+                        white_space,
+                    });
+                }
+                self.last_was_space = true;
+            } else if is_cjk(c) {
+                self.push_word(node, &mut word, extent);
+                self.items.push(InlineItem::Text {
+                    node: node.clone(),
+                    text: String::from(cluster),
+                    width: self.text_width(node, cluster),
+                    extent,
+                    cjk: true,
+                });
+                self.last_was_space = false;
+            } else {
+                word.push_str(cluster);
+                self.last_was_space = false;
+            }
+        }
+        self.push_word(node, &mut word, extent);
+    }
+
+    fn push_word(
+        &mut self,
+        node: &Rc<RefCell<LayoutObject>>,
+        word: &mut String,
+        extent: FontExtent,
+    ) {
+        if word.is_empty() {
+            return;
+        }
+        self.items.push(InlineItem::Text {
+            node: node.clone(),
+            text: word.clone(),
+            width: self.text_width(node, word),
+            extent,
+            cjk: false,
+        });
+        word.clear();
+    }
+}
+
+/// インラインレベルの内容の幅(max-contentとmin-content)を測る
+/// 空白のまとめ方や改行できる位置を行のレイアウトと揃えるため、同じように行に並べる要素の列に変換する
+pub struct InlineMeasure<'a> {
+    collector: ItemCollector<'a>,
+}
+
+impl<'a> InlineMeasure<'a> {
+    pub fn new(fonts: &'a FontCollection) -> Self {
+        Self {
+            collector: ItemCollector {
+                available_width: 0,
+                fonts,
+                items: Vec::new(),
+                nodes: Vec::new(),
+                floats: Vec::new(),
+                positioned: Vec::new(),
+                last_was_space: true,
+            },
+        }
+    }
+
+    /// テキストと、行に並ぶマーカーを加える
+    pub fn push_text(&mut self, node: &Rc<RefCell<LayoutObject>>) {
+        self.collector.collect(node);
+    }
+
+    /// インライン要素の開始と、左側のマージン、ボーダー、パディングの幅を加える
+    pub fn open_box(&mut self, node: &Rc<RefCell<LayoutObject>>, width: i64) {
+        let extent = FontExtent::new(node, self.collector.fonts);
+        self.collector.items.push(InlineItem::OpenBox {
+            node: node.clone(),
+            width,
+            extent,
+        });
+    }
+
+    /// インライン要素の終了と、右側のマージン、ボーダー、パディングの幅を加える
+    pub fn close_box(&mut self, node: &Rc<RefCell<LayoutObject>>, width: i64) {
+        self.collector.items.push(InlineItem::CloseBox {
+            node: node.clone(),
+            width,
+        });
+    }
+
+    /// インラインブロックのように、行の中で分割されない幅`width`のボックスを加える
+    pub fn push_atomic(&mut self, node: &Rc<RefCell<LayoutObject>>, width: i64) {
+        self.collector.items.push(InlineItem::Atomic {
+            node: node.clone(),
+            width,
+            height: 0,
+        });
+        self.collector.last_was_space = false;
+    }
+
+    /// 改行しない場合の最も広い行の幅
+    pub fn max_content(&self) -> i64 {
+        self.widest_line(i64::MAX)
+    }
+
+    /// 改行できる位置ですべて改行した場合の最も広い行の幅
+    pub fn min_content(&self) -> i64 {
+        self.widest_line(0)
+    }
+
+    fn widest_line(&self, available_width: i64) -> i64 {
+        break_lines(&self.collector.items, available_width)
+            .iter()
+            .map(|line| line.width)
+            .max()
+            .unwrap_or(0)
+    }
+}
+
+/// `index`の直前で改行できるかどうか
+/// インライン要素の開始は後ろの要素と、終了は前の要素と同じ行に置く
+fn is_break_opportunity(items: &[InlineItem], index: usize) -> bool {
+    if index == 0 || index >= items.len() {
+        return false;
+    }
+    if matches!(items[index - 1], InlineItem::OpenBox { .. })
+        || matches!(items[index], InlineItem::CloseBox { .. })
+    {
+        return false;
+    }
+
+    let before = items[..index].iter().rev().find(|i| !i.is_box_edge());
+    if before.is_some_and(|i| i.is_line_break()) {
+        return true;
+    }
+    if items[index].is_space() {
+        return false;
+    }
+
+    let after = items[index..].iter().find(|i| !i.is_box_edge());
+    match (before, after) {
+        (Some(before), Some(after)) => {
+            (before.is_space()
+                || matches!(before, InlineItem::Atomic { .. })
+                || matches!(after, InlineItem::Atomic { .. })
+                || matches!(before, InlineItem::Text { cjk: true, .. })
+                || matches!(after, InlineItem::Text { cjk: true, .. }))
+                && wraps_between(before.node(), after.node())
+        }
+        _ => false,
+    }
+}
+
+/// 2つの要素の間で自動的に改行できるかどうかは、共通する最も近い祖先のwhite-spaceで決まる
+/// https://www.w3.org/TR/css-text-3/#white-space-wrapping
+fn wraps_between(a: &Rc<RefCell<LayoutObject>>, b: &Rc<RefCell<LayoutObject>>) -> bool {
+    let mut ancestors = Vec::new();
+    let mut node = Some(a.clone());
+    while let Some(n) = node {
+        node = n.borrow().parent().upgrade();
+        ancestors.push(n);
+    }
+
+    let mut node = Some(b.clone());
+    while let Some(n) = node {
+        if ancestors.iter().any(|a| Rc::ptr_eq(a, &n)) {
+            return n.borrow().style().white_space().wraps();
+        }
+        node = n.borrow().parent().upgrade();
+    }
+    b.borrow().style().white_space().wraps()
+}
+
+/// 改行できる位置で区切り、行に収まらない場合は次の行に送る
+fn break_lines(items: &[InlineItem], available_width: i64) -> Vec<LineBox> {
+    let mut lines = Vec::new();
+    let mut start = 0;
+    while start < items.len() {
+        let (line, next) = next_line(items, start, available_width);
+        if line.items.is_empty() {
+            break;
+        }
+        lines.push(line);
+        start = next;
+    }
+    lines
+}
+
+/// `start`の要素から、`available_width`に収まるだけの要素を1つの行にまとめる
+/// 行ボックスと、次の行の開始位置を返す
+fn next_line(items: &[InlineItem], start: usize, available_width: i64) -> (LineBox, usize) {
+    let mut line = LineBox::new();
+
+    let mut start = start;
+    while start < items.len() {
+        let mut end = start + 1;
+        while end < items.len() && !is_break_opportunity(items, end) {
+            end += 1;
+        }
+        let chunk = &items[start..end];
+
+        // 行末にくる空白は幅に含めずに、行に収まるかどうかを判断する
+        let trailing_spaces: i64 = chunk
+            .iter()
+            .rev()
+            .take_while(|i| i.is_trailing_space() || i.is_box_edge() || i.is_line_break())
+            .filter(|i| i.is_trailing_space())
+            .map(|i| i.width())
+            .sum();
+        let chunk_width: i64 = chunk.iter().map(|i| i.width()).sum();
+        if line.has_content() && line.width + chunk_width - trailing_spaces > available_width {
+            break;
+        }
+
+        for item in chunk {
+            // 行頭の空白は取り除く
+            if item.is_collapsible_space() && !line.has_content() {
+                continue;
+            }
+            line.width += item.width();
+            line.items.push(item.clone());
+        }
+
+        start = end;
+        if line.ends_with_line_break() {
+            break;
+        }
+    }
+
+    line.trim_end();
+    (line, start)
+}
+
+/// テキストの断片はコンテンツ領域(文字そのものの高さ)を占める
+/// `merge`が真の場合、同じ行で隣り合う断片はまとめる
+fn add_text_fragment(
+    node: &Rc<RefCell<LayoutObject>>,
+    text: &str,
+    x: i64,
+    width: i64,
+    baseline: i64,
+    extent: FontExtent,
+    merge: bool,
+) {
+    let y = baseline - extent.content_ascent;
+    let mut fragments = node.borrow().fragments();
+
+    if let Some(last) = fragments.last_mut() {
+        if merge && last.point().y() == y && last.point().x() + last.size().width() == x {
+            last.push_str(text, width);
+            node.borrow_mut().set_fragments(fragments);
+            return;
+        }
+    }
+
+    fragments.push(LayoutFragment::new(
+        String::from(text),
+        LayoutPoint::new(x, y),
+        LayoutSize::new(width, extent.content_height),
+    ));
+    node.borrow_mut().set_fragments(fragments);
+}
+
+fn add_box_fragment(
+    node: &Rc<RefCell<LayoutObject>>,
+    start: i64,
+    end: i64,
+    baseline: i64,
+    extent: FontExtent,
+) {
+    let mut fragments = node.borrow().fragments();
+    fragments.push(LayoutFragment::new(
+        String::new(),
+        LayoutPoint::new(start, baseline - extent.content_ascent),
+        LayoutSize::new(end - start, extent.content_height),
+    ));
+    node.borrow_mut().set_fragments(fragments);
+}
+
+/// 断片をすべて囲む矩形をボックスの位置とサイズにする
+/// リスト項目の外側に置くマーカーを、最初の行のベースラインに揃えて内容の左側に置く
+/// 行ボックスがない場合は、リスト項目の内容の上端に空の行があるものとする
+pub fn place_outside_marker(
+    list_item: &Rc<RefCell<LayoutObject>>,
+    content_x: i64,
+    content_top: i64,
+    fonts: &FontCollection,
+) {
+    let marker = match list_item.borrow().first_child() {
+        Some(child) if child.borrow().is_outside_marker() => child,
+        _ => return,
+    };
+    let content = match marker.borrow().marker() {
+        Some(content) => content,
+        None => return,
+    };
+
+    let baseline = match first_text_fragment(list_item) {
+        Some((text, fragment)) => {
+            fragment.point().y() + FontExtent::new(&text, fonts).content_ascent
+        }
+        None => content_top + FontExtent::new(list_item, fonts).ascent,
+    };
+    let width = content.width(&marker.borrow(), fonts);
+    let text = match &content {
+        ListMarker::Text(text) => text.clone(),
+        ListMarker::Bullet(_) => String::new(),
+    };
+
+    marker.borrow_mut().set_fragments(Vec::new());
+    add_text_fragment(
+        &marker,
+        &text,
+        content_x - width,
+        width,
+        baseline,
+        FontExtent::new(&marker, fonts),
+        false,
+    );
+    update_bounding_box(&marker, content_x - width, content_top);
+}
+
+/// 子孫のうち、最初に行ボックスに並んだテキストとその断片
+fn first_text_fragment(
+    node: &Rc<RefCell<LayoutObject>>,
+) -> Option<(Rc<RefCell<LayoutObject>>, LayoutFragment)> {
+    let mut child = node.borrow().first_child();
+    while let Some(c) = child {
+        if c.borrow().kind() == LayoutObjectKind::Text {
+            if let Some(fragment) = c.borrow().fragments().first() {
+                return Some((c.clone(), fragment.clone()));
+            }
+        } else if let Some(found) = first_text_fragment(&c) {
+            return Some(found);
+        }
+        child = c.borrow().next_sibling();
+    }
+    None
+}
+
+fn update_bounding_box(node: &Rc<RefCell<LayoutObject>>, content_x: i64, top: i64) {
+    let fragments = node.borrow().fragments();
+    let first = match fragments.first() {
+        Some(f) => f,
+        None => {
+            node.borrow_mut()
+                .set_point(LayoutPoint::new(content_x, top));
+            node.borrow_mut().set_size(LayoutSize::new(0, 0));
+            return;
+        }
+    };
+
+    let mut left = first.point().x();
+    let mut top = first.point().y();
+    let mut right = left + first.size().width();
+    let mut bottom = top + first.size().height();
+    for f in &fragments {
+        left = core::cmp::min(left, f.point().x());
+        top = core::cmp::min(top, f.point().y());
+        right = core::cmp::max(right, f.point().x() + f.size().width());
+        bottom = core::cmp::max(bottom, f.point().y() + f.size().height());
+    }
+
+    node.borrow_mut().set_point(LayoutPoint::new(left, top));
+    node.borrow_mut()
+        .set_size(LayoutSize::new(right - left, bottom - top));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::renderer::layout::test_util::create_layout_view;
+    use alloc::string::ToString;
+    use alloc::vec;
+
+    fn fragments(node: &Option<Rc<RefCell<LayoutObject>>>) -> Vec<(String, LayoutPoint)> {
+        node.as_ref()
+            .expect("node should exist")
+            .borrow()
+            .fragments()
+            .iter()
+            .map(|f| (f.text(), f.point()))
+            .collect()
+    }
+
+    #[test]
+    fn test_inline_element_on_same_line() {
+        let html = "<html><body><p>Go to <a>page 2</a> now</p></body></html>".to_string();
+        let view = create_layout_view(html);
+        let p = view
+            .root()
+            .expect("root should exist")
+            .borrow()
+            .first_child();
+        let text1 = p.as_ref().expect("p should exist").borrow().first_child();
+        let a = text1
+            .as_ref()
+            .expect("text should exist")
+            .borrow()
+            .next_sibling();
+        let text2 = a.as_ref().expect("a should exist").borrow().next_sibling();
+        let link = a.as_ref().expect("a should exist").borrow().first_child();
+
+        assert_eq!(
+            vec![("Go to ".to_string(), LayoutPoint::new(0, 0))],
+            fragments(&text1)
+        );
+        assert_eq!(
+            vec![("page 2".to_string(), LayoutPoint::new(48, 0))],
+            fragments(&link)
+        );
+        assert_eq!(
+            vec![(" now".to_string(), LayoutPoint::new(96, 0))],
+            fragments(&text2)
+        );
+        assert_eq!(
+            LayoutPoint::new(48, 0),
+            a.as_ref().expect("a should exist").borrow().point()
+        );
+        assert_eq!(
+            LayoutSize::new(48, 20),
+            a.as_ref().expect("a should exist").borrow().size()
+        );
+        assert_eq!(
+            LayoutSize::new(590, 20),
+            p.expect("p should exist").borrow().size()
+        );
+    }
+
+    #[test]
+    fn test_line_break_across_inline_elements() {
+        // 1行に収まるのは 200 / 8 = 25 文字まで
+        let html = r#"<html><head><style>p { width: 200px; }</style></head>
+            <body><p>aaaa bbbb cccc <a>dddd eeee ffff</a> gggg</p></body></html>"#
+            .to_string();
+        let view = create_layout_view(html);
+        let p = view
+            .root()
+            .expect("root should exist")
+            .borrow()
+            .first_child();
+        let text1 = p.as_ref().expect("p should exist").borrow().first_child();
+        let a = text1
+            .as_ref()
+            .expect("text should exist")
+            .borrow()
+            .next_sibling();
+        let text2 = a.as_ref().expect("a should exist").borrow().next_sibling();
+        let link = a.as_ref().expect("a should exist").borrow().first_child();
+
+        assert_eq!(
+            vec![("aaaa bbbb cccc ".to_string(), LayoutPoint::new(0, 0))],
+            fragments(&text1)
+        );
+        assert_eq!(
+            vec![
+                ("dddd eeee".to_string(), LayoutPoint::new(120, 0)),
+                ("ffff".to_string(), LayoutPoint::new(0, 20)),
+            ],
+            fragments(&link)
+        );
+        assert_eq!(
+            vec![(" gggg".to_string(), LayoutPoint::new(32, 20))],
+            fragments(&text2)
+        );
+
+        // 複数行にまたがるインライン要素は、各行の断片を囲む矩形になる
+        let a = a.expect("a should exist");
+        assert_eq!(LayoutPoint::new(0, 0), a.borrow().point());
+        assert_eq!(LayoutSize::new(192, 40), a.borrow().size());
+        assert_eq!(2, a.borrow().fragments().len());
+        assert_eq!(
+            LayoutSize::new(200, 40),
+            p.expect("p should exist").borrow().size()
+        );
+    }
+
+    #[test]
+    fn test_no_break_inside_word_split_by_element() {
+        let html = r#"<html><head><style>p { width: 80px; }</style></head>
+            <body><p>aaaaaa bb<a>cc</a></p></body></html>"#
+            .to_string();
+        let view = create_layout_view(html);
+        let p = view
+            .root()
+            .expect("root should exist")
+            .borrow()
+            .first_child();
+        let text = p.as_ref().expect("p should exist").borrow().first_child();
+        let a = text
+            .as_ref()
+            .expect("text should exist")
+            .borrow()
+            .next_sibling();
+        let link = a.as_ref().expect("a should exist").borrow().first_child();
+
+        assert_eq!(
+            vec![
+                ("aaaaaa".to_string(), LayoutPoint::new(0, 0)),
+                ("bb".to_string(), LayoutPoint::new(0, 20)),
+            ],
+            fragments(&text)
+        );
+        assert_eq!(
+            vec![("cc".to_string(), LayoutPoint::new(16, 20))],
+            fragments(&link)
+        );
+    }
+
+    #[test]
+    fn test_paint_emits_fragments() {
+        let html = r#"<html><head><style>p { width: 80px; }</style></head>
+            <body><p>aaaa bbbb cc</p></body></html>"#
+            .to_string();
+        let view = create_layout_view(html);
+
+        let texts = view
+            .paint()
+            .into_iter()
+            .filter_map(|item| match item {
+                crate::display_item::DisplayItem::Text {
+                    text, layout_point, ..
+                } => Some((text, layout_point)),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            vec![
+                ("aaaa bbbb".to_string(), LayoutPoint::new(0, 0)),
+                ("cc".to_string(), LayoutPoint::new(0, 20)),
+            ],
+            texts
+        );
+    }
+
+    #[test]
+    fn test_leading_and_trailing_white_space() {
+        let html = "<html><body><p>  hello   world  </p></body></html>".to_string();
+        let view = create_layout_view(html);
+        let p = view
+            .root()
+            .expect("root should exist")
+            .borrow()
+            .first_child();
+        let text = p.as_ref().expect("p should exist").borrow().first_child();
+
+        assert_eq!(
+            vec![("hello world".to_string(), LayoutPoint::new(0, 0))],
+            fragments(&text)
+        );
+    }
+
+    #[test]
+    fn test_japanese_text_wraps_by_character() {
+        // 全角文字は16pxなので、1行に5文字まで入る
+        let html = r#"<html><head><style>p { width: 80px; }</style></head>
+            <body><p>あいうえおかきく</p></body></html>"#
+            .to_string();
+        let view = create_layout_view(html);
+        let p = view
+            .root()
+            .expect("root should exist")
+            .borrow()
+            .first_child();
+        let text = p.as_ref().expect("p should exist").borrow().first_child();
+
+        assert_eq!(
+            vec![
+                ("あいうえお".to_string(), LayoutPoint::new(0, 0)),
+                ("かきく".to_string(), LayoutPoint::new(0, 20)),
+            ],
+            fragments(&text)
+        );
+    }
+
+    #[test]
+    fn test_inline_block_sets_line_height() {
+        let html = r#"<html><head><style>
+            div { display: inline-block; width: 16px; height: 40px; }
+            </style></head>
+            <body><p>ab<div></div>cd</p></body></html>"#
+            .to_string();
+        let view = create_layout_view(html);
+        let p = view
+            .root()
+            .expect("root should exist")
+            .borrow()
+            .first_child();
+        let text1 = p.as_ref().expect("p should exist").borrow().first_child();
+        let div = text1
+            .as_ref()
+            .expect("text should exist")
+            .borrow()
+            .next_sibling();
+        let text2 = div
+            .as_ref()
+            .expect("div should exist")
+            .borrow()
+            .next_sibling();
+
+        // インラインブロックの下端とテキストのベースラインを揃える
+        assert_eq!(
+            vec![("ab".to_string(), LayoutPoint::new(0, 24))],
+            fragments(&text1)
+        );
+        assert_eq!(
+            LayoutPoint::new(16, 0),
+            div.expect("div should exist").borrow().point()
+        );
+        assert_eq!(
+            vec![("cd".to_string(), LayoutPoint::new(32, 24))],
+            fragments(&text2)
+        );
+        assert_eq!(
+            LayoutSize::new(590, 44),
+            p.expect("p should exist").borrow().size()
+        );
+    }
+
+    fn first_text_fragments(html: &str) -> Vec<(String, LayoutPoint)> {
+        let view = create_layout_view(html.to_string());
+        let p = view
+            .root()
+            .expect("root should exist")
+            .borrow()
+            .first_child();
+        let text = p.as_ref().expect("p should exist").borrow().first_child();
+        fragments(&text)
+    }
+
+    /// 最初の要素の子孫にあるテキストの断片を、文書の順にすべて返す
+    fn descendant_text_fragments(html: &str) -> Vec<(String, LayoutPoint)> {
+        fn collect(node: &Rc<RefCell<LayoutObject>>, result: &mut Vec<(String, LayoutPoint)>) {
+            if node.borrow().kind() == LayoutObjectKind::Text {
+                result.extend(
+                    node.borrow()
+                        .fragments()
+                        .iter()
+                        .map(|f| (f.text(), f.point())),
+                );
+            }
+            let mut child = node.borrow().first_child();
+            while let Some(c) = child {
+                collect(&c, result);
+                child = c.borrow().next_sibling();
+            }
+        }
+
+        let view = create_layout_view(html.to_string());
+        let root = view.root().expect("root should exist");
+        let mut child = root.borrow().first_child();
+        while let Some(c) = child {
+            if c.borrow().kind() != LayoutObjectKind::Text {
+                let mut result = Vec::new();
+                collect(&c, &mut result);
+                return result;
+            }
+            child = c.borrow().next_sibling();
+        }
+        Vec::new()
+    }
+
+    #[test]
+    fn test_white_space_between_inline_elements() {
+        // 空白だけのインライン要素の中身も、前後のテキストの間の空白になる
+        let expected = vec![
+            ("a".to_string(), LayoutPoint::new(0, 0)),
+            (" ".to_string(), LayoutPoint::new(8, 0)),
+            ("b".to_string(), LayoutPoint::new(16, 0)),
+        ];
+        assert_eq!(
+            expected,
+            descendant_text_fragments(
+                "<html><body><p><span>a</span><span> </span><span>b</span></p></body></html>"
+            )
+        );
+        // 要素の間の改行と字下げは1つの空白にまとめられ、行頭と行末では取り除かれる
+        assert_eq!(
+            expected,
+            descendant_text_fragments(
+                "<html><body><div>\n  <span>a</span>\n  <span>b</span>\n</div></body></html>"
+            )
+        );
+    }
+
+    #[test]
+    fn test_white_space_pre() {
+        // 空白と改行をそのまま残し、行に収まらなくても改行しない
+        // 空行も1行分の高さを持ち、タブは空白8文字分の幅の1つの空白になる
+        assert_eq!(
+            vec![
+                ("  a  b".to_string(), LayoutPoint::new(0, 0)),
+                (" c".to_string(), LayoutPoint::new(0, 40)),
+            ],
+            first_text_fragments(
+                "<html><head><style>p { width: 40px; white-space: pre; }</style></head>\
+                <body><p>  a  b\n\n\tc</p></body></html>"
+            )
+        );
+    }
+
+    #[test]
+    fn test_white_space_nowrap() {
+        assert_eq!(
+            vec![("aa bb cc".to_string(), LayoutPoint::new(0, 0))],
+            first_text_fragments(
+                r#"<html><head><style>p { width: 40px; white-space: nowrap; }</style></head>
+                <body><p>aa bb  cc</p></body></html>"#
+            )
+        );
+        // nowrapの要素の前では、親のwhite-spaceに従って改行できる
+        assert_eq!(
+            vec![
+                ("aaaa".to_string(), LayoutPoint::new(0, 0)),
+                ("bbb ccc".to_string(), LayoutPoint::new(0, 20)),
+            ],
+            descendant_text_fragments(
+                r#"<html><head><style>
+                p { width: 80px; }
+                span { white-space: nowrap; }
+                </style></head>
+                <body><p>aaaa <span>bbb ccc</span></p></body></html>"#
+            )
+        );
+    }
+
+    #[test]
+    fn test_white_space_pre_wrap_and_pre_line() {
+        // pre-wrapは空白を残したまま改行し、行末の空白は幅に含めない
+        assert_eq!(
+            vec![
+                ("  aa".to_string(), LayoutPoint::new(0, 0)),
+                ("bb cc".to_string(), LayoutPoint::new(0, 20)),
+            ],
+            first_text_fragments(
+                r#"<html><head><style>p { width: 40px; white-space: pre-wrap; }</style></head>
+                <body><p>  aa  bb cc</p></body></html>"#
+            )
+        );
+        // pre-lineは空白をまとめ、改行だけを残す
+        assert_eq!(
+            vec![
+                ("a b".to_string(), LayoutPoint::new(0, 0)),
+                ("c".to_string(), LayoutPoint::new(0, 20)),
+            ],
+            first_text_fragments(
+                "<html><head><style>p { white-space: pre-line; }</style></head>\
+                <body><p>  a   b \n  c</p></body></html>"
+            )
+        );
+    }
+
+    #[test]
+    fn test_text_align_center_and_right() {
+        assert_eq!(
+            vec![("abc".to_string(), LayoutPoint::new(38, 0))],
+            first_text_fragments(
+                r#"<html><head><style>p { width: 100px; text-align: center; }</style></head>
+                <body><p>abc</p></body></html>"#
+            )
+        );
+        assert_eq!(
+            vec![("abc".to_string(), LayoutPoint::new(76, 0))],
+            first_text_fragments(
+                r#"<html><head><style>p { width: 100px; text-align: right; }</style></head>
+                <body><p>abc</p></body></html>"#
+            )
+        );
+    }
+
+    #[test]
+    fn test_text_align_justify() {
+        // 1行目の余った16pxを2つの空白に分け、最後の行は左に揃える
+        assert_eq!(
+            vec![
+                ("aa".to_string(), LayoutPoint::new(0, 0)),
+                (" ".to_string(), LayoutPoint::new(16, 0)),
+                ("bb".to_string(), LayoutPoint::new(32, 0)),
+                (" ".to_string(), LayoutPoint::new(48, 0)),
+                ("cc".to_string(), LayoutPoint::new(64, 0)),
+                ("dd".to_string(), LayoutPoint::new(0, 20)),
+            ],
+            first_text_fragments(
+                r#"<html><head><style>p { width: 80px; text-align: justify; }</style></head>
+                <body><p>aa bb cc dd</p></body></html>"#
+            )
+        );
+    }
+
+    #[test]
+    fn test_line_height() {
+        let html = r#"<html><head><style>p { width: 80px; line-height: 2; }</style></head>
+            <body><p>aaaa bbbb cc</p></body></html>"#
+            .to_string();
+        let view = create_layout_view(html);
+        let p = view
+            .root()
+            .expect("root should exist")
+            .borrow()
+            .first_child();
+        let text = p.as_ref().expect("p should exist").borrow().first_child();
+
+        // 行の高さは32pxになり、文字の上下に6pxずつ余白ができる
+        assert_eq!(
+            vec![
+                ("aaaa bbbb".to_string(), LayoutPoint::new(0, 6)),
+                ("cc".to_string(), LayoutPoint::new(0, 38)),
+            ],
+            fragments(&text)
+        );
+        assert_eq!(
+            LayoutSize::new(80, 64),
+            p.expect("p should exist").borrow().size()
+        );
+    }
+
+    #[test]
+    fn test_line_height_em_is_inherited_as_length() {
+        // 2emはpのフォントサイズ(16px)で32pxになり、32pxの文字を含む行でもそのまま使われる
+        let html = r#"<html><head><style>
+            p { width: 200px; line-height: 2em; }
+            a { font-size: 32px; }
+            </style></head>
+            <body><p><a>x</a></p></body></html>"#
+            .to_string();
+        let view = create_layout_view(html);
+        let p = view
+            .root()
+            .expect("root should exist")
+            .borrow()
+            .first_child();
+        assert_eq!(
+            LayoutSize::new(200, 32),
+            p.expect("p should exist").borrow().size()
+        );
+    }
+}
@@ -1,19 +1,45 @@
-use crate::constants::CHAR_HEIGHT_WITH_PADDING;
-use crate::constants::CHAR_WIDTH;
-use crate::constants::CONTENT_AREA_WIDTH;
-use crate::constants::WINDOW_PADDING;
-use crate::constants::WINDOW_WIDTH;
 use crate::display_item::DisplayItem;
+use crate::error::Error;
+use crate::image::Image;
 use crate::renderer::css::cssom::ComponentValue;
 use crate::renderer::css::cssom::Declaration;
 use crate::renderer::css::cssom::Selector;
 use crate::renderer::css::cssom::StyleSheet;
+use crate::renderer::dom::node::ElementKind;
 use crate::renderer::dom::node::Node;
 use crate::renderer::dom::node::NodeKind;
+use crate::renderer::layout::computed_style::parse_flex;
+use crate::renderer::layout::computed_style::parse_font_family;
+use crate::renderer::layout::computed_style::AlignItems;
+use crate::renderer::layout::computed_style::BorderCollapse;
+use crate::renderer::layout::computed_style::BorderStyle;
+use crate::renderer::layout::computed_style::BoxEdges;
+use crate::renderer::layout::computed_style::Clear;
 use crate::renderer::layout::computed_style::Color;
 use crate::renderer::layout::computed_style::ComputedStyle;
 use crate::renderer::layout::computed_style::DisplayType;
+use crate::renderer::layout::computed_style::FlexDirection;
+use crate::renderer::layout::computed_style::FlexWrap;
+use crate::renderer::layout::computed_style::Float;
 use crate::renderer::layout::computed_style::FontSize;
+use crate::renderer::layout::computed_style::FontStyle;
+use crate::renderer::layout::computed_style::FontWeight;
+use crate::renderer::layout::computed_style::JustifyContent;
+use crate::renderer::layout::computed_style::Length;
+use crate::renderer::layout::computed_style::LineHeight;
+use crate::renderer::layout::computed_style::ListStylePosition;
+use crate::renderer::layout::computed_style::ListStyleType;
+use crate::renderer::layout::computed_style::Overflow;
+use crate::renderer::layout::computed_style::Position;
+use crate::renderer::layout::computed_style::TextAlign;
+use crate::renderer::layout::computed_style::TextDecoration;
+use crate::renderer::layout::computed_style::WhiteSpace;
+use crate::renderer::layout::computed_style::ZIndex;
+use crate::renderer::layout::list_marker::bullet_size;
+use crate::renderer::layout::list_marker::list_item_ordinal;
+use crate::renderer::layout::list_marker::ListMarker;
+use crate::renderer::layout::property::side_of;
+use crate::renderer::layout::property::Property;
 use alloc::rc::Rc;
 use alloc::rc::Weak;
 use alloc::string::String;
@@ -21,32 +47,7 @@ use alloc::string::ToString;
 use alloc::vec;
 use alloc::vec::Vec;
 use core::cell::RefCell;
-
-fn find_index_for_line_break(line: String, max_index: usize) -> usize {
-    for i in (0..max_index).rev() {
-        if line.chars().collect::<Vec<char>>()[i] == ' ' {
-            return i;
-        }
-    }
-    max_index
-}
-
-fn split_text(line: String, char_width: i64) -> Vec<String> {
-    let mut result = vec![];
-
-    if line.len() as i64 * char_width > (WINDOW_WIDTH + WINDOW_PADDING) {
-        let s = line.split_at(find_index_for_line_break(
-            line.clone(),
-            ((WINDOW_WIDTH + WINDOW_PADDING) / char_width) as usize,
-        ));
-        result.push(s.0.to_string());
-        result.extend(split_text(s.1.trim().to_string(), char_width));
-    } else {
-        result.push(line);
-    }
-
-    result
-}
+use core::str::FromStr;
 
 #[derive(Debug, Clone)]
 pub struct LayoutObject {
@@ -58,6 +59,22 @@ pub struct LayoutObject {
     style: ComputedStyle,
     point: LayoutPoint,
     size: LayoutSize,
+    margin: LayoutEdges,
+    border: LayoutEdges,
+    padding: LayoutEdges,
+    fragments: Vec<LayoutFragment>,
+    /// フレックスレイアウトで決まったボーダーボックスの横幅
+    override_width: Option<i64>,
+    /// フレックスレイアウトで決まったコンテンツボックスの高さ
+    override_height: Option<i64>,
+    /// マーカーのボックスが表示する記号や番号
+    marker: Option<ListMarker>,
+    /// スクロールコンテナの内容をスクロールした量
+    scroll_offset: LayoutPoint,
+    /// スティッキー位置指定で、通常フローの位置からずらした量
+    sticky_offset: LayoutPoint,
+    /// `<img>`要素が表示する画像。読み込めなかった場合はNone
+    image: Option<Rc<Image>>,
 }
 
 impl PartialEq for LayoutObject {
@@ -82,192 +99,590 @@ impl LayoutObject {
             style: ComputedStyle::new(),
             point: LayoutPoint::new(0, 0),
             size: LayoutSize::new(0, 0),
+            margin: LayoutEdges::default(),
+            border: LayoutEdges::default(),
+            padding: LayoutEdges::default(),
+            fragments: Vec::new(),
+            override_width: None,
+            override_height: None,
+            marker: None,
+            scroll_offset: LayoutPoint::new(0, 0),
+            sticky_offset: LayoutPoint::new(0, 0),
+            image: None,
         }
     }
 
+    /// リスト項目の先頭に置くマーカーのボックスを作る
+    /// `list-style-type: none`の場合は作らない
+    pub fn new_marker(list_item: &Rc<RefCell<LayoutObject>>) -> Option<Self> {
+        let (node, style) = {
+            let item = list_item.borrow();
+            (item.node.clone(), item.style())
+        };
+        let marker = ListMarker::new(style.list_style_type(), list_item_ordinal(&node))?;
+
+        // ::marker擬似要素と同じように、リスト項目のスタイルを継承する
+        let mut object = Self::new(node.clone(), &Some(list_item.clone()));
+        object.style.set_display(DisplayType::Inline);
+        object.defaulting_style(&node, Some(style));
+        object.kind = LayoutObjectKind::Marker;
+        object.marker = Some(marker);
+        Some(object)
+    }
+
     pub fn paint(&mut self) -> Vec<DisplayItem> {
         if self.style.display() == DisplayType::DisplayNone {
             return vec![];
         }
         match self.kind {
-            LayoutObjectKind::Block => {
+            LayoutObjectKind::Block
+            | LayoutObjectKind::InlineBlock
+            | LayoutObjectKind::ListItem => {
                 if let NodeKind::Element(_e) = self.node_kind() {
-                    return vec![DisplayItem::Rect {
-                        style: self.style(),
-                        layout_point: self.point(),
-                        layout_size: self.size(),
-                    }];
+                    let mut v = Vec::new();
+                    // 透明な背景は描画しない
+                    if !self.style.background_color().is_transparent() {
+                        v.push(DisplayItem::Rect {
+                            style: self.style(),
+                            layout_point: self.point(),
+                            layout_size: self.size(),
+                        });
+                    }
+                    v.extend(self.paint_border());
+                    v.extend(self.paint_image());
+                    return v;
                 }
             }
             LayoutObjectKind::Inline => {
-                // 本書のブラウザでは、描画するインライン要素はない
-                // <img>タグなどをサポートする場合は、ここで描画処理を行う
+                // インラインの置換要素はインラインブロックとしてレイアウトするので、
+                // ここで描画するものはない
             }
             LayoutObjectKind::Text => {
-                if let NodeKind::Text(t) = self.node_kind() {
-                    let mut v = vec![];
-                    let ratio = match self.style.font_size() {
-                        FontSize::Medium => 1,
-                        FontSize::XLarge => 2,
-                        FontSize::XXLarge => 3,
-                    };
+                // インラインレイアウトで決まった断片をそのまま描画する
+                let mut style = self.style();
+                style.set_text_decoration(self.propagated_text_decoration());
+                return self
+                    .fragments
+                    .iter()
+                    .map(|f| DisplayItem::Text {
+                        text: f.text(),
+                        style: style.clone(),
+                        layout_point: f.point(),
+                    })
+                    .collect();
+            }
+            LayoutObjectKind::Marker => return self.paint_marker(),
+        }
 
-                    let plain_text = t
-                        .replace("\n", " ")
-                        .split(' ')
-                        .filter(|s| !s.is_empty())
-                        .collect::<Vec<_>>()
-                        .join(" ");
-                    let lines = split_text(plain_text, CHAR_WIDTH * ratio);
-                    let mut i = 0;
-                    for line in lines {
-                        let item = DisplayItem::Text {
-                            text: line,
-                            style: self.style(),
-                            layout_point: LayoutPoint::new(
-                                self.point().x(),
-                                self.point().y() + CHAR_HEIGHT_WITH_PADDING * i,
-                            ),
-                        };
-                        v.push(item);
-                        i += 1;
-                    }
+        vec![]
+    }
 
-                    return v;
-                }
+    /// 祖先のボックスから伝わるものを含めた、テキストに引く線
+    /// 浮動化、絶対位置指定、インラインブロックのボックスには、その外側から伝わらない
+    /// https://www.w3.org/TR/css-text-decor-3/#line-decoration
+    fn propagated_text_decoration(&self) -> TextDecoration {
+        let stops = |object: &LayoutObject| {
+            object.is_float()
+                || object.is_absolutely_positioned()
+                || object.kind == LayoutObjectKind::InlineBlock
+        };
+        if self.style.text_decoration() != TextDecoration::None || stops(self) {
+            return self.style.text_decoration();
+        }
+        let mut ancestor = self.parent.upgrade();
+        while let Some(node) = ancestor {
+            let object = node.borrow();
+            if object.style.text_decoration() != TextDecoration::None || stops(&object) {
+                return object.style.text_decoration();
             }
+            ancestor = object.parent.upgrade();
         }
+        TextDecoration::None
+    }
 
-        vec![]
+    /// 記号は行の高さの中央に、番号はテキストと同じように描画する
+    fn paint_marker(&self) -> Vec<DisplayItem> {
+        let marker = match &self.marker {
+            Some(marker) => marker,
+            None => return vec![],
+        };
+        let size = bullet_size(self.font_size_px());
+        self.fragments
+            .iter()
+            .map(|f| match marker {
+                ListMarker::Bullet(shape) => DisplayItem::Bullet {
+                    shape: *shape,
+                    style: self.style(),
+                    layout_point: LayoutPoint::new(
+                        f.point().x(),
+                        f.point().y() + (f.size().height() - size) / 2,
+                    ),
+                    layout_size: LayoutSize::new(size, size),
+                },
+                ListMarker::Text(text) => DisplayItem::Text {
+                    text: text.clone(),
+                    style: self.style(),
+                    layout_point: f.point(),
+                },
+            })
+            .collect()
     }
 
-    pub fn compute_size(&mut self, parent_size: LayoutSize) {
-        let mut size = LayoutSize::new(0, 0);
+    /// 画像をコンテンツボックスの大きさに拡大縮小して描画する
+    fn paint_image(&self) -> Option<DisplayItem> {
+        let image = self.image.clone()?;
+        let padding = self.padding;
+        let border = self.border;
+        let size = LayoutSize::new(
+            self.size.width() - border.horizontal() - padding.horizontal(),
+            self.size.height() - border.vertical() - padding.vertical(),
+        );
+        if size.width() <= 0 || size.height() <= 0 {
+            return None;
+        }
+        Some(DisplayItem::Image {
+            image,
+            layout_point: LayoutPoint::new(
+                self.point.x() + border.left + padding.left,
+                self.point.y() + border.top + padding.top,
+            ),
+            layout_size: size,
+        })
+    }
 
-        match self.kind() {
-            LayoutObjectKind::Block => {
-                size.set_width(parent_size.width());
+    fn paint_border(&self) -> Vec<DisplayItem> {
+        let border = self.border;
+        if self.style.border_style() == BorderStyle::None {
+            return vec![];
+        }
 
-                // すべての子ノードの高さを足し合わせた結果が高さになる
-                // ただし、インライン要素が並んでいる場合は注意が必要
-                let mut height = 0;
-                let mut child = self.first_child();
-                let mut previous_child_kind = LayoutObjectKind::Block;
-                while child.is_some() {
-                    let c = match child {
-                        Some(c) => c,
-                        None => panic!("first child should exist"),
-                    };
+        // ボーダーはボーダーの色で塗りつぶした矩形として描画する
+        let mut style = self.style();
+        style.set_background_color(self.style.border_color());
 
-                    if previous_child_kind == LayoutObjectKind::Block
-                        || c.borrow().kind() == LayoutObjectKind::Block
-                    {
-                        height += c.borrow().size.height();
-                    }
+        let x = self.point.x();
+        let y = self.point.y();
+        let width = self.size.width();
+        let height = self.size.height();
+        let sides = [
+            (x, y, width, border.top),
+            (x, y + height - border.bottom, width, border.bottom),
+            (x, y, border.left, height),
+            (x + width - border.right, y, border.right, height),
+        ];
 
-                    previous_child_kind = c.borrow().kind();
-                    child = c.borrow().next_sibling();
-                }
+        sides
+            .iter()
+            .filter(|(_, _, w, h)| *w > 0 && *h > 0)
+            .map(|(x, y, w, h)| DisplayItem::Rect {
+                style: style.clone(),
+                layout_point: LayoutPoint::new(*x, *y),
+                layout_size: LayoutSize::new(*w, *h),
+            })
+            .collect()
+    }
 
-                size.set_height(height);
-            }
-            LayoutObjectKind::Inline => {
-                // すべての子ノードの高さと横幅を足し合わせた結果が現在のノードの高さと横幅になる
-                let mut width = 0;
-                let mut height = 0;
-                let mut child = self.first_child();
-                while child.is_some() {
-                    let c = match child {
-                        Some(c) => c,
-                        None => panic!("first child should exist"),
-                    };
+    /// 包含ブロックの横幅をもとに、マージン、ボーダー、パディングの値とボーダーボックスの横幅を決める
+    /// `shrink_to_fit`がtrueの場合、widthがautoであれば`preferred_width`(コンテンツの幅)に合わせる
+    pub fn resolve_box_model(
+        &mut self,
+        containing_width: i64,
+        shrink_to_fit: bool,
+        preferred_width: i64,
+    ) {
+        let font_size = self.font_size_px();
+        let margin = self.style.margin();
+        let padding = self.style.padding();
+        let border = self.style.border_width();
 
-                    width += c.borrow().size.width();
-                    height += c.borrow().size.height();
+        let px = |l: Length| l.to_px(containing_width, font_size).unwrap_or(0);
+        self.padding = LayoutEdges::new(
+            px(padding.top),
+            px(padding.right),
+            px(padding.bottom),
+            px(padding.left),
+        );
+        self.border = LayoutEdges::new(
+            px(border.top),
+            px(border.right),
+            px(border.bottom),
+            px(border.left),
+        );
+        let margin_top = px(margin.top);
+        let margin_bottom = px(margin.bottom);
+        let margin_left = margin.left.to_px(containing_width, font_size);
+        let margin_right = margin.right.to_px(containing_width, font_size);
 
-                    child = c.borrow().next_sibling();
-                }
+        // フレックスアイテムの横幅はフレックスレイアウトで決まっている
+        // autoのマージンもフレックスレイアウトで配置するので、ここでは0にする
+        if let Some(width) = self.override_width {
+            self.margin = LayoutEdges::new(
+                margin_top,
+                margin_right.unwrap_or(0),
+                margin_bottom,
+                margin_left.unwrap_or(0),
+            );
+            self.size.set_width(core::cmp::max(width, 0));
+            return;
+        }
 
-                size.set_width(width);
-                size.set_height(height);
-            }
-            LayoutObjectKind::Text => {
-                if let NodeKind::Text(t) = self.node_kind() {
-                    let ratio = match self.style.font_size() {
-                        FontSize::Medium => 1,
-                        FontSize::XLarge => 2,
-                        FontSize::XXLarge => 3,
-                    };
+        let extra = self.padding.horizontal() + self.border.horizontal();
+        let available = containing_width - margin_left.unwrap_or(0) - margin_right.unwrap_or(0);
+        let specified_width = if self.is_replaced() {
+            Some(self.replaced_width(containing_width))
+        } else {
+            self.style.width().to_px(containing_width, font_size)
+        };
+        let width = match specified_width {
+            Some(w) => w + extra,
+            None if shrink_to_fit => core::cmp::min(preferred_width, available),
+            None => available,
+        };
 
-                    let width = CHAR_WIDTH * ratio * t.len() as i64;
-                    if width > CONTENT_AREA_WIDTH {
-                        size.set_width(CONTENT_AREA_WIDTH);
-                        let line_num = if width.wrapping_rem(CONTENT_AREA_WIDTH) == 0 {
-                            width.wrapping_div(CONTENT_AREA_WIDTH)
-                        } else {
-                            width.wrapping_div(CONTENT_AREA_WIDTH) + 1
-                        };
-                        size.set_height(CHAR_HEIGHT_WITH_PADDING * ratio * line_num);
-                    } else {
-                        // テキストが1行に収まる場合
-                        size.set_width(width);
-                        size.set_height(CHAR_HEIGHT_WITH_PADDING * ratio);
-                    }
-                }
-            }
+        // 横幅が決まっている場合、autoのマージンは残りの領域を埋める
+        let remaining = containing_width - width;
+        let (left, right) = match (margin_left, margin_right) {
+            (None, None) if !shrink_to_fit => (
+                core::cmp::max(remaining / 2, 0),
+                core::cmp::max(remaining / 2, 0),
+            ),
+            (None, Some(r)) if !shrink_to_fit => (remaining - r, r),
+            (Some(l), None) if !shrink_to_fit => (l, remaining - l),
+            (l, r) => (l.unwrap_or(0), r.unwrap_or(0)),
+        };
+
+        self.margin = LayoutEdges::new(margin_top, right, margin_bottom, left);
+        self.size.set_width(core::cmp::max(width, 0));
+    }
+
+    /// 明示的に指定された高さ(コンテンツボックス)を返す
+    pub fn specified_height(&self) -> Option<i64> {
+        if self.override_height.is_some() {
+            return self.override_height;
         }
+        if self.is_replaced() {
+            let content_width =
+                self.size.width() - self.padding.horizontal() - self.border.horizontal();
+            return Some(self.replaced_height(content_width));
+        }
+        match self.style.height() {
+            // 包含ブロックの高さに依存するパーセンテージはautoとして扱う
+            Length::Percent(_) => None,
+            h => h.to_px(0, self.font_size_px()),
+        }
+    }
 
-        self.size = size;
+    /// 中身をレイアウトせず、画像などで置き換えて表示する置換要素かどうか
+    pub fn is_replaced(&self) -> bool {
+        matches!(&self.node_kind(), NodeKind::Element(e) if e.kind() == ElementKind::Img)
     }
 
-    pub fn compute_position(
-        &mut self,
-        parent_point: LayoutPoint,
-        previous_sibling_kind: LayoutObjectKind,
-        previous_sibling_point: Option<LayoutPoint>,
-        previous_sibling_size: Option<LayoutSize>,
-    ) {
-        let mut point = LayoutPoint::new(0, 0);
+    pub fn image(&self) -> Option<Rc<Image>> {
+        self.image.clone()
+    }
 
-        match (self.kind(), previous_sibling_kind) {
-            // ブロック要素が兄弟ノードの場合、Y軸方向に進む
-            (LayoutObjectKind::Block, _) | (_, LayoutObjectKind::Block) => {
-                if let (Some(size), Some(pos)) = (previous_sibling_size, previous_sibling_point) {
-                    point.set_y(pos.y() + size.height());
-                } else {
-                    point.set_y(parent_point.y());
-                }
-                point.set_x(parent_point.x());
-            }
-            // インライン要素が兄弟ノードの場合、X軸方向に進む
-            (LayoutObjectKind::Inline, LayoutObjectKind::Inline) => {
-                if let (Some(size), Some(pos)) = (previous_sibling_size, previous_sibling_point) {
-                    point.set_x(pos.x() + size.width());
-                    point.set_y(pos.y());
-                } else {
-                    point.set_x(parent_point.x());
-                    point.set_y(parent_point.y());
-                }
-            }
-            _ => {
-                point.set_x(parent_point.x());
-                point.set_y(parent_point.y());
-            }
+    pub fn set_image(&mut self, image: Option<Rc<Image>>) {
+        self.image = image;
+    }
+
+    /// 画像の大きさ。画像がない場合は0になる
+    fn intrinsic_size(&self) -> (i64, i64) {
+        match &self.image {
+            Some(image) => (image.width(), image.height()),
+            None => (0, 0),
+        }
+    }
+
+    /// `width`か`height`属性の値。数字だけの値をピクセルとして読む
+    fn dimension_attribute(&self, name: &str) -> Option<i64> {
+        match self.node_kind() {
+            NodeKind::Element(e) => e.get_attribute(name)?.trim().parse::<i64>().ok(),
+            _ => None,
+        }
+    }
+
+    /// CSSの`height`か、`height`属性で指定された置換要素の高さ
+    fn specified_replaced_height(&self) -> Option<i64> {
+        match self.style.height() {
+            Length::Percent(_) => None,
+            h => h.to_px(0, self.font_size_px()),
         }
+        .or_else(|| self.dimension_attribute("height"))
+    }
 
+    /// 置換要素のコンテンツボックスの横幅
+    /// CSSの`width`、`width`属性、高さと画像の縦横比、画像の横幅の順に決める
+    /// https://www.w3.org/TR/CSS2/visudet.html#inline-replaced-width
+    pub fn replaced_width(&self, containing_width: i64) -> i64 {
+        if let Some(w) = self
+            .style
+            .width()
+            .to_px(containing_width, self.font_size_px())
+            .or_else(|| self.dimension_attribute("width"))
+        {
+            return w;
+        }
+        let (width, height) = self.intrinsic_size();
+        match self.specified_replaced_height() {
+            Some(h) if height > 0 => h * width / height,
+            _ => width,
+        }
+    }
+
+    /// 置換要素のコンテンツボックスの高さ
+    /// 高さが指定されていない場合は、決まった横幅と画像の縦横比から決める
+    /// https://www.w3.org/TR/CSS2/visudet.html#inline-replaced-height
+    pub fn replaced_height(&self, content_width: i64) -> i64 {
+        if let Some(h) = self.specified_replaced_height() {
+            return h;
+        }
+        let (width, height) = self.intrinsic_size();
+        if width > 0 {
+            content_width * height / width
+        } else {
+            height
+        }
+    }
+
+    pub fn font_size_px(&self) -> i64 {
+        self.style.font_size().to_px()
+    }
+
+    /// 新しいブロック整形コンテキスト(BFC)を作るかどうか
+    pub fn establishes_bfc(&self) -> bool {
+        if self.parent.upgrade().is_none() {
+            // ルート要素
+            return true;
+        }
+        self.kind == LayoutObjectKind::InlineBlock
+            || self.is_float()
+            || self.is_absolutely_positioned()
+            || self.style.overflow() != Overflow::Visible
+            || self.style.display() == DisplayType::FlowRoot
+            || self.is_flex_container()
+            || self.is_flex_item()
+            || matches!(
+                self.style.display(),
+                DisplayType::Table | DisplayType::TableCaption | DisplayType::TableCell
+            )
+    }
+
+    pub fn is_table(&self) -> bool {
+        self.style.display() == DisplayType::Table
+    }
+
+    /// 子要素をフレックスレイアウトで配置するかどうか
+    pub fn is_flex_container(&self) -> bool {
+        matches!(
+            self.style.display(),
+            DisplayType::Flex | DisplayType::InlineFlex
+        )
+    }
+
+    /// 親要素がフレックスコンテナかどうか
+    pub fn is_flex_item(&self) -> bool {
+        match self.parent.upgrade() {
+            Some(parent) => parent.borrow().is_flex_container(),
+            None => false,
+        }
+    }
+
+    /// フレックスレイアウトで決めた大きさで、ボックスをレイアウトさせる
+    /// Noneの場合はスタイルから大きさを決める
+    pub fn set_override_size(&mut self, width: Option<i64>, height: Option<i64>) {
+        self.override_width = width;
+        self.override_height = height;
+    }
+
+    pub fn is_float(&self) -> bool {
+        self.style.float() != Float::None
+    }
+
+    /// `position`がstatic以外の、位置指定されたボックスかどうか
+    pub fn is_positioned(&self) -> bool {
+        self.style.position() != Position::Static
+    }
+
+    /// 通常フローから取り除かれる、absoluteかfixedのボックスかどうか
+    pub fn is_absolutely_positioned(&self) -> bool {
+        self.style.position().is_absolute()
+    }
+
+    /// 子孫を重ねる順序をまとめる、重ね合わせコンテキストを作るかどうか
+    /// https://www.w3.org/TR/CSS2/visuren.html#z-index
+    pub fn establishes_stacking_context(&self) -> bool {
+        if self.parent.upgrade().is_none() {
+            // ルート要素
+            return true;
+        }
+        match self.style.position() {
+            Position::Static => false,
+            Position::Fixed | Position::Sticky => true,
+            Position::Relative | Position::Absolute => self.style.z_index() != ZIndex::Auto,
+        }
+    }
+
+    /// 重ね合わせコンテキストの中で並べる順序。autoは0として扱う
+    pub fn z_index(&self) -> i64 {
+        match self.style.z_index() {
+            ZIndex::Auto => 0,
+            ZIndex::Index(z) => z,
+        }
+    }
+
+    /// 内容をパディングボックスで切り取り、スクロールできるようにするかどうか
+    /// https://www.w3.org/TR/css-overflow-3/#scroll-container
+    pub fn is_scroll_container(&self) -> bool {
+        self.style.overflow() != Overflow::Visible
+    }
+
+    pub fn scroll_offset(&self) -> LayoutPoint {
+        self.scroll_offset
+    }
+
+    pub fn set_scroll_offset(&mut self, scroll_offset: LayoutPoint) {
+        self.scroll_offset = scroll_offset;
+    }
+
+    pub fn sticky_offset(&self) -> LayoutPoint {
+        self.sticky_offset
+    }
+
+    pub fn set_sticky_offset(&mut self, sticky_offset: LayoutPoint) {
+        self.sticky_offset = sticky_offset;
+    }
+
+    /// パディングボックスの左上と大きさ
+    pub fn padding_box(&self) -> (LayoutPoint, LayoutSize) {
+        (
+            LayoutPoint::new(
+                self.point.x() + self.border.left,
+                self.point.y() + self.border.top,
+            ),
+            LayoutSize::new(
+                self.size.width() - self.border.horizontal(),
+                self.size.height() - self.border.vertical(),
+            ),
+        )
+    }
+
+    pub fn is_block_level(&self) -> bool {
+        matches!(
+            self.kind,
+            LayoutObjectKind::Block | LayoutObjectKind::ListItem
+        )
+    }
+
+    pub fn marker(&self) -> Option<ListMarker> {
+        self.marker.clone()
+    }
+
+    /// 行ボックスに並ばず、リスト項目の左側に置かれるマーカーかどうか
+    pub fn is_outside_marker(&self) -> bool {
+        self.kind == LayoutObjectKind::Marker
+            && self.style.list_style_position() == ListStylePosition::Outside
+    }
+
+    pub fn set_point(&mut self, point: LayoutPoint) {
         self.point = point;
     }
 
+    pub fn set_size(&mut self, size: LayoutSize) {
+        self.size = size;
+    }
+
+    pub fn margin(&self) -> LayoutEdges {
+        self.margin
+    }
+
+    pub fn border(&self) -> LayoutEdges {
+        self.border
+    }
+
+    pub fn padding(&self) -> LayoutEdges {
+        self.padding
+    }
+
+    /// 行ボックスに配置されたテキストやインライン要素の断片
+    pub fn fragments(&self) -> Vec<LayoutFragment> {
+        self.fragments.clone()
+    }
+
+    pub fn set_fragments(&mut self, fragments: Vec<LayoutFragment>) {
+        self.fragments = fragments;
+    }
+
+    /// ボックスと断片を移動させる
+    pub fn translate(&mut self, dx: i64, dy: i64) {
+        self.point = LayoutPoint::new(self.point.x() + dx, self.point.y() + dy);
+        for f in &mut self.fragments {
+            f.point = LayoutPoint::new(f.point.x() + dx, f.point.y() + dy);
+        }
+    }
+
+    /// 位置がボックスの内側にあるかどうか
+    /// 断片を持つ場合は、いずれかの断片の内側にあるかどうかで判断する
+    pub fn contains(&self, x: i64, y: i64) -> bool {
+        let inside = |point: LayoutPoint, size: LayoutSize| {
+            point.x() <= x
+                && x <= point.x() + size.width()
+                && point.y() <= y
+                && y <= point.y() + size.height()
+        };
+
+        if self.fragments.is_empty() {
+            inside(self.point, self.size)
+        } else {
+            self.fragments.iter().any(|f| inside(f.point, f.size))
+        }
+    }
+
     pub fn update_kind(&mut self) {
         match self.node_kind() {
             NodeKind::Document => panic!("should not create layout object for a Document node"),
             NodeKind::Element(_) => {
                 let display = self.style.display();
+                // フレックスアイテムはブロックレベルのボックスになる
+                // https://www.w3.org/TR/css-display-3/#blockify
+                if self.is_flex_item() {
+                    self.kind = match display {
+                        DisplayType::ListItem => LayoutObjectKind::ListItem,
+                        _ => LayoutObjectKind::Block,
+                    };
+                    return;
+                }
+                // フロートと絶対位置指定のボックスもブロックレベルのボックスになる
+                if (self.is_float() || self.is_absolutely_positioned())
+                    && display != DisplayType::ListItem
+                {
+                    self.kind = LayoutObjectKind::Block;
+                    return;
+                }
                 match display {
-                    DisplayType::Block => self.kind = LayoutObjectKind::Block,
+                    DisplayType::Block
+                    | DisplayType::FlowRoot
+                    | DisplayType::Flex
+                    | DisplayType::Table
+                    | DisplayType::TableCaption
+                    | DisplayType::TableHeaderGroup
+                    | DisplayType::TableRowGroup
+                    | DisplayType::TableFooterGroup
+                    | DisplayType::TableRow
+                    | DisplayType::TableCell => self.kind = LayoutObjectKind::Block,
+
+                    DisplayType::ListItem => self.kind = LayoutObjectKind::ListItem,
+
+                    // インラインの置換要素は、行の中で1つの箱として扱う
+                    DisplayType::Inline if self.is_replaced() => {
+                        self.kind = LayoutObjectKind::InlineBlock
+                    }
 
                     DisplayType::Inline => self.kind = LayoutObjectKind::Inline,
 
+                    DisplayType::InlineBlock | DisplayType::InlineFlex => {
+                        self.kind = LayoutObjectKind::InlineBlock
+                    }
+
                     DisplayType::DisplayNone => {
                         panic!("should not create layout object for a display:none");
                     }
@@ -285,41 +700,78 @@ impl LayoutObject {
         self.style.defaulting(node, parent_style);
     }
 
+    /// 親要素から継承する色。`color: currentColor`は`inherit`と同じ意味になる
+    fn inherited_color(&self) -> Color {
+        match self.parent.upgrade() {
+            Some(parent) => parent.borrow().style().color(),
+            None => Color::black(),
+        }
+    }
+
+    /// `currentColor`をこの要素の`color`の値に置き換えて色を読み込む
+    fn parse_color(&self, value: &ComponentValue) -> Result<Color, Error> {
+        match value {
+            ComponentValue::Ident(ident) if ident.eq_ignore_ascii_case("currentcolor") => {
+                Ok(self.style.current_color().unwrap_or(self.inherited_color()))
+            }
+            _ => Color::from_component_value(value),
+        }
+    }
+
+    /// すべてのプロパティで使える`inherit`、`initial`、`unset`を適用する
+    /// キーワードでない場合はfalseを返す
+    /// https://www.w3.org/TR/css-cascade-4/#defaulting-keywords
+    fn apply_css_wide_keyword(&mut self, declaration: &Declaration) -> bool {
+        let keyword = match &declaration.value {
+            ComponentValue::Ident(ident) if declaration.values.len() == 1 => {
+                ident.to_ascii_lowercase()
+            }
+            _ => return false,
+        };
+        if !matches!(keyword.as_str(), "inherit" | "initial" | "unset") {
+            return false;
+        }
+
+        let parent_style = self.parent.upgrade().map(|parent| parent.borrow().style());
+        let initial = ComputedStyle::initial();
+        for property in Property::longhands(&declaration.property) {
+            let inherit = match keyword.as_str() {
+                "inherit" => true,
+                "initial" => false,
+                _ => property.inherited(),
+            };
+            // ルート要素のinheritは初期値になる
+            let from = match (&parent_style, inherit) {
+                (Some(parent_style), true) => parent_style,
+                _ => &initial,
+            };
+            match side_of(&declaration.property) {
+                Some(side) => self.style.copy_side(property, side, from),
+                None => self.style.copy_property(property, from),
+            }
+        }
+
+        true
+    }
+
     pub fn cascading_style(&mut self, declarations: Vec<Declaration>) {
-        for declaration in declarations {
+        // currentColorを解決できるように、colorを先に適用する
+        let (colors, others): (Vec<_>, Vec<_>) = declarations
+            .into_iter()
+            .partition(|declaration| declaration.property == "color");
+        for declaration in colors.into_iter().chain(others) {
+            if self.apply_css_wide_keyword(&declaration) {
+                continue;
+            }
+
             match declaration.property.as_str() {
                 "background-color" => {
-                    if let ComponentValue::Ident(value) = &declaration.value {
-                        let color = match Color::from_name(&value) {
-                            Ok(color) => color,
-                            Err(_) => Color::white(),
-                        };
+                    if let Ok(color) = self.parse_color(&declaration.value) {
                         self.style.set_background_color(color);
-                        continue;
-                    }
-                    if let ComponentValue::HashToken(color_code) = &declaration.value {
-                        let color = match Color::from_code(&color_code) {
-                            Ok(color) => color,
-                            Err(_) => Color::white(),
-                        };
-                        self.style.set_background_color(color);
-                        continue;
                     }
                 }
                 "color" => {
-                    if let ComponentValue::Ident(value) = &declaration.value {
-                        let color = match Color::from_name(&value) {
-                            Ok(color) => color,
-                            Err(_) => Color::black(),
-                        };
-                        self.style.set_color(color);
-                    }
-
-                    if let ComponentValue::HashToken(color_code) = &declaration.value {
-                        let color = match Color::from_code(&color_code) {
-                            Ok(color) => color,
-                            Err(_) => Color::black(),
-                        };
+                    if let Ok(color) = self.parse_color(&declaration.value) {
                         self.style.set_color(color);
                     }
                 }
@@ -332,6 +784,330 @@ impl LayoutObject {
                         self.style.set_display(display_type);
                     }
                 }
+                "width" | "height" => {
+                    if let Ok(length) = Length::from_component_value(&declaration.value) {
+                        if declaration.property == "width" {
+                            self.style.set_width(length);
+                        } else {
+                            self.style.set_height(length);
+                        }
+                    }
+                }
+                "margin" | "padding" | "border-width" => {
+                    let lengths = declaration
+                        .values
+                        .iter()
+                        .filter_map(|v| Length::from_component_value(v).ok())
+                        .collect::<Vec<_>>();
+                    if let Some(edges) = BoxEdges::from_shorthand(&lengths) {
+                        match declaration.property.as_str() {
+                            "margin" => self.style.set_margin(edges),
+                            "padding" => self.style.set_padding(edges),
+                            _ => self.style.set_border_width(edges),
+                        }
+                    }
+                }
+                "margin-top" | "margin-right" | "margin-bottom" | "margin-left" => {
+                    if let Ok(length) = Length::from_component_value(&declaration.value) {
+                        let mut margin = self.style.margin_or_initial();
+                        margin.set_side(&declaration.property["margin-".len()..], length);
+                        self.style.set_margin(margin);
+                    }
+                }
+                "padding-top" | "padding-right" | "padding-bottom" | "padding-left" => {
+                    if let Ok(length) = Length::from_component_value(&declaration.value) {
+                        let mut padding = self.style.padding_or_initial();
+                        padding.set_side(&declaration.property["padding-".len()..], length);
+                        self.style.set_padding(padding);
+                    }
+                }
+                "border-top-width"
+                | "border-right-width"
+                | "border-bottom-width"
+                | "border-left-width" => {
+                    if let Ok(length) = Length::from_component_value(&declaration.value) {
+                        let side = declaration.property["border-".len()..]
+                            .trim_end_matches("-width")
+                            .to_string();
+                        let mut border_width = self.style.border_width_or_initial();
+                        border_width.set_side(&side, length);
+                        self.style.set_border_width(border_width);
+                    }
+                }
+                "border-style" => {
+                    if let ComponentValue::Ident(value) = &declaration.value {
+                        if let Ok(border_style) = BorderStyle::from_str(value) {
+                            self.style.set_border_style(border_style);
+                        }
+                    }
+                }
+                "border-color" => {
+                    if let Ok(color) = self.parse_color(&declaration.value) {
+                        self.style.set_border_color(color);
+                    }
+                }
+                "border" => {
+                    // `border: 1px solid red;` の各値を順不同で読み込む
+                    for value in &declaration.values {
+                        if let Ok(length) = Length::from_component_value(value) {
+                            self.style.set_border_width(BoxEdges::all(length));
+                            continue;
+                        }
+                        if let ComponentValue::Ident(ident) = value {
+                            if let Ok(border_style) = BorderStyle::from_str(ident) {
+                                self.style.set_border_style(border_style);
+                                continue;
+                            }
+                        }
+                        if let Ok(color) = self.parse_color(value) {
+                            self.style.set_border_color(color);
+                        }
+                    }
+                }
+                "font-size" => {
+                    // emやパーセンテージは親要素のフォントサイズをもとに計算する
+                    let parent_px = match self.parent.upgrade() {
+                        Some(parent) => parent.borrow().font_size_px(),
+                        None => FontSize::Medium.to_px(),
+                    };
+                    if let Ok(font_size) =
+                        FontSize::from_component_value(&declaration.value, parent_px)
+                    {
+                        self.style.set_font_size(font_size);
+                    }
+                }
+                "font-weight" => {
+                    if let Ok(font_weight) = FontWeight::from_component_value(&declaration.value) {
+                        self.style.set_font_weight(font_weight);
+                    }
+                }
+                "font-style" => {
+                    if let ComponentValue::Ident(value) = &declaration.value {
+                        if let Ok(font_style) = FontStyle::from_str(value) {
+                            self.style.set_font_style(font_style);
+                        }
+                    }
+                }
+                "font-family" => {
+                    if let Ok(font_family) = parse_font_family(&declaration.values) {
+                        self.style.set_font_family(font_family);
+                    }
+                }
+                "line-height" => {
+                    if let Ok(line_height) = LineHeight::from_component_value(&declaration.value) {
+                        self.style.set_line_height(line_height);
+                    }
+                }
+                "text-align" => {
+                    if let ComponentValue::Ident(value) = &declaration.value {
+                        if let Ok(text_align) = TextAlign::from_str(value) {
+                            self.style.set_text_align(text_align);
+                        }
+                    }
+                }
+                "white-space" => {
+                    if let ComponentValue::Ident(value) = &declaration.value {
+                        if let Ok(white_space) = WhiteSpace::from_str(value) {
+                            self.style.set_white_space(white_space);
+                        }
+                    }
+                }
+                "text-decoration" => {
+                    if let ComponentValue::Ident(value) = &declaration.value {
+                        if let Ok(text_decoration) = TextDecoration::from_str(value) {
+                            self.style.set_text_decoration(text_decoration);
+                        }
+                    }
+                }
+                "overflow" => {
+                    if let ComponentValue::Ident(value) = &declaration.value {
+                        if let Ok(overflow) = Overflow::from_str(value) {
+                            self.style.set_overflow(overflow);
+                        }
+                    }
+                }
+                "float" => {
+                    if let ComponentValue::Ident(value) = &declaration.value {
+                        if let Ok(float) = Float::from_str(value) {
+                            self.style.set_float(float);
+                        }
+                    }
+                }
+                "clear" => {
+                    if let ComponentValue::Ident(value) = &declaration.value {
+                        if let Ok(clear) = Clear::from_str(value) {
+                            self.style.set_clear(clear);
+                        }
+                    }
+                }
+                "position" => {
+                    if let ComponentValue::Ident(value) = &declaration.value {
+                        if let Ok(position) = Position::from_str(value) {
+                            self.style.set_position(position);
+                        }
+                    }
+                }
+                "inset" => {
+                    let lengths = declaration
+                        .values
+                        .iter()
+                        .filter_map(|v| Length::from_component_value(v).ok())
+                        .collect::<Vec<_>>();
+                    if let Some(edges) = BoxEdges::from_shorthand(&lengths) {
+                        self.style.set_inset(edges);
+                    }
+                }
+                "top" | "right" | "bottom" | "left" => {
+                    if let Ok(length) = Length::from_component_value(&declaration.value) {
+                        let mut inset = self.style.inset_or_initial();
+                        inset.set_side(&declaration.property, length);
+                        self.style.set_inset(inset);
+                    }
+                }
+                "z-index" => {
+                    if let Ok(z_index) = ZIndex::from_component_value(&declaration.value) {
+                        self.style.set_z_index(z_index);
+                    }
+                }
+                "flex-direction" => {
+                    if let ComponentValue::Ident(value) = &declaration.value {
+                        if let Ok(direction) = FlexDirection::from_str(value) {
+                            self.style.set_flex_direction(direction);
+                        }
+                    }
+                }
+                "flex-wrap" => {
+                    if let ComponentValue::Ident(value) = &declaration.value {
+                        if let Ok(wrap) = FlexWrap::from_str(value) {
+                            self.style.set_flex_wrap(wrap);
+                        }
+                    }
+                }
+                "flex-flow" => {
+                    for value in &declaration.values {
+                        if let ComponentValue::Ident(value) = value {
+                            if let Ok(direction) = FlexDirection::from_str(value) {
+                                self.style.set_flex_direction(direction);
+                            } else if let Ok(wrap) = FlexWrap::from_str(value) {
+                                self.style.set_flex_wrap(wrap);
+                            }
+                        }
+                    }
+                }
+                "justify-content" => {
+                    if let ComponentValue::Ident(value) = &declaration.value {
+                        if let Ok(justify) = JustifyContent::from_str(value) {
+                            self.style.set_justify_content(justify);
+                        }
+                    }
+                }
+                "align-items" => {
+                    if let ComponentValue::Ident(value) = &declaration.value {
+                        if let Ok(align) = AlignItems::from_str(value) {
+                            self.style.set_align_items(align);
+                        }
+                    }
+                }
+                "flex-grow" | "flex-shrink" => {
+                    if let ComponentValue::Number(n) = declaration.value {
+                        if n >= 0.0 {
+                            if declaration.property == "flex-grow" {
+                                self.style.set_flex_grow(n);
+                            } else {
+                                self.style.set_flex_shrink(n);
+                            }
+                        }
+                    }
+                }
+                "flex-basis" => {
+                    if let Ok(length) = Length::from_component_value(&declaration.value) {
+                        self.style.set_flex_basis(length);
+                    }
+                }
+                "flex" => {
+                    if let Ok((grow, shrink, basis)) = parse_flex(&declaration.values) {
+                        self.style.set_flex_grow(grow);
+                        self.style.set_flex_shrink(shrink);
+                        self.style.set_flex_basis(basis);
+                    }
+                }
+                "list-style-type" => {
+                    if let ComponentValue::Ident(value) = &declaration.value {
+                        if let Ok(list_style_type) = ListStyleType::from_str(value) {
+                            self.style.set_list_style_type(list_style_type);
+                        }
+                    }
+                }
+                "list-style-position" => {
+                    if let ComponentValue::Ident(value) = &declaration.value {
+                        if let Ok(position) = ListStylePosition::from_str(value) {
+                            self.style.set_list_style_position(position);
+                        }
+                    }
+                }
+                "list-style" => {
+                    // 指定されなかった値は初期値に戻る
+                    let mut list_style_type = ListStyleType::Disc;
+                    let mut position = ListStylePosition::Outside;
+                    for value in &declaration.values {
+                        if let ComponentValue::Ident(value) = value {
+                            if let Ok(p) = ListStylePosition::from_str(value) {
+                                position = p;
+                            } else if let Ok(t) = ListStyleType::from_str(value) {
+                                list_style_type = t;
+                            }
+                        }
+                    }
+                    self.style.set_list_style_type(list_style_type);
+                    self.style.set_list_style_position(position);
+                }
+                "border-collapse" => {
+                    if let ComponentValue::Ident(value) = &declaration.value {
+                        if let Ok(border_collapse) = BorderCollapse::from_str(value) {
+                            self.style.set_border_collapse(border_collapse);
+                        }
+                    }
+                }
+                "border-spacing" => {
+                    let lengths = declaration
+                        .values
+                        .iter()
+                        .map(Length::from_component_value)
+                        .collect::<Result<Vec<_>, _>>();
+                    match lengths.as_deref() {
+                        Ok([spacing]) => self.style.set_border_spacing(*spacing, *spacing),
+                        Ok([horizontal, vertical]) => {
+                            self.style.set_border_spacing(*horizontal, *vertical)
+                        }
+                        _ => {}
+                    }
+                }
+                "gap" | "row-gap" | "column-gap" => {
+                    // normalはフレックスコンテナでは0になる
+                    let lengths = declaration
+                        .values
+                        .iter()
+                        .map(|v| match v {
+                            ComponentValue::Ident(ident) if ident == "normal" => {
+                                Ok(Length::Px(0.0))
+                            }
+                            v => Length::from_component_value(v),
+                        })
+                        .collect::<Result<Vec<_>, _>>();
+                    match (declaration.property.as_str(), lengths.as_deref()) {
+                        ("gap", Ok([gap])) => {
+                            self.style.set_row_gap(*gap);
+                            self.style.set_column_gap(*gap);
+                        }
+                        ("gap", Ok([row, column])) => {
+                            self.style.set_row_gap(*row);
+                            self.style.set_column_gap(*column);
+                        }
+                        ("row-gap", Ok([gap])) => self.style.set_row_gap(*gap),
+                        ("column-gap", Ok([gap])) => self.style.set_column_gap(*gap),
+                        _ => {}
+                    }
+                }
                 _ => {}
             }
         }
@@ -413,7 +1189,44 @@ impl LayoutObject {
 pub enum LayoutObjectKind {
     Block,
     Inline,
+    InlineBlock,
     Text,
+    /// `display: list-item`のボックス。先頭の子にマーカーのボックスを持つ
+    ListItem,
+    /// リスト項目の記号や番号
+    Marker,
+}
+
+/// 1つの行ボックスに収まるテキストやインライン要素の一部分
+#[derive(Debug, Clone, PartialEq)]
+pub struct LayoutFragment {
+    text: String,
+    point: LayoutPoint,
+    size: LayoutSize,
+}
+
+impl LayoutFragment {
+    pub fn new(text: String, point: LayoutPoint, size: LayoutSize) -> Self {
+        Self { text, point, size }
+    }
+
+    pub fn text(&self) -> String {
+        self.text.clone()
+    }
+
+    pub fn point(&self) -> LayoutPoint {
+        self.point
+    }
+
+    pub fn size(&self) -> LayoutSize {
+        self.size
+    }
+
+    /// 同じ行で右隣に続くテキストを追加する
+    pub fn push_str(&mut self, text: &str, width: i64) {
+        self.text.push_str(text);
+        self.size.set_width(self.size.width() + width);
+    }
 }
 
 #[derive(Debug, Clone, PartialEq, Copy)]
@@ -444,6 +1257,33 @@ impl LayoutPoint {
     }
 }
 
+#[derive(Debug, Clone, PartialEq, Copy, Default)]
+pub struct LayoutEdges {
+    pub top: i64,
+    pub right: i64,
+    pub bottom: i64,
+    pub left: i64,
+}
+
+impl LayoutEdges {
+    pub fn new(top: i64, right: i64, bottom: i64, left: i64) -> Self {
+        Self {
+            top,
+            right,
+            bottom,
+            left,
+        }
+    }
+
+    pub fn horizontal(&self) -> i64 {
+        self.left + self.right
+    }
+
+    pub fn vertical(&self) -> i64 {
+        self.top + self.bottom
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Copy)]
 pub struct LayoutSize {
     width: i64,
@@ -472,12 +1312,30 @@ impl LayoutSize {
     }
 }
 
+/// ボックスの子を順に返す
+pub fn children(node: &Rc<RefCell<LayoutObject>>) -> Vec<Rc<RefCell<LayoutObject>>> {
+    let mut children = Vec::new();
+    let mut child = node.borrow().first_child();
+    while let Some(c) = child {
+        children.push(c.clone());
+        child = c.borrow().next_sibling();
+    }
+    children
+}
+
 pub fn create_layout_object(
     node: &Option<Rc<RefCell<Node>>>,
     parent_obj: &Option<Rc<RefCell<LayoutObject>>>,
     cssom: &StyleSheet,
 ) -> Option<Rc<RefCell<LayoutObject>>> {
     if let Some(n) = node {
+        // フレックスコンテナ直下の空白だけのテキストは、フレックスアイテムにならない
+        if let Some(parent) = parent_obj {
+            if parent.borrow().is_flex_container() && is_white_space_text(n) {
+                return None;
+            }
+        }
+
         let layout_object = Rc::new(RefCell::new(LayoutObject::new(n.clone(), parent_obj)));
 
         for rule in &cssom.rules {
@@ -505,3 +1363,10 @@ pub fn create_layout_object(
 
     None
 }
+
+fn is_white_space_text(node: &Rc<RefCell<Node>>) -> bool {
+    match node.borrow().kind() {
+        NodeKind::Text(t) => t.chars().all(|c| c.is_whitespace()),
+        _ => false,
+    }
+}
@@ -1,14 +1,27 @@
-use crate::constants::CONTENT_AREA_WIDTH;
 use crate::display_item::DisplayItem;
+use crate::font::collection::FontCollection;
+use crate::image::ImageMap;
 use crate::renderer::css::cssom::StyleSheet;
 use crate::renderer::dom::api::get_target_element_node;
 use crate::renderer::dom::node::ElementKind;
 use crate::renderer::dom::node::Node;
+use crate::renderer::dom::node::NodeKind;
+use crate::renderer::layout::block_layout::layout_formatting_context_root;
 use crate::renderer::layout::layout_object::create_layout_object;
 use crate::renderer::layout::layout_object::LayoutObject;
 use crate::renderer::layout::layout_object::LayoutObjectKind;
 use crate::renderer::layout::layout_object::LayoutPoint;
-use crate::renderer::layout::layout_object::LayoutSize;
+use crate::renderer::layout::overflow::apply_scroll_offsets;
+use crate::renderer::layout::overflow::box_extent;
+use crate::renderer::layout::overflow::find_scroll_container;
+use crate::renderer::layout::overflow::scroll_container_by;
+use crate::renderer::layout::position_layout::apply_sticky_offsets;
+use crate::renderer::layout::position_layout::layout_positioned_boxes;
+use crate::renderer::layout::position_layout::translate_fixed_boxes;
+use crate::renderer::layout::position_layout::update_sticky_offsets;
+use crate::renderer::layout::stacking_context::hit_test_stacking_context;
+use crate::renderer::layout::stacking_context::paint_stacking_context;
+use crate::renderer::layout::viewport::Viewport;
 use alloc::rc::Rc;
 use alloc::vec::Vec;
 use core::cell::RefCell;
@@ -16,142 +29,169 @@ use core::cell::RefCell;
 #[derive(Debug, Clone)]
 pub struct LayoutView {
     root: Option<Rc<RefCell<LayoutObject>>>,
+    fonts: FontCollection,
+    viewport: Viewport,
+    /// ページ全体をスクロールした量。ビューポートの左上にあたるページの座標
+    scroll_offset: LayoutPoint,
 }
 
 impl LayoutView {
-    pub fn new(root: Rc<RefCell<Node>>, cssom: &StyleSheet) -> Self {
+    /// `viewport`の大きさに合わせてレイアウトする
+    pub fn new(root: Rc<RefCell<Node>>, cssom: &StyleSheet, viewport: Viewport) -> Self {
+        Self::with_fonts(root, cssom, viewport, FontCollection::default())
+    }
+
+    /// `font-family`に応じて`fonts`から選んだフォントで、文字の大きさを測ってレイアウトする
+    pub fn with_fonts(
+        root: Rc<RefCell<Node>>,
+        cssom: &StyleSheet,
+        viewport: Viewport,
+        fonts: FontCollection,
+    ) -> Self {
+        Self::with_images(root, cssom, viewport, fonts, &ImageMap::new())
+    }
+
+    /// `<img>`要素に、`src`属性の値をキーにして`images`から探した画像を表示させる
+    pub fn with_images(
+        root: Rc<RefCell<Node>>,
+        cssom: &StyleSheet,
+        viewport: Viewport,
+        fonts: FontCollection,
+        images: &ImageMap,
+    ) -> Self {
         let body_root = get_target_element_node(Some(root), ElementKind::Body);
 
         let mut tree = Self {
             root: build_layout_tree(&body_root, &None, cssom),
+            fonts,
+            viewport,
+            scroll_offset: LayoutPoint::new(0, 0),
         };
 
+        attach_images(&tree.root, images);
         tree.update_layout();
 
         tree
     }
 
-    pub fn find_node_by_position(&self, position: (i64, i64)) -> Option<Rc<RefCell<LayoutObject>>> {
-        Self::find_node_by_position_internal(&self.root(), position)
-    }
-
-    fn find_node_by_position_internal(
-        node: &Option<Rc<RefCell<LayoutObject>>>,
-        position: (i64, i64),
-    ) -> Option<Rc<RefCell<LayoutObject>>> {
-        match node {
-            Some(n) => {
-                let first_child = n.borrow().first_child();
-                let result1 = Self::find_node_by_position_internal(&first_child, position);
-                if result1.is_some() {
-                    return result1;
-                }
-
-                let next_sibling = n.borrow().next_sibling();
-                let result2 = Self::find_node_by_position_internal(&next_sibling, position);
-                if result2.is_some() {
-                    return result2;
-                }
-
-                if n.borrow().point().x() <= position.0
-                    && position.0 <= (n.borrow().point().x() + n.borrow().size().width())
-                    && n.borrow().point().y() <= position.1
-                    && position.1 <= (n.borrow().point().y() + n.borrow().size().height())
-                {
-                    return Some(n.clone());
-                }
-                None
-            }
-            None => None,
-        }
+    pub fn viewport(&self) -> Viewport {
+        self.viewport
     }
 
-    fn calculate_node_size(node: &Option<Rc<RefCell<LayoutObject>>>, parent_size: LayoutSize) {
-        if let Some(n) = node {
-            // ノードがブロック要素の場合、子ノードのレイアウトを計算する前にサイズを横幅を決める
-            if n.borrow_mut().kind() == LayoutObjectKind::Block {
-                n.borrow_mut().compute_size(parent_size);
-            };
-
-            let first_child = n.borrow().first_child();
-            Self::calculate_node_size(&first_child, n.borrow().size());
-
-            let next_sibling = n.borrow().next_sibling();
-            Self::calculate_node_size(&next_sibling, parent_size);
+    /// ビューポートの大きさが変わったときに、レイアウトし直す
+    /// スクロールした量は、新しい大きさでスクロールできる範囲に収める
+    pub fn set_viewport(&mut self, viewport: Viewport) {
+        if viewport == self.viewport {
+            return;
+        }
+        self.viewport = viewport;
+        self.update_layout();
 
-            // 子ノードのサイズを計算した後に、サイズを計算する
-            // ブロック要素の場合、高さは子要素の高さに依存する
-            // インライン要素の場合、高さも横幅も子要素に依存する
-            n.borrow_mut().compute_size(parent_size);
+        let max = self.max_scroll_offset();
+        let offset = LayoutPoint::new(
+            self.scroll_offset.x().clamp(0, max.x()),
+            self.scroll_offset.y().clamp(0, max.y()),
+        );
+        if offset != self.scroll_offset {
+            self.scroll_offset = offset;
+            self.update_layout();
         }
     }
 
-    fn calculate_node_position(
-        node: &Option<Rc<RefCell<LayoutObject>>>,
-        parent_point: LayoutPoint,
-        previous_sibling_kind: LayoutObjectKind,
-        previous_sibling_point: Option<LayoutPoint>,
-        previous_sibling_size: Option<LayoutSize>,
-    ) {
-        if let Some(n) = node {
-            n.borrow_mut().compute_position(
-                parent_point,
-                previous_sibling_kind,
-                previous_sibling_point,
-                previous_sibling_size,
-            );
-
-            let first_child = n.borrow().first_child();
-            Self::calculate_node_position(
-                &first_child,
-                n.borrow().point(),
-                LayoutObjectKind::Block,
-                None,
-                None,
-            );
+    /// ビューポートの`position`にあるボックスのうち、最も手前に描画されるものを探す
+    pub fn find_node_by_position(&self, position: (i64, i64)) -> Option<Rc<RefCell<LayoutObject>>> {
+        let position = (
+            position.0 + self.scroll_offset.x(),
+            position.1 + self.scroll_offset.y(),
+        );
+        // 手前に描画されるボックスほど先に見つかる
+        hit_test_stacking_context(self.root.as_ref()?, position)
+    }
 
-            let next_sibling = n.borrow().next_sibling();
-            Self::calculate_node_position(
-                &next_sibling,
-                parent_point,
-                n.borrow().kind(),
-                Some(n.borrow().point()),
-                Some(n.borrow().size()),
+    fn update_layout(&mut self) {
+        if let Some(root) = &self.root {
+            layout_formatting_context_root(
+                root,
+                LayoutPoint::new(0, 0),
+                self.viewport.width(),
+                &self.fonts,
             );
+            // 絶対位置指定のボックスは、包含ブロックの大きさが決まってから配置する
+            // 固定位置指定のボックスは、スクロールしたビューポートに配置する
+            layout_positioned_boxes(root, self.scroll_offset, self.viewport.size(), &self.fonts);
+            apply_scroll_offsets(root);
+            // スティッキー位置指定のボックスは、スクロールコンテナの内容を移動させてからずらす
+            apply_sticky_offsets(root, self.scroll_offset, self.viewport.size());
         }
     }
 
-    fn update_layout(&mut self) {
-        Self::calculate_node_size(&self.root, LayoutSize::new(CONTENT_AREA_WIDTH, 0));
-
-        Self::calculate_node_position(
-            &self.root,
-            LayoutPoint::new(0, 0),
-            LayoutObjectKind::Block,
-            None,
-            None,
-        );
+    pub fn scroll_offset(&self) -> LayoutPoint {
+        self.scroll_offset
     }
 
-    fn paint_node(node: &Option<Rc<RefCell<LayoutObject>>>, display_items: &mut Vec<DisplayItem>) {
-        match node {
-            Some(n) => {
-                display_items.extend(n.borrow_mut().paint());
+    /// ページ全体をスクロールできる最大の量
+    pub fn max_scroll_offset(&self) -> LayoutPoint {
+        let (right, bottom) = match &self.root {
+            Some(root) => box_extent(root),
+            None => (0, 0),
+        };
+        LayoutPoint::new(
+            core::cmp::max(right - self.viewport.width(), 0),
+            core::cmp::max(bottom - self.viewport.height(), 0),
+        )
+    }
 
-                let first_child = n.borrow().first_child();
-                Self::paint_node(&first_child, display_items);
+    /// ページ全体を、ビューポートの左上が(x, y)になるようにスクロールする
+    pub fn scroll_to(&mut self, x: i64, y: i64) {
+        let max = self.max_scroll_offset();
+        let offset = LayoutPoint::new(x.clamp(0, max.x()), y.clamp(0, max.y()));
+        if offset == self.scroll_offset {
+            return;
+        }
+        let (dx, dy) = (
+            offset.x() - self.scroll_offset.x(),
+            offset.y() - self.scroll_offset.y(),
+        );
+        self.scroll_offset = offset;
+        // レイアウトし直さずに、固定位置指定とスティッキー位置指定のボックスだけを配置し直す
+        // 他のボックスは、描画するときにスクロールした量だけずらす
+        if let Some(root) = &self.root {
+            translate_fixed_boxes(root, dx, dy);
+            update_sticky_offsets(root, self.scroll_offset, self.viewport.size());
+        }
+    }
 
-                let next_sibling = n.borrow().next_sibling();
-                Self::paint_node(&next_sibling, display_items);
+    /// ビューポートの`position`にある、(dx, dy)の方向にスクロールできる最も内側のスクロールコンテナをスクロールする
+    /// そのようなスクロールコンテナがない場合は、ページ全体をスクロールする
+    /// スクロールした場合はtrueを返す
+    pub fn scroll_by(&mut self, position: (i64, i64), dx: i64, dy: i64) -> bool {
+        let container = self
+            .find_node_by_position(position)
+            .and_then(|node| find_scroll_container(&node, dx, dy));
+        if let Some(container) = container {
+            scroll_container_by(&container, dx, dy);
+            if let Some(root) = &self.root {
+                update_sticky_offsets(root, self.scroll_offset, self.viewport.size());
             }
-            None => (),
+            return true;
         }
+
+        let before = self.scroll_offset;
+        self.scroll_to(before.x() + dx, before.y() + dy);
+        self.scroll_offset != before
     }
 
+    /// 重ね合わせの順序(z-index)に従い、奥にあるものから順に描画する
+    /// アイテムの座標は、ページのスクロールを反映したビューポートの座標になる
     pub fn paint(&self) -> Vec<DisplayItem> {
         let mut display_items = Vec::new();
 
-        Self::paint_node(&self.root, &mut display_items);
+        if let Some(root) = &self.root {
+            paint_stacking_context(root, &mut display_items);
+        }
+        for item in &mut display_items {
+            item.translate(-self.scroll_offset.x(), -self.scroll_offset.y());
+        }
 
         display_items
     }
@@ -161,6 +201,23 @@ impl LayoutView {
     }
 }
 
+/// 置換要素に読み込んだ画像を持たせる
+fn attach_images(node: &Option<Rc<RefCell<LayoutObject>>>, images: &ImageMap) {
+    let mut current = node.clone();
+    while let Some(n) = current {
+        if n.borrow().is_replaced() {
+            let src = match n.borrow().node_kind() {
+                NodeKind::Element(e) => e.get_attribute("src"),
+                _ => None,
+            };
+            let image = src.and_then(|src| images.get(&src).cloned());
+            n.borrow_mut().set_image(image);
+        }
+        attach_images(&n.borrow().first_child(), images);
+        current = n.borrow().next_sibling();
+    }
+}
+
 fn build_layout_tree(
     node: &Option<Rc<RefCell<Node>>>,
     parent_obj: &Option<Rc<RefCell<LayoutObject>>>,
@@ -181,7 +238,7 @@ fn build_layout_tree(
         let original_first_child = n.borrow().first_child();
         let original_next_sibling = n.borrow().next_sibling();
         let mut first_child = build_layout_tree(&original_first_child, &layout_object, cssom);
-        let mut next_sibling = build_layout_tree(&original_next_sibling, &None, cssom);
+        let mut next_sibling = build_layout_tree(&original_next_sibling, parent_obj, cssom);
 
         // display: none の場合、レイアウトツリーには追加しない走査は継続
         if first_child.is_none() && original_first_child.is_some() {
@@ -212,7 +269,7 @@ fn build_layout_tree(
                 .next_sibling();
 
             loop {
-                next_sibling = build_layout_tree(&original_dom_node, &None, cssom);
+                next_sibling = build_layout_tree(&original_dom_node, parent_obj, cssom);
 
                 if next_sibling.is_none() && original_dom_node.is_some() {
                     original_dom_node = original_dom_node
@@ -230,6 +287,14 @@ fn build_layout_tree(
             Some(ref obj) => obj,
             None => panic!("render object should exist here"),
         };
+        // リスト項目は、先頭の子としてマーカーのボックスを持つ
+        if obj.borrow().kind() == LayoutObjectKind::ListItem {
+            if let Some(marker) = LayoutObject::new_marker(obj) {
+                let marker = Rc::new(RefCell::new(marker));
+                marker.borrow_mut().set_next_sibling(first_child);
+                first_child = Some(marker);
+            }
+        }
         obj.borrow_mut().set_first_child(first_child);
         obj.borrow_mut().set_next_sibling(next_sibling);
     }
@@ -240,27 +305,32 @@ fn build_layout_tree(
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::constants::CONTENT_AREA_HEIGHT;
+    use crate::constants::CONTENT_AREA_WIDTH;
+    use crate::image::Image;
     use crate::renderer::css::cssom::CssParser;
     use crate::renderer::css::token::CssTokenizer;
     use crate::renderer::dom::api::get_style_content;
     use crate::renderer::dom::node::Element;
-    use crate::renderer::dom::node::NodeKind;
     use crate::renderer::html::parser::HtmlParser;
     use crate::renderer::html::token::HtmlTokenizer;
+    use crate::renderer::layout::computed_style::BoxEdges;
+    use crate::renderer::layout::computed_style::Color;
+    use crate::renderer::layout::computed_style::FontSize;
+    use crate::renderer::layout::computed_style::FontStyle;
+    use crate::renderer::layout::computed_style::FontWeight;
+    use crate::renderer::layout::computed_style::Length;
+    use crate::renderer::layout::computed_style::TextDecoration;
+    use crate::renderer::layout::layout_object::children;
+    use crate::renderer::layout::layout_object::LayoutSize;
+    use crate::renderer::layout::test_util::create_layout_view;
+    use crate::renderer::layout::test_util::create_layout_view_in;
+    use alloc::format;
     use alloc::string::String;
     use alloc::string::ToString;
+    use alloc::vec;
     use alloc::vec::Vec;
 
-    fn create_layout_view(html: String) -> LayoutView {
-        let t = HtmlTokenizer::new(html);
-        let window = HtmlParser::new(t).construct_tree();
-        let dom = window.borrow().document();
-        let style = get_style_content(dom.clone());
-        let css_tokenizer = CssTokenizer::new(style);
-        let cssom = CssParser::new(css_tokenizer).parse_stylesheet();
-        LayoutView::new(dom, &cssom)
-    }
-
     #[test]
     fn test_empty() {
         let layout_view = create_layout_view("".to_string());
@@ -368,28 +438,460 @@ mod tests {
                 .node_kind()
         );
 
-        let p = root.expect("root should exist").borrow().first_child();
-        assert!(p.is_some());
-        assert_eq!(
-            LayoutObjectKind::Block,
-            p.clone().expect("p node should exist").borrow().kind()
-        );
+        // 要素の間の空白だけのテキストは残るが、行ボックスを作らない
+        let mut elements = Vec::new();
+        let mut child = root.expect("root should exist").borrow().first_child();
+        while let Some(c) = child {
+            if c.borrow().kind() == LayoutObjectKind::Text {
+                assert!(c.borrow().fragments().is_empty());
+            } else {
+                elements.push(c.clone());
+            }
+            child = c.borrow().next_sibling();
+        }
+        assert_eq!(1, elements.len());
+
+        let p = &elements[0];
+        assert_eq!(LayoutObjectKind::Block, p.borrow().kind());
         assert_eq!(
             NodeKind::Element(Element::new("p", Vec::new())),
-            p.clone().expect("p node should exist").borrow().node_kind()
+            p.borrow().node_kind()
         );
+        assert!(p.borrow().first_child().is_none());
+    }
 
-        assert!(p
-            .clone()
-            .expect("p node should exist")
+    #[test]
+    fn test_font_weight_and_style() {
+        let html = r#"<html><body><p>a<b>bold <i>both</i></b></p><h1>title</h1></body></html>"#
+            .to_string();
+        let layout_view = create_layout_view(html);
+
+        let p = layout_view
+            .root()
+            .expect("root should exist")
+            .borrow()
+            .first_child()
+            .expect("p should exist");
+        let b = p
             .borrow()
             .first_child()
-            .is_none());
-        assert!(p
-            .clone()
-            .expect("p node should exist")
+            .expect("text should exist")
             .borrow()
             .next_sibling()
-            .is_none());
+            .expect("b should exist");
+        let i = b
+            .borrow()
+            .first_child()
+            .expect("text should exist")
+            .borrow()
+            .next_sibling()
+            .expect("i should exist");
+        let h1 = p.borrow().next_sibling().expect("h1 should exist");
+
+        assert_eq!(FontWeight::Normal, p.borrow().style().font_weight());
+        assert_eq!(FontWeight::Bold, b.borrow().style().font_weight());
+        // 太字は子要素に継承される
+        assert_eq!(FontWeight::Bold, i.borrow().style().font_weight());
+        assert_eq!(FontStyle::Italic, i.borrow().style().font_style());
+        assert_eq!(FontStyle::Normal, b.borrow().style().font_style());
+        assert_eq!(FontWeight::Bold, h1.borrow().style().font_weight());
+    }
+
+    #[test]
+    fn test_font_properties_from_css() {
+        let html = r#"<html><head><style>
+            p { font-family: "Noto Sans", Times New Roman, serif; font-weight: 700; }
+            b { font-weight: normal; font-style: oblique; }
+            </style></head>
+            <body><p>a<b>b</b></p></body></html>"#
+            .to_string();
+        let layout_view = create_layout_view(html);
+
+        let p = layout_view
+            .root()
+            .expect("root should exist")
+            .borrow()
+            .first_child()
+            .expect("p should exist");
+        let text = p.borrow().first_child().expect("text should exist");
+        let b = text.borrow().next_sibling().expect("b should exist");
+
+        assert_eq!(
+            vec![
+                "Noto Sans".to_string(),
+                "Times New Roman".to_string(),
+                "serif".to_string()
+            ],
+            text.borrow().style().font_family()
+        );
+        assert_eq!(FontWeight::Bold, text.borrow().style().font_weight());
+        assert_eq!(FontWeight::Normal, b.borrow().style().font_weight());
+        assert_eq!(FontStyle::Oblique, b.borrow().style().font_style());
+    }
+
+    #[test]
+    fn test_colors_from_css() {
+        let html = r#"<html><head><style>
+            p { border-color: currentColor; color: rgb(255 0 0 / 50%); background-color: nocolor; }
+            a { color: currentColor; background-color: #00f8; }
+            </style></head>
+            <body><p><a>link</a></p></body></html>"#
+            .to_string();
+        let layout_view = create_layout_view(html);
+
+        let p = layout_view
+            .root()
+            .expect("root should exist")
+            .borrow()
+            .first_child()
+            .expect("p should exist");
+        let a = p.borrow().first_child().expect("a should exist");
+
+        let red = Color::new(255, 0, 0, 128);
+        assert_eq!(red, p.borrow().style().color());
+        // colorより前に書かれていても、currentColorはこの要素のcolorになる
+        assert_eq!(red, p.borrow().style().border_color());
+        // 不正な値は無視され、初期値の透明になる
+        assert_eq!(Color::transparent(), p.borrow().style().background_color());
+        // colorのcurrentColorは親要素の色を継承する
+        assert_eq!(red, a.borrow().style().color());
+        assert_eq!(
+            Color::new(0, 0, 255, 0x88),
+            a.borrow().style().background_color()
+        );
+    }
+
+    #[test]
+    fn test_css_wide_keywords() {
+        let html = r#"<html><head><style>
+            p { color: red; background-color: blue; margin: 5px; border: 1px solid; }
+            p { border-top-width: initial; }
+            a { margin: inherit; color: initial; background-color: inherit; }
+            b { font-weight: initial; }
+            h1 { font-size: unset; margin: unset; }
+            </style></head>
+            <body><p><a>link</a><b>bold</b></p><h1>title</h1></body></html>"#
+            .to_string();
+        let layout_view = create_layout_view(html);
+
+        let p = layout_view
+            .root()
+            .expect("root should exist")
+            .borrow()
+            .first_child()
+            .expect("p should exist");
+        let a = p.borrow().first_child().expect("a should exist");
+        let b = a.borrow().next_sibling().expect("b should exist");
+        let b_text = b.borrow().first_child().expect("text should exist");
+        let h1 = p.borrow().next_sibling().expect("h1 should exist");
+
+        let p_style = p.borrow().style();
+        assert_eq!(Length::Px(3.0), p_style.border_width().top);
+        assert_eq!(Length::Px(1.0), p_style.border_width().left);
+
+        let a_style = a.borrow().style();
+        assert_eq!(BoxEdges::all(Length::Px(5.0)), a_style.margin());
+        assert_eq!(Color::black(), a_style.color());
+        assert_eq!(Color::from_u32(0x0000ff), a_style.background_color());
+
+        // 継承されるプロパティは、親要素の色によらず継承される
+        assert_eq!(Color::from_u32(0xff0000), b_text.borrow().style().color());
+        // 継承されないプロパティは初期値になる
+        assert_eq!(Color::transparent(), b.borrow().style().background_color());
+        assert_eq!(BoxEdges::zero(), b.borrow().style().margin());
+        assert_eq!(FontWeight::Normal, b.borrow().style().font_weight());
+        assert_eq!(FontWeight::Normal, b_text.borrow().style().font_weight());
+
+        // unsetは継承されるプロパティではinherit、それ以外ではinitialになる
+        assert_eq!(FontSize::Medium, h1.borrow().style().font_size());
+        assert_eq!(BoxEdges::zero(), h1.borrow().style().margin());
+        assert_eq!(FontWeight::Bold, h1.borrow().style().font_weight());
+    }
+
+    /// `a.png`という20x10の画像を読み込んだことにしてレイアウトする
+    fn create_layout_view_with_image(style: &str, body: &str) -> LayoutView {
+        let html = format!(
+            "<html><head><style>{}</style></head><body>{}</body></html>",
+            style, body
+        );
+        let t = HtmlTokenizer::new(html);
+        let window = HtmlParser::new(t).construct_tree();
+        let dom = window.borrow().document();
+        let style = get_style_content(dom.clone());
+        let cssom = CssParser::new(CssTokenizer::new(style)).parse_stylesheet();
+        let mut images = ImageMap::new();
+        images.insert(
+            "a.png".to_string(),
+            Rc::new(Image::new(20, 10).expect("failed to create image")),
+        );
+        LayoutView::with_images(
+            dom,
+            &cssom,
+            Viewport::new(CONTENT_AREA_WIDTH, CONTENT_AREA_HEIGHT),
+            FontCollection::default(),
+            &images,
+        )
+    }
+
+    /// 最初に見つかった`<img>`要素のボックス
+    fn find_img(node: Option<Rc<RefCell<LayoutObject>>>) -> Option<Rc<RefCell<LayoutObject>>> {
+        let mut current = node;
+        while let Some(n) = current {
+            if n.borrow().is_replaced() {
+                return Some(n);
+            }
+            if let Some(found) = find_img(n.borrow().first_child()) {
+                return Some(found);
+            }
+            current = n.borrow().next_sibling();
+        }
+        None
+    }
+
+    fn img_size(style: &str, body: &str) -> LayoutSize {
+        let view = create_layout_view_with_image(style, body);
+        let img = find_img(view.root()).expect("img should exist");
+        let size = img.borrow().size();
+        size
+    }
+
+    #[test]
+    fn test_img_intrinsic_size() {
+        let view = create_layout_view_with_image("", "<img src=\"a.png\">");
+        let img = find_img(view.root()).expect("img should exist");
+        assert_eq!(LayoutObjectKind::InlineBlock, img.borrow().kind());
+        assert_eq!(LayoutSize::new(20, 10), img.borrow().size());
+    }
+
+    #[test]
+    fn test_img_size_from_attributes() {
+        assert_eq!(
+            LayoutSize::new(8, 30),
+            img_size("", "<img src=\"a.png\" width=\"8\" height=\"30\">")
+        );
+        // 片方だけ指定した場合は、画像の縦横比を保つ
+        assert_eq!(
+            LayoutSize::new(40, 20),
+            img_size("", "<img src=\"a.png\" width=\"40\">")
+        );
+        assert_eq!(
+            LayoutSize::new(10, 5),
+            img_size("", "<img src=\"a.png\" height=\"5\">")
+        );
+    }
+
+    #[test]
+    fn test_img_size_from_css() {
+        // CSSの値は属性より優先される
+        assert_eq!(
+            LayoutSize::new(60, 30),
+            img_size(
+                ".w { width: 60px; }",
+                "<img class=\"w\" src=\"a.png\" width=\"8\">"
+            )
+        );
+        // ボーダーとパディングはコンテンツボックスの外側に加わる
+        assert_eq!(
+            LayoutSize::new(30, 20),
+            img_size(
+                ".p { padding: 2px; border: 3px solid; }",
+                "<img class=\"p\" src=\"a.png\">"
+            )
+        );
+    }
+
+    #[test]
+    fn test_img_without_image() {
+        // 画像を読み込めなかった場合は、属性で指定した大きさの空の箱になる
+        assert_eq!(
+            LayoutSize::new(0, 0),
+            img_size("", "<img src=\"missing.png\">")
+        );
+        assert_eq!(
+            LayoutSize::new(16, 12),
+            img_size("", "<img src=\"missing.png\" width=\"16\" height=\"12\">")
+        );
+    }
+
+    #[test]
+    fn test_img_in_line() {
+        let view = create_layout_view_with_image("", "<p>ab<img src=\"a.png\">cd</p>");
+        let img = find_img(view.root()).expect("img should exist");
+        assert_eq!(16, img.borrow().point().x());
+
+        let cd = img.borrow().next_sibling().expect("text should exist");
+        assert_eq!(36, cd.borrow().fragments()[0].point().x());
+    }
+
+    #[test]
+    fn test_paint_img() {
+        let view = create_layout_view_with_image(
+            ".b { display: block; padding: 2px; border: 3px solid; }",
+            "<img class=\"b\" src=\"a.png\" width=\"40\">",
+        );
+        let items = view.paint();
+        let image = items
+            .iter()
+            .find_map(|item| match item {
+                DisplayItem::Image {
+                    layout_point,
+                    layout_size,
+                    ..
+                } => Some((*layout_point, *layout_size)),
+                _ => None,
+            })
+            .expect("image should be painted");
+        // 画像はコンテンツボックスに描画する
+        assert_eq!((LayoutPoint::new(5, 5), LayoutSize::new(40, 20)), image);
+    }
+
+    #[test]
+    fn test_paint_text_decoration() {
+        let view = create_layout_view_in(
+            Viewport::new(CONTENT_AREA_WIDTH, CONTENT_AREA_HEIGHT),
+            "p { text-decoration: underline; } .f { float: left; } .u { text-decoration: unset; }",
+            "<p>a<b>b</b><span class=\"f\">c</span><span class=\"u\">d</span></p>",
+        );
+        let p = view
+            .root()
+            .expect("root should exist")
+            .borrow()
+            .first_child()
+            .expect("p should exist");
+        let b = p
+            .borrow()
+            .first_child()
+            .and_then(|a| a.borrow().next_sibling())
+            .expect("b should exist");
+        // text-decorationは継承されない
+        assert_eq!(TextDecoration::None, b.borrow().style().text_decoration());
+
+        let underlines: Vec<(String, bool)> = view
+            .paint()
+            .iter()
+            .filter_map(|item| match item {
+                DisplayItem::Text { text, style, .. } => Some((
+                    text.clone(),
+                    style.text_decoration() == TextDecoration::Underline,
+                )),
+                _ => None,
+            })
+            .collect();
+        // 線は子孫のテキストにも引くが、浮動化したボックスの中には引かない
+        // unsetでも祖先の線は消えない
+        assert_eq!(
+            vec![
+                ("a".to_string(), true),
+                ("b".to_string(), true),
+                ("c".to_string(), false),
+                ("d".to_string(), true),
+            ],
+            underlines
+        );
+    }
+
+    #[test]
+    fn test_viewport_width() {
+        let view = create_layout_view_in(Viewport::new(1280, 800), "", "<div>a</div>");
+        let root = view.root().expect("root should exist");
+        assert_eq!(1280, root.borrow().size().width());
+        let div = root.borrow().first_child().expect("div should exist");
+        assert_eq!(1280, div.borrow().size().width());
+    }
+
+    #[test]
+    fn test_set_viewport() {
+        // 横幅が狭いと2行に折り返す
+        let mut view = create_layout_view_in(Viewport::new(60, 100), "", "<p>aaaa bbbb</p>");
+        let p = view
+            .root()
+            .expect("root should exist")
+            .borrow()
+            .first_child()
+            .expect("p should exist");
+        assert_eq!(LayoutSize::new(60, 40), p.borrow().size());
+
+        view.set_viewport(Viewport::new(200, 100));
+        assert_eq!(LayoutSize::new(200, 20), p.borrow().size());
+    }
+
+    #[test]
+    fn test_set_viewport_clamps_scroll_offset() {
+        let mut view = create_layout_view_in(
+            Viewport::new(100, 100),
+            ".t { height: 300px; }",
+            "<div class=\"t\"></div>",
+        );
+        view.scroll_to(0, 500);
+        assert_eq!(LayoutPoint::new(0, 200), view.scroll_offset());
+
+        // 高さが増えた分だけスクロールできる量が減る
+        view.set_viewport(Viewport::new(100, 250));
+        assert_eq!(LayoutPoint::new(0, 50), view.scroll_offset());
+        assert_eq!(Viewport::new(100, 250), view.viewport());
+    }
+
+    /// ボックスと断片の位置と大きさを、木の順にすべて集める
+    fn geometry(node: &Rc<RefCell<LayoutObject>>, result: &mut Vec<(LayoutPoint, LayoutSize)>) {
+        result.push((node.borrow().point(), node.borrow().size()));
+        for f in node.borrow().fragments() {
+            result.push((f.point(), f.size()));
+        }
+        for c in children(node) {
+            geometry(&c, result);
+        }
+    }
+
+    #[test]
+    fn test_scroll_does_not_change_layout() {
+        let mut body = "<div class=\"f\">f</div><div class=\"g\">g</div><div class=\"s\">s</div>\
+            <div class=\"c\"><div class=\"i\">a</div><div class=\"i\">b</div></div>"
+            .to_string();
+        for _ in 0..10 {
+            body.push_str("<div class=\"i\">x</div>");
+        }
+        let mut view = create_layout_view_in(
+            Viewport::new(200, 100),
+            ".f { position: fixed; bottom: 0; height: 10px; } .g { position: fixed; }\
+            .s { position: sticky; top: 0; height: 10px; }\
+            .c { overflow: auto; height: 40px; } .i { height: 30px; }",
+            &body,
+        );
+        let root = view.root().expect("root should exist");
+        let nodes = children(&root);
+        let mut before = Vec::new();
+        geometry(&root, &mut before);
+
+        assert!(view.scroll_by((100, 30), 0, 15));
+        view.scroll_to(0, 100);
+
+        // 通常フローのボックスは動かず、描画するときにずらす
+        assert_eq!(LayoutSize::new(200, 350), root.borrow().size());
+        let last = nodes.last().expect("item should exist");
+        assert_eq!(LayoutPoint::new(0, 320), last.borrow().point());
+        // スクロールコンテナの内容だけが、スクロールした量だけ動く
+        let c = &nodes[3];
+        assert_eq!(LayoutPoint::new(0, 10), c.borrow().point());
+        assert_eq!(LayoutPoint::new(0, -5), children(c)[0].borrow().point());
+        // 固定位置指定のボックスは静的位置のものもビューポートと一緒に動き、スティッキー位置指定のボックスは上端に張り付く
+        assert_eq!(LayoutPoint::new(0, 190), nodes[0].borrow().point());
+        assert_eq!(LayoutPoint::new(0, 100), nodes[1].borrow().point());
+        assert_eq!(LayoutPoint::new(0, 100), nodes[2].borrow().point());
+
+        // レイアウトし直しても同じ配置になる
+        let mut scrolled = Vec::new();
+        geometry(&root, &mut scrolled);
+        view.set_viewport(Viewport::new(200, 90));
+        view.set_viewport(Viewport::new(200, 100));
+        let mut relayout = Vec::new();
+        geometry(&root, &mut relayout);
+        assert_eq!(scrolled, relayout);
+
+        // スクロールを戻すと、最初の配置に戻る
+        view.scroll_to(0, 0);
+        assert!(view.scroll_by((100, 30), 0, -15));
+        let mut after = Vec::new();
+        geometry(&root, &mut after);
+        assert_eq!(before, after);
     }
 }
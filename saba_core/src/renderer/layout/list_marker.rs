@@ -0,0 +1,427 @@
+//! リスト項目の先頭に置くマーカー
+//! https://www.w3.org/TR/css-lists-3/#markers
+
+use crate::font::collection::FontCollection;
+use crate::renderer::dom::node::ElementKind;
+use crate::renderer::dom::node::Node;
+use crate::renderer::layout::computed_style::ListStyleType;
+use crate::renderer::layout::layout_object::LayoutObject;
+use alloc::format;
+use alloc::rc::Rc;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::cell::RefCell;
+
+/// マーカーの中身
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ListMarker {
+    /// disc、circle、squareの記号
+    Bullet(BulletShape),
+    /// "1. "や"iv. "のような番号
+    Text(String),
+}
+
+impl ListMarker {
+    /// `list-style-type`と番号から作る。noneの場合はマーカーを作らない
+    pub fn new(list_style_type: ListStyleType, ordinal: i64) -> Option<Self> {
+        let marker = match list_style_type {
+            ListStyleType::Disc => Self::Bullet(BulletShape::Disc),
+            ListStyleType::Circle => Self::Bullet(BulletShape::Circle),
+            ListStyleType::Square => Self::Bullet(BulletShape::Square),
+            ListStyleType::Decimal => Self::Text(format!("{}. ", ordinal)),
+            ListStyleType::LowerAlpha => Self::Text(format!("{}. ", to_alphabetic(ordinal))),
+            ListStyleType::UpperAlpha => {
+                Self::Text(format!("{}. ", to_alphabetic(ordinal).to_uppercase()))
+            }
+            ListStyleType::LowerRoman => Self::Text(format!("{}. ", to_roman(ordinal))),
+            ListStyleType::UpperRoman => {
+                Self::Text(format!("{}. ", to_roman(ordinal).to_uppercase()))
+            }
+            ListStyleType::None => return None,
+        };
+        Some(marker)
+    }
+
+    /// 記号の後ろにはフォントサイズの半分の空きを入れる
+    pub fn width(&self, node: &LayoutObject, fonts: &FontCollection) -> i64 {
+        let font_size = node.font_size_px();
+        match self {
+            Self::Bullet(_) => bullet_size(font_size) + font_size / 2,
+            Self::Text(text) => fonts
+                .select(&node.style().font_family())
+                .text_width(text, font_size),
+        }
+    }
+}
+
+/// 記号の一辺の長さ
+pub fn bullet_size(font_size: i64) -> i64 {
+    core::cmp::max(font_size / 3, 1)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BulletShape {
+    Disc,
+    Circle,
+    Square,
+}
+
+impl BulletShape {
+    /// 一辺が`size`の正方形の中で塗りつぶす部分を、横1行ずつの`(x, y, 幅)`で返す
+    pub fn spans(&self, size: i64) -> Vec<(i64, i64, i64)> {
+        // 中心からの距離を2倍した座標で比べ、左右と上下を対称にする
+        let outer = size * size;
+        let inner = (size - 2) * (size - 2);
+        let filled = |x: i64, y: i64| {
+            let dx = 2 * x + 1 - size;
+            let dy = 2 * y + 1 - size;
+            let d = dx * dx + dy * dy;
+            match self {
+                Self::Disc => d <= outer,
+                Self::Circle => d <= outer && d > inner,
+                Self::Square => true,
+            }
+        };
+
+        let mut spans = Vec::new();
+        for y in 0..size {
+            let mut start = None;
+            for x in 0..=size {
+                match (start, x < size && filled(x, y)) {
+                    (None, true) => start = Some(x),
+                    (Some(s), false) => {
+                        spans.push((s, y, x - s));
+                        start = None;
+                    }
+                    _ => {}
+                }
+            }
+        }
+        spans
+    }
+}
+
+/// リスト項目の番号
+/// `value`属性を持つ前の項目か、ol要素の`start`属性から数える
+/// 要素のdisplayはわからないので、同じ種類の兄弟要素をリスト項目として数える
+pub fn list_item_ordinal(node: &Rc<RefCell<Node>>) -> i64 {
+    let kind = node.borrow().element_kind();
+    let value_of = |node: &Rc<RefCell<Node>>, name: &str| {
+        node.borrow()
+            .get_element()
+            .and_then(|e| e.get_attribute(name))
+            .and_then(|v| v.trim().parse::<i64>().ok())
+    };
+
+    let mut count = 0;
+    let mut sibling = Some(node.clone());
+    while let Some(s) = sibling {
+        if s.borrow().element_kind() == kind {
+            if let Some(value) = value_of(&s, "value") {
+                return value + count;
+            }
+            count += 1;
+        }
+        sibling = s.borrow().previous_sibling().upgrade();
+    }
+
+    let start = match node.borrow().parent().upgrade() {
+        Some(parent) if parent.borrow().element_kind() == Some(ElementKind::Ol) => {
+            value_of(&parent, "start").unwrap_or(1)
+        }
+        _ => 1,
+    };
+    start + count - 1
+}
+
+/// 1からa、b、...、z、aa、ab、...の順に数える
+/// 0以下の番号は10進数で表す
+fn to_alphabetic(ordinal: i64) -> String {
+    if ordinal <= 0 {
+        return format!("{}", ordinal);
+    }
+
+    let mut n = ordinal;
+    let mut chars = Vec::new();
+    while n > 0 {
+        n -= 1;
+        chars.push((b'a' + (n % 26) as u8) as char);
+        n /= 26;
+    }
+    chars.iter().rev().collect()
+}
+
+/// ローマ数字で表せる1から3999以外の番号は10進数で表す
+fn to_roman(ordinal: i64) -> String {
+    if !(1..=3999).contains(&ordinal) {
+        return format!("{}", ordinal);
+    }
+
+    let numerals = [
+        (1000, "m"),
+        (900, "cm"),
+        (500, "d"),
+        (400, "cd"),
+        (100, "c"),
+        (90, "xc"),
+        (50, "l"),
+        (40, "xl"),
+        (10, "x"),
+        (9, "ix"),
+        (5, "v"),
+        (4, "iv"),
+        (1, "i"),
+    ];
+    let mut n = ordinal;
+    let mut roman = String::new();
+    for (value, numeral) in numerals {
+        while n >= value {
+            roman.push_str(numeral);
+            n -= value;
+        }
+    }
+    roman
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::display_item::DisplayItem;
+    use crate::renderer::layout::layout_object::LayoutObjectKind;
+    use crate::renderer::layout::layout_object::LayoutPoint;
+    use crate::renderer::layout::layout_object::LayoutSize;
+    use crate::renderer::layout::layout_view::LayoutView;
+    use crate::renderer::layout::test_util::create_layout_view;
+    use alloc::string::ToString;
+    use alloc::vec;
+
+    /// ツリーの順に並べたマーカーのボックス
+    fn markers(view: &LayoutView) -> Vec<Rc<RefCell<LayoutObject>>> {
+        fn walk(node: &Rc<RefCell<LayoutObject>>, found: &mut Vec<Rc<RefCell<LayoutObject>>>) {
+            if node.borrow().kind() == LayoutObjectKind::Marker {
+                found.push(node.clone());
+            }
+            let mut child = node.borrow().first_child();
+            while let Some(c) = child {
+                walk(&c, found);
+                child = c.borrow().next_sibling();
+            }
+        }
+
+        let mut found = Vec::new();
+        walk(&view.root().expect("root should exist"), &mut found);
+        found
+    }
+
+    fn text_of(marker: &Rc<RefCell<LayoutObject>>) -> String {
+        match marker.borrow().marker() {
+            Some(ListMarker::Text(text)) => text,
+            marker => panic!("expected a text marker, but got {:?}", marker),
+        }
+    }
+
+    #[test]
+    fn test_outside_bullet() {
+        let view = create_layout_view(
+            "<html><body><ul><li>a</li><li>b</li></ul></body></html>".to_string(),
+        );
+        let ul = view.root().unwrap().borrow().first_child().unwrap();
+        // ulは上下に1emのマージンと、左に40pxのパディングを持つ
+        assert_eq!(LayoutPoint::new(0, 16), ul.borrow().point());
+        assert_eq!(LayoutSize::new(590, 40), ul.borrow().size());
+
+        let items = markers(&view);
+        assert_eq!(2, items.len());
+        // 記号の一辺は5px、後ろの空きは8pxなので、内容の左端から13px左に置く
+        let first = items[0].borrow().fragments();
+        assert_eq!(LayoutPoint::new(27, 16), first[0].point());
+        assert_eq!(LayoutSize::new(13, 20), first[0].size());
+        assert_eq!(
+            LayoutPoint::new(27, 36),
+            items[1].borrow().fragments()[0].point()
+        );
+
+        let bullets: Vec<_> = view
+            .paint()
+            .into_iter()
+            .filter_map(|item| match item {
+                DisplayItem::Bullet {
+                    shape,
+                    layout_point,
+                    layout_size,
+                    ..
+                } => Some((shape, layout_point, layout_size)),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(
+            vec![
+                (
+                    BulletShape::Disc,
+                    LayoutPoint::new(27, 23),
+                    LayoutSize::new(5, 5)
+                ),
+                (
+                    BulletShape::Disc,
+                    LayoutPoint::new(27, 43),
+                    LayoutSize::new(5, 5)
+                ),
+            ],
+            bullets
+        );
+    }
+
+    #[test]
+    fn test_ordered_list_numbering() {
+        let view = create_layout_view(
+            "<html><body><ol start=\"3\"><li>a<li value=\"10\">b<li>c</ol></body></html>"
+                .to_string(),
+        );
+        let items = markers(&view);
+        assert_eq!(
+            vec!["3. ", "10. ", "11. "],
+            items.iter().map(text_of).collect::<Vec<_>>()
+        );
+        // 番号の右端を内容の左端に揃える
+        assert_eq!(
+            LayoutPoint::new(16, 16),
+            items[0].borrow().fragments()[0].point()
+        );
+        assert_eq!(
+            LayoutPoint::new(8, 36),
+            items[1].borrow().fragments()[0].point()
+        );
+    }
+
+    #[test]
+    fn test_nested_lists() {
+        let view = create_layout_view(
+            "<html><body><ul><li>a<ul><li>b<ul><li>c</ul></ul></ul></body></html>".to_string(),
+        );
+        let items = markers(&view);
+        let shapes: Vec<_> = items.iter().map(|m| m.borrow().marker()).collect();
+        assert_eq!(
+            vec![
+                Some(ListMarker::Bullet(BulletShape::Disc)),
+                Some(ListMarker::Bullet(BulletShape::Circle)),
+                Some(ListMarker::Bullet(BulletShape::Square)),
+            ],
+            shapes
+        );
+        // 入れ子のリストはマージンを持たず、40pxずつ字下げされる
+        let points: Vec<_> = items
+            .iter()
+            .map(|m| m.borrow().fragments()[0].point())
+            .collect();
+        assert_eq!(
+            vec![
+                LayoutPoint::new(27, 16),
+                LayoutPoint::new(67, 36),
+                LayoutPoint::new(107, 56)
+            ],
+            points
+        );
+    }
+
+    #[test]
+    fn test_inside_marker() {
+        let view = create_layout_view(
+            "<html><head><style>ol { list-style: inside lower-roman; }</style></head><body><ol><li>a</li><li>b</li></ol></body></html>"
+                .to_string(),
+        );
+        let items = markers(&view);
+        assert_eq!(
+            vec!["i. ", "ii. "],
+            items.iter().map(text_of).collect::<Vec<_>>()
+        );
+        // マーカーは行の先頭に並び、テキストはその後ろから始まる
+        assert_eq!(
+            LayoutPoint::new(40, 16),
+            items[0].borrow().fragments()[0].point()
+        );
+        let text = items[0].borrow().next_sibling().unwrap();
+        assert_eq!(
+            LayoutPoint::new(64, 16),
+            text.borrow().fragments()[0].point()
+        );
+        let second = items[1].borrow().next_sibling().unwrap();
+        assert_eq!(
+            LayoutPoint::new(72, 36),
+            second.borrow().fragments()[0].point()
+        );
+    }
+
+    #[test]
+    fn test_list_style_none() {
+        let view = create_layout_view(
+            "<html><head><style>ul { list-style-type: none; }</style></head><body><ul><li>a</li></ul></body></html>"
+                .to_string(),
+        );
+        assert!(markers(&view).is_empty());
+    }
+
+    #[test]
+    fn test_display_list_item() {
+        let view = create_layout_view(
+            "<html><head><style>div { display: list-item; list-style-type: upper-alpha; }</style></head><body><div>a</div><div>b</div></body></html>"
+                .to_string(),
+        );
+        let root = view.root().unwrap();
+        let div = root.borrow().first_child().unwrap();
+        assert_eq!(LayoutObjectKind::ListItem, div.borrow().kind());
+        // ol要素の外でも番号を数え、マーカーは包含ブロックの外にはみ出す
+        let items = markers(&view);
+        assert_eq!(
+            vec!["A. ", "B. "],
+            items.iter().map(text_of).collect::<Vec<_>>()
+        );
+        assert_eq!(
+            LayoutPoint::new(-24, 0),
+            items[0].borrow().fragments()[0].point()
+        );
+    }
+
+    #[test]
+    fn test_marker_text() {
+        let text = |list_style_type, ordinal| match ListMarker::new(list_style_type, ordinal) {
+            Some(ListMarker::Text(text)) => text,
+            marker => panic!("expected a text marker, but got {:?}", marker),
+        };
+        assert_eq!("3. ", text(ListStyleType::Decimal, 3));
+        assert_eq!("c. ", text(ListStyleType::LowerAlpha, 3));
+        assert_eq!("AB. ", text(ListStyleType::UpperAlpha, 28));
+        assert_eq!("xiv. ", text(ListStyleType::LowerRoman, 14));
+        assert_eq!("MCMXCIV. ", text(ListStyleType::UpperRoman, 1994));
+        assert_eq!("0. ", text(ListStyleType::LowerRoman, 0));
+        assert_eq!(
+            Some(ListMarker::Bullet(BulletShape::Square)),
+            ListMarker::new(ListStyleType::Square, 1)
+        );
+        assert_eq!(None, ListMarker::new(ListStyleType::None, 1));
+    }
+
+    #[test]
+    fn test_alphabetic() {
+        assert_eq!("a", to_alphabetic(1));
+        assert_eq!("z", to_alphabetic(26));
+        assert_eq!("aa", to_alphabetic(27));
+        assert_eq!("-1".to_string(), to_alphabetic(-1));
+    }
+
+    #[test]
+    fn test_bullet_spans() {
+        assert_eq!(
+            vec![(0, 0, 3), (0, 1, 3), (0, 2, 3)],
+            BulletShape::Square.spans(3)
+        );
+        // 四隅が欠けた円になる
+        assert_eq!(
+            vec![(1, 0, 3), (0, 1, 5), (0, 2, 5), (0, 3, 5), (1, 4, 3)],
+            BulletShape::Disc.spans(5)
+        );
+        // 中心は塗らない
+        let ring = BulletShape::Circle.spans(5);
+        assert!(ring.contains(&(0, 2, 1)));
+        assert!(ring.contains(&(4, 2, 1)));
+    }
+}
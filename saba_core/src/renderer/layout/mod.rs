@@ -1,3 +1,16 @@
+pub mod block_layout;
 pub mod computed_style;
+pub mod flex_layout;
+pub mod float_layout;
+pub mod inline_layout;
 pub mod layout_object;
 pub mod layout_view;
+pub mod list_marker;
+pub mod overflow;
+pub mod position_layout;
+pub mod property;
+pub mod stacking_context;
+pub mod table_layout;
+#[cfg(test)]
+pub(crate) mod test_util;
+pub mod viewport;
@@ -0,0 +1,264 @@
+//! オーバーフローとスクロール
+//! https://www.w3.org/TR/css-overflow-3/
+
+use crate::renderer::layout::computed_style::Position;
+use crate::renderer::layout::layout_object::LayoutObject;
+use crate::renderer::layout::layout_object::LayoutPoint;
+use alloc::rc::Rc;
+use core::cell::RefCell;
+
+/// ボックスと子孫が広がる範囲の右端と下端
+/// スクロールコンテナの子孫は切り取られるので、スクロールコンテナのボックスまでしか広がらない
+pub fn box_extent(node: &Rc<RefCell<LayoutObject>>) -> (i64, i64) {
+    let n = node.borrow();
+    let mut right = n.point().x() + n.size().width();
+    let mut bottom = n.point().y() + n.size().height();
+    for f in n.fragments() {
+        right = core::cmp::max(right, f.point().x() + f.size().width());
+        bottom = core::cmp::max(bottom, f.point().y() + f.size().height());
+    }
+    if n.is_scroll_container() {
+        return (right, bottom);
+    }
+    drop(n);
+
+    children_extent(node, (right, bottom))
+}
+
+/// 子孫が広がる範囲と`(right, bottom)`を合わせた範囲の右端と下端
+/// 固定位置指定のボックスはスクロールしても動かないので含めない
+fn children_extent(node: &Rc<RefCell<LayoutObject>>, (right, bottom): (i64, i64)) -> (i64, i64) {
+    let mut right = right;
+    let mut bottom = bottom;
+    let mut child = node.borrow().first_child();
+    while let Some(c) = child {
+        if c.borrow().style().position() != Position::Fixed {
+            let (r, b) = box_extent(&c);
+            right = core::cmp::max(right, r);
+            bottom = core::cmp::max(bottom, b);
+        }
+        child = c.borrow().next_sibling();
+    }
+    (right, bottom)
+}
+
+/// スクロールコンテナをスクロールできる最大の量
+/// `applied`は、すでに子孫を移動させたスクロールの量
+pub fn max_scroll_offset(node: &Rc<RefCell<LayoutObject>>, applied: LayoutPoint) -> LayoutPoint {
+    let (point, size) = node.borrow().padding_box();
+    let end_x = point.x() + size.width();
+    let end_y = point.y() + size.height();
+    let (right, bottom) = children_extent(node, (end_x - applied.x(), end_y - applied.y()));
+    LayoutPoint::new(
+        core::cmp::max(right + applied.x() - end_x, 0),
+        core::cmp::max(bottom + applied.y() - end_y, 0),
+    )
+}
+
+/// レイアウトした直後の`node`とその子孫のスクロールコンテナについて、スクロールした量だけ内容を移動させる
+/// スクロールした量は、内容の大きさに合わせて切り詰める
+pub fn apply_scroll_offsets(node: &Rc<RefCell<LayoutObject>>) {
+    if node.borrow().is_scroll_container() {
+        let max = max_scroll_offset(node, LayoutPoint::new(0, 0));
+        let offset = node.borrow().scroll_offset();
+        let offset = LayoutPoint::new(offset.x().clamp(0, max.x()), offset.y().clamp(0, max.y()));
+        node.borrow_mut().set_scroll_offset(offset);
+        translate_descendants(node, -offset.x(), -offset.y());
+    }
+
+    let mut child = node.borrow().first_child();
+    while let Some(c) = child {
+        apply_scroll_offsets(&c);
+        child = c.borrow().next_sibling();
+    }
+}
+
+/// スクロールコンテナを(dx, dy)だけスクロールし、レイアウトし直さずに内容を移動させる
+/// スクロールした量は、内容の大きさに合わせて切り詰める
+pub fn scroll_container_by(node: &Rc<RefCell<LayoutObject>>, dx: i64, dy: i64) {
+    let offset = node.borrow().scroll_offset();
+    let max = max_scroll_offset(node, offset);
+    let scrolled = LayoutPoint::new(
+        (offset.x() + dx).clamp(0, max.x()),
+        (offset.y() + dy).clamp(0, max.y()),
+    );
+    node.borrow_mut().set_scroll_offset(scrolled);
+    translate_descendants(node, offset.x() - scrolled.x(), offset.y() - scrolled.y());
+}
+
+/// 子孫を移動させる。固定位置指定のボックスはビューポートに配置するので動かさない
+pub fn translate_descendants(node: &Rc<RefCell<LayoutObject>>, dx: i64, dy: i64) {
+    if dx == 0 && dy == 0 {
+        return;
+    }
+
+    let mut child = node.borrow().first_child();
+    while let Some(c) = child {
+        if c.borrow().style().position() != Position::Fixed {
+            c.borrow_mut().translate(dx, dy);
+            translate_descendants(&c, dx, dy);
+        }
+        child = c.borrow().next_sibling();
+    }
+}
+
+/// `node`と祖先のうち、(dx, dy)の方向にまだスクロールできる最も内側のスクロールコンテナ
+pub fn find_scroll_container(
+    node: &Rc<RefCell<LayoutObject>>,
+    dx: i64,
+    dy: i64,
+) -> Option<Rc<RefCell<LayoutObject>>> {
+    let mut current = Some(node.clone());
+    while let Some(n) = current {
+        if n.borrow().is_scroll_container() {
+            let offset = n.borrow().scroll_offset();
+            let max = max_scroll_offset(&n, offset);
+            let can_scroll = |delta: i64, offset: i64, max: i64| {
+                (delta < 0 && offset > 0) || (delta > 0 && offset < max)
+            };
+            if can_scroll(dx, offset.x(), max.x()) || can_scroll(dy, offset.y(), max.y()) {
+                return Some(n);
+            }
+        }
+        current = n.borrow().parent().upgrade();
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::constants::CONTENT_AREA_HEIGHT;
+    use crate::display_item::DisplayItem;
+    use crate::renderer::layout::layout_object::children;
+    use crate::renderer::layout::layout_object::LayoutObject;
+    use crate::renderer::layout::layout_object::LayoutPoint;
+    use crate::renderer::layout::layout_object::LayoutSize;
+    use crate::renderer::layout::layout_view::LayoutView;
+    use crate::renderer::layout::test_util::create_layout_view_with_style;
+    use alloc::rc::Rc;
+    use alloc::string::ToString;
+    use core::cell::RefCell;
+
+    fn scroller(view: &LayoutView) -> Rc<RefCell<LayoutObject>> {
+        children(&view.root().expect("root should exist"))[0].clone()
+    }
+
+    const SCROLLER: &str =
+        ".s { overflow: auto; height: 40px; border: 5px solid; } .i { height: 20px; }";
+    const ITEMS: &str =
+        "<div class=\"s\"><div class=\"i\">a</div><div class=\"i\">b</div><div class=\"i\">c</div><div class=\"i\">d</div><div class=\"i\">e</div></div>";
+
+    #[test]
+    fn test_clip_display_items() {
+        let view = create_layout_view_with_style(SCROLLER, ITEMS);
+        let items = view.paint();
+        // ボーダーはクリップの外側に、内容はパディングボックスのクリップの内側に描画する
+        let push = items
+            .iter()
+            .position(|item| matches!(item, DisplayItem::PushClip { .. }))
+            .expect("clip should be pushed");
+        assert_eq!(
+            DisplayItem::PushClip {
+                layout_point: LayoutPoint::new(5, 5),
+                layout_size: LayoutSize::new(580, 40),
+            },
+            items[push]
+        );
+        assert!(items[..push]
+            .iter()
+            .all(|item| matches!(item, DisplayItem::Rect { .. })));
+        assert_eq!(Some(&DisplayItem::PopClip), items.last());
+        let texts = items[push..]
+            .iter()
+            .filter(|item| matches!(item, DisplayItem::Text { .. }))
+            .count();
+        assert_eq!(5, texts);
+    }
+
+    #[test]
+    fn test_scroll_element() {
+        let mut view = create_layout_view_with_style(SCROLLER, ITEMS);
+        let s = scroller(&view);
+
+        // 内容の高さ100pxのうち40pxが見えるので、60pxまでスクロールできる
+        assert!(view.scroll_by((10, 10), 0, 30));
+        assert_eq!(LayoutPoint::new(0, 30), s.borrow().scroll_offset());
+        assert_eq!(LayoutPoint::new(5, -25), children(&s)[0].borrow().point());
+        // ボックス自体は動かない
+        assert_eq!(LayoutPoint::new(0, 0), s.borrow().point());
+
+        assert!(view.scroll_by((10, 10), 0, 100));
+        assert_eq!(LayoutPoint::new(0, 60), s.borrow().scroll_offset());
+        assert_eq!(LayoutPoint::new(5, 25), children(&s)[4].borrow().point());
+
+        // これ以上スクロールできず、ページも画面に収まっているのでスクロールしない
+        assert!(!view.scroll_by((10, 10), 0, 20));
+        assert!(view.scroll_by((10, 10), 0, -60));
+        assert_eq!(LayoutPoint::new(5, 5), children(&s)[0].borrow().point());
+    }
+
+    #[test]
+    fn test_hit_test_is_clipped() {
+        let mut view = create_layout_view_with_style(SCROLLER, ITEMS);
+        let s = scroller(&view);
+        // 3つ目の項目はパディングボックスの外側にあり、見えない
+        assert_eq!(Some(s.clone()), view.find_node_by_position((10, 48)));
+        assert!(view.scroll_by((10, 10), 0, 40));
+        // スクロールすると、同じ位置に5つ目の項目が見える
+        let text = children(&children(&s)[4])[0].clone();
+        assert_eq!(Some(text), view.find_node_by_position((10, 30)));
+    }
+
+    #[test]
+    fn test_scroll_page() {
+        let mut body = "<div class=\"f\">fixed</div>".to_string();
+        for _ in 0..30 {
+            body.push_str("<div class=\"i\">x</div>");
+        }
+        let mut view = create_layout_view_with_style(
+            ".i { height: 20px; } .f { position: fixed; top: 0; height: 10px; background-color: red; }",
+            &body,
+        );
+        assert_eq!(
+            LayoutPoint::new(0, 600 - CONTENT_AREA_HEIGHT),
+            view.max_scroll_offset()
+        );
+
+        assert!(view.scroll_by((0, 0), 0, 100));
+        assert_eq!(LayoutPoint::new(0, 100), view.scroll_offset());
+        // 固定位置指定のボックスは、スクロールしたビューポートの上端に置かれる
+        let root = view.root().unwrap();
+        let fixed = children(&root)[0].clone();
+        assert_eq!(LayoutPoint::new(0, 100), fixed.borrow().point());
+
+        // ディスプレイアイテムはビューポートの座標になる
+        let items = view.paint();
+        let first_text = items
+            .iter()
+            .find_map(|item| match item {
+                DisplayItem::Text {
+                    text, layout_point, ..
+                } if text == "x" => Some(*layout_point),
+                _ => None,
+            })
+            .unwrap();
+        assert_eq!(LayoutPoint::new(0, -100), first_text);
+        assert!(items.iter().any(|item| matches!(
+            item,
+            DisplayItem::Rect { layout_point, .. } if *layout_point == LayoutPoint::new(0, 0)
+        )));
+
+        // ビューポートの座標で探す
+        let item = children(&root)[6].clone();
+        let text = children(&item)[0].clone();
+        assert_eq!(Some(text), view.find_node_by_position((4, 15)));
+
+        // 最後までスクロールすると止まる
+        view.scroll_to(0, 1000);
+        assert_eq!(
+            LayoutPoint::new(0, 600 - CONTENT_AREA_HEIGHT),
+            view.scroll_offset()
+        );
+        assert!(!view.scroll_by((0, 0), 0, 20));
+    }
+}
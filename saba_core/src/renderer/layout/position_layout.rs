@@ -0,0 +1,460 @@
+//! 位置指定されたボックスの配置
+//! https://www.w3.org/TR/CSS2/visuren.html#positioning-scheme
+//! https://www.w3.org/TR/css-position-3/
+
+use crate::font::collection::FontCollection;
+use crate::renderer::layout::block_layout::layout_formatting_context_root;
+use crate::renderer::layout::block_layout::translate_subtree;
+use crate::renderer::layout::computed_style::Position;
+use crate::renderer::layout::layout_object::LayoutObject;
+use crate::renderer::layout::layout_object::LayoutPoint;
+use crate::renderer::layout::layout_object::LayoutSize;
+use crate::renderer::layout::overflow::translate_descendants;
+use alloc::rc::Rc;
+use core::cell::RefCell;
+
+/// 相対位置指定のずれ
+/// leftとrightの両方が指定された場合はleftを、topとbottomの両方が指定された場合はtopを使う
+/// https://www.w3.org/TR/CSS2/visuren.html#relative-positioning
+pub fn relative_offset(node: &LayoutObject, containing_width: i64) -> (i64, i64) {
+    let inset = node.style().inset();
+    let font_size = node.font_size_px();
+    let dx = match (
+        inset.left.to_px(containing_width, font_size),
+        inset.right.to_px(containing_width, font_size),
+    ) {
+        (Some(left), _) => left,
+        (None, Some(right)) => -right,
+        (None, None) => 0,
+    };
+    // 包含ブロックの高さに依存するパーセンテージは0として扱う
+    let dy = match (
+        inset.top.to_px(0, font_size),
+        inset.bottom.to_px(0, font_size),
+    ) {
+        (Some(top), _) => top,
+        (None, Some(bottom)) => -bottom,
+        (None, None) => 0,
+    };
+    (dx, dy)
+}
+
+/// `position: relative`のボックスを、通常フローで配置した位置からずらす
+pub fn apply_relative_offset(node: &Rc<RefCell<LayoutObject>>, containing_width: i64) {
+    let (dx, dy) = {
+        let n = node.borrow();
+        if n.style().position() != Position::Relative {
+            return;
+        }
+        relative_offset(&n, containing_width)
+    };
+    translate_subtree(node, dx, dy);
+}
+
+/// 通常フローのレイアウトが終わった後に、`node`の子孫にある絶対位置指定と固定位置指定のボックスを配置する
+/// `viewport`は固定位置指定の包含ブロックになる
+/// 位置指定された祖先を持たない絶対位置指定のボックスは、原点に置いたビューポートの大きさの初期包含ブロックに配置する
+pub fn layout_positioned_boxes(
+    node: &Rc<RefCell<LayoutObject>>,
+    viewport_point: LayoutPoint,
+    viewport_size: LayoutSize,
+    fonts: &FontCollection,
+) {
+    let mut child = node.borrow().first_child();
+    while let Some(c) = child {
+        let position = c.borrow().style().position();
+        match position {
+            Position::Absolute => {
+                let (point, size) =
+                    containing_block(&c).unwrap_or((LayoutPoint::new(0, 0), viewport_size));
+                layout_absolute_box(&c, point, size, fonts);
+            }
+            Position::Fixed => {
+                // 静的位置もスクロールしたビューポートに合わせて動かす
+                translate_subtree(&c, viewport_point.x(), viewport_point.y());
+                layout_absolute_box(&c, viewport_point, viewport_size, fonts);
+            }
+            Position::Static | Position::Relative | Position::Sticky => {}
+        }
+        // 子孫は、配置が決まった祖先をもとに配置する
+        layout_positioned_boxes(&c, viewport_point, viewport_size, fonts);
+        child = c.borrow().next_sibling();
+    }
+}
+
+/// ページをスクロールしたときに、固定位置指定のボックスをビューポートと一緒に移動させる
+pub fn translate_fixed_boxes(node: &Rc<RefCell<LayoutObject>>, dx: i64, dy: i64) {
+    let mut child = node.borrow().first_child();
+    while let Some(c) = child {
+        if c.borrow().style().position() == Position::Fixed {
+            translate_subtree(&c, dx, dy);
+        } else {
+            translate_fixed_boxes(&c, dx, dy);
+        }
+        child = c.borrow().next_sibling();
+    }
+}
+
+/// レイアウトしてスクロールした量だけ内容を移動させた後に、`node`の子孫にあるスティッキー位置指定のボックスをずらす
+/// `viewport`はスティッキー位置指定のボックスが張り付く領域になる
+pub fn apply_sticky_offsets(
+    node: &Rc<RefCell<LayoutObject>>,
+    viewport_point: LayoutPoint,
+    viewport_size: LayoutSize,
+) {
+    let mut child = node.borrow().first_child();
+    while let Some(c) = child {
+        if c.borrow().style().position() == Position::Sticky {
+            apply_sticky_offset(&c, viewport_point, viewport_size);
+        }
+        apply_sticky_offsets(&c, viewport_point, viewport_size);
+        child = c.borrow().next_sibling();
+    }
+}
+
+/// スクロールしたときに、スティッキー位置指定のボックスを通常フローの位置に戻してから、ずらし直す
+pub fn update_sticky_offsets(
+    node: &Rc<RefCell<LayoutObject>>,
+    viewport_point: LayoutPoint,
+    viewport_size: LayoutSize,
+) {
+    reset_sticky_offsets(node);
+    apply_sticky_offsets(node, viewport_point, viewport_size);
+}
+
+fn reset_sticky_offsets(node: &Rc<RefCell<LayoutObject>>) {
+    let mut child = node.borrow().first_child();
+    while let Some(c) = child {
+        let offset = c.borrow().sticky_offset();
+        translate_sticky_box(&c, -offset.x(), -offset.y());
+        reset_sticky_offsets(&c);
+        child = c.borrow().next_sibling();
+    }
+}
+
+/// スティッキー位置指定のボックスを子孫ごと移動させ、ずらした量を覚えておく
+/// 固定位置指定の子孫はビューポートに配置するので動かさない
+fn translate_sticky_box(node: &Rc<RefCell<LayoutObject>>, dx: i64, dy: i64) {
+    if dx == 0 && dy == 0 {
+        return;
+    }
+    let offset = node.borrow().sticky_offset();
+    node.borrow_mut()
+        .set_sticky_offset(LayoutPoint::new(offset.x() + dx, offset.y() + dy));
+    node.borrow_mut().translate(dx, dy);
+    translate_descendants(node, dx, dy);
+}
+
+/// 最も近い位置指定された祖先のパディングボックス
+fn containing_block(node: &Rc<RefCell<LayoutObject>>) -> Option<(LayoutPoint, LayoutSize)> {
+    let mut parent = node.borrow().parent().upgrade();
+    while let Some(p) = parent {
+        let n = p.borrow();
+        if n.is_positioned() {
+            return Some(n.padding_box());
+        }
+        parent = n.parent().upgrade();
+    }
+    None
+}
+
+/// 絶対位置指定のボックスを、包含ブロックからのtop、right、bottom、leftの距離で配置する
+/// 向かい合う辺の両方がautoの場合は、通常フローで置かれるはずだった静的位置に置く
+/// https://www.w3.org/TR/CSS2/visudet.html#abs-non-replaced-width
+fn layout_absolute_box(
+    node: &Rc<RefCell<LayoutObject>>,
+    containing_point: LayoutPoint,
+    containing_size: LayoutSize,
+    fonts: &FontCollection,
+) {
+    let (static_point, inset, font_size, width_is_auto, height_is_auto) = {
+        let n = node.borrow();
+        let style = n.style();
+        (
+            n.point(),
+            style.inset(),
+            n.font_size_px(),
+            style.width().is_auto(),
+            style.height().is_auto(),
+        )
+    };
+    let containing_width = containing_size.width();
+    let containing_height = containing_size.height();
+    let left = inset.left.to_px(containing_width, font_size);
+    let right = inset.right.to_px(containing_width, font_size);
+    let top = inset.top.to_px(containing_height, font_size);
+    let bottom = inset.bottom.to_px(containing_height, font_size);
+
+    // widthがautoの場合は内容に合わせて縮める
+    node.borrow_mut().set_override_size(None, None);
+    layout_formatting_context_root(node, LayoutPoint::new(0, 0), containing_width, fonts);
+
+    // 向かい合う辺が両方とも指定されている場合は、その間を埋める大きさにする
+    let (override_width, override_height) = {
+        let n = node.borrow();
+        let margin = n.margin();
+        let edges = n.border().vertical() + n.padding().vertical();
+        let width = match (left, right) {
+            (Some(l), Some(r)) if width_is_auto => {
+                Some(containing_width - l - r - margin.horizontal())
+            }
+            _ => None,
+        };
+        let height = match (top, bottom) {
+            (Some(t), Some(b)) if height_is_auto => Some(core::cmp::max(
+                containing_height - t - b - margin.vertical() - edges,
+                0,
+            )),
+            _ => None,
+        };
+        (width, height)
+    };
+    if override_width.is_some() || override_height.is_some() {
+        node.borrow_mut()
+            .set_override_size(override_width, override_height);
+        layout_formatting_context_root(node, LayoutPoint::new(0, 0), containing_width, fonts);
+    }
+
+    let (point, size, margin) = {
+        let n = node.borrow();
+        (n.point(), n.size(), n.margin())
+    };
+    let x = match (left, right) {
+        (Some(l), _) => containing_point.x() + l,
+        (None, Some(r)) => {
+            containing_point.x() + containing_width - r - size.width() - margin.horizontal()
+        }
+        (None, None) => static_point.x(),
+    };
+    let y = match (top, bottom) {
+        (Some(t), _) => containing_point.y() + t,
+        (None, Some(b)) => {
+            containing_point.y() + containing_height - b - size.height() - margin.vertical()
+        }
+        (None, None) => static_point.y(),
+    };
+    translate_subtree(
+        node,
+        x + margin.left - point.x(),
+        y + margin.top - point.y(),
+    );
+}
+
+/// 通常フローの位置にあるスティッキー位置指定のボックスを、ビューポートの端からtopなどの距離より内側に収まるようにずらす
+/// ずらす範囲は親のコンテンツボックスの中に限られる
+/// https://www.w3.org/TR/css-position-3/#stickypos-insets
+fn apply_sticky_offset(
+    node: &Rc<RefCell<LayoutObject>>,
+    viewport_point: LayoutPoint,
+    viewport_size: LayoutSize,
+) {
+    let parent = match node.borrow().parent().upgrade() {
+        Some(parent) => parent,
+        None => return,
+    };
+    let (content_left, content_top, content_right, content_bottom) = {
+        let p = parent.borrow();
+        let left = p.point().x() + p.border().left + p.padding().left;
+        let top = p.point().y() + p.border().top + p.padding().top;
+        (
+            left,
+            top,
+            p.point().x() + p.size().width() - p.border().right - p.padding().right,
+            p.point().y() + p.size().height() - p.border().bottom - p.padding().bottom,
+        )
+    };
+
+    let (dx, dy) = {
+        let n = node.borrow();
+        let inset = n.style().inset();
+        let font_size = n.font_size_px();
+        let (point, size, margin) = (n.point(), n.size(), n.margin());
+
+        let dy = sticky_shift(
+            (point.y(), point.y() + size.height()),
+            (
+                viewport_point.y(),
+                viewport_point.y() + viewport_size.height(),
+            ),
+            (
+                inset.top.to_px(viewport_size.height(), font_size),
+                inset.bottom.to_px(viewport_size.height(), font_size),
+            ),
+            (
+                content_top - (point.y() - margin.top),
+                content_bottom - (point.y() + size.height() + margin.bottom),
+            ),
+        );
+        let dx = sticky_shift(
+            (point.x(), point.x() + size.width()),
+            (
+                viewport_point.x(),
+                viewport_point.x() + viewport_size.width(),
+            ),
+            (
+                inset.left.to_px(viewport_size.width(), font_size),
+                inset.right.to_px(viewport_size.width(), font_size),
+            ),
+            (
+                content_left - (point.x() - margin.left),
+                content_right - (point.x() + size.width() + margin.right),
+            ),
+        );
+        (dx, dy)
+    };
+    node.borrow_mut().set_sticky_offset(LayoutPoint::new(0, 0));
+    translate_sticky_box(node, dx, dy);
+}
+
+/// 1つの軸について、スティッキー位置指定でずらす量を求める
+/// `min`と`max`は、親のコンテンツボックスからはみ出さずにずらせる量の下限と上限
+fn sticky_shift(
+    (start, end): (i64, i64),
+    (view_start, view_end): (i64, i64),
+    (inset_start, inset_end): (Option<i64>, Option<i64>),
+    (min, max): (i64, i64),
+) -> i64 {
+    let mut shift = 0;
+    if let Some(inset) = inset_end {
+        if end > view_end - inset {
+            shift = view_end - inset - end;
+        }
+    }
+    // 両方が指定されている場合は、始まりの辺を優先する
+    if let Some(inset) = inset_start {
+        if start + shift < view_start + inset {
+            shift = view_start + inset - start;
+        }
+    }
+    if shift > 0 {
+        core::cmp::min(shift, core::cmp::max(max, 0))
+    } else {
+        core::cmp::max(shift, core::cmp::min(min, 0))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::constants::CONTENT_AREA_HEIGHT;
+    use crate::renderer::layout::layout_object::children;
+    use crate::renderer::layout::layout_object::LayoutPoint;
+    use crate::renderer::layout::layout_object::LayoutSize;
+    use crate::renderer::layout::test_util::body_children;
+    use crate::renderer::layout::test_util::create_layout_view_with_style;
+    use alloc::vec;
+    use alloc::vec::Vec;
+
+    #[test]
+    fn test_relative() {
+        let view = create_layout_view_with_style(
+            ".r { position: relative; left: 10px; top: 5px; } .b { position: relative; right: 4px; bottom: 2px; }",
+            "<div class=\"r\">a</div><div>b <span class=\"b\">c</span></div>",
+        );
+        let nodes = body_children(&view);
+        assert_eq!(LayoutPoint::new(10, 5), nodes[0].borrow().point());
+        let text = children(&nodes[0])[0].clone();
+        assert_eq!(
+            LayoutPoint::new(10, 5),
+            text.borrow().fragments()[0].point()
+        );
+        // 後に続くボックスは、ずらす前の位置をもとに配置される
+        assert_eq!(LayoutPoint::new(0, 20), nodes[1].borrow().point());
+        // インライン要素は断片ごとずれる
+        let span = children(&nodes[1])[1].clone();
+        assert_eq!(
+            vec![LayoutPoint::new(12, 18)],
+            span.borrow()
+                .fragments()
+                .iter()
+                .map(|f| f.point())
+                .collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn test_absolute_in_positioned_ancestor() {
+        let view = create_layout_view_with_style(
+            ".c { position: relative; margin-left: 50px; height: 100px; border: 2px solid; } .a { position: absolute; right: 10px; bottom: 10px; width: 30px; height: 20px; }",
+            "<div class=\"c\"><div class=\"a\"></div></div><div>x</div>",
+        );
+        let nodes = body_children(&view);
+        // 包含ブロックは祖先のパディングボックス(52, 2)から(588, 102)
+        let a = children(&nodes[0])[0].clone();
+        assert_eq!(LayoutPoint::new(548, 72), a.borrow().point());
+        assert_eq!(LayoutSize::new(30, 20), a.borrow().size());
+        // 絶対位置指定のボックスは親の高さに影響しない
+        assert_eq!(LayoutSize::new(540, 104), nodes[0].borrow().size());
+        assert_eq!(LayoutPoint::new(0, 104), nodes[1].borrow().point());
+    }
+
+    #[test]
+    fn test_absolute_stretches_between_insets() {
+        let view = create_layout_view_with_style(
+            ".a { position: absolute; left: 10px; right: 20px; top: 30px; bottom: 40px; padding: 5px; }",
+            "<div class=\"a\">abc</div>",
+        );
+        let a = body_children(&view)[0].clone();
+        assert_eq!(LayoutPoint::new(10, 30), a.borrow().point());
+        assert_eq!(
+            LayoutSize::new(560, CONTENT_AREA_HEIGHT - 70),
+            a.borrow().size()
+        );
+        let text = children(&a)[0].clone();
+        assert_eq!(
+            LayoutPoint::new(15, 35),
+            text.borrow().fragments()[0].point()
+        );
+    }
+
+    #[test]
+    fn test_absolute_static_position() {
+        let view = create_layout_view_with_style(
+            ".a { position: absolute; }",
+            "<div>x</div><div class=\"a\">yy</div><div>z</div>",
+        );
+        let nodes = body_children(&view);
+        // 通常フローで置かれるはずだった位置に、内容の幅で置かれる
+        assert_eq!(LayoutPoint::new(0, 20), nodes[1].borrow().point());
+        assert_eq!(LayoutSize::new(16, 20), nodes[1].borrow().size());
+        assert_eq!(LayoutPoint::new(0, 20), nodes[2].borrow().point());
+        // bodyの高さには含まれない
+        let root = view.root().unwrap();
+        assert_eq!(LayoutSize::new(590, 40), root.borrow().size());
+    }
+
+    #[test]
+    fn test_fixed() {
+        let view = create_layout_view_with_style(
+            ".c { position: relative; margin: 50px; height: 100px; } .f { position: fixed; left: 0; bottom: 0; width: 100px; height: 10px; }",
+            "<div class=\"c\"><div class=\"f\"></div></div>",
+        );
+        let c = body_children(&view)[0].clone();
+        let f = children(&c)[0].clone();
+        // 位置指定された祖先ではなく、ビューポートに配置する
+        assert_eq!(
+            LayoutPoint::new(0, CONTENT_AREA_HEIGHT - 10),
+            f.borrow().point()
+        );
+    }
+
+    #[test]
+    fn test_sticky() {
+        let view = create_layout_view_with_style(
+            ".s { position: sticky; top: 10px; height: 20px; } .tall { height: 100px; } .far { position: sticky; top: 200px; height: 20px; } .short { height: 25px; }",
+            "<div><div class=\"s\"></div><div class=\"tall\"></div></div><div class=\"short\"><div class=\"far\"></div></div>",
+        );
+        let nodes = body_children(&view);
+        // ビューポートの上端から10pxの位置まで下がる
+        let s = children(&nodes[0])[0].clone();
+        assert_eq!(LayoutPoint::new(0, 10), s.borrow().point());
+        // 後に続くボックスは、ずらす前の位置をもとに配置される
+        assert_eq!(
+            LayoutPoint::new(0, 20),
+            children(&nodes[0])[1].borrow().point()
+        );
+        // 親のコンテンツボックスからははみ出さない
+        assert_eq!(LayoutPoint::new(0, 120), nodes[1].borrow().point());
+        let far = children(&nodes[1])[0].clone();
+        assert_eq!(LayoutPoint::new(0, 125), far.borrow().point());
+    }
+}
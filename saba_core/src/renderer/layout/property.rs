@@ -0,0 +1,236 @@
+//! 計算値を持つCSSのプロパティの一覧と、値が継承されるかどうか
+//! https://www.w3.org/TR/css-cascade-4/#inheriting
+
+use alloc::vec;
+use alloc::vec::Vec;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Property {
+    BackgroundColor,
+    Color,
+    Display,
+    FontSize,
+    TextDecoration,
+    FontWeight,
+    FontStyle,
+    FontFamily,
+    LineHeight,
+    TextAlign,
+    WhiteSpace,
+    Height,
+    Width,
+    Margin,
+    Padding,
+    BorderWidth,
+    BorderStyle,
+    BorderColor,
+    Overflow,
+    Float,
+    Clear,
+    Position,
+    Inset,
+    ZIndex,
+    FlexDirection,
+    FlexWrap,
+    JustifyContent,
+    AlignItems,
+    FlexGrow,
+    FlexShrink,
+    FlexBasis,
+    RowGap,
+    ColumnGap,
+    BorderCollapse,
+    BorderSpacing,
+    ListStyleType,
+    ListStylePosition,
+}
+
+impl Property {
+    pub const ALL: [Property; 37] = [
+        Property::BackgroundColor,
+        Property::Color,
+        Property::Display,
+        Property::FontSize,
+        Property::TextDecoration,
+        Property::FontWeight,
+        Property::FontStyle,
+        Property::FontFamily,
+        Property::LineHeight,
+        Property::TextAlign,
+        Property::WhiteSpace,
+        Property::Height,
+        Property::Width,
+        Property::Margin,
+        Property::Padding,
+        Property::BorderWidth,
+        Property::BorderStyle,
+        Property::BorderColor,
+        Property::Overflow,
+        Property::Float,
+        Property::Clear,
+        Property::Position,
+        Property::Inset,
+        Property::ZIndex,
+        Property::FlexDirection,
+        Property::FlexWrap,
+        Property::JustifyContent,
+        Property::AlignItems,
+        Property::FlexGrow,
+        Property::FlexShrink,
+        Property::FlexBasis,
+        Property::RowGap,
+        Property::ColumnGap,
+        Property::BorderCollapse,
+        Property::BorderSpacing,
+        Property::ListStyleType,
+        Property::ListStylePosition,
+    ];
+
+    /// 値を指定されていない場合に、親要素の計算値を使うプロパティ
+    /// それ以外のプロパティは初期値になる
+    pub fn inherited(&self) -> bool {
+        match self {
+            Property::Color
+            | Property::FontSize
+            | Property::FontWeight
+            | Property::FontStyle
+            | Property::FontFamily
+            | Property::LineHeight
+            | Property::TextAlign
+            | Property::WhiteSpace
+            | Property::BorderCollapse
+            | Property::BorderSpacing
+            | Property::ListStyleType
+            | Property::ListStylePosition => true,
+            // 子孫のテキストにも線を引くが、継承ではなく描画するときに伝える
+            Property::TextDecoration
+            | Property::BackgroundColor
+            | Property::Display
+            | Property::Height
+            | Property::Width
+            | Property::Margin
+            | Property::Padding
+            | Property::BorderWidth
+            | Property::BorderStyle
+            | Property::BorderColor
+            | Property::Overflow
+            | Property::Float
+            | Property::Clear
+            | Property::Position
+            | Property::Inset
+            | Property::ZIndex
+            | Property::FlexDirection
+            | Property::FlexWrap
+            | Property::JustifyContent
+            | Property::AlignItems
+            | Property::FlexGrow
+            | Property::FlexShrink
+            | Property::FlexBasis
+            | Property::RowGap
+            | Property::ColumnGap => false,
+        }
+    }
+
+    /// 宣言のプロパティ名から、値を保持するプロパティを返す
+    /// `border`のような一括指定は複数のプロパティに、`margin-top`のような辺ごとの指定はその辺を含むプロパティになる
+    pub fn longhands(name: &str) -> Vec<Property> {
+        match name {
+            "background-color" => vec![Property::BackgroundColor],
+            "color" => vec![Property::Color],
+            "display" => vec![Property::Display],
+            "font-size" => vec![Property::FontSize],
+            "text-decoration" => vec![Property::TextDecoration],
+            "font-weight" => vec![Property::FontWeight],
+            "font-style" => vec![Property::FontStyle],
+            "font-family" => vec![Property::FontFamily],
+            "line-height" => vec![Property::LineHeight],
+            "text-align" => vec![Property::TextAlign],
+            "white-space" => vec![Property::WhiteSpace],
+            "height" => vec![Property::Height],
+            "width" => vec![Property::Width],
+            "margin" | "margin-top" | "margin-right" | "margin-bottom" | "margin-left" => {
+                vec![Property::Margin]
+            }
+            "padding" | "padding-top" | "padding-right" | "padding-bottom" | "padding-left" => {
+                vec![Property::Padding]
+            }
+            "border-width"
+            | "border-top-width"
+            | "border-right-width"
+            | "border-bottom-width"
+            | "border-left-width" => vec![Property::BorderWidth],
+            "border-style" => vec![Property::BorderStyle],
+            "border-color" => vec![Property::BorderColor],
+            "border" => vec![
+                Property::BorderWidth,
+                Property::BorderStyle,
+                Property::BorderColor,
+            ],
+            "overflow" => vec![Property::Overflow],
+            "float" => vec![Property::Float],
+            "clear" => vec![Property::Clear],
+            "position" => vec![Property::Position],
+            "inset" | "top" | "right" | "bottom" | "left" => vec![Property::Inset],
+            "z-index" => vec![Property::ZIndex],
+            "flex-direction" => vec![Property::FlexDirection],
+            "flex-wrap" => vec![Property::FlexWrap],
+            "flex-flow" => vec![Property::FlexDirection, Property::FlexWrap],
+            "justify-content" => vec![Property::JustifyContent],
+            "align-items" => vec![Property::AlignItems],
+            "flex-grow" => vec![Property::FlexGrow],
+            "flex-shrink" => vec![Property::FlexShrink],
+            "flex-basis" => vec![Property::FlexBasis],
+            "flex" => vec![
+                Property::FlexGrow,
+                Property::FlexShrink,
+                Property::FlexBasis,
+            ],
+            "row-gap" => vec![Property::RowGap],
+            "column-gap" => vec![Property::ColumnGap],
+            "gap" => vec![Property::RowGap, Property::ColumnGap],
+            "border-collapse" => vec![Property::BorderCollapse],
+            "border-spacing" => vec![Property::BorderSpacing],
+            "list-style-type" => vec![Property::ListStyleType],
+            "list-style-position" => vec![Property::ListStylePosition],
+            "list-style" => vec![Property::ListStyleType, Property::ListStylePosition],
+            _ => Vec::new(),
+        }
+    }
+}
+
+/// `margin-top`のような辺ごとのプロパティ名から、辺の名前を取り出す
+pub fn side_of(name: &str) -> Option<&str> {
+    let side = name
+        .trim_start_matches("margin-")
+        .trim_start_matches("padding-")
+        .trim_start_matches("border-")
+        .trim_end_matches("-width");
+    match side {
+        "top" | "right" | "bottom" | "left" => Some(side),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_longhands() {
+        assert_eq!(vec![Property::Margin], Property::longhands("margin-left"));
+        assert_eq!(3, Property::longhands("border").len());
+        assert_eq!(
+            vec![Property::RowGap, Property::ColumnGap],
+            Property::longhands("gap")
+        );
+        assert!(Property::longhands("unknown").is_empty());
+    }
+
+    #[test]
+    fn test_side_of() {
+        assert_eq!(Some("top"), side_of("margin-top"));
+        assert_eq!(Some("left"), side_of("border-left-width"));
+        assert_eq!(None, side_of("border-width"));
+        assert_eq!(None, side_of("margin"));
+    }
+}
@@ -0,0 +1,342 @@
+//! 重ね合わせコンテキストに従った描画の順序
+//! https://www.w3.org/TR/CSS2/zindex.html
+
+use crate::display_item::DisplayItem;
+use crate::renderer::layout::computed_style::Position;
+use crate::renderer::layout::layout_object::children;
+use crate::renderer::layout::layout_object::LayoutObject;
+use crate::renderer::layout::layout_object::LayoutPoint;
+use crate::renderer::layout::layout_object::LayoutSize;
+use alloc::rc::Rc;
+use alloc::vec::Vec;
+use core::cell::RefCell;
+
+/// 重ね合わせコンテキストの中で、通常フローとは別の順序で描画する位置指定されたボックス
+struct Layer {
+    node: Rc<RefCell<LayoutObject>>,
+    /// コンテキストのルートとの間にあるスクロールコンテナのパディングボックス
+    clips: Vec<(LayoutPoint, LayoutSize)>,
+}
+
+/// 重ね合わせコンテキストを作る`root`とその子孫を、手前に重なるものほど後になるように描画する
+/// 1. ルートの背景とボーダー
+/// 2. z-indexが負の子のコンテキスト
+/// 3. 位置指定されていない子孫(木の順序)
+/// 4. z-indexがautoか0の位置指定された子孫(木の順序)
+/// 5. z-indexが正の子のコンテキスト
+pub fn paint_stacking_context(root: &Rc<RefCell<LayoutObject>>, items: &mut Vec<DisplayItem>) {
+    items.extend(root.borrow_mut().paint());
+
+    let layers = sorted_layers(root);
+    paint_clipped(root, items, |items| {
+        for layer in layers.iter().filter(|l| l.node.borrow().z_index() < 0) {
+            paint_layer(layer, items);
+        }
+        paint_in_flow(root, items);
+        for layer in layers.iter().filter(|l| l.node.borrow().z_index() >= 0) {
+            paint_layer(layer, items);
+        }
+    });
+}
+
+/// `root`の重ね合わせコンテキストに属する位置指定された子孫を、z-indexの順に並べる
+fn sorted_layers(root: &Rc<RefCell<LayoutObject>>) -> Vec<Layer> {
+    let mut layers = Vec::new();
+    collect_layers(root, &mut Vec::new(), &mut layers);
+    // 同じz-indexのものは木の順序を保つ
+    layers.sort_by_key(|layer| layer.node.borrow().z_index());
+    layers
+}
+
+/// `root`とその子孫のうち、`position`にあって最も手前に描画されるボックスを返す
+/// `paint_stacking_context`と逆の順序で探す
+pub fn hit_test_stacking_context(
+    root: &Rc<RefCell<LayoutObject>>,
+    position: (i64, i64),
+) -> Option<Rc<RefCell<LayoutObject>>> {
+    if !is_clipped_out(root, position) {
+        let layers = sorted_layers(root);
+        let (negative, others): (Vec<&Layer>, Vec<&Layer>) =
+            layers.iter().partition(|l| l.node.borrow().z_index() < 0);
+        let found = others
+            .iter()
+            .rev()
+            .find_map(|layer| hit_test_layer(layer, position))
+            .or_else(|| hit_test_in_flow(root, position))
+            .or_else(|| {
+                negative
+                    .iter()
+                    .rev()
+                    .find_map(|layer| hit_test_layer(layer, position))
+            });
+        if found.is_some() {
+            return found;
+        }
+    }
+    hit_test_box(root, position)
+}
+
+fn hit_test_layer(layer: &Layer, position: (i64, i64)) -> Option<Rc<RefCell<LayoutObject>>> {
+    // 祖先のスクロールコンテナで切り取られた部分は見えない
+    if layer
+        .clips
+        .iter()
+        .any(|(point, size)| !padding_box_contains(*point, *size, position))
+    {
+        return None;
+    }
+
+    let node = &layer.node;
+    if node.borrow().establishes_stacking_context() {
+        return hit_test_stacking_context(node, position);
+    }
+    if !is_clipped_out(node, position) {
+        if let Some(found) = hit_test_in_flow(node, position) {
+            return Some(found);
+        }
+    }
+    hit_test_box(node, position)
+}
+
+/// 位置指定されていない子孫を、後に描画されるものから探す
+fn hit_test_in_flow(
+    node: &Rc<RefCell<LayoutObject>>,
+    position: (i64, i64),
+) -> Option<Rc<RefCell<LayoutObject>>> {
+    for c in children(node).iter().rev() {
+        if c.borrow().is_positioned() {
+            continue;
+        }
+        if !is_clipped_out(c, position) {
+            if let Some(found) = hit_test_in_flow(c, position) {
+                return Some(found);
+            }
+        }
+        if let Some(found) = hit_test_box(c, position) {
+            return Some(found);
+        }
+    }
+    None
+}
+
+fn hit_test_box(
+    node: &Rc<RefCell<LayoutObject>>,
+    position: (i64, i64),
+) -> Option<Rc<RefCell<LayoutObject>>> {
+    if node.borrow().contains(position.0, position.1) {
+        Some(node.clone())
+    } else {
+        None
+    }
+}
+
+/// スクロールコンテナのパディングボックスの外側にある子孫は、切り取られて見えない
+fn is_clipped_out(node: &Rc<RefCell<LayoutObject>>, position: (i64, i64)) -> bool {
+    let n = node.borrow();
+    if !n.is_scroll_container() {
+        return false;
+    }
+    let (point, size) = n.padding_box();
+    !padding_box_contains(point, size, position)
+}
+
+fn padding_box_contains(point: LayoutPoint, size: LayoutSize, position: (i64, i64)) -> bool {
+    point.x() <= position.0
+        && position.0 <= point.x() + size.width()
+        && point.y() <= position.1
+        && position.1 <= point.y() + size.height()
+}
+
+/// 位置指定されたボックスを、祖先のスクロールコンテナで切り取って描画する
+/// 重ね合わせコンテキストを作らない場合、位置指定された子孫は親のコンテキストで描画される
+fn paint_layer(layer: &Layer, items: &mut Vec<DisplayItem>) {
+    for (point, size) in &layer.clips {
+        items.push(DisplayItem::PushClip {
+            layout_point: *point,
+            layout_size: *size,
+        });
+    }
+
+    let node = &layer.node;
+    if node.borrow().establishes_stacking_context() {
+        paint_stacking_context(node, items);
+    } else {
+        items.extend(node.borrow_mut().paint());
+        paint_clipped(node, items, |items| paint_in_flow(node, items));
+    }
+
+    for _ in &layer.clips {
+        items.push(DisplayItem::PopClip);
+    }
+}
+
+/// 位置指定されていない子孫を木の順序で描画する
+fn paint_in_flow(node: &Rc<RefCell<LayoutObject>>, items: &mut Vec<DisplayItem>) {
+    let mut child = node.borrow().first_child();
+    while let Some(c) = child {
+        if !c.borrow().is_positioned() {
+            items.extend(c.borrow_mut().paint());
+            paint_clipped(&c, items, |items| paint_in_flow(&c, items));
+        }
+        child = c.borrow().next_sibling();
+    }
+}
+
+/// スクロールコンテナの場合、`paint`で描画する子孫をパディングボックスで切り取る
+fn paint_clipped(
+    node: &Rc<RefCell<LayoutObject>>,
+    items: &mut Vec<DisplayItem>,
+    paint: impl FnOnce(&mut Vec<DisplayItem>),
+) {
+    let clip = {
+        let n = node.borrow();
+        if n.is_scroll_container() {
+            Some(n.padding_box())
+        } else {
+            None
+        }
+    };
+    match clip {
+        Some((point, size)) => {
+            items.push(DisplayItem::PushClip {
+                layout_point: point,
+                layout_size: size,
+            });
+            paint(items);
+            items.push(DisplayItem::PopClip);
+        }
+        None => paint(items),
+    }
+}
+
+/// 重ね合わせコンテキストに属する、位置指定された子孫を木の順序で集める
+/// 新しい重ね合わせコンテキストを作る子孫の中身は、その子孫のコンテキストに属する
+/// `clips`には、たどってきたスクロールコンテナのパディングボックスが入っている
+fn collect_layers(
+    node: &Rc<RefCell<LayoutObject>>,
+    clips: &mut Vec<(LayoutPoint, LayoutSize)>,
+    layers: &mut Vec<Layer>,
+) {
+    let mut child = node.borrow().first_child();
+    while let Some(c) = child {
+        let (positioned, fixed, scroll_container) = {
+            let n = c.borrow();
+            (
+                n.is_positioned(),
+                n.style().position() == Position::Fixed,
+                n.is_scroll_container(),
+            )
+        };
+        if positioned {
+            // 固定位置指定のボックスは、スクロールコンテナの中にあっても切り取らない
+            layers.push(Layer {
+                node: c.clone(),
+                clips: if fixed { Vec::new() } else { clips.clone() },
+            });
+        }
+        if !c.borrow().establishes_stacking_context() {
+            if scroll_container {
+                clips.push(c.borrow().padding_box());
+            }
+            collect_layers(&c, clips, layers);
+            if scroll_container {
+                clips.pop();
+            }
+        }
+        child = c.borrow().next_sibling();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::display_item::DisplayItem;
+    use crate::renderer::layout::layout_view::LayoutView;
+    use crate::renderer::layout::test_util::create_layout_view_with_style;
+    use alloc::vec;
+    use alloc::vec::Vec;
+
+    /// 描画される背景の色を順に返す
+    fn background_colors(view: &LayoutView) -> Vec<u32> {
+        view.paint()
+            .iter()
+            .filter_map(|item| match item {
+                DisplayItem::Rect { style, .. } => Some(style.background_color().code_u32()),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_in_flow_order() {
+        let view = create_layout_view_with_style(
+            "div { height: 10px; } .a { background-color: #000001; } .b { background-color: #000002; } .c { background-color: #000003; }",
+            "<div class=\"a\"><div class=\"b\"></div></div><div class=\"c\"></div>",
+        );
+        // 位置指定されたボックスがなければ木の順序になる
+        assert_eq!(vec![1, 2, 3], background_colors(&view));
+    }
+
+    #[test]
+    fn test_positioned_boxes_are_painted_after_in_flow_boxes() {
+        let view = create_layout_view_with_style(
+            "div { height: 10px; } .a { position: relative; background-color: #000001; } .b { background-color: #000002; } .c { position: absolute; z-index: -1; background-color: #000003; width: 10px; }",
+            "<div class=\"a\"></div><div class=\"b\"><div class=\"c\"></div></div>",
+        );
+        // z-indexが負のボックス、通常フローのボックス、z-indexがautoのボックスの順
+        assert_eq!(vec![3, 2, 1], background_colors(&view));
+    }
+
+    #[test]
+    fn test_z_index_order() {
+        let view = create_layout_view_with_style(
+            "div { height: 10px; width: 10px; position: absolute; } .a { z-index: 2; background-color: #000001; } .b { z-index: 1; background-color: #000002; } .c { z-index: 100; background-color: #000003; } .d { background-color: #000004; } .e { z-index: 0; background-color: #000005; }",
+            "<div class=\"a\"></div><div class=\"b\"><div class=\"c\"></div></div><div class=\"d\"></div><div class=\"e\"></div>",
+        );
+        // cのz-indexはbの重ね合わせコンテキストの中でだけ比べられ、aより手前にはならない
+        // z-indexがautoのdと0のeは木の順序になる
+        assert_eq!(vec![4, 5, 2, 3, 1], background_colors(&view));
+    }
+
+    /// `position`で見つかるボックスの背景の色を返す
+    fn hit_color(view: &LayoutView, position: (i64, i64)) -> u32 {
+        view.find_node_by_position(position)
+            .expect("a box should be hit")
+            .borrow()
+            .style()
+            .background_color()
+            .code_u32()
+    }
+
+    #[test]
+    fn test_hit_test_in_reverse_paint_order() {
+        let view = create_layout_view_with_style(
+            "div { height: 10px; width: 10px; position: absolute; top: 0; left: 0; } .a { z-index: 1; background-color: #000001; } .b { background-color: #000002; } .c { z-index: -1; background-color: #000003; width: 30px; } .d { position: static; background-color: #000004; width: 20px; }",
+            "<div class=\"a\"></div><div class=\"b\"></div><div class=\"c\"></div><div class=\"d\"></div>",
+        );
+        // 木の順序では先にあっても、z-indexが大きいボックスが手前にある
+        assert_eq!(1, hit_color(&view, (5, 5)));
+        // z-indexが負のボックスは、通常フローのボックスの後ろにある
+        assert_eq!(4, hit_color(&view, (15, 5)));
+        assert_eq!(3, hit_color(&view, (25, 5)));
+    }
+
+    #[test]
+    fn test_hit_test_later_sibling_in_flow() {
+        let view = create_layout_view_with_style(
+            "div { height: 10px; width: 10px; } .a { background-color: #000001; } .b { background-color: #000002; margin-top: -10px; }",
+            "<div class=\"a\"></div><div class=\"b\"></div>",
+        );
+        // 後に描画される兄弟が重なっている部分を覆う
+        assert_eq!(2, hit_color(&view, (5, 5)));
+    }
+
+    #[test]
+    fn test_hit_test_within_stacking_context() {
+        let view = create_layout_view_with_style(
+            "div { height: 10px; width: 10px; position: absolute; top: 0; left: 0; } .a { z-index: 2; background-color: #000001; } .b { z-index: 1; background-color: #000002; } .c { z-index: 100; background-color: #000003; }",
+            "<div class=\"a\"></div><div class=\"b\"><div class=\"c\"></div></div>",
+        );
+        // cはbの重ね合わせコンテキストの中にあるので、aより後ろにある
+        assert_eq!(1, hit_color(&view, (5, 5)));
+    }
+}
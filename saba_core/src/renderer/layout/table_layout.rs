@@ -0,0 +1,661 @@
+//! テーブルのセルを行と列の格子に並べる
+//! 列の幅は、セルの中身の最小幅(min-content)と最大幅(max-content)から自動的に決める
+//! https://www.w3.org/TR/CSS22/tables.html
+
+use crate::font::collection::FontCollection;
+use crate::renderer::dom::node::NodeKind;
+use crate::renderer::layout::block_layout::layout_formatting_context_root;
+use crate::renderer::layout::block_layout::max_content_width;
+use crate::renderer::layout::block_layout::min_content_width;
+use crate::renderer::layout::computed_style::BorderCollapse;
+use crate::renderer::layout::computed_style::DisplayType;
+use crate::renderer::layout::computed_style::Length;
+use crate::renderer::layout::layout_object::children;
+use crate::renderer::layout::layout_object::LayoutObject;
+use crate::renderer::layout::layout_object::LayoutPoint;
+use crate::renderer::layout::layout_object::LayoutSize;
+use alloc::rc::Rc;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::cell::RefCell;
+use core::ops::Range;
+
+/// 1つのセルが結合できる行と列の上限
+const MAX_SPAN: usize = 1000;
+
+#[derive(Debug, Clone)]
+struct TableCell {
+    node: Rc<RefCell<LayoutObject>>,
+    row: usize,
+    column: usize,
+    row_span: usize,
+    column_span: usize,
+}
+
+/// セルの間隔。ボーダーを相殺する場合、隣り合うセルは間隔の分だけ重なる
+#[derive(Debug, Clone, Copy)]
+struct Spacing {
+    /// テーブルの端と最初(最後)のセルの間隔
+    edge_x: i64,
+    edge_y: i64,
+    /// 隣り合うセルの間隔
+    between_x: i64,
+    between_y: i64,
+}
+
+impl Spacing {
+    fn total_x(&self, columns: usize) -> i64 {
+        total(self.edge_x, self.between_x, columns)
+    }
+
+    fn total_y(&self, rows: usize) -> i64 {
+        total(self.edge_y, self.between_y, rows)
+    }
+}
+
+fn total(edge: i64, between: i64, count: usize) -> i64 {
+    if count == 0 {
+        return 0;
+    }
+    edge * 2 + between * (count as i64 - 1)
+}
+
+/// 行グループと、そのグループに含まれる行の範囲
+/// テーブルの直下にある行は、グループを持たない
+type RowGroup = (Option<Rc<RefCell<LayoutObject>>>, Range<usize>);
+
+/// テーブルの子を、キャプションと行グループ、行、セルに分けたもの
+#[derive(Debug, Clone)]
+struct TableGrid {
+    captions: Vec<Rc<RefCell<LayoutObject>>>,
+    groups: Vec<RowGroup>,
+    rows: Vec<Rc<RefCell<LayoutObject>>>,
+    cells: Vec<TableCell>,
+    columns: usize,
+}
+
+impl TableGrid {
+    fn new(table: &Rc<RefCell<LayoutObject>>) -> Self {
+        let mut captions = Vec::new();
+        let mut headers = Vec::new();
+        let mut bodies = Vec::new();
+        let mut footers = Vec::new();
+
+        let mut child = table.borrow().first_child();
+        while let Some(c) = child {
+            let display = c.borrow().style().display();
+            match display {
+                DisplayType::TableCaption => captions.push(c.clone()),
+                DisplayType::TableHeaderGroup => headers.push((Some(c.clone()), children(&c))),
+                DisplayType::TableRowGroup => bodies.push((Some(c.clone()), children(&c))),
+                DisplayType::TableFooterGroup => footers.push((Some(c.clone()), children(&c))),
+                // 連続する行はグループを持たない1つのまとまりにする
+                DisplayType::TableRow => match bodies.last_mut() {
+                    Some((None, rows)) => rows.push(c.clone()),
+                    _ => bodies.push((None, vec![c.clone()])),
+                },
+                _ => {}
+            }
+            child = c.borrow().next_sibling();
+        }
+
+        // ヘッダーは先頭に、フッターは末尾に置く
+        let mut groups = Vec::new();
+        let mut rows = Vec::new();
+        for (group, group_rows) in headers.into_iter().chain(bodies).chain(footers) {
+            let start = rows.len();
+            rows.extend(
+                group_rows
+                    .into_iter()
+                    .filter(|r| r.borrow().style().display() == DisplayType::TableRow),
+            );
+            groups.push((group, start..rows.len()));
+        }
+
+        let mut grid = Self {
+            captions,
+            groups,
+            rows,
+            cells: Vec::new(),
+            columns: 0,
+        };
+        grid.assign_slots();
+        grid
+    }
+
+    /// セルを格子に割り当てる
+    /// 上の行から伸びてきたセルが占めている場所は飛ばす
+    fn assign_slots(&mut self) {
+        let row_count = self.rows.len();
+        let mut occupied: Vec<Vec<bool>> = vec![Vec::new(); row_count];
+
+        for (row, row_node) in self.rows.iter().enumerate() {
+            let mut column = 0;
+            for cell in children(row_node) {
+                if cell.borrow().style().display() != DisplayType::TableCell {
+                    continue;
+                }
+                while occupied[row].get(column).copied().unwrap_or(false) {
+                    column += 1;
+                }
+
+                let column_span = span(&cell, "colspan").unwrap_or(1).clamp(1, MAX_SPAN);
+                // rowspan="0"は最後の行まで結合する
+                let row_span = match span(&cell, "rowspan") {
+                    Some(0) => row_count - row,
+                    Some(n) => n.clamp(1, row_count - row),
+                    None => 1,
+                };
+
+                for slots in &mut occupied[row..row + row_span] {
+                    if slots.len() < column + column_span {
+                        slots.resize(column + column_span, false);
+                    }
+                    for slot in &mut slots[column..column + column_span] {
+                        *slot = true;
+                    }
+                }
+                self.columns = core::cmp::max(self.columns, column + column_span);
+                self.cells.push(TableCell {
+                    node: cell,
+                    row,
+                    column,
+                    row_span,
+                    column_span,
+                });
+                column += column_span;
+            }
+        }
+    }
+
+    fn spacing(&self, table: &Rc<RefCell<LayoutObject>>) -> Spacing {
+        let (style, font_size) = {
+            let t = table.borrow();
+            (t.style(), t.font_size_px())
+        };
+
+        if style.border_collapse() == BorderCollapse::Separate {
+            let (horizontal, vertical) = style.border_spacing();
+            let horizontal = horizontal.to_px(0, font_size).unwrap_or(0);
+            let vertical = vertical.to_px(0, font_size).unwrap_or(0);
+            return Spacing {
+                edge_x: horizontal,
+                edge_y: vertical,
+                between_x: horizontal,
+                between_y: vertical,
+            };
+        }
+
+        // ボーダーを相殺する場合、隣り合うセルのボーダーを重ねて1本の線にする
+        // 線の太さはテーブル全体で最も太いボーダーに揃える
+        let mut border_x = 0;
+        let mut border_y = 0;
+        for cell in &self.cells {
+            let c = cell.node.borrow();
+            let border = c.style().border_width();
+            let px = |l: Length| l.to_px(0, c.font_size_px()).unwrap_or(0);
+            border_x = core::cmp::max(border_x, core::cmp::min(px(border.left), px(border.right)));
+            border_y = core::cmp::max(border_y, core::cmp::min(px(border.top), px(border.bottom)));
+        }
+        Spacing {
+            edge_x: 0,
+            edge_y: 0,
+            between_x: -border_x,
+            between_y: -border_y,
+        }
+    }
+
+    /// 列ごとの最小幅と最大幅
+    fn column_width_ranges(
+        &self,
+        spacing: &Spacing,
+        fonts: &FontCollection,
+    ) -> (Vec<i64>, Vec<i64>) {
+        let mut mins = vec![0; self.columns];
+        let mut maxs = vec![0; self.columns];
+
+        let mut cells = self.cells.iter().collect::<Vec<_>>();
+        // 結合していないセルから順に、列の幅に反映する
+        cells.sort_by_key(|cell| cell.column_span);
+        for cell in cells {
+            let min = min_content_width(&cell.node, fonts);
+            let max = core::cmp::max(max_content_width(&cell.node, fonts), min);
+            let columns = cell.column..cell.column + cell.column_span;
+            let between = spacing.between_x * (cell.column_span as i64 - 1);
+
+            // 結合したセルが収まらない場合、足りない幅を列に均等に分ける
+            for (widths, needed) in [(&mut mins, min), (&mut maxs, max)] {
+                let current = widths[columns.clone()].iter().sum::<i64>() + between;
+                if needed > current {
+                    let weights = vec![1; cell.column_span];
+                    for (width, extra) in widths[columns.clone()]
+                        .iter_mut()
+                        .zip(share(needed - current, &weights))
+                    {
+                        *width += extra;
+                    }
+                }
+            }
+        }
+
+        for (min, max) in mins.iter().zip(maxs.iter_mut()) {
+            *max = core::cmp::max(*max, *min);
+        }
+        (mins, maxs)
+    }
+}
+
+/// colspanやrowspan属性の値
+fn span(cell: &Rc<RefCell<LayoutObject>>, name: &str) -> Option<usize> {
+    match cell.borrow().node_kind() {
+        NodeKind::Element(e) => e.get_attribute(name)?.trim().parse::<usize>().ok(),
+        _ => None,
+    }
+}
+
+/// `total`を`weights`の比で分ける
+/// 端数が出ても合計が`total`になるように、累積値を丸めて分ける
+fn share(total: i64, weights: &[i64]) -> Vec<i64> {
+    let weight_sum = weights.iter().sum::<i64>();
+    let count = weights.len() as i64;
+    let mut accumulated = 0;
+    let mut previous = 0;
+    weights
+        .iter()
+        .map(|weight| {
+            // 重みがすべて0の場合は均等に分ける
+            let (numerator, denominator) = if weight_sum > 0 {
+                (*weight, weight_sum)
+            } else {
+                (1, count)
+            };
+            accumulated += numerator;
+            let current = total * accumulated / denominator;
+            let share = current - previous;
+            previous = current;
+            share
+        })
+        .collect()
+}
+
+/// テーブルの列の幅を、使える幅`available`に合わせて決める
+/// https://www.w3.org/TR/CSS22/tables.html#auto-table-layout
+fn distribute_widths(mins: &[i64], maxs: &[i64], available: i64) -> Vec<i64> {
+    let min_sum = mins.iter().sum::<i64>();
+    let max_sum = maxs.iter().sum::<i64>();
+
+    if available >= max_sum {
+        // 余った幅は最大幅の比で分ける
+        maxs.iter()
+            .zip(share(available - max_sum, maxs))
+            .map(|(max, extra)| max + extra)
+            .collect()
+    } else if available > min_sum {
+        // 最小幅と最大幅の差の比で、最小幅から広げる
+        let ranges = mins
+            .iter()
+            .zip(maxs)
+            .map(|(min, max)| max - min)
+            .collect::<Vec<_>>();
+        mins.iter()
+            .zip(share(available - min_sum, &ranges))
+            .map(|(min, extra)| min + extra)
+            .collect()
+    } else {
+        mins.to_vec()
+    }
+}
+
+/// テーブルのコンテンツボックスの最小幅と最大幅
+pub fn table_intrinsic_widths(
+    table: &Rc<RefCell<LayoutObject>>,
+    fonts: &FontCollection,
+) -> (i64, i64) {
+    let grid = TableGrid::new(table);
+    let spacing = grid.spacing(table);
+    let (mins, maxs) = grid.column_width_ranges(&spacing, fonts);
+    let mut min = mins.iter().sum::<i64>() + spacing.total_x(grid.columns);
+    let mut max = maxs.iter().sum::<i64>() + spacing.total_x(grid.columns);
+
+    for caption in &grid.captions {
+        min = core::cmp::max(min, min_content_width(caption, fonts));
+        max = core::cmp::max(max, max_content_width(caption, fonts));
+    }
+    (min, max)
+}
+
+/// テーブルの中身を配置し、コンテンツボックスの幅と高さを返す
+/// 列の最小幅の和が`content_width`より大きい場合、返す幅は`content_width`より大きくなる
+pub fn layout_table(
+    table: &Rc<RefCell<LayoutObject>>,
+    content_x: i64,
+    content_y: i64,
+    content_width: i64,
+    fonts: &FontCollection,
+) -> (i64, i64) {
+    let grid = TableGrid::new(table);
+    let spacing = grid.spacing(table);
+    let (mins, maxs) = grid.column_width_ranges(&spacing, fonts);
+    let widths = distribute_widths(&mins, &maxs, content_width - spacing.total_x(grid.columns));
+    let grid_width = widths.iter().sum::<i64>() + spacing.total_x(grid.columns);
+    let table_width = core::cmp::max(content_width, grid_width);
+
+    // キャプションはテーブルの上に置く
+    let mut y = content_y;
+    for caption in &grid.captions {
+        layout_formatting_context_root(caption, LayoutPoint::new(content_x, y), table_width, fonts);
+        let c = caption.borrow();
+        y += c.size().height() + c.margin().vertical();
+    }
+    let grid_top = y;
+
+    let mut column_x = Vec::new();
+    let mut x = spacing.edge_x;
+    for width in &widths {
+        column_x.push(x);
+        x += width + spacing.between_x;
+    }
+    let cell_width = |cell: &TableCell| {
+        widths[cell.column..cell.column + cell.column_span]
+            .iter()
+            .sum::<i64>()
+            + spacing.between_x * (cell.column_span as i64 - 1)
+    };
+
+    // セルの横幅を決めてから高さを測る
+    let mut cell_heights = Vec::new();
+    for cell in &grid.cells {
+        cell.node
+            .borrow_mut()
+            .set_override_size(Some(cell_width(cell)), None);
+        layout_formatting_context_root(&cell.node, LayoutPoint::new(0, 0), table_width, fonts);
+        let height = cell.node.borrow().size().height();
+        cell_heights.push(height);
+    }
+
+    let mut row_heights = grid
+        .rows
+        .iter()
+        .map(|row| row.borrow().specified_height().unwrap_or(0))
+        .collect::<Vec<_>>();
+    let mut spanning = Vec::new();
+    for (cell, height) in grid.cells.iter().zip(&cell_heights) {
+        if cell.row_span == 1 {
+            row_heights[cell.row] = core::cmp::max(row_heights[cell.row], *height);
+        } else {
+            spanning.push((cell, *height));
+        }
+    }
+    // 結合したセルが収まらない場合、最後の行を高くする
+    spanning.sort_by_key(|(cell, _)| cell.row_span);
+    for (cell, height) in spanning {
+        let rows = cell.row..cell.row + cell.row_span;
+        let current = row_heights[rows.clone()].iter().sum::<i64>()
+            + spacing.between_y * (cell.row_span as i64 - 1);
+        if height > current {
+            row_heights[rows.end - 1] += height - current;
+        }
+    }
+
+    let mut row_y = Vec::new();
+    let mut y = spacing.edge_y;
+    for height in &row_heights {
+        row_y.push(y);
+        y += height + spacing.between_y;
+    }
+    let grid_height = row_heights.iter().sum::<i64>() + spacing.total_y(grid.rows.len());
+
+    // セルは結合した行の高さまで引き伸ばす
+    for cell in &grid.cells {
+        let height = row_heights[cell.row..cell.row + cell.row_span]
+            .iter()
+            .sum::<i64>()
+            + spacing.between_y * (cell.row_span as i64 - 1);
+        let extra = {
+            let c = cell.node.borrow();
+            c.border().vertical() + c.padding().vertical()
+        };
+        cell.node.borrow_mut().set_override_size(
+            Some(cell_width(cell)),
+            Some(core::cmp::max(height - extra, 0)),
+        );
+        layout_formatting_context_root(
+            &cell.node,
+            LayoutPoint::new(
+                content_x + column_x[cell.column],
+                grid_top + row_y[cell.row],
+            ),
+            table_width,
+            fonts,
+        );
+    }
+
+    // 行と行グループの背景を描画できるように、セルを囲む大きさにする
+    let rows_x = content_x + spacing.edge_x;
+    let rows_width = grid_width - spacing.edge_x * 2;
+    for (row, (y, height)) in grid.rows.iter().zip(row_y.iter().zip(&row_heights)) {
+        let mut r = row.borrow_mut();
+        r.set_point(LayoutPoint::new(rows_x, grid_top + y));
+        r.set_size(LayoutSize::new(rows_width, *height));
+    }
+    for (group, rows) in &grid.groups {
+        let group = match group {
+            Some(group) => group,
+            None => continue,
+        };
+        let (top, bottom) = if rows.is_empty() {
+            (grid_top, grid_top)
+        } else {
+            (
+                grid_top + row_y[rows.start],
+                grid_top + row_y[rows.end - 1] + row_heights[rows.end - 1],
+            )
+        };
+        let mut g = group.borrow_mut();
+        g.set_point(LayoutPoint::new(rows_x, top));
+        g.set_size(LayoutSize::new(rows_width, bottom - top));
+    }
+
+    (table_width, grid_top - content_y + grid_height)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::display_item::DisplayItem;
+    use crate::renderer::layout::layout_view::LayoutView;
+    use crate::renderer::layout::test_util::create_layout_view;
+    use alloc::string::String;
+
+    fn table_of(view: &LayoutView) -> Rc<RefCell<LayoutObject>> {
+        view.root()
+            .expect("root should exist")
+            .borrow()
+            .first_child()
+            .expect("table should exist")
+    }
+
+    /// テーブルのすべてのセルを、行ごとに返す
+    fn cells(table: &Rc<RefCell<LayoutObject>>) -> Vec<Vec<Rc<RefCell<LayoutObject>>>> {
+        let grid = TableGrid::new(table);
+        grid.rows
+            .iter()
+            .map(|row| {
+                children(row)
+                    .into_iter()
+                    .filter(|c| c.borrow().style().display() == DisplayType::TableCell)
+                    .collect()
+            })
+            .collect()
+    }
+
+    fn table_html(style: &str, body: &str) -> String {
+        let mut html = String::from("<html><head><style>");
+        html.push_str(style);
+        html.push_str("</style></head><body><table>");
+        html.push_str(body);
+        html.push_str("</table></body></html>");
+        html
+    }
+
+    #[test]
+    fn test_auto_column_widths() {
+        let view = create_layout_view(table_html(
+            "table { border-spacing: 0; } td { padding: 0; }",
+            "<tr><td>aa</td><td>bbbb</td></tr><tr><td>a</td><td>b</td></tr>",
+        ));
+        let table = table_of(&view);
+        let cells = cells(&table);
+
+        // 列の幅は最も広いセルのmax-contentになる
+        assert_eq!(LayoutPoint::new(0, 0), cells[0][0].borrow().point());
+        assert_eq!(LayoutSize::new(16, 20), cells[0][0].borrow().size());
+        assert_eq!(LayoutPoint::new(16, 0), cells[0][1].borrow().point());
+        assert_eq!(LayoutSize::new(32, 20), cells[0][1].borrow().size());
+        assert_eq!(LayoutPoint::new(16, 20), cells[1][1].borrow().point());
+        assert_eq!(LayoutSize::new(32, 20), cells[1][1].borrow().size());
+        assert_eq!(LayoutSize::new(48, 40), table.borrow().size());
+    }
+
+    #[test]
+    fn test_default_spacing_and_padding() {
+        let view = create_layout_view(table_html("", "<tr><td>aa</td><td>b</td></tr>"));
+        let table = table_of(&view);
+        let cells = cells(&table);
+
+        // border-spacing: 2px、セルのpadding: 1px
+        assert_eq!(LayoutPoint::new(2, 2), cells[0][0].borrow().point());
+        assert_eq!(LayoutSize::new(18, 22), cells[0][0].borrow().size());
+        assert_eq!(LayoutPoint::new(22, 2), cells[0][1].borrow().point());
+        assert_eq!(
+            LayoutSize::new(2 + 18 + 2 + 10 + 2, 26),
+            table.borrow().size()
+        );
+    }
+
+    #[test]
+    fn test_specified_table_width() {
+        let view = create_layout_view(table_html(
+            "table { border-spacing: 0; width: 300px; } td { padding: 0; }",
+            "<tr><td>aa</td><td>bbbb</td></tr>",
+        ));
+        let table = table_of(&view);
+        let cells = cells(&table);
+
+        // 余った幅は最大幅の比で分ける
+        assert_eq!(100, cells[0][0].borrow().size().width());
+        assert_eq!(200, cells[0][1].borrow().size().width());
+        assert_eq!(300, table.borrow().size().width());
+    }
+
+    #[test]
+    fn test_narrow_table() {
+        let view = create_layout_view(table_html(
+            "table { border-spacing: 0; width: 50px; } td { padding: 0; }",
+            "<tr><td>aaaa aaaa</td><td>bb</td></tr>",
+        ));
+        let table = table_of(&view);
+        let cells = cells(&table);
+
+        // min-contentは32pxと16px、max-contentは72pxと16px
+        assert_eq!(LayoutSize::new(34, 40), cells[0][0].borrow().size());
+        assert_eq!(LayoutSize::new(16, 40), cells[0][1].borrow().size());
+
+        let view = create_layout_view(table_html(
+            "table { border-spacing: 0; width: 10px; } td { padding: 0; }",
+            "<tr><td>aaaa aaaa</td><td>bb</td></tr>",
+        ));
+        // 最小幅の和より狭くはならない
+        assert_eq!(48, table_of(&view).borrow().size().width());
+    }
+
+    #[test]
+    fn test_colspan() {
+        let view = create_layout_view(table_html(
+            "table { border-spacing: 0; } td { padding: 0; }",
+            r#"<tr><td colspan="2">aaaaaaaaaa</td></tr><tr><td>a</td><td>b</td></tr>"#,
+        ));
+        let table = table_of(&view);
+        let cells = cells(&table);
+
+        assert_eq!(LayoutSize::new(80, 20), cells[0][0].borrow().size());
+        assert_eq!(LayoutSize::new(40, 20), cells[1][0].borrow().size());
+        assert_eq!(LayoutPoint::new(40, 20), cells[1][1].borrow().point());
+    }
+
+    #[test]
+    fn test_rowspan() {
+        let view = create_layout_view(table_html(
+            "table { border-spacing: 0; } td { padding: 0; } .tall { height: 50px; }",
+            r#"<tr><td rowspan="2" class="tall">a</td><td>b</td></tr><tr><td>c</td></tr>"#,
+        ));
+        let table = table_of(&view);
+        let cells = cells(&table);
+
+        assert_eq!(LayoutSize::new(8, 50), cells[0][0].borrow().size());
+        // 2行目のセルは結合したセルの右に置かれ、結合したセルが収まるように高くなる
+        assert_eq!(LayoutPoint::new(8, 20), cells[1][0].borrow().point());
+        assert_eq!(LayoutSize::new(8, 30), cells[1][0].borrow().size());
+        assert_eq!(LayoutSize::new(16, 50), table.borrow().size());
+    }
+
+    #[test]
+    fn test_border_collapse() {
+        let view = create_layout_view(table_html(
+            "table { border-collapse: collapse; } td { padding: 0; border: 1px solid black; }",
+            "<tr><td>a</td><td>b</td></tr><tr><td>c</td><td>d</td></tr>",
+        ));
+        let table = table_of(&view);
+        let cells = cells(&table);
+
+        // 隣り合うセルのボーダーは重なる
+        assert_eq!(LayoutPoint::new(0, 0), cells[0][0].borrow().point());
+        assert_eq!(LayoutSize::new(10, 22), cells[0][0].borrow().size());
+        assert_eq!(LayoutPoint::new(9, 0), cells[0][1].borrow().point());
+        assert_eq!(LayoutPoint::new(9, 21), cells[1][1].borrow().point());
+        assert_eq!(LayoutSize::new(19, 43), table.borrow().size());
+    }
+
+    #[test]
+    fn test_caption_and_row_groups() {
+        let view = create_layout_view(table_html(
+            "table { border-spacing: 0; } td { padding: 0; }",
+            "<caption>title</caption><tfoot><tr><td>f</td></tr></tfoot><thead><tr><td>h</td></tr></thead><tbody><tr><td>b</td></tr></tbody>",
+        ));
+        let table = table_of(&view);
+        let grid = TableGrid::new(&table);
+
+        let caption = grid.captions[0].clone();
+        assert_eq!(LayoutPoint::new(0, 0), caption.borrow().point());
+        assert_eq!(LayoutSize::new(40, 20), caption.borrow().size());
+
+        // ヘッダー、本体、フッターの順に並ぶ
+        let y = |i: usize| grid.rows[i].borrow().point().y();
+        assert_eq!((20, 40, 60), (y(0), y(1), y(2)));
+        let (tfoot, _) = grid.groups[2].clone();
+        assert_eq!(
+            LayoutPoint::new(0, 60),
+            tfoot.expect("tfoot should exist").borrow().point()
+        );
+        assert_eq!(LayoutSize::new(40, 80), table.borrow().size());
+    }
+
+    #[test]
+    fn test_paint_table() {
+        let view = create_layout_view(table_html(
+            "table { border-spacing: 0; } td { padding: 0; background-color: yellow; }",
+            "<tr><td>a</td></tr>",
+        ));
+        let items = view.paint();
+
+        assert!(items.iter().any(|item| matches!(
+            item,
+            DisplayItem::Rect { layout_size, .. } if *layout_size == LayoutSize::new(8, 20)
+        )));
+        assert!(items.iter().any(|item| matches!(
+            item,
+            DisplayItem::Text { text, layout_point, .. }
+                if text == "a" && *layout_point == LayoutPoint::new(0, 0)
+        )));
+    }
+}
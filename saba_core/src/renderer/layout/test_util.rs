@@ -0,0 +1,55 @@
+//! レイアウトのテストで共通して使う、HTMLからレイアウトツリーを作る関数
+
+use crate::constants::CONTENT_AREA_HEIGHT;
+use crate::constants::CONTENT_AREA_WIDTH;
+use crate::renderer::css::cssom::CssParser;
+use crate::renderer::css::token::CssTokenizer;
+use crate::renderer::dom::api::get_style_content;
+use crate::renderer::html::parser::HtmlParser;
+use crate::renderer::html::token::HtmlTokenizer;
+use crate::renderer::layout::layout_object::children;
+use crate::renderer::layout::layout_object::LayoutObject;
+use crate::renderer::layout::layout_view::LayoutView;
+use crate::renderer::layout::viewport::Viewport;
+use alloc::format;
+use alloc::rc::Rc;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::cell::RefCell;
+
+/// コンテンツ領域の大きさのビューポートで、HTMLの文書をレイアウトする
+pub(crate) fn create_layout_view(html: String) -> LayoutView {
+    layout(html, Viewport::new(CONTENT_AREA_WIDTH, CONTENT_AREA_HEIGHT))
+}
+
+/// `style`のスタイルシートと`body`の中身からなる文書をレイアウトする
+pub(crate) fn create_layout_view_with_style(style: &str, body: &str) -> LayoutView {
+    create_layout_view_in(
+        Viewport::new(CONTENT_AREA_WIDTH, CONTENT_AREA_HEIGHT),
+        style,
+        body,
+    )
+}
+
+/// `create_layout_view_with_style`と同じ文書を、`viewport`の大きさでレイアウトする
+pub(crate) fn create_layout_view_in(viewport: Viewport, style: &str, body: &str) -> LayoutView {
+    let html = format!(
+        "<html><head><style>{}</style></head><body>{}</body></html>",
+        style, body
+    );
+    layout(html, viewport)
+}
+
+fn layout(html: String, viewport: Viewport) -> LayoutView {
+    let t = HtmlTokenizer::new(html);
+    let window = HtmlParser::new(t).construct_tree();
+    let dom = window.borrow().document();
+    let style = get_style_content(dom.clone());
+    let cssom = CssParser::new(CssTokenizer::new(style)).parse_stylesheet();
+    LayoutView::new(dom, &cssom, viewport)
+}
+
+/// bodyのボックスの子を順に返す
+pub(crate) fn body_children(view: &LayoutView) -> Vec<Rc<RefCell<LayoutObject>>> {
+    children(&view.root().expect("root should exist"))
+}
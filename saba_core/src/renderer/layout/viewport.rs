@@ -0,0 +1,92 @@
+//! ページを表示する領域
+//! レイアウトはCSSピクセルで行い、描画するときにデバイスピクセル比を掛ける
+
+use crate::renderer::layout::layout_object::LayoutSize;
+
+/// ビューポートの大きさ(CSSピクセル)と、1 CSSピクセルあたりのデバイスピクセル数
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Viewport {
+    width: i64,
+    height: i64,
+    device_pixel_ratio: f32,
+}
+
+impl Viewport {
+    /// デバイスピクセル比が1のビューポート
+    pub fn new(width: i64, height: i64) -> Self {
+        Self {
+            width: core::cmp::max(width, 0),
+            height: core::cmp::max(height, 0),
+            device_pixel_ratio: 1.0,
+        }
+    }
+
+    /// 0以下の比は1として扱う
+    pub fn with_device_pixel_ratio(mut self, device_pixel_ratio: f32) -> Self {
+        self.device_pixel_ratio = if device_pixel_ratio > 0.0 {
+            device_pixel_ratio
+        } else {
+            1.0
+        };
+        self
+    }
+
+    pub fn width(&self) -> i64 {
+        self.width
+    }
+
+    pub fn height(&self) -> i64 {
+        self.height
+    }
+
+    pub fn size(&self) -> LayoutSize {
+        LayoutSize::new(self.width, self.height)
+    }
+
+    pub fn device_pixel_ratio(&self) -> f32 {
+        self.device_pixel_ratio
+    }
+
+    /// デバイスピクセルでの横幅
+    pub fn device_width(&self) -> i64 {
+        self.to_device_pixels(self.width)
+    }
+
+    /// デバイスピクセルでの高さ
+    pub fn device_height(&self) -> i64 {
+        self.to_device_pixels(self.height)
+    }
+
+    /// CSSピクセルの長さをデバイスピクセルにする
+    pub fn to_device_pixels(&self, length: i64) -> i64 {
+        let scaled = length as f32 * self.device_pixel_ratio;
+        // 四捨五入する
+        if scaled >= 0.0 {
+            (scaled + 0.5) as i64
+        } else {
+            (scaled - 0.5) as i64
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_device_pixels() {
+        let viewport = Viewport::new(1280, 800).with_device_pixel_ratio(1.5);
+        assert_eq!(LayoutSize::new(1280, 800), viewport.size());
+        assert_eq!(1920, viewport.device_width());
+        assert_eq!(1200, viewport.device_height());
+        assert_eq!(2, viewport.to_device_pixels(1));
+        assert_eq!(-2, viewport.to_device_pixels(-1));
+    }
+
+    #[test]
+    fn test_invalid_ratio() {
+        let viewport = Viewport::new(-1, 10).with_device_pixel_ratio(0.0);
+        assert_eq!(0, viewport.width());
+        assert_eq!(1.0, viewport.device_pixel_ratio());
+    }
+}
@@ -1,23 +1,34 @@
 use crate::browser::Browser;
 use crate::display_item::DisplayItem;
+use crate::error::Error;
+use crate::font::collection::FontCollection;
 use crate::http::HttpResponse;
+use crate::image;
+use crate::image::ImageMap;
 use crate::renderer::css::cssom::CssParser;
 use crate::renderer::css::cssom::StyleSheet;
 use crate::renderer::css::token::CssTokenizer;
-use crate::renderer::dom::api::get_js_content;
+use crate::renderer::dom::api::get_image_sources;
+use crate::renderer::dom::api::get_script;
 use crate::renderer::dom::api::get_style_content;
+use crate::renderer::dom::api::Script;
 use crate::renderer::dom::node::ElementKind;
+use crate::renderer::dom::node::Node;
 use crate::renderer::dom::node::NodeKind;
 use crate::renderer::dom::node::Window;
 use crate::renderer::html::parser::HtmlParser;
 use crate::renderer::html::token::HtmlTokenizer;
 use crate::renderer::js::ast::JsParser;
+use crate::renderer::js::runtime::date::Clock;
 use crate::renderer::js::runtime::JsRuntime;
 use crate::renderer::js::token::JsLexer;
 use crate::renderer::layout::layout_view::LayoutView;
+use crate::renderer::layout::viewport::Viewport;
+use crate::url::Url;
 use alloc::rc::Rc;
 use alloc::rc::Weak;
 use alloc::string::String;
+use alloc::string::ToString;
 use alloc::vec::Vec;
 use core::cell::RefCell;
 
@@ -28,19 +39,68 @@ pub struct Page {
     style: Option<StyleSheet>,
     layout_view: Option<LayoutView>,
     display_items: Vec<DisplayItem>,
+    fonts: FontCollection,
+    viewport: Viewport,
+    /// 表示している文書のURL。相対URLの基準になる
+    url: Option<Url>,
+    /// 画像やスクリプトなどのサブリソースを取得する関数
+    loader: Option<fn(String) -> Result<HttpResponse, Error>>,
+    images: ImageMap,
+    /// スクリプトの`Date`が現在の時刻として使う時計。Noneなら実行環境の既定の時計を使う
+    clock: Option<Rc<dyn Clock>>,
 }
 
 impl Page {
-    pub fn new() -> Self {
+    /// `viewport`の大きさでページをレイアウトする
+    pub fn new(viewport: Viewport) -> Self {
         Self {
             browser: Weak::new(),
             frame: None,
             style: None,
             layout_view: None,
             display_items: Vec::new(),
+            fonts: FontCollection::default(),
+            viewport,
+            url: None,
+            loader: None,
+            images: ImageMap::new(),
+            clock: None,
         }
     }
 
+    pub fn viewport(&self) -> Viewport {
+        self.viewport
+    }
+
+    /// ウィンドウの大きさが変わったときに呼ぶ。表示中の文書はレイアウトし直す
+    pub fn set_viewport(&mut self, viewport: Viewport) {
+        self.viewport = viewport;
+        if let Some(view) = &mut self.layout_view {
+            view.set_viewport(viewport);
+            self.paint_tree();
+        }
+    }
+
+    /// 次に受け取る文書のURL。`receive_response`の前に呼ぶ
+    pub fn set_url(&mut self, url: String) {
+        self.url = Url::new(url).parse().ok();
+    }
+
+    /// `<img>`の画像や`<script src>`のスクリプトを取得するときに使う関数
+    pub fn set_loader(&mut self, loader: fn(String) -> Result<HttpResponse, Error>) {
+        self.loader = Some(loader);
+    }
+
+    /// スクリプトが読む現在の時刻。`receive_response`の前に呼ぶ
+    pub fn set_clock(&mut self, clock: Rc<dyn Clock>) {
+        self.clock = Some(clock);
+    }
+
+    /// レイアウトで文字の大きさを測るときに使うフォント
+    pub fn set_fonts(&mut self, fonts: FontCollection) {
+        self.fonts = fonts;
+    }
+
     pub fn clicked(&self, position: (i64, i64)) -> Option<String> {
         let view = match &self.layout_view {
             Some(v) => v,
@@ -60,6 +120,19 @@ impl Page {
         None
     }
 
+    /// コンテンツエリアの`position`の下にあるスクロールコンテナか、ページ全体を(dx, dy)だけスクロールする
+    /// スクロールした場合はディスプレイアイテムを作り直し、trueを返す
+    pub fn scroll(&mut self, position: (i64, i64), dx: i64, dy: i64) -> bool {
+        let scrolled = match &mut self.layout_view {
+            Some(view) => view.scroll_by(position, dx, dy),
+            None => false,
+        };
+        if scrolled {
+            self.paint_tree();
+        }
+        scrolled
+    }
+
     pub fn set_browser(&mut self, browser: Weak<RefCell<Browser>>) {
         self.browser = browser;
     }
@@ -67,32 +140,84 @@ impl Page {
     pub fn receive_response(&mut self, response: HttpResponse) {
         self.create_frame(response.body());
 
-        self.execute_js();
-
+        self.load_images();
         self.set_layout_view();
         self.paint_tree();
     }
 
-    fn execute_js(&mut self) {
+    /// `src`のスクリプトを取得する。取得できなかったスクリプトは実行しない
+    fn fetch_script(&self, src: &str) -> Option<String> {
+        let loader = self.loader?;
+        match loader(self.resolve_url(src)) {
+            Ok(response) if response.status_code() == 200 => Some(response.body()),
+            _ => None,
+        }
+    }
+
+    /// 文書のURLを基準にして相対URLを解決する
+    fn resolve_url(&self, src: &str) -> String {
+        match &self.url {
+            Some(base) => base.resolve(src),
+            None => src.to_string(),
+        }
+    }
+
+    /// `<img>`の画像を取得してデコードする。取得やデコードに失敗した画像は表示しない
+    fn load_images(&mut self) {
+        self.images = ImageMap::new();
+        let loader = match self.loader {
+            Some(loader) => loader,
+            None => return,
+        };
         let dom = match &self.frame {
             Some(frame) => frame.borrow().document(),
             None => return,
         };
 
-        let js = get_js_content(dom.clone());
-        let lexer = JsLexer::new(js);
-
-        let mut parser = JsParser::new(lexer);
-        let ast = parser.parse_ast();
-
-        let mut runtime = JsRuntime::new(dom);
-        runtime.execute(&ast);
+        for src in get_image_sources(dom) {
+            let response = match loader(self.resolve_url(&src)) {
+                Ok(response) if response.status_code() == 200 => response,
+                _ => continue,
+            };
+            if let Ok(image) = image::decode(&response.body_bytes()) {
+                self.images.insert(src, Rc::new(image));
+            }
+        }
     }
 
+    /// 文書を解析し、`<script>`要素を実行しながらDOMツリーを作る
     fn create_frame(&mut self, html: String) {
         let html_tokenizer = HtmlTokenizer::new(html);
-        let frame = HtmlParser::new(html_tokenizer).construct_tree();
-        let dom = frame.borrow().document();
+        let mut parser = HtmlParser::new(html_tokenizer);
+        let dom = parser.window().borrow().document();
+
+        // 全てのスクリプトで1つの実行環境を共有する
+        let mut runtime = None;
+        let clock = self.clock.clone();
+        let mut async_scripts = Vec::new();
+        let mut deferred_scripts = Vec::new();
+        let frame = parser.construct_tree_with_scripts(|node| match get_script(node) {
+            Some(Script::Inline(source)) => execute_js(&mut runtime, &dom, &clock, source),
+            Some(Script::Blocking(src)) => {
+                if let Some(source) = self.fetch_script(&src) {
+                    execute_js(&mut runtime, &dom, &clock, source);
+                }
+            }
+            // ローダーは取得を終えるまで戻らないので、asyncのスクリプトはここで取得しておく
+            Some(Script::Async(src)) => async_scripts.extend(self.fetch_script(&src)),
+            Some(Script::Deferred(src)) => deferred_scripts.push(src),
+            None => {}
+        });
+
+        // 解析が終わったら、asyncのスクリプトを取得できた順に実行し、deferのスクリプトを文書の順に実行する
+        for source in async_scripts {
+            execute_js(&mut runtime, &dom, &clock, source);
+        }
+        for src in deferred_scripts {
+            if let Some(source) = self.fetch_script(&src) {
+                execute_js(&mut runtime, &dom, &clock, source);
+            }
+        }
 
         let style = get_style_content(dom);
         let css_tokenizer = CssTokenizer::new(style);
@@ -113,7 +238,8 @@ impl Page {
             None => return,
         };
 
-        let layout_view = LayoutView::new(dom, &style);
+        let layout_view =
+            LayoutView::with_images(dom, &style, self.viewport, self.fonts.clone(), &self.images);
 
         self.layout_view = Some(layout_view);
     }
@@ -132,3 +258,166 @@ impl Page {
         self.display_items = Vec::new();
     }
 }
+
+/// スクリプトを1つ実行する。実行環境は最初のスクリプトを実行するときに作る
+fn execute_js(
+    runtime: &mut Option<JsRuntime>,
+    dom: &Rc<RefCell<Node>>,
+    clock: &Option<Rc<dyn Clock>>,
+    source: String,
+) {
+    let mut parser = JsParser::new(JsLexer::new(source));
+    // 構文エラーのあるスクリプトは実行しない
+    let ast = match parser.parse_ast() {
+        Ok(ast) => ast,
+        Err(_) => return,
+    };
+
+    runtime
+        .get_or_insert_with(|| {
+            let mut runtime = JsRuntime::new(dom.clone());
+            if let Some(clock) = clock {
+                runtime.set_clock(clock.clone());
+            }
+            runtime
+        })
+        .execute(&ast);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::renderer::dom::api::get_element_by_id;
+    use crate::renderer::dom::api::get_text_content;
+    use crate::renderer::js::runtime::date::FixedClock;
+    use crate::renderer::layout::layout_object::LayoutPoint;
+    use crate::renderer::layout::layout_object::LayoutSize;
+    use alloc::format;
+
+    /// 1x1の赤いPNG画像を返す
+    fn load_red_png(url: String) -> Result<HttpResponse, Error> {
+        if url != "http://example.com:80/images/red.png" {
+            return HttpResponse::new("HTTP/1.1 404 Not Found\n\n".to_string());
+        }
+        let mut raw = b"HTTP/1.1 200 OK\r\nContent-Type: image/png\r\n\r\n".to_vec();
+        raw.extend_from_slice(&[
+            0x89, 0x50, 0x4e, 0x47, 0x0d, 0x0a, 0x1a, 0x0a, 0x00, 0x00, 0x00, 0x0d, 0x49, 0x48,
+            0x44, 0x52, 0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x01, 0x08, 0x02, 0x00, 0x00,
+            0x00, 0x90, 0x77, 0x53, 0xde, 0x00, 0x00, 0x00, 0x0c, 0x49, 0x44, 0x41, 0x54, 0x78,
+            0xda, 0x63, 0xf8, 0xcf, 0xc0, 0x00, 0x00, 0x03, 0x01, 0x01, 0x00, 0xf7, 0x03, 0x41,
+            0x43, 0x00, 0x00, 0x00, 0x00, 0x49, 0x45, 0x4e, 0x44, 0xae, 0x42, 0x60, 0x82,
+        ]);
+        HttpResponse::from_bytes(&raw)
+    }
+
+    /// スクリプトを返す。ログの末尾に文字を足して、id="log"の要素に書き出す
+    fn load_scripts(url: String) -> Result<HttpResponse, Error> {
+        let script = match url.as_str() {
+            "http://example.com:80/js/blocking.js" => {
+                "log += document.getElementById(\"b\") === null ? \"x\" : \"2\";"
+            }
+            "http://example.com:80/js/async.js" => "log += \"5\";",
+            "http://example.com:80/js/defer.js" => {
+                "log += \"6\"; document.getElementById(\"log\").textContent = log;"
+            }
+            _ => return HttpResponse::new("HTTP/1.1 404 Not Found\n\n".to_string()),
+        };
+        HttpResponse::new(format!("HTTP/1.1 200 OK\n\n{}", script))
+    }
+
+    #[test]
+    fn test_execute_scripts() {
+        let html = "<html><head>\
+            <script>var log = \"\";</script>\
+            <script defer src=\"js/defer.js\"></script>\
+            <script async src=\"js/async.js\"></script>\
+            </head><body><p id=\"log\"></p>\
+            <script>log += document.getElementById(\"b\") === null ? \"1\" : \"x\";</script>\
+            <p id=\"b\">b</p>\
+            <script src=\"js/blocking.js\"></script>\
+            <script type=\"text/javascript\">log += \"3\";</script>\
+            <script type=\"text/plain\">log += \"x\";</script>\
+            <script src=\"js/missing.js\"></script>\
+            <script>log += \"4\"; document.getElementById(\"log\").textContent = log;</script>\
+            </body></html>";
+        let response = HttpResponse::new(format!("HTTP/1.1 200 OK\n\n{}", html))
+            .expect("failed to parse http response");
+
+        let mut page = Page::new(Viewport::new(600, 400));
+        page.set_loader(load_scripts);
+        page.set_url("http://example.com/index.html".to_string());
+        page.receive_response(response);
+
+        // 文書の順に実行し、asyncとdeferのスクリプトは解析が終わってから実行する
+        let dom = page.frame.as_ref().unwrap().borrow().document();
+        let log = get_element_by_id(Some(dom), &"log".to_string()).unwrap();
+        assert_eq!("123456", get_text_content(log));
+    }
+
+    #[test]
+    fn test_set_clock() {
+        let html = "<html><body><p id=\"now\"></p>\
+            <script>document.getElementById(\"now\").textContent = new Date().toISOString();</script>\
+            </body></html>";
+        let response = HttpResponse::new(format!("HTTP/1.1 200 OK\n\n{}", html))
+            .expect("failed to parse http response");
+
+        let mut page = Page::new(Viewport::new(600, 400));
+        page.set_clock(Rc::new(FixedClock(1704164645006.0)));
+        page.receive_response(response);
+
+        let dom = page.frame.as_ref().unwrap().borrow().document();
+        let now = get_element_by_id(Some(dom), &"now".to_string()).unwrap();
+        assert_eq!("2024-01-02T03:04:05.006Z", get_text_content(now));
+    }
+
+    #[test]
+    fn test_load_images() {
+        let html = "<html><head></head><body><img src=\"images/red.png\" width=\"4\"><img src=\"missing.png\" width=\"4\" height=\"4\"></body></html>";
+        let response = HttpResponse::new(format!("HTTP/1.1 200 OK\n\n{}", html))
+            .expect("failed to parse http response");
+
+        let mut page = Page::new(Viewport::new(600, 400));
+        page.set_loader(load_red_png);
+        page.set_url("http://example.com/index.html".to_string());
+        page.receive_response(response);
+
+        // 読み込めなかった画像は描画しない
+        let images = page
+            .display_items()
+            .into_iter()
+            .filter_map(|item| match item {
+                DisplayItem::Image {
+                    image,
+                    layout_point,
+                    layout_size,
+                } => Some((image, layout_point, layout_size)),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(1, images.len());
+        let (image, layout_point, layout_size) = &images[0];
+        assert_eq!(1, image.width());
+        assert_eq!(LayoutPoint::new(0, 0), *layout_point);
+        assert_eq!(LayoutSize::new(4, 4), *layout_size);
+    }
+
+    #[test]
+    fn test_set_viewport() {
+        let html = "<html><head><style>div { background-color: red; }</style></head><body><div>a</div></body></html>";
+        let response = HttpResponse::new(format!("HTTP/1.1 200 OK\n\n{}", html))
+            .expect("failed to parse http response");
+
+        let mut page = Page::new(Viewport::new(1280, 800));
+        page.receive_response(response);
+        let width = |page: &Page| match &page.display_items()[0] {
+            DisplayItem::Rect { layout_size, .. } => layout_size.width(),
+            item => panic!("expected a rect, but got {:?}", item),
+        };
+        assert_eq!(1280, width(&page));
+
+        // 大きさが変わると、レイアウトし直してディスプレイアイテムを作り直す
+        page.set_viewport(Viewport::new(400, 300));
+        assert_eq!(400, width(&page));
+    }
+}
@@ -1,3 +1,4 @@
+use alloc::format;
 use alloc::string::String;
 use alloc::string::ToString;
 use alloc::vec::Vec;
@@ -45,6 +46,27 @@ impl Url {
         false
     }
 
+    /// このURLを基準に、`<img src="...">`のような相対URLを絶対URLにする
+    /// `parse`した後に呼ぶ。`..`や`.`のセグメントは解決しない
+    pub fn resolve(&self, reference: &str) -> String {
+        if reference.contains("://") {
+            return reference.to_string();
+        }
+        if let Some(rest) = reference.strip_prefix("//") {
+            return format!("http://{}", rest);
+        }
+        let origin = format!("http://{}:{}", self.host, self.port);
+        if reference.starts_with('/') {
+            return format!("{}{}", origin, reference);
+        }
+        // パスの最後のセグメントを取り除いたディレクトリからの相対パス
+        let directory = match self.path.rfind('/') {
+            Some(index) => &self.path[..index + 1],
+            None => "",
+        };
+        format!("{}/{}{}", origin, directory, reference)
+    }
+
     fn extract_host(&self) -> String {
         let url_parts: Vec<&str> = self
             .url
@@ -195,6 +217,34 @@ mod tests {
         assert_eq!(expected, Url::new(url).parse());
     }
 
+    #[test]
+    fn test_resolve() {
+        let base = Url::new("http://example.com:8080/docs/index.html".to_string())
+            .parse()
+            .expect("failed to parse url");
+        assert_eq!(
+            "http://example.com:8080/docs/logo.png",
+            base.resolve("logo.png")
+        );
+        assert_eq!(
+            "http://example.com:8080/images/logo.png",
+            base.resolve("/images/logo.png")
+        );
+        assert_eq!(
+            "http://cdn.example/a.gif",
+            base.resolve("//cdn.example/a.gif")
+        );
+        assert_eq!(
+            "http://other.example/a.jpg",
+            base.resolve("http://other.example/a.jpg")
+        );
+
+        let root = Url::new("http://example.com".to_string())
+            .parse()
+            .expect("failed to parse url");
+        assert_eq!("http://example.com:80/logo.png", root.resolve("logo.png"));
+    }
+
     #[test]
     fn test_no_scheme() {
         let url = "example.com".to_string();
@@ -0,0 +1,92 @@
+//! test262の形式のテストをディレクトリから読み込んで実行する
+//! 環境変数TEST262_DIRでtest262のチェックアウトを指定できる。指定しない場合はtests/test262を使う
+
+use saba_core::renderer::js::runtime::Engine;
+use saba_core::renderer::js::test262::Test262Suite;
+use std::env;
+use std::fs;
+use std::path::Path;
+use std::path::PathBuf;
+
+/// ディレクトリの中の.jsのファイルを再帰的に集める
+fn collect_files(dir: &Path, files: &mut Vec<PathBuf>) {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if path.is_dir() {
+            collect_files(&path, files);
+        } else if path.extension().is_some_and(|e| e == "js") {
+            files.push(path);
+        }
+    }
+}
+
+fn load_suite(root: &Path) -> Test262Suite {
+    let mut suite = Test262Suite::new();
+
+    let mut harness = Vec::new();
+    collect_files(&root.join("harness"), &mut harness);
+    for path in harness {
+        let name = path.file_name().unwrap().to_string_lossy().to_string();
+        suite.add_harness(&name, fs::read_to_string(&path).unwrap());
+    }
+
+    let test_dir = root.join("test");
+    let mut tests = Vec::new();
+    collect_files(&test_dir, &mut tests);
+    for path in tests {
+        // _FIXTURE.jsは他のテストから読み込まれるファイルで、テストではない
+        if path.to_string_lossy().ends_with("_FIXTURE.js") {
+            continue;
+        }
+        let relative = path.strip_prefix(&test_dir).unwrap().to_string_lossy();
+        suite.add_test(
+            &relative.replace('\\', "/"),
+            fs::read_to_string(&path).unwrap(),
+        );
+    }
+    suite
+}
+
+#[test]
+fn test262() {
+    let custom = env::var_os("TEST262_DIR").map(PathBuf::from);
+    let root = custom
+        .clone()
+        .unwrap_or_else(|| Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/test262"));
+    let mut suite = load_suite(&root);
+    // 構文木の評価とバイトコードの実行の両方で動かす
+    for engine in [Engine::TreeWalker, Engine::Bytecode] {
+        suite.set_engine(engine);
+        let summary = suite.run();
+        println!("{:?}\n{}", engine, summary);
+
+        // リポジトリのテストはすべて通る。test262のチェックアウトでは結果を表示するだけ
+        if custom.is_none() {
+            assert_eq!(0, summary.failed(), "{}", summary);
+            assert!(summary.passed() > 0);
+        }
+    }
+}
+
+/// すべてのテストを両方の方法で実行し、結果が同じになることを確かめる
+#[test]
+fn test262_engines_agree() {
+    let custom = env::var_os("TEST262_DIR").map(PathBuf::from);
+    let root = custom
+        .clone()
+        .unwrap_or_else(|| Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/test262"));
+    let suite = load_suite(&root);
+    let differences = suite.differences();
+    for (path, tree_walker, bytecode) in &differences {
+        println!(
+            "{}\n  TreeWalker: {:?}\n  Bytecode: {:?}",
+            path, tree_walker, bytecode
+        );
+    }
+    if custom.is_none() {
+        assert!(differences.is_empty(), "{} tests differ", differences.len());
+    }
+}
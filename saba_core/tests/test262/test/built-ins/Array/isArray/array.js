@@ -0,0 +1,6 @@
+/*---
+description: Array.isArray (array)
+---*/
+
+assert(Array.isArray([]));
+assert(!Array.isArray({length: 0}));
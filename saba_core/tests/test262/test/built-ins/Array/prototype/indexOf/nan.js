@@ -0,0 +1,6 @@
+/*---
+description: Array.prototype.indexOf (nan)
+---*/
+
+assert.sameValue([NaN].indexOf(NaN), -1);
+assert.sameValue([NaN].includes(NaN), true);
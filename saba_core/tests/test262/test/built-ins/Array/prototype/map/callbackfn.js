@@ -0,0 +1,6 @@
+/*---
+description: Array.prototype.map (callbackfn)
+includes: [compareArray.js]
+---*/
+
+assert.compareArray([1, 2, 3].map(function (x, i) { return x * i; }), [0, 2, 6]);
@@ -0,0 +1,8 @@
+/*---
+description: Array.prototype.push (length)
+includes: [compareArray.js]
+---*/
+
+var a = [1, 2];
+assert.sameValue(a.push(3, 4), 4);
+assert.compareArray(a, [1, 2, 3, 4]);
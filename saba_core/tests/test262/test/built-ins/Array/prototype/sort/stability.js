@@ -0,0 +1,7 @@
+/*---
+description: Array.prototype.sort (stability)
+---*/
+
+var a = [{k: 1, v: 'a'}, {k: 0, v: 'b'}, {k: 1, v: 'c'}, {k: 0, v: 'd'}];
+a.sort(function (x, y) { return x.k - y.k; });
+assert.sameValue(a.map(function (e) { return e.v; }).join(''), 'bdac');
@@ -0,0 +1,7 @@
+/*---
+description: Date.UTC (fields)
+---*/
+
+assert.sameValue(Date.UTC(2024, 1, 29, 12), 1709208000000);
+assert.sameValue(new Date(0).toISOString(), '1970-01-01T00:00:00.000Z');
+assert.sameValue(new Date(NaN).getTime(), NaN);
@@ -0,0 +1,6 @@
+/*---
+description: Error.prototype.toString (message)
+---*/
+
+assert.sameValue(String(new TypeError('bad')), 'TypeError: bad');
+assert(new RangeError() instanceof Error);
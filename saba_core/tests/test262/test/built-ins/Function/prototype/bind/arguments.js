@@ -0,0 +1,8 @@
+/*---
+description: Function.prototype.bind (arguments)
+---*/
+
+function f(a, b) { return this.x + a + b; }
+var g = f.bind({x: 1}, 2);
+assert.sameValue(g(3), 6);
+assert.sameValue(g.name, 'bound f');
@@ -0,0 +1,10 @@
+/*---
+description: JSON.parse (reviver)
+---*/
+
+var o = JSON.parse('{"a": [1, 2], "b": {"c": 3}}', function (k, v) {
+return typeof v === 'number' ? v * 10 : v;
+});
+assert.sameValue(o.a[1], 20);
+assert.sameValue(o.b.c, 30);
+assert.throws(SyntaxError, function () { JSON.parse('{a: 1}'); });
@@ -0,0 +1,9 @@
+/*---
+description: JSON.stringify (space)
+---*/
+
+assert.sameValue(JSON.stringify({a: [1, {}]}, null, 2), '{\n  "a": [\n    1,\n    {}\n  ]\n}');
+assert.sameValue(JSON.stringify(undefined), undefined);
+var o = {};
+o.self = o;
+assert.throws(TypeError, function () { JSON.stringify(o); });
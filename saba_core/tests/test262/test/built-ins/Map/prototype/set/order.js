@@ -0,0 +1,13 @@
+/*---
+description: Map.prototype.set (order)
+includes: [compareArray.js]
+---*/
+
+var m = new Map([[1, 'a'], [2, 'b']]);
+m.set(-0, 'z');
+m.delete(1);
+assert.sameValue(m.size, 2);
+assert.sameValue(m.get(0), 'z');
+var keys = [];
+m.forEach(function (v, k) { keys.push(k); });
+assert.compareArray(keys, [2, 0]);
@@ -0,0 +1,8 @@
+/*---
+description: Math.max (zero)
+---*/
+
+assert.sameValue(Math.max(-0, 0), 0);
+assert.sameValue(1 / Math.min(0, -0), -Infinity);
+assert.sameValue(Math.max(), -Infinity);
+assert.sameValue(Math.max(1, NaN), NaN);
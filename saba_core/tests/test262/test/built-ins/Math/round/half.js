@@ -0,0 +1,7 @@
+/*---
+description: Math.round (half)
+---*/
+
+assert.sameValue(Math.round(2.5), 3);
+assert.sameValue(Math.round(-2.5), -2);
+assert.sameValue(1 / Math.round(-0.4), -Infinity);
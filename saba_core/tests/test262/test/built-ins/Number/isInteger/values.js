@@ -0,0 +1,7 @@
+/*---
+description: Number.isInteger (values)
+---*/
+
+assert(Number.isInteger(5));
+assert(!Number.isInteger(5.5));
+assert(!Number.isInteger('5'));
@@ -0,0 +1,7 @@
+/*---
+description: Number.prototype.toFixed (rounding)
+---*/
+
+assert.sameValue((1.005).toFixed(2), '1.00');
+assert.sameValue((2.5).toFixed(0), '3');
+assert.throws(RangeError, function () { (1).toFixed(101); });
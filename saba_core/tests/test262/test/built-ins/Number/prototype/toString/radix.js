@@ -0,0 +1,6 @@
+/*---
+description: Number.prototype.toString (radix)
+---*/
+
+assert.sameValue((255).toString(16), 'ff');
+assert.sameValue((-5.5).toString(2), '-101.1');
@@ -0,0 +1,7 @@
+/*---
+description: Object.freeze (frozen)
+---*/
+
+var o = Object.freeze({a: 1});
+assert(Object.isFrozen(o));
+assert.sameValue(Object.getOwnPropertyDescriptor(o, 'a').writable, false);
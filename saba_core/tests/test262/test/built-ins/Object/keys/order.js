@@ -0,0 +1,6 @@
+/*---
+description: Object.keys (order)
+includes: [compareArray.js]
+---*/
+
+assert.compareArray(Object.keys({b: 1, 2: 1, a: 1, 1: 1}), ['1', '2', 'b', 'a']);
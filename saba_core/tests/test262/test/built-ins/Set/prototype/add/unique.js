@@ -0,0 +1,7 @@
+/*---
+description: Set.prototype.add (unique)
+---*/
+
+var s = new Set([1, 1, NaN, NaN]);
+assert.sameValue(s.size, 2);
+assert.throws(TypeError, function () { Set(); });
@@ -0,0 +1,6 @@
+/*---
+description: String.prototype.padStart (fill)
+---*/
+
+assert.sameValue('5'.padStart(3, '0'), '005');
+assert.sameValue('abc'.padEnd(6, '12'), 'abc121');
@@ -0,0 +1,5 @@
+/*---
+description: String.prototype.replace (patterns)
+---*/
+
+assert.sameValue('abc'.replace('b', '[$&$`$\']'), 'a[bac]c');
@@ -0,0 +1,7 @@
+/*---
+description: String.prototype.split (separator)
+includes: [compareArray.js]
+---*/
+
+assert.compareArray('a,b,,c'.split(','), ['a', 'b', '', 'c']);
+assert.compareArray('abc'.split(''), ['a', 'b', 'c']);
@@ -0,0 +1,5 @@
+/*---
+description: String.prototype.toUpperCase (simple)
+---*/
+
+assert.sameValue('abc'.toUpperCase(), 'ABC');
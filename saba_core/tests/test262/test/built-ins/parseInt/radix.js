@@ -0,0 +1,8 @@
+/*---
+description: parseInt (radix)
+---*/
+
+assert.sameValue(parseInt('0x1F'), 31);
+assert.sameValue(parseInt('z', 36), 35);
+assert.sameValue(parseInt('12px'), 12);
+assert.sameValue(parseInt(''), NaN);
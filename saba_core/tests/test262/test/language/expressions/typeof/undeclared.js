@@ -0,0 +1,5 @@
+/*---
+description: typeof of an undeclared identifier is "undefined"
+---*/
+
+assert.sameValue(typeof undeclaredVariable, "undefined");
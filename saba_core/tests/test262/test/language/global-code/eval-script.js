@@ -0,0 +1,9 @@
+/*---
+description: $262.evalScript declares variables in the global scope
+---*/
+
+assert.sameValue($262.evalScript("var declared = 1; declared + 1;"), 2);
+assert.sameValue(declared, 1);
+assert.throws(SyntaxError, function () {
+  $262.evalScript("var;");
+});
@@ -0,0 +1,6 @@
+/*---
+description: Modules are reported as skipped
+flags: [module]
+---*/
+
+export default 1;
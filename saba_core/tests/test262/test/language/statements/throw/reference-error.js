@@ -0,0 +1,8 @@
+/*---
+description: Reading an undeclared identifier throws a ReferenceError
+negative:
+  phase: runtime
+  type: ReferenceError
+---*/
+
+undeclaredVariable;
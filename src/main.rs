@@ -77,7 +77,7 @@ fn handle_url(url: String) -> Result<HttpResponse, Error> {
 }
 
 fn main() -> u64 {
-    let browser = Browser::new();
+    let browser = Browser::new(WasabiUI::content_area_viewport());
 
     let ui = Rc::new(RefCell::new(WasabiUI::new(browser)));
 
@@ -3,6 +3,8 @@ use crate::cursor::Cursor;
 use alloc::format;
 use alloc::rc::Rc;
 use alloc::string::String;
+use alloc::vec;
+use alloc::vec::Vec;
 use core::cell::RefCell;
 use noli::error::Result as OsResult;
 use noli::prelude::SystemApi;
@@ -17,8 +19,12 @@ use saba_core::constants::*;
 use saba_core::display_item::DisplayItem;
 use saba_core::error::Error;
 use saba_core::http::HttpResponse;
+use saba_core::renderer::js::runtime::date::parse_date;
+use saba_core::renderer::js::runtime::date::FixedClock;
+use saba_core::renderer::layout::computed_style::Color;
 use saba_core::renderer::layout::computed_style::FontSize;
 use saba_core::renderer::layout::computed_style::TextDecoration;
+use saba_core::renderer::layout::viewport::Viewport;
 
 #[derive(Clone, Copy, Debug, Eq, PartialEq)]
 enum InputMode {
@@ -33,6 +39,8 @@ pub struct WasabiUI {
     input_mode: InputMode,
     window: Window,
     cursor: Cursor,
+    /// コンテンツエリアの中で最後にマウスがあった位置。この位置にあるボックスをスクロールする
+    pointer: (i64, i64),
 }
 
 impl WasabiUI {
@@ -51,9 +59,15 @@ impl WasabiUI {
             )
             .unwrap(),
             cursor: Cursor::new(),
+            pointer: (0, 0),
         }
     }
 
+    /// ウィンドウのコンテンツエリアの大きさ。ページはこの大きさでレイアウトする
+    pub fn content_area_viewport() -> Viewport {
+        Viewport::new(CONTENT_AREA_WIDTH, CONTENT_AREA_HEIGHT)
+    }
+
     fn handle_mouse_input(
         &mut self,
         handle_url: fn(String) -> Result<HttpResponse, Error>,
@@ -64,6 +78,15 @@ impl WasabiUI {
             self.window.flush_area(self.cursor.rect());
             self.cursor.flush();
 
+            // クリックと同じく、コンテンツエリアの座標に変換する
+            let content_x = position.x - WINDOW_INIT_X_POS;
+            let content_y = position.y - WINDOW_INIT_Y_POS - TITLE_BAR_HEIGHT - TOOLBAR_HEIGHT;
+            if (0..CONTENT_AREA_WIDTH).contains(&content_x)
+                && (0..CONTENT_AREA_HEIGHT).contains(&content_y)
+            {
+                self.pointer = (content_x, content_y);
+            }
+
             if button.l() || button.c() || button.r() {
                 let relative_pos = (
                     position.x - WINDOW_INIT_X_POS,
@@ -118,8 +141,23 @@ impl WasabiUI {
     ) -> Result<(), Error> {
         match self.input_mode {
             InputMode::Normal => {
-                // InputMode::Normalの場合、何もしない
-                let _ = Api::read_key();
+                // j/kで1行ずつ、スペース/bで1画面ずつスクロールする
+                let page_height = CONTENT_AREA_HEIGHT - CHAR_HEIGHT_WITH_PADDING;
+                let dy = match Api::read_key() {
+                    Some('j') => CHAR_HEIGHT_WITH_PADDING,
+                    Some('k') => -CHAR_HEIGHT_WITH_PADDING,
+                    Some(' ') => page_height,
+                    Some('b') => -page_height,
+                    _ => 0,
+                };
+                if dy != 0 {
+                    let page = self.browser.borrow().current_page();
+                    let scrolled = page.borrow_mut().scroll(self.pointer, 0, dy);
+                    if scrolled {
+                        self.clear_content_area()?;
+                        self.update_ui()?;
+                    }
+                }
             }
             InputMode::Editing => {
                 if let Some(c) = Api::read_key() {
@@ -151,9 +189,18 @@ impl WasabiUI {
     ) -> Result<(), Error> {
         self.clear_content_area()?;
 
-        match handle_url(destination) {
+        match handle_url(destination.clone()) {
             Ok(response) => {
                 let page = self.browser.borrow().current_page();
+                page.borrow_mut().set_loader(handle_url);
+                page.borrow_mut().set_url(destination);
+                // サーバーがレスポンスを返した時刻を、スクリプトが読む現在の時刻にする
+                if let Ok(date) = response.header_value("Date") {
+                    let time = parse_date(&date);
+                    if !time.is_nan() {
+                        page.borrow_mut().set_clock(Rc::new(FixedClock(time)));
+                    }
+                }
                 page.borrow_mut().receive_response(response);
             }
             Err(e) => {
@@ -174,17 +221,45 @@ impl WasabiUI {
             .borrow()
             .display_items();
 
+        // ウィンドウからピクセルを読み出せないので、塗った矩形を覚えておき、
+        // 半透明の色はその点の下にある矩形の色と合成する
+        let mut painted: Vec<(i64, i64, i64, i64, Color)> = Vec::new();
+        let background_at = |painted: &Vec<(i64, i64, i64, i64, Color)>, x: i64, y: i64| {
+            painted
+                .iter()
+                .rev()
+                .find(|(px, py, w, h, _)| x >= *px && x < px + w && y >= *py && y < py + h)
+                .map_or(Color::white(), |(_, _, _, _, color)| *color)
+        };
+
+        // 描画できる範囲。コンテンツエリアの外側とスクロールコンテナの外側には描画しない
+        let mut clips = vec![(0, 0, CONTENT_AREA_WIDTH, CONTENT_AREA_HEIGHT)];
+
         for item in display_items {
+            let clip = *clips.last().expect("content area clip should exist");
             match item {
                 DisplayItem::Text {
                     text,
                     style,
                     layout_point,
                 } => {
+                    // 文字列は途中で切り取れないので、上端か下端がはみ出す場合は描画しない
+                    let (_, clip_y0, clip_x1, clip_y1) = clip;
+                    if layout_point.x() >= clip_x1
+                        || layout_point.y() < clip_y0
+                        || layout_point.y() + style.font_size().to_px() > clip_y1
+                    {
+                        continue;
+                    }
+                    let color = style.color().composite_over(background_at(
+                        &painted,
+                        layout_point.x(),
+                        layout_point.y(),
+                    ));
                     if self
                         .window
                         .draw_string(
-                            style.color().code_u32(),
+                            color.code_u32(),
                             layout_point.x() + WINDOW_PADDING,
                             layout_point.y() + WINDOW_PADDING + TOOLBAR_HEIGHT,
                             &text,
@@ -201,20 +276,153 @@ impl WasabiUI {
                     layout_point,
                     layout_size,
                 } => {
+                    let background = style.background_color();
+                    if background.is_transparent() {
+                        continue;
+                    }
+                    let (x, y, width, height) = match clip_rect(
+                        clip,
+                        layout_point.x(),
+                        layout_point.y(),
+                        layout_size.width(),
+                        layout_size.height(),
+                    ) {
+                        Some(rect) => rect,
+                        None => continue,
+                    };
+                    let color = background.composite_over(background_at(&painted, x, y));
+                    painted.push((x, y, width, height, color));
                     if self
                         .window
                         .fill_rect(
-                            style.background_color().code_u32(),
-                            layout_point.x() + WINDOW_PADDING,
-                            layout_point.y() + WINDOW_PADDING + TOOLBAR_HEIGHT,
-                            layout_size.width(),
-                            layout_size.height(),
+                            color.code_u32(),
+                            x + WINDOW_PADDING,
+                            y + WINDOW_PADDING + TOOLBAR_HEIGHT,
+                            width,
+                            height,
                         )
                         .is_err()
                     {
                         return Err(Error::InvalidUI("failed to draw a rect".to_string()));
                     }
                 }
+                DisplayItem::Bullet {
+                    shape,
+                    style,
+                    layout_point,
+                    layout_size,
+                } => {
+                    let color = style.color().composite_over(background_at(
+                        &painted,
+                        layout_point.x(),
+                        layout_point.y(),
+                    ));
+                    for (x, y, width) in shape.spans(layout_size.width()) {
+                        let (x, y, width, height) = match clip_rect(
+                            clip,
+                            layout_point.x() + x,
+                            layout_point.y() + y,
+                            width,
+                            1,
+                        ) {
+                            Some(rect) => rect,
+                            None => continue,
+                        };
+                        if self
+                            .window
+                            .fill_rect(
+                                color.code_u32(),
+                                x + WINDOW_PADDING,
+                                y + WINDOW_PADDING + TOOLBAR_HEIGHT,
+                                width,
+                                height,
+                            )
+                            .is_err()
+                        {
+                            return Err(Error::InvalidUI("failed to draw a bullet".to_string()));
+                        }
+                    }
+                }
+                DisplayItem::Image {
+                    image,
+                    layout_point,
+                    layout_size,
+                } => {
+                    let (x, y, width, height) = match clip_rect(
+                        clip,
+                        layout_point.x(),
+                        layout_point.y(),
+                        layout_size.width(),
+                        layout_size.height(),
+                    ) {
+                        Some(rect) => rect,
+                        None => continue,
+                    };
+                    // 最も近いピクセルの色で拡大縮小し、同じ色が続く部分はまとめて塗る
+                    for py in y..y + height {
+                        let sy = (py - layout_point.y()) * image.height() / layout_size.height();
+                        let mut run_start = x;
+                        let mut run_color: Option<Color> = None;
+                        for px in x..=x + width {
+                            let color = if px < x + width {
+                                let sx =
+                                    (px - layout_point.x()) * image.width() / layout_size.width();
+                                Some(
+                                    image
+                                        .pixel(sx, sy)
+                                        .composite_over(background_at(&painted, px, py)),
+                                )
+                            } else {
+                                None
+                            };
+                            if color == run_color {
+                                continue;
+                            }
+                            if let Some(c) = run_color {
+                                if self
+                                    .window
+                                    .fill_rect(
+                                        c.code_u32(),
+                                        run_start + WINDOW_PADDING,
+                                        py + WINDOW_PADDING + TOOLBAR_HEIGHT,
+                                        px - run_start,
+                                        1,
+                                    )
+                                    .is_err()
+                                {
+                                    return Err(Error::InvalidUI(
+                                        "failed to draw an image".to_string(),
+                                    ));
+                                }
+                            }
+                            run_start = px;
+                            run_color = color;
+                        }
+                    }
+                }
+                DisplayItem::PushClip {
+                    layout_point,
+                    layout_size,
+                } => {
+                    let (x0, y0, x1, y1) = clip;
+                    let nx0 = core::cmp::max(x0, layout_point.x());
+                    let ny0 = core::cmp::max(y0, layout_point.y());
+                    let nx1 = core::cmp::max(
+                        core::cmp::min(x1, layout_point.x() + layout_size.width()),
+                        nx0,
+                    );
+                    let ny1 = core::cmp::max(
+                        core::cmp::min(y1, layout_point.y() + layout_size.height()),
+                        ny0,
+                    );
+                    clips.push((nx0, ny0, nx1, ny1));
+                }
+                DisplayItem::PopClip => {
+                    // コンテンツエリアのクリップは残す
+                    if clips.len() > 1 {
+                        clips.pop();
+                    }
+                }
             }
         }
         self.window.flush();
@@ -389,10 +597,33 @@ impl WasabiUI {
     }
 }
 
+/// 矩形を描画できる範囲`clip`(左上と右下)の内側に切り詰める
+/// 重なる部分がない場合はNoneを返す
+fn clip_rect(
+    (x0, y0, x1, y1): (i64, i64, i64, i64),
+    x: i64,
+    y: i64,
+    width: i64,
+    height: i64,
+) -> Option<(i64, i64, i64, i64)> {
+    let left = core::cmp::max(x, x0);
+    let top = core::cmp::max(y, y0);
+    let right = core::cmp::min(x + width, x1);
+    let bottom = core::cmp::min(y + height, y1);
+    if left >= right || top >= bottom {
+        return None;
+    }
+    Some((left, top, right - left, bottom - top))
+}
+
 fn convert_font_size(size: FontSize) -> StringSize {
     match size {
         FontSize::Medium => StringSize::Medium,
         FontSize::XLarge => StringSize::Large,
         FontSize::XXLarge => StringSize::XLarge,
+        // ビットマップフォントは3つの大きさしかないので、最も近いものを使う
+        FontSize::Px(px) if px < 24 => StringSize::Medium,
+        FontSize::Px(px) if px < 40 => StringSize::Large,
+        FontSize::Px(_) => StringSize::XLarge,
     }
 }